@@ -0,0 +1,10 @@
+-- Record of Mostro's `P_M` signature over a disputed Cashu escrow (Track D).
+-- Set in the same conditional write that moves the order out of `dispute`, so
+-- the resolution is auditable and a repeated `AdminSettle` re-delivers to the
+-- same party. `NULL` for Lightning orders and for resolutions that needed no
+-- signature (the seller already reclaimed after the locktime).
+--
+-- * cashu_pm_signed_to  Trade pubkey of the dispute winner the signatures went to.
+-- * cashu_pm_signed_at  Unix timestamp (seconds) of the resolution that signed.
+ALTER TABLE orders ADD COLUMN cashu_pm_signed_to text;
+ALTER TABLE orders ADD COLUMN cashu_pm_signed_at integer;
@@ -0,0 +1,30 @@
+-- Durable outbox for protocol messages mostrod sends to users. Handlers still
+-- enqueue into the in-memory `MESSAGE_QUEUES`; the flush job moves them here
+-- and delivers from this table, so a message survives restarts and relay
+-- outages. A row is deleted once delivered (at-least-once: a crash between the
+-- send and the delete resends it).
+--
+-- * queue            'order' | 'cantdo' | 'restore' — the in-memory queue it came from.
+-- * destination      Hex pubkey the message is sent to.
+-- * action           The message's inner action, for dead-letter inspection.
+-- * message          `Message` JSON as handed to `send_dm`.
+-- * status           'pending' | 'dead'. Dead rows exhausted their attempts and
+--                    wait for an operator (admin RPC `RetryOutboxMessage`).
+-- * attempts         Failed delivery attempts so far.
+-- * next_attempt_at  Unix seconds before which the row is not retried (backoff).
+-- * last_error       Error of the most recent failed attempt.
+CREATE TABLE IF NOT EXISTS outbox (
+  id               integer primary key autoincrement,
+  queue            varchar(8) not null,
+  destination      char(64) not null,
+  action           varchar(32),
+  message          text not null,
+  status           varchar(8) not null default 'pending',
+  attempts         integer not null default 0,
+  next_attempt_at  integer not null,
+  last_error       text,
+  created_at       integer not null
+);
+
+CREATE INDEX IF NOT EXISTS idx_outbox_status_due ON outbox (status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_outbox_destination ON outbox (destination, status);
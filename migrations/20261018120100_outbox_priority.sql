@@ -0,0 +1,6 @@
+-- Delivery priority of outbox rows: 0 = urgent (hold invoices, payout
+-- requests, Cashu signatures), 1 = normal, 2 = low (restore-session replies,
+-- order listings). Due rows are sent lowest value first.
+ALTER TABLE outbox ADD COLUMN priority integer not null default 1;
+
+CREATE INDEX IF NOT EXISTS idx_outbox_status_priority ON outbox (status, priority, id);
@@ -0,0 +1,10 @@
+-- Multi-tenant mode (`[[instances]]`, docs/MULTI_TENANT.md).
+--
+-- * orders.instance  Id of the Mostro instance the order was created on;
+--                    `default` is the `[nostr]` identity. An instance only
+--                    acts on its own orders.
+-- * outbox.sender    Id of the instance whose key signs the message.
+ALTER TABLE orders ADD COLUMN instance varchar(64) not null default 'default';
+CREATE INDEX IF NOT EXISTS idx_orders_instance ON orders (instance);
+
+ALTER TABLE outbox ADD COLUMN sender varchar(64) not null default 'default';
@@ -0,0 +1,19 @@
+-- Node key rotation (`[nostr].previous_nsec_privkey`, docs/KEY_ROTATION.md).
+--
+-- * key_rotations        One row per retired key, written on the first start
+--                        after the rotation. `completed_at` is set once every
+--                        trade pinned to the retired key has finished.
+-- * key_rotation_orders  Trades in flight at rotation time. They keep being
+--                        served, and their messages and order events signed,
+--                        by the retired key.
+CREATE TABLE IF NOT EXISTS key_rotations (
+  old_pubkey    char(64) primary key not null,
+  new_pubkey    char(64) not null,
+  rotated_at    integer not null,
+  completed_at  integer
+);
+
+CREATE TABLE IF NOT EXISTS key_rotation_orders (
+  order_id    char(36) primary key not null,
+  old_pubkey  char(64) not null
+);
@@ -0,0 +1,17 @@
+-- Per-trade ratings behind the weighted reputation score (src/reputation.rs).
+--
+-- The users table keeps the running aggregate (`total_rating`, ...) that
+-- clients already read; each row here is one rating with the trade's volume
+-- and time, so the score can weight ratings by volume and age.
+CREATE TABLE IF NOT EXISTS user_ratings (
+  pubkey       char(64) not null,
+  order_id     char(36) not null,
+  rating       integer not null,
+  amount_sats  integer not null,
+  created_at   integer not null,
+  primary key (pubkey, order_id)
+);
+
+-- Trade count and volume are read from completed orders per identity.
+CREATE INDEX IF NOT EXISTS idx_orders_master_buyer ON orders (master_buyer_pubkey);
+CREATE INDEX IF NOT EXISTS idx_orders_master_seller ON orders (master_seller_pubkey);
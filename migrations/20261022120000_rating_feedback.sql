@@ -0,0 +1,17 @@
+-- Structured rating feedback and dispute marks (docs/REPUTATION.md).
+--
+-- * user_ratings.feedback  Comma-separated feedback labels sent with the
+--                          rating, e.g. `fast-payment,good-communication`.
+-- * dispute_marks          One row per dispute an identity lost, recorded by
+--                          admin-settle (seller lost) and admin-cancel
+--                          (buyer lost). Counts as a lowest rating in the
+--                          weighted score.
+ALTER TABLE user_ratings ADD COLUMN feedback varchar(200) not null default '';
+
+CREATE TABLE IF NOT EXISTS dispute_marks (
+  pubkey       char(64) not null,
+  order_id     char(36) not null,
+  amount_sats  integer not null,
+  created_at   integer not null,
+  primary key (pubkey, order_id)
+);
@@ -0,0 +1,18 @@
+-- Evidence attached to a dispute by its buyer or seller (src/app/evidence.rs).
+--
+-- `content` is NIP-44 encrypted to `key_pubkey`, the Mostro key that received
+-- it, and empty for a hash-only receipt. `sha256` is the hash of the plain
+-- content, or the receipt hash the party committed to.
+CREATE TABLE IF NOT EXISTS dispute_evidence (
+  id            integer primary key autoincrement,
+  dispute_id    char(36) not null,
+  order_id      char(36) not null,
+  submitted_by  varchar(6) not null,
+  kind          varchar(16) not null,
+  content       text not null,
+  sha256        char(64) not null,
+  occurred_at   integer,
+  key_pubkey    char(64) not null,
+  created_at    integer not null
+);
+CREATE INDEX IF NOT EXISTS idx_dispute_evidence_dispute ON dispute_evidence (dispute_id);
@@ -0,0 +1,14 @@
+-- Dispute SLA tracking (docs/ADMIN_RPC_AND_DISPUTES.md).
+--
+-- One row per dispute the SLA job or a resolution touched:
+-- * take_alerted_at  When solvers and the admin were alerted that the
+--                    dispute was still untaken; NULL while not alerted.
+-- * escalated_at     When the admin was told a taken dispute was still
+--                    unresolved; NULL while not escalated.
+-- * resolved_at      When the dispute reached a final status.
+CREATE TABLE IF NOT EXISTS dispute_sla (
+  dispute_id       char(36) primary key not null,
+  take_alerted_at  integer,
+  escalated_at     integer,
+  resolved_at      integer
+);
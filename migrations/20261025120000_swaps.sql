@@ -0,0 +1,30 @@
+-- Reverse submarine swaps paying a buyer on-chain (src/swap/).
+--
+-- `id` is the swap id at the swap service. Mostro picks `preimage` and only
+-- reveals it to the service once the on-chain lockup is confirmed; the swap
+-- invoice pays to `preimage_hash`. `claim_key` is the secret behind the claim
+-- public key handed to the service, kept to claim the lockup by hand if the
+-- service stops cooperating. `settle_hold` is 1 while the seller's hold
+-- invoice still waits for this swap to complete.
+CREATE TABLE IF NOT EXISTS swaps (
+  id                    text primary key not null,
+  order_id              char(36) not null,
+  address               text not null,
+  invoice               text not null,
+  invoice_amount        integer not null,
+  onchain_amount        integer not null,
+  lockup_address        text not null,
+  timeout_block_height  integer not null,
+  preimage              char(64) not null,
+  preimage_hash         char(64) not null,
+  claim_key             char(64) not null,
+  settle_hold           integer not null default 0,
+  status                varchar(10) not null,
+  provider_status       text,
+  created_at            integer not null,
+  updated_at            integer not null
+);
+CREATE INDEX IF NOT EXISTS idx_swaps_order ON swaps (order_id);
+-- At most one live swap per order: a second one would pay the buyer twice.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_swaps_live_order ON swaps (order_id)
+  WHERE status IN ('created', 'claimed');
@@ -0,0 +1,6 @@
+-- Buyer signatures over a locked Cashu escrow, sent with the buyer's `Cancel`
+-- when the buyer starts a cooperative cancel (docs/cashu/04-track-c-coop-cancel.md
+-- §5). Kept until the seller confirms, then relayed to the seller and cleared
+-- by the conditional write that moves the order to `cooperatively-canceled`.
+-- JSON array of `CashuProofSignature`; `NULL` otherwise.
+ALTER TABLE orders ADD COLUMN cashu_cancel_signatures text;
@@ -0,0 +1,10 @@
+-- Mostro claims a swap's lockup itself (src/swap/script.rs) instead of
+-- handing the preimage to the swap service.
+--
+-- `refund_public_key` is the service's key in the lockup script; with the
+-- claim key, the preimage hash and the timeout it lets Mostro rebuild the
+-- lockup address and check it. `claim_tx` is the signed claim transaction
+-- (hex), stored before it is broadcast so a dropped claim is re-sent as is;
+-- `NULL` until the lockup is claimed.
+ALTER TABLE swaps ADD COLUMN refund_public_key text not null default '';
+ALTER TABLE swaps ADD COLUMN claim_tx text;
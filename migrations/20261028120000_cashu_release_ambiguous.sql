@@ -0,0 +1,6 @@
+-- When the release watcher saw a released Cashu order's escrow spent only
+-- after its locktime (docs/cashu/03-track-b-release.md §5C): a late buyer
+-- redeem and the seller's reclaim look the same to the mint, so the order is
+-- left for a dispute. Set once, when solvers are alerted; the watcher skips
+-- the order from then on. `NULL` otherwise.
+ALTER TABLE orders ADD COLUMN cashu_release_ambiguous_at integer;
@@ -0,0 +1,7 @@
+-- The reputation trade count reads completed orders per identity on either
+-- side (src/reputation.rs). Index each side with the status, so the count
+-- does not visit the identity's open and canceled orders.
+DROP INDEX IF EXISTS idx_orders_master_buyer;
+DROP INDEX IF EXISTS idx_orders_master_seller;
+CREATE INDEX IF NOT EXISTS idx_orders_master_buyer_status ON orders (master_buyer_pubkey, status);
+CREATE INDEX IF NOT EXISTS idx_orders_master_seller_status ON orders (master_seller_pubkey, status);
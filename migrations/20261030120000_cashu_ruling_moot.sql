@@ -0,0 +1,6 @@
+-- When a solver ruled on a disputed Cashu order whose escrow was already
+-- spent at the mint. Every spend path needs the seller's key and the mint
+-- does not say who was paid, so such a ruling is moot: it closes the dispute
+-- without signing and without marking a loser. `NULL` for a ruling that
+-- signed the escrow over to the winner.
+ALTER TABLE orders ADD COLUMN cashu_ruling_moot_at integer;
@@ -0,0 +1,6 @@
+-- The weighted reputation score last published for each identity
+-- (src/reputation.rs), kept so the order book query can filter makers on the
+-- score clients see rather than the raw running average. Identities not
+-- profiled since start from their running average.
+ALTER TABLE users ADD COLUMN reputation_score real;
+UPDATE users SET reputation_score = total_rating WHERE total_reviews > 0;
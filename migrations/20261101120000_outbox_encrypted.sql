@@ -0,0 +1,7 @@
+-- Outbox messages are stored NIP-44 encrypted to the key that sends them, so
+-- the database never holds payout invoices or Cashu signatures in the clear.
+--
+-- * outbox.key_pubkey  Hex pubkey whose key encrypted `message`. Empty for
+--                      rows stored in plaintext before this migration; they
+--                      are still delivered as they are.
+ALTER TABLE outbox ADD COLUMN key_pubkey varchar(64) not null default '';
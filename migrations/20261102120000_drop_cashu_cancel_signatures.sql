@@ -0,0 +1,6 @@
+-- A buyer's cooperative-cancel signatures are relayed to the seller with the
+-- step-1 cancel request instead of being kept on the order until the seller
+-- confirms (docs/cashu/04-track-c-coop-cancel.md §5): with `P_M` in the same
+-- process, a stored `P_B` signature would meet the 2-of-3 threshold. Dropping
+-- the column also discards any signatures stored before this migration.
+ALTER TABLE orders DROP COLUMN cashu_cancel_signatures;
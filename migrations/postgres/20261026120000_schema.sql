@@ -0,0 +1,232 @@
+-- PostgreSQL schema, equivalent to every SQLite migration in `migrations/` up
+-- to 20261026120000_cashu_cancel_signatures.sql. Column meanings are
+-- documented there; this file only carries the PostgreSQL types:
+--
+-- * ids that the code binds as `Uuid` are `uuid` (SQLite stores them as blobs);
+-- * flags mostro-core decodes as `bool` are `boolean`, counters and unix
+--   timestamps are `bigint` (SQLite `integer` is 64-bit, PostgreSQL's is not);
+-- * text columns are `text`: SQLite never enforced the `char(n)` /
+--   `varchar(n)` widths and several statuses are longer than their column;
+-- * order and dispute columns mostro-core decodes into non-`Option` fields are
+--   `not null`: SQLite hands a NULL back as ''/0, PostgreSQL refuses it.
+--
+-- A later schema change adds a migration here alongside its SQLite one.
+
+CREATE TABLE orders (
+  id                        uuid primary key not null,
+  kind                      text not null,
+  event_id                  text not null,
+  hash                      text,
+  preimage                  text,
+  creator_pubkey            text not null default '',
+  cancel_initiator_pubkey   text,
+  dispute_initiator_pubkey  text,
+  buyer_pubkey              text,
+  master_buyer_pubkey       text,
+  seller_pubkey             text,
+  master_seller_pubkey      text,
+  status                    text not null,
+  price_from_api            boolean not null default false,
+  premium                   bigint not null,
+  payment_method            text not null,
+  amount                    bigint not null,
+  min_amount                bigint default 0,
+  max_amount                bigint default 0,
+  buyer_dispute             boolean not null default false,
+  seller_dispute            boolean not null default false,
+  buyer_cooperativecancel   boolean not null default false,
+  seller_cooperativecancel  boolean not null default false,
+  fee                       bigint not null default 0,
+  routing_fee               bigint not null default 0,
+  fiat_code                 text not null,
+  fiat_amount               bigint not null,
+  buyer_invoice             text,
+  range_parent_id           uuid,
+  invoice_held_at           bigint not null default 0,
+  taken_at                  bigint not null default 0,
+  created_at                bigint not null,
+  buyer_sent_rate           boolean not null default false,
+  seller_sent_rate          boolean not null default false,
+  payment_attempts          bigint not null default 0,
+  failed_payment            boolean not null default false,
+  expires_at                bigint not null,
+  trade_index_seller        bigint default 0,
+  trade_index_buyer         bigint default 0,
+  next_trade_pubkey         text,
+  next_trade_index          bigint default 0,
+  dev_fee                   bigint not null default 0,
+  dev_fee_paid              boolean not null default false,
+  dev_fee_payment_hash      text,
+  cashu_mint_url            text,
+  cashu_escrow_token        text,
+  cashu_escrow_locked_at    bigint,
+  payout_payment_hash       text,
+  payout_claimed_at         bigint,
+  cashu_pm_signed_to        text,
+  cashu_pm_signed_at        bigint,
+  instance                  text not null default 'default',
+  cashu_cancel_signatures   text
+);
+CREATE UNIQUE INDEX idx_orders_cashu_escrow_token
+  ON orders (cashu_escrow_token)
+  WHERE cashu_escrow_token IS NOT NULL;
+CREATE INDEX idx_orders_instance ON orders (instance);
+CREATE INDEX idx_orders_master_buyer ON orders (master_buyer_pubkey);
+CREATE INDEX idx_orders_master_seller ON orders (master_seller_pubkey);
+
+CREATE TABLE disputes (
+  id                     uuid primary key not null,
+  order_id               uuid unique not null,
+  status                 text not null,
+  order_previous_status  text not null,
+  solver_pubkey          text,
+  created_at             bigint not null,
+  taken_at               bigint not null default 0
+);
+
+CREATE TABLE users (
+  pubkey            text primary key not null,
+  is_admin          bigint not null default 0,
+  admin_password    text,
+  is_solver         bigint not null default 0,
+  is_banned         bigint not null default 0,
+  category          bigint not null default 0,
+  last_trade_index  bigint not null default 0,
+  total_reviews     bigint not null default 0,
+  total_rating      double precision not null default 0.0,
+  last_rating       bigint not null default 0,
+  max_rating        bigint not null default 0,
+  min_rating        bigint not null default 0,
+  created_at        bigint not null
+);
+
+CREATE TABLE bonds (
+  id                        uuid primary key not null,
+  order_id                  uuid not null references orders(id),
+  parent_bond_id            uuid,
+  child_order_id            uuid,
+  pubkey                    text not null,
+  role                      text not null,
+  amount_sats               bigint not null,
+  slashed_share_sats        bigint not null default 0,
+  state                     text not null,
+  slashed_reason            text,
+  hash                      text,
+  preimage                  text,
+  payment_request           text,
+  payout_invoice            text,
+  payout_routing_fee_sats   bigint,
+  node_share_sats           bigint,
+  payout_attempts           bigint not null default 0,
+  invoice_request_attempts  bigint not null default 0,
+  last_invoice_request_at   bigint,
+  locked_at                 bigint,
+  released_at               bigint,
+  slashed_at                bigint,
+  created_at                bigint not null,
+  taker_identity            text,
+  taker_trade_index         bigint,
+  taker_invoice             text,
+  taker_fiat_amount         bigint,
+  taker_amount              bigint,
+  taker_fee                 bigint,
+  taker_dev_fee             bigint,
+  payout_payment_hash       text
+);
+CREATE INDEX idx_bonds_order_id ON bonds (order_id);
+CREATE INDEX idx_bonds_state ON bonds (state);
+CREATE INDEX idx_bonds_parent ON bonds (parent_bond_id);
+CREATE UNIQUE INDEX idx_bonds_parent_child_unique
+  ON bonds (parent_bond_id, child_order_id)
+  WHERE parent_bond_id IS NOT NULL AND child_order_id IS NOT NULL;
+
+CREATE TABLE outbox (
+  id               bigserial primary key,
+  queue            text not null,
+  destination      text not null,
+  action           text,
+  message          text not null,
+  status           text not null default 'pending',
+  attempts         bigint not null default 0,
+  next_attempt_at  bigint not null,
+  last_error       text,
+  created_at       bigint not null,
+  priority         bigint not null default 1,
+  sender           text not null default 'default'
+);
+CREATE INDEX idx_outbox_status_due ON outbox (status, next_attempt_at);
+CREATE INDEX idx_outbox_destination ON outbox (destination, status);
+CREATE INDEX idx_outbox_status_priority ON outbox (status, priority, id);
+
+CREATE TABLE key_rotations (
+  old_pubkey    text primary key not null,
+  new_pubkey    text not null,
+  rotated_at    bigint not null,
+  completed_at  bigint
+);
+
+CREATE TABLE key_rotation_orders (
+  order_id    uuid primary key not null,
+  old_pubkey  text not null
+);
+
+CREATE TABLE user_ratings (
+  pubkey       text not null,
+  order_id     uuid not null,
+  rating       bigint not null,
+  amount_sats  bigint not null,
+  created_at   bigint not null,
+  feedback     text not null default '',
+  primary key (pubkey, order_id)
+);
+
+CREATE TABLE dispute_marks (
+  pubkey       text not null,
+  order_id     uuid not null,
+  amount_sats  bigint not null,
+  created_at   bigint not null,
+  primary key (pubkey, order_id)
+);
+
+CREATE TABLE dispute_evidence (
+  id            bigserial primary key,
+  dispute_id    uuid not null,
+  order_id      uuid not null,
+  submitted_by  text not null,
+  kind          text not null,
+  content       text not null,
+  sha256        text not null,
+  occurred_at   bigint,
+  key_pubkey    text not null,
+  created_at    bigint not null
+);
+CREATE INDEX idx_dispute_evidence_dispute ON dispute_evidence (dispute_id);
+
+CREATE TABLE dispute_sla (
+  dispute_id       uuid primary key not null,
+  take_alerted_at  bigint,
+  escalated_at     bigint,
+  resolved_at      bigint
+);
+
+CREATE TABLE swaps (
+  id                    text primary key not null,
+  order_id              uuid not null,
+  address               text not null,
+  invoice               text not null,
+  invoice_amount        bigint not null,
+  onchain_amount        bigint not null,
+  lockup_address        text not null,
+  timeout_block_height  bigint not null,
+  preimage              text not null,
+  preimage_hash         text not null,
+  claim_key             text not null,
+  settle_hold           boolean not null default false,
+  status                text not null,
+  provider_status       text,
+  created_at            bigint not null,
+  updated_at            bigint not null
+);
+CREATE INDEX idx_swaps_order ON swaps (order_id);
+CREATE UNIQUE INDEX idx_swaps_live_order ON swaps (order_id)
+  WHERE status IN ('created', 'claimed');
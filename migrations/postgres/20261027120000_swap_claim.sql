@@ -0,0 +1,3 @@
+-- See migrations/20261027120000_swap_claim.sql.
+ALTER TABLE swaps ADD COLUMN refund_public_key text not null default '';
+ALTER TABLE swaps ADD COLUMN claim_tx text;
@@ -0,0 +1,2 @@
+-- See migrations/20261028120000_cashu_release_ambiguous.sql.
+ALTER TABLE orders ADD COLUMN cashu_release_ambiguous_at bigint;
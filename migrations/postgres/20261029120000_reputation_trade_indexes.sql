@@ -0,0 +1,5 @@
+-- See migrations/20261029120000_reputation_trade_indexes.sql.
+DROP INDEX IF EXISTS idx_orders_master_buyer;
+DROP INDEX IF EXISTS idx_orders_master_seller;
+CREATE INDEX IF NOT EXISTS idx_orders_master_buyer_status ON orders (master_buyer_pubkey, status);
+CREATE INDEX IF NOT EXISTS idx_orders_master_seller_status ON orders (master_seller_pubkey, status);
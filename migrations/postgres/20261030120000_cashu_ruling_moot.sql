@@ -0,0 +1,2 @@
+-- See migrations/20261030120000_cashu_ruling_moot.sql.
+ALTER TABLE orders ADD COLUMN cashu_ruling_moot_at bigint;
@@ -0,0 +1,3 @@
+-- See migrations/20261031120000_users_reputation_score.sql.
+ALTER TABLE users ADD COLUMN reputation_score double precision;
+UPDATE users SET reputation_score = total_rating WHERE total_reviews > 0;
@@ -0,0 +1,2 @@
+-- See migrations/20261101120000_outbox_encrypted.sql.
+ALTER TABLE outbox ADD COLUMN key_pubkey text not null default '';
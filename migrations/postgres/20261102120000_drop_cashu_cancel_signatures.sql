@@ -0,0 +1,2 @@
+-- See migrations/20261102120000_drop_cashu_cancel_signatures.sql.
+ALTER TABLE orders DROP COLUMN cashu_cancel_signatures;
@@ -8,6 +8,7 @@ pub mod context;
 pub mod add_cashu_escrow; // Cashu escrow lock handler (Track A / CF-5 stub)
 pub mod add_invoice; // Handles invoice creation
 pub mod admin_add_solver; // Admin functionality to add dispute solvers
+pub mod admin_ban_user; // Admin ban / unban of user identities
 pub mod admin_cancel; // Admin order cancellation
 pub mod admin_settle; // Admin dispute settlement
 pub mod admin_take_dispute; // Admin dispute handling
@@ -15,6 +16,7 @@ pub mod bond; // Anti-abuse bond data + helpers (issue #711)
 pub mod cancel; // User order cancellation
 pub mod dev_fee; // Dev fee payment lifecycle
 pub mod dispute; // User dispute handling
+pub mod evidence; // Dispute evidence
 pub mod fiat_sent; // Fiat payment confirmation
 pub mod last_trade_index;
 pub mod order; // Order creation and management
@@ -30,8 +32,9 @@ pub mod trade_pubkey; // Trade pubkey action // Sync user trade index action
 use crate::app::add_cashu_escrow::add_cashu_escrow_action;
 use crate::app::add_invoice::add_invoice_action;
 use crate::app::admin_add_solver::admin_add_solver_action;
-use crate::app::admin_cancel::admin_cancel_action;
-use crate::app::admin_settle::admin_settle_action;
+use crate::app::admin_ban_user::{admin_ban_user_action, BanRequest};
+use crate::app::admin_cancel::{admin_cancel_action, admin_cancel_cashu_action};
+use crate::app::admin_settle::{admin_settle_action, admin_settle_cashu_action};
 use crate::app::admin_take_dispute::admin_take_dispute_action;
 use crate::app::bond::add_bond_invoice_action;
 use crate::app::cancel::cancel_action;
@@ -50,7 +53,9 @@ use crate::app::trade_pubkey::trade_pubkey_action;
 // Core functionality imports
 use crate::db::add_new_user;
 use crate::db::is_user_present;
-use crate::lightning::LndConnector;
+use crate::escrow::{CashuBackend, EscrowBackend};
+use crate::instance::{self, Instance};
+use crate::metrics::{self, event_dropped, DropReason};
 use crate::spam_gate::SpamGate;
 use crate::util::enqueue_cant_do_msg;
 use crate::Result;
@@ -59,7 +64,7 @@ use crate::Result;
 use mostro_core::error::CantDoReason;
 use mostro_core::error::MostroError;
 use mostro_core::error::ServiceError;
-use mostro_core::message::{Action, Message};
+use mostro_core::message::{Action, Message, MessageKind, Payload};
 use mostro_core::nip59::UnwrappedMessage;
 use mostro_core::transport::unwrap_incoming;
 use mostro_core::user::User;
@@ -144,6 +149,18 @@ async fn check_trade_index(
     // If user is present, we check the trade index and signature
     match is_user_present(pool, event.identity.to_string()).await {
         Ok(user) => {
+            // Banned identities may not create or take orders.
+            if user.is_banned != 0 {
+                tracing::info!("Banned user {} refused", event.identity);
+                manage_errors(
+                    MostroError::MostroCantDo(CantDoReason::NotAuthorized),
+                    msg.clone(),
+                    event.clone(),
+                    &message_kind.action,
+                )
+                .await;
+                return Err(MostroError::MostroCantDo(CantDoReason::NotAuthorized));
+            }
             if let index @ 1.. = message_kind.trade_index() {
                 // Inner-tuple signature is already decoded by unwrap_message.
                 let sig = event.signature.ok_or_else(|| {
@@ -247,9 +264,15 @@ async fn handle_message_action_no_ln(
             .map_err(|e| e.into()),
 
         // Admin actions without LN
-        Action::AdminAddSolver => admin_add_solver_action(ctx, msg, event, my_keys)
-            .await
-            .map_err(|e| e.into()),
+        Action::AdminAddSolver => match BanRequest::from_message(&msg) {
+            Some(Ok(request)) => admin_ban_user_action(ctx, msg, request, event, my_keys)
+                .await
+                .map_err(|e| e.into()),
+            Some(Err(e)) => Err(e.into()),
+            None => admin_add_solver_action(ctx, msg, event, my_keys)
+                .await
+                .map_err(|e| e.into()),
+        },
         Action::AdminTakeDispute => admin_take_dispute_action(ctx, msg, event, my_keys)
             .await
             .map_err(|e| e.into()),
@@ -274,7 +297,7 @@ async fn handle_message_action(
     msg: Message,
     event: &UnwrappedMessage,
     my_keys: &Keys,
-    ln_client: &mut LndConnector,
+    ln_client: &mut dyn EscrowBackend,
     ctx: &AppContext,
 ) -> Result<()> {
     match action {
@@ -324,9 +347,11 @@ async fn accept_event(
     if !event.check_pow(pow) {
         // Discard events that don't meet POW requirements
         tracing::info!("Not POW verified event!");
+        event_dropped(DropReason::Pow);
         return None;
     }
     if event.kind != accepted_kind {
+        event_dropped(DropReason::Kind);
         return None;
     }
     // Authenticate the event BEFORE anything downstream records state keyed on
@@ -344,6 +369,7 @@ async fn accept_event(
     // id or signature. Do not delete this as redundant.
     if event.verify().is_err() {
         tracing::warn!("Dropping event {} with an invalid signature", event.id);
+        event_dropped(DropReason::InvalidSignature);
         return None;
     }
     // Phase 2 anti-spam gate (protocol v2 / kind 14 only):
@@ -359,6 +385,7 @@ async fn accept_event(
         // above proved the event is the author's own.
         if gate.is_replay(event.id, now) {
             tracing::debug!("Dropping replayed event {}", event.id);
+            event_dropped(DropReason::Replay);
             return None;
         }
         // Two lanes: a sender already in an active trade is
@@ -380,6 +407,7 @@ async fn accept_event(
                 event.pubkey,
                 pow_first_contact
             );
+            event_dropped(DropReason::SpamGate);
             return None;
         }
     }
@@ -393,9 +421,13 @@ async fn accept_event(
     let unwrapped = match unwrap_incoming(event, my_keys).await {
         Ok(Some(u)) => u,
         // NIP-44 decrypt failed: not addressed to this node.
-        Ok(None) => return None,
+        Ok(None) => {
+            event_dropped(DropReason::Decrypt);
+            return None;
+        }
         Err(e) => {
             tracing::warn!("Error unwrapping incoming message: {}", e);
+            event_dropped(DropReason::Unwrap);
             return None;
         }
     };
@@ -405,6 +437,7 @@ async fn accept_event(
         .unwrap()
         .timestamp() as u64;
     if unwrapped.created_at.as_secs() < since_time {
+        event_dropped(DropReason::Stale);
         return None;
     }
     let message = unwrapped.message.clone();
@@ -419,6 +452,7 @@ async fn accept_event(
             unwrapped.identity,
             unwrapped.sender
         );
+        event_dropped(DropReason::MissingInnerSignature);
         return None;
     }
 
@@ -427,16 +461,33 @@ async fn accept_event(
     // Check if message is message with trade index
     if let Err(e) = check_trade_index(ctx, &unwrapped, &message).await {
         tracing::warn!("Error checking trade index: {}", e);
+        event_dropped(DropReason::TradeIndex);
         return None;
     }
 
-    if !inner_message.verify() {
+    if !inner_message.verify() && !is_daemon_extension(inner_message) {
+        event_dropped(DropReason::InnerVerify);
         return None;
     }
-    let action = message.inner_action()?;
+    let Some(action) = message.inner_action() else {
+        event_dropped(DropReason::NoAction);
+        return None;
+    };
+    metrics::event_accepted();
     Some((action, message, unwrapped))
 }
 
+/// Payloads this daemon accepts beyond mostro-core's `MessageKind::verify`:
+/// the JSON order book query of `orders` (docs/ORDERS_AND_ACTIONS.md) and
+/// the JSON rating with feedback of `rate-user` (docs/REPUTATION.md).
+fn is_daemon_extension(kind: &MessageKind) -> bool {
+    matches!(
+        (&kind.action, &kind.payload),
+        (Action::Orders, Some(Payload::TextMessage(_)))
+            | (Action::RateUser, Some(Payload::TextMessage(_)))
+    )
+}
+
 /// Shared post-dispatch error handling (identical in both loops). A handler
 /// `Err` is downcast to a `MostroError` and turned into the right reply
 /// (`manage_errors`) or logged (`warning_msg`); `Ok` is a no-op. Factored out
@@ -460,6 +511,54 @@ async fn finalize_dispatch(
     }
 }
 
+/// The Mostro identity (`[[instances]]` or the retired key) `event` is
+/// addressed to, and the context to handle it with. A single-key node always
+/// gets `ctx` back.
+fn route_event(ctx: &AppContext, event: &Event) -> (Option<&'static Instance>, AppContext) {
+    if !instance::has_several_keys() {
+        return (None, ctx.clone());
+    }
+    match instance::for_event(event) {
+        Some(found) => (Some(found), ctx.for_instance(found)),
+        None => (None, ctx.clone()),
+    }
+}
+
+/// Multi-tenant isolation: an instance only acts on its own orders. A
+/// message about another instance's order is answered as if the order did
+/// not exist. The retired key of a rotation only serves the trades pinned to
+/// it (`crate::rotation`). `admin-take-dispute` carries a dispute id and is
+/// checked against the disputed order.
+async fn check_order_instance(ctx: &AppContext, message: &Message) -> Result<()> {
+    let inner = message.get_inner_message_kind();
+    let order_id = match (inner.action.clone(), inner.id) {
+        (Action::AdminTakeDispute, Some(dispute_id)) => Some(
+            instance::dispute_order_id(ctx.pool(), dispute_id)
+                .await?
+                .unwrap_or(dispute_id),
+        ),
+        (_, id) => id,
+    };
+    if instance::current().is_some_and(|current| current.retired) {
+        return match order_id {
+            Some(id) if crate::rotation::serves(ctx.pool(), id).await => Ok(()),
+            Some(_) => Err(MostroError::MostroCantDo(CantDoReason::NotFound).into()),
+            None => Err(MostroError::MostroCantDo(CantDoReason::InvalidAction).into()),
+        };
+    }
+    if !instance::is_multi_tenant() {
+        return Ok(());
+    }
+    let Some(order_id) = order_id else {
+        return Ok(());
+    };
+    if instance::owns_order(ctx.pool(), instance::current_id(), order_id).await? {
+        Ok(())
+    } else {
+        Err(MostroError::MostroCantDo(CantDoReason::NotFound).into())
+    }
+}
+
 /// Resolve the anti-spam gate for a transport, once per event loop.
 ///
 /// The gate applies to the v2 (kind-14) transport only: there the visible
@@ -478,60 +577,81 @@ fn gate_for(is_v2: bool) -> Option<&'static SpamGate> {
     }
 }
 
-/// Main event loop that processes incoming Nostr events.
-/// Handles message verification, POW checking, and routes valid messages to appropriate handlers.
-///
-/// # Arguments
-/// * `my_keys` - The node's keypair
-/// * `client` - Nostr client instance
-/// * `ln_client` - Lightning network connector
-pub async fn run(ctx: AppContext, ln_client: &mut LndConnector) -> Result<()> {
-    let my_keys = ctx.keys();
-    let client = ctx.nostr_client();
-    let pow = ctx.settings().mostro.pow;
+/// Admission settings for an event routed to `ctx`'s instance: its `pow`
+/// and first-contact `pow`, the accepted event kind and the spam gate.
+fn admission(ctx: &AppContext) -> (u8, u8, Kind, Option<&'static SpamGate>) {
+    let mostro = &ctx.settings().mostro;
     // The node speaks exactly one transport (protocol v1 gift wrap or v2
     // NIP-44 direct); events of any other kind are dropped before any
     // decryption work. See docs/TRANSPORT_V2_SPEC.md.
     // DEPRECATED(v0.19.0, #786): with the `transport` knob gone this becomes
     // unconditionally kind 14 and the v1/v2 branching below collapses.
     #[allow(deprecated)]
-    let accepted_kind = ctx.settings().mostro.transport.event_kind();
+    let accepted_kind = mostro.transport.event_kind();
     // Phase 2 anti-spam gate (docs/TRANSPORT_V2_SPEC.md §6): on the v2 (kind
     // 14) transport the visible author is the trade key, so the daemon can
     // pre-validate before decrypting. Unknown (first-contact) senders must
     // clear `pow_first_contact`; known active-trade keys need only `pow`. The
     // gate is meaningless for v1 (gift wraps are signed by throwaway keys).
-    let pow_first_contact = ctx.settings().mostro.effective_pow_first_contact();
     let gate = gate_for(accepted_kind.as_u16() == crate::config::constants::DM_EVENT_KIND);
+    (
+        mostro.pow,
+        mostro.effective_pow_first_contact(),
+        accepted_kind,
+        gate,
+    )
+}
+
+/// Main event loop that processes incoming Nostr events.
+/// Handles message verification, POW checking, and routes valid messages to appropriate handlers.
+///
+/// # Arguments
+/// * `my_keys` - The node's keypair
+/// * `client` - Nostr client instance
+/// * `ln_client` - Lightning node behind the escrow (see [`crate::escrow::connect_lightning`])
+pub async fn run(ctx: AppContext, ln_client: &mut dyn EscrowBackend) -> Result<()> {
+    let client = ctx.nostr_client();
 
     loop {
         let mut notifications = client.notifications();
 
         while let Some(notification) = notifications.next().await {
             if let ClientNotification::Event { event, .. } = notification {
-                let Some((action, message, unwrapped)) = accept_event(
-                    &ctx,
-                    &event,
-                    my_keys,
-                    pow,
-                    pow_first_contact,
-                    accepted_kind,
-                    gate,
-                )
-                .await
-                else {
-                    continue;
-                };
-                let result = handle_message_action(
-                    &action,
-                    message.clone(),
-                    &unwrapped,
-                    my_keys,
-                    ln_client,
-                    &ctx,
-                )
+                let (instance, ctx) = route_event(&ctx, &event);
+                // Thresholds of the instance the event is addressed to.
+                let (pow, pow_first_contact, accepted_kind, gate) = admission(&ctx);
+                instance::within(instance, async {
+                    let my_keys = ctx.keys();
+                    let Some((action, message, unwrapped)) = accept_event(
+                        &ctx,
+                        &event,
+                        my_keys,
+                        pow,
+                        pow_first_contact,
+                        accepted_kind,
+                        gate,
+                    )
+                    .await
+                    else {
+                        return;
+                    };
+                    let result = match check_order_instance(&ctx, &message).await {
+                        Ok(()) => {
+                            handle_message_action(
+                                &action,
+                                message.clone(),
+                                &unwrapped,
+                                my_keys,
+                                ln_client,
+                                &ctx,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    finalize_dispatch(result, message, unwrapped, &action).await;
+                })
                 .await;
-                finalize_dispatch(result, message, unwrapped, &action).await;
             }
         }
     }
@@ -547,35 +667,41 @@ pub async fn run(ctx: AppContext, ln_client: &mut LndConnector) -> Result<()> {
 /// `CantDo(InvalidAction)`; the feature tracks replace those arms one at a time
 /// (see `docs/cashu/01-fundamentals.md` §6 action-ownership matrix).
 pub async fn run_cashu(ctx: AppContext) -> Result<()> {
-    let my_keys = ctx.keys();
     let client = ctx.nostr_client();
-    let pow = ctx.settings().mostro.pow;
-    #[allow(deprecated)]
-    let accepted_kind = ctx.settings().mostro.transport.event_kind();
-    let pow_first_contact = ctx.settings().mostro.effective_pow_first_contact();
-    let gate = gate_for(accepted_kind.as_u16() == crate::config::constants::DM_EVENT_KIND);
 
     loop {
         let mut notifications = client.notifications();
 
         while let Some(notification) = notifications.next().await {
             if let ClientNotification::Event { event, .. } = notification {
-                let Some((action, message, unwrapped)) = accept_event(
-                    &ctx,
-                    &event,
-                    my_keys,
-                    pow,
-                    pow_first_contact,
-                    accepted_kind,
-                    gate,
-                )
-                .await
-                else {
-                    continue;
-                };
-                let result =
-                    dispatch_cashu(&action, message.clone(), &unwrapped, my_keys, &ctx).await;
-                finalize_dispatch(result, message, unwrapped, &action).await;
+                let (instance, ctx) = route_event(&ctx, &event);
+                // Thresholds of the instance the event is addressed to.
+                let (pow, pow_first_contact, accepted_kind, gate) = admission(&ctx);
+                instance::within(instance, async {
+                    let my_keys = ctx.keys();
+                    let Some((action, message, unwrapped)) = accept_event(
+                        &ctx,
+                        &event,
+                        my_keys,
+                        pow,
+                        pow_first_contact,
+                        accepted_kind,
+                        gate,
+                    )
+                    .await
+                    else {
+                        return;
+                    };
+                    let result = match check_order_instance(&ctx, &message).await {
+                        Ok(()) => {
+                            dispatch_cashu(&action, message.clone(), &unwrapped, my_keys, &ctx)
+                                .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    finalize_dispatch(result, message, unwrapped, &action).await;
+                })
+                .await;
             }
         }
     }
@@ -592,6 +718,15 @@ pub async fn run_cashu(ctx: AppContext) -> Result<()> {
 ///   `RestoreSession`, `TradePubkey`.
 /// - **`AddCashuEscrow`** → `add_cashu_escrow_action` (a CF-5 stub Track A
 ///   fills in). Frozen here so Track A edits only its own file (G-1).
+/// - **Release happy path** (Track B) → `FiatSent` and `RateUser` through
+///   `handle_message_action_no_ln`, `Release` through `release_action` with
+///   the inert [`CashuBackend`]; each handler branches on Cashu mode itself.
+/// - **Cooperative cancel** (Track C) → `Cancel` through `cancel_action` with
+///   the inert [`CashuBackend`]; no Cashu order carries a hold invoice hash.
+/// - **Disputes** (Track D) → `Dispute`, `AdminTakeDispute` and
+///   `AdminAddSolver` through `handle_message_action_no_ln`; `AdminSettle` and
+///   `AdminCancel` through their Cashu handlers, which sign with `P_M` for the
+///   winner.
 /// - **Blocked** → `CantDo(InvalidAction)` — everything that creates, advances,
 ///   or settles an order (there is no escrow behind it yet). The feature tracks
 ///   replace these arms one at a time; the action-ownership matrix in
@@ -620,9 +755,37 @@ async fn dispatch_cashu(
         Action::AddCashuEscrow => add_cashu_escrow_action(ctx, msg, event, my_keys)
             .await
             .map_err(|e| e.into()),
-        // Everything that advances or settles an order past the lock has no
-        // handler yet during Track A — reject it cleanly. Later tracks
-        // (release/cancel/dispute) replace these arms one at a time.
+        // Release happy path (Track B). `fiat_sent_action` applies the
+        // remaining-locktime guard, `release_action` takes its Cashu branch
+        // before touching the escrow backend — the stub is only there to
+        // satisfy the signature and fails typed if it is ever reached — and
+        // rating stays gated on `Success`, which only the release watcher
+        // reaches.
+        Action::FiatSent | Action::RateUser => {
+            handle_message_action_no_ln(action, msg, event, my_keys, ctx).await
+        }
+        Action::Release => release_action(ctx, msg, event, my_keys, &mut CashuBackend::new())
+            .await
+            .map_err(|e| e.into()),
+        // Cooperative cancel (Track C). The handshake is unchanged; step 2
+        // takes its Cashu branch and the seller reclaims the escrow itself.
+        Action::Cancel => cancel_action(ctx, msg, event, my_keys, &mut CashuBackend::new())
+            .await
+            .map_err(|e| e.into()),
+        // Disputes (Track D). Opening and taking a dispute touch no escrow;
+        // the solver's ruling makes Mostro sign the escrow with `P_M` for the
+        // winner instead of settling or cancelling a hold invoice.
+        Action::Dispute | Action::AdminTakeDispute | Action::AdminAddSolver => {
+            handle_message_action_no_ln(action, msg, event, my_keys, ctx).await
+        }
+        Action::AdminSettle => admin_settle_cashu_action(ctx, msg, event, my_keys)
+            .await
+            .map_err(|e| e.into()),
+        Action::AdminCancel => admin_cancel_cashu_action(ctx, msg, event, my_keys)
+            .await
+            .map_err(|e| e.into()),
+        // Everything else that advances or settles an order past the lock has
+        // no handler yet — reject it cleanly.
         _ => Err(MostroError::MostroCantDo(CantDoReason::InvalidAction).into()),
     }
 }
@@ -651,20 +814,60 @@ mod tests {
         )
     }
 
+    #[test]
+    fn order_book_query_passes_inner_verification() {
+        let query = Message::new_order(
+            None,
+            Some(1),
+            None,
+            Action::Orders,
+            Some(Payload::TextMessage("{}".to_string())),
+        );
+        let kind = query.get_inner_message_kind();
+        assert!(!kind.verify(), "mostro-core alone rejects the query");
+        assert!(is_daemon_extension(kind));
+
+        let other = Message::new_order(
+            None,
+            Some(1),
+            None,
+            Action::NewOrder,
+            Some(Payload::TextMessage("{}".to_string())),
+        );
+        assert!(!is_daemon_extension(other.get_inner_message_kind()));
+    }
+
     // An AppContext backed by a fresh in-memory database with the migrations
     // applied. Shared by every child test module through `use super::*`.
     async fn create_migrated_ctx() -> AppContext {
-        let pool = std::sync::Arc::new(sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+        let pool = std::sync::Arc::new(crate::db::test_utils::migrated_pool().await);
         crate::app::context::test_utils::TestContextBuilder::new()
             .with_pool(pool)
             .with_settings(crate::app::context::test_utils::test_settings())
             .build()
     }
 
+    #[tokio::test]
+    async fn admission_uses_the_pow_of_the_addressed_instance() {
+        let ctx = create_migrated_ctx().await;
+        let mut settings = crate::app::context::test_utils::test_settings();
+        settings.mostro.pow = 0;
+        settings.instances = vec![crate::config::types::InstanceSettings {
+            id: "strict".into(),
+            nsec_privkey: secrecy::SecretString::from(
+                create_test_keys().secret_key().to_bech32().unwrap(),
+            ),
+            pow: Some(20),
+            ..Default::default()
+        }];
+        let instances = instance::build(&mut settings, &create_test_keys()).unwrap();
+
+        let (pow, first_contact, ..) = admission(&ctx.for_instance(&instances[0]));
+        assert_eq!((pow, first_contact), (0, 0));
+        let (pow, first_contact, ..) = admission(&ctx.for_instance(&instances[1]));
+        assert_eq!((pow, first_contact), (20, 20));
+    }
+
     // Helper function to create an UnwrappedMessage for testing. Identity and
     // sender (trade key) are distinct to mirror the canonical Mostro flow.
     fn create_test_unwrapped_message() -> UnwrappedMessage {
@@ -904,6 +1107,49 @@ mod tests {
             );
         }
 
+        /// An order book query goes through the whole prologue — unwrap,
+        /// trade index, inner verification — and on to the `orders` handler.
+        #[tokio::test]
+        async fn order_book_query_is_accepted_and_dispatched() {
+            let ctx = create_migrated_ctx().await;
+            let mostro = create_test_keys();
+            let trade = create_test_keys();
+            let query = Message::new_order(
+                None,
+                Some(1),
+                None,
+                Action::Orders,
+                Some(Payload::TextMessage(
+                    r#"{"kind":"sell","limit":10}"#.to_string(),
+                )),
+            );
+            let event = wrap_message_nip44(
+                &query,
+                &trade,
+                &trade,
+                mostro.public_key(),
+                WrapOptions::default(),
+            )
+            .expect("wrap kind-14 event");
+
+            let (action, message, unwrapped) = accept_event(
+                &ctx,
+                &event,
+                &mostro,
+                0,
+                0,
+                NostrKind::from(crate::config::constants::DM_EVENT_KIND),
+                None,
+            )
+            .await
+            .expect("the query must pass inner verification");
+            assert_eq!(action, Action::Orders);
+
+            let result =
+                handle_message_action_no_ln(&action, message, &unwrapped, &mostro, &ctx).await;
+            assert!(result.is_ok(), "{result:?}");
+        }
+
         /// The v2-only policy lives in one place now; both event loops read it
         /// from here, so this is where it gets covered.
         #[test]
@@ -918,11 +1164,10 @@ mod tests {
     mod check_trade_index_tests {
         use super::*;
         use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-        use sqlx::SqlitePool;
         use std::sync::Arc;
 
         async fn create_test_ctx() -> AppContext {
-            let pool = Arc::new(SqlitePool::connect(":memory:").await.unwrap());
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
             TestContextBuilder::new()
                 .with_pool(pool)
                 .with_settings(test_settings())
@@ -1079,6 +1324,33 @@ mod tests {
             assert_eq!(user.last_trade_index, 4);
         }
 
+        #[tokio::test]
+        async fn check_trade_index_refuses_banned_identities() {
+            let ctx = create_migrated_ctx().await;
+            let event = create_test_unwrapped_message();
+            crate::db::set_user_banned(ctx.pool(), &event.identity.to_hex(), true)
+                .await
+                .unwrap();
+
+            for action in [Action::NewOrder, Action::TakeBuy, Action::TakeSell] {
+                let message = create_test_message(action, Some(1));
+                let result = check_trade_index(&ctx, &event, &message).await;
+                assert!(matches!(
+                    result,
+                    Err(MostroError::MostroCantDo(CantDoReason::NotAuthorized))
+                ));
+            }
+            // Non-trading actions are untouched.
+            let message = create_test_message(Action::FiatSent, None);
+            assert!(check_trade_index(&ctx, &event, &message).await.is_ok());
+
+            crate::db::set_user_banned(ctx.pool(), &event.identity.to_hex(), false)
+                .await
+                .unwrap();
+            let message = create_test_message(Action::NewOrder, None);
+            assert!(check_trade_index(&ctx, &event, &message).await.is_ok());
+        }
+
         #[tokio::test]
         async fn test_check_trade_index_with_valid_index() {
             let ctx = create_test_ctx().await;
@@ -1096,7 +1368,6 @@ mod tests {
     mod handle_message_action_tests {
         use super::*;
         use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-        use sqlx::SqlitePool;
         use std::sync::Arc;
 
         fn create_restore_session_message() -> Message {
@@ -1105,11 +1376,7 @@ mod tests {
 
         #[tokio::test]
         async fn routes_last_trade_index_to_handler_and_propagates_error() {
-            let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
 
             let ctx = TestContextBuilder::new()
                 .with_pool(pool)
@@ -1131,11 +1398,7 @@ mod tests {
 
         #[tokio::test]
         async fn routes_restore_session_to_handler_and_returns_ok() {
-            let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
 
             let ctx = TestContextBuilder::new()
                 .with_pool(pool)
@@ -1155,11 +1418,7 @@ mod tests {
 
         #[tokio::test]
         async fn routes_orders_to_handler_and_propagates_error() {
-            let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
 
             let ctx = TestContextBuilder::new()
                 .with_pool(pool)
@@ -1185,11 +1444,7 @@ mod tests {
                 crate::config::MOSTRO_CONFIG.set(crate::app::context::test_utils::test_settings());
             let _ = crate::NOSTR_CLIENT.set(nostr_sdk::prelude::Client::default());
 
-            let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
 
             let ctx = TestContextBuilder::new()
                 .with_pool(pool)
@@ -1225,11 +1480,7 @@ mod tests {
 
         #[tokio::test]
         async fn routes_payinvoice_to_typed_invalid_action_error() {
-            let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
 
             let ctx = TestContextBuilder::new()
                 .with_pool(pool)
@@ -1263,12 +1514,11 @@ mod tests {
             )
         }
 
-        /// Actions with no Cashu handler yet — release/cancel/dispute, the
-        /// permanently-blocked buyer-invoice/bond actions, and Track D admin
-        /// actions — must still be rejected with `CantDo(InvalidAction)`. The
-        /// Track A actions (`NewOrder`, `TakeBuy`, `TakeSell` in TA-2;
-        /// `AddCashuEscrow` in TA-1) are excluded: they now route to their real
-        /// handlers, not to `InvalidAction`.
+        /// Actions with no Cashu handler — the permanently-blocked
+        /// buyer-invoice and bond actions — must still be rejected with
+        /// `CantDo(InvalidAction)`. Every other lifecycle action (Track A
+        /// creation and take, Track B release, Track C cancel, Track D
+        /// disputes) now routes to its real handler.
         #[tokio::test]
         async fn blocks_every_order_lifecycle_action_with_invalid_action() {
             let _ =
@@ -1278,24 +1528,42 @@ mod tests {
             let my_keys = create_test_keys();
             let event = create_test_unwrapped_message();
 
+            for action in [Action::AddInvoice, Action::AddBondInvoice] {
+                let msg = create_test_message(action.clone(), None);
+                let result = dispatch_cashu(&action, msg, &event, &my_keys, &ctx).await;
+                assert!(
+                    is_invalid_action(result),
+                    "{action:?} must be blocked with InvalidAction in Cashu mode"
+                );
+            }
+        }
+
+        /// The Track B release path, the Track C cancel and the Track D
+        /// dispute actions reach their handlers: with no such order or dispute
+        /// in the database each one fails on the lookup, never with
+        /// `InvalidAction`.
+        #[tokio::test]
+        async fn routes_release_cancel_and_disputes_to_handlers() {
+            let ctx = create_migrated_ctx().await;
+            let my_keys = create_test_keys();
+            let event = create_test_unwrapped_message();
+
             for action in [
-                Action::AddInvoice,
                 Action::FiatSent,
                 Action::Release,
+                Action::RateUser,
                 Action::Cancel,
                 Action::Dispute,
-                Action::RateUser,
-                Action::AdminCancel,
-                Action::AdminSettle,
-                Action::AddBondInvoice,
                 Action::AdminTakeDispute,
                 Action::AdminAddSolver,
+                Action::AdminSettle,
+                Action::AdminCancel,
             ] {
                 let msg = create_test_message(action.clone(), None);
                 let result = dispatch_cashu(&action, msg, &event, &my_keys, &ctx).await;
                 assert!(
-                    is_invalid_action(result),
-                    "{action:?} must be blocked with InvalidAction in Cashu mode"
+                    result.is_err() && !is_invalid_action(result),
+                    "{action:?} must route to its handler in Cashu mode"
                 );
             }
         }
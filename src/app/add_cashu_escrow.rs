@@ -18,13 +18,13 @@
 use crate::app::context::AppContext;
 use crate::cashu::{cashu_pubkey_from_xonly_hex, Error as CashuError};
 use crate::config::settings::Settings;
+use crate::db::Crud;
+use crate::db::DbPool;
 use crate::db::{cashu_escrow_token_in_use, update_order_cashu_escrow};
 use crate::util::{enqueue_order_msg, get_order, update_order_event};
 use chrono::Utc;
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::SqlitePool;
 
 /// Seconds in a day — the escrow locktime floor is configured in days
 /// (`cashu.escrow_locktime_days`, §4B) and enforced here in seconds.
@@ -89,7 +89,7 @@ async fn notify_escrow_locked(
 ///
 /// `Ok(())` therefore means "safe idempotent no-op", never "accepted".
 async fn classify_zero_row_cas(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: uuid::Uuid,
     token: &str,
 ) -> Result<(), MostroError> {
@@ -295,6 +295,7 @@ pub async fn add_cashu_escrow_action(
         );
         return Ok(());
     }
+    crate::rpc::events::order_status_changed(order.id, &Status::Active.to_string());
 
     // 9. Publish the updated (Active) order event so the public state stays
     //    consistent, mirroring the LN funding path. Best-effort: the lock is
@@ -331,17 +332,15 @@ pub async fn add_cashu_escrow_action(
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+    use crate::db::DbPool;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
-    async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    fn build_ctx(pool: &SqlitePool) -> AppContext {
+    fn build_ctx(pool: &DbPool) -> AppContext {
         TestContextBuilder::new()
             .with_pool(Arc::new(pool.clone()))
             .with_settings(test_settings())
@@ -370,10 +369,10 @@ mod tests {
 
     /// Stamp the escrow columns straight onto a row, standing in for a lock
     /// this handler already committed (the state a replay arrives against).
-    async fn mark_locked(pool: &SqlitePool, id: uuid::Uuid, token: &str, status: Status) {
+    async fn mark_locked(pool: &DbPool, id: uuid::Uuid, token: &str, status: Status) {
         sqlx::query(
-            "UPDATE orders SET cashu_mint_url = ?1, cashu_escrow_token = ?2,
-             cashu_escrow_locked_at = ?3, status = ?4 WHERE id = ?5",
+            "UPDATE orders SET cashu_mint_url = $1, cashu_escrow_token = $2,
+             cashu_escrow_locked_at = $3, status = $4 WHERE id = $5",
         )
         .bind("https://mint.example.com")
         .bind(token)
@@ -387,9 +386,9 @@ mod tests {
 
     /// The escrow columns as stored, to assert a rejected or replayed
     /// submission rewrote nothing.
-    async fn escrow_columns(pool: &SqlitePool, id: uuid::Uuid) -> (Option<String>, Option<i64>) {
+    async fn escrow_columns(pool: &DbPool, id: uuid::Uuid) -> (Option<String>, Option<i64>) {
         sqlx::query_as(
-            "SELECT cashu_escrow_token, cashu_escrow_locked_at FROM orders WHERE id = ?1",
+            "SELECT cashu_escrow_token, cashu_escrow_locked_at FROM orders WHERE id = $1",
         )
         .bind(id)
         .fetch_one(pool)
@@ -667,7 +666,7 @@ mod tests {
             .create(&pool)
             .await
             .unwrap();
-        sqlx::query("UPDATE orders SET status = ?1 WHERE id = ?2")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::Canceled.to_string())
             .bind(moved.id)
             .execute(&pool)
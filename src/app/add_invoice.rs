@@ -1,12 +1,12 @@
 use crate::app::context::AppContext;
+use crate::db::Crud;
+use crate::db::DbPool;
 use crate::util::{
     enqueue_order_msg, get_order, notify_taker_reputation, show_hold_invoice, update_order_event,
     validate_invoice, HoldInvoiceOrigin,
 };
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 
 /// Persist a replacement buyer invoice on a `SettledHoldInvoice` order and reset
 /// `payment_attempts`.
@@ -17,17 +17,20 @@ use sqlx::{Pool, Sqlite};
 /// `payout_payment_hash IS NULL` guard additionally rejects the swap while a
 /// prior payout for the order is still in flight: without it, swapping to a
 /// fresh invoice and resetting `payment_attempts` re-arms a new payout on top of
-/// an already-dispatched one (the invoice-swap re-arm drain). When the CAS
+/// an already-dispatched one (the invoice-swap re-arm drain). A live
+/// on-chain swap payout (`crate::swap`) blocks the update the same way. When the CAS
 /// misses, returns `CantDo(NotAllowedByStatus)` and does not enqueue
 /// `InvoiceUpdated`.
 pub async fn pay_new_invoice(
     order: &mut Order,
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     msg: &Message,
 ) -> Result<(), MostroError> {
     let result = sqlx::query(
-        "UPDATE orders SET buyer_invoice = ?, payment_attempts = 0 \
-         WHERE id = ? AND status = ? AND payout_payment_hash IS NULL",
+        "UPDATE orders SET buyer_invoice = $1, payment_attempts = 0 \
+         WHERE id = $2 AND status = $3 AND payout_payment_hash IS NULL \
+           AND NOT EXISTS (SELECT 1 FROM swaps WHERE swaps.order_id = orders.id \
+                           AND swaps.status IN ('created', 'claimed'))",
     )
     .bind(&order.buyer_invoice)
     .bind(order.id)
@@ -106,6 +109,7 @@ pub async fn add_invoice_action(
                 updated_order.clone().update(pool).await.map_err(|cause| {
                     MostroInternalErr(ServiceError::DbAccessError(cause.to_string()))
                 })?;
+                crate::rpc::events::order_status_changed(updated_order.id, &updated_order.status);
                 updated_order
             }
             Err(e) => return Err(e),
@@ -138,6 +142,7 @@ pub async fn add_invoice_action(
         )
         .await;
     } else if let Err(cause) = show_hold_invoice(
+        pool,
         my_keys,
         None,
         &buyer_pubkey,
@@ -161,19 +166,15 @@ pub async fn add_invoice_action(
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-    use sqlx::SqlitePool;
+    use crate::db::DbPool;
     use std::sync::Arc;
 
-    async fn setup_pool() -> Arc<SqlitePool> {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+    async fn setup_pool() -> Arc<DbPool> {
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         pool
     }
 
-    fn build_ctx(pool: Arc<SqlitePool>) -> AppContext {
+    fn build_ctx(pool: Arc<DbPool>) -> AppContext {
         // update_order_event reads the global config (expiration/nostr);
         // seeding it is idempotent and safe under concurrent tests.
         let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
@@ -225,9 +226,7 @@ mod tests {
     }
 
     async fn queued_actions_for(destination: PublicKey) -> Vec<Action> {
-        crate::config::MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(_, pk)| *pk == destination)
@@ -79,6 +79,14 @@ pub async fn admin_add_solver_action(
         }
     }
 
+    ack_admin_add_solver(event, my_keys, request_id).await
+}
+
+pub(crate) async fn ack_admin_add_solver(
+    event: &UnwrappedMessage,
+    my_keys: &Keys,
+    request_id: Option<u64>,
+) -> Result<(), MostroError> {
     let message = Message::new_dispute(None, request_id, None, Action::AdminAddSolver, None);
     let message = message
         .as_json()
@@ -189,18 +197,16 @@ mod tests {
         use crate::app::context::test_utils::{test_settings, TestContextBuilder};
         use crate::app::context::AppContext;
         use crate::db::is_user_present;
+        use crate::db::DbPool;
         use mostro_core::prelude::*;
         use nostr_sdk::prelude::*;
-        use sqlx::SqlitePool;
         use std::sync::Arc;
 
-        async fn create_test_pool() -> SqlitePool {
-            let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-            sqlx::migrate!().run(&pool).await.unwrap();
-            pool
+        async fn create_test_pool() -> DbPool {
+            crate::db::test_utils::migrated_pool().await
         }
 
-        fn build_ctx(pool: &SqlitePool) -> AppContext {
+        fn build_ctx(pool: &DbPool) -> AppContext {
             // send_dm reads the global config (event expiration); seed it
             // once, ignoring the error when another test already did.
             let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
@@ -0,0 +1,160 @@
+use crate::app::admin_add_solver::ack_admin_add_solver;
+use crate::app::context::AppContext;
+use crate::db::set_user_banned;
+use mostro_core::prelude::*;
+use nostr_sdk::prelude::*;
+use serde::Deserialize;
+use tracing::info;
+
+/// Admin request to ban or unban an identity.
+///
+/// mostro-core has no ban action, so the request travels with
+/// `admin-add-solver` as a JSON `Payload::TextMessage` — `{"ban":"<npub>"}`
+/// or `{"unban":"<npub>"}` — which no solver key can be mistaken for. Any
+/// JSON object on that action is read as a ban request.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum BanRequest {
+    Ban(String),
+    Unban(String),
+}
+
+impl BanRequest {
+    /// The ban request carried by `msg`, or `None` when the payload is not a
+    /// JSON object (the solver handler then parses it as a solver key). A
+    /// JSON object that is not a well-formed request is rejected here rather
+    /// than handed to the solver handler.
+    pub fn from_message(msg: &Message) -> Option<Result<Self, MostroError>> {
+        match msg.get_inner_message_kind().payload.as_ref()? {
+            Payload::TextMessage(raw) if raw.trim_start().starts_with('{') => Some(
+                serde_json::from_str(raw)
+                    .map_err(|_| MostroCantDo(CantDoReason::InvalidTextMessage)),
+            ),
+            _ => None,
+        }
+    }
+
+    fn parts(&self) -> (&str, bool) {
+        match self {
+            BanRequest::Ban(key) => (key, true),
+            BanRequest::Unban(key) => (key, false),
+        }
+    }
+}
+
+pub async fn admin_ban_user_action(
+    ctx: &AppContext,
+    msg: Message,
+    request: BanRequest,
+    event: &UnwrappedMessage,
+    my_keys: &Keys,
+) -> Result<(), MostroError> {
+    if event.identity != my_keys.public_key() {
+        return Err(MostroInternalErr(ServiceError::InvalidPubkey));
+    }
+
+    let (key, banned) = request.parts();
+    let public_key =
+        PublicKey::parse(key.trim()).map_err(|_| MostroCantDo(CantDoReason::InvalidPubkey))?;
+    set_user_banned(ctx.pool(), &public_key.to_hex(), banned).await?;
+    info!(
+        "User {} {}",
+        public_key,
+        if banned { "banned" } else { "unbanned" }
+    );
+
+    ack_admin_add_solver(event, my_keys, msg.get_inner_message_kind().request_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+    use crate::db::is_user_present;
+    use std::sync::Arc;
+
+    fn ban_msg(text: &str) -> Message {
+        Message::new_dispute(
+            None,
+            Some(1),
+            None,
+            Action::AdminAddSolver,
+            Some(Payload::TextMessage(text.to_string())),
+        )
+    }
+
+    #[test]
+    fn from_message_reads_only_the_typed_payload() {
+        assert_eq!(
+            BanRequest::from_message(&ban_msg(r#"{"ban":"npub1test"}"#)),
+            Some(Ok(BanRequest::Ban("npub1test".to_string())))
+        );
+        assert_eq!(
+            BanRequest::from_message(&ban_msg(r#"{"unban":"npub1test"}"#)),
+            Some(Ok(BanRequest::Unban("npub1test".to_string())))
+        );
+        // Solver payloads, including the old `<npub>:ban` form, are not bans.
+        for text in ["npub1test", "npub1test:read", "npub1test:ban"] {
+            assert_eq!(BanRequest::from_message(&ban_msg(text)), None, "{text}");
+        }
+        // A malformed ban is refused, never read as a solver key.
+        for text in [
+            r#"{"mute":"x"}"#,
+            r#"{"ban":5}"#,
+            r#"{"ban":"npub1test","unban":"npub1test"}"#,
+            r#" {"ban":"npub1test""#,
+        ] {
+            assert_eq!(
+                BanRequest::from_message(&ban_msg(text)),
+                Some(Err(MostroCantDo(CantDoReason::InvalidTextMessage))),
+                "{text}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn ban_and_unban_toggle_the_flag() {
+        let pool = crate::db::test_utils::migrated_pool().await;
+        // send_dm reads the global config (event expiration).
+        let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
+        let ctx = TestContextBuilder::new()
+            .with_pool(Arc::new(pool.clone()))
+            .with_settings(test_settings())
+            .build();
+        let my_keys = Keys::generate();
+        let event = UnwrappedMessage {
+            message: ban_msg(""),
+            signature: None,
+            sender: my_keys.public_key(),
+            identity: my_keys.public_key(),
+            created_at: Timestamp::now(),
+        };
+        let user = Keys::generate().public_key();
+        let npub = user.to_bech32().unwrap();
+
+        for (text, expected) in [
+            (format!(r#"{{"ban":"{npub}"}}"#), 1),
+            (format!(r#"{{"unban":"{npub}"}}"#), 0),
+        ] {
+            let msg = ban_msg(&text);
+            let request = BanRequest::from_message(&msg).unwrap().unwrap();
+            admin_ban_user_action(&ctx, msg, request, &event, &my_keys)
+                .await
+                .unwrap();
+            let row = is_user_present(&pool, user.to_hex()).await.unwrap();
+            assert_eq!(row.is_banned, expected);
+        }
+
+        // Only the Mostro key may ban.
+        let intruder = UnwrappedMessage {
+            identity: Keys::generate().public_key(),
+            ..event
+        };
+        let msg = ban_msg(&format!(r#"{{"ban":"{npub}"}}"#));
+        let request = BanRequest::from_message(&msg).unwrap().unwrap();
+        assert!(matches!(
+            admin_ban_user_action(&ctx, msg, request, &intruder, &my_keys).await,
+            Err(MostroInternalErr(ServiceError::InvalidPubkey))
+        ));
+    }
+}
@@ -1,18 +1,21 @@
+use chrono::Utc;
 use std::str::FromStr;
 
 use crate::app::bond::{self, BondSlashReason};
 use crate::app::context::AppContext;
-use crate::db::{
-    ensure_dispute_finalize_permission, find_dispute_by_order_id, is_assigned_solver,
-    is_dispute_taken_by_admin,
+use crate::app::dispute::{
+    authorize_dispute_finalizer, cashu_escrow_snapshot, cashu_ruling_needs_signature,
+    close_dispute_after_user_resolution, sign_escrow_as_arbitrator,
 };
-use crate::lightning::LndConnector;
+use crate::db::Crud;
+use crate::db::{find_dispute_by_order_id, resolve_cashu_dispute};
+use crate::escrow::EscrowBackend;
 use crate::nip33::{create_dispute_event_tags, new_dispute_event};
+use crate::reputation::{self, DisputeLoser};
 use crate::util::{enqueue_order_msg, get_order, send_dm, update_order_event};
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 /// Admin-initiated order cancellation.
 ///
@@ -49,7 +52,7 @@ pub async fn admin_cancel_action(
     msg: Message,
     event: &UnwrappedMessage,
     my_keys: &Keys,
-    ln_client: &mut LndConnector,
+    ln_client: &mut dyn EscrowBackend,
 ) -> Result<(), MostroError> {
     let pool = ctx.pool();
     // Get request id
@@ -57,30 +60,7 @@ pub async fn admin_cancel_action(
     // Get order
     let order = get_order(&msg, pool).await?;
     // Check if the solver is assigned to the order
-    match is_assigned_solver(pool, &event.identity.to_string(), order.id).await {
-        Ok(false) => {
-            // Check if admin has taken over the dispute
-            if is_dispute_taken_by_admin(pool, order.id, &my_keys.public_key().to_string()).await? {
-                return Err(MostroCantDo(CantDoReason::DisputeTakenByAdmin));
-            } else {
-                return Err(MostroCantDo(CantDoReason::IsNotYourDispute));
-            }
-        }
-        Err(e) => {
-            return Err(MostroInternalErr(ServiceError::DbAccessError(
-                e.to_string(),
-            )));
-        }
-        _ => {}
-    }
-
-    ensure_dispute_finalize_permission(
-        pool,
-        &event.identity.to_string(),
-        &my_keys.public_key().to_string(),
-        order.id,
-    )
-    .await?;
+    authorize_dispute_finalizer(pool, event, my_keys, order.id).await?;
 
     // Was order cooperatively cancelled?
     if order.check_status(Status::CooperativelyCanceled).is_ok() {
@@ -140,6 +120,20 @@ pub async fn admin_cancel_action(
         d.update(pool)
             .await
             .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+        crate::rpc::events::dispute_changed(
+            dispute_id,
+            order.id,
+            &DisputeStatus::SellerRefunded.to_string(),
+        );
+        if let Err(e) = crate::dispute_sla::record_resolution(
+            pool,
+            dispute_id,
+            Timestamp::now().as_secs() as i64,
+        )
+        .await
+        {
+            tracing::warn!(%dispute_id, "dispute resolution time not recorded: {e}");
+        }
         // We create a tag to show status of the dispute
         let tags = create_dispute_event_tags(
             DisputeStatus::SellerRefunded.to_string(),
@@ -165,10 +159,18 @@ pub async fn admin_cancel_action(
     let order_updated = update_order_event(my_keys, Status::CanceledByAdmin, &order)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
-    order_updated
+    let order_updated = order_updated
         .update(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::order_status_changed(order_updated.id, &order_updated.status);
+    for loser in DisputeLoser::of_cancel(&bond_resolution) {
+        if let Err(e) =
+            reputation::record_lost_dispute(pool, &order, loser, Utc::now().timestamp()).await
+        {
+            warn!(order_id = %order.id, "admin_cancel: dispute mark failed: {}", e);
+        }
+    }
     // We create a Message for cancel
     let message = Message::new_order(
         Some(order.id),
@@ -251,24 +253,153 @@ pub async fn admin_cancel_action(
     Ok(())
 }
 
+/// Admin cancel in Cashu mode (Track D §4B(3)): the solver rules for the
+/// seller, so Mostro signs the escrow with `P_M` and hands the signatures to
+/// the seller, who redeems with `P_M + P_S`.
+///
+/// Moves `Dispute → CanceledByAdmin` with a conditional write; see
+/// [`cashu_ruling_needs_signature`] for what the mint state allows. A
+/// repeated cancel of a `CanceledByAdmin` order re-sends fresh signatures
+/// while the escrow is still unspent. Refunding the Mostro fee token is left
+/// to the fee-token work (TA-1f), which does not exist yet.
+pub async fn admin_cancel_cashu_action(
+    ctx: &AppContext,
+    msg: Message,
+    event: &UnwrappedMessage,
+    my_keys: &Keys,
+) -> Result<(), MostroError> {
+    let pool = ctx.pool();
+    let request_id = msg.get_inner_message_kind().request_id;
+    let trade_index = msg.get_inner_message_kind().trade_index;
+    let order = get_order(&msg, pool).await?;
+
+    authorize_dispute_finalizer(pool, event, my_keys, order.id).await?;
+
+    if order.check_status(Status::CooperativelyCanceled).is_ok() {
+        enqueue_order_msg(
+            request_id,
+            Some(order.id),
+            Action::CooperativeCancelAccepted,
+            None,
+            event.identity,
+            trade_index,
+        )
+        .await;
+        return Ok(());
+    }
+
+    let redelivery = order.check_status(Status::CanceledByAdmin).is_ok();
+    if !redelivery && order.check_status(Status::Dispute).is_err() {
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+
+    let escrow = cashu_escrow_snapshot(ctx, &order).await?;
+    let now = Utc::now().timestamp();
+    let sign = cashu_ruling_needs_signature(escrow.state).inspect_err(|_| {
+        error!(
+            "admin_cancel: order {}: escrow is {:?} at the mint (locktime {}), refusing to cancel",
+            order.id, escrow.state, escrow.locktime
+        )
+    })?;
+    if redelivery && !sign {
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+    let signatures = if sign {
+        Some(sign_escrow_as_arbitrator(&escrow.token, my_keys)?)
+    } else {
+        None
+    };
+    let seller_pubkey = order.get_seller_pubkey().map_err(MostroInternalErr)?;
+    let buyer_pubkey = order.get_buyer_pubkey().map_err(MostroInternalErr)?;
+
+    if !redelivery {
+        let order_updated = update_order_event(my_keys, Status::CanceledByAdmin, &order)
+            .await
+            .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+        let signed_to = sign.then(|| seller_pubkey.to_string());
+        let won = resolve_cashu_dispute(pool, &order_updated, signed_to.as_deref(), now).await?;
+        if !won {
+            warn!(
+                "Order {} not transitioned to canceled-by-admin: status changed concurrently",
+                order.id
+            );
+            return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+        }
+        close_dispute_after_user_resolution(
+            ctx,
+            &order_updated,
+            DisputeStatus::SellerRefunded,
+            my_keys,
+            "admin cancel",
+        )
+        .await;
+        if !sign {
+            warn!(
+                "admin_cancel: order {}: escrow already spent at the mint, ruling recorded as moot",
+                order.id
+            );
+        } else {
+            for loser in DisputeLoser::of_cancel(&bond::extract_bond_resolution(&msg)) {
+                if let Err(e) =
+                    reputation::record_lost_dispute(pool, &order_updated, loser, now).await
+                {
+                    warn!(order_id = %order_updated.id, "admin_cancel: dispute mark failed: {}", e);
+                }
+            }
+        }
+    }
+
+    if let Some(signatures) = signatures {
+        enqueue_order_msg(
+            None,
+            Some(order.id),
+            Action::CashuPmSignature,
+            Some(Payload::CashuSignatures(signatures)),
+            seller_pubkey,
+            None,
+        )
+        .await;
+    }
+    enqueue_order_msg(
+        request_id,
+        Some(order.id),
+        Action::AdminCanceled,
+        None,
+        event.sender,
+        trade_index,
+    )
+    .await;
+    if !redelivery {
+        for pubkey in [seller_pubkey, buyer_pubkey] {
+            enqueue_order_msg(
+                None,
+                Some(order.id),
+                Action::AdminCanceled,
+                None,
+                pubkey,
+                None,
+            )
+            .await;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+    use crate::db::DbPool;
     use crate::lightning::LndConnector;
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
-    async fn setup_pool() -> Arc<SqlitePool> {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+    async fn setup_pool() -> Arc<DbPool> {
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         pool
     }
 
-    fn build_ctx(pool: Arc<SqlitePool>) -> AppContext {
+    fn build_ctx(pool: Arc<DbPool>) -> AppContext {
         let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
         TestContextBuilder::new()
             .with_pool(pool)
@@ -322,7 +453,7 @@ mod tests {
         }
     }
 
-    async fn assign_solver(pool: &SqlitePool, order_id: uuid::Uuid, solver: &PublicKey) {
+    async fn assign_solver(pool: &DbPool, order_id: uuid::Uuid, solver: &PublicKey) {
         let mut dispute = Dispute::new(order_id, Status::Dispute.to_string());
         dispute.status = DisputeStatus::InProgress.to_string();
         dispute.solver_pubkey = Some(solver.to_string());
@@ -334,9 +465,7 @@ mod tests {
     }
 
     async fn queued_actions_for(destination: PublicKey) -> Vec<Action> {
-        crate::config::MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(_, pk)| *pk == destination)
@@ -597,8 +726,11 @@ mod tests {
         let seller = Keys::generate().public_key();
         let buyer = Keys::generate().public_key();
 
+        let buyer_identity = Keys::generate().public_key().to_string();
+
         let mut order = dispute_order(seller, buyer);
         order.seller_dispute = true;
+        order.master_buyer_pubkey = Some(buyer_identity.clone());
         let order = order.create(ctx.pool()).await.unwrap();
         assign_solver(ctx.pool(), order.id, &admin.public_key()).await;
         let dispute_id = find_dispute_by_order_id(ctx.pool(), order.id)
@@ -625,5 +757,15 @@ mod tests {
             stored_dispute.status,
             DisputeStatus::SellerRefunded.to_string()
         );
+        // Refunding the seller is a ruling against the buyer, slash or not
+        let marks: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM dispute_marks WHERE pubkey = $1 AND order_id = $2",
+        )
+        .bind(&buyer_identity)
+        .bind(order.id)
+        .fetch_one(ctx.pool())
+        .await
+        .unwrap();
+        assert_eq!(marks, 1);
     }
 }
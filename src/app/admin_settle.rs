@@ -1,18 +1,21 @@
 use crate::app::bond::{self, BondSlashReason};
 use crate::app::context::AppContext;
-use crate::db::{
-    ensure_dispute_finalize_permission, find_dispute_by_order_id, is_assigned_solver,
-    is_dispute_taken_by_admin,
+use crate::app::dispute::{
+    authorize_dispute_finalizer, cashu_escrow_snapshot, cashu_ruling_needs_signature,
+    close_dispute_after_user_resolution, sign_escrow_as_arbitrator,
 };
-use crate::lightning::LndConnector;
+use crate::db::{find_dispute_by_order_id, resolve_cashu_dispute};
+use crate::escrow::EscrowBackend;
 use crate::nip33::{create_dispute_event_tags, new_dispute_event};
+use crate::reputation::{self, DisputeLoser};
 use crate::util::{enqueue_order_msg, get_order, settle_seller_hold_invoice, update_order_event};
 
-use mostro_core::db::Crud;
+use crate::db::Crud;
+use chrono::Utc;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
 use std::str::FromStr;
-use tracing::error;
+use tracing::{error, warn};
 
 use super::release::do_payment;
 
@@ -21,7 +24,7 @@ pub async fn admin_settle_action(
     msg: Message,
     event: &UnwrappedMessage,
     my_keys: &Keys,
-    ln_client: &mut LndConnector,
+    ln_client: &mut dyn EscrowBackend,
 ) -> Result<(), MostroError> {
     let pool = ctx.pool();
     // Get request id
@@ -29,34 +32,7 @@ pub async fn admin_settle_action(
     // Get order
     let order = get_order(&msg, pool).await?;
 
-    match is_assigned_solver(pool, &event.identity.to_string(), order.id).await {
-        Ok(false) => {
-            // Check if admin has taken over the dispute
-            if is_dispute_taken_by_admin(pool, order.id, &my_keys.public_key().to_string()).await? {
-                return Err(MostroCantDo(
-                    mostro_core::error::CantDoReason::DisputeTakenByAdmin,
-                ));
-            } else {
-                return Err(MostroCantDo(
-                    mostro_core::error::CantDoReason::IsNotYourDispute,
-                ));
-            }
-        }
-        Err(e) => {
-            return Err(MostroInternalErr(ServiceError::DbAccessError(
-                e.to_string(),
-            )));
-        }
-        _ => {}
-    }
-
-    ensure_dispute_finalize_permission(
-        pool,
-        &event.identity.to_string(),
-        &my_keys.public_key().to_string(),
-        order.id,
-    )
-    .await?;
+    authorize_dispute_finalizer(pool, event, my_keys, order.id).await?;
 
     // Was order cooperatively cancelled?
     if order.check_status(Status::CooperativelyCanceled).is_ok() {
@@ -102,7 +78,7 @@ pub async fn admin_settle_action(
 
     // Persist the status change to DB before calling do_payment (same reason as release_action)
     let result =
-        sqlx::query("UPDATE orders SET status = ?, event_id = ? WHERE id = ? AND status = ?")
+        sqlx::query("UPDATE orders SET status = $1, event_id = $2 WHERE id = $3 AND status = $4")
             .bind(&order_updated.status)
             .bind(&order_updated.event_id)
             .bind(order_updated.id)
@@ -118,6 +94,17 @@ pub async fn admin_settle_action(
         );
         return Ok(());
     }
+    crate::rpc::events::order_status_changed(order_updated.id, &order_updated.status);
+    if let Err(e) = reputation::record_lost_dispute(
+        pool,
+        &order_updated,
+        DisputeLoser::Seller,
+        Utc::now().timestamp(),
+    )
+    .await
+    {
+        warn!(order_id = %order_updated.id, "admin_settle: dispute mark failed: {}", e);
+    }
 
     // we check if there is a dispute
     let dispute = find_dispute_by_order_id(pool, order.id).await;
@@ -130,6 +117,20 @@ pub async fn admin_settle_action(
         d.update(pool)
             .await
             .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+        crate::rpc::events::dispute_changed(
+            dispute_id,
+            order.id,
+            &DisputeStatus::Settled.to_string(),
+        );
+        if let Err(e) = crate::dispute_sla::record_resolution(
+            pool,
+            dispute_id,
+            Timestamp::now().as_secs() as i64,
+        )
+        .await
+        {
+            tracing::warn!(%dispute_id, "dispute resolution time not recorded: {e}");
+        }
 
         // Get the creator of the dispute
         let dispute_initiator = match (order.seller_dispute, order.buyer_dispute) {
@@ -245,24 +246,180 @@ pub async fn admin_settle_action(
     Ok(())
 }
 
+/// Admin settle in Cashu mode (Track D §4B(3)): the solver rules for the
+/// buyer, so Mostro signs the escrow with `P_M` and hands the signatures to
+/// the buyer, who redeems with `P_M + P_B`.
+///
+/// - Refuses (`NotAllowedByStatus`, no signature, no status change) when the
+///   escrow is partly spent at the mint
+/// - Moves `Dispute → SettledByAdmin` with a conditional write recording who
+///   was signed for; `CompletedByAdmin` is left to the release watcher, once
+///   the redeem is observed
+/// - Moves `Dispute → CompletedByAdmin` without signing or marking the seller
+///   when the escrow is already spent: a moot ruling, see
+///   [`cashu_ruling_needs_signature`]
+/// - Re-sends fresh signatures on a repeated settle of a `SettledByAdmin`
+///   order, the recovery for a lost message
+pub async fn admin_settle_cashu_action(
+    ctx: &AppContext,
+    msg: Message,
+    event: &UnwrappedMessage,
+    my_keys: &Keys,
+) -> Result<(), MostroError> {
+    let pool = ctx.pool();
+    let request_id = msg.get_inner_message_kind().request_id;
+    let trade_index = msg.get_inner_message_kind().trade_index;
+    let order = get_order(&msg, pool).await?;
+
+    authorize_dispute_finalizer(pool, event, my_keys, order.id).await?;
+
+    if order.check_status(Status::CooperativelyCanceled).is_ok() {
+        enqueue_order_msg(
+            request_id,
+            Some(order.id),
+            Action::CooperativeCancelAccepted,
+            None,
+            event.identity,
+            trade_index,
+        )
+        .await;
+        return Ok(());
+    }
+
+    let redelivery = order.check_status(Status::SettledByAdmin).is_ok();
+    if !redelivery {
+        if let Err(cause) = order.check_status(Status::Dispute) {
+            return Err(MostroCantDo(cause));
+        }
+    }
+
+    let escrow = cashu_escrow_snapshot(ctx, &order).await?;
+    let sign = cashu_ruling_needs_signature(escrow.state).inspect_err(|_| {
+        error!(
+            "admin_settle: order {}: escrow is {:?} at the mint, refusing to settle",
+            order.id, escrow.state
+        )
+    })?;
+    if redelivery && !sign {
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+    if sign && Utc::now().timestamp() as u64 >= escrow.locktime {
+        warn!(
+            "admin_settle: order {}: escrow locktime {} has passed, the seller can reclaim at any moment — the buyer must swap immediately",
+            order.id, escrow.locktime
+        );
+    }
+    let signatures = if sign {
+        Some(sign_escrow_as_arbitrator(&escrow.token, my_keys)?)
+    } else {
+        None
+    };
+    let buyer_pubkey = order.get_buyer_pubkey().map_err(MostroInternalErr)?;
+    let seller_pubkey = order.get_seller_pubkey().map_err(MostroInternalErr)?;
+
+    if !redelivery {
+        // With the escrow already spent there is no redeem left for the
+        // release watcher to observe: the ruling completes the order, as a
+        // moot one (see `cashu_ruling_needs_signature`).
+        let status = if sign {
+            Status::SettledByAdmin
+        } else {
+            Status::CompletedByAdmin
+        };
+        let order_updated = update_order_event(my_keys, status, &order)
+            .await
+            .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+        let signed_to = sign.then(|| buyer_pubkey.to_string());
+        let won = resolve_cashu_dispute(
+            pool,
+            &order_updated,
+            signed_to.as_deref(),
+            Utc::now().timestamp(),
+        )
+        .await?;
+        if !won {
+            warn!(
+                "Order {} not transitioned to settled-by-admin: status changed concurrently",
+                order.id
+            );
+            return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+        }
+        close_dispute_after_user_resolution(
+            ctx,
+            &order_updated,
+            DisputeStatus::Settled,
+            my_keys,
+            "admin settle",
+        )
+        .await;
+        if !sign {
+            warn!(
+                "admin_settle: order {}: escrow already spent at the mint, ruling recorded as moot",
+                order.id
+            );
+        } else if let Err(e) = reputation::record_lost_dispute(
+            pool,
+            &order_updated,
+            DisputeLoser::Seller,
+            Utc::now().timestamp(),
+        )
+        .await
+        {
+            warn!(order_id = %order_updated.id, "admin_settle: dispute mark failed: {}", e);
+        }
+    }
+
+    if let Some(signatures) = signatures {
+        enqueue_order_msg(
+            None,
+            Some(order.id),
+            Action::CashuPmSignature,
+            Some(Payload::CashuSignatures(signatures)),
+            buyer_pubkey,
+            None,
+        )
+        .await;
+    }
+    enqueue_order_msg(
+        request_id,
+        Some(order.id),
+        Action::AdminSettled,
+        None,
+        event.sender,
+        trade_index,
+    )
+    .await;
+    if !redelivery {
+        for pubkey in [seller_pubkey, buyer_pubkey] {
+            enqueue_order_msg(
+                None,
+                Some(order.id),
+                Action::AdminSettled,
+                None,
+                pubkey,
+                None,
+            )
+            .await;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod handler_tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+    use crate::db::DbPool;
     use crate::lightning::LndConnector;
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
-    async fn setup_pool() -> Arc<SqlitePool> {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+    async fn setup_pool() -> Arc<DbPool> {
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         pool
     }
 
-    fn build_ctx(pool: Arc<SqlitePool>) -> AppContext {
+    fn build_ctx(pool: Arc<DbPool>) -> AppContext {
         let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
         TestContextBuilder::new()
             .with_pool(pool)
@@ -318,7 +475,7 @@ mod handler_tests {
         }
     }
 
-    async fn assign_solver(pool: &SqlitePool, order_id: uuid::Uuid, solver: &PublicKey) {
+    async fn assign_solver(pool: &DbPool, order_id: uuid::Uuid, solver: &PublicKey) {
         // `Dispute::new` always starts in `Initiated`; the admin-takeover
         // detection queries for `in-progress`, so set it explicitly.
         let mut dispute = Dispute::new(order_id, Status::Dispute.to_string());
@@ -332,9 +489,7 @@ mod handler_tests {
     }
 
     async fn queued_actions_for(destination: PublicKey) -> Vec<Action> {
-        crate::config::MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(_, pk)| *pk == destination)
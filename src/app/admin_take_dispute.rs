@@ -1,15 +1,19 @@
 use crate::app::admin_add_solver::SOLVER_CATEGORY_READ_ONLY;
 use crate::app::context::AppContext;
+use crate::app::evidence::deliver_to_solver;
+use crate::app::fiat_sent::settlement_window_open;
+use crate::cashu::escrow_token_locktime;
+use crate::config::settings::Settings;
+use crate::db::DbPool;
 use crate::db::{find_solver_pubkey, is_user_present, user_has_solver_write_permission};
 use crate::nip33::{create_dispute_event_tags, new_dispute_event};
 use crate::util::{get_dispute, send_dm};
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 
-use mostro_core::db::Crud;
+use crate::db::Crud;
 use std::str::FromStr;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Prepares the solver information message for a dispute.
 ///
@@ -34,7 +38,7 @@ use tracing::info;
 /// This function may return errors related to invalid public keys or database access issues, which are handled
 /// by mapping them to `MostroError`.
 async fn prepare_solver_info_message(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
     dispute: &Dispute,
 ) -> Result<SolverDisputeInfo, MostroError> {
@@ -84,7 +88,7 @@ async fn prepare_solver_info_message(
 }
 
 pub async fn pubkey_event_can_solve(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     ev_pubkey: &PublicKey,
     status: DisputeStatus,
     current_solver_pubkey: Option<&str>,
@@ -146,6 +150,24 @@ pub async fn pubkey_event_can_solve(
     current_solver.is_solver != 0_i64 && current_solver.category == SOLVER_CATEGORY_READ_ONLY
 }
 
+/// Log a warning, with the deadline, when a disputed Cashu escrow has less
+/// than the settlement margin of locktime left.
+fn warn_if_escrow_near_locktime(order: &Order, margin_days: u32) {
+    let Some(locktime) = order
+        .cashu_escrow_token
+        .as_deref()
+        .and_then(|token| escrow_token_locktime(token).ok())
+    else {
+        return;
+    };
+    if !settlement_window_open(locktime, Timestamp::now().as_secs(), margin_days) {
+        warn!(
+            "Order {}: escrow locktime expires at {} — the seller can reclaim the escrow after it, rule before then",
+            order.id, locktime
+        );
+    }
+}
+
 pub async fn admin_take_dispute_action(
     ctx: &AppContext,
     msg: Message,
@@ -187,6 +209,12 @@ pub async fn admin_take_dispute_action(
         return Err(MostroCantDo(CantDoReason::NotFound));
     };
 
+    // Cashu escrow mode: once the locktime passes the seller can reclaim
+    // the escrow alone, so a ruling for the buyer must land before it.
+    if let Some(cashu) = Settings::get_cashu().filter(|c| c.enabled) {
+        warn_if_escrow_near_locktime(&order, cashu.escrow_settlement_margin_days);
+    }
+
     // Update dispute fields
     dispute.status = Status::InProgress.to_string();
     dispute.solver_pubkey = Some(event.identity.to_string());
@@ -200,6 +228,7 @@ pub async fn admin_take_dispute_action(
         .update(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::dispute_changed(dispute.id, order.id, &dispute.status);
 
     // Get the creator of the dispute
     let dispute_initiator = match (order.seller_dispute, order.buyer_dispute) {
@@ -249,6 +278,10 @@ pub async fn admin_take_dispute_action(
     send_dm(event.identity, mostro_keys, &message, None)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+    // Followed by the evidence the parties attached so far
+    if let Err(e) = deliver_to_solver(pool, dispute.id, event.identity, mostro_keys).await {
+        warn!(dispute_id = %dispute.id, "evidence not delivered to solver: {e}");
+    }
 
     // Now we create a message to both parties of the order
     // to them know who will assist them on the dispute
@@ -306,23 +339,21 @@ mod tests {
     use crate::app::admin_add_solver::{SOLVER_CATEGORY_READ_ONLY, SOLVER_CATEGORY_READ_WRITE};
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
     use crate::db::add_new_user;
+    use crate::db::DbPool;
     use mostro_core::user::User;
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
-    async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    async fn insert_solver(pool: &SqlitePool, pubkey: &str, category: i64) {
+    async fn insert_solver(pool: &DbPool, pubkey: &str, category: i64) {
         add_new_user(pool, User::new(pubkey.to_string(), 0, 1, 0, category, 0))
             .await
             .unwrap();
     }
 
-    fn build_ctx(pool: &SqlitePool) -> AppContext {
+    fn build_ctx(pool: &DbPool) -> AppContext {
         // The DM/publish path reads the global config (event expiration);
         // seed it once, ignoring the error when another test already did.
         let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
@@ -2,8 +2,9 @@
 
 use std::future::Future;
 
-use mostro_core::db::Crud;
-use sqlx::{query_builder::Separated, Pool, QueryBuilder, Sqlite};
+use crate::db::Crud;
+use crate::db::{Db, DbPool};
+use sqlx::{query_builder::Separated, QueryBuilder};
 
 use super::model::Bond;
 
@@ -45,7 +46,7 @@ pub(crate) const BOND_INSERT_COLUMNS: &[&str] = &[
     "taker_dev_fee",
 ];
 
-fn push_bond_insert_binds(b: &mut Separated<'_, Sqlite, &'static str>, bond: &Bond) {
+fn push_bond_insert_binds(b: &mut Separated<'_, Db, &'static str>, bond: &Bond) {
     b.push_bind(bond.id)
         .push_bind(bond.order_id)
         .push_bind(bond.parent_bond_id)
@@ -79,7 +80,7 @@ fn push_bond_insert_binds(b: &mut Separated<'_, Sqlite, &'static str>, bond: &Bo
         .push_bind(bond.taker_dev_fee);
 }
 
-fn push_bond_update_set(set: &mut Separated<'_, Sqlite, &'static str>, bond: &Bond) {
+fn push_bond_update_set(set: &mut Separated<'_, Db, &'static str>, bond: &Bond) {
     // `created_at` is insert-only: omit from UPDATE so a mutated in-memory
     // `Bond` cannot rewrite the row's creation timestamp (schedulers order by it).
     set.push("order_id = ").push_bind_unseparated(bond.order_id);
@@ -138,7 +139,7 @@ fn push_bond_update_set(set: &mut Separated<'_, Sqlite, &'static str>, bond: &Bo
 }
 
 impl Crud for Bond {
-    fn create(self, pool: &Pool<Sqlite>) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
+    fn create(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
         let pool = pool.clone();
         async move {
             let mut qb = QueryBuilder::new("INSERT INTO bonds (");
@@ -157,7 +158,7 @@ impl Crud for Bond {
         }
     }
 
-    fn update(self, pool: &Pool<Sqlite>) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
+    fn update(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
         let pool = pool.clone();
         async move {
             let mut qb = QueryBuilder::new("UPDATE bonds SET ");
@@ -173,12 +174,12 @@ impl Crud for Bond {
     }
 
     fn by_id(
-        pool: &Pool<Sqlite>,
+        pool: &DbPool,
         id: uuid::Uuid,
     ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send {
         let pool = pool.clone();
         async move {
-            sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE id = ? LIMIT 1")
+            sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE id = $1 LIMIT 1")
                 .bind(id)
                 .fetch_optional(&pool)
                 .await
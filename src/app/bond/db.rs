@@ -3,10 +3,11 @@
 //! Phase 0 exposes the CRUD surface later phases will need. Nothing in
 //! this module hits LND or the Nostr client — it's purely storage.
 
-use mostro_core::db::Crud;
+use crate::db::Crud;
+use crate::db::DbPool;
 use mostro_core::error::{MostroError, MostroError::MostroInternalErr, ServiceError};
 use mostro_core::order::{Order, Status};
-use sqlx::{AssertSqlSafe, Pool, Sqlite};
+use sqlx::AssertSqlSafe;
 use uuid::Uuid;
 
 use super::model::Bond;
@@ -20,7 +21,7 @@ const MAX_RANGE_CHAIN_DEPTH: usize = 1024;
 
 /// Insert a new bond row. Returns the persisted `Bond`.
 pub async fn create_bond(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: Bond,
 ) -> Result<Bond, mostro_core::error::MostroError> {
     bond.create(pool)
@@ -37,14 +38,14 @@ pub async fn create_bond(
 /// `parent_bond_id IS NULL` predicate keeps this lookup pinned to the
 /// parent bond so state transitions always target the right row.
 pub async fn find_bond_by_order_and_role(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
     role: BondRole,
 ) -> Result<Option<Bond>, mostro_core::error::MostroError> {
     let role_str = role.to_string();
     sqlx::query_as::<_, Bond>(
         "SELECT * FROM bonds \
-         WHERE order_id = ? AND role = ? AND parent_bond_id IS NULL \
+         WHERE order_id = $1 AND role = $2 AND parent_bond_id IS NULL \
          LIMIT 1",
     )
     .bind(order_id)
@@ -57,11 +58,11 @@ pub async fn find_bond_by_order_and_role(
 /// List every bond currently in the given state. Used by the Phase 3
 /// payout scheduler.
 pub async fn find_bonds_by_state(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     state: BondState,
 ) -> Result<Vec<Bond>, mostro_core::error::MostroError> {
     let state_str = state.to_string();
-    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE state = ? ORDER BY created_at ASC")
+    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE state = $1 ORDER BY created_at ASC")
         .bind(state_str)
         .fetch_all(pool)
         .await
@@ -72,7 +73,7 @@ pub async fn find_bonds_by_state(
 /// scheduler to check a child slash row's parent state (skip while the
 /// parent HTLC is still `Locked`, i.e. before range close).
 pub async fn find_bond_by_id(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     id: Uuid,
 ) -> Result<Option<Bond>, mostro_core::error::MostroError> {
     Bond::by_id(pool, id)
@@ -84,10 +85,10 @@ pub async fn find_bond_by_id(
 /// identifies the bond hold invoice, so this is what the LND subscriber
 /// uses to correlate incoming invoice events back to a `Bond`.
 pub async fn find_bond_by_hash(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     hash: &str,
 ) -> Result<Option<Bond>, mostro_core::error::MostroError> {
-    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE hash = ? LIMIT 1")
+    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE hash = $1 LIMIT 1")
         .bind(hash)
         .fetch_optional(pool)
         .await
@@ -100,11 +101,11 @@ pub async fn find_bond_by_hash(
 /// "always release" exits — we filter further on `order_id` in
 /// [`find_active_bonds_for_order`].
 pub async fn find_active_bonds(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
 ) -> Result<Vec<Bond>, mostro_core::error::MostroError> {
     let requested = BondState::Requested.to_string();
     let locked = BondState::Locked.to_string();
-    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE state IN (?, ?) ORDER BY created_at ASC")
+    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE state IN ($1, $2) ORDER BY created_at ASC")
         .bind(requested)
         .bind(locked)
         .fetch_all(pool)
@@ -116,14 +117,14 @@ pub async fn find_active_bonds(
 /// uses this to release every bond on any order exit path (cancel,
 /// release, admin actions, scheduler timeouts).
 pub async fn find_active_bonds_for_order(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
 ) -> Result<Vec<Bond>, mostro_core::error::MostroError> {
     let requested = BondState::Requested.to_string();
     let locked = BondState::Locked.to_string();
     sqlx::query_as::<_, Bond>(
         "SELECT * FROM bonds \
-         WHERE order_id = ? AND state IN (?, ?) \
+         WHERE order_id = $1 AND state IN ($2, $3) \
          ORDER BY created_at ASC",
     )
     .bind(order_id)
@@ -145,7 +146,7 @@ pub async fn find_active_bonds_for_order(
 /// Filters on `parent_bond_id IS NULL` to ignore Phase 6 child slash
 /// rows, mirroring `find_bond_by_order_and_role`.
 pub async fn find_active_bond_by_taker(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
     taker_pubkey: &str,
 ) -> Result<Option<Bond>, mostro_core::error::MostroError> {
@@ -153,7 +154,7 @@ pub async fn find_active_bond_by_taker(
     let locked = BondState::Locked.to_string();
     sqlx::query_as::<_, Bond>(
         "SELECT * FROM bonds \
-         WHERE order_id = ? AND pubkey = ? AND state IN (?, ?) \
+         WHERE order_id = $1 AND pubkey = $2 AND state IN ($3, $4) \
            AND parent_bond_id IS NULL \
          ORDER BY created_at ASC \
          LIMIT 1",
@@ -176,10 +177,7 @@ pub async fn find_active_bond_by_taker(
 /// non-range order or an already-root order returns itself. The walk is
 /// bounded by [`MAX_RANGE_CHAIN_DEPTH`]; a missing parent row (should never
 /// happen) terminates the walk at the deepest order we could load.
-pub async fn find_range_root_order(
-    pool: &Pool<Sqlite>,
-    order: Order,
-) -> Result<Order, MostroError> {
+pub async fn find_range_root_order(pool: &DbPool, order: Order) -> Result<Order, MostroError> {
     let mut current = order;
     for _ in 0..MAX_RANGE_CHAIN_DEPTH {
         let Some(parent_id) = current.range_parent_id else {
@@ -207,7 +205,7 @@ pub async fn find_range_root_order(
 /// bond exists (feature off, `apply_to` excludes the maker, or the bond
 /// was already released).
 pub async fn find_maker_bond_for_order(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
 ) -> Result<Option<Bond>, MostroError> {
     let root = find_range_root_order(pool, order.clone()).await?;
@@ -218,11 +216,11 @@ pub async fn find_maker_bond_for_order(
 /// range-order accounting). Ordered oldest-first for deterministic
 /// iteration at parent-close.
 pub async fn find_child_slashes_for_parent(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     parent_bond_id: Uuid,
 ) -> Result<Vec<Bond>, MostroError> {
     sqlx::query_as::<_, Bond>(
-        "SELECT * FROM bonds WHERE parent_bond_id = ? ORDER BY created_at ASC",
+        "SELECT * FROM bonds WHERE parent_bond_id = $1 ORDER BY created_at ASC",
     )
     .bind(parent_bond_id)
     .fetch_all(pool)
@@ -252,12 +250,12 @@ const TERMINAL_ORDER_STATUSES: [Status; 7] = [
 /// (`reconcile_stranded_range_maker_bonds`) starts from this set and then
 /// filters to the ones whose whole range tree has terminated. Child slash
 /// rows and refund rows (both `parent_bond_id IS NOT NULL`) are excluded.
-pub async fn find_locked_maker_parent_bonds(pool: &Pool<Sqlite>) -> Result<Vec<Bond>, MostroError> {
+pub async fn find_locked_maker_parent_bonds(pool: &DbPool) -> Result<Vec<Bond>, MostroError> {
     let locked = BondState::Locked.to_string();
     let maker = BondRole::Maker.to_string();
     sqlx::query_as::<_, Bond>(
         "SELECT * FROM bonds \
-         WHERE state = ? AND role = ? AND parent_bond_id IS NULL \
+         WHERE state = $1 AND role = $2 AND parent_bond_id IS NULL \
          ORDER BY created_at ASC",
     )
     .bind(locked)
@@ -276,13 +274,11 @@ pub async fn find_locked_maker_parent_bonds(pool: &Pool<Sqlite>) -> Result<Vec<B
 /// `true` only when every order in the tree — the root included — is in a
 /// [`TERMINAL_ORDER_STATUSES`] state, so the maker bond can be safely closed.
 /// A non-range or already-root order tree is just the single root row.
-pub async fn range_tree_fully_terminal(
-    pool: &Pool<Sqlite>,
-    root_id: Uuid,
-) -> Result<bool, MostroError> {
+pub async fn range_tree_fully_terminal(pool: &DbPool, root_id: Uuid) -> Result<bool, MostroError> {
     let placeholders = TERMINAL_ORDER_STATUSES
         .iter()
-        .map(|_| "?")
+        .enumerate()
+        .map(|(i, _)| format!("${}", i + 2))
         .collect::<Vec<_>>()
         .join(", ");
     // `UNION` (not `UNION ALL`) is deliberate: it deduplicates, so a corrupt
@@ -293,7 +289,7 @@ pub async fn range_tree_fully_terminal(
     // reduced to `COUNT(*) … == 0` below — the exact count is never used.
     let sql = format!(
         "WITH RECURSIVE tree(id, status) AS ( \
-             SELECT id, status FROM orders WHERE id = ? \
+             SELECT id, status FROM orders WHERE id = $1 \
              UNION \
              SELECT o.id, o.status FROM orders o JOIN tree t ON o.range_parent_id = t.id \
          ) \
@@ -312,12 +308,15 @@ pub async fn range_tree_fully_terminal(
 
 /// Update a bond row by primary key. Returns the persisted `Bond`.
 pub async fn update_bond(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: Bond,
 ) -> Result<Bond, mostro_core::error::MostroError> {
-    bond.update(pool)
+    let bond = bond
+        .update(pool)
         .await
-        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::bond_state_changed(&bond);
+    Ok(bond)
 }
 
 #[cfg(test)]
@@ -326,71 +325,18 @@ mod tests {
     use crate::app::bond::crud::BOND_INSERT_COLUMNS;
     use crate::app::bond::model::Bond;
     use crate::app::bond::types::{BondRole, BondSlashReason, BondState};
-    use sqlx::sqlite::SqlitePoolOptions;
+    use crate::db::DbRow;
 
-    async fn setup_pool() -> Pool<Sqlite> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .expect("open in-memory sqlite");
-        // Minimal orders table: bonds has an FK on it.
-        sqlx::query(include_str!(
-            "../../../migrations/20221222153301_orders.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("orders migration");
-        sqlx::query(include_str!(
-            "../../../migrations/20260423120000_anti_abuse_bond.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bonds migration");
-        sqlx::query(include_str!(
-            "../../../migrations/20260518120000_bond_payout_payment_hash.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bond_payout_payment_hash migration");
-        sqlx::query(include_str!(
-            "../../../migrations/20260611120000_bond_slice_slash_unique.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bond_slice_slash_unique migration");
-        // Later ALTER-only migrations add columns `Order::by_id` SELECTs
-        // (dev_fee, cashu escrow). Apply each statement separately —
-        // sqlx::query treats the whole string as one statement.
-        for stmt in include_str!("../../../migrations/20251126120000_dev_fee.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt).execute(&pool).await.expect("dev_fee");
-        }
-        for stmt in include_str!("../../../migrations/20260530120000_cashu_escrow_fields.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt).execute(&pool).await.expect("cashu");
-        }
-        // SQLite doesn't enforce FKs unless asked. Turn them on so the FK to
-        // `orders` is a real constraint in tests (mirrors production).
-        sqlx::query("PRAGMA foreign_keys = ON")
-            .execute(&pool)
-            .await
-            .expect("enable fk");
-        pool
+    async fn setup_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    async fn insert_parent_order(pool: &Pool<Sqlite>, id: Uuid) {
+    async fn insert_parent_order(pool: &DbPool, id: Uuid) {
         sqlx::query(
             r#"INSERT INTO orders (
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, created_at, expires_at
-            ) VALUES (?, 'buy', ?, 'pending', 0, 'ln', 1000, 'USD', 10, 0, 0)"#,
+            ) VALUES ($1, 'buy', $2, 'pending', 0, 'ln', 1000, 'USD', 10, 0, 0)"#,
         )
         .bind(id)
         .bind(id.simple().to_string())
@@ -402,7 +348,7 @@ mod tests {
     /// Insert an order with an explicit status and optional `range_parent_id`,
     /// so the range-tree-terminal walk can be exercised over a real chain.
     async fn insert_order_with(
-        pool: &Pool<Sqlite>,
+        pool: &DbPool,
         id: Uuid,
         status: Status,
         range_parent_id: Option<Uuid>,
@@ -411,7 +357,7 @@ mod tests {
             r#"INSERT INTO orders (
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, range_parent_id, created_at, expires_at
-            ) VALUES (?, 'sell', ?, ?, 0, 'ln', 1000, 'USD', 10, ?, 0, 0)"#,
+            ) VALUES ($1, 'sell', $2, $3, 0, 'ln', 1000, 'USD', 10, $4, 0, 0)"#,
         )
         .bind(id)
         .bind(id.simple().to_string())
@@ -501,7 +447,7 @@ mod tests {
         }
     }
 
-    fn row_text(row: &sqlx::sqlite::SqliteRow, column: &str) -> Option<String> {
+    fn row_text(row: &DbRow, column: &str) -> Option<String> {
         use sqlx::Row;
 
         match column {
@@ -552,7 +498,7 @@ mod tests {
         let bond_id = bond.id;
         create_bond(&pool, bond.clone()).await.unwrap();
 
-        let row = sqlx::query("SELECT * FROM bonds WHERE id = ?")
+        let row = sqlx::query("SELECT * FROM bonds WHERE id = $1")
             .bind(bond_id)
             .fetch_one(&pool)
             .await
@@ -582,7 +528,7 @@ mod tests {
         );
 
         // Terminate the descendant → the whole tree is terminal.
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::Expired.to_string())
             .bind(child)
             .execute(&pool)
@@ -610,13 +556,13 @@ mod tests {
         // Insert both first (no parent), then point them at each other.
         insert_order_with(&pool, a, Status::Active, None).await;
         insert_order_with(&pool, b, Status::Active, None).await;
-        sqlx::query("UPDATE orders SET range_parent_id = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET range_parent_id = $1 WHERE id = $2")
             .bind(b)
             .bind(a)
             .execute(&pool)
             .await
             .unwrap();
-        sqlx::query("UPDATE orders SET range_parent_id = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET range_parent_id = $1 WHERE id = $2")
             .bind(a)
             .bind(b)
             .execute(&pool)
@@ -627,7 +573,7 @@ mod tests {
         assert!(!range_tree_fully_terminal(&pool, a).await.unwrap());
 
         // Terminate both → the cyclic tree reads as terminal (still no hang).
-        sqlx::query("UPDATE orders SET status = ?")
+        sqlx::query("UPDATE orders SET status = $1")
             .bind(Status::Expired.to_string())
             .execute(&pool)
             .await
@@ -915,7 +861,7 @@ mod tests {
         let order_id = Uuid::new_v4();
         insert_parent_order(&pool, order_id).await;
         let missing_parent = Uuid::new_v4(); // never inserted
-        sqlx::query("UPDATE orders SET range_parent_id = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET range_parent_id = $1 WHERE id = $2")
             .bind(missing_parent)
             .bind(order_id)
             .execute(&pool)
@@ -938,7 +884,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_parent_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET range_parent_id = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET range_parent_id = $1 WHERE id = $2")
             .bind(order_id)
             .bind(order_id)
             .execute(&pool)
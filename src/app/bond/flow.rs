@@ -41,20 +41,21 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
+use crate::db::Crud;
+use crate::db::DbPool;
 use bitcoin::hashes::hex::FromHex;
 use chrono::Utc;
 use fedimint_tonic_lnd::lnrpc::invoice::InvoiceState;
-use mostro_core::db::Crud;
 use mostro_core::error::{MostroError, MostroError::MostroInternalErr, ServiceError};
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 use tokio::sync::mpsc::channel;
 use tracing::{info, warn};
 use uuid::Uuid;
 
 use crate::config::settings::Settings;
-use crate::lightning::{InvoiceMessage, LndConnector};
+use crate::escrow::connect_lightning;
+use crate::lightning::InvoiceMessage;
 use crate::util::{
     bytes_to_string, enqueue_order_msg, get_keys, set_waiting_invoice_status, show_hold_invoice,
     HoldInvoiceOrigin,
@@ -149,7 +150,7 @@ pub struct TakerContext {
 /// index, per-bond pricing, etc. live in `taker_*` columns) so the
 /// order row stays unmutated until the winning bond locks.
 pub async fn request_taker_bond(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
     taker_pubkey: PublicKey,
     request_id: Option<u64>,
@@ -167,8 +168,8 @@ pub async fn request_taker_bond(
     let amount = compute_bond_amount(taker_ctx.amount, cfg);
     let memo = format!("mostro bond order_id={}", order.id);
 
-    let mut ln_client = LndConnector::new().await?;
-    let (invoice_resp, preimage, hash) = ln_client
+    let mut ln_client = connect_lightning().await?;
+    let (payment_request, preimage, hash) = ln_client
         .create_hold_invoice(&memo, amount)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::HoldInvoiceError(e.to_string())))?;
@@ -176,7 +177,7 @@ pub async fn request_taker_bond(
     let mut bond = Bond::new_requested(order.id, taker_pubkey.to_string(), BondRole::Taker, amount);
     bond.hash = Some(bytes_to_string(&hash));
     bond.preimage = Some(bytes_to_string(&preimage));
-    bond.payment_request = Some(invoice_resp.payment_request.clone());
+    bond.payment_request = Some(payment_request.clone());
     bond.taker_identity = Some(taker_ctx.identity.clone());
     bond.taker_trade_index = Some(taker_ctx.trade_index);
     bond.taker_invoice = taker_ctx.buyer_invoice.clone();
@@ -224,7 +225,7 @@ pub async fn request_taker_bond(
     // the persisted bond so we don't strand a `Requested` row with
     // no listener — and keep the invoice unsent so the taker can
     // retry the take cleanly.
-    if let Err(e) = bond_invoice_subscribe(hash, request_id).await {
+    if let Err(e) = bond_invoice_subscribe(pool, hash, request_id).await {
         warn!(
             bond_id = %bond.id,
             order_id = %bond.order_id,
@@ -244,7 +245,7 @@ pub async fn request_taker_bond(
         Action::PayBondInvoice,
         Some(Payload::PaymentRequest(
             Some(bond_small),
-            invoice_resp.payment_request,
+            payment_request,
             None,
         )),
         taker_pubkey,
@@ -268,7 +269,7 @@ pub async fn request_taker_bond(
     // exit cleanly. If we win, we then republish the NIP-33 event and
     // patch the persisted `event_id` only (not the full row) so we
     // never clobber concurrent field updates.
-    let cas = sqlx::query("UPDATE orders SET status = ? WHERE id = ? AND status = ?")
+    let cas = sqlx::query("UPDATE orders SET status = $1 WHERE id = $2 AND status = $3")
         .bind(Status::WaitingTakerBond.to_string())
         .bind(order.id)
         .bind(Status::Pending.to_string())
@@ -285,10 +286,11 @@ pub async fn request_taker_bond(
         }
     };
     if claimed {
+        crate::rpc::events::order_status_changed(order.id, &Status::WaitingTakerBond.to_string());
         let my_keys = get_keys()?;
         match crate::util::update_order_event(my_keys, Status::WaitingTakerBond, order).await {
             Ok(updated) => {
-                if let Err(e) = sqlx::query("UPDATE orders SET event_id = ? WHERE id = ?")
+                if let Err(e) = sqlx::query("UPDATE orders SET event_id = $1 WHERE id = $2")
                     .bind(&updated.event_id)
                     .bind(order.id)
                     .execute(pool)
@@ -334,7 +336,7 @@ pub async fn request_taker_bond(
 /// `WaitingMakerBond` expiry path, mirroring the taker "always release"
 /// contract.
 pub async fn request_maker_bond(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
     maker_pubkey: PublicKey,
     notional_sats: i64,
@@ -350,8 +352,8 @@ pub async fn request_maker_bond(
     let amount = compute_bond_amount(notional_sats, cfg);
     let memo = format!("mostro bond order_id={}", order.id);
 
-    let mut ln_client = LndConnector::new().await?;
-    let (invoice_resp, preimage, hash) = ln_client
+    let mut ln_client = connect_lightning().await?;
+    let (payment_request, preimage, hash) = ln_client
         .create_hold_invoice(&memo, amount)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::HoldInvoiceError(e.to_string())))?;
@@ -359,7 +361,7 @@ pub async fn request_maker_bond(
     let mut bond = Bond::new_requested(order.id, maker_pubkey.to_string(), BondRole::Maker, amount);
     bond.hash = Some(bytes_to_string(&hash));
     bond.preimage = Some(bytes_to_string(&preimage));
-    bond.payment_request = Some(invoice_resp.payment_request.clone());
+    bond.payment_request = Some(payment_request.clone());
     // No `taker_*` context on a maker bond: those columns describe the
     // deferred take snapshot of a concurrent taker bond and stay NULL here.
 
@@ -397,7 +399,7 @@ pub async fn request_maker_bond(
     // rationale as the taker side: a fast payer must not race ahead of
     // the listener). On subscribe failure, release the row so we don't
     // strand a `Requested` bond with no listener.
-    if let Err(e) = bond_invoice_subscribe(hash, request_id).await {
+    if let Err(e) = bond_invoice_subscribe(pool, hash, request_id).await {
         warn!(
             bond_id = %bond.id,
             order_id = %bond.order_id,
@@ -414,7 +416,7 @@ pub async fn request_maker_bond(
         Action::PayBondInvoice,
         Some(Payload::PaymentRequest(
             Some(bond_small),
-            invoice_resp.payment_request,
+            payment_request,
             None,
         )),
         maker_pubkey,
@@ -440,7 +442,7 @@ pub(crate) enum CancelOutcome {
     Transient,
 }
 
-/// Classify the error returned by `LndConnector::cancel_hold_invoice`.
+/// Classify the error returned by `EscrowBackend::cancel_hold_invoice`.
 ///
 /// We rely on the `code=<grpc::Code>` prefix `cancel_hold_invoice`
 /// embeds, plus message-text patterns LND emits when an invoice is
@@ -500,7 +502,7 @@ pub(crate) fn classify_cancel_error(err: &MostroError) -> CancelOutcome {
 /// - Operators see a structured `warn` event with `bond_id`, `order_id`,
 ///   and the classified outcome so they can spot and intervene if a
 ///   bond stays stuck.
-pub async fn release_bond(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), MostroError> {
+pub async fn release_bond(pool: &DbPool, bond: &Bond) -> Result<(), MostroError> {
     // Parse `state` once into the enum so callers don't depend on the
     // `Display` form for control flow (and a malformed value short-
     // circuits to "no-op" instead of falsely transitioning).
@@ -515,7 +517,7 @@ pub async fn release_bond(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), Mostro
     }
 
     if let Some(hash) = bond.hash.as_ref() {
-        match LndConnector::new().await {
+        match connect_lightning().await {
             Ok(mut ln) => {
                 if let Err(e) = ln.cancel_hold_invoice(hash).await {
                     match classify_cancel_error(&e) {
@@ -591,10 +593,7 @@ pub async fn release_bond(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), Mostro
 /// Returns `Ok(())` when no active bonds exist; never fails the caller
 /// for individual bond failures (those are logged and the loop
 /// continues).
-pub async fn release_bonds_for_order(
-    pool: &Pool<Sqlite>,
-    order_id: Uuid,
-) -> Result<(), MostroError> {
+pub async fn release_bonds_for_order(pool: &DbPool, order_id: Uuid) -> Result<(), MostroError> {
     release_active_bonds(pool, order_id, false).await
 }
 
@@ -608,7 +607,7 @@ pub async fn release_bonds_for_order(
 /// abandoning taker side is released. Every other path releases all bonds
 /// (`retain_makers = false`).
 async fn release_active_bonds(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
     retain_makers: bool,
 ) -> Result<(), MostroError> {
@@ -632,11 +631,7 @@ async fn release_active_bonds(
 /// failure log a warning tagged with the call site — never propagate.
 /// Centralising the pattern keeps each call site to a single line and
 /// guarantees consistent log structure for operators.
-pub async fn release_bonds_for_order_or_warn(
-    pool: &Pool<Sqlite>,
-    order_id: Uuid,
-    context: &'static str,
-) {
+pub async fn release_bonds_for_order_or_warn(pool: &DbPool, order_id: Uuid, context: &'static str) {
     if let Err(e) = release_bonds_for_order(pool, order_id).await {
         warn!("{context}: bond release failed for {}: {}", order_id, e);
     }
@@ -648,7 +643,7 @@ pub async fn release_bonds_for_order_or_warn(
 /// book with the maker still committed; only the abandoning taker side is
 /// released.
 pub async fn release_taker_bonds_for_order_or_warn(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
     context: &'static str,
 ) {
@@ -664,41 +659,49 @@ pub async fn release_taker_bonds_for_order_or_warn(
 /// Mirrors the structure of `crate::util::invoice_subscribe` so restart
 /// resilience can later reuse the same shape.
 pub async fn bond_invoice_subscribe(
+    pool: &DbPool,
     hash: Vec<u8>,
     request_id: Option<u64>,
 ) -> Result<(), MostroError> {
-    let mut ln_client = LndConnector::new().await?;
+    let mut ln_client = connect_lightning().await?;
     let (tx, mut rx) = channel::<InvoiceMessage>(100);
 
-    tokio::spawn(async move {
+    crate::instance::spawn(async move {
         if let Err(e) = ln_client.subscribe_invoice(hash, tx).await {
             warn!("Bond invoice subscriber ended with error: {e}");
         }
     });
 
-    let pool = crate::config::settings::get_db_pool();
+    let pool = pool.clone();
 
-    tokio::spawn(async move {
+    crate::instance::spawn(async move {
         while let Some(msg) = rx.recv().await {
             let hash_hex = bytes_to_string(msg.hash.as_ref());
-            match msg.state {
-                InvoiceState::Accepted => {
-                    if let Err(e) = on_bond_invoice_accepted(&hash_hex, &pool, request_id).await {
-                        warn!("Bond invoice accepted handler error: {e}");
+            // The subscriber outlives the handler that started it: act as
+            // the instance owning the bonded order.
+            let owner = crate::instance::of_bond_hash(&pool, &hash_hex).await;
+            crate::instance::within(owner, async {
+                match msg.state {
+                    InvoiceState::Accepted => {
+                        if let Err(e) = on_bond_invoice_accepted(&hash_hex, &pool, request_id).await
+                        {
+                            warn!("Bond invoice accepted handler error: {e}");
+                        }
                     }
-                }
-                InvoiceState::Canceled => {
-                    if let Err(e) = on_bond_invoice_canceled(&hash_hex, &pool).await {
-                        warn!("Bond invoice canceled handler error: {e}");
+                    InvoiceState::Canceled => {
+                        if let Err(e) = on_bond_invoice_canceled(&hash_hex, &pool).await {
+                            warn!("Bond invoice canceled handler error: {e}");
+                        }
+                    }
+                    InvoiceState::Settled => {
+                        info!("Bond hash {hash_hex}: invoice settled");
+                    }
+                    InvoiceState::Open => {
+                        info!("Bond hash {hash_hex}: invoice open (waiting for payment)");
                     }
                 }
-                InvoiceState::Settled => {
-                    info!("Bond hash {hash_hex}: invoice settled");
-                }
-                InvoiceState::Open => {
-                    info!("Bond hash {hash_hex}: invoice open (waiting for payment)");
-                }
-            }
+            })
+            .await;
         }
     });
 
@@ -713,14 +716,14 @@ pub async fn bond_invoice_subscribe(
 /// continue to flow through state transitions even after an operator
 /// disables the feature, otherwise their hold invoices stay stranded
 /// in LND.
-pub async fn resubscribe_active_bonds(pool: &Arc<Pool<Sqlite>>) -> Result<(), MostroError> {
+pub async fn resubscribe_active_bonds(pool: &Arc<DbPool>) -> Result<(), MostroError> {
     let bonds = find_active_bonds(pool.as_ref()).await?;
     for bond in bonds.into_iter() {
         if let Some(hash) = bond.hash.as_ref() {
             // Hex string back to bytes for LND.
             match Vec::<u8>::from_hex(hash) {
                 Ok(bytes) => {
-                    if let Err(e) = bond_invoice_subscribe(bytes, None).await {
+                    if let Err(e) = bond_invoice_subscribe(pool.as_ref(), bytes, None).await {
                         warn!("Failed to resubscribe bond {}: {}", bond.id, e);
                     } else {
                         info!("Resubscribed bond {} (state={})", bond.id, bond.state);
@@ -770,7 +773,7 @@ pub async fn resubscribe_active_bonds(pool: &Arc<Pool<Sqlite>>) -> Result<(), Mo
 /// still `Pending`.
 async fn on_bond_invoice_accepted(
     hash: &str,
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     request_id: Option<u64>,
 ) -> Result<(), MostroError> {
     let bond = match find_bond_by_hash(pool, hash).await? {
@@ -803,11 +806,11 @@ async fn on_bond_invoice_accepted(
     // taker on a maker-bonded order.
     let now = Utc::now().timestamp();
     let result = sqlx::query(
-        "UPDATE bonds SET state = ?, locked_at = ? \
-         WHERE id = ? AND state = ? \
+        "UPDATE bonds SET state = $1, locked_at = $2 \
+         WHERE id = $3 AND state = $4 \
            AND NOT EXISTS ( \
              SELECT 1 FROM bonds b2 \
-             WHERE b2.order_id = ? AND b2.state = ? AND b2.role = ? AND b2.id != ? \
+             WHERE b2.order_id = $5 AND b2.state = $6 AND b2.role = $7 AND b2.id != $8 \
            )",
     )
     .bind(BondState::Locked.to_string())
@@ -821,6 +824,13 @@ async fn on_bond_invoice_accepted(
     .execute(pool)
     .await
     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    if result.rows_affected() == 1 {
+        crate::rpc::events::bond_transitioned(
+            bond.id,
+            bond.order_id,
+            &BondState::Locked.to_string(),
+        );
+    }
 
     // Re-read the bond so a concurrent release (Locked → Released) is
     // visible.
@@ -973,12 +983,12 @@ async fn on_bond_invoice_accepted(
 async fn on_maker_bond_accepted(
     bond: &Bond,
     hash: &str,
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     request_id: Option<u64>,
 ) -> Result<(), MostroError> {
     let now = Utc::now().timestamp();
     let result =
-        sqlx::query("UPDATE bonds SET state = ?, locked_at = ? WHERE id = ? AND state = ?")
+        sqlx::query("UPDATE bonds SET state = $1, locked_at = $2 WHERE id = $3 AND state = $4")
             .bind(BondState::Locked.to_string())
             .bind(now)
             .bind(bond.id)
@@ -986,6 +996,13 @@ async fn on_maker_bond_accepted(
             .execute(pool)
             .await
             .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    if result.rows_affected() == 1 {
+        crate::rpc::events::bond_transitioned(
+            bond.id,
+            bond.order_id,
+            &BondState::Locked.to_string(),
+        );
+    }
 
     // Re-read so a concurrent release (e.g. the order expired and its
     // bond was cancelled) is visible before we try to publish.
@@ -1069,7 +1086,7 @@ async fn notify_loser(bond: &Bond) {
 /// `resume_take_after_bond` reads them back via
 /// `order.get_buyer_pubkey()` / `order.get_seller_pubkey()`.
 async fn promote_taker_context_to_order(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     mut order: Order,
     bond: &Bond,
 ) -> Result<Order, MostroError> {
@@ -1124,7 +1141,7 @@ async fn promote_taker_context_to_order(
 /// reflects the empty-bond state. If other bonds are still racing,
 /// the order stays in `WaitingTakerBond` (its NIP-69 wire bucket
 /// remains `pending` either way).
-async fn on_bond_invoice_canceled(hash: &str, pool: &Pool<Sqlite>) -> Result<(), MostroError> {
+async fn on_bond_invoice_canceled(hash: &str, pool: &DbPool) -> Result<(), MostroError> {
     let bond = match find_bond_by_hash(pool, hash).await? {
         Some(b) => b,
         None => return Ok(()),
@@ -1194,15 +1211,15 @@ async fn on_bond_invoice_canceled(hash: &str, pool: &Pool<Sqlite>) -> Result<(),
 /// cancelled would never drop back to `Pending`. The drop-to-Pending
 /// decision depends solely on whether any *taker* is still racing.
 pub(crate) async fn drop_waiting_taker_bond_to_pending(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
 ) -> Result<bool, MostroError> {
     let cas = sqlx::query(
-        "UPDATE orders SET status = ? \
-         WHERE id = ? AND status = ? \
+        "UPDATE orders SET status = $1 \
+         WHERE id = $2 AND status = $3 \
            AND NOT EXISTS ( \
              SELECT 1 FROM bonds \
-             WHERE order_id = ? AND state IN (?, ?) AND role = ? \
+             WHERE order_id = $4 AND state IN ($5, $6) AND role = $7 \
            )",
     )
     .bind(Status::Pending.to_string())
@@ -1231,7 +1248,7 @@ pub(crate) async fn drop_waiting_taker_bond_to_pending(
 /// [`drop_waiting_taker_bond_to_pending`]; this wrapper adds the
 /// NIP-33 republish.
 pub(crate) async fn maybe_drop_waiting_taker_bond(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
 ) -> Result<(), MostroError> {
     if !drop_waiting_taker_bond_to_pending(pool, order_id).await? {
@@ -1241,6 +1258,7 @@ pub(crate) async fn maybe_drop_waiting_taker_bond(
         // status transition to publish.
         return Ok(());
     }
+    crate::rpc::events::order_status_changed(order_id, &Status::Pending.to_string());
 
     // We won the transition. Republish the NIP-33 event so the
     // orderbook reflects the new status. `update_order_event` re-reads
@@ -1259,7 +1277,7 @@ pub(crate) async fn maybe_drop_waiting_taker_bond(
     let my_keys = get_keys()?;
     match crate::util::update_order_event(my_keys, Status::Pending, &fresh).await {
         Ok(updated) => {
-            if let Err(e) = sqlx::query("UPDATE orders SET event_id = ? WHERE id = ?")
+            if let Err(e) = sqlx::query("UPDATE orders SET event_id = $1 WHERE id = $2")
                 .bind(&updated.event_id)
                 .bind(order_id)
                 .execute(pool)
@@ -1294,7 +1312,7 @@ pub(crate) async fn maybe_drop_waiting_taker_bond(
 /// `buyer_invoice` / per-take pricing in place and we can drive the
 /// trade flow exactly as the legacy path would have.
 async fn resume_take_after_bond(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     mut order: Order,
     my_keys: &Keys,
     request_id: Option<u64>,
@@ -1308,6 +1326,7 @@ async fn resume_take_after_bond(
         // mirror the post-take path in take_buy_action.
         mostro_core::order::Kind::Buy => {
             show_hold_invoice(
+                pool,
                 my_keys,
                 None,
                 &buyer_pubkey,
@@ -1325,6 +1344,7 @@ async fn resume_take_after_bond(
             if order.buyer_invoice.is_some() {
                 let payment_request = order.buyer_invoice.clone();
                 show_hold_invoice(
+                    pool,
                     my_keys,
                     payment_request,
                     &buyer_pubkey,
@@ -1371,66 +1391,17 @@ async fn resume_take_after_bond(
 mod tests {
     use super::*;
     use crate::app::bond::types::BondRole;
-    use sqlx::sqlite::SqlitePoolOptions;
 
-    async fn setup_pool() -> Pool<Sqlite> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .expect("open in-memory sqlite");
-        sqlx::query(include_str!(
-            "../../../migrations/20221222153301_orders.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("orders migration");
-        // dev_fee columns were added by a later migration but
-        // `Order::by_id` SELECTs them. Apply each ALTER as a separate
-        // statement (sqlx::query treats the whole string as one).
-        for stmt in include_str!("../../../migrations/20251126120000_dev_fee.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt)
-                .execute(&pool)
-                .await
-                .expect("dev_fee migration");
-        }
-        sqlx::query(include_str!(
-            "../../../migrations/20260423120000_anti_abuse_bond.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bonds migration");
-        sqlx::query(include_str!(
-            "../../../migrations/20260518120000_bond_payout_payment_hash.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bond_payout_payment_hash migration");
-        // cashu escrow columns (mostro-core 0.12.1) — `Order::by_id` SELECTs
-        // them. Apply each ALTER separately for the same reason as dev_fee.
-        for stmt in include_str!("../../../migrations/20260530120000_cashu_escrow_fields.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt)
-                .execute(&pool)
-                .await
-                .expect("cashu escrow migration");
-        }
-        pool
+    async fn setup_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    async fn insert_order(pool: &Pool<Sqlite>, id: Uuid) {
+    async fn insert_order(pool: &DbPool, id: Uuid) {
         sqlx::query(
             r#"INSERT INTO orders (
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, created_at, expires_at
-            ) VALUES (?, 'buy', ?, 'pending', 0, 'ln', 1000, 'USD', 10, 0, 0)"#,
+            ) VALUES ($1, 'buy', $2, 'pending', 0, 'ln', 1000, 'USD', 10, 0, 0)"#,
         )
         .bind(id)
         .bind(id.simple().to_string())
@@ -1450,13 +1421,13 @@ mod tests {
     /// `on_bond_invoice_accepted` (including the `role = 'taker'` filter on
     /// the `NOT EXISTS` guard). Returns `rows_affected`. Kept in lockstep
     /// with the real query so the race tests verify the actual semantics.
-    async fn try_lock(pool: &Pool<Sqlite>, bond: &Bond) -> u64 {
+    async fn try_lock(pool: &DbPool, bond: &Bond) -> u64 {
         sqlx::query(
-            "UPDATE bonds SET state = ?, locked_at = ? \
-             WHERE id = ? AND state = ? \
+            "UPDATE bonds SET state = $1, locked_at = $2 \
+             WHERE id = $3 AND state = $4 \
                AND NOT EXISTS ( \
                  SELECT 1 FROM bonds b2 \
-                 WHERE b2.order_id = ? AND b2.state = ? AND b2.role = ? AND b2.id != ? \
+                 WHERE b2.order_id = $5 AND b2.state = $6 AND b2.role = $7 AND b2.id != $8 \
                )",
         )
         .bind(BondState::Locked.to_string())
@@ -1603,8 +1574,8 @@ mod tests {
         bond.hash = Some("e".repeat(64));
         let bond = create_bond(&pool, bond).await.unwrap();
 
-        async fn try_lock(pool: &Pool<Sqlite>, bond: &Bond) -> u64 {
-            sqlx::query("UPDATE bonds SET state = ?, locked_at = ? WHERE id = ? AND state = ?")
+        async fn try_lock(pool: &DbPool, bond: &Bond) -> u64 {
+            sqlx::query("UPDATE bonds SET state = $1, locked_at = $2 WHERE id = $3 AND state = $4")
                 .bind(BondState::Locked.to_string())
                 .bind(Utc::now().timestamp())
                 .bind(bond.id)
@@ -1648,7 +1619,7 @@ mod tests {
         other.hash = Some("0".repeat(64));
         let other = create_bond(&pool, other).await.unwrap();
 
-        sqlx::query("UPDATE bonds SET state = ?, locked_at = ? WHERE id = ? AND state = ?")
+        sqlx::query("UPDATE bonds SET state = $1, locked_at = $2 WHERE id = $3 AND state = $4")
             .bind(BondState::Locked.to_string())
             .bind(Utc::now().timestamp())
             .bind(maker.id)
@@ -1765,7 +1736,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -1787,7 +1758,7 @@ mod tests {
             "order must drop to Pending: the Locked maker bond must not count as an active taker bond"
         );
 
-        let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = ?")
+        let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
             .bind(order_id)
             .fetch_one(&pool)
             .await
@@ -1804,7 +1775,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -1821,7 +1792,7 @@ mod tests {
             .unwrap();
         assert!(!dropped, "an active taker bond must keep the order parked");
 
-        let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = ?")
+        let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
             .bind(order_id)
             .fetch_one(&pool)
             .await
@@ -1863,7 +1834,7 @@ mod tests {
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
         // Mark order as WaitingTakerBond.
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -1910,7 +1881,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -1949,7 +1920,7 @@ mod tests {
 
         // Simulate a concurrent transition (e.g. `on_bond_invoice_accepted`
         // racing past us): the order is no longer `Pending`.
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingPayment.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -1957,7 +1928,7 @@ mod tests {
             .unwrap();
 
         // The CAS that `request_taker_bond` issues:
-        let result = sqlx::query("UPDATE orders SET status = ? WHERE id = ? AND status = ?")
+        let result = sqlx::query("UPDATE orders SET status = $1 WHERE id = $2 AND status = $3")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .bind(Status::Pending.to_string())
@@ -1987,7 +1958,7 @@ mod tests {
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await; // inserted as 'pending'
 
-        let result = sqlx::query("UPDATE orders SET status = ? WHERE id = ? AND status = ?")
+        let result = sqlx::query("UPDATE orders SET status = $1 WHERE id = $2 AND status = $3")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .bind(Status::Pending.to_string())
@@ -2089,6 +2060,7 @@ mod tests {
                     "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
                 ),
                 relays: vec![],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: Default::default(),
             lightning: Default::default(),
@@ -2096,22 +2068,22 @@ mod tests {
             expiration: Some(Default::default()),
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         });
     }
 
-    async fn load_order(pool: &Pool<Sqlite>, id: Uuid) -> Order {
+    async fn load_order(pool: &DbPool, id: Uuid) -> Order {
         Order::by_id(pool, id).await.unwrap().unwrap()
     }
 
-    /// Count queued messages for `order_id` with `action` in the global
-    /// message queue. Each test uses a fresh order id, so the count is
+    /// Count queued messages for `order_id` with `action` in the shared
+    /// outbox. Each test uses a fresh order id, so the count is
     /// deterministic under parallel tests.
     async fn count_msgs(order_id: Uuid, action: Action) -> usize {
-        use crate::config::MESSAGE_QUEUES;
-        MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(m, _)| {
@@ -2269,7 +2241,8 @@ mod tests {
     #[tokio::test]
     async fn bond_invoice_subscribe_errors_when_lnd_unreachable() {
         init_test_settings();
-        let result = bond_invoice_subscribe(vec![0u8; 32], None).await;
+        let pool = setup_pool().await;
+        let result = bond_invoice_subscribe(&pool, vec![0u8; 32], None).await;
         assert!(result.is_err(), "no LND in the unit-test harness");
     }
 
@@ -2449,7 +2422,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = 'canceled' WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = 'canceled' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2483,11 +2456,8 @@ mod tests {
         w.hash = Some("a".repeat(64));
         create_bond(&pool, w).await.unwrap();
 
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query("DELETE FROM orders WHERE id = ?")
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        sqlx::query("DELETE FROM orders WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2590,7 +2560,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingMakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -2651,7 +2621,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -2715,7 +2685,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET kind = 'sell' WHERE id = ?")
+        sqlx::query("UPDATE orders SET kind = 'sell' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2749,7 +2719,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = 'canceled' WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = 'canceled' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2784,7 +2754,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = 'canceled' WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = 'canceled' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2858,7 +2828,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET kind = 'sell' WHERE id = ?")
+        sqlx::query("UPDATE orders SET kind = 'sell' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2881,7 +2851,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET kind = 'sell' WHERE id = ?")
+        sqlx::query("UPDATE orders SET kind = 'sell' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -2911,14 +2881,8 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
 
         release_bonds_for_order_or_warn(&pool, order_id, "unit_test").await;
     }
@@ -2930,14 +2894,8 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
 
         release_taker_bonds_for_order_or_warn(&pool, order_id, "unit_test").await;
     }
@@ -2954,14 +2912,14 @@ mod tests {
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
 
-        sqlx::query(
-            "CREATE TRIGGER vanish_after_lock AFTER UPDATE OF state ON bonds \
-             WHEN NEW.state = 'locked' \
-             BEGIN DELETE FROM bonds WHERE id = NEW.id; END",
+        crate::db::test_utils::create_trigger(
+            &pool,
+            "vanish_after_lock",
+            "AFTER UPDATE OF state ON bonds",
+            Some("NEW.state = 'locked'"),
+            "DELETE FROM bonds WHERE id = NEW.id",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let mut b = make_bond(order_id, BondState::Requested);
         b.hash = Some("a".repeat(64));
@@ -2972,6 +2930,9 @@ mod tests {
             .expect("bond vanishing after the lock CAS must no-op, not error");
     }
 
+    // Relies on SQLite's type affinity to plant an undecodable row;
+    // PostgreSQL rejects the insert itself.
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn accepted_callback_warns_when_enumerating_losers_fails() {
         // After winning the lock, the callback enumerates every other
@@ -2996,7 +2957,7 @@ mod tests {
 
         sqlx::query(
             "INSERT INTO bonds (id, order_id, pubkey, role, amount_sats, state, created_at) \
-             VALUES (?, ?, ?, 'taker', 'not-a-number', 'requested', ?)",
+             VALUES ($1, $2, $3, 'taker', 'not-a-number', 'requested', $4)",
         )
         .bind(Uuid::new_v4())
         .bind(order_id)
@@ -3076,14 +3037,14 @@ mod tests {
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
 
-        sqlx::query(
-            "CREATE TRIGGER flip_after_lock AFTER UPDATE OF state ON bonds \
-             WHEN NEW.state = 'locked' \
-             BEGIN UPDATE bonds SET state = 'released' WHERE id = NEW.id AND state = 'locked'; END",
+        crate::db::test_utils::create_trigger(
+            &pool,
+            "flip_after_lock",
+            "AFTER UPDATE OF state ON bonds",
+            Some("NEW.state = 'locked'"),
+            "UPDATE bonds SET state = 'released' WHERE id = NEW.id AND state = 'locked'",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let mut b = make_bond(order_id, BondState::Requested);
         b.hash = Some("a".repeat(64));
@@ -3114,14 +3075,14 @@ mod tests {
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
 
-        sqlx::query(
-            "CREATE TRIGGER vanish_maker_after_lock AFTER UPDATE OF state ON bonds \
-             WHEN NEW.state = 'locked' \
-             BEGIN DELETE FROM bonds WHERE id = NEW.id; END",
+        crate::db::test_utils::create_trigger(
+            &pool,
+            "vanish_maker_after_lock",
+            "AFTER UPDATE OF state ON bonds",
+            Some("NEW.state = 'locked'"),
+            "DELETE FROM bonds WHERE id = NEW.id",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let mut maker = Bond::new_requested(order_id, "m".repeat(64), BondRole::Maker, 1_000);
         maker.hash = Some("e".repeat(64));
@@ -3147,11 +3108,8 @@ mod tests {
         maker.hash = Some("e".repeat(64));
         create_bond(&pool, maker).await.unwrap();
 
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query("DELETE FROM orders WHERE id = ?")
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        sqlx::query("DELETE FROM orders WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -3173,7 +3131,7 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
@@ -3184,14 +3142,8 @@ mod tests {
         b.hash = Some("a".repeat(64));
         create_bond(&pool, b).await.unwrap();
 
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        crate::db::test_utils::drop_table(&pool, "orders").await;
 
         on_bond_invoice_canceled(&"a".repeat(64), &pool)
             .await
@@ -3218,21 +3170,21 @@ mod tests {
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id).await;
-        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::WaitingTakerBond.to_string())
             .bind(order_id)
             .execute(&pool)
             .await
             .unwrap();
 
-        sqlx::query(
-            "CREATE TRIGGER vanish_after_pending AFTER UPDATE OF status ON orders \
-             WHEN NEW.status = 'pending' \
-             BEGIN DELETE FROM orders WHERE id = NEW.id; END",
+        crate::db::test_utils::create_trigger(
+            &pool,
+            "vanish_after_pending",
+            "AFTER UPDATE OF status ON orders",
+            Some("NEW.status = 'pending'"),
+            "DELETE FROM orders WHERE id = NEW.id",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         maybe_drop_waiting_taker_bond(&pool, order_id)
             .await
@@ -3262,7 +3214,7 @@ mod tests {
         insert_order(&pool, order_id).await; // kind 'buy'
         let same_pubkey = Keys::generate().public_key().to_string();
         sqlx::query(
-            "UPDATE orders SET status = ?, buyer_pubkey = ?, master_buyer_pubkey = ? WHERE id = ?",
+            "UPDATE orders SET status = $1, buyer_pubkey = $2, master_buyer_pubkey = $3 WHERE id = $4",
         )
         .bind(Status::WaitingTakerBond.to_string())
         .bind(&same_pubkey)
@@ -149,7 +149,7 @@ pub struct Bond {
 
 impl Bond {
     /// Construct a new `Requested` bond row. The caller is responsible for
-    /// inserting it via [`mostro_core::db::Crud::create`].
+    /// inserting it via [`crate::db::Crud::create`].
     pub fn new_requested(order_id: Uuid, pubkey: String, role: BondRole, amount_sats: i64) -> Self {
         Self {
             id: Uuid::new_v4(),
@@ -51,9 +51,10 @@
 use std::str::FromStr;
 use std::time::Duration;
 
+use crate::db::Crud;
+use crate::db::DbPool;
 use chrono::Utc;
 use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
-use mostro_core::db::Crud;
 use mostro_core::error::{
     CantDoReason,
     MostroError::{self, MostroCantDo, MostroInternalErr},
@@ -63,7 +64,6 @@ use mostro_core::message::{Action, BondPayoutRequest, Message, Payload};
 use mostro_core::nip59::UnwrappedMessage;
 use mostro_core::order::{Order, SmallOrder};
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 use tokio::sync::mpsc::channel;
 use tokio::time::timeout;
 use tracing::{error, info, warn};
@@ -71,13 +71,14 @@ use uuid::Uuid;
 
 use crate::app::context::AppContext;
 use crate::config::settings::Settings;
+use crate::escrow::EscrowBackend;
 use crate::lightning::invoice::{decode_invoice, is_valid_invoice};
-use crate::lightning::{routing_fee_cap_sats, LndConnector};
+use crate::lightning::routing_fee_cap_sats;
 use crate::util::{bytes_to_string, enqueue_order_msg};
 
 use super::db::{find_bond_by_id, find_bonds_by_state};
 use super::model::Bond;
-use super::types::{BondSlashReason, BondState};
+use super::types::{BondRole, BondSlashReason, BondState};
 
 /// One full pass over every bond in [`BondState::PendingPayout`].
 ///
@@ -87,7 +88,7 @@ use super::types::{BondSlashReason, BondState};
 /// — but every state transition is still done with a guarded CAS so a
 /// concurrent admin retry or a daemon restart that re-fires the same
 /// tick cannot double-settle a row.
-pub async fn run_bond_payout_cycle(pool: &Pool<Sqlite>, ln_client: &mut LndConnector) {
+pub async fn run_bond_payout_cycle(pool: &DbPool, ln_client: &mut dyn EscrowBackend) {
     let bonds = match find_bonds_by_state(pool, BondState::PendingPayout).await {
         Ok(b) => b,
         Err(e) => {
@@ -158,7 +159,7 @@ pub async fn run_bond_payout_cycle(pool: &Pool<Sqlite>, ln_client: &mut LndConne
 /// never happen) is treated as "skip" defensively. Non-child rows
 /// (`parent_bond_id IS NULL`) are never blocked.
 async fn child_payout_blocked_by_locked_parent(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: &Bond,
 ) -> Result<bool, MostroError> {
     let Some(parent_id) = bond.parent_bond_id else {
@@ -178,8 +179,8 @@ async fn child_payout_blocked_by_locked_parent(
 }
 
 async fn process_one_bond(
-    pool: &Pool<Sqlite>,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     bond: &Bond,
 ) -> Result<(), MostroError> {
     // Phase 6 — a child payout row (slice slash or maker refund) must not
@@ -287,9 +288,9 @@ fn counterparty_share_sats(bond: &Bond) -> Result<i64, MostroError> {
 /// snapshot and the UPDATE below, we must not flip the row to
 /// `Forfeited` and silently discard their bolt11 — on the next tick
 /// `pay_counterparty` will take over and route the funds.
-async fn forfeit_bond(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), MostroError> {
+async fn forfeit_bond(pool: &DbPool, bond: &Bond) -> Result<(), MostroError> {
     let result = sqlx::query(
-        "UPDATE bonds SET state = ? WHERE id = ? AND state = ? AND payout_invoice IS NULL",
+        "UPDATE bonds SET state = $1 WHERE id = $2 AND state = $3 AND payout_invoice IS NULL",
     )
     .bind(BondState::Forfeited.to_string())
     .bind(bond.id)
@@ -299,6 +300,11 @@ async fn forfeit_bond(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), MostroErro
     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
 
     if result.rows_affected() == 1 {
+        crate::rpc::events::bond_transitioned(
+            bond.id,
+            bond.order_id,
+            &BondState::Forfeited.to_string(),
+        );
         info!(
             bond_id = %bond.id,
             order_id = %bond.order_id,
@@ -319,8 +325,8 @@ async fn forfeit_bond(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), MostroErro
 /// HTLC was already settled at slash time, so there is nothing left
 /// for the scheduler to do beyond flipping the row to `Slashed`. No
 /// messages, no `send_payment`.
-async fn finalize_node_only(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), MostroError> {
-    let result = sqlx::query("UPDATE bonds SET state = ? WHERE id = ? AND state = ?")
+async fn finalize_node_only(pool: &DbPool, bond: &Bond) -> Result<(), MostroError> {
+    let result = sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2 AND state = $3")
         .bind(BondState::Slashed.to_string())
         .bind(bond.id)
         .bind(BondState::PendingPayout.to_string())
@@ -329,6 +335,11 @@ async fn finalize_node_only(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), Most
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
 
     if result.rows_affected() == 1 {
+        crate::rpc::events::bond_transitioned(
+            bond.id,
+            bond.order_id,
+            &BondState::Slashed.to_string(),
+        );
         info!(
             bond_id = %bond.id,
             order_id = %bond.order_id,
@@ -346,7 +357,7 @@ async fn finalize_node_only(pool: &Pool<Sqlite>, bond: &Bond) -> Result<(), Most
 /// retry budget (`payout_max_retries`) is for `send_payment` failures
 /// once an invoice is in hand, not for invoice-request messages.
 async fn request_payout_invoice(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: &Bond,
     invoice_window_seconds: i64,
 ) -> Result<(), MostroError> {
@@ -431,8 +442,8 @@ async fn request_payout_invoice(
     let result = sqlx::query(
         "UPDATE bonds \
            SET invoice_request_attempts = invoice_request_attempts + 1, \
-               last_invoice_request_at = ? \
-         WHERE id = ? AND state = ?",
+               last_invoice_request_at = $1 \
+         WHERE id = $2 AND state = $3",
     )
     .bind(now)
     .bind(bond.id)
@@ -526,8 +537,8 @@ const SLASH_CAS_MAX_ATTEMPTS: u32 = 5;
 ///    all fail, the persisted hash means the next tick will reconcile
 ///    cleanly; we surface the error loudly so operators see it.
 async fn pay_counterparty(
-    pool: &Pool<Sqlite>,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     bond: &Bond,
     invoice: &str,
     max_retries: i64,
@@ -628,8 +639,8 @@ async fn pay_counterparty(
     let routing_fee_cap = routing_fee_cap_sats(counterparty_share);
     let persisted = sqlx::query(
         "UPDATE bonds \
-           SET payout_routing_fee_sats = ?, payout_payment_hash = ? \
-         WHERE id = ? AND state = ?",
+           SET payout_routing_fee_sats = $1, payout_payment_hash = $2 \
+         WHERE id = $3 AND state = $4",
     )
     .bind(routing_fee_cap)
     .bind(&payment_hash_hex)
@@ -809,7 +820,7 @@ fn classify_send_verdict(
 /// scheduler tick will reconcile via LND and re-attempt this CAS rather
 /// than re-issuing `send_payment`.
 async fn slash_after_success(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: &Bond,
     counterparty_share: i64,
 ) -> Result<(), MostroError> {
@@ -822,7 +833,7 @@ async fn slash_after_success(
             let backoff_ms = 50u64 << (attempt - 1);
             tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
         }
-        match sqlx::query("UPDATE bonds SET state = ? WHERE id = ? AND state = ?")
+        match sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2 AND state = $3")
             .bind(BondState::Slashed.to_string())
             .bind(bond.id)
             .bind(BondState::PendingPayout.to_string())
@@ -831,6 +842,11 @@ async fn slash_after_success(
         {
             Ok(result) => {
                 if result.rows_affected() == 1 {
+                    crate::rpc::events::bond_transitioned(
+                        bond.id,
+                        bond.order_id,
+                        &BondState::Slashed.to_string(),
+                    );
                     info!(
                         bond_id = %bond.id,
                         order_id = %bond.order_id,
@@ -936,7 +952,7 @@ enum PaymentFailureKind {
 ///
 /// [issue #750]: https://github.com/MostroP2P/mostro/issues/750
 async fn on_send_payment_failure(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: &Bond,
     max_retries: i64,
     claim_window_seconds: i64,
@@ -947,7 +963,7 @@ async fn on_send_payment_failure(
     // the invoice (see below), so without a cap a long LND outage could
     // grow the counter without bound.
     let new_attempts = std::cmp::min(bond.payout_attempts + 1, max_retries);
-    sqlx::query("UPDATE bonds SET payout_attempts = ? WHERE id = ? AND state = ?")
+    sqlx::query("UPDATE bonds SET payout_attempts = $1 WHERE id = $2 AND state = $3")
         .bind(new_attempts)
         .bind(bond.id)
         .bind(BondState::PendingPayout.to_string())
@@ -1018,7 +1034,7 @@ async fn on_send_payment_failure(
                    payout_payment_hash = NULL, \
                    payout_attempts = 0, \
                    last_invoice_request_at = NULL \
-             WHERE id = ? AND state = ?",
+             WHERE id = $1 AND state = $2",
         )
         .bind(bond.id)
         .bind(BondState::PendingPayout.to_string())
@@ -1047,13 +1063,20 @@ async fn on_send_payment_failure(
         return Ok(());
     }
 
-    sqlx::query("UPDATE bonds SET state = ? WHERE id = ? AND state = ?")
+    let result = sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2 AND state = $3")
         .bind(BondState::Failed.to_string())
         .bind(bond.id)
         .bind(BondState::PendingPayout.to_string())
         .execute(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    if result.rows_affected() == 1 {
+        crate::rpc::events::bond_transitioned(
+            bond.id,
+            bond.order_id,
+            &BondState::Failed.to_string(),
+        );
+    }
     error!(
         bond_id = %bond.id,
         order_id = %bond.order_id,
@@ -1075,17 +1098,23 @@ async fn on_send_payment_failure(
 /// - **`Timeout` (Phase 4+).** Same logic — the slashed party is the
 ///   one responsible for the elapsed waiting state, and the recipient
 ///   is the other side. The §9.2 table is encoded by *who got
-///   slashed*, not consulted here.
+///   slashed*, not consulted here. A timed-out taker is no longer on
+///   the order by payout time (the timeout republished it, and a new
+///   taker may hold the slot), so the recipient is the maker who
+///   created it.
 fn resolve_recipient(
     order: &Order,
     bond: &Bond,
-    _reason: BondSlashReason,
+    reason: BondSlashReason,
 ) -> Result<Option<PublicKey>, MostroError> {
     let buyer = order.buyer_pubkey.as_deref();
     let seller = order.seller_pubkey.as_deref();
     let recipient_str = match (buyer, seller) {
         (Some(b), Some(s)) if bond.pubkey == b => Some(s),
         (Some(b), Some(s)) if bond.pubkey == s => Some(b),
+        _ if reason == BondSlashReason::Timeout && bond.role == BondRole::Taker.to_string() => {
+            Some(order.creator_pubkey.as_str())
+        }
         _ => None,
     };
     let pk = recipient_str
@@ -1189,7 +1218,7 @@ async fn enqueue_payout_ack(
 /// submitter (`recipient`) right after the invoice is persisted, so the
 /// client stops prompting the user for another invoice. Best-effort.
 async fn notify_invoice_received(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: &Bond,
     recipient: PublicKey,
     counterparty_share: i64,
@@ -1224,7 +1253,7 @@ async fn notify_invoice_received(
 /// Best-effort. **Not** sent on the node-only
 /// (`slash_node_share_pct = 1.0`) path — there is no counterparty leg
 /// and the winner was never asked for an invoice.
-async fn notify_payout_completed(pool: &Pool<Sqlite>, bond: &Bond, counterparty_share: i64) {
+async fn notify_payout_completed(pool: &DbPool, bond: &Bond, counterparty_share: i64) {
     let order = match Order::by_id(pool, bond.order_id).await {
         Ok(Some(o)) => o,
         Ok(None) => {
@@ -1454,7 +1483,7 @@ pub async fn add_bond_invoice_action(
 ///   *before* the CAS; a tiny window-edge race is benign because the
 ///   CAS itself does not gate on time.
 async fn apply_payout_invoice(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond: &Bond,
     invoice: &str,
     now: i64,
@@ -1469,8 +1498,8 @@ async fn apply_payout_invoice(
         BondState::PendingPayout => {
             let result = sqlx::query(
                 "UPDATE bonds \
-                   SET payout_invoice = ?, invoice_request_attempts = 0 \
-                 WHERE id = ? AND state = ? AND payout_invoice IS NULL",
+                   SET payout_invoice = $1, invoice_request_attempts = 0 \
+                 WHERE id = $2 AND state = $3 AND payout_invoice IS NULL",
             )
             .bind(invoice)
             .bind(bond.id)
@@ -1526,12 +1555,12 @@ async fn apply_payout_invoice(
             // invoice.
             let result = sqlx::query(
                 "UPDATE bonds \
-                   SET state = ?, \
-                       payout_invoice = ?, \
+                   SET state = $1, \
+                       payout_invoice = $2, \
                        payout_attempts = 0, \
                        invoice_request_attempts = 0, \
                        payout_payment_hash = NULL \
-                 WHERE id = ? AND state = ?",
+                 WHERE id = $3 AND state = $4",
             )
             .bind(BondState::PendingPayout.to_string())
             .bind(invoice)
@@ -1544,6 +1573,11 @@ async fn apply_payout_invoice(
             if result.rows_affected() == 0 {
                 Ok(InvoiceApplyOutcome::Rejected)
             } else {
+                crate::rpc::events::bond_transitioned(
+                    bond.id,
+                    bond.order_id,
+                    &BondState::PendingPayout.to_string(),
+                );
                 Ok(InvoiceApplyOutcome::Resurrected)
             }
         }
@@ -1574,14 +1608,14 @@ async fn apply_payout_invoice(
 /// recipient via [`resolve_recipient`], and confirm it matches the
 /// sender of the AddBondInvoice reply.
 async fn find_recoverable_bond_for_recipient(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order_id: Uuid,
     sender_pubkey: &str,
     expected_share_sats: Option<i64>,
 ) -> Result<Option<Bond>, MostroError> {
     let bonds: Vec<Bond> = sqlx::query_as::<_, Bond>(
         "SELECT * FROM bonds \
-          WHERE order_id = ? AND (state = ? OR state = ?) \
+          WHERE order_id = $1 AND (state = $2 OR state = $3) \
           ORDER BY slashed_at DESC",
     )
     .bind(order_id)
@@ -1647,56 +1681,11 @@ mod tests {
     use super::*;
     use crate::app::bond::db::create_bond;
     use crate::app::bond::types::BondRole;
+    use crate::lightning::LndConnector;
     use mostro_core::order::{Kind, Status};
-    use sqlx::sqlite::SqlitePoolOptions;
 
-    async fn setup_pool() -> Pool<Sqlite> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .expect("open in-memory sqlite");
-        sqlx::query(include_str!(
-            "../../../migrations/20221222153301_orders.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("orders migration");
-        for stmt in include_str!("../../../migrations/20251126120000_dev_fee.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt)
-                .execute(&pool)
-                .await
-                .expect("dev_fee migration");
-        }
-        sqlx::query(include_str!(
-            "../../../migrations/20260423120000_anti_abuse_bond.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bonds migration");
-        sqlx::query(include_str!(
-            "../../../migrations/20260518120000_bond_payout_payment_hash.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bond_payout_payment_hash migration");
-        // cashu escrow columns (mostro-core 0.12.1) — `Order::by_id` SELECTs
-        // them. Apply each ALTER separately for the same reason as dev_fee.
-        for stmt in include_str!("../../../migrations/20260530120000_cashu_escrow_fields.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt)
-                .execute(&pool)
-                .await
-                .expect("cashu escrow migration");
-        }
-        pool
+    async fn setup_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn taker_pk() -> &'static str {
@@ -1706,13 +1695,13 @@ mod tests {
         "2222222222222222222222222222222222222222222222222222222222222222"
     }
 
-    async fn insert_order(pool: &Pool<Sqlite>, id: Uuid, seller: &str, buyer: &str) {
+    async fn insert_order(pool: &DbPool, id: Uuid, seller: &str, buyer: &str) {
         sqlx::query(
             r#"INSERT INTO orders (
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, created_at, expires_at,
                 seller_pubkey, buyer_pubkey
-            ) VALUES (?, 'sell', ?, ?, 0, 'cash', 100000, 'USD', 10, 0, 0, ?, ?)"#,
+            ) VALUES ($1, 'sell', $2, $3, 0, 'cash', 100000, 'USD', 10, 0, 0, $4, $5)"#,
         )
         .bind(id)
         .bind(id.simple().to_string())
@@ -1784,6 +1773,29 @@ mod tests {
         assert!(r.is_none());
     }
 
+    #[test]
+    fn resolve_recipient_timed_out_taker_pays_the_maker_after_republish() {
+        // The waiting timeout republished the order: the slashed taker is
+        // gone and a new taker may already hold the slot.
+        let order = Order {
+            kind: Kind::Sell.to_string(),
+            creator_pubkey: maker_pk().to_string(),
+            seller_pubkey: Some(maker_pk().to_string()),
+            buyer_pubkey: None,
+            ..Order::default()
+        };
+        let bond = pending_payout_bond(Uuid::new_v4(), taker_pk(), 10_000, 5_000, 0, None, None);
+        let r = resolve_recipient(&order, &bond, BondSlashReason::Timeout).unwrap();
+        assert_eq!(r.unwrap().to_string(), maker_pk());
+
+        let retaken = Order {
+            buyer_pubkey: Some(Keys::generate().public_key().to_string()),
+            ..order
+        };
+        let r = resolve_recipient(&retaken, &bond, BondSlashReason::Timeout).unwrap();
+        assert_eq!(r.unwrap().to_string(), maker_pk());
+    }
+
     #[test]
     fn resolve_payout_recipient_refund_row_pays_the_maker() {
         // Phase 6 maker-refund row: `parent_bond_id` set, `child_order_id`
@@ -1850,7 +1862,7 @@ mod tests {
             .unwrap());
 
         // Settle the parent (range close) → child is unblocked.
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::Slashed.to_string())
             .bind(parent.id)
             .execute(&pool)
@@ -1890,7 +1902,7 @@ mod tests {
         request_payout_invoice(&pool, &bond, 300).await.unwrap();
 
         let row: (i64, Option<i64>) = sqlx::query_as(
-            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = ?",
+            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = $1",
         )
         .bind(bond.id)
         .fetch_one(&pool)
@@ -1904,14 +1916,11 @@ mod tests {
 
     /// Count queued `Action::AddBondInvoice` messages targeting
     /// `order_id`. Used to verify enqueue ordering against the
-    /// global `MESSAGE_QUEUES` without conflicting with concurrent
+    /// shared outbox without conflicting with concurrent
     /// tests — each test's `order_id` is a fresh `Uuid::new_v4()`
     /// so filtering by it makes the count deterministic.
     async fn count_add_bond_invoice_msgs(order_id: Uuid) -> usize {
-        use crate::config::MESSAGE_QUEUES;
-        MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(m, _)| {
@@ -1941,7 +1950,7 @@ mod tests {
 
         // Durable state advanced.
         let row: (i64, Option<i64>) = sqlx::query_as(
-            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = ?",
+            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = $1",
         )
         .bind(bond.id)
         .fetch_one(&pool)
@@ -1982,7 +1991,7 @@ mod tests {
         let after = count_add_bond_invoice_msgs(order_id).await;
 
         let row: (i64, Option<i64>) = sqlx::query_as(
-            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = ?",
+            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = $1",
         )
         .bind(bond.id)
         .fetch_one(&pool)
@@ -2014,7 +2023,7 @@ mod tests {
         // Simulate the row having moved on under us: flip it to
         // Forfeited in the DB *after* the scheduler's snapshot
         // captured it as PendingPayout.
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::Forfeited.to_string())
             .bind(bond.id)
             .execute(&pool)
@@ -2027,7 +2036,7 @@ mod tests {
 
         // Durable state unchanged (CAS rejected by state predicate).
         let row: (i64, Option<i64>) = sqlx::query_as(
-            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = ?",
+            "SELECT invoice_request_attempts, last_invoice_request_at FROM bonds WHERE id = $1",
         )
         .bind(bond.id)
         .fetch_one(&pool)
@@ -2056,7 +2065,7 @@ mod tests {
 
         forfeit_bond(&pool, &bond).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2088,7 +2097,7 @@ mod tests {
 
         forfeit_bond(&pool, &bond).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2130,7 +2139,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let bond_after_1: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let bond_after_1: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2150,7 +2159,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let bond_after_2: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let bond_after_2: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2167,7 +2176,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let bond_after_3: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let bond_after_3: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2218,7 +2227,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let after_1: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after_1: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2237,7 +2246,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let after_2: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after_2: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2255,7 +2264,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let after_3: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after_3: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2315,7 +2324,7 @@ mod tests {
             )
             .await
             .unwrap();
-            current = sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE id = ?")
+            current = sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE id = $1")
                 .bind(current.id)
                 .fetch_one(&pool)
                 .await
@@ -2340,7 +2349,7 @@ mod tests {
         )
         .await
         .unwrap();
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(current.id)
             .fetch_one(&pool)
             .await
@@ -2460,7 +2469,7 @@ mod tests {
 
         finalize_node_only(&pool, &bond).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2488,7 +2497,7 @@ mod tests {
             None,
         );
         let bond = create_bond(&pool, bond).await.unwrap();
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::Forfeited.to_string())
             .bind(bond.id)
             .execute(&pool)
@@ -2497,7 +2506,7 @@ mod tests {
 
         finalize_node_only(&pool, &bond).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2531,7 +2540,7 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Persisted);
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2567,7 +2576,7 @@ mod tests {
         assert_eq!(outcome, InvoiceApplyOutcome::Rejected);
 
         let after: (String, Option<String>) =
-            sqlx::query_as("SELECT state, payout_invoice FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, payout_invoice FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2607,7 +2616,7 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Resurrected);
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2649,12 +2658,13 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Rejected);
 
-        let after: (String, Option<String>, i64) =
-            sqlx::query_as("SELECT state, payout_invoice, payout_attempts FROM bonds WHERE id = ?")
-                .bind(bond.id)
-                .fetch_one(&pool)
-                .await
-                .unwrap();
+        let after: (String, Option<String>, i64) = sqlx::query_as(
+            "SELECT state, payout_invoice, payout_attempts FROM bonds WHERE id = $1",
+        )
+        .bind(bond.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
         assert_eq!(after.0, BondState::Failed.to_string());
         assert_eq!(after.1.as_deref(), Some("lnbc1pBAD"));
         assert_eq!(after.2, 5);
@@ -2757,7 +2767,7 @@ mod tests {
         assert_eq!(outcome_b, InvoiceApplyOutcome::Rejected);
 
         let after: (String, Option<String>) =
-            sqlx::query_as("SELECT state, payout_invoice FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, payout_invoice FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2796,7 +2806,7 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Resurrected);
 
-        let bond_after_b: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let bond_after_b: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2823,7 +2833,7 @@ mod tests {
             )
             .await
             .unwrap();
-            current = sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE id = ?")
+            current = sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2837,7 +2847,7 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Resurrected);
 
-        let final_bond: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let final_bond: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2883,7 +2893,7 @@ mod tests {
                     .unwrap();
             assert_eq!(outcome, InvoiceApplyOutcome::Rejected, "state {state}");
 
-            let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+            let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2945,7 +2955,7 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Resurrected);
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2977,7 +2987,7 @@ mod tests {
             .unwrap();
         assert_eq!(outcome, InvoiceApplyOutcome::Persisted);
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3004,7 +3014,7 @@ mod tests {
 
         slash_after_success(&pool, &bond, 5_000).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3040,7 +3050,7 @@ mod tests {
         snapshot.state = BondState::PendingPayout.to_string();
         slash_after_success(&pool, &snapshot, 5_000).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3050,12 +3060,9 @@ mod tests {
 
     /// Recipient pubkeys (hex) of queued messages matching `order_id` +
     /// `action`. Lets a test assert both the count and the destination
-    /// of Phase 3.5 acks against the global `MESSAGE_QUEUES`.
+    /// of Phase 3.5 acks against the shared outbox.
     async fn ack_recipients(order_id: Uuid, action: Action) -> Vec<String> {
-        use crate::config::MESSAGE_QUEUES;
-        MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(m, _)| {
@@ -3136,7 +3143,7 @@ mod tests {
         slash_after_success(&pool, &bond, 5_000).await.unwrap();
         let after = ack_recipients(order_id, Action::BondPayoutCompleted).await;
 
-        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3165,7 +3172,7 @@ mod tests {
         finalize_node_only(&pool, &bond).await.unwrap();
         let after = ack_recipients(order_id, Action::BondPayoutCompleted).await;
 
-        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3190,6 +3197,7 @@ mod tests {
                     "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
                 ),
                 relays: vec![],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: Default::default(),
             lightning: Default::default(),
@@ -3197,7 +3205,10 @@ mod tests {
             expiration: Some(Default::default()),
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         });
         let _ = &MOSTRO_CONFIG;
     }
@@ -3292,10 +3303,7 @@ mod tests {
     async fn run_cycle_survives_enumeration_failure() {
         init_test_settings();
         let pool = setup_pool().await;
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
         let mut ln = dead_lnd().await;
         // Must log-and-return, not panic or propagate.
         run_bond_payout_cycle(&pool, &mut ln).await;
@@ -3331,13 +3339,13 @@ mod tests {
         let mut ln = dead_lnd().await;
         run_bond_payout_cycle(&pool, &mut ln).await;
 
-        let broken_after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let broken_after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(broken.id)
             .fetch_one(&pool)
             .await
             .unwrap();
         assert_eq!(broken_after.0, BondState::PendingPayout.to_string());
-        let node_after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let node_after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(node_only.id)
             .fetch_one(&pool)
             .await
@@ -3365,7 +3373,7 @@ mod tests {
         let mut ln = dead_lnd().await;
         process_one_bond(&pool, &mut ln, &child).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(child.id)
             .fetch_one(&pool)
             .await
@@ -3386,7 +3394,7 @@ mod tests {
         let mut ln = dead_lnd().await;
         process_one_bond(&pool, &mut ln, &bond).await.unwrap();
 
-        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let after: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3445,7 +3453,7 @@ mod tests {
         let mut ln = dead_lnd().await;
         process_one_bond(&pool, &mut ln, &bond).await.unwrap();
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3455,26 +3463,55 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn request_invoice_skips_when_order_row_missing() {
+    async fn process_one_bond_slashes_after_the_counterparty_is_paid() {
         init_test_settings();
         let pool = setup_pool().await;
         let order_id = Uuid::new_v4();
         insert_order(&pool, order_id, maker_pk(), taker_pk()).await;
+        let invoice = signed_test_invoice(5_000);
         let bond = pending_payout_bond(
             order_id,
             taker_pk(),
             10_000,
             5_000,
             Utc::now().timestamp(),
-            None,
+            Some(&invoice),
             None,
         );
         let bond = create_bond(&pool, bond).await.unwrap();
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
+
+        let mut escrow = crate::escrow::memory::MemoryEscrow::new();
+        process_one_bond(&pool, &mut escrow, &bond).await.unwrap();
+
+        let payment = escrow.sent_payments().pop().expect("payout sent");
+        assert_eq!(payment.payment_request, invoice);
+        assert_eq!(payment.amount, 5_000);
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
+            .bind(bond.id)
+            .fetch_one(&pool)
             .await
             .unwrap();
-        sqlx::query("DELETE FROM orders WHERE id = ?")
+        assert_eq!(after.state, BondState::Slashed.to_string());
+    }
+
+    #[tokio::test]
+    async fn request_invoice_skips_when_order_row_missing() {
+        init_test_settings();
+        let pool = setup_pool().await;
+        let order_id = Uuid::new_v4();
+        insert_order(&pool, order_id, maker_pk(), taker_pk()).await;
+        let bond = pending_payout_bond(
+            order_id,
+            taker_pk(),
+            10_000,
+            5_000,
+            Utc::now().timestamp(),
+            None,
+            None,
+        );
+        let bond = create_bond(&pool, bond).await.unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        sqlx::query("DELETE FROM orders WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -3482,11 +3519,12 @@ mod tests {
 
         request_payout_invoice(&pool, &bond, 300).await.unwrap();
         // No cadence bump: the helper bailed before the CAS.
-        let row: (i64,) = sqlx::query_as("SELECT invoice_request_attempts FROM bonds WHERE id = ?")
-            .bind(bond.id)
-            .fetch_one(&pool)
-            .await
-            .unwrap();
+        let row: (i64,) =
+            sqlx::query_as("SELECT invoice_request_attempts FROM bonds WHERE id = $1")
+                .bind(bond.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
         assert_eq!(row.0, 0);
     }
 
@@ -3523,7 +3561,7 @@ mod tests {
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, created_at, expires_at,
                 seller_pubkey
-            ) VALUES (?, 'sell', ?, 'dispute', 0, 'cash', 100000, 'USD', 10, 0, 0, ?)"#,
+            ) VALUES ($1, 'sell', $2, 'dispute', 0, 'cash', 100000, 'USD', 10, 0, 0, $3)"#,
         )
         .bind(order_id)
         .bind(order_id.simple().to_string())
@@ -3589,7 +3627,7 @@ mod tests {
         .await
         .unwrap();
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3627,7 +3665,7 @@ mod tests {
             .await
             .unwrap();
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3671,7 +3709,7 @@ mod tests {
             .await
             .unwrap();
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3699,7 +3737,7 @@ mod tests {
             None,
         );
         let bond = create_bond(&pool, bond).await.unwrap();
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::Forfeited.to_string())
             .bind(bond.id)
             .execute(&pool)
@@ -3711,7 +3749,7 @@ mod tests {
             .await
             .unwrap();
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3743,13 +3781,14 @@ mod tests {
             None,
         );
         let bond = create_bond(&pool, bond).await.unwrap();
-        sqlx::query(
-            "CREATE TRIGGER fail_bonds_update BEFORE UPDATE ON bonds \
-             BEGIN SELECT RAISE(ABORT, 'forced bonds failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_bonds_update",
+            "BEFORE UPDATE ON bonds",
+            None,
+            "forced bonds failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let result = slash_after_success(&pool, &bond, 5_000).await;
         assert!(
@@ -3779,7 +3818,7 @@ mod tests {
         bond.payout_attempts = 2;
         let bond = create_bond(&pool, bond).await.unwrap();
         // Row moves on under us.
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::Slashed.to_string())
             .bind(bond.id)
             .execute(&pool)
@@ -3800,7 +3839,7 @@ mod tests {
         .await
         .unwrap();
 
-        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let after: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -3858,10 +3897,7 @@ mod tests {
         // as an error to callers that already committed the underlying
         // state transition.
         let pool = setup_pool().await;
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "orders").await;
         let order_id = Uuid::new_v4();
         let bond = pending_payout_bond(order_id, taker_pk(), 10_000, 5_000, 0, None, None);
         let recipient = PublicKey::from_str(maker_pk()).unwrap();
@@ -3931,7 +3967,7 @@ mod tests {
     use mostro_core::message::MessageKind;
     use nostr_sdk::prelude::Timestamp;
 
-    fn build_ctx(pool: &Pool<Sqlite>) -> crate::app::context::AppContext {
+    fn build_ctx(pool: &DbPool) -> crate::app::context::AppContext {
         TestContextBuilder::new()
             .with_pool(std::sync::Arc::new(pool.clone()))
             .with_settings(test_settings())
@@ -4091,7 +4127,7 @@ mod tests {
         let after = ack_recipients(order_id, Action::BondInvoiceAccepted).await;
         assert_eq!(after.len() - before, 1);
 
-        let row: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let row: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -4134,7 +4170,7 @@ mod tests {
             .await
             .expect("resurrection within the claim window must be accepted");
 
-        let row: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = ?")
+        let row: Bond = sqlx::query_as("SELECT * FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -4179,7 +4215,7 @@ mod tests {
         ));
 
         let row: (Option<String>,) =
-            sqlx::query_as("SELECT payout_invoice FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT payout_invoice FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -4196,10 +4232,7 @@ mod tests {
         // rather than error, exactly like the "no candidate at all" case
         // looks from the caller's perspective.
         let pool = setup_pool().await;
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
         let order_id = Uuid::new_v4();
         // Deliberately no `insert_order` call.
         let bond = pending_payout_bond(
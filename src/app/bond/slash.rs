@@ -41,8 +41,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::str::FromStr;
 
+use crate::db::Crud;
+use crate::db::DbPool;
 use chrono::Utc;
-use mostro_core::db::Crud;
 use mostro_core::error::{
     CantDoReason,
     MostroError::{self, MostroCantDo, MostroInternalErr},
@@ -51,7 +52,6 @@ use mostro_core::error::{
 use mostro_core::message::{Action, BondResolution, Message, Payload};
 use mostro_core::order::{Kind, Order, SmallOrder, Status};
 use nostr_sdk::prelude::PublicKey;
-use sqlx::{Pool, Sqlite};
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -67,12 +67,12 @@ use super::model::Bond;
 use super::types::{BondRole, BondSlashReason, BondState};
 use crate::config::settings::Settings;
 use crate::config::types::AntiAbuseBondSettings;
-use crate::lightning::LndConnector;
+use crate::escrow::{connect_lightning, EscrowBackend};
 use crate::util::enqueue_order_msg;
 
 /// Minimal LND-side capability the slash path needs: settle a hold
 /// invoice by preimage. Mirrors the [`crate::app::cancel::CancelLightning`]
-/// pattern so tests can pass a stub instead of a live `LndConnector`.
+/// pattern so tests can pass a stub instead of a live node.
 pub trait SettleLightning {
     fn settle_hold_invoice<'a>(
         &'a mut self,
@@ -80,16 +80,12 @@ pub trait SettleLightning {
     ) -> Pin<Box<dyn Future<Output = Result<(), MostroError>> + Send + 'a>>;
 }
 
-impl SettleLightning for LndConnector {
+impl<E: EscrowBackend + ?Sized> SettleLightning for E {
     fn settle_hold_invoice<'a>(
         &'a mut self,
         preimage: &'a str,
     ) -> Pin<Box<dyn Future<Output = Result<(), MostroError>> + Send + 'a>> {
-        Box::pin(async move {
-            LndConnector::settle_hold_invoice(self, preimage)
-                .await
-                .map(|_| ())
-        })
+        EscrowBackend::settle_hold_invoice(self, preimage)
     }
 }
 
@@ -104,15 +100,15 @@ pub(super) fn is_already_settled_error(err: &MostroError) -> bool {
         || s.contains("code=alreadyexists")
 }
 
-/// Classify a SQLite error as a UNIQUE-constraint violation. sqlx 0.6's
-/// `DatabaseError` exposes the extended result code (2067 =
-/// `SQLITE_CONSTRAINT_UNIQUE`) and the driver message; either is sufficient.
+/// Classify a database error as a UNIQUE-constraint violation. sqlx maps the
+/// backend's code (SQLite 2067 = `SQLITE_CONSTRAINT_UNIQUE`, PostgreSQL
+/// 23505) to `is_unique_violation`; the driver message is a fallback.
 /// Used so the partial UNIQUE index on `(parent_bond_id, child_order_id)`
 /// (migration `20260611120000`) collapses a forced duplicate slice slash into
 /// the same idempotent no-op as the `INSERT ... WHERE NOT EXISTS` guard.
 pub(super) fn is_unique_violation(err: &sqlx::Error) -> bool {
     err.as_database_error().is_some_and(|d| {
-        d.code().as_deref() == Some("2067")
+        d.is_unique_violation()
             || d.message()
                 .to_lowercase()
                 .contains("unique constraint failed")
@@ -166,7 +162,7 @@ pub fn extract_bond_resolution(msg: &Message) -> BondResolution {
 ///   "feature disabled / `apply_to=none` / no bond posted" without a
 ///   separate config check — the absence of the row is the signal.
 pub async fn validate_bond_resolution(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
     resolution: &BondResolution,
 ) -> Result<(), MostroError> {
@@ -216,7 +212,7 @@ fn side_is_maker(order: &Order, side: Side) -> Result<bool, MostroError> {
 ///    range root, not the slice). Walks `range_parent_id` via
 ///    [`find_maker_bond_for_order`].
 async fn resolve_slash_target(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
     bonds: &[Bond],
     side: Side,
@@ -272,8 +268,8 @@ async fn resolve_slash_target(
 /// notice is never untruthful; an idempotent admin retry yields none either,
 /// so a winner is never re-notified. Range rows carry the slice amount, all
 /// others the full bond amount.
-pub async fn apply_bond_resolution<L: SettleLightning + Send>(
-    pool: &Pool<Sqlite>,
+pub async fn apply_bond_resolution<L: SettleLightning + Send + ?Sized>(
+    pool: &DbPool,
     ln_client: &mut L,
     order: &Order,
     resolution: &BondResolution,
@@ -384,10 +380,7 @@ pub async fn apply_bond_resolution<L: SettleLightning + Send>(
 /// the order's range root carries a `max_amount`. Range maker bonds use
 /// the Phase 6 accumulate-and-settle-at-close path; everything else uses
 /// the Phase 2/5 inline settle.
-async fn order_has_range_maker_bond(
-    pool: &Pool<Sqlite>,
-    order: &Order,
-) -> Result<bool, MostroError> {
+async fn order_has_range_maker_bond(pool: &DbPool, order: &Order) -> Result<bool, MostroError> {
     let root = find_range_root_order(pool, order.clone()).await?;
     Ok(root.max_amount.is_some())
 }
@@ -474,7 +467,7 @@ fn order_republishes_on_timeout(order: &Order) -> bool {
 /// republish-vs-cancel distinction: on a republish the maker's `Locked`
 /// bond is retained (it follows the order's lifecycle), otherwise every
 /// bond is released.
-async fn release_on_timeout(pool: &Pool<Sqlite>, order_id: Uuid, republishes: bool) {
+async fn release_on_timeout(pool: &DbPool, order_id: Uuid, republishes: bool) {
     if republishes {
         release_taker_bonds_for_order_or_warn(pool, order_id, "scheduler_timeout").await;
     } else {
@@ -482,8 +475,8 @@ async fn release_on_timeout(pool: &Pool<Sqlite>, order_id: Uuid, republishes: bo
     }
 }
 
-pub async fn slash_or_release_on_timeout<L: SettleLightning + Send>(
-    pool: &Pool<Sqlite>,
+pub async fn slash_or_release_on_timeout<L: SettleLightning + Send + ?Sized>(
+    pool: &DbPool,
     ln_client: &mut L,
     order: &Order,
     bond_cfg: Option<&AntiAbuseBondSettings>,
@@ -650,16 +643,18 @@ pub async fn slash_or_release_on_timeout<L: SettleLightning + Send>(
 /// to the caller for the `BondSlashed` notice (it carries the slice's
 /// proportional slashed amount, not the whole parent bond).
 async fn find_slice_slash_child(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     parent_bond_id: Uuid,
     slice_order_id: Uuid,
 ) -> Result<Option<Bond>, MostroError> {
-    sqlx::query_as::<_, Bond>("SELECT * FROM bonds WHERE parent_bond_id = ? AND child_order_id = ?")
-        .bind(parent_bond_id)
-        .bind(slice_order_id)
-        .fetch_optional(pool)
-        .await
-        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+    sqlx::query_as::<_, Bond>(
+        "SELECT * FROM bonds WHERE parent_bond_id = $1 AND child_order_id = $2",
+    )
+    .bind(parent_bond_id)
+    .bind(slice_order_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
 }
 
 /// Confirm a slash with the `expected` reason actually landed on `bond_id`
@@ -687,12 +682,12 @@ async fn find_slice_slash_child(
 /// caller never confirms on a dispute slash's `LostDispute` witness and
 /// vice-versa — each slash path owns its own messaging.
 async fn slash_reason_recorded(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     bond_id: Uuid,
     expected: BondSlashReason,
 ) -> Result<bool, MostroError> {
     let row: Option<(Option<String>,)> =
-        sqlx::query_as("SELECT slashed_reason FROM bonds WHERE id = ?")
+        sqlx::query_as("SELECT slashed_reason FROM bonds WHERE id = $1")
             .bind(bond_id)
             .fetch_optional(pool)
             .await
@@ -705,7 +700,7 @@ async fn slash_reason_recorded(
 /// [`slash_reason_recorded`] keyed on `BondSlashReason::Timeout`; see that
 /// function for why the durable `slashed_reason` witness is used instead of
 /// a transient `state = PendingPayout` check.
-async fn timeout_slash_confirmed(pool: &Pool<Sqlite>, bond_id: Uuid) -> Result<bool, MostroError> {
+async fn timeout_slash_confirmed(pool: &DbPool, bond_id: Uuid) -> Result<bool, MostroError> {
     slash_reason_recorded(pool, bond_id, BondSlashReason::Timeout).await
 }
 
@@ -780,8 +775,8 @@ pub async fn notify_bond_slashed(order: &Order, slashed: &Bond) {
 /// `node_share_sats` are populated for a `LostDispute` row, which is
 /// what makes the split snapshot deterministic across restarts and
 /// config changes.
-async fn slash_one<L: SettleLightning + Send>(
-    pool: &Pool<Sqlite>,
+async fn slash_one<L: SettleLightning + Send + ?Sized>(
+    pool: &DbPool,
     ln_client: &mut L,
     bond: &Bond,
     reason: BondSlashReason,
@@ -820,8 +815,8 @@ async fn slash_one<L: SettleLightning + Send>(
     let now = Utc::now().timestamp();
     let result = sqlx::query(
         "UPDATE bonds \
-           SET state = ?, slashed_reason = ?, slashed_at = ?, node_share_sats = ? \
-         WHERE id = ? AND state = ?",
+           SET state = $1, slashed_reason = $2, slashed_at = $3, node_share_sats = $4 \
+         WHERE id = $5 AND state = $6",
     )
     .bind(BondState::PendingPayout.to_string())
     .bind(reason.to_string())
@@ -833,6 +828,11 @@ async fn slash_one<L: SettleLightning + Send>(
     .await;
     match result {
         Ok(r) if r.rows_affected() == 1 => {
+            crate::rpc::events::bond_transitioned(
+                bond.id,
+                bond.order_id,
+                &BondState::PendingPayout.to_string(),
+            );
             info!(
                 bond_id = %bond.id,
                 order_id = %bond.order_id,
@@ -888,7 +888,7 @@ async fn slash_one<L: SettleLightning + Send>(
 /// one-shot `BondSlashed` notice on this so a scheduler retry never
 /// re-notifies the maker for a slash that already landed.
 async fn record_maker_slice_slash(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     slice: &Order,
     root: &Order,
     parent_bond: &Bond,
@@ -966,11 +966,11 @@ async fn record_maker_slice_slash(
         "INSERT INTO bonds \
             (id, order_id, parent_bond_id, child_order_id, pubkey, role, \
              amount_sats, state, slashed_reason, node_share_sats, slashed_at, created_at) \
-         SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ? \
+         SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12 \
          WHERE NOT EXISTS ( \
-             SELECT 1 FROM bonds WHERE parent_bond_id = ? AND child_order_id = ?) \
+             SELECT 1 FROM bonds WHERE parent_bond_id = $13 AND child_order_id = $14) \
            AND EXISTS ( \
-             SELECT 1 FROM bonds WHERE id = ? AND state = ?)",
+             SELECT 1 FROM bonds WHERE id = $15 AND state = $16)",
     )
     .bind(Uuid::new_v4())
     .bind(slice.id)
@@ -1030,9 +1030,9 @@ async fn record_maker_slice_slash(
     // is excluded so it never inflates the slashed total.
     sqlx::query(
         "UPDATE bonds SET slashed_share_sats = \
-            (SELECT COALESCE(SUM(amount_sats), 0) FROM bonds \
-               WHERE parent_bond_id = ? AND child_order_id IS NOT NULL) \
-         WHERE id = ? AND state = ?",
+            (SELECT CAST(COALESCE(SUM(amount_sats), 0) AS BIGINT) FROM bonds \
+               WHERE parent_bond_id = $1 AND child_order_id IS NOT NULL) \
+         WHERE id = $2 AND state = $3",
     )
     .bind(parent_bond.id)
     .bind(parent_bond.id)
@@ -1070,8 +1070,8 @@ async fn record_maker_slice_slash(
 ///   per-slice child rows and the refund row — all `PendingPayout` — are
 ///   then driven by the Phase 3 payout scheduler, which skipped them while
 ///   the parent was `Locked`.
-pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
-    pool: &Pool<Sqlite>,
+pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send + ?Sized>(
+    pool: &DbPool,
     ln_client: &mut L,
     order: &Order,
 ) -> Result<(), MostroError> {
@@ -1151,7 +1151,7 @@ pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
     // ensures exactly one close wins if two terminal hooks race; the loser
     // sees `rows_affected = 0`, rolls back, and returns with no side effects
     // (so it never writes a second refund row).
-    let cas = sqlx::query("UPDATE bonds SET state = ? WHERE id = ? AND state = ?")
+    let cas = sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2 AND state = $3")
         .bind(BondState::Slashed.to_string())
         .bind(parent.id)
         .bind(BondState::Locked.to_string())
@@ -1176,8 +1176,8 @@ pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
     // read and the CAS — the late child would still be paid out, pushing the
     // total distributed (children + refund) past the single settled HTLC.
     let total_slashed: i64 = sqlx::query_scalar::<_, i64>(
-        "SELECT COALESCE(SUM(amount_sats), 0) FROM bonds \
-         WHERE parent_bond_id = ? AND child_order_id IS NOT NULL",
+        "SELECT CAST(COALESCE(SUM(amount_sats), 0) AS BIGINT) FROM bonds \
+         WHERE parent_bond_id = $1 AND child_order_id IS NOT NULL",
     )
     .bind(parent.id)
     .fetch_one(&mut *tx)
@@ -1195,8 +1195,8 @@ pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
     // almost immediately, but the timeout-slash path in Phase 7 may not.)
     if !slice_children.is_empty() {
         sqlx::query(
-            "UPDATE bonds SET slashed_at = ? \
-             WHERE parent_bond_id = ? AND child_order_id IS NOT NULL AND state = ?",
+            "UPDATE bonds SET slashed_at = $1 \
+             WHERE parent_bond_id = $2 AND child_order_id IS NOT NULL AND state = $3",
         )
         .bind(now)
         .bind(parent.id)
@@ -1221,7 +1221,7 @@ pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
             "INSERT INTO bonds \
                 (id, order_id, parent_bond_id, child_order_id, pubkey, role, \
                  amount_sats, state, slashed_reason, node_share_sats, slashed_at, created_at) \
-             VALUES (?, ?, ?, NULL, ?, ?, ?, ?, ?, ?, ?, ?)",
+             VALUES ($1, $2, $3, NULL, $4, $5, $6, $7, $8, $9, $10, $11)",
         )
         .bind(Uuid::new_v4())
         .bind(root.id)
@@ -1242,6 +1242,11 @@ pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
     tx.commit()
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::bond_transitioned(
+        parent.id,
+        parent.order_id,
+        &BondState::Slashed.to_string(),
+    );
 
     info!(
         bond_id = %parent.id,
@@ -1256,14 +1261,14 @@ pub async fn resolve_range_maker_bond_at_close<L: SettleLightning + Send>(
     Ok(())
 }
 
-/// Open an `LndConnector` and run [`resolve_range_maker_bond_at_close`],
+/// Connect to the Lightning node and run [`resolve_range_maker_bond_at_close`],
 /// logging on failure. For the non-admin terminal hooks (completion,
 /// cancel, scheduler expiry) that don't already hold an LND client. A cheap
 /// pre-check skips opening LND entirely when there is no `Locked` maker bond
 /// to resolve — the common case. The admin handlers pass their own
 /// `ln_client` to the generic function directly.
 pub async fn resolve_range_maker_bond_at_close_or_warn(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     order: &Order,
     context: &'static str,
 ) {
@@ -1276,14 +1281,14 @@ pub async fn resolve_range_maker_bond_at_close_or_warn(
             return;
         }
     }
-    let mut ln = match LndConnector::new().await {
+    let mut ln = match connect_lightning().await {
         Ok(l) => l,
         Err(e) => {
             warn!("{context}: cannot connect to LND to resolve maker bond at close: {e}");
             return;
         }
     };
-    if let Err(e) = resolve_range_maker_bond_at_close(pool, &mut ln, order).await {
+    if let Err(e) = resolve_range_maker_bond_at_close(pool, ln.as_mut(), order).await {
         warn!(
             order_id = %order.id,
             "{context}: resolve_range_maker_bond_at_close failed: {e}"
@@ -1304,9 +1309,7 @@ pub async fn resolve_range_maker_bond_at_close_or_warn(
 /// every order in its range tree (root + every `range_parent_id` descendant)
 /// is in a terminal status, so a legitimately-open range — whose maker bond
 /// is `Locked` by design — is never touched.
-async fn collect_stranded_range_maker_roots(
-    pool: &Pool<Sqlite>,
-) -> Result<Vec<Order>, MostroError> {
+async fn collect_stranded_range_maker_roots(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
     let mut stranded = Vec::new();
     for bond in super::db::find_locked_maker_parent_bonds(pool).await? {
         // Best-effort, per-root isolation (§8.2): a transient failure on one
@@ -1352,8 +1355,10 @@ async fn collect_stranded_range_maker_roots(
 /// Slashed`), so re-invoking it is safe whether the prior close half-finished
 /// or never ran. A per-bond failure is logged and the sweep moves on — the
 /// next tick (and ultimately the CLTV safety net) retries.
-pub(crate) async fn reconcile_stranded_range_maker_bonds_with<L: SettleLightning + Send>(
-    pool: &Pool<Sqlite>,
+pub(crate) async fn reconcile_stranded_range_maker_bonds_with<
+    L: SettleLightning + Send + ?Sized,
+>(
+    pool: &DbPool,
     ln_client: &mut L,
 ) -> usize {
     let roots = match collect_stranded_range_maker_roots(pool).await {
@@ -1378,10 +1383,10 @@ pub(crate) async fn reconcile_stranded_range_maker_bonds_with<L: SettleLightning
 
 /// Reconciliation sweep (scheduler entry point): scan for range maker bonds
 /// stranded `Locked` after a failed close and retry each one. Opens a single
-/// `LndConnector` for the batch, and only when there is at least one stranded
+/// node connection for the batch, and only when there is at least one stranded
 /// bond — the common case (no stranded bond) costs one indexed query and
 /// never touches LND.
-pub async fn reconcile_stranded_range_maker_bonds(pool: &Pool<Sqlite>) {
+pub async fn reconcile_stranded_range_maker_bonds(pool: &DbPool) {
     // Cheap pre-check so an idle node never opens LND just to find nothing.
     match collect_stranded_range_maker_roots(pool).await {
         Ok(roots) if roots.is_empty() => return,
@@ -1391,14 +1396,14 @@ pub async fn reconcile_stranded_range_maker_bonds(pool: &Pool<Sqlite>) {
             return;
         }
     }
-    let mut ln = match LndConnector::new().await {
+    let mut ln = match connect_lightning().await {
         Ok(l) => l,
         Err(e) => {
             warn!("reconcile_sweep: cannot connect to LND to retry stranded maker bonds: {e}");
             return;
         }
     };
-    let resolved = reconcile_stranded_range_maker_bonds_with(pool, &mut ln).await;
+    let resolved = reconcile_stranded_range_maker_bonds_with(pool, ln.as_mut()).await;
     if resolved > 0 {
         info!(
             resolved,
@@ -1431,11 +1436,10 @@ fn resolve_locked_bond<'a>(order: &Order, bonds: &'a [Bond], side: Side) -> Opti
 mod tests {
     use std::sync::{Arc, Mutex};
 
+    use crate::db::DbPool;
     use mostro_core::error::{MostroError, ServiceError};
     use mostro_core::message::{Action, Message, Payload};
     use mostro_core::order::{Kind, Order, Status};
-    use sqlx::sqlite::SqlitePoolOptions;
-    use sqlx::{Pool, Sqlite};
 
     use super::*;
     use crate::app::bond::model::Bond;
@@ -1483,69 +1487,17 @@ mod tests {
         }
     }
 
-    async fn setup_pool() -> Pool<Sqlite> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .expect("open in-memory sqlite");
-        sqlx::query(include_str!(
-            "../../../migrations/20221222153301_orders.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("orders migration");
-        for stmt in include_str!("../../../migrations/20251126120000_dev_fee.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt)
-                .execute(&pool)
-                .await
-                .expect("dev_fee migration");
-        }
-        sqlx::query(include_str!(
-            "../../../migrations/20260423120000_anti_abuse_bond.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bonds migration");
-        sqlx::query(include_str!(
-            "../../../migrations/20260518120000_bond_payout_payment_hash.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bond_payout_payment_hash migration");
-        // Phase 6 chain-walk tests load full `Order` rows via `Order::by_id`,
-        // which selects every column the model declares — so the orders
-        // table must carry the later Cashu columns too.
-        for stmt in include_str!("../../../migrations/20260530120000_cashu_escrow_fields.sql")
-            .split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty() && !s.lines().all(|l| l.trim_start().starts_with("--")))
-        {
-            sqlx::query(stmt)
-                .execute(&pool)
-                .await
-                .expect("cashu_escrow_fields migration");
-        }
-        sqlx::query(include_str!(
-            "../../../migrations/20260611120000_bond_slice_slash_unique.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("bond_slice_slash_unique migration");
-        pool
+    async fn setup_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    async fn insert_order_row(pool: &Pool<Sqlite>, order: &Order) {
+    async fn insert_order_row(pool: &DbPool, order: &Order) {
         sqlx::query(
             r#"INSERT INTO orders (
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, created_at, expires_at,
                 seller_pubkey, buyer_pubkey
-            ) VALUES (?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+            ) VALUES ($1, $2, $3, $4, 0, $5, $6, $7, $8, $9, $10, $11, $12)"#,
         )
         .bind(order.id)
         .bind(&order.kind)
@@ -1589,12 +1541,7 @@ mod tests {
         "00".repeat(32)
     }
 
-    async fn insert_bond(
-        pool: &Pool<Sqlite>,
-        order_id: Uuid,
-        pubkey: &str,
-        state: BondState,
-    ) -> Bond {
+    async fn insert_bond(pool: &DbPool, order_id: Uuid, pubkey: &str, state: BondState) -> Bond {
         insert_bond_with_role(pool, order_id, pubkey, BondRole::Taker, state).await
     }
 
@@ -1603,7 +1550,7 @@ mod tests {
     /// buyer/seller → bond-row resolution resolves to the maker row when
     /// the maker is on the named side (§3.1).
     async fn insert_bond_with_role(
-        pool: &Pool<Sqlite>,
+        pool: &DbPool,
         order_id: Uuid,
         pubkey: &str,
         role: BondRole,
@@ -1763,7 +1710,7 @@ mod tests {
         .await
         .unwrap();
 
-        let row: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let row: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -1800,7 +1747,7 @@ mod tests {
 
         let row: (String, Option<String>, Option<i64>, Option<i64>) = sqlx::query_as(
             "SELECT state, slashed_reason, slashed_at, node_share_sats \
-             FROM bonds WHERE id = ?",
+             FROM bonds WHERE id = $1",
         )
         .bind(bond.id)
         .fetch_one(&pool)
@@ -1859,7 +1806,7 @@ mod tests {
         .unwrap();
 
         let maker_row: (String, Option<String>) =
-            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = $1")
                 .bind(maker_bond.id)
                 .fetch_one(&pool)
                 .await
@@ -1871,7 +1818,7 @@ mod tests {
         );
         assert_eq!(maker_row.1.as_deref(), Some("lost-dispute"));
 
-        let taker_state: String = sqlx::query_scalar("SELECT state FROM bonds WHERE id = ?")
+        let taker_state: String = sqlx::query_scalar("SELECT state FROM bonds WHERE id = $1")
             .bind(taker_bond.id)
             .fetch_one(&pool)
             .await
@@ -1918,7 +1865,7 @@ mod tests {
         .unwrap();
 
         let row: (String, Option<String>) =
-            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = $1")
                 .bind(maker_bond.id)
                 .fetch_one(&pool)
                 .await
@@ -1954,7 +1901,7 @@ mod tests {
         .await
         .unwrap();
         let first: (String, Option<i64>, Option<i64>) =
-            sqlx::query_as("SELECT state, slashed_at, node_share_sats FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_at, node_share_sats FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -1973,7 +1920,7 @@ mod tests {
         .await
         .unwrap();
         let second: (String, Option<i64>, Option<i64>) =
-            sqlx::query_as("SELECT state, slashed_at, node_share_sats FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_at, node_share_sats FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2047,7 +1994,7 @@ mod tests {
             vec![stub_preimage()],
             "slash path must settle exactly the slashed bond's HTLC"
         );
-        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2087,7 +2034,7 @@ mod tests {
             "both slashed bonds must have their HTLCs settled immediately"
         );
         for b in [&buyer_bond, &seller_bond] {
-            let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+            let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
                 .bind(b.id)
                 .fetch_one(&pool)
                 .await
@@ -2119,7 +2066,7 @@ mod tests {
             ln.calls().is_empty(),
             "non-slashed (released) bonds must not invoke settle_hold_invoice"
         );
-        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2149,7 +2096,7 @@ mod tests {
             .await
             .unwrap();
 
-        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2181,7 +2128,7 @@ mod tests {
             .await
             .unwrap();
 
-        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+        let state: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(bond.id)
             .fetch_one(&pool)
             .await
@@ -2221,8 +2168,8 @@ mod tests {
         o
     }
 
-    async fn read_bond_state(pool: &Pool<Sqlite>, id: Uuid) -> String {
-        let row: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = ?")
+    async fn read_bond_state(pool: &DbPool, id: Uuid) -> String {
+        let row: (String,) = sqlx::query_as("SELECT state FROM bonds WHERE id = $1")
             .bind(id)
             .fetch_one(pool)
             .await
@@ -2299,7 +2246,7 @@ mod tests {
             "slash settles exactly the responsible bond's HTLC"
         );
         let row: (String, Option<String>, Option<i64>) =
-            sqlx::query_as("SELECT state, slashed_reason, slashed_at FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_reason, slashed_at FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2600,7 +2547,7 @@ mod tests {
             "the non-range maker slash settles the HTLC inline"
         );
         let row: (String, Option<String>) =
-            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = $1")
                 .bind(maker_bond.id)
                 .fetch_one(&pool)
                 .await
@@ -2904,7 +2851,7 @@ mod tests {
             let bond = insert_bond(&pool, order.id, taker_pk(), state).await;
             // Stamp the timeout slash metadata that `slash_one` writes
             // atomically with the Locked → PendingPayout CAS.
-            sqlx::query("UPDATE bonds SET slashed_reason = ? WHERE id = ?")
+            sqlx::query("UPDATE bonds SET slashed_reason = $1 WHERE id = $2")
                 .bind(BondSlashReason::Timeout.to_string())
                 .bind(bond.id)
                 .execute(&pool)
@@ -2927,7 +2874,7 @@ mod tests {
         // A dispute slash (LostDispute) must not be mistaken for a timeout
         // slash, so the timeout notice is not sent for it.
         let dispute = insert_bond(&pool, order.id, taker_pk(), BondState::PendingPayout).await;
-        sqlx::query("UPDATE bonds SET slashed_reason = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET slashed_reason = $1 WHERE id = $2")
             .bind(BondSlashReason::LostDispute.to_string())
             .bind(dispute.id)
             .execute(&pool)
@@ -2943,7 +2890,6 @@ mod tests {
     async fn notify_bond_slashed_targets_the_slashed_user() {
         // The forfeiture notice goes to the bonded (slashed) taker,
         // carrying Action::BondSlashed scoped to the order.
-        use crate::config::MESSAGE_QUEUES;
         let pool = setup_pool().await;
         let order = waiting_order(
             Kind::Sell,
@@ -2956,9 +2902,7 @@ mod tests {
 
         notify_bond_slashed(&order, &bond).await;
 
-        let recipients: Vec<String> = MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        let recipients: Vec<String> = crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(m, _)| {
@@ -2983,13 +2927,13 @@ mod tests {
 
     /// Insert an order row including the range columns (`min_amount`,
     /// `max_amount`, `range_parent_id`) that `insert_order_row` omits.
-    async fn insert_range_order_row(pool: &Pool<Sqlite>, order: &Order) {
+    async fn insert_range_order_row(pool: &DbPool, order: &Order) {
         sqlx::query(
             r#"INSERT INTO orders (
                 id, kind, event_id, status, premium, payment_method,
                 amount, fiat_code, fiat_amount, min_amount, max_amount,
                 range_parent_id, created_at, expires_at, seller_pubkey, buyer_pubkey
-            ) VALUES (?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+            ) VALUES ($1, $2, $3, $4, 0, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)"#,
         )
         .bind(order.id)
         .bind(&order.kind)
@@ -3033,7 +2977,7 @@ mod tests {
     /// `hash` (so the release path skips the live LND `cancel_hold_invoice`
     /// and the settle path is exercised only via the `StubSettle`).
     async fn insert_parent_maker_bond(
-        pool: &Pool<Sqlite>,
+        pool: &DbPool,
         order_id: Uuid,
         pubkey: &str,
         amount_sats: i64,
@@ -3246,14 +3190,14 @@ mod tests {
         // Inject a DB failure mid-transaction: a trigger that aborts the
         // maker-refund INSERT (the row with parent_bond_id set, child_order_id
         // NULL). The slice-slash insert and the CAS/UPDATE are unaffected.
-        sqlx::query(
-            "CREATE TRIGGER bond_refund_fail BEFORE INSERT ON bonds \
-             WHEN NEW.parent_bond_id IS NOT NULL AND NEW.child_order_id IS NULL \
-             BEGIN SELECT RAISE(ABORT, 'injected refund insert failure'); END",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "bond_refund_fail",
+            "BEFORE INSERT ON bonds",
+            Some("NEW.parent_bond_id IS NOT NULL AND NEW.child_order_id IS NULL"),
+            "injected refund insert failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         // Settle succeeds, then the transaction aborts → the whole close errors.
         let stub = StubSettle::new();
@@ -3287,15 +3231,12 @@ mod tests {
 
         // Remove the injected failure and re-run the close (the sweep's retry).
         // The HTLC is already settled, so LND reports "already settled".
-        sqlx::query("DROP TRIGGER bond_refund_fail")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_trigger(&pool, "bond_refund_fail", "bonds").await;
         // Backdate the slice child so the close-time re-anchor is observable
         // despite 1-second timestamp granularity.
         sqlx::query(
             "UPDATE bonds SET slashed_at = 1000000 \
-             WHERE parent_bond_id = ? AND child_order_id IS NOT NULL",
+             WHERE parent_bond_id = $1 AND child_order_id IS NOT NULL",
         )
         .bind(parent.id)
         .execute(&pool)
@@ -3452,7 +3393,7 @@ mod tests {
         // Backdate the slice child's slashed_at to simulate a range that
         // stayed open well past the claim window before closing.
         sqlx::query(
-            "UPDATE bonds SET slashed_at = ? WHERE parent_bond_id = ? AND child_order_id IS NOT NULL",
+            "UPDATE bonds SET slashed_at = $1 WHERE parent_bond_id = $2 AND child_order_id IS NOT NULL",
         )
         .bind(1_000_000i64)
         .bind(parent.id)
@@ -3535,7 +3476,7 @@ mod tests {
         let parent = insert_parent_maker_bond(&pool, root.id, maker_pk(), 1000).await;
 
         // Simulate the close having already moved the parent off `Locked`.
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::Slashed.to_string())
             .bind(parent.id)
             .execute(&pool)
@@ -3593,7 +3534,7 @@ mod tests {
             "INSERT INTO bonds \
                 (id, order_id, parent_bond_id, child_order_id, pubkey, role, \
                  amount_sats, state, created_at) \
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
         )
         .bind(Uuid::new_v4())
         .bind(root.id)
@@ -3618,7 +3559,7 @@ mod tests {
             "INSERT INTO bonds \
                 (id, order_id, parent_bond_id, child_order_id, pubkey, role, \
                  amount_sats, state, created_at) \
-             VALUES (?, ?, ?, NULL, ?, ?, ?, ?, ?)",
+             VALUES ($1, $2, $3, NULL, $4, $5, $6, $7, $8)",
         )
         .bind(Uuid::new_v4())
         .bind(root.id)
@@ -3727,15 +3668,9 @@ mod tests {
         // (a corrupt/partially-deleted state). FK enforcement is on by
         // default, so drop it just for this orphan insert to simulate that.
         let orphan_order_id = Uuid::new_v4();
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
         let bad = insert_parent_maker_bond(&pool, orphan_order_id, maker_pk(), 1000).await;
-        sqlx::query("PRAGMA foreign_keys = ON")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, true).await;
 
         // Good root: a fully-terminal range with one slashed slice — stranded
         // and genuinely resolvable.
@@ -4111,9 +4046,9 @@ mod tests {
 
     #[tokio::test]
     async fn is_unique_violation_false_for_non_unique_db_error() {
-        // The message-based fallback branch only runs when `code()` is not
-        // "2067" — a genuine unique-constraint violation on SQLite always
-        // carries that code, so the fallback is only reachable from some
+        // The message-based fallback branch only runs when the driver does
+        // not report a unique violation — a genuine one always carries the
+        // backend's code, so the fallback is only reachable from some
         // *other* kind of database error. Drive it with a plain SQL error
         // (unknown column) and confirm it classifies as "not a unique
         // violation" rather than panicking or misclassifying.
@@ -4218,7 +4153,6 @@ mod tests {
 
     #[tokio::test]
     async fn notify_bond_slashed_skips_when_pubkey_unparseable() {
-        use crate::config::MESSAGE_QUEUES;
         let order = waiting_order(
             Kind::Sell,
             maker_pk(),
@@ -4231,9 +4165,7 @@ mod tests {
 
         notify_bond_slashed(&order, &bond).await;
 
-        let has_notice = MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        let has_notice = crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .any(|(m, _)| {
@@ -4248,7 +4180,6 @@ mod tests {
 
     #[tokio::test]
     async fn notify_bond_slashed_skips_when_order_kind_unparseable() {
-        use crate::config::MESSAGE_QUEUES;
         let mut order = waiting_order(
             Kind::Sell,
             maker_pk(),
@@ -4260,9 +4191,7 @@ mod tests {
 
         notify_bond_slashed(&order, &bond).await;
 
-        let has_notice = MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        let has_notice = crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .any(|(m, _)| {
@@ -4316,7 +4245,7 @@ mod tests {
         let order = fixture_order(Kind::Sell, maker_pk(), taker_pk());
         insert_order_row(&pool, &order).await;
         let bond = insert_bond(&pool, order.id, taker_pk(), BondState::Locked).await;
-        sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
             .bind(BondState::PendingPayout.to_string())
             .bind(bond.id)
             .execute(&pool)
@@ -4332,7 +4261,7 @@ mod tests {
             "settle is still attempted before the CAS no-op is discovered"
         );
         let row: (String, Option<String>) =
-            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = ?")
+            sqlx::query_as("SELECT state, slashed_reason FROM bonds WHERE id = $1")
                 .bind(bond.id)
                 .fetch_one(&pool)
                 .await
@@ -4353,10 +4282,7 @@ mod tests {
         let order = fixture_order(Kind::Sell, maker_pk(), taker_pk());
         insert_order_row(&pool, &order).await;
         let bond = insert_bond(&pool, order.id, taker_pk(), BondState::Locked).await;
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
         let mut ln = StubSettle::new();
 
         // Must not panic despite the CAS query itself failing.
@@ -4472,7 +4398,7 @@ mod tests {
         let root = range_slice(Kind::Sell, maker_pk(), taker_pk(), 40, 10, 100);
         insert_range_order_row(&pool, &root).await;
         let parent = insert_parent_maker_bond(&pool, root.id, maker_pk(), 1000).await;
-        sqlx::query("UPDATE bonds SET slashed_share_sats = ? WHERE id = ?")
+        sqlx::query("UPDATE bonds SET slashed_share_sats = $1 WHERE id = $2")
             .bind(1000_i64)
             .bind(parent.id)
             .execute(&pool)
@@ -4510,10 +4436,7 @@ mod tests {
         let root = range_slice(Kind::Sell, maker_pk(), taker_pk(), 40, 10, 100);
         insert_range_order_row(&pool, &root).await;
         let parent = insert_parent_maker_bond(&pool, root.id, maker_pk(), 1000).await;
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
 
         let err = record_maker_slice_slash(
             &pool,
@@ -4588,7 +4511,7 @@ mod tests {
         )
         .await
         .unwrap();
-        sqlx::query("UPDATE bonds SET preimage = NULL WHERE id = ?")
+        sqlx::query("UPDATE bonds SET preimage = NULL WHERE id = $1")
             .bind(parent.id)
             .execute(&pool)
             .await
@@ -4618,7 +4541,7 @@ mod tests {
     /// deterministically drive `resolve_range_maker_bond_at_close`'s
     /// lost-the-race branch without real concurrency.
     struct RaceWinningSettle {
-        pool: Pool<Sqlite>,
+        pool: DbPool,
         bond_id: Uuid,
     }
 
@@ -4628,7 +4551,7 @@ mod tests {
             _preimage: &'a str,
         ) -> Pin<Box<dyn Future<Output = Result<(), MostroError>> + Send + 'a>> {
             Box::pin(async move {
-                sqlx::query("UPDATE bonds SET state = ? WHERE id = ?")
+                sqlx::query("UPDATE bonds SET state = $1 WHERE id = $2")
                     .bind(BondState::Slashed.to_string())
                     .bind(self.bond_id)
                     .execute(&self.pool)
@@ -4740,10 +4663,7 @@ mod tests {
         let pool = setup_pool().await;
         let order = fixture_order(Kind::Sell, maker_pk(), taker_pk());
         insert_order_row(&pool, &order).await;
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
 
         resolve_range_maker_bond_at_close_or_warn(&pool, &order, "unit_test").await;
     }
@@ -4761,14 +4681,8 @@ mod tests {
         insert_range_order_row(&pool, &root).await;
         insert_parent_maker_bond(&pool, root.id, maker_pk(), 1000).await;
 
-        sqlx::query("PRAGMA foreign_keys = OFF")
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::foreign_keys(&pool, false).await;
+        crate::db::test_utils::drop_table(&pool, "orders").await;
 
         let stranded = collect_stranded_range_maker_roots(&pool).await.unwrap();
         assert!(
@@ -4783,10 +4697,7 @@ mod tests {
         // failure) must be logged and yield zero resolved, never panic or
         // propagate.
         let pool = setup_pool().await;
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
         let stub = StubSettle::new();
 
         let resolved = reconcile_stranded_range_maker_bonds_with(&pool, &mut stub.clone()).await;
@@ -4817,14 +4728,14 @@ mod tests {
 
         // Force the close's refund-row insert to fail once it reaches the
         // transaction (mirrors `range_close_crash_after_settle_is_resumed`).
-        sqlx::query(
-            "CREATE TRIGGER bond_refund_fail_sweep BEFORE INSERT ON bonds \
-             WHEN NEW.parent_bond_id IS NOT NULL AND NEW.child_order_id IS NULL \
-             BEGIN SELECT RAISE(ABORT, 'injected refund insert failure'); END",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "bond_refund_fail_sweep",
+            "BEFORE INSERT ON bonds",
+            Some("NEW.parent_bond_id IS NOT NULL AND NEW.child_order_id IS NULL"),
+            "injected refund insert failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let stub = StubSettle::new();
         let resolved = reconcile_stranded_range_maker_bonds_with(&pool, &mut stub.clone()).await;
@@ -4856,10 +4767,7 @@ mod tests {
         // A scan failure must short-circuit before the LND connect step
         // too, for the same MOSTRO_CONFIG-safety reason.
         let pool = setup_pool().await;
-        sqlx::query("DROP TABLE bonds")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "bonds").await;
 
         reconcile_stranded_range_maker_bonds(&pool).await;
     }
@@ -1,13 +1,15 @@
 use crate::app::bond;
 use crate::app::context::AppContext;
 use crate::app::dispute::close_dispute_after_user_resolution;
+use crate::cashu::{cashu_pubkey_from_xonly_hex, verify_escrow_signatures};
+use crate::config::settings::Settings;
+use crate::db::Crud;
+use crate::db::DbPool;
 use crate::db::{edit_pubkeys_order, update_order_to_initial_state};
-use crate::lightning::LndConnector;
+use crate::escrow::EscrowBackend;
 use crate::util::{enqueue_order_msg, get_order, update_order_event};
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 use std::str::FromStr;
 use tracing::{info, warn};
 
@@ -18,17 +20,24 @@ pub trait CancelLightning {
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MostroError>> + Send + 'a>>;
 }
 
-impl CancelLightning for LndConnector {
+/// Every escrow backend can cancel. Cashu mode never reaches it: its orders
+/// carry no hold invoice hash, so the inert [`crate::escrow::CashuBackend`]
+/// only satisfies the handler bound.
+impl<E: EscrowBackend + ?Sized> CancelLightning for E {
     fn cancel_hold_invoice<'a>(
         &'a mut self,
         hash: &'a str,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MostroError>> + Send + 'a>>
     {
-        Box::pin(async move {
-            LndConnector::cancel_hold_invoice(self, hash)
-                .await
-                .map(|_| ())
-        })
+        EscrowBackend::cancel_hold_invoice(self, hash)
+    }
+}
+
+/// Buyer signatures carried on a `Cancel` message (Track C §5), if any.
+fn cancel_cashu_signatures(msg: &Message) -> Option<Vec<CashuProofSignature>> {
+    match &msg.get_inner_message_kind().payload {
+        Some(Payload::CashuSignatures(sigs)) => Some(sigs.clone()),
+        _ => None,
     }
 }
 
@@ -74,7 +83,8 @@ async fn notify_creator(order: &Order, request_id: Option<u64>) -> Result<(), Mo
 /// - Cancels the hold invoice if present (funds go back to seller)
 /// - Persists `Status::CooperativelyCanceled`
 /// - Publishes a new replaceable nostr event and notifies both parties
-async fn cancel_cooperative_execution_step_2<L: CancelLightning + Send>(
+#[allow(clippy::too_many_arguments)]
+async fn cancel_cooperative_execution_step_2<L: CancelLightning + Send + ?Sized>(
     ctx: &AppContext,
     event: &UnwrappedMessage,
     request_id: Option<u64>,
@@ -82,6 +92,7 @@ async fn cancel_cooperative_execution_step_2<L: CancelLightning + Send>(
     counterparty_pubkey: String,
     my_keys: &Keys,
     ln_client: &mut L,
+    cashu_signatures: Option<Vec<CashuProofSignature>>,
 ) -> Result<(), MostroError> {
     let pool = ctx.pool();
     // Guard: the same party cannot both initiate and confirm the cooperative cancel.
@@ -92,6 +103,19 @@ async fn cancel_cooperative_execution_step_2<L: CancelLightning + Send>(
         }
     }
 
+    if Settings::is_cashu_enabled() {
+        return cancel_cooperative_execution_cashu(
+            ctx,
+            event,
+            request_id,
+            order,
+            counterparty_pubkey,
+            my_keys,
+            cashu_signatures,
+        )
+        .await;
+    }
+
     // Cancel hold invoice if present; if funds were locked, this returns them to the seller.
     if let Some(hash) = &order.hash {
         // We return funds to seller
@@ -108,6 +132,7 @@ async fn cancel_cooperative_execution_step_2<L: CancelLightning + Send>(
         .update(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::order_status_changed(order.id, &order.status);
     // Publish a replaceable nostr event reflecting the new status and persist the mapping.
     update_order_event(my_keys, Status::CooperativelyCanceled, &order)
         .await
@@ -156,16 +181,203 @@ async fn cancel_cooperative_execution_step_2<L: CancelLightning + Send>(
     Ok(())
 }
 
+/// Step 2 of a cooperative cancel in Cashu mode (Track C §5).
+///
+/// There is no hold invoice to cancel: the seller reclaims the locked token
+/// itself with the buyer's signatures (2-of-3 path), so the cancel only
+/// completes once the seller has them:
+/// - When the buyer confirms, takes the buyer's signatures from this
+///   `Cancel` and relays them to the seller once the cancel is persisted.
+///   When the seller confirms, the buyer's step-1 `Cancel` already carried
+///   them to the seller
+/// - Persists `Status::CooperativelyCanceled` with a conditional write, so a
+///   concurrent terminal transition leaves exactly one winner
+/// - Publishes the order event and notifies both parties
+///
+/// Mostro never stores the signatures: with `P_M` in the same process they
+/// would meet the 2-of-3 threshold.
+///
+/// The fee refund (§4) needs the TA-1f fee token, which is not stored yet.
+async fn cancel_cooperative_execution_cashu(
+    ctx: &AppContext,
+    event: &UnwrappedMessage,
+    request_id: Option<u64>,
+    order: Order,
+    counterparty_pubkey: String,
+    my_keys: &Keys,
+    cashu_signatures: Option<Vec<CashuProofSignature>>,
+) -> Result<(), MostroError> {
+    let pool = ctx.pool();
+    let token = order
+        .cashu_escrow_token
+        .as_deref()
+        .ok_or(MostroCantDo(CantDoReason::CashuEscrowNotLocked))?;
+    let buyer_pubkey = order.get_buyer_pubkey().map_err(MostroInternalErr)?;
+    let seller_pubkey = order.get_seller_pubkey().map_err(MostroInternalErr)?;
+
+    // Without the buyer's signatures the seller could only wait out the
+    // escrow locktime to reclaim its own funds, so the cancel is refused.
+    let signatures = if event.sender == buyer_pubkey {
+        let sigs = cashu_signatures.ok_or(MostroCantDo(CantDoReason::InvalidSignature))?;
+        check_buyer_cancel_signatures(&order, token, buyer_pubkey, &sigs)?;
+        Some(sigs)
+    } else {
+        if cashu_signatures.is_some() {
+            return Err(MostroCantDo(CantDoReason::InvalidPayload));
+        }
+        None
+    };
+
+    let order = update_order_event(my_keys, Status::CooperativelyCanceled, &order)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+    if !persist_cashu_cooperative_cancel(pool, &order).await? {
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+
+    let counterparty_pubkey = PublicKey::from_str(&counterparty_pubkey)
+        .map_err(|_| MostroInternalErr(ServiceError::InvalidPubkey))?;
+    // Only the seller's copy carries the buyer's confirming signatures.
+    let counterparty_payload = signatures.map(Payload::CashuSignatures);
+    enqueue_order_msg(
+        request_id,
+        Some(order.id),
+        Action::CooperativeCancelAccepted,
+        None,
+        event.sender,
+        None,
+    )
+    .await;
+    enqueue_order_msg(
+        None,
+        Some(order.id),
+        Action::CooperativeCancelAccepted,
+        counterparty_payload,
+        counterparty_pubkey,
+        None,
+    )
+    .await;
+    info!(
+        "Cancel: Order Id {} canceled cooperatively, seller {} reclaims the escrow",
+        order.id, seller_pubkey
+    );
+
+    close_dispute_after_user_resolution(
+        ctx,
+        &order,
+        DisputeStatus::SellerRefunded,
+        my_keys,
+        "cooperative cancel",
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Checks that `sigs` are the buyer's signatures over every proof of the
+/// escrow `token`.
+fn check_buyer_cancel_signatures(
+    order: &Order,
+    token: &str,
+    buyer_pubkey: PublicKey,
+    sigs: &[CashuProofSignature],
+) -> Result<(), MostroError> {
+    let p_b = cashu_pubkey_from_xonly_hex(&buyer_pubkey.to_string())
+        .map_err(|_| MostroCantDo(CantDoReason::InvalidPubkey))?;
+    let pairs: Vec<(String, String)> = sigs
+        .iter()
+        .map(|s| (s.secret.clone(), s.signature.clone()))
+        .collect();
+    if let Err(e) = verify_escrow_signatures(token, p_b, &pairs) {
+        warn!(
+            "Cooperative cancel: Order Id {}: rejected buyer signatures: {}",
+            order.id, e
+        );
+        return Err(MostroCantDo(CantDoReason::InvalidSignature));
+    }
+    Ok(())
+}
+
+/// Step 1 of a cooperative cancel in Cashu mode: a buyer starting the cancel
+/// must send its signatures, which are checked and returned to be relayed to
+/// the seller with the cancel request; a seller starting it sends none.
+///
+/// Signing is the buyer's consent to refund the seller, so the seller may
+/// reclaim the escrow before confirming. Relaying them at once is what lets
+/// Mostro avoid holding them until the confirmation.
+fn check_cashu_step_1_signatures(
+    event: &UnwrappedMessage,
+    order: &Order,
+    cashu_signatures: Option<Vec<CashuProofSignature>>,
+) -> Result<Option<Vec<CashuProofSignature>>, MostroError> {
+    let buyer_pubkey = order.get_buyer_pubkey().map_err(MostroInternalErr)?;
+    if event.sender != buyer_pubkey {
+        return match cashu_signatures {
+            Some(_) => Err(MostroCantDo(CantDoReason::InvalidPayload)),
+            None => Ok(None),
+        };
+    }
+    let token = order
+        .cashu_escrow_token
+        .as_deref()
+        .ok_or(MostroCantDo(CantDoReason::CashuEscrowNotLocked))?;
+    let sigs = cashu_signatures.ok_or(MostroCantDo(CantDoReason::InvalidSignature))?;
+    check_buyer_cancel_signatures(order, token, buyer_pubkey, &sigs)?;
+    Ok(Some(sigs))
+}
+
+/// Persist a Cashu cooperative cancel only while the trade is still live.
+///
+/// Returns `Ok(false)` when the status moved on concurrently (a release or
+/// an admin resolution won the row), in which case nothing was written.
+async fn persist_cashu_cooperative_cancel(
+    pool: &DbPool,
+    order: &Order,
+) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        r#"
+          UPDATE orders
+          SET status = $1, event_id = $2, buyer_cooperativecancel = $3,
+              seller_cooperativecancel = $4
+          WHERE id = $5 AND status IN ($6, $7, $8)
+        "#,
+    )
+    .bind(&order.status)
+    .bind(&order.event_id)
+    .bind(order.buyer_cooperativecancel)
+    .bind(order.seller_cooperativecancel)
+    .bind(order.id)
+    .bind(Status::Active.to_string())
+    .bind(Status::FiatSent.to_string())
+    .bind(Status::Dispute.to_string())
+    .execute(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+
+    if result.rows_affected() == 0 {
+        warn!(
+            "Order {} not transitioned to cooperatively-canceled: status changed concurrently",
+            order.id
+        );
+        return Ok(false);
+    }
+    crate::rpc::events::order_status_changed(order.id, &order.status);
+    Ok(true)
+}
+
 /// Step 1 of a cooperative cancel flow: first party signals intent.
 ///
 /// - Records the initiator's pubkey
-/// - Notifies both parties so the counterparty can confirm (step 2)
+/// - Notifies both parties so the counterparty can confirm (step 2); the
+///   counterparty's copy carries `counterparty_payload` (a Cashu buyer's
+///   signatures)
 async fn cancel_cooperative_execution_step_1(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     event: &UnwrappedMessage,
     mut order: Order,
     counterparty_pubkey: String,
     request_id: Option<u64>,
+    counterparty_payload: Option<Payload>,
 ) -> Result<(), MostroError> {
     order.cancel_initiator_pubkey = Some(event.sender.to_string());
     // update db
@@ -190,7 +402,7 @@ async fn cancel_cooperative_execution_step_1(
         None,
         Some(order.id),
         Action::CooperativeCancelInitiatedByPeer,
-        None,
+        counterparty_payload,
         counterparty_pubkey,
         None,
     )
@@ -209,8 +421,8 @@ async fn cancel_cooperative_execution_step_1(
 /// (no other bonds remain after the release); otherwise the order
 /// stays in `Pending` with the surviving bonds still in flight and
 /// the cancel is effectively scoped to a per-taker release + message.
-async fn cancel_order_by_taker<L: CancelLightning + Send>(
-    pool: &Pool<Sqlite>,
+async fn cancel_order_by_taker<L: CancelLightning + Send + ?Sized>(
+    pool: &DbPool,
     event: &UnwrappedMessage,
     order: Order,
     my_keys: &Keys,
@@ -275,8 +487,8 @@ async fn cancel_order_by_taker<L: CancelLightning + Send>(
     .await
 }
 
-async fn cancel_order_by_taker_inner<L: CancelLightning + Send>(
-    pool: &Pool<Sqlite>,
+async fn cancel_order_by_taker_inner<L: CancelLightning + Send + ?Sized>(
+    pool: &DbPool,
     event: &UnwrappedMessage,
     mut order: Order,
     my_keys: &Keys,
@@ -308,6 +520,7 @@ async fn cancel_order_by_taker_inner<L: CancelLightning + Send>(
     update_order_to_initial_state(pool, order.id, order.amount, order.fee, order.dev_fee)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::order_status_changed(order.id, &Status::Pending.to_string());
 
     // Clean stored pubkeys for this order; republish will set them anew.
     let order = edit_pubkeys_order(pool, &order)
@@ -335,8 +548,8 @@ async fn cancel_order_by_taker_inner<L: CancelLightning + Send>(
 /// - Publishes `Status::Canceled` and persists it
 /// - Cancels any hold invoice
 /// - Notifies both parties
-async fn cancel_order_by_maker<L: CancelLightning + Send>(
-    pool: &Pool<Sqlite>,
+async fn cancel_order_by_maker<L: CancelLightning + Send + ?Sized>(
+    pool: &DbPool,
     event: &UnwrappedMessage,
     order: Order,
     taker_pubkey: PublicKey,
@@ -346,10 +559,11 @@ async fn cancel_order_by_maker<L: CancelLightning + Send>(
 ) -> Result<(), MostroError> {
     // We publish a new replaceable kind nostr event with the status updated
     if let Ok(order_updated) = update_order_event(my_keys, Status::Canceled, &order).await {
-        order_updated
+        let order_updated = order_updated
             .update(pool)
             .await
             .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+        crate::rpc::events::order_status_changed(order_updated.id, &order_updated.status);
     }
     // Cancel hold invoice if present
     if let Some(hash) = &order.hash {
@@ -392,7 +606,7 @@ async fn cancel_order_by_maker<L: CancelLightning + Send>(
 /// This updates the replaceable event to `Status::Canceled`, persists it, and
 /// notifies the maker. No invoice is involved yet in this state.
 async fn cancel_pending_order_from_maker(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     event: &UnwrappedMessage,
     order: &mut Order,
     my_keys: &Keys,
@@ -489,17 +703,17 @@ async fn cancel_pending_order_from_maker(
 ///
 /// The database connection pool and other dependencies are extracted from `ctx`.
 /// Internal routing logic is delegated to `cancel_action_generic`.
-pub async fn cancel_action(
+pub async fn cancel_action<L: CancelLightning + Send + ?Sized>(
     ctx: &AppContext,
     msg: Message,
     event: &UnwrappedMessage,
     my_keys: &Keys,
-    ln_client: &mut LndConnector,
+    ln_client: &mut L,
 ) -> Result<(), MostroError> {
     cancel_action_generic(ctx, msg, event, my_keys, ln_client).await
 }
 
-async fn cancel_action_generic<L: CancelLightning + Send>(
+async fn cancel_action_generic<L: CancelLightning + Send + ?Sized>(
     ctx: &AppContext,
     msg: Message,
     event: &UnwrappedMessage,
@@ -509,6 +723,7 @@ async fn cancel_action_generic<L: CancelLightning + Send>(
     let pool = ctx.pool();
     // Get request id
     let request_id = msg.get_inner_message_kind().request_id;
+    let cashu_signatures = cancel_cashu_signatures(&msg);
     // Get order id
     let mut order = get_order(&msg, pool).await?;
 
@@ -590,7 +805,16 @@ async fn cancel_action_generic<L: CancelLightning + Send>(
             cancel_not_active_order(pool, event, order, my_keys, request_id, ln_client).await?
         }
         Status::Active | Status::FiatSent | Status::Dispute => {
-            cancel_active_order(ctx, event, order, my_keys, request_id, ln_client).await?
+            cancel_active_order(
+                ctx,
+                event,
+                order,
+                my_keys,
+                request_id,
+                ln_client,
+                cashu_signatures,
+            )
+            .await?
         }
         _ => return Err(MostroCantDo(CantDoReason::NotAllowedByStatus)),
     }
@@ -602,13 +826,14 @@ async fn cancel_action_generic<L: CancelLightning + Send>(
 ///
 /// Marks which side initiated the cooperative cancel and either starts the flow
 /// (step 1) or completes it (step 2) when both sides have acknowledged.
-async fn cancel_active_order<L: CancelLightning + Send>(
+async fn cancel_active_order<L: CancelLightning + Send + ?Sized>(
     ctx: &AppContext,
     event: &UnwrappedMessage,
     mut order: Order,
     my_keys: &Keys,
     request_id: Option<u64>,
     ln_client: &mut L,
+    cashu_signatures: Option<Vec<CashuProofSignature>>,
 ) -> Result<(), MostroError> {
     let pool = ctx.pool();
     // Get seller and buyer pubkey
@@ -644,16 +869,23 @@ async fn cancel_active_order<L: CancelLightning + Send>(
                 counterparty_pubkey,
                 my_keys,
                 ln_client,
+                cashu_signatures,
             )
             .await?;
         }
         None => {
+            let signatures = if Settings::is_cashu_enabled() {
+                check_cashu_step_1_signatures(event, &order, cashu_signatures)?
+            } else {
+                None
+            };
             cancel_cooperative_execution_step_1(
                 pool,
                 event,
                 order,
                 counterparty_pubkey,
                 request_id,
+                signatures.map(Payload::CashuSignatures),
             )
             .await?;
         }
@@ -665,8 +897,8 @@ async fn cancel_active_order<L: CancelLightning + Send>(
 ///
 /// If the maker sent the event, run the maker path; otherwise, only the taker
 /// can cancel. This ensures the correct party authorization for early cancels.
-async fn cancel_not_active_order<L: CancelLightning + Send>(
-    pool: &Pool<Sqlite>,
+async fn cancel_not_active_order<L: CancelLightning + Send + ?Sized>(
+    pool: &DbPool,
     event: &UnwrappedMessage,
     order: Order,
     my_keys: &Keys,
@@ -718,9 +950,9 @@ async fn cancel_not_active_order<L: CancelLightning + Send>(
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-    use mostro_core::db::Crud;
+    use crate::db::Crud;
+    use crate::db::DbPool;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
     /// Build an `UnwrappedMessage` whose trade key (rumor author / `sender`)
@@ -803,11 +1035,7 @@ mod tests {
 
     #[tokio::test]
     async fn cancel_action_with_ctx_rejects_non_creator_for_pending_order() {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         let ctx = TestContextBuilder::new()
             .with_pool(pool)
             .with_settings(test_settings())
@@ -844,8 +1072,7 @@ mod tests {
     #[tokio::test]
     async fn pending_maker_cancel_preserves_concurrently_promoted_taker_context() {
         set_global_config();
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
 
         let maker = Keys::generate().public_key();
         let taker = Keys::generate().public_key();
@@ -858,8 +1085,8 @@ mod tests {
         // Externally apply what the bond subscriber's promotion writes
         // (status still pre-trade: the escrow is not stored yet).
         sqlx::query(
-            "UPDATE orders SET status = 'waiting-taker-bond', buyer_pubkey = ?1, \
-             master_buyer_pubkey = ?2 WHERE id = ?3",
+            "UPDATE orders SET status = 'waiting-taker-bond', buyer_pubkey = $1, \
+             master_buyer_pubkey = $2 WHERE id = $3",
         )
         .bind(taker.to_string())
         .bind(taker.to_string())
@@ -893,8 +1120,7 @@ mod tests {
     #[tokio::test]
     async fn pending_maker_cancel_loses_once_the_take_committed() {
         set_global_config();
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
 
         let maker = Keys::generate().public_key();
         let taker = Keys::generate().public_key();
@@ -903,7 +1129,7 @@ mod tests {
             .await
             .unwrap();
         // The take committed: escrow stored, status moved on.
-        sqlx::query("UPDATE orders SET status = 'waiting-payment', hash = ?1 WHERE id = ?2")
+        sqlx::query("UPDATE orders SET status = 'waiting-payment', hash = $1 WHERE id = $2")
             .bind("ab".repeat(32))
             .bind(snapshot.id)
             .execute(&pool)
@@ -940,11 +1166,7 @@ mod tests {
     #[tokio::test]
     async fn pending_taker_with_active_bond_is_not_routed_as_intruder() {
         use crate::app::bond::db::find_active_bonds_for_order;
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
 
         let maker = Keys::generate().public_key();
         let taker = Keys::generate().public_key();
@@ -985,16 +1207,12 @@ mod tests {
         );
     }
 
-    async fn setup_pool() -> Arc<SqlitePool> {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+    async fn setup_pool() -> Arc<DbPool> {
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         pool
     }
 
-    fn build_ctx(pool: Arc<SqlitePool>) -> AppContext {
+    fn build_ctx(pool: Arc<DbPool>) -> AppContext {
         TestContextBuilder::new()
             .with_pool(pool)
             .with_settings(test_settings())
@@ -1015,7 +1233,7 @@ mod tests {
     /// race with a different pool; tests relying on this therefore pin
     /// `master_*_pubkey == trade pubkey` so the rating lookup falls back
     /// to `(0.0, 0, 0)` deterministically regardless of which pool won.
-    fn set_global_db_pool(pool: &Arc<SqlitePool>) {
+    fn set_global_db_pool(pool: &Arc<DbPool>) {
         let _ = crate::config::DB_POOL.set(pool.clone());
     }
 
@@ -1027,9 +1245,7 @@ mod tests {
     /// Other tests push to the same queue concurrently, so callers must
     /// only assert on destinations built from this test's fresh keys.
     async fn queued_actions_for(destination: PublicKey) -> Vec<Action> {
-        crate::config::MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(_, pk)| *pk == destination)
@@ -1037,15 +1253,11 @@ mod tests {
             .collect()
     }
 
-    async fn order_by_id(pool: &SqlitePool, id: uuid::Uuid) -> Order {
+    async fn order_by_id(pool: &DbPool, id: uuid::Uuid) -> Order {
         Order::by_id(pool, id).await.unwrap().unwrap()
     }
 
-    async fn insert_requested_taker_bond(
-        pool: &SqlitePool,
-        order_id: uuid::Uuid,
-        pubkey: &PublicKey,
-    ) {
+    async fn insert_requested_taker_bond(pool: &DbPool, order_id: uuid::Uuid, pubkey: &PublicKey) {
         // `hash: None` keeps `release_bond` off the LND connect path.
         let bond = crate::app::bond::Bond::new_requested(
             order_id,
@@ -1593,6 +1805,48 @@ mod tests {
             .contains(&Action::CooperativeCancelAccepted));
     }
 
+    #[tokio::test]
+    async fn cooperative_cancel_of_active_order_refunds_the_held_invoice() {
+        use crate::escrow::memory::MemoryEscrow;
+        use fedimint_tonic_lnd::lnrpc::invoice::InvoiceState;
+
+        set_global_config();
+        let pool = setup_pool().await;
+        let ctx = build_ctx(pool.clone());
+        let maker = Keys::generate().public_key();
+        let taker = Keys::generate().public_key();
+        let mut escrow = MemoryEscrow::new();
+        let (_, _, hash) = escrow.create_hold_invoice("trade", 1_000).await.unwrap();
+        let hash = crate::util::bytes_to_string(&hash);
+        escrow.accept(&hash);
+
+        let mut order = create_pending_order(maker, taker);
+        order.status = Status::Active.to_string();
+        order.cancel_initiator_pubkey = Some(taker.to_string());
+        order.buyer_cooperativecancel = true;
+        order.hash = Some(hash.clone());
+        let order = order.create(ctx.pool()).await.unwrap();
+
+        // The seller (maker) confirms through the `dyn EscrowBackend` entry
+        // point the event loop uses.
+        let backend: &mut dyn EscrowBackend = &mut escrow;
+        let result = cancel_action(
+            &ctx,
+            cancel_msg(order.id),
+            &create_unwrapped_message_with_pubkey(maker),
+            &Keys::generate(),
+            backend,
+        )
+        .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(escrow.invoice_state(&hash), Some(InvoiceState::Canceled));
+        assert_eq!(
+            order_by_id(ctx.pool(), order.id).await.status,
+            Status::CooperativelyCanceled.to_string()
+        );
+    }
+
     #[tokio::test]
     async fn cancel_active_order_rejects_preexisting_stranger_initiator() {
         set_global_config();
@@ -1679,4 +1933,279 @@ mod tests {
             Err(MostroInternalErr(ServiceError::InvalidPubkey))
         ));
     }
+
+    /// A locked Cashu trade in `Active`, seller-maker, buyer-taker, whose
+    /// escrow token is bound to the parties' trade keys. The buyer has
+    /// already initiated the cooperative cancel unless `initiator` says
+    /// otherwise.
+    async fn create_locked_cashu_order(
+        pool: &DbPool,
+        seller: &Keys,
+        buyer: &Keys,
+        initiator: PublicKey,
+    ) -> Order {
+        let to_cashu =
+            |pk: PublicKey| crate::cashu::cashu_pubkey_from_xonly_hex(&pk.to_string()).unwrap();
+        let token = crate::cashu::test_utils::escrow_token(
+            to_cashu(buyer.public_key()),
+            to_cashu(seller.public_key()),
+            to_cashu(Keys::generate().public_key()),
+            2_000_000_000,
+        );
+        let mut order = create_pending_order(seller.public_key(), buyer.public_key());
+        order.status = Status::Active.to_string();
+        order.cancel_initiator_pubkey = Some(initiator.to_string());
+        order.cashu_escrow_token = Some(token);
+        order.cashu_escrow_locked_at = Some(Timestamp::now().as_secs() as i64);
+        order.create(pool).await.unwrap()
+    }
+
+    fn buyer_signatures(order: &Order, buyer: &Keys) -> Vec<CashuProofSignature> {
+        crate::cashu::test_utils::sign_escrow_proofs(
+            order.cashu_escrow_token.as_deref().unwrap(),
+            &buyer.secret_key().to_secret_hex(),
+        )
+        .into_iter()
+        .map(|(secret, signature)| CashuProofSignature::new(secret, signature))
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn cashu_cooperative_cancel_relays_buyer_signatures_to_seller() {
+        set_global_config();
+        let pool = setup_pool().await;
+        let ctx = build_ctx(pool.clone());
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+        let order =
+            create_locked_cashu_order(ctx.pool(), &seller, &buyer, seller.public_key()).await;
+        let sigs = buyer_signatures(&order, &buyer);
+
+        // The buyer confirms the seller-initiated cancel with its signatures.
+        let event = create_unwrapped_message_with_pubkey(buyer.public_key());
+        cancel_cooperative_execution_cashu(
+            &ctx,
+            &event,
+            Some(1),
+            order.clone(),
+            seller.public_key().to_string(),
+            &Keys::generate(),
+            Some(sigs.clone()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            order_by_id(ctx.pool(), order.id).await.status,
+            Status::CooperativelyCanceled.to_string()
+        );
+        let relayed = crate::outbox::test_utils::queued("order")
+            .await
+            .iter()
+            .find(|(_, pk)| *pk == seller.public_key())
+            .map(|(m, _)| m.get_inner_message_kind().clone())
+            .expect("seller notified");
+        assert_eq!(relayed.action, Action::CooperativeCancelAccepted);
+        assert!(
+            matches!(relayed.payload, Some(Payload::CashuSignatures(ref got)) if *got == sigs),
+            "the buyer's signatures must reach the seller"
+        );
+        assert!(queued_actions_for(buyer.public_key())
+            .await
+            .contains(&Action::CooperativeCancelAccepted));
+    }
+
+    #[tokio::test]
+    async fn cashu_cooperative_cancel_rejects_bad_or_misplaced_signatures() {
+        set_global_config();
+        let pool = setup_pool().await;
+        let ctx = build_ctx(pool.clone());
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+
+        // Signatures by anyone but the buyer never reach the seller.
+        let order =
+            create_locked_cashu_order(ctx.pool(), &seller, &buyer, seller.public_key()).await;
+        let forged = buyer_signatures(&order, &Keys::generate());
+        let event = create_unwrapped_message_with_pubkey(buyer.public_key());
+        let result = cancel_cooperative_execution_cashu(
+            &ctx,
+            &event,
+            None,
+            order.clone(),
+            seller.public_key().to_string(),
+            &Keys::generate(),
+            Some(forged),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MostroCantDo(CantDoReason::InvalidSignature))
+        ));
+
+        // The seller confirming cannot attach signatures either.
+        let order =
+            create_locked_cashu_order(ctx.pool(), &seller, &buyer, buyer.public_key()).await;
+        let sigs = buyer_signatures(&order, &buyer);
+        let event = create_unwrapped_message_with_pubkey(seller.public_key());
+        let result = cancel_cooperative_execution_cashu(
+            &ctx,
+            &event,
+            None,
+            order.clone(),
+            buyer.public_key().to_string(),
+            &Keys::generate(),
+            Some(sigs),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MostroCantDo(CantDoReason::InvalidPayload))
+        ));
+        assert_eq!(
+            order_by_id(ctx.pool(), order.id).await.status,
+            Status::Active.to_string(),
+            "a rejected confirmation must not cancel the trade"
+        );
+    }
+
+    #[tokio::test]
+    async fn cashu_cooperative_cancel_started_by_the_buyer_relays_its_signatures_at_once() {
+        set_global_config();
+        let pool = setup_pool().await;
+        let ctx = build_ctx(pool.clone());
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+        let mut order =
+            create_locked_cashu_order(ctx.pool(), &seller, &buyer, buyer.public_key()).await;
+        order.cancel_initiator_pubkey = None;
+        let buyer_event = create_unwrapped_message_with_pubkey(buyer.public_key());
+        let seller_event = create_unwrapped_message_with_pubkey(seller.public_key());
+
+        // Step 1: the buyer must sign, the seller must not.
+        assert!(matches!(
+            check_cashu_step_1_signatures(&buyer_event, &order, None),
+            Err(MostroCantDo(CantDoReason::InvalidSignature))
+        ));
+        let sigs = buyer_signatures(&order, &buyer);
+        assert!(matches!(
+            check_cashu_step_1_signatures(&seller_event, &order, Some(sigs.clone())),
+            Err(MostroCantDo(CantDoReason::InvalidPayload))
+        ));
+        assert!(check_cashu_step_1_signatures(&seller_event, &order, None)
+            .unwrap()
+            .is_none());
+
+        let checked = check_cashu_step_1_signatures(&buyer_event, &order, Some(sigs.clone()))
+            .unwrap()
+            .map(Payload::CashuSignatures);
+        cancel_cooperative_execution_step_1(
+            ctx.pool(),
+            &buyer_event,
+            order.clone(),
+            seller.public_key().to_string(),
+            Some(1),
+            checked,
+        )
+        .await
+        .unwrap();
+        let request = crate::outbox::test_utils::queued("order")
+            .await
+            .iter()
+            .find(|(_, pk)| *pk == seller.public_key())
+            .map(|(m, _)| m.get_inner_message_kind().clone())
+            .expect("seller notified");
+        assert_eq!(request.action, Action::CooperativeCancelInitiatedByPeer);
+        assert!(
+            matches!(request.payload, Some(Payload::CashuSignatures(ref got)) if *got == sigs),
+            "the buyer's signatures must reach the seller with the request"
+        );
+
+        // The seller already holds them: its confirmation completes the cancel.
+        let order = order_by_id(ctx.pool(), order.id).await;
+        cancel_cooperative_execution_cashu(
+            &ctx,
+            &seller_event,
+            Some(2),
+            order.clone(),
+            buyer.public_key().to_string(),
+            &Keys::generate(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            order_by_id(ctx.pool(), order.id).await.status,
+            Status::CooperativelyCanceled.to_string()
+        );
+        assert!(queued_actions_for(seller.public_key())
+            .await
+            .contains(&Action::CooperativeCancelAccepted));
+    }
+
+    #[tokio::test]
+    async fn cashu_cooperative_cancel_confirmed_by_the_buyer_needs_its_signatures() {
+        set_global_config();
+        let pool = setup_pool().await;
+        let ctx = build_ctx(pool.clone());
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+        let order =
+            create_locked_cashu_order(ctx.pool(), &seller, &buyer, seller.public_key()).await;
+
+        let event = create_unwrapped_message_with_pubkey(buyer.public_key());
+        let result = cancel_cooperative_execution_cashu(
+            &ctx,
+            &event,
+            None,
+            order.clone(),
+            seller.public_key().to_string(),
+            &Keys::generate(),
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MostroCantDo(CantDoReason::InvalidSignature))
+        ));
+        assert_eq!(
+            order_by_id(ctx.pool(), order.id).await.status,
+            Status::Active.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn cashu_cooperative_cancel_loses_to_a_concurrent_terminal_transition() {
+        set_global_config();
+        let pool = setup_pool().await;
+        let ctx = build_ctx(pool.clone());
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+        let order =
+            create_locked_cashu_order(ctx.pool(), &seller, &buyer, buyer.public_key()).await;
+
+        // A release lands between the handler's read and its write.
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
+            .bind(Status::SettledHoldInvoice.to_string())
+            .bind(order.id)
+            .execute(ctx.pool())
+            .await
+            .unwrap();
+
+        let event = create_unwrapped_message_with_pubkey(seller.public_key());
+        let result = cancel_cooperative_execution_cashu(
+            &ctx,
+            &event,
+            None,
+            order.clone(),
+            buyer.public_key().to_string(),
+            &Keys::generate(),
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MostroCantDo(CantDoReason::NotAllowedByStatus))
+        ));
+        assert_eq!(
+            order_by_id(ctx.pool(), order.id).await.status,
+            Status::SettledHoldInvoice.to_string()
+        );
+        assert!(queued_actions_for(seller.public_key()).await.is_empty());
+    }
 }
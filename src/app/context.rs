@@ -8,11 +8,9 @@
 
 use crate::cashu::CashuClient;
 use crate::config::settings::Settings;
-use mostro_core::prelude::Message;
-use nostr_sdk::prelude::{Client, Keys, PublicKey};
-use sqlx::{Pool, Sqlite};
+use crate::db::DbPool;
+use nostr_sdk::prelude::{Client, Keys};
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 /// Shared application context passed to all handler functions.
 ///
@@ -29,15 +27,11 @@ use tokio::sync::RwLock;
 /// let pool = ctx.pool();
 /// let settings = ctx.settings();
 /// ```
-/// Shared queue type used for outbound order messages.
-pub type OrderMsgQueue = Arc<RwLock<Vec<(Message, PublicKey)>>>;
-
 #[derive(Clone)]
 pub struct AppContext {
-    pool: Arc<Pool<Sqlite>>,
+    pool: Arc<DbPool>,
     nostr_client: Client,
     settings: Arc<Settings>,
-    order_msg_queue: OrderMsgQueue,
     keys: Keys,
     /// Connected Cashu mint client (docs/cashu/, CF-5). `Some` only in Cashu
     /// mode, where `main.rs` connects the configured mint at boot and attaches
@@ -52,17 +46,15 @@ impl AppContext {
     /// The Cashu client defaults to `None` (Lightning mode). Cashu-mode boot
     /// attaches a connected client with [`AppContext::with_cashu_client`].
     pub fn new(
-        pool: Arc<Pool<Sqlite>>,
+        pool: Arc<DbPool>,
         nostr_client: Client,
         settings: Arc<Settings>,
-        order_msg_queue: OrderMsgQueue,
         keys: Keys,
     ) -> Self {
         Self {
             pool,
             nostr_client,
             settings,
-            order_msg_queue,
             keys,
             cashu_client: None,
         }
@@ -76,13 +68,23 @@ impl AppContext {
         self
     }
 
+    /// This context as seen by another Mostro identity (`[[instances]]`):
+    /// its keys and its settings, everything else shared.
+    pub fn for_instance(&self, instance: &crate::instance::Instance) -> Self {
+        Self {
+            settings: instance.settings.clone(),
+            keys: instance.keys.clone(),
+            ..self.clone()
+        }
+    }
+
     /// Database connection pool.
-    pub fn pool(&self) -> &Pool<Sqlite> {
+    pub fn pool(&self) -> &DbPool {
         &self.pool
     }
 
     /// Cloned database connection pool (`Arc`) for `'static` tasks/spawns.
-    pub fn pool_arc(&self) -> Arc<Pool<Sqlite>> {
+    pub fn pool_arc(&self) -> Arc<DbPool> {
         self.pool.clone()
     }
 
@@ -96,11 +98,6 @@ impl AppContext {
         &self.settings
     }
 
-    /// Shared queue for outbound order messages.
-    pub fn order_msg_queue(&self) -> &OrderMsgQueue {
-        &self.order_msg_queue
-    }
-
     /// Mostro's Nostr signing keys.
     ///
     /// Parsed once at startup from `settings.nostr.nsec_privkey`.
@@ -129,42 +126,6 @@ pub mod test_utils {
     };
     use secrecy::SecretString;
 
-    /// Test helper wrapper for inspecting the shared order-message queue.
-    #[derive(Debug, Clone)]
-    pub struct MockOrderMsgQueue {
-        queue: OrderMsgQueue,
-    }
-
-    impl MockOrderMsgQueue {
-        pub fn new() -> Self {
-            Self {
-                queue: Arc::new(RwLock::new(Vec::new())),
-            }
-        }
-
-        pub fn queue(&self) -> OrderMsgQueue {
-            self.queue.clone()
-        }
-
-        pub async fn len(&self) -> usize {
-            self.queue.read().await.len()
-        }
-
-        pub async fn is_empty(&self) -> bool {
-            self.queue.read().await.is_empty()
-        }
-
-        pub async fn clear(&self) {
-            self.queue.write().await.clear();
-        }
-    }
-
-    impl Default for MockOrderMsgQueue {
-        fn default() -> Self {
-            Self::new()
-        }
-    }
-
     /// Builder for creating test contexts with mock/test dependencies.
     ///
     /// # Example
@@ -176,11 +137,9 @@ pub mod test_utils {
     ///     .build();
     /// ```
     pub struct TestContextBuilder {
-        pool: Option<Arc<Pool<Sqlite>>>,
+        pool: Option<Arc<DbPool>>,
         nostr_client: Option<Client>,
         settings: Option<Arc<Settings>>,
-        order_msg_queue: Option<OrderMsgQueue>,
-        mock_order_msg_queue: Option<MockOrderMsgQueue>,
         keys: Option<Keys>,
     }
 
@@ -190,14 +149,12 @@ pub mod test_utils {
                 pool: None,
                 nostr_client: None,
                 settings: None,
-                order_msg_queue: None,
-                mock_order_msg_queue: None,
                 keys: None,
             }
         }
 
         /// Use a specific database pool (e.g., in-memory SQLite for tests).
-        pub fn with_pool(mut self, pool: Arc<Pool<Sqlite>>) -> Self {
+        pub fn with_pool(mut self, pool: Arc<DbPool>) -> Self {
             self.pool = Some(pool);
             self
         }
@@ -214,20 +171,6 @@ pub mod test_utils {
             self
         }
 
-        /// Use a specific order message queue.
-        pub fn with_order_msg_queue(mut self, queue: OrderMsgQueue) -> Self {
-            self.order_msg_queue = Some(queue);
-            self.mock_order_msg_queue = None;
-            self
-        }
-
-        /// Attach a mock order-message queue helper for test assertions.
-        pub fn with_mock_order_msg_queue(mut self, mock: MockOrderMsgQueue) -> Self {
-            self.order_msg_queue = Some(mock.queue());
-            self.mock_order_msg_queue = Some(mock);
-            self
-        }
-
         /// Use specific keys for tests.
         pub fn with_keys(mut self, keys: Keys) -> Self {
             self.keys = Some(keys);
@@ -253,10 +196,6 @@ pub mod test_utils {
                 .settings
                 .expect("TestContextBuilder requires with_settings() — Settings has no Default");
 
-            let order_msg_queue = self
-                .order_msg_queue
-                .unwrap_or_else(|| Arc::new(RwLock::new(Vec::new())));
-
             let mut settings = Arc::try_unwrap(settings).unwrap_or_else(|arc| (*arc).clone());
 
             let keys = match self.keys {
@@ -270,14 +209,7 @@ pub mod test_utils {
 
             let settings = Arc::new(settings);
 
-            AppContext::new(pool, nostr_client, settings, order_msg_queue, keys)
-        }
-
-        /// Build context plus mock handles used for assertions.
-        pub fn build_with_mocks(self) -> (AppContext, Option<MockOrderMsgQueue>) {
-            let mock_order_msg_queue = self.mock_order_msg_queue.clone();
-            let ctx = self.build();
-            (ctx, mock_order_msg_queue)
+            AppContext::new(pool, nostr_client, settings, keys)
         }
     }
 
@@ -292,6 +224,7 @@ pub mod test_utils {
         Settings {
             database: DatabaseSettings {
                 url: "sqlite::memory:".to_string(),
+                ..Default::default()
             },
             nostr: NostrSettings {
                 // Valid test nsec from src/config/mod.rs tests
@@ -299,6 +232,7 @@ pub mod test_utils {
                     "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
                 ),
                 relays: vec!["wss://relay.test".to_string()],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: MostroSettings::default(),
             lightning: LightningSettings::default(),
@@ -306,35 +240,22 @@ pub mod test_utils {
             expiration: Some(ExpirationSettings::default()),
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::test_utils::{test_settings, MockOrderMsgQueue, TestContextBuilder};
-    use sqlx::SqlitePool;
+    use super::test_utils::{test_settings, TestContextBuilder};
     use std::sync::Arc;
 
-    #[tokio::test]
-    async fn builder_can_inject_mock_order_message_queue() {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        let mock_queue = MockOrderMsgQueue::new();
-
-        let (ctx, mock_order_queue) = TestContextBuilder::new()
-            .with_pool(pool)
-            .with_settings(test_settings())
-            .with_mock_order_msg_queue(mock_queue)
-            .build_with_mocks();
-
-        let queue = mock_order_queue.expect("mock queue should be present");
-        assert!(Arc::ptr_eq(&queue.queue(), ctx.order_msg_queue()));
-    }
-
     #[tokio::test]
     async fn context_accessors_expose_injected_dependencies() {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         let keys = nostr_sdk::prelude::Keys::generate();
         let client = nostr_sdk::prelude::Client::default();
 
@@ -358,48 +279,4 @@ mod tests {
         // settings() exposes the injected settings.
         assert_eq!(ctx.settings().database.url, "sqlite::memory:");
     }
-
-    #[tokio::test]
-    async fn default_builders_and_mock_queue_helpers_work() {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-
-        // Default impls delegate to new().
-        let mock: super::test_utils::MockOrderMsgQueue = Default::default();
-        let builder: super::test_utils::TestContextBuilder = Default::default();
-
-        let ctx = builder
-            .with_pool(pool)
-            .with_settings(test_settings())
-            .with_mock_order_msg_queue(mock.clone())
-            .build();
-
-        // Fresh queue starts empty.
-        assert!(mock.is_empty().await);
-        assert_eq!(mock.len().await, 0);
-
-        // Push through the context handle, observe through the mock handle.
-        let msg = mostro_core::prelude::Message::new_restore(None);
-        let key = nostr_sdk::prelude::Keys::generate().public_key();
-        ctx.order_msg_queue().write().await.push((msg, key));
-        assert_eq!(mock.len().await, 1);
-        assert!(!mock.is_empty().await);
-
-        // clear() drains it again.
-        mock.clear().await;
-        assert!(mock.is_empty().await);
-    }
-
-    #[tokio::test]
-    async fn builder_can_use_custom_queue() {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        let queue: super::OrderMsgQueue = Arc::new(tokio::sync::RwLock::new(Vec::new()));
-
-        let ctx = TestContextBuilder::new()
-            .with_pool(pool)
-            .with_settings(test_settings())
-            .with_order_msg_queue(queue.clone())
-            .build();
-
-        assert!(Arc::ptr_eq(&queue, ctx.order_msg_queue()));
-    }
 }
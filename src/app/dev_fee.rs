@@ -61,11 +61,12 @@
 
 use crate::config::constants::DEV_FEE_LIGHTNING_ADDRESS;
 use crate::db::find_unpaid_dev_fees;
+use crate::escrow::EscrowBackend;
 use crate::lightning::invoice::{decode_invoice, validate_payout_invoice};
-use crate::lightning::LndConnector;
 use crate::lnurl::resolv_ln_address;
 use crate::util::{bytes_to_string, publish_dev_fee_audit_event};
 
+use crate::db::DbPool;
 use chrono::Utc;
 use mostro_core::error::MostroError;
 use mostro_core::error::MostroError::MostroInternalErr;
@@ -73,7 +74,6 @@ use mostro_core::error::ServiceError;
 use mostro_core::order::Order;
 use nostr_sdk::prelude::ToBech32;
 use nostr_sdk::prelude::{Keys, PublicKey};
-use sqlx::SqlitePool;
 use std::collections::HashSet;
 use tokio::sync::mpsc::channel;
 use tracing::{error, info, warn};
@@ -86,8 +86,8 @@ use tracing::{error, info, warn};
 /// phase can rely on the DB state left by the previous one.
 #[mutants::skip]
 pub async fn run_dev_fee_cycle(
-    pool: &SqlitePool,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     confirmed: &mut HashSet<uuid::Uuid>,
     keys: &Keys,
 ) {
@@ -103,7 +103,7 @@ pub async fn run_dev_fee_cycle(
 
 /// Reset PENDING markers older than `CLEANUP_TTL_SECS` so those orders
 /// become eligible for a fresh payment attempt on the next cycle.
-async fn cleanup_stale_pending_markers(pool: &SqlitePool) {
+async fn cleanup_stale_pending_markers(pool: &DbPool) {
     const CLEANUP_TTL_SECS: u64 = 300; // 5 minutes
     let now_unix = Utc::now().timestamp() as u64;
 
@@ -159,8 +159,8 @@ async fn cleanup_stale_pending_markers(pool: &SqlitePool) {
         // Targeted update: only touch dev fee columns, and only if marker still matches.
         match sqlx::query(
             "UPDATE orders
-             SET dev_fee_paid = 0, dev_fee_payment_hash = NULL
-             WHERE id = ? AND dev_fee_payment_hash = ?",
+             SET dev_fee_paid = FALSE, dev_fee_payment_hash = NULL
+             WHERE id = $1 AND dev_fee_payment_hash = $2",
         )
         .bind(order_id)
         .bind(marker)
@@ -203,13 +203,13 @@ async fn cleanup_stale_pending_markers(pool: &SqlitePool) {
 /// payment actually succeeded on the LN node. On daemon restart the
 /// `confirmed` set is empty so every paid order gets re‑checked once.
 async fn verify_confirmed_orders(
-    pool: &SqlitePool,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     confirmed: &mut HashSet<uuid::Uuid>,
 ) {
     let real_hash_orders = match sqlx::query_as::<_, Order>(
         "SELECT * FROM orders
-         WHERE dev_fee_paid = 1
+         WHERE dev_fee_paid = TRUE
            AND dev_fee_payment_hash IS NOT NULL
            AND dev_fee_payment_hash NOT LIKE 'PENDING-%'
            AND (status = 'settled-hold-invoice' OR status = 'success')",
@@ -259,15 +259,15 @@ async fn verify_confirmed_orders(
 /// the PRIMARY defense against duplicate payments (#620): reuse the
 /// existing hash instead of resolving a new LNURL invoice.
 async fn recover_partial_payments(
-    pool: &SqlitePool,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     confirmed: &mut HashSet<uuid::Uuid>,
 ) {
     let hash_orders = match sqlx::query_as::<_, Order>(
         "SELECT * FROM orders
          WHERE (status = 'settled-hold-invoice' OR status = 'success')
            AND dev_fee > 0
-           AND dev_fee_paid = 0
+           AND dev_fee_paid = FALSE
            AND dev_fee_payment_hash IS NOT NULL
            AND dev_fee_payment_hash != ''
            AND dev_fee_payment_hash NOT LIKE 'PENDING-%'",
@@ -300,8 +300,8 @@ async fn recover_partial_payments(
                 // Targeted update: mark paid only, without touching unrelated order columns.
                 match sqlx::query(
                     "UPDATE orders
-                     SET dev_fee_paid = 1
-                     WHERE id = ? AND dev_fee_paid = 0 AND dev_fee_payment_hash = ?",
+                     SET dev_fee_paid = TRUE
+                     WHERE id = $1 AND dev_fee_paid = FALSE AND dev_fee_payment_hash = $2",
                 )
                 .bind(order_id)
                 .bind(&existing_hash)
@@ -312,7 +312,7 @@ async fn recover_partial_payments(
                         if result.rows_affected() > 0 {
                             confirmed.insert(order_id);
                             if let Ok(updated) =
-                                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+                                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
                                     .bind(order_id)
                                     .fetch_one(pool)
                                     .await
@@ -328,14 +328,14 @@ async fn recover_partial_payments(
                             }
                         } else {
                             // Already updated (or hash changed). If it's already paid, confirm it.
-                            if let Ok(is_paid) = sqlx::query_scalar::<_, i32>(
-                                "SELECT dev_fee_paid FROM orders WHERE id = ?",
+                            if let Ok(is_paid) = sqlx::query_scalar::<_, bool>(
+                                "SELECT dev_fee_paid FROM orders WHERE id = $1",
                             )
                             .bind(order_id)
                             .fetch_one(pool)
                             .await
                             {
-                                if is_paid != 0 {
+                                if is_paid {
                                     confirmed.insert(order_id);
                                 }
                             }
@@ -358,7 +358,7 @@ async fn recover_partial_payments(
                 if let Err(e) = sqlx::query(
                     "UPDATE orders
                      SET dev_fee_payment_hash = NULL
-                     WHERE id = ? AND dev_fee_paid = 0 AND dev_fee_payment_hash = ?",
+                     WHERE id = $1 AND dev_fee_paid = FALSE AND dev_fee_payment_hash = $2",
                 )
                 .bind(order_id)
                 .bind(&existing_hash)
@@ -392,8 +392,8 @@ async fn recover_partial_payments(
 /// Claim, resolve LNURL invoice, store hash, and send payment for orders
 /// that have no existing payment hash.
 async fn process_new_dev_fee_payments(
-    pool: &SqlitePool,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     confirmed: &mut HashSet<uuid::Uuid>,
     keys: &Keys,
 ) {
@@ -471,8 +471,8 @@ async fn process_new_dev_fee_payments(
         // Targeted update: store real hash only if we still own the PENDING marker.
         let updated = match sqlx::query(
             "UPDATE orders
-             SET dev_fee_payment_hash = ?
-             WHERE id = ? AND dev_fee_paid = 0 AND dev_fee_payment_hash = ?",
+             SET dev_fee_payment_hash = $1
+             WHERE id = $2 AND dev_fee_paid = FALSE AND dev_fee_payment_hash = $3",
         )
         .bind(&payment_hash_hex)
         .bind(order_id)
@@ -499,7 +499,7 @@ async fn process_new_dev_fee_payments(
             continue;
         }
 
-        let order = match sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+        let order = match sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
             .bind(order_id)
             .fetch_one(pool)
             .await
@@ -541,7 +541,7 @@ async fn process_new_dev_fee_payments(
 
 async fn handle_payment_success(
     mut order: Order,
-    pool: &SqlitePool,
+    pool: &DbPool,
     confirmed: &mut HashSet<uuid::Uuid>,
     payment_hash: &str,
 ) {
@@ -562,8 +562,8 @@ async fn handle_payment_success(
 
     match sqlx::query(
         "UPDATE orders
-         SET dev_fee_paid = 1, dev_fee_payment_hash = ?
-         WHERE id = ? AND dev_fee_paid = 0",
+         SET dev_fee_paid = TRUE, dev_fee_payment_hash = $1
+         WHERE id = $2 AND dev_fee_paid = FALSE",
     )
     .bind(payment_hash)
     .bind(order_id)
@@ -584,12 +584,12 @@ async fn handle_payment_success(
             if result.rows_affected() == 0 {
                 // Already updated concurrently; still consider it confirmed if DB shows paid.
                 if let Ok(is_paid) =
-                    sqlx::query_scalar::<_, i32>("SELECT dev_fee_paid FROM orders WHERE id = ?")
+                    sqlx::query_scalar::<_, bool>("SELECT dev_fee_paid FROM orders WHERE id = $1")
                         .bind(order_id)
                         .fetch_one(pool)
                         .await
                 {
-                    if is_paid != 0 {
+                    if is_paid {
                         confirmed.insert(order_id);
                     }
                 }
@@ -600,7 +600,7 @@ async fn handle_payment_success(
             confirmed.insert(order_id);
 
             if let Ok(verified_order) =
-                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
                     .bind(order_id)
                     .fetch_one(pool)
                     .await
@@ -625,7 +625,7 @@ async fn handle_payment_success(
 
 async fn handle_payment_failure(
     _order: Order,
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: uuid::Uuid,
     e: &MostroError,
 ) {
@@ -643,8 +643,8 @@ async fn handle_payment_failure(
     // Targeted no-op-ish update: never revert paid orders to unpaid.
     if let Err(db_err) = sqlx::query(
         "UPDATE orders
-         SET dev_fee_paid = 0
-         WHERE id = ? AND dev_fee_paid = 0",
+         SET dev_fee_paid = FALSE
+         WHERE id = $1 AND dev_fee_paid = FALSE",
     )
     .bind(order_id)
     .execute(pool)
@@ -659,8 +659,8 @@ async fn handle_payment_failure(
 
 async fn handle_payment_timeout(
     order: Order,
-    pool: &SqlitePool,
-    ln_client: &mut LndConnector,
+    pool: &DbPool,
+    ln_client: &mut dyn EscrowBackend,
     confirmed: &mut HashSet<uuid::Uuid>,
 ) {
     let order_id = order.id;
@@ -678,8 +678,8 @@ async fn handle_payment_timeout(
             );
             if let Err(e) = sqlx::query(
                 "UPDATE orders
-                 SET dev_fee_paid = 1
-                 WHERE id = ? AND dev_fee_paid = 0 AND dev_fee_payment_hash = ?",
+                 SET dev_fee_paid = TRUE
+                 WHERE id = $1 AND dev_fee_paid = FALSE AND dev_fee_payment_hash = $2",
             )
             .bind(order_id)
             .bind(order.dev_fee_payment_hash.as_deref().unwrap_or_default())
@@ -707,8 +707,8 @@ async fn handle_payment_timeout(
             // Targeted reset: only clear hash if it still matches, and only while unpaid.
             if let Err(e) = sqlx::query(
                 "UPDATE orders
-                 SET dev_fee_paid = 0, dev_fee_payment_hash = NULL
-                 WHERE id = ? AND dev_fee_paid = 0 AND dev_fee_payment_hash = ?",
+                 SET dev_fee_paid = FALSE, dev_fee_payment_hash = NULL
+                 WHERE id = $1 AND dev_fee_paid = FALSE AND dev_fee_payment_hash = $2",
             )
             .bind(order_id)
             .bind(order.dev_fee_payment_hash.as_deref().unwrap_or_default())
@@ -734,10 +734,10 @@ async fn handle_payment_timeout(
 
 /// Release a PENDING claim back to NULL using exact marker match (safe release).
 /// Logs if the UPDATE fails (e.g. transient DB error).
-async fn release_pending_claim(pool: &SqlitePool, order_id: uuid::Uuid, pending_marker: &str) {
+async fn release_pending_claim(pool: &DbPool, order_id: uuid::Uuid, pending_marker: &str) {
     if let Err(e) = sqlx::query(
         "UPDATE orders SET dev_fee_payment_hash = NULL
-         WHERE id = ? AND dev_fee_payment_hash = ?",
+         WHERE id = $1 AND dev_fee_payment_hash = $2",
     )
     .bind(order_id)
     .bind(pending_marker)
@@ -768,7 +768,7 @@ enum DevFeePaymentState {
 /// Returns the current payment state so the caller can decide what to do.
 async fn check_dev_fee_payment_status(
     order: &Order,
-    ln_client: &mut LndConnector,
+    ln_client: &mut dyn EscrowBackend,
 ) -> DevFeePaymentState {
     use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
 
@@ -797,14 +797,15 @@ async fn check_dev_fee_payment_status(
 
     match tokio::time::timeout(
         std::time::Duration::from_secs(10),
-        ln_client.check_payment_status(&payment_hash_bytes),
+        ln_client.lookup_payment_status(&payment_hash_bytes),
     )
     .await
     {
         Ok(Ok(status)) => match status {
-            PaymentStatus::Succeeded => DevFeePaymentState::Succeeded,
-            PaymentStatus::InFlight => DevFeePaymentState::InFlight,
-            PaymentStatus::Failed => DevFeePaymentState::Failed,
+            Some(PaymentStatus::Succeeded) => DevFeePaymentState::Succeeded,
+            Some(PaymentStatus::InFlight) => DevFeePaymentState::InFlight,
+            Some(PaymentStatus::Failed) => DevFeePaymentState::Failed,
+            // The node has no record of the hash, or reports it as unknown.
             _ => DevFeePaymentState::Unknown,
         },
         Ok(Err(e)) => {
@@ -833,16 +834,16 @@ async fn check_dev_fee_payment_status(
 /// `Ok(false)` when the order was already claimed (rows_affected == 0),
 /// and `Err` on database errors.
 pub(crate) async fn try_claim_order_for_dev_fee(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: uuid::Uuid,
     pending_marker: &str,
 ) -> Result<bool, MostroError> {
     let result = sqlx::query(
-        "UPDATE orders SET dev_fee_payment_hash = ?
-         WHERE id = ?
+        "UPDATE orders SET dev_fee_payment_hash = $1
+         WHERE id = $2
            AND (status = 'settled-hold-invoice' OR status = 'success')
            AND dev_fee > 0
-           AND dev_fee_paid = 0
+           AND dev_fee_paid = FALSE
            AND (dev_fee_payment_hash IS NULL OR dev_fee_payment_hash = '')",
     )
     .bind(pending_marker)
@@ -973,7 +974,7 @@ pub async fn resolve_dev_fee_invoice(
 pub async fn send_dev_fee_payment(
     order: &Order,
     payment_request: &str,
-    ln_client: &mut LndConnector,
+    ln_client: &mut dyn EscrowBackend,
 ) -> Result<String, MostroError> {
     use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
 
@@ -1065,8 +1066,8 @@ mod tests {
     };
     use crate::config::settings::Settings;
     use crate::config::MOSTRO_CONFIG;
+    use crate::db::DbPool;
     use mostro_core::error::MostroError;
-    use sqlx::sqlite::SqlitePoolOptions;
     use std::collections::HashSet;
 
     fn init_test_settings() {
@@ -1081,6 +1082,7 @@ mod tests {
                     "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
                 ),
                 relays: vec![],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: Default::default(),
             lightning: Default::default(),
@@ -1088,38 +1090,20 @@ mod tests {
             expiration: Some(Default::default()),
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         });
     }
 
-    async fn setup_orders_db() -> sqlx::SqlitePool {
+    async fn setup_orders_db() -> DbPool {
         init_test_settings();
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .expect("Failed to create in-memory DB");
-
-        sqlx::query(include_str!("../../migrations/20221222153301_orders.sql"))
-            .execute(&pool)
-            .await
-            .expect("Failed to create base orders table");
-        sqlx::query(include_str!("../../migrations/20251126120000_dev_fee.sql"))
-            .execute(&pool)
-            .await
-            .expect("Failed to apply dev_fee migration");
-        sqlx::query(include_str!(
-            "../../migrations/20260530120000_cashu_escrow_fields.sql"
-        ))
-        .execute(&pool)
-        .await
-        .expect("Failed to apply cashu escrow migration");
-
-        pool
+        crate::db::test_utils::migrated_pool().await
     }
 
     async fn insert_test_order(
-        pool: &sqlx::SqlitePool,
+        pool: &DbPool,
         id: uuid::Uuid,
         status: &str,
         dev_fee: i64,
@@ -1131,13 +1115,13 @@ mod tests {
             INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                                 amount, fiat_code, fiat_amount, created_at, expires_at,
                                 dev_fee, dev_fee_paid, dev_fee_payment_hash)
-            VALUES (?, 'sell', 'evt1', ?, 0, 'cash', 1000, 'USD', 100, 0, 0, ?, ?, ?)
+            VALUES ($1, 'sell', 'evt1', $2, 0, 'cash', 1000, 'USD', 100, 0, 0, $3, $4, $5)
             "#,
         )
         .bind(id)
         .bind(status)
         .bind(dev_fee)
-        .bind(dev_fee_paid as i32)
+        .bind(dev_fee_paid)
         .bind(dev_fee_payment_hash)
         .execute(pool)
         .await
@@ -1156,7 +1140,7 @@ mod tests {
         assert!(claimed, "Should successfully claim an unclaimed order");
 
         let hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(order_id)
                 .fetch_one(&pool)
                 .await
@@ -1187,7 +1171,7 @@ mod tests {
         );
 
         let hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(order_id)
                 .fetch_one(&pool)
                 .await
@@ -1312,25 +1296,25 @@ mod tests {
 
         cleanup_stale_pending_markers(&pool).await;
 
-        let fresh: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let fresh: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(fresh_id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        assert_eq!(fresh.0, 0);
+        assert!(!fresh.0);
         assert_eq!(
             fresh.1.as_deref(),
             Some("PENDING-550e8400-e29b-41d4-a716-446655440000-9999999999")
         );
 
-        let stale: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let stale: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(stale_id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        assert_eq!(stale.0, 0);
+        assert!(!stale.0);
         assert_eq!(stale.1, None);
     }
 
@@ -1351,7 +1335,7 @@ mod tests {
         // Wrong marker should not change anything.
         release_pending_claim(&pool, id, "PENDING-other-marker").await;
         let hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1361,7 +1345,7 @@ mod tests {
         // Exact marker clears the hash.
         release_pending_claim(&pool, id, "PENDING-test-marker").await;
         let hash_after: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1376,18 +1360,19 @@ mod tests {
         let pool = setup_orders_db().await;
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, Some("PENDING-marker")).await;
-        sqlx::query(
-            "CREATE TRIGGER fail_orders_update BEFORE UPDATE ON orders \
-             BEGIN SELECT RAISE(ABORT, 'forced failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_orders_update",
+            "BEFORE UPDATE ON orders",
+            None,
+            "forced failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         release_pending_claim(&pool, id, "PENDING-marker").await;
 
         let hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1407,7 +1392,7 @@ mod tests {
         let order_id = uuid::Uuid::new_v4();
         insert_test_order(&pool, order_id, "success", 100, false, None).await;
 
-        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
             .bind(order_id)
             .fetch_one(&pool)
             .await
@@ -1416,14 +1401,14 @@ mod tests {
         let mut confirmed = HashSet::new();
         handle_payment_success(order, &pool, &mut confirmed, "deadbeef").await;
 
-        let row: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let row: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(order_id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
 
-        assert_eq!(row.0, 1);
+        assert!(row.0);
         assert_eq!(row.1.as_deref(), Some("deadbeef"));
         assert!(confirmed.contains(&order_id));
     }
@@ -1445,7 +1430,7 @@ mod tests {
         )
         .await;
 
-        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
             .bind(order_id)
             .fetch_one(&pool)
             .await
@@ -1457,14 +1442,14 @@ mod tests {
 
         handle_payment_failure(order, &pool, order_id, &err).await;
 
-        let row: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let row: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(order_id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
 
-        assert_eq!(row.0, 0);
+        assert!(!row.0);
         assert_eq!(row.1.as_deref(), Some("existing-hash"));
     }
 
@@ -1487,14 +1472,14 @@ mod tests {
         .await;
 
         // Stale snapshot (simulates dev-fee loop holding an Order across awaits).
-        let stale_copy = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+        let stale_copy = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
             .bind(order_id)
             .fetch_one(&pool)
             .await
             .unwrap();
 
         // Concurrently, buyer payment succeeds and status is advanced.
-        sqlx::query("UPDATE orders SET status = 'success' WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = 'success' WHERE id = $1")
             .bind(order_id)
             .execute(&pool)
             .await
@@ -1504,8 +1489,8 @@ mod tests {
         let mut confirmed = HashSet::new();
         handle_payment_success(stale_copy, &pool, &mut confirmed, "deadbeef").await;
 
-        let row: (String, i32, Option<String>) = sqlx::query_as(
-            "SELECT status, dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?",
+        let row: (String, bool, Option<String>) = sqlx::query_as(
+            "SELECT status, dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1",
         )
         .bind(order_id)
         .fetch_one(&pool)
@@ -1516,7 +1501,7 @@ mod tests {
             row.0, "success",
             "status must not be overwritten by dev-fee write"
         );
-        assert_eq!(row.1, 1);
+        assert!(row.1);
         assert_eq!(row.2.as_deref(), Some("deadbeef"));
         assert!(confirmed.contains(&order_id));
     }
@@ -1603,13 +1588,13 @@ mod tests {
 
         cleanup_stale_pending_markers(&pool).await;
 
-        let row: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let row: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        assert_eq!(row.0, 0);
+        assert!(!row.0);
         assert_eq!(row.1, None, "legacy marker must be cleared");
     }
 
@@ -1628,16 +1613,17 @@ mod tests {
             Some("PENDING-550e8400-e29b-41d4-a716-446655440000-1"),
         )
         .await;
-        sqlx::query(
-            "CREATE TRIGGER fail_orders_update BEFORE UPDATE ON orders \
-             BEGIN SELECT RAISE(ABORT, 'forced failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_orders_update",
+            "BEFORE UPDATE ON orders",
+            None,
+            "forced failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
         cleanup_stale_pending_markers(&pool).await;
         let hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1646,10 +1632,7 @@ mod tests {
 
         // SELECT failure: no orders table at all → phase returns early.
         let pool2 = setup_orders_db().await;
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool2)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool2, "orders").await;
         cleanup_stale_pending_markers(&pool2).await;
     }
 
@@ -1685,30 +1668,28 @@ mod tests {
         )
         .await;
 
-        sqlx::query(
-            "CREATE TRIGGER simulate_concurrent_claim AFTER UPDATE OF dev_fee_payment_hash ON orders
-             WHEN NEW.dev_fee_payment_hash IS NULL
-             BEGIN
-                 UPDATE orders SET dev_fee_payment_hash = 'PENDING-raced-9999999999'
-                 WHERE dev_fee_payment_hash LIKE 'PENDING-%' AND id != NEW.id;
-             END;",
+        crate::db::test_utils::create_trigger(
+            &pool,
+            "simulate_concurrent_claim",
+            "AFTER UPDATE OF dev_fee_payment_hash ON orders",
+            Some("NEW.dev_fee_payment_hash IS NULL"),
+            "UPDATE orders SET dev_fee_payment_hash = 'PENDING-raced-9999999999' \
+             WHERE dev_fee_payment_hash LIKE 'PENDING-%' AND id != NEW.id",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         cleanup_stale_pending_markers(&pool).await;
 
         let first_hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(first_id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
         assert_eq!(first_hash, None, "first stale order resets normally");
 
-        let raced: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let raced: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(raced_id)
                 .fetch_one(&pool)
                 .await
@@ -1718,7 +1699,7 @@ mod tests {
             Some("PENDING-raced-9999999999"),
             "concurrently-changed marker must be left untouched, not reset"
         );
-        assert_eq!(raced.0, 0);
+        assert!(!raced.0);
     }
 
     #[tokio::test]
@@ -1751,10 +1732,7 @@ mod tests {
         assert!(!confirmed.contains(&unknown_hex));
 
         // Query-error arm: drop the table and re-run.
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "orders").await;
         verify_confirmed_orders(&pool, &mut ln, &mut confirmed).await;
     }
 
@@ -1770,21 +1748,18 @@ mod tests {
         let mut confirmed = HashSet::new();
         recover_partial_payments(&pool, &mut ln, &mut confirmed).await;
 
-        let row: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let row: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        assert_eq!(row.0, 0);
+        assert!(!row.0);
         assert_eq!(row.1.as_deref(), Some(VALID_HEX_HASH));
         assert!(confirmed.is_empty());
 
         // Query-error arm.
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "orders").await;
         recover_partial_payments(&pool, &mut ln, &mut confirmed).await;
     }
 
@@ -1794,7 +1769,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, Some(VALID_HEX_HASH)).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1805,7 +1780,7 @@ mod tests {
         handle_payment_timeout(order, &pool, &mut ln, &mut confirmed).await;
 
         let hash: Option<String> =
-            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = ?")
+            sqlx::query_scalar("SELECT dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1822,7 +1797,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, Some("PENDING-x-1")).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1842,25 +1817,26 @@ mod tests {
         let pool = setup_orders_db().await;
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, None).await;
-        sqlx::query(
-            "CREATE TRIGGER fail_orders_update BEFORE UPDATE ON orders \
-             BEGIN SELECT RAISE(ABORT, 'forced failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_orders_update",
+            "BEFORE UPDATE ON orders",
+            None,
+            "forced failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let mut ln = dead_lnd().await;
         let mut confirmed = HashSet::new();
         process_new_dev_fee_payments(&pool, &mut ln, &mut confirmed, &Keys::generate()).await;
 
-        let row: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let row: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        assert_eq!(row.0, 0);
+        assert!(!row.0);
         assert_eq!(row.1, None);
         assert!(confirmed.is_empty());
     }
@@ -1868,10 +1844,7 @@ mod tests {
     #[tokio::test]
     async fn process_new_payments_query_failure_returns_early() {
         let pool = setup_orders_db().await;
-        sqlx::query("DROP TABLE orders")
-            .execute(&pool)
-            .await
-            .unwrap();
+        crate::db::test_utils::drop_table(&pool, "orders").await;
         let mut ln = dead_lnd().await;
         let mut confirmed = HashSet::new();
         process_new_dev_fee_payments(&pool, &mut ln, &mut confirmed, &Keys::generate()).await;
@@ -1884,7 +1857,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, Some("stored-hash")).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1893,13 +1866,13 @@ mod tests {
         let mut confirmed = HashSet::new();
         handle_payment_success(order, &pool, &mut confirmed, "lnd-hash").await;
 
-        let row: (i32, Option<String>) =
-            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = ?")
+        let row: (bool, Option<String>) =
+            sqlx::query_as("SELECT dev_fee_paid, dev_fee_payment_hash FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        assert_eq!(row.0, 1);
+        assert!(row.0);
         assert_eq!(row.1.as_deref(), Some("lnd-hash"), "LND's hash wins");
         assert!(confirmed.contains(&id));
     }
@@ -1913,7 +1886,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, true, Some("deadbeef")).await;
         let mut order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -1932,18 +1905,19 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, None).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        sqlx::query(
-            "CREATE TRIGGER fail_orders_update BEFORE UPDATE ON orders \
-             BEGIN SELECT RAISE(ABORT, 'forced failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_orders_update",
+            "BEFORE UPDATE ON orders",
+            None,
+            "forced failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let mut confirmed = HashSet::new();
         handle_payment_success(order, &pool, &mut confirmed, "deadbeef").await;
@@ -1959,18 +1933,19 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, Some("h")).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        sqlx::query(
-            "CREATE TRIGGER fail_orders_update BEFORE UPDATE ON orders \
-             BEGIN SELECT RAISE(ABORT, 'forced failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_orders_update",
+            "BEFORE UPDATE ON orders",
+            None,
+            "forced failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let err = MostroError::MostroInternalErr(ServiceError::UnexpectedError("x".into()));
         handle_payment_failure(order, &pool, id, &err).await;
@@ -1982,7 +1957,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 0, false, None).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -2017,7 +1992,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 0, false, None).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -2038,7 +2013,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         insert_test_order(&pool, id, "success", 100, false, None).await;
         let order =
-            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = ?")
+            sqlx::query_as::<_, mostro_core::order::Order>("SELECT * FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
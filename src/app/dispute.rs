@@ -3,13 +3,21 @@
 //! and publish dispute events to the network.
 
 use crate::app::context::AppContext;
-use crate::db::find_dispute_by_order_id;
+use crate::app::evidence::submit_evidence_action;
+use crate::cashu::{escrow_token_locktime, sign_with_pm, EscrowSpendState};
+use crate::config::settings::Settings;
+use crate::db::DbPool;
+use crate::db::{
+    ensure_dispute_finalize_permission, find_dispute_by_order_id, is_assigned_solver,
+    is_dispute_taken_by_admin,
+};
 use crate::nip33::{create_dispute_event_tags, new_dispute_event};
 use crate::util::{enqueue_order_msg, get_order};
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
 
-use mostro_core::db::Crud;
+use crate::db::Crud;
+use std::str::FromStr;
 use uuid::Uuid;
 
 /// Publishes a dispute event to the Nostr network.
@@ -79,20 +87,133 @@ fn get_counterpart_info(sender: &str, buyer: &str, seller: &str) -> Result<bool,
 ///
 /// Checks that:
 /// - The order exists
-/// - The order status allows disputes (Active or FiatSent)
+/// - The order status allows disputes (Active or FiatSent; in Cashu mode also
+///   SettledHoldInvoice, where the seller released but the buyer may never
+///   have received a usable signature)
 async fn get_valid_order(ctx: &AppContext, msg: &Message) -> Result<Order, MostroError> {
     // Try to fetch the order from the database
     let order = get_order(msg, ctx.pool()).await?;
 
-    // Check if the order status is Active or FiatSent
-    if order.check_status(Status::Active).is_err() && order.check_status(Status::FiatSent).is_err()
-    {
+    if !dispute_allowed_from(&order, Settings::is_cashu_enabled()) {
         return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
     }
 
     Ok(order)
 }
 
+fn dispute_allowed_from(order: &Order, cashu_mode: bool) -> bool {
+    order.check_status(Status::Active).is_ok()
+        || order.check_status(Status::FiatSent).is_ok()
+        || (cashu_mode && order.check_status(Status::SettledHoldInvoice).is_ok())
+}
+
+/// Gate shared by every dispute ruling (`AdminSettle`, `AdminCancel`): the
+/// caller must be the solver assigned to the order's dispute, and one with
+/// write permission.
+pub(crate) async fn authorize_dispute_finalizer(
+    pool: &DbPool,
+    event: &UnwrappedMessage,
+    my_keys: &Keys,
+    order_id: Uuid,
+) -> Result<(), MostroError> {
+    match is_assigned_solver(pool, &event.identity.to_string(), order_id).await {
+        Ok(false) => {
+            // Check if admin has taken over the dispute
+            if is_dispute_taken_by_admin(pool, order_id, &my_keys.public_key().to_string()).await? {
+                return Err(MostroCantDo(CantDoReason::DisputeTakenByAdmin));
+            } else {
+                return Err(MostroCantDo(CantDoReason::IsNotYourDispute));
+            }
+        }
+        Err(e) => {
+            return Err(MostroInternalErr(ServiceError::DbAccessError(
+                e.to_string(),
+            )));
+        }
+        _ => {}
+    }
+
+    ensure_dispute_finalize_permission(
+        pool,
+        &event.identity.to_string(),
+        &my_keys.public_key().to_string(),
+        order_id,
+    )
+    .await
+}
+
+/// What a Cashu ruling is decided on (Track D §4B): the stored escrow token,
+/// its state at the mint and its seller-recovery locktime.
+pub(crate) struct CashuEscrowSnapshot {
+    pub token: String,
+    pub state: EscrowSpendState,
+    pub locktime: u64,
+}
+
+/// Read a disputed order's escrow and ask the mint about it. A never-locked
+/// order is `CashuEscrowNotLocked`; an unreachable mint is
+/// `CashuMintUnavailable`, so the solver can simply retry.
+pub(crate) async fn cashu_escrow_snapshot(
+    ctx: &AppContext,
+    order: &Order,
+) -> Result<CashuEscrowSnapshot, MostroError> {
+    let token = order
+        .cashu_escrow_token
+        .clone()
+        .ok_or(MostroCantDo(CantDoReason::CashuEscrowNotLocked))?;
+    let cashu_client = ctx.cashu_client().ok_or_else(|| {
+        MostroInternalErr(ServiceError::UnexpectedError(
+            "cashu client not initialised".to_string(),
+        ))
+    })?;
+    let locktime = escrow_token_locktime(&token)
+        .map_err(|e| MostroInternalErr(ServiceError::UnexpectedError(e.to_string())))?;
+    let state = cashu_client.escrow_spend_state(&token).await.map_err(|e| {
+        tracing::error!(
+            "Dispute: order {}: escrow state check failed: {}",
+            order.id,
+            e
+        );
+        MostroCantDo(CantDoReason::CashuMintUnavailable)
+    })?;
+    Ok(CashuEscrowSnapshot {
+        token,
+        state,
+        locktime,
+    })
+}
+
+/// Whether a Cashu ruling must hand the winner Mostro's `P_M` signatures,
+/// given what the mint reports for the escrow.
+///
+/// - `Unspent`: yes — the winner redeems with `P_M` and its own key
+/// - `Spent`: no — the escrow already moved, and every spend path needs the
+///   seller's key: a redeem by both parties before the locktime, or the
+///   seller's reclaim after it. The mint does not say who was paid, so the
+///   ruling is moot: it closes the dispute but marks no loser, and the order
+///   records it in `cashu_ruling_moot_at`
+/// - `Mixed`: refused, the escrow moved in a way the solver has to look at
+///   before anything is recorded
+pub(crate) fn cashu_ruling_needs_signature(state: EscrowSpendState) -> Result<bool, MostroError> {
+    match state {
+        EscrowSpendState::Unspent => Ok(true),
+        EscrowSpendState::Spent => Ok(false),
+        EscrowSpendState::Mixed => Err(MostroCantDo(CantDoReason::NotAllowedByStatus)),
+    }
+}
+
+/// Mostro's `P_M` signatures over a disputed escrow, for the winner of a
+/// ruling.
+pub(crate) fn sign_escrow_as_arbitrator(
+    token: &str,
+    my_keys: &Keys,
+) -> Result<Vec<CashuProofSignature>, MostroError> {
+    let p_m_secret = cdk::nuts::SecretKey::from_hex(my_keys.secret_key().to_secret_hex())
+        .map_err(|e| MostroInternalErr(ServiceError::UnexpectedError(e.to_string())))?;
+    sign_with_pm(token, &p_m_secret)
+        .map_err(|e| MostroInternalErr(ServiceError::UnexpectedError(e.to_string())))
+}
+
 async fn notify_dispute_to_users(
     dispute: &Dispute,
     msg: &Message,
@@ -145,8 +266,21 @@ pub async fn dispute_action(
     } else {
         return Err(MostroCantDo(CantDoReason::NotFound));
     };
+    let existing = find_dispute_by_order_id(pool, order_id).await.ok();
+    // A JSON text payload on an open dispute attaches evidence to it; on an
+    // order not in dispute yet it is ignored and the dispute is opened.
+    if let (Some(dispute), Some(Payload::TextMessage(json))) =
+        (&existing, &msg.get_inner_message_kind().payload)
+    {
+        if matches!(
+            DisputeStatus::from_str(&dispute.status),
+            Ok(DisputeStatus::Initiated | DisputeStatus::InProgress)
+        ) {
+            return submit_evidence_action(ctx, &msg, event, my_keys, json).await;
+        }
+    }
     // Check dispute for this order id is yet present.
-    if find_dispute_by_order_id(pool, order_id).await.is_ok() {
+    if existing.is_some() {
         return Err(MostroInternalErr(ServiceError::DisputeAlreadyExists));
     }
     // Get and validate order
@@ -183,6 +317,7 @@ pub async fn dispute_action(
         .create(pool)
         .await
         .map_err(|cause| MostroInternalErr(ServiceError::DbAccessError(cause.to_string())))?;
+    crate::rpc::events::dispute_changed(dispute.id, order_id, &dispute.status);
 
     // Get pubkeys of initiator and counterpart
     let (initiator_pubkey, counterpart_pubkey) = if is_buyer_dispute {
@@ -261,6 +396,16 @@ pub async fn close_dispute_after_user_resolution(
                 context,
                 order.id
             );
+            crate::rpc::events::dispute_changed(dispute_id, order.id, &new_status.to_string());
+            if let Err(e) = crate::dispute_sla::record_resolution(
+                pool,
+                dispute_id,
+                Timestamp::now().as_secs() as i64,
+            )
+            .await
+            {
+                tracing::warn!(%dispute_id, "dispute resolution time not recorded: {e}");
+            }
 
             // Determine who initiated the dispute for the event tag
             let dispute_initiator = match (order.seller_dispute, order.buyer_dispute) {
@@ -311,18 +456,15 @@ pub async fn close_dispute_after_user_resolution(
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-    use crate::config::MESSAGE_QUEUES;
+    use crate::db::DbPool;
     use nostr_sdk::prelude::Keys;
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
-    async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    fn build_ctx(pool: &SqlitePool) -> AppContext {
+    fn build_ctx(pool: &DbPool) -> AppContext {
         // The publish path reads the global config (event expiration);
         // seed it once, ignoring the error when another test already did.
         let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
@@ -363,6 +505,33 @@ mod tests {
         Message::new_order(order_id, Some(1), None, Action::Dispute, None)
     }
 
+    #[test]
+    fn cashu_ruling_signs_only_while_the_escrow_is_unspent() {
+        use crate::cashu::EscrowSpendState::*;
+        assert!(cashu_ruling_needs_signature(Unspent).unwrap());
+        // Reclaimed by the seller or redeemed by the winner: nothing to sign.
+        assert!(!cashu_ruling_needs_signature(Spent).unwrap());
+        assert!(matches!(
+            cashu_ruling_needs_signature(Mixed),
+            Err(MostroCantDo(CantDoReason::NotAllowedByStatus))
+        ));
+    }
+
+    #[test]
+    fn settled_hold_invoice_is_disputable_only_in_cashu_mode() {
+        let (buyer, seller) = (Keys::generate().public_key(), Keys::generate().public_key());
+        for status in [Status::Active, Status::FiatSent] {
+            let order = create_order(Some(buyer), Some(seller), status);
+            assert!(dispute_allowed_from(&order, false));
+            assert!(dispute_allowed_from(&order, true));
+        }
+        let released = create_order(Some(buyer), Some(seller), Status::SettledHoldInvoice);
+        assert!(!dispute_allowed_from(&released, false));
+        assert!(dispute_allowed_from(&released, true));
+        let done = create_order(Some(buyer), Some(seller), Status::Success);
+        assert!(!dispute_allowed_from(&done, true));
+    }
+
     #[test]
     fn get_counterpart_info_identifies_initiator_and_rejects_stranger() {
         let buyer = "buyer-pubkey";
@@ -588,7 +757,7 @@ mod tests {
         assert_eq!(dispute.order_previous_status, Status::Active.to_string());
 
         // Both parties were notified (queue is global; filter by order id)
-        let queue = MESSAGE_QUEUES.queue_order_msg.read().await;
+        let queue = crate::outbox::test_utils::queued("order").await;
         let notifications: Vec<_> = queue
             .iter()
             .filter(|(m, _)| m.get_inner_message_kind().id == Some(order.id))
@@ -602,6 +771,53 @@ mod tests {
         }));
     }
 
+    #[tokio::test]
+    async fn dispute_action_with_text_opens_a_dispute_not_yet_open() {
+        let pool = create_test_pool().await;
+        let ctx = build_ctx(&pool);
+        let buyer = Keys::generate().public_key();
+        let seller = Keys::generate().public_key();
+        let order = create_order(Some(buyer), Some(seller), Status::Active)
+            .create(&pool)
+            .await
+            .unwrap();
+        let with_text = || {
+            Message::new_order(
+                Some(order.id),
+                Some(1),
+                None,
+                Action::Dispute,
+                Some(Payload::TextMessage(
+                    r#"{"kind":"text","content":"x"}"#.into(),
+                )),
+            )
+        };
+
+        // Not evidence: there is no dispute to attach it to yet
+        let result =
+            dispute_action(&ctx, with_text(), &create_event(buyer), &Keys::generate()).await;
+        assert!(matches!(
+            result,
+            Err(MostroInternalErr(ServiceError::DisputeEventError))
+        ));
+        let dispute = find_dispute_by_order_id(&pool, order.id).await.unwrap();
+        assert_eq!(dispute.status, DisputeStatus::Initiated.to_string());
+
+        // Nor once the dispute is closed
+        sqlx::query("UPDATE disputes SET status = $1 WHERE id = $2")
+            .bind(DisputeStatus::SellerRefunded.to_string())
+            .bind(dispute.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let result =
+            dispute_action(&ctx, with_text(), &create_event(buyer), &Keys::generate()).await;
+        assert!(matches!(
+            result,
+            Err(MostroInternalErr(ServiceError::DisputeAlreadyExists))
+        ));
+    }
+
     #[tokio::test]
     async fn dispute_action_seller_initiated_flow_on_fiat_sent_order() {
         let pool = create_test_pool().await;
@@ -636,7 +852,7 @@ mod tests {
         let dispute = find_dispute_by_order_id(&pool, order.id).await.unwrap();
         assert_eq!(dispute.order_previous_status, Status::FiatSent.to_string());
 
-        let queue = MESSAGE_QUEUES.queue_order_msg.read().await;
+        let queue = crate::outbox::test_utils::queued("order").await;
         let notifications: Vec<_> = queue
             .iter()
             .filter(|(m, _)| m.get_inner_message_kind().id == Some(order.id))
@@ -0,0 +1,532 @@
+//! Dispute evidence.
+//!
+//! While a dispute is `initiated` or `in-progress`, its buyer and seller
+//! attach evidence by sending `dispute` on the disputed order with a JSON
+//! `Payload::TextMessage`:
+//!
+//! ```json
+//! {"kind": "receipt", "content": "<base64 or text>", "occurred_at": 1700000000}
+//! ```
+//!
+//! Items are stored in `dispute_evidence`, NIP-44 encrypted to the Mostro
+//! key that received them, with the SHA-256 of the plain content so the
+//! basis of a resolution can be audited later. The assigned solver receives
+//! every item when taking the dispute and new ones as they arrive, both
+//! through the outbox; admins list them with the `ListDisputeEvidence` RPC.
+
+use crate::app::context::AppContext;
+use crate::db::find_dispute_by_order_id;
+use crate::db::DbPool;
+use crate::util::{enqueue_dispute_msg, enqueue_order_msg, get_order};
+use bitcoin::hashes::{sha256, Hash};
+use mostro_core::prelude::*;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Largest `content` accepted, in bytes of plain text. NIP-44 takes at most
+/// 65 535 bytes of plain text, and an item forwarded to the solver is
+/// encrypted twice (seal, then gift wrap), each pass growing it by about a
+/// third: 32 KiB leaves room for both.
+pub const MAX_EVIDENCE_BYTES: usize = 32 * 1024;
+/// Items each party may attach to one dispute.
+pub const MAX_EVIDENCE_PER_PARTY: i64 = 20;
+
+/// Evidence kinds: free text, or a payment receipt given as content, as the
+/// hash of a document kept by the party, or both.
+const EVIDENCE_KINDS: [&str; 2] = ["text", "receipt"];
+
+/// JSON payload of an evidence submission.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EvidenceSubmission {
+    kind: String,
+    #[serde(default)]
+    content: String,
+    /// Hash of a receipt not sent as content.
+    sha256: Option<String>,
+    /// When the evidenced event happened, e.g. the payment time.
+    occurred_at: Option<i64>,
+}
+
+/// One stored evidence item, with its content decrypted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Evidence {
+    pub id: i64,
+    pub dispute_id: Uuid,
+    /// `buyer` or `seller`.
+    pub submitted_by: String,
+    pub kind: String,
+    pub content: String,
+    pub sha256: String,
+    pub occurred_at: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct EvidenceRow {
+    id: i64,
+    dispute_id: Uuid,
+    submitted_by: String,
+    kind: String,
+    content: String,
+    sha256: String,
+    occurred_at: Option<i64>,
+    key_pubkey: String,
+    created_at: i64,
+}
+
+fn db_err(e: sqlx::Error) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+fn invalid() -> MostroError {
+    MostroCantDo(CantDoReason::InvalidParameters)
+}
+
+/// Validates `json` and returns the kind, content and hash to store.
+fn parse_submission(json: &str) -> Result<(EvidenceSubmission, String), MostroError> {
+    let submission: EvidenceSubmission = serde_json::from_str(json).map_err(|_| invalid())?;
+    if !EVIDENCE_KINDS.contains(&submission.kind.as_str())
+        || submission.content.len() > MAX_EVIDENCE_BYTES
+        || submission.occurred_at.is_some_and(|ts| ts <= 0)
+    {
+        return Err(invalid());
+    }
+    let hash = match (&submission.sha256, submission.content.is_empty()) {
+        // A receipt kept by the party: only its hash is attached
+        (Some(hash), true) if submission.kind == "receipt" => {
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            hash.to_lowercase()
+        }
+        (None, false) => sha256::Hash::hash(submission.content.as_bytes()).to_string(),
+        _ => return Err(invalid()),
+    };
+    Ok((submission, hash))
+}
+
+/// Keys among this process's Mostro identities whose public key is `pubkey`,
+/// checking `known` first.
+pub fn keys_for(pubkey: &str, known: &Keys) -> Option<Keys> {
+    if known.public_key().to_hex() == pubkey {
+        return Some(known.clone());
+    }
+    crate::instance::all()
+        .iter()
+        .chain(crate::instance::retired())
+        .map(|instance| &instance.keys)
+        .find(|keys| keys.public_key().to_hex() == pubkey)
+        .cloned()
+}
+
+fn encrypt(keys: &Keys, content: &str) -> Result<String, MostroError> {
+    if content.is_empty() {
+        return Ok(String::new());
+    }
+    nip44::encrypt(
+        keys.secret_key(),
+        &keys.public_key(),
+        content,
+        nip44::Version::V2,
+    )
+    .map_err(|e| MostroInternalErr(ServiceError::EncryptionError(e.to_string())))
+}
+
+/// Evidence of `dispute_id`, oldest first, decrypted with `keys` or, for
+/// items received by another identity of this process, with that identity.
+pub async fn list_evidence(
+    pool: &DbPool,
+    dispute_id: Uuid,
+    keys: &Keys,
+) -> Result<Vec<Evidence>, MostroError> {
+    let rows = sqlx::query_as::<_, EvidenceRow>(
+        "SELECT id, dispute_id, submitted_by, kind, content, sha256, occurred_at, key_pubkey, \
+         created_at FROM dispute_evidence WHERE dispute_id = $1 ORDER BY id",
+    )
+    .bind(dispute_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    rows.into_iter()
+        .map(|row| {
+            let content = if row.content.is_empty() {
+                String::new()
+            } else {
+                let keys = keys_for(&row.key_pubkey, keys).ok_or_else(|| {
+                    MostroInternalErr(ServiceError::DecryptionError(format!(
+                        "no key {} for evidence {}",
+                        row.key_pubkey, row.id
+                    )))
+                })?;
+                nip44::decrypt(keys.secret_key(), &keys.public_key(), &row.content)
+                    .map_err(|e| MostroInternalErr(ServiceError::DecryptionError(e.to_string())))?
+            };
+            Ok(Evidence {
+                id: row.id,
+                dispute_id: row.dispute_id,
+                submitted_by: row.submitted_by,
+                kind: row.kind,
+                content,
+                sha256: row.sha256,
+                occurred_at: row.occurred_at,
+                created_at: row.created_at,
+            })
+        })
+        .collect()
+}
+
+/// Queues `items` for the solver, one message each.
+async fn send_to_solver(solver: PublicKey, items: &[Evidence]) -> Result<(), MostroError> {
+    for item in items {
+        let json = serde_json::to_string(item)
+            .map_err(|_| MostroInternalErr(ServiceError::MessageSerializationError))?;
+        enqueue_dispute_msg(
+            Some(item.dispute_id),
+            Action::Dispute,
+            Some(Payload::TextMessage(json)),
+            solver,
+        )
+        .await;
+    }
+    Ok(())
+}
+
+/// Sends the solver who just took `dispute_id` every item attached so far.
+pub async fn deliver_to_solver(
+    pool: &DbPool,
+    dispute_id: Uuid,
+    solver: PublicKey,
+    keys: &Keys,
+) -> Result<(), MostroError> {
+    let items = list_evidence(pool, dispute_id, keys).await?;
+    send_to_solver(solver, &items).await
+}
+
+/// Stores the evidence `json` sent by the buyer or seller of a disputed
+/// order and acknowledges it with its id and hash.
+pub async fn submit_evidence_action(
+    ctx: &AppContext,
+    msg: &Message,
+    event: &UnwrappedMessage,
+    my_keys: &Keys,
+    json: &str,
+) -> Result<(), MostroError> {
+    let pool = ctx.pool();
+    let order = get_order(msg, pool).await?;
+    let dispute = find_dispute_by_order_id(pool, order.id)
+        .await
+        .map_err(|_| MostroCantDo(CantDoReason::NotFound))?;
+    let status = DisputeStatus::from_str(&dispute.status)
+        .map_err(|_| MostroInternalErr(ServiceError::InvalidDisputeStatus))?;
+    if !matches!(status, DisputeStatus::Initiated | DisputeStatus::InProgress) {
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+
+    let sender = event.sender.to_string();
+    let submitted_by = if order.buyer_pubkey.as_deref() == Some(sender.as_str()) {
+        "buyer"
+    } else if order.seller_pubkey.as_deref() == Some(sender.as_str()) {
+        "seller"
+    } else {
+        return Err(MostroCantDo(CantDoReason::InvalidPubkey));
+    };
+
+    let (submission, hash) = parse_submission(json)?;
+
+    // Count and insert behind a write on the dispute row, which also
+    // rechecks that it is open: concurrent submissions queue on that row and
+    // cannot both take the last slot.
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    let open =
+        sqlx::query("UPDATE disputes SET status = status WHERE id = $1 AND status IN ($2, $3)")
+            .bind(dispute.id)
+            .bind(DisputeStatus::Initiated.to_string())
+            .bind(DisputeStatus::InProgress.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?
+            .rows_affected()
+            == 1;
+    if !open {
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+    let submitted: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM dispute_evidence WHERE dispute_id = $1 AND submitted_by = $2",
+    )
+    .bind(dispute.id)
+    .bind(submitted_by)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    if submitted >= MAX_EVIDENCE_PER_PARTY {
+        return Err(MostroCantDo(CantDoReason::TooManyRequests));
+    }
+
+    let now = Timestamp::now().as_secs() as i64;
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO dispute_evidence (dispute_id, order_id, submitted_by, kind, content, \
+         sha256, occurred_at, key_pubkey, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
+    )
+    .bind(dispute.id)
+    .bind(order.id)
+    .bind(submitted_by)
+    .bind(&submission.kind)
+    .bind(encrypt(my_keys, &submission.content)?)
+    .bind(&hash)
+    .bind(submission.occurred_at)
+    .bind(my_keys.public_key().to_hex())
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+
+    let receipt = serde_json::json!({ "evidence_id": id, "sha256": hash }).to_string();
+    enqueue_order_msg(
+        msg.get_inner_message_kind().request_id,
+        Some(order.id),
+        Action::Dispute,
+        Some(Payload::TextMessage(receipt)),
+        event.sender,
+        None,
+    )
+    .await;
+
+    // A solver already on the dispute gets the new item right away
+    if let Some(solver) = dispute
+        .solver_pubkey
+        .as_deref()
+        .and_then(|pk| PublicKey::from_hex(pk).ok())
+    {
+        let item = Evidence {
+            id,
+            dispute_id: dispute.id,
+            submitted_by: submitted_by.to_string(),
+            kind: submission.kind,
+            content: submission.content,
+            sha256: hash,
+            occurred_at: submission.occurred_at,
+            created_at: now,
+        };
+        if let Err(e) = send_to_solver(solver, &[item]).await {
+            warn!(dispute_id = %dispute.id, "evidence {id} not forwarded to solver: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+    use crate::db::Crud;
+    use std::sync::Arc;
+
+    async fn setup_ctx() -> AppContext {
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
+        TestContextBuilder::new()
+            .with_pool(pool)
+            .with_settings(test_settings())
+            .build()
+    }
+
+    async fn disputed_order(pool: &DbPool, seller: PublicKey, buyer: PublicKey) -> Order {
+        let order = Order {
+            id: Uuid::new_v4(),
+            status: Status::Dispute.to_string(),
+            kind: mostro_core::order::Kind::Sell.to_string(),
+            fiat_code: "USD".to_string(),
+            seller_pubkey: Some(seller.to_string()),
+            buyer_pubkey: Some(buyer.to_string()),
+            buyer_dispute: true,
+            amount: 50_000,
+            ..Default::default()
+        }
+        .create(pool)
+        .await
+        .unwrap();
+        Dispute::new(order.id, Status::Active.to_string())
+            .create(pool)
+            .await
+            .unwrap();
+        order
+    }
+
+    fn evidence_msg(order_id: Uuid, json: &str) -> Message {
+        Message::new_dispute(
+            Some(order_id),
+            Some(1),
+            None,
+            Action::Dispute,
+            Some(Payload::TextMessage(json.to_string())),
+        )
+    }
+
+    fn from(sender: PublicKey) -> UnwrappedMessage {
+        UnwrappedMessage {
+            message: evidence_msg(Uuid::new_v4(), "{}"),
+            signature: None,
+            sender,
+            identity: sender,
+            created_at: Timestamp::now(),
+        }
+    }
+
+    #[test]
+    fn submissions_are_validated() {
+        let (text, hash) = parse_submission(r#"{"kind":"text","content":"paid"}"#).unwrap();
+        assert_eq!(text.content, "paid");
+        assert_eq!(hash, sha256::Hash::hash(b"paid").to_string());
+
+        let receipt_hash = "AB".repeat(32);
+        let (_, hash) = parse_submission(&format!(
+            r#"{{"kind":"receipt","sha256":"{receipt_hash}"}}"#
+        ))
+        .unwrap();
+        assert_eq!(hash, receipt_hash.to_lowercase());
+
+        let too_long = format!(
+            r#"{{"kind":"text","content":"{}"}}"#,
+            "x".repeat(MAX_EVIDENCE_BYTES + 1)
+        );
+        for json in [
+            "not json",
+            r#"{"kind":"video","content":"x"}"#,
+            r#"{"kind":"text"}"#,
+            r#"{"kind":"text","sha256":"00"}"#,
+            r#"{"kind":"receipt","content":"x","sha256":"00"}"#,
+            r#"{"kind":"receipt","sha256":"xyz"}"#,
+            r#"{"kind":"text","content":"x","occurred_at":-1}"#,
+            r#"{"kind":"text","content":"x","extra":1}"#,
+            too_long.as_str(),
+        ] {
+            assert!(parse_submission(json).is_err(), "{json} must be rejected");
+        }
+    }
+
+    #[tokio::test]
+    async fn parties_attach_encrypted_evidence_to_an_open_dispute() {
+        let ctx = setup_ctx().await;
+        let keys = Keys::generate();
+        let seller = Keys::generate().public_key();
+        let buyer = Keys::generate().public_key();
+        let order = disputed_order(ctx.pool(), seller, buyer).await;
+
+        let json = r#"{"kind":"receipt","content":"bank transfer #42","occurred_at":1700000000}"#;
+        submit_evidence_action(
+            &ctx,
+            &evidence_msg(order.id, json),
+            &from(buyer),
+            &keys,
+            json,
+        )
+        .await
+        .unwrap();
+        // `dispute` with a text payload on a disputed order lands here
+        let json = r#"{"kind":"text","content":"nothing arrived"}"#;
+        crate::app::dispute::dispute_action(
+            &ctx,
+            evidence_msg(order.id, json),
+            &from(seller),
+            &keys,
+        )
+        .await
+        .unwrap();
+
+        // Stored encrypted, listed decrypted
+        let stored: String =
+            sqlx::query_scalar("SELECT content FROM dispute_evidence WHERE submitted_by = 'buyer'")
+                .fetch_one(ctx.pool())
+                .await
+                .unwrap();
+        assert!(!stored.contains("bank transfer"));
+
+        let dispute = find_dispute_by_order_id(ctx.pool(), order.id)
+            .await
+            .unwrap();
+        let items = list_evidence(ctx.pool(), dispute.id, &keys).await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].submitted_by, "buyer");
+        assert_eq!(items[0].content, "bank transfer #42");
+        assert_eq!(items[0].occurred_at, Some(1_700_000_000));
+        assert_eq!(items[1].submitted_by, "seller");
+
+        // A solver on the dispute gets new items through the outbox
+        let solver = Keys::generate().public_key();
+        sqlx::query("UPDATE disputes SET solver_pubkey = $1, status = 'in-progress'")
+            .bind(solver.to_hex())
+            .execute(ctx.pool())
+            .await
+            .unwrap();
+        let json = r#"{"kind":"text","content":"see the chat log"}"#;
+        submit_evidence_action(
+            &ctx,
+            &evidence_msg(order.id, json),
+            &from(buyer),
+            &keys,
+            json,
+        )
+        .await
+        .unwrap();
+        let forwarded: Vec<_> = crate::outbox::test_utils::queued("dispute")
+            .await
+            .into_iter()
+            .filter(|(_, destination)| *destination == solver)
+            .collect();
+        assert_eq!(forwarded.len(), 1);
+        let Some(Payload::TextMessage(item)) = &forwarded[0].0.get_inner_message_kind().payload
+        else {
+            panic!("evidence is a text payload");
+        };
+        assert!(item.contains("see the chat log"));
+
+        // Strangers cannot attach evidence
+        let stranger = Keys::generate().public_key();
+        let result = submit_evidence_action(
+            &ctx,
+            &evidence_msg(order.id, json),
+            &from(stranger),
+            &keys,
+            json,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MostroCantDo(CantDoReason::InvalidPubkey))
+        ));
+    }
+
+    #[tokio::test]
+    async fn closed_disputes_take_no_evidence() {
+        let ctx = setup_ctx().await;
+        let keys = Keys::generate();
+        let seller = Keys::generate().public_key();
+        let buyer = Keys::generate().public_key();
+        let order = disputed_order(ctx.pool(), seller, buyer).await;
+        sqlx::query("UPDATE disputes SET status = 'settled'")
+            .execute(ctx.pool())
+            .await
+            .unwrap();
+
+        let json = r#"{"kind":"text","content":"late"}"#;
+        let result = submit_evidence_action(
+            &ctx,
+            &evidence_msg(order.id, json),
+            &from(buyer),
+            &keys,
+            json,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MostroCantDo(CantDoReason::NotAllowedByStatus))
+        ));
+    }
+}
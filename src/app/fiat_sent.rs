@@ -1,9 +1,70 @@
 use crate::app::context::AppContext;
+use crate::cashu::escrow_token_locktime;
+use crate::config::settings::Settings;
+use crate::db::Crud;
 use crate::util::{enqueue_order_msg, get_order, update_order_event};
-use mostro_core::db::Crud;
+use chrono::Utc;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
 
+/// Seconds in a day — the settlement margin is configured in days
+/// (`cashu.escrow_settlement_margin_days`, Track B §4).
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Whether enough escrow locktime remains for the buyer to send fiat.
+///
+/// Unsigned, saturating arithmetic on purpose: an already-expired locktime
+/// collapses to `remaining == 0` and is rejected, instead of underflowing into
+/// a huge "remaining" that would let fiat through while the seller can
+/// already reclaim the escrow alone.
+pub(crate) fn settlement_window_open(locktime: u64, now: u64, margin_days: u32) -> bool {
+    let remaining = locktime.saturating_sub(now);
+    remaining > 0 && remaining >= u64::from(margin_days).saturating_mul(SECONDS_PER_DAY)
+}
+
+/// Cashu-mode `FiatSent` guard (Track B §4): reject the buyer's fiat signal
+/// when less than `margin_days` of the stored escrow token's locktime is left.
+///
+/// An `Active` Cashu order always carries a locked token with a locktime, so a
+/// missing or unparseable one is a data bug — logged and answered with
+/// `CashuEscrowNotLocked`. A closed window is answered with
+/// `NotAllowedByStatus` until a dedicated reason ships in `mostro-core`.
+fn check_cashu_settlement_window(
+    order: &Order,
+    now: u64,
+    margin_days: u32,
+) -> Result<(), MostroError> {
+    let locktime = match order
+        .cashu_escrow_token
+        .as_deref()
+        .map(escrow_token_locktime)
+    {
+        Some(Ok(locktime)) => locktime,
+        Some(Err(e)) => {
+            tracing::error!(
+                "cashu fiat-sent: escrow token of order {} has no readable locktime: {e}",
+                order.id
+            );
+            return Err(MostroCantDo(CantDoReason::CashuEscrowNotLocked));
+        }
+        None => {
+            tracing::error!(
+                "cashu fiat-sent: active order {} has no escrow token",
+                order.id
+            );
+            return Err(MostroCantDo(CantDoReason::CashuEscrowNotLocked));
+        }
+    };
+    if !settlement_window_open(locktime, now, margin_days) {
+        tracing::info!(
+            "cashu fiat-sent: order {} rejected, escrow locktime {locktime} is inside the {margin_days}-day settlement margin",
+            order.id
+        );
+        return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
+    }
+    Ok(())
+}
+
 // Handle fiat sent action
 pub async fn fiat_sent_action(
     ctx: &AppContext,
@@ -26,6 +87,16 @@ pub async fn fiat_sent_action(
         return Err(MostroCantDo(CantDoReason::InvalidPubkey));
     }
 
+    // Cashu escrow mode: fiat may only move while the seller cannot yet
+    // reclaim the escrow through the locktime path (Track B §4).
+    if let Some(cashu) = Settings::get_cashu().filter(|c| c.enabled) {
+        check_cashu_settlement_window(
+            &order,
+            Utc::now().timestamp() as u64,
+            cashu.escrow_settlement_margin_days,
+        )?;
+    }
+
     // Get next trade key
     let next_trade = msg
         .get_inner_message_kind()
@@ -84,10 +155,11 @@ pub async fn fiat_sent_action(
     }
 
     // Update order
-    order_updated
+    let order_updated = order_updated
         .update(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::order_status_changed(order_updated.id, &order_updated.status);
 
     Ok(())
 }
@@ -97,9 +169,9 @@ mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
     use crate::app::context::AppContext;
-    use crate::config::{MESSAGE_QUEUES, MOSTRO_CONFIG};
+    use crate::config::MOSTRO_CONFIG;
+    use crate::db::DbPool;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
     /// The `MOSTRO_CONFIG` OnceLock is process-global: set it to the shared
@@ -108,13 +180,11 @@ mod tests {
         let _ = MOSTRO_CONFIG.set(test_settings());
     }
 
-    async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    fn build_ctx(pool: &SqlitePool) -> AppContext {
+    fn build_ctx(pool: &DbPool) -> AppContext {
         TestContextBuilder::new()
             .with_pool(Arc::new(pool.clone()))
             .with_settings(test_settings())
@@ -165,9 +235,7 @@ mod tests {
     /// The queue is shared across concurrently running tests, so assertions
     /// must always filter by our own order id.
     async fn queued_actions_for(order_id: uuid::Uuid) -> Vec<Action> {
-        MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(msg, _)| msg.get_inner_message_kind().id == Some(order_id))
@@ -309,4 +377,38 @@ mod tests {
         assert_eq!(db_order.next_trade_pubkey, Some(next_trade.to_string()));
         assert_eq!(db_order.next_trade_index, Some(7));
     }
+
+    #[test]
+    fn settlement_window_respects_margin_and_expiry() {
+        let now = 1_700_000_000u64;
+        let day = SECONDS_PER_DAY;
+        // Comfortably outside the 3-day margin.
+        assert!(settlement_window_open(now + 10 * day, now, 3));
+        // Exactly at the margin is still allowed.
+        assert!(settlement_window_open(now + 3 * day, now, 3));
+        // Inside the margin.
+        assert!(!settlement_window_open(now + 3 * day - 1, now, 3));
+        // Already expired: saturates to zero, never underflows.
+        assert!(!settlement_window_open(now - day, now, 3));
+        assert!(!settlement_window_open(now, now, 1));
+    }
+
+    #[test]
+    fn cashu_guard_rejects_order_without_readable_escrow() {
+        let seller = Keys::generate().public_key();
+        let buyer = Keys::generate().public_key();
+        let mut order = active_sell_order(seller, buyer);
+        let now = Utc::now().timestamp() as u64;
+
+        assert!(matches!(
+            check_cashu_settlement_window(&order, now, 3),
+            Err(MostroCantDo(CantDoReason::CashuEscrowNotLocked))
+        ));
+
+        order.cashu_escrow_token = Some("cashuAgarbage".to_string());
+        assert!(matches!(
+            check_cashu_settlement_window(&order, now, 3),
+            Err(MostroCantDo(CantDoReason::CashuEscrowNotLocked))
+        ));
+    }
 }
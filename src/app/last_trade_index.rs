@@ -100,9 +100,8 @@ pub async fn last_trade_index(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::DbPool;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::sqlite::SqlitePoolOptions;
-    use sqlx::SqlitePool;
 
     // Helper function to create test keys
     fn create_test_keys() -> Keys {
@@ -135,46 +134,16 @@ mod tests {
     }
 
     // Helper function to set up in-memory test database with user table
-    async fn setup_test_db() -> SqlitePool {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .unwrap();
-
-        // Create the users table schema matching application expectations
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS users (
-                pubkey CHAR(64) PRIMARY KEY NOT NULL,
-                is_admin INTEGER NOT NULL DEFAULT 0,
-                admin_password CHAR(64),
-                is_solver INTEGER NOT NULL DEFAULT 0,
-                is_banned INTEGER NOT NULL DEFAULT 0,
-                category INTEGER NOT NULL DEFAULT 0,
-                last_trade_index INTEGER NOT NULL DEFAULT 0,
-                total_reviews INTEGER NOT NULL DEFAULT 0,
-                total_rating REAL NOT NULL DEFAULT 0.0,
-                last_rating INTEGER NOT NULL DEFAULT 0,
-                max_rating INTEGER NOT NULL DEFAULT 0,
-                min_rating INTEGER NOT NULL DEFAULT 0,
-                created_at INTEGER NOT NULL DEFAULT 0
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        pool
+    async fn setup_test_db() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     // Helper function to insert a test user
-    async fn insert_test_user(pool: &SqlitePool, pubkey: &str, last_trade_index: i64) {
+    async fn insert_test_user(pool: &DbPool, pubkey: &str, last_trade_index: i64) {
         sqlx::query(
             r#"
-            INSERT INTO users (pubkey, last_trade_index)
-            VALUES (?, ?)
+            INSERT INTO users (pubkey, last_trade_index, created_at)
+            VALUES ($1, $2, 0)
             "#,
         )
         .bind(pubkey)
@@ -168,11 +168,11 @@ mod tests {
     use super::*;
     use mostro_core::message::MessageKind;
 
+    use crate::db::DbPool;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
 
-    async fn create_test_pool() -> SqlitePool {
-        SqlitePool::connect(":memory:").await.unwrap()
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn create_test_keys() -> Keys {
@@ -397,7 +397,7 @@ mod tests {
         use std::sync::Arc;
 
         async fn create_ctx() -> AppContext {
-            let pool = Arc::new(SqlitePool::connect(":memory:").await.unwrap());
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
             TestContextBuilder::new()
                 .with_pool(pool)
                 .with_settings(test_settings())
@@ -489,11 +489,7 @@ mod tests {
         use std::sync::Arc;
 
         async fn create_migrated_ctx() -> AppContext {
-            let pool = Arc::new(SqlitePool::connect(":memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
             TestContextBuilder::new()
                 .with_pool(pool)
                 .with_settings(test_settings())
@@ -1,6 +1,75 @@
 use crate::app::context::AppContext;
+use crate::db::DbPool;
+use crate::db::{find_order_book, OrderBookFilter};
 use crate::util::{enqueue_order_msg, get_user_orders_by_id};
 use mostro_core::prelude::*;
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Order book query sent as a JSON `Payload::TextMessage` with the `orders`
+/// action. Every field is optional; see docs/ORDERS_AND_ACTIONS.md.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OrderBookQuery {
+    kind: Option<String>,
+    fiat_code: Option<String>,
+    payment_method: Option<String>,
+    min_amount: Option<i64>,
+    max_amount: Option<i64>,
+    min_premium: Option<i64>,
+    max_premium: Option<i64>,
+    min_rating: Option<f64>,
+    /// The last order of the previous page.
+    after: Option<OrderBookCursor>,
+    limit: Option<u32>,
+}
+
+/// Position in the order book, carried by the client: the `created_at` and
+/// `id` of the last order it received.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OrderBookCursor {
+    created_at: i64,
+    id: Uuid,
+}
+
+fn inverted<T: PartialOrd>(min: Option<T>, max: Option<T>) -> bool {
+    matches!((min, max), (Some(min), Some(max)) if min > max)
+}
+
+/// Validates `json` and turns it into a database filter; the page size is
+/// capped at `max_results`.
+fn parse_order_book_query(json: &str, max_results: u32) -> Result<OrderBookFilter, MostroError> {
+    let query: OrderBookQuery =
+        serde_json::from_str(json).map_err(|_| MostroCantDo(CantDoReason::InvalidParameters))?;
+
+    let kind = match query.kind.as_deref() {
+        None => None,
+        Some(kind @ ("buy" | "sell")) => Some(kind.to_string()),
+        Some(_) => return Err(MostroCantDo(CantDoReason::InvalidParameters)),
+    };
+    if inverted(query.min_amount, query.max_amount)
+        || inverted(query.min_premium, query.max_premium)
+        || query.min_amount.is_some_and(|a| a < 0)
+        || query.min_rating.is_some_and(|r| !(0.0..=5.0).contains(&r))
+        || query.limit == Some(0)
+    {
+        return Err(MostroCantDo(CantDoReason::InvalidParameters));
+    }
+
+    Ok(OrderBookFilter {
+        kind,
+        fiat_code: query.fiat_code.map(|code| code.to_uppercase()),
+        payment_method: query.payment_method.filter(|m| !m.trim().is_empty()),
+        min_amount: query.min_amount,
+        max_amount: query.max_amount,
+        min_premium: query.min_premium,
+        max_premium: query.max_premium,
+        min_rating: query.min_rating,
+        after: query.after.map(|cursor| (cursor.created_at, cursor.id)),
+        limit: query.limit.unwrap_or(max_results).min(max_results) as i64,
+    })
+}
 
 // Handle orders action
 pub async fn orders_action(
@@ -9,35 +78,47 @@ pub async fn orders_action(
     event: &UnwrappedMessage,
 ) -> Result<(), MostroError> {
     let pool = ctx.pool();
-    // Get order
+    let mostro_settings = &ctx.settings().mostro;
     let payload = msg.get_inner_message_kind().get_payload();
 
-    let ids = match payload {
-        Some(Payload::Ids(ids)) => ids,
+    let orders = match payload {
+        Some(Payload::Ids(ids)) => {
+            // Return an error to the caller if the payload contains no usable identifiers
+            if ids.is_empty() {
+                return Err(MostroCantDo(CantDoReason::InvalidParameters));
+            }
+            if ids.len() > mostro_settings.max_orders_per_response as usize {
+                return Err(MostroCantDo(CantDoReason::TooManyRequests));
+            }
+            let orders = user_orders(pool, ids, &event.identity.to_string()).await?;
+            if orders.is_empty() {
+                return Err(MostroCantDo(CantDoReason::NotFound));
+            }
+            orders
+        }
+        // Order book query: an empty page is a valid answer
+        Some(Payload::TextMessage(json)) => {
+            let filter =
+                parse_order_book_query(json, mostro_settings.max_orders_per_response.into())?;
+            find_order_book(pool, crate::instance::current_id(), &filter).await?
+        }
         _ => return Err(MostroCantDo(CantDoReason::InvalidParameters)),
     };
 
-    // Return an error to the caller if the payload contains no usable identifiers
-    if ids.is_empty() {
-        return Err(MostroCantDo(CantDoReason::InvalidParameters));
-    }
-
-    let mostro_settings = &ctx.settings().mostro;
-    if ids.len() > mostro_settings.max_orders_per_response as usize {
-        return Err(MostroCantDo(CantDoReason::TooManyRequests));
-    }
-
-    // Get orders
-    let orders = get_user_orders_by_id(pool, ids, &event.identity.to_string()).await?;
-    if orders.is_empty() {
-        return Err(MostroCantDo(CantDoReason::NotFound));
-    }
+    let identity = event.identity.to_string();
     let small_orders = orders
         .into_iter()
         .map(|order| {
+            let is_own = order.master_buyer_pubkey.as_deref() == Some(identity.as_str())
+                || order.master_seller_pubkey.as_deref() == Some(identity.as_str());
             let mut small = SmallOrder::from(order);
             // Clear buyer_invoice to avoid leaking buyer's payment info
             small.buyer_invoice = None;
+            // Trade keys of other users' orders are not part of the order book
+            if !is_own {
+                small.buyer_trade_pubkey = None;
+                small.seller_trade_pubkey = None;
+            }
             small
         })
         .collect::<Vec<SmallOrder>>();
@@ -56,22 +137,37 @@ pub async fn orders_action(
     Ok(())
 }
 
+/// The caller's orders among `ids`, restricted to the current instance.
+async fn user_orders(
+    pool: &DbPool,
+    ids: &[Uuid],
+    identity: &str,
+) -> Result<Vec<Order>, MostroError> {
+    let orders = get_user_orders_by_id(pool, ids, identity).await?;
+    // Orders of other Mostro instances in this process stay invisible
+    if !crate::instance::is_multi_tenant() {
+        return Ok(orders);
+    }
+    let mut own = Vec::with_capacity(orders.len());
+    for order in orders {
+        if crate::instance::owns_order(pool, crate::instance::current_id(), order.id).await? {
+            own.push(order);
+        }
+    }
+    Ok(own)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-    use mostro_core::db::Crud;
+    use crate::db::Crud;
     use nostr_sdk::prelude::{Keys, PublicKey, Timestamp};
-    use sqlx::SqlitePool;
     use std::sync::Arc;
     use uuid::Uuid;
 
     async fn setup_ctx() -> AppContext {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         TestContextBuilder::new()
             .with_pool(pool)
             .with_settings(test_settings())
@@ -196,9 +292,7 @@ mod tests {
 
         // The response goes to the process-global queue; filter by this
         // test's unique sender key to stay isolated from parallel tests.
-        let queued: Vec<Message> = crate::config::MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        let queued: Vec<Message> = crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(_, pk)| *pk == sender)
@@ -220,4 +314,181 @@ mod tests {
             other => panic!("expected Payload::Orders, got {other:?}"),
         }
     }
+
+    /// Ids of the order lists queued for `sender`, one entry per response.
+    async fn queued_order_ids(sender: PublicKey) -> Vec<Vec<Uuid>> {
+        crate::outbox::test_utils::queued("order")
+            .await
+            .iter()
+            .filter(|(_, pk)| *pk == sender)
+            .map(|(m, _)| match m.get_inner_message_kind().get_payload() {
+                Some(Payload::Orders(orders)) => orders.iter().filter_map(|o| o.id).collect(),
+                other => panic!("expected Payload::Orders, got {other:?}"),
+            })
+            .collect()
+    }
+
+    fn query_msg(json: &str) -> Message {
+        orders_msg(Some(Payload::TextMessage(json.to_string())))
+    }
+
+    #[tokio::test]
+    async fn order_book_query_applies_filters() {
+        let ctx = setup_ctx().await;
+        let sender = Keys::generate().public_key();
+        let event = orders_event(sender, Keys::generate().public_key());
+
+        let rated_maker = Keys::generate().public_key().to_string();
+        // The published score, not the raw running average, is what counts
+        let faded_maker = Keys::generate().public_key().to_string();
+        for (maker, score) in [(&rated_maker, 4.5), (&faded_maker, 2.0)] {
+            sqlx::query(
+                "INSERT INTO users (pubkey, total_rating, reputation_score, created_at) \
+                 VALUES ($1, 4.5, $2, 1)",
+            )
+            .bind(maker)
+            .bind(score)
+            .execute(ctx.pool())
+            .await
+            .unwrap();
+        }
+        let order = |amount: i64, method: &str, maker: Option<&str>| Order {
+            payment_method: method.to_string(),
+            amount,
+            master_seller_pubkey: maker.map(str::to_string),
+            seller_pubkey: maker.map(str::to_string),
+            ..base_order()
+        };
+
+        let matching = order(20_000, "Bank transfer,Zelle", Some(&rated_maker))
+            .create(ctx.pool())
+            .await
+            .unwrap();
+        // Market-priced: no sats amount yet, so the amount range does not exclude it
+        let market = order(0, "zelle", Some(&rated_maker))
+            .create(ctx.pool())
+            .await
+            .unwrap();
+        order(20_000, "Revolut", Some(&rated_maker))
+            .create(ctx.pool())
+            .await
+            .unwrap();
+        order(90_000, "Zelle", Some(&rated_maker))
+            .create(ctx.pool())
+            .await
+            .unwrap();
+        order(20_000, "Zelle", None)
+            .create(ctx.pool())
+            .await
+            .unwrap();
+        order(20_000, "Zelle", Some(&faded_maker))
+            .create(ctx.pool())
+            .await
+            .unwrap();
+        Order {
+            fiat_code: "EUR".to_string(),
+            ..order(20_000, "Zelle", Some(&rated_maker))
+        }
+        .create(ctx.pool())
+        .await
+        .unwrap();
+        Order {
+            kind: mostro_core::order::Kind::Buy.to_string(),
+            master_buyer_pubkey: Some(rated_maker.clone()),
+            ..order(20_000, "Zelle", None)
+        }
+        .create(ctx.pool())
+        .await
+        .unwrap();
+        Order {
+            status: Status::Active.to_string(),
+            ..order(20_000, "Zelle", Some(&rated_maker))
+        }
+        .create(ctx.pool())
+        .await
+        .unwrap();
+
+        let query = r#"{"kind":"sell","fiat_code":"usd","payment_method":"ZELLE",
+            "min_amount":10000,"max_amount":50000,"min_rating":4}"#;
+        orders_action(&ctx, query_msg(query), &event).await.unwrap();
+
+        let mut returned = queued_order_ids(sender).await.remove(0);
+        returned.sort();
+        let mut expected = vec![matching.id, market.id];
+        expected.sort();
+        assert_eq!(returned, expected);
+    }
+
+    #[tokio::test]
+    async fn order_book_query_pages_with_cursor() {
+        let ctx = setup_ctx().await;
+        let sender = Keys::generate().public_key();
+        let event = orders_event(sender, Keys::generate().public_key());
+
+        let mut ids = Vec::new();
+        for created_at in [100, 300, 200] {
+            let order = Order {
+                created_at,
+                ..base_order()
+            };
+            ids.push(order.create(ctx.pool()).await.unwrap().id);
+        }
+        let after = |created_at: i64, id: Uuid| {
+            format!(r#"{{"limit":2,"after":{{"created_at":{created_at},"id":"{id}"}}}}"#)
+        };
+
+        orders_action(&ctx, query_msg(r#"{"limit":2}"#), &event)
+            .await
+            .unwrap();
+        let first = queued_order_ids(sender).await.remove(0);
+        assert_eq!(first, vec![ids[1], ids[2]], "newest first");
+
+        orders_action(&ctx, query_msg(&after(200, first[1])), &event)
+            .await
+            .unwrap();
+        assert_eq!(queued_order_ids(sender).await[1], vec![ids[0]]);
+
+        // The end of the book is an empty page, not an error
+        orders_action(&ctx, query_msg(&after(100, ids[0])), &event)
+            .await
+            .unwrap();
+        assert!(queued_order_ids(sender).await[2].is_empty());
+
+        // The cursor is the client's own: the last order need not still be
+        // in the book, and no order of another instance is looked up
+        sqlx::query("DELETE FROM orders WHERE id = $1")
+            .bind(ids[2])
+            .execute(ctx.pool())
+            .await
+            .unwrap();
+        orders_action(&ctx, query_msg(&after(200, ids[2])), &event)
+            .await
+            .unwrap();
+        assert_eq!(queued_order_ids(sender).await[3], vec![ids[0]]);
+    }
+
+    #[tokio::test]
+    async fn order_book_query_rejects_invalid_queries() {
+        let ctx = setup_ctx().await;
+        let event = orders_event(Keys::generate().public_key(), Keys::generate().public_key());
+
+        let bare_id_cursor = format!(r#"{{"after":"{}"}}"#, Uuid::new_v4());
+        for query in [
+            "not json",
+            r#"{"currency":"USD"}"#,
+            r#"{"kind":"swap"}"#,
+            r#"{"min_amount":500,"max_amount":100}"#,
+            r#"{"min_premium":5,"max_premium":-5}"#,
+            r#"{"min_rating":6}"#,
+            r#"{"limit":0}"#,
+            bare_id_cursor.as_str(),
+            r#"{"after":{"created_at":1}}"#,
+        ] {
+            let result = orders_action(&ctx, query_msg(query), &event).await;
+            assert!(
+                matches!(result, Err(MostroCantDo(CantDoReason::InvalidParameters))),
+                "{query} must be rejected, got {result:?}"
+            );
+        }
+    }
 }
@@ -1,8 +1,39 @@
 use crate::app::context::AppContext;
+use crate::config::settings::Settings;
 use crate::db::{claim_order_rating_flag, update_user_rating};
+use crate::reputation;
 use crate::util::{enqueue_order_msg, get_order, update_user_rating_event};
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
+use serde::Deserialize;
+
+/// JSON form of a `rate-user` payload carrying structured feedback, sent as
+/// `Payload::TextMessage`: `{"rating": 5, "feedback": ["fast-payment"]}`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RatingWithFeedback {
+    rating: u8,
+    #[serde(default)]
+    feedback: Vec<String>,
+}
+
+/// Rating and feedback labels (see [`reputation::FEEDBACK_LABELS`]) of a
+/// `rate-user` message. A bare `Payload::RatingUser` carries no feedback.
+fn get_rating_and_feedback(msg: &Message) -> Result<(u8, String), MostroError> {
+    let kind = msg.get_inner_message_kind();
+    let Some(Payload::TextMessage(json)) = &kind.payload else {
+        return Ok((kind.get_rating().map_err(MostroInternalErr)?, String::new()));
+    };
+    let rating: RatingWithFeedback =
+        serde_json::from_str(json).map_err(|_| MostroCantDo(CantDoReason::InvalidParameters))?;
+    if !(MIN_RATING..=MAX_RATING).contains(&rating.rating) {
+        return Err(MostroInternalErr(ServiceError::InvalidRatingValue));
+    }
+    Ok((
+        rating.rating,
+        reputation::feedback_column(&rating.feedback)?,
+    ))
+}
 
 pub fn prepare_variables_for_vote(
     message_sender: &str,
@@ -62,9 +93,9 @@ pub fn prepare_variables_for_vote(
 /// 2. Verifies the order status is "Success", or "SettledHoldInvoice" for seller-initiated ratings
 /// 3. Determines if the rating is from buyer or seller
 /// 4. Fast-path skips when the sender's rate flag is already set (durable claim is step 6)
-/// 5. Validates privacy mode settings
+/// 5. Reads the rating and its optional feedback labels, and validates privacy mode settings
 /// 6. Claims the sender's rating flag and updates the recipient's metrics in one transaction
-/// 7. Creates and enqueues a new rating event after commit
+/// 7. Creates and enqueues a new rating event, with the weighted reputation, after commit
 /// 8. Sends a confirmation message to the rating user
 pub async fn update_user_reputation_action(
     ctx: &AppContext,
@@ -80,9 +111,13 @@ pub async fn update_user_reputation_action(
     let (counterpart_trade_pubkey, buyer_rating, seller_rating) =
         prepare_variables_for_vote(&event.sender.to_string(), &order)?;
 
-    // Check if order is success, but sellers can rate in status settled-hold-invoice
+    // Check if order is success, but sellers can rate in status settled-hold-invoice.
+    // In Cashu mode settled-hold-invoice only means "released, not yet redeemed"
+    // (Track B §5B), so nobody can rate before the release watcher reaches success.
     if !(order.check_status(Status::Success).is_ok()
-        || (order.check_status(Status::SettledHoldInvoice).is_ok() && seller_rating))
+        || (order.check_status(Status::SettledHoldInvoice).is_ok()
+            && seller_rating
+            && !Settings::is_cashu_enabled()))
     {
         return Err(MostroCantDo(CantDoReason::InvalidOrderStatus));
     }
@@ -100,11 +135,8 @@ pub async fn update_user_reputation_action(
         return Ok(());
     };
 
-    // Get rating from message
-    let new_rating = msg
-        .get_inner_message_kind()
-        .get_rating()
-        .map_err(MostroInternalErr)?;
+    // Get rating, and optional structured feedback, from message
+    let (new_rating, feedback) = get_rating_and_feedback(&msg)?;
 
     // Check if users are in full privacy mode
     let (normal_buyer_idkey, normal_seller_idkey) = order
@@ -144,7 +176,7 @@ pub async fn update_user_reputation_action(
         r#"
             SELECT *
             FROM users
-            WHERE pubkey == ?1
+            WHERE pubkey = $1
             LIMIT 1
         "#,
     )
@@ -165,6 +197,17 @@ pub async fn update_user_reputation_action(
         user_to_vote.total_rating,
     )
     .await?;
+    // Per-trade copy behind the volume- and recency-weighted score
+    reputation::record_rating(
+        &mut *tx,
+        &user_to_vote.pubkey,
+        order.id,
+        new_rating,
+        &feedback,
+        order.amount,
+        Timestamp::now().as_secs() as i64,
+    )
+    .await?;
 
     tx.commit().await.map_err(|e| {
         MostroInternalErr(ServiceError::DbAccessError(format!(
@@ -185,6 +228,7 @@ pub async fn update_user_reputation_action(
     let days = calculate_days_since_creation(user_to_vote.created_at);
     let mut tags: Vec<Tag> = reputation_event.into_iter().collect();
     tags.push(Tag::custom("days", vec![days.to_string()]));
+    tags.extend(reputation::profile(pool, &user_to_vote).await?.tags());
     let reputation_event = Tags::from_list(tags);
 
     if buyer_rating || seller_rating {
@@ -220,11 +264,11 @@ mod tests {
     use super::*;
     use crate::config::settings::Settings;
     use crate::config::MOSTRO_CONFIG;
-    use mostro_core::db::Crud;
+    use crate::db::Crud;
+    use crate::db::DbPool;
     use mostro_core::message::{MessageKind, Payload};
     use mostro_core::order::Order;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
     use uuid::Uuid;
 
     fn init_test_settings() {
@@ -239,6 +283,7 @@ mod tests {
                     "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
                 ),
                 relays: vec![],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: Default::default(),
             lightning: Default::default(),
@@ -246,15 +291,16 @@ mod tests {
             expiration: Some(Default::default()),
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         });
     }
 
-    async fn create_test_pool() -> SqlitePool {
+    async fn create_test_pool() -> DbPool {
         init_test_settings();
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn create_test_keys() -> Keys {
@@ -433,6 +479,89 @@ mod tests {
             .unwrap()
             .expect("order not found");
         assert!(updated_order.buyer_sent_rate);
+
+        // The rating is kept per trade for the weighted score
+        let stored: (i64, i64) = sqlx::query_as(
+            "SELECT rating, amount_sats FROM user_ratings WHERE pubkey = $1 AND order_id = $2",
+        )
+        .bind(&seller_user.pubkey)
+        .bind(order.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored, (5, order.amount));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_reputation_stores_structured_feedback() {
+        use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+        use crate::db::add_new_user;
+
+        let pool = create_test_pool().await;
+        let ctx = TestContextBuilder::new()
+            .with_pool(std::sync::Arc::new(pool.clone()))
+            .with_settings(test_settings())
+            .build();
+        let seller_pk = create_test_keys().public_key();
+        let buyer_pk = create_test_keys().public_key();
+        let seller_id = create_test_keys().public_key().to_string();
+        add_new_user(
+            &pool,
+            User {
+                pubkey: seller_id.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut order = create_test_order(Status::Success, seller_pk, buyer_pk);
+        order.master_seller_pubkey = Some(seller_id.clone());
+        order.master_buyer_pubkey = Some(create_test_keys().public_key().to_string());
+        let order = order.create(&pool).await.unwrap();
+
+        let event = create_unwrapped_message_with_pubkey(buyer_pk);
+        let rate = |json: &str| {
+            Message::new_order(
+                Some(order.id),
+                Some(1),
+                None,
+                Action::RateUser,
+                Some(Payload::TextMessage(json.to_string())),
+            )
+        };
+
+        // Unknown labels are rejected before the rating flag is claimed
+        let rejected = update_user_reputation_action(
+            &ctx,
+            rate(r#"{"rating":4,"feedback":["friendly"]}"#),
+            &event,
+            &create_test_keys(),
+        )
+        .await;
+        assert!(matches!(
+            rejected,
+            Err(MostroCantDo(CantDoReason::InvalidParameters))
+        ));
+
+        update_user_reputation_action(
+            &ctx,
+            rate(r#"{"rating":4,"feedback":["good-communication","fast-payment"]}"#),
+            &event,
+            &create_test_keys(),
+        )
+        .await
+        .unwrap();
+
+        let stored: (i64, String) = sqlx::query_as(
+            "SELECT rating, feedback FROM user_ratings WHERE pubkey = $1 AND order_id = $2",
+        )
+        .bind(&seller_id)
+        .bind(order.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored, (4, "fast-payment,good-communication".to_string()));
     }
 
     #[tokio::test]
@@ -881,13 +1010,14 @@ mod tests {
         order.master_buyer_pubkey = Some(create_test_keys().public_key().to_string());
         let order = order.create(&pool).await.unwrap();
 
-        sqlx::query(
-            "CREATE TRIGGER fail_users_update BEFORE UPDATE ON users \
-             BEGIN SELECT RAISE(ABORT, 'forced users failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_users_update",
+            "BEFORE UPDATE ON users",
+            None,
+            "forced users failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let event = create_unwrapped_message_with_pubkey(buyer_pk);
         let msg = create_rate_user_message(order.id, 5);
@@ -927,13 +1057,14 @@ mod tests {
         order.master_buyer_pubkey = Some(create_test_keys().public_key().to_string());
         let order = order.create(&pool).await.unwrap();
 
-        sqlx::query(
-            "CREATE TRIGGER fail_orders_update BEFORE UPDATE ON orders \
-             BEGIN SELECT RAISE(ABORT, 'forced orders failure'); END;",
+        crate::db::test_utils::abort_trigger(
+            &pool,
+            "fail_orders_update",
+            "BEFORE UPDATE ON orders",
+            None,
+            "forced orders failure",
         )
-        .execute(&pool)
-        .await
-        .unwrap();
+        .await;
 
         let event = create_unwrapped_message_with_pubkey(buyer_pk);
         let msg = create_rate_user_message(order.id, 5);
@@ -1,9 +1,11 @@
 use crate::app::bond;
 use crate::app::context::AppContext;
 use crate::app::dispute::close_dispute_after_user_resolution;
+use crate::cashu::{escrow_token_locktime, CashuClient, EscrowSpendState};
+use crate::config::settings::Settings;
 use crate::escrow::EscrowBackend;
 use crate::lightning::invoice::{decode_invoice, validate_payout_invoice};
-use crate::lightning::{LndConnector, PaymentMessage, PAYOUT_SEND_PAYMENT_TIMEOUT};
+use crate::lightning::{PaymentMessage, PAYOUT_SEND_PAYMENT_TIMEOUT};
 use crate::lnurl::resolv_ln_address;
 use crate::nip33::{new_order_event_with_created_at, order_to_tags};
 use crate::util::{
@@ -12,14 +14,15 @@ use crate::util::{
 };
 use crate::Result;
 use bitcoin::hashes::hex::FromHex;
+use chrono::Utc;
 
+use crate::db::DbPool;
+use crate::db::{create_order_as, Crud};
 use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
 use lnurl::lightning_address::LightningAddress;
 use lnurl::lnurl::LnUrl;
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 use std::cmp::Ordering;
 use std::str::FromStr;
 use std::time::Duration;
@@ -32,7 +35,7 @@ use tracing::{info, warn};
 /// right after claiming, the scheduler retry loop can fan a backlog of N
 /// failed payouts into N background tasks; this semaphore makes the sends
 /// queue instead of fanning out. It bounds concurrent *payment streams*, NOT
-/// LND connections: `LndConnector::new()` runs before the claim (a connect
+/// LND connections: `connect_lightning()` runs before the claim (a connect
 /// blip must never leave a marker set), so a backlog still opens N
 /// connections that sit idle while queued — a deliberate trade for the
 /// fail-fast-before-claim property.
@@ -62,7 +65,11 @@ const PAYOUT_QUEUE_HEARTBEAT: Duration =
 
 /// Run [`check_failure_retries`] and surface bookkeeping failures instead of
 /// silently dropping them. On success, preserves the existing retry-count log.
-async fn check_failure_retries_or_log(ctx: &AppContext, order: &Order, request_id: Option<u64>) {
+pub(crate) async fn check_failure_retries_or_log(
+    ctx: &AppContext,
+    order: &Order,
+    request_id: Option<u64>,
+) {
     match check_failure_retries(ctx, order, request_id).await {
         Ok(failed_payment) => {
             info!(
@@ -150,7 +157,7 @@ pub async fn check_failure_retries(
 
     // Only update payment-retry fields to avoid overwriting fields modified by
     // concurrent processes (dev_fee_paid, dev_fee_payment_hash, status, etc.)
-    sqlx::query("UPDATE orders SET failed_payment = ?, payment_attempts = ? WHERE id = ?")
+    sqlx::query("UPDATE orders SET failed_payment = $1, payment_attempts = $2 WHERE id = $3")
         .bind(order.failed_payment)
         .bind(order.payment_attempts)
         .bind(order.id)
@@ -251,34 +258,63 @@ pub async fn release_action(
         .get_next_trade_key()
         .map_err(MostroInternalErr)?;
 
+    // Cashu escrow mode (Track B §5B): there is no hold invoice to settle —
+    // the buyer redeems the 2-of-3 token with the seller's signature, which
+    // travels peer to peer and never through Mostro.
+    if Settings::is_cashu_enabled() {
+        return release_cashu_escrow(
+            ctx,
+            order,
+            next_trade,
+            my_keys,
+            request_id,
+            seller_pubkey,
+            buyer_pubkey,
+        )
+        .await;
+    }
+
+    // On-chain payout: the swap is created up front and the hold invoice
+    // stays held until the swap watcher sees it complete. A swap that cannot
+    // be created falls back to the usual release, its payout counted as a
+    // failed attempt by `do_payment`.
+    let swap = if crate::swap::is_swap_payout(&order) {
+        match crate::swap::payout::prepare_swap_payout(ctx, &order, true).await {
+            Ok(Some(swap)) => Some(swap),
+            Ok(None) => return Err(MostroCantDo(CantDoReason::NotAllowedByStatus)),
+            Err(e) => {
+                warn!("Order id {}: swap payout unavailable: {e}", order.id);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     // Settle seller hold invoice
-    settle_seller_hold_invoice(event, escrow, Action::Released, false, &order).await?;
+    if swap.is_none() {
+        settle_seller_hold_invoice(event, escrow, Action::Released, false, &order).await?;
+    }
     // Update order event with status SettledHoldInvoice
-    order = update_order_event(my_keys, Status::SettledHoldInvoice, &order)
-        .await
-        .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+    order = match update_order_event(my_keys, Status::SettledHoldInvoice, &order).await {
+        Ok(order) => order,
+        Err(e) => {
+            if let Some(swap) = &swap {
+                crate::swap::payout::abandon_swap(ctx, swap).await;
+            }
+            return Err(MostroInternalErr(ServiceError::NostrError(e.to_string())));
+        }
+    };
 
     // Persist the status change to DB before calling do_payment.
     // do_payment spawns async tasks that capture an Order copy; without this
     // explicit write the settled-hold-invoice status only lived in memory and
     // was persisted as a side-effect of the full-row writes in
     // check_failure_retries / payment_success (now replaced by targeted updates).
-    let result =
-        sqlx::query("UPDATE orders SET status = ?, event_id = ? WHERE id = ? AND status IN (?, ?)")
-            .bind(&order.status)
-            .bind(&order.event_id)
-            .bind(order.id)
-            .bind(Status::FiatSent.to_string())
-            .bind(Status::Dispute.to_string())
-            .execute(pool)
-            .await
-            .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
-
-    if result.rows_affected() == 0 {
-        tracing::warn!(
-            "Order {} not transitioned to settled-hold-invoice: status changed concurrently",
-            order.id
-        );
+    if !persist_release_transition(pool, &order).await? {
+        if let Some(swap) = &swap {
+            crate::swap::payout::abandon_swap(ctx, swap).await;
+        }
         return Ok(());
     }
 
@@ -297,21 +333,107 @@ pub async fn release_action(
     )
     .await;
 
-    // Handle child order for range orders. A spawned remainder means the
-    // range continues, so the maker stays committed and its bond stays
-    // `Locked`. No remainder means the range is fully consumed (or this was
-    // a fixed-amount order) — resolve the maker bond at close (Phase 6
-    // settle-at-close, or the Phase 5 release for a non-range maker bond).
+    continue_range_after_release(ctx, &order, next_trade, my_keys, request_id).await;
+
+    // We send a HoldInvoicePaymentSettled message to seller, the client should
+    // indicate *funds released* message to seller
+    enqueue_order_msg(
+        request_id,
+        Some(order.id),
+        Action::HoldInvoicePaymentSettled,
+        None,
+        seller_pubkey,
+        None,
+    )
+    .await;
+
+    // We send a message to seller indicating seller released funds
+    enqueue_order_msg(
+        None,
+        Some(order.id),
+        Action::Rate,
+        None,
+        seller_pubkey,
+        None,
+    )
+    .await;
+
+    // Phase 1/6: release the taker bond(s) on this slice. The maker bond is
+    // handled by `resolve_range_maker_bond_at_close_or_warn` above — its
+    // single HTLC may span a whole range, so releasing it here would
+    // wrongly cancel a bond still committed to a continuing range. A failed
+    // bond release is logged but does not block trade finalization.
+    bond::release_taker_bonds_for_order_or_warn(pool, order.id, "release_action").await;
+
+    // Finally we try to pay buyer's invoice
+    match swap {
+        Some(swap) => {
+            if let Err(e) = crate::swap::payout::dispatch_swap_payment(&swap).await {
+                // The swap lapses unpaid and the watcher falls back to the
+                // failed-payment retries.
+                warn!("Order id {}: swap {} not paid: {e}", order.id, swap.id);
+            }
+        }
+        None => {
+            let _ = do_payment(ctx, order, request_id).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Persist `FiatSent|Dispute → SettledHoldInvoice` for a release.
+///
+/// Only `status` and `event_id` are written, guarded by `status IN (FiatSent,
+/// Dispute)`, so a concurrent terminal transition is never overwritten.
+/// Returns `false` (after logging) when the status moved on concurrently and
+/// the caller must stop without notifying anyone.
+async fn persist_release_transition(pool: &DbPool, order: &Order) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        "UPDATE orders SET status = $1, event_id = $2 WHERE id = $3 AND status IN ($4, $5)",
+    )
+    .bind(&order.status)
+    .bind(&order.event_id)
+    .bind(order.id)
+    .bind(Status::FiatSent.to_string())
+    .bind(Status::Dispute.to_string())
+    .execute(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+
+    if result.rows_affected() == 0 {
+        tracing::warn!(
+            "Order {} not transitioned to settled-hold-invoice: status changed concurrently",
+            order.id
+        );
+        return Ok(false);
+    }
+    crate::rpc::events::order_status_changed(order.id, &order.status);
+    Ok(true)
+}
+
+/// Handle child order for range orders. A spawned remainder means the
+/// range continues, so the maker stays committed and its bond stays
+/// `Locked`. No remainder means the range is fully consumed (or this was
+/// a fixed-amount order) — resolve the maker bond at close (Phase 6
+/// settle-at-close, or the Phase 5 release for a non-range maker bond).
+async fn continue_range_after_release(
+    ctx: &AppContext,
+    order: &Order,
+    next_trade: Option<(String, u32)>,
+    my_keys: &Keys,
+    request_id: Option<u64>,
+) {
+    let pool = ctx.pool();
     match get_child_order(ctx, order.clone(), my_keys).await {
         Ok((Some(child_order), Some(event))) => {
             let child_order_id = child_order.id;
-            // The hold invoice is already settled at this point, so a child
+            // The escrow is already released at this point, so a child
             // failure must never abort the release: skip the remainder,
             // resolve the maker bond and continue to the buyer payout. The
             // child order is persisted before its event is published so a
             // persistence failure never leaves a ghost order on the book.
-            match handle_child_order(child_order, &order, next_trade, ctx.pool(), request_id).await
-            {
+            match handle_child_order(child_order, order, next_trade, pool, request_id).await {
                 Ok(()) => {
                     let client = ctx.nostr_client();
                     // A per-relay rejection resolves to `Ok` with the
@@ -341,13 +463,13 @@ pub async fn release_action(
                         error = %e,
                         "handle_child_order failed (e.g. Release without NextTrade); skipping remainder, resolving maker bond and continuing with buyer payout"
                     );
-                    bond::resolve_range_maker_bond_at_close_or_warn(pool, &order, "release_action")
+                    bond::resolve_range_maker_bond_at_close_or_warn(pool, order, "release_action")
                         .await;
                 }
             }
         }
         Ok(_) => {
-            bond::resolve_range_maker_bond_at_close_or_warn(pool, &order, "release_action").await;
+            bond::resolve_range_maker_bond_at_close_or_warn(pool, order, "release_action").await;
         }
         Err(e) => {
             // `get_child_order` only *computes* the remainder (it neither
@@ -362,46 +484,263 @@ pub async fn release_action(
                 error = %e,
                 "get_child_order failed; resolving maker bond at close (no remainder was created)"
             );
-            bond::resolve_range_maker_bond_at_close_or_warn(pool, &order, "release_action").await;
+            bond::resolve_range_maker_bond_at_close_or_warn(pool, order, "release_action").await;
         }
     }
+}
 
-    // We send a HoldInvoicePaymentSettled message to seller, the client should
-    // indicate *funds released* message to seller
+/// Cashu branch of [`release_action`] (Track B §5B).
+///
+/// The seller's identity and the order status were already checked by the
+/// caller. Advances `FiatSent|Dispute → SettledHoldInvoice` with the same
+/// conditional write as the Lightning path and tells the buyer to redeem.
+/// The order is **not** terminal and not rateable yet: only the release
+/// watcher ([`confirm_cashu_release`]) moves it to `Success`, once the mint
+/// reports the escrow spent. Mostro never sees or stores the seller's Cashu
+/// signature — with `P_M` it would hold two of three keys.
+async fn release_cashu_escrow(
+    ctx: &AppContext,
+    order: Order,
+    next_trade: Option<(String, u32)>,
+    my_keys: &Keys,
+    request_id: Option<u64>,
+    seller_pubkey: PublicKey,
+    buyer_pubkey: PublicKey,
+) -> Result<(), MostroError> {
+    let pool = ctx.pool();
+    let order = update_order_event(my_keys, Status::SettledHoldInvoice, &order)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+    if !persist_release_transition(pool, &order).await? {
+        return Ok(());
+    }
+
+    close_dispute_after_user_resolution(ctx, &order, DisputeStatus::Settled, my_keys, "release")
+        .await;
+
+    // The buyer's cue to redeem the escrow with the seller's signature.
     enqueue_order_msg(
-        request_id,
+        None,
         Some(order.id),
-        Action::HoldInvoicePaymentSettled,
+        Action::Released,
         None,
-        seller_pubkey,
+        buyer_pubkey,
         None,
     )
     .await;
 
-    // We send a message to seller indicating seller released funds
+    continue_range_after_release(ctx, &order, next_trade, my_keys, request_id).await;
+
+    // Seller ack. Unlike Lightning there is no `Rate` yet: the trade only
+    // becomes rateable once the watcher observes the redeem.
     enqueue_order_msg(
-        None,
+        request_id,
         Some(order.id),
-        Action::Rate,
+        Action::HoldInvoicePaymentSettled,
         None,
         seller_pubkey,
         None,
     )
     .await;
 
-    // Phase 1/6: release the taker bond(s) on this slice. The maker bond is
-    // handled by `resolve_range_maker_bond_at_close_or_warn` above — its
-    // single HTLC may span a whole range, so releasing it here would
-    // wrongly cancel a bond still committed to a continuing range. A failed
-    // bond release is logged but does not block trade finalization.
-    bond::release_taker_bonds_for_order_or_warn(pool, order.id, "release_action").await;
+    Ok(())
+}
 
-    // Finally we try to pay buyer's invoice
-    let _ = do_payment(ctx, order, request_id).await;
+/// What the release watcher should do with a released Cashu order, given the
+/// escrow's state at the mint and when it was observed (Track B §5C).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CashuReleaseOutcome {
+    /// Spent before the locktime: only the 2-of-3 path was open and Mostro
+    /// did not sign, so the buyer redeemed — advance to `Success`.
+    Redeemed,
+    /// Not (fully) spent yet — check again next tick.
+    Waiting,
+    /// Spent, but observed after the locktime: a late buyer redeem and the
+    /// seller's locktime reclaim look identical to the mint. Left for a
+    /// dispute, never auto-completed; solvers are alerted once.
+    Ambiguous,
+}
+
+fn cashu_release_outcome(state: EscrowSpendState, locktime: u64, now: u64) -> CashuReleaseOutcome {
+    match state {
+        EscrowSpendState::Spent if now < locktime => CashuReleaseOutcome::Redeemed,
+        EscrowSpendState::Spent => CashuReleaseOutcome::Ambiguous,
+        EscrowSpendState::Unspent | EscrowSpendState::Mixed => CashuReleaseOutcome::Waiting,
+    }
+}
+
+/// One release-watcher step for a released Cashu order (Track B §5C, Track D
+/// §5D): ask the mint whether the escrow was redeemed and, if it was before
+/// the locktime, move `SettledHoldInvoice → Success` (or `SettledByAdmin →
+/// CompletedByAdmin`) and ask both parties to rate.
+///
+/// Returns whether this call completed the trade. Mint errors are returned so
+/// the scheduler logs them and retries on the next tick; the order stays
+/// eligible.
+pub async fn confirm_cashu_release(
+    ctx: &AppContext,
+    cashu_client: &CashuClient,
+    order: &Order,
+) -> Result<bool, MostroError> {
+    let token = order
+        .cashu_escrow_token
+        .as_deref()
+        .ok_or(MostroCantDo(CantDoReason::CashuEscrowNotLocked))?;
+    let locktime = escrow_token_locktime(token)
+        .map_err(|e| MostroInternalErr(ServiceError::UnexpectedError(e.to_string())))?;
+    let state = cashu_client
+        .escrow_spend_state(token)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::UnexpectedError(e.to_string())))?;
+
+    match cashu_release_outcome(state, locktime, Utc::now().timestamp() as u64) {
+        CashuReleaseOutcome::Waiting => Ok(false),
+        CashuReleaseOutcome::Ambiguous => {
+            hold_ambiguous_cashu_release(ctx, order, locktime).await?;
+            Ok(false)
+        }
+        CashuReleaseOutcome::Redeemed => finalize_cashu_release(ctx, order).await,
+    }
+}
 
+/// Payload of the alert sent when a released Cashu order's escrow is seen
+/// spent only after its locktime.
+#[derive(Debug, serde::Serialize)]
+struct AmbiguousCashuRelease {
+    cashu_release: &'static str,
+    order_id: uuid::Uuid,
+    locktime: u64,
+    observed_at: i64,
+}
+
+/// Take a released Cashu order whose escrow was observed spent after its
+/// locktime out of the release watcher and alert every solver and the
+/// dispute admin, once. The order keeps its status for the dispute path;
+/// the conditional write makes a repeated tick a no-op that sends nothing.
+async fn hold_ambiguous_cashu_release(
+    ctx: &AppContext,
+    order: &Order,
+    locktime: u64,
+) -> Result<(), MostroError> {
+    let pool = ctx.pool();
+    let now = Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE orders SET cashu_release_ambiguous_at = $1 \
+         WHERE id = $2 AND status = $3 AND cashu_release_ambiguous_at IS NULL",
+    )
+    .bind(now)
+    .bind(order.id)
+    .bind(&order.status)
+    .execute(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    if result.rows_affected() == 0 {
+        return Ok(());
+    }
+    warn!(
+        "cashu release: escrow of order {} observed spent after its locktime — not completing, solvers alerted",
+        order.id
+    );
+
+    let payload = serde_json::to_string(&AmbiguousCashuRelease {
+        cashu_release: "ambiguous",
+        order_id: order.id,
+        locktime,
+        observed_at: now,
+    })
+    .map_err(|_| MostroInternalErr(ServiceError::MessageSerializationError))?;
+    let mut recipients: std::collections::BTreeSet<PublicKey> =
+        crate::dispute_sla::solver_pubkeys(pool)
+            .await?
+            .into_iter()
+            .collect();
+    recipients.extend(ctx.settings().mostro.dispute_admin());
+    for recipient in recipients {
+        enqueue_order_msg(
+            None,
+            Some(order.id),
+            Action::Dispute,
+            Some(Payload::TextMessage(payload.clone())),
+            recipient,
+            None,
+        )
+        .await;
+    }
     Ok(())
 }
 
+/// Move a redeemed Cashu order to its completed status and notify both
+/// parties. The conditional write goes first: a concurrent or repeated tick
+/// loses it and publishes and sends nothing.
+async fn finalize_cashu_release(ctx: &AppContext, order: &Order) -> Result<bool, MostroError> {
+    let pool = ctx.pool();
+    let buyer_pubkey = order.get_buyer_pubkey().map_err(MostroInternalErr)?;
+    let seller_pubkey = order.get_seller_pubkey().map_err(MostroInternalErr)?;
+    let from = order.get_order_status().map_err(MostroInternalErr)?;
+    let to = cashu_completed_status(from).ok_or(MostroCantDo(CantDoReason::NotAllowedByStatus))?;
+
+    let result = sqlx::query("UPDATE orders SET status = $1 WHERE id = $2 AND status = $3")
+        .bind(to.to_string())
+        .bind(order.id)
+        .bind(from.to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    // The status is already the source of truth: a failed publish is left to
+    // the orderbook reconciler rather than undoing the completed trade.
+    match update_order_event(ctx.keys(), to, order).await {
+        Ok(order_updated) => {
+            sqlx::query("UPDATE orders SET event_id = $1 WHERE id = $2 AND status = $3")
+                .bind(&order_updated.event_id)
+                .bind(order.id)
+                .bind(to.to_string())
+                .execute(pool)
+                .await
+                .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+        }
+        Err(e) => {
+            warn!(
+                "cashu release: order {} completed but its event was not published: {e}",
+                order.id
+            );
+            mark_orderbook_publish_failed(order.id);
+        }
+    }
+    crate::rpc::events::order_status_changed(order.id, &to.to_string());
+    info!(
+        "cashu release: order {} redeemed by the buyer — {}",
+        order.id, to
+    );
+
+    for pubkey in [buyer_pubkey, seller_pubkey] {
+        enqueue_order_msg(
+            None,
+            Some(order.id),
+            Action::PurchaseCompleted,
+            None,
+            pubkey,
+            None,
+        )
+        .await;
+        enqueue_order_msg(None, Some(order.id), Action::Rate, None, pubkey, None).await;
+    }
+    Ok(true)
+}
+
+/// The status a released Cashu order completes to once the buyer's redeem is
+/// observed: a seller release ends in `Success`, a solver's ruling for the
+/// buyer in `CompletedByAdmin`, as on the Lightning path.
+fn cashu_completed_status(released: Status) -> Option<Status> {
+    match released {
+        Status::SettledHoldInvoice => Some(Status::Success),
+        Status::SettledByAdmin => Some(Status::CompletedByAdmin),
+        _ => None,
+    }
+}
+
 /// Helper function to handle buy order case in child order creation
 fn handle_buy_child_order(
     child_order: &mut Order,
@@ -512,7 +851,7 @@ async fn handle_child_order(
     mut child_order: Order,
     order: &Order,
     next_trade: Option<(String, u32)>,
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     request_id: Option<u64>,
 ) -> Result<(), MostroError> {
     // Check if users are in rating mode or full privacy mode - if a key is Some the user in in normal mode
@@ -552,9 +891,12 @@ async fn handle_child_order(
 
     // Create the child order in database before queueing its notification,
     // so the queue never delivers a NewOrder message for a row that does
-    // not exist (e.g. on a transient insert failure).
-    child_order
-        .create(pool)
+    // not exist (e.g. on a transient insert failure). The child belongs to
+    // the instance of the order it was split from.
+    let owner = crate::instance::order_instance_id(pool, order.id)
+        .await?
+        .unwrap_or_else(|| crate::instance::DEFAULT_INSTANCE.to_string());
+    create_order_as(pool, child_order, &owner)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
 
@@ -591,6 +933,10 @@ async fn handle_child_order(
 /// bookkeeping from the background task. Callers such as `release_action`
 /// ignore the result after hold settlement — retries are driven by the
 /// failed-payment job.
+///
+/// A BOLT12 offer is paid with an invoice fetched from it on every attempt
+/// ([`crate::lightning::bolt12::fetch_payout_invoice`]); a bitcoin address
+/// through a reverse swap instead ([`crate::swap::payout::swap_payout`]).
 pub async fn do_payment(
     ctx: &AppContext,
     order: Order,
@@ -601,6 +947,10 @@ pub async fn do_payment(
         _ => return Err(MostroInternalErr(ServiceError::InvoiceInvalidError)),
     };
 
+    if crate::swap::is_swap_payout(&order) {
+        return crate::swap::payout::swap_payout(ctx, &order, request_id).await;
+    }
+
     let ln_addr = LightningAddress::from_str(&payment_request);
     // Calculate buyer's portion after subtracting only the Mostro fee
     // Dev fee is NOT charged to buyer - it's paid by mostrod from its earnings
@@ -665,6 +1015,25 @@ pub async fn do_payment(
         None => payment_request,
     };
 
+    // A BOLT12 offer is reusable: every attempt fetches a fresh invoice for
+    // the exact payout, so a retry never pays a stale one. Fetch and
+    // validation failures are failed attempts, as for a payout address.
+    let payment_request = if crate::lightning::bolt12::is_offer(&payment_request) {
+        match crate::lightning::bolt12::fetch_payout_invoice(&payment_request, amount).await {
+            Ok(invoice) => invoice,
+            Err(e) => {
+                warn!(
+                    "Order id {}: could not get an invoice from the payout offer: {:?}",
+                    order.id, e
+                );
+                check_failure_retries_or_log(ctx, &order, request_id).await;
+                return Err(e);
+            }
+        }
+    } else {
+        payment_request
+    };
+
     // Resolve the buyer pubkey *before* claiming: a malformed order fails
     // here without a claim, so no marker is ever left set for a payout that
     // was never dispatched.
@@ -673,7 +1042,7 @@ pub async fn do_payment(
     // Connect to LND *before* claiming: if the connection fails, `?` returns
     // here without a claim, so a transient connect blip never leaves a marker
     // set with no payment behind it.
-    let mut ln_client_payment = LndConnector::new().await?;
+    let mut ln_client_payment = crate::escrow::connect_lightning().await?;
 
     // Idempotency claim: persist the payout invoice's `payment_hash` (and the
     // claim timestamp) immediately before dispatch. While the marker is set,
@@ -687,8 +1056,8 @@ pub async fn do_payment(
     // (`touch_order_payout_claim`) after its semaphore wait, so the window
     // between the (refreshed) claim and LND registering the payment is only
     // the send call itself even when the task queued behind a backlog.
-    let payout_hash = decode_invoice(&payment_request)
-        .map(|inv| bytes_to_string(inv.payment_hash().as_ref()))
+    let payout_hash = crate::lightning::bolt12::payment_hash_and_amount(&payment_request)
+        .map(|(hash, _)| bytes_to_string(&hash))
         .map_err(|_| MostroInternalErr(ServiceError::InvoiceInvalidError))?;
     let Some(payout_claimed_at) =
         crate::db::claim_order_payout(ctx.pool(), order.id, &payout_hash).await?
@@ -714,7 +1083,7 @@ pub async fn do_payment(
     // this task (RPC error, timeout, process restart), reconciliation can
     // always finish or fail the payout by hash, so it is never lost or paid
     // twice.
-    tokio::spawn(async move {
+    crate::instance::spawn(async move {
         // The claim token; refreshed by every successful touch below.
         let mut payout_claimed_at = payout_claimed_at;
 
@@ -877,7 +1246,7 @@ pub async fn do_payment(
                 }
             }
         };
-        tokio::spawn(watcher);
+        crate::instance::spawn(watcher);
 
         let send_outcome = timeout(
             PAYOUT_SEND_PAYMENT_TIMEOUT,
@@ -1007,7 +1376,7 @@ fn classify_dispatch(
 ///
 /// Buyer notifications (`PurchaseCompleted`, `Rate`) are enqueued only after a
 /// successful commit, so a retried finalization never spams the buyer.
-async fn payment_success(
+pub(crate) async fn payment_success(
     ctx: &AppContext,
     order: &mut Order,
     buyer_pubkey: PublicKey,
@@ -1028,7 +1397,7 @@ async fn payment_success(
     // concurrent processes (dev_fee_paid, dev_fee_payment_hash, etc.)
     // The WHERE guard prevents double success transitions from concurrent tasks.
     let result =
-        sqlx::query("UPDATE orders SET status = ?, event_id = ? WHERE id = ? AND status = ?")
+        sqlx::query("UPDATE orders SET status = $1, event_id = $2 WHERE id = $3 AND status = $4")
             .bind(&order_updated.status)
             .bind(&order_updated.event_id)
             .bind(order_updated.id)
@@ -1049,6 +1418,8 @@ async fn payment_success(
     }
 
     // Committed by us — notify the buyer now.
+    crate::rpc::events::order_status_changed(order_updated.id, &order_updated.status);
+    crate::rpc::events::payout_result(order_updated.id, true);
     enqueue_order_msg(
         None,
         Some(order_updated.id),
@@ -1087,7 +1458,7 @@ pub trait PayoutStatusLookup {
     >;
 }
 
-impl PayoutStatusLookup for LndConnector {
+impl<E: EscrowBackend + ?Sized> PayoutStatusLookup for E {
     fn lookup_payment_status<'a>(
         &'a mut self,
         payment_hash: &'a [u8],
@@ -1098,7 +1469,7 @@ impl PayoutStatusLookup for LndConnector {
                 + 'a,
         >,
     > {
-        Box::pin(async move { LndConnector::lookup_payment_status(self, payment_hash).await })
+        EscrowBackend::lookup_payment_status(self, payment_hash)
     }
 }
 
@@ -1125,9 +1496,9 @@ impl PayoutStatusLookup for LndConnector {
 /// `payout_claimed_at` is the per-claim token observed for this marker; every
 /// release is scoped to it so a claim replaced between the snapshot and here is
 /// never clobbered.
-pub async fn reconcile_inflight_payout(
+pub async fn reconcile_inflight_payout<L: PayoutStatusLookup + ?Sized>(
     ctx: &AppContext,
-    ln_client: &mut impl PayoutStatusLookup,
+    ln_client: &mut L,
     order_id: uuid::Uuid,
     payout_payment_hash: &str,
     payout_claimed_at: Option<i64>,
@@ -1298,10 +1669,14 @@ async fn create_order_event(
     let tags = match crate::db::is_user_present(pool, identity_pubkey.to_string()).await {
         Ok(user) => order_to_tags(
             new_order,
-            Some((user.total_rating, user.total_reviews, user.created_at)),
+            Some(crate::reputation::profile(pool, &user).await?),
+            Some(&mostro_pubkey),
+        )?,
+        Err(_) => order_to_tags(
+            new_order,
+            Some(crate::reputation::Reputation::unrated()),
             Some(&mostro_pubkey),
         )?,
-        Err(_) => order_to_tags(new_order, Some((0.0, 0, 0)), Some(&mostro_pubkey))?,
     };
 
     // Prepare new child order event for sending (kind 38383 for orders).
@@ -1353,10 +1728,11 @@ mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
     use crate::app::context::AppContext;
-    use crate::config::{MESSAGE_QUEUES, MOSTRO_CONFIG};
+    use crate::config::MOSTRO_CONFIG;
+    use crate::db::DbPool;
     use async_trait::async_trait;
+    use fedimint_tonic_lnd::lnrpc::invoice::InvoiceState;
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
     /// The `MOSTRO_CONFIG` OnceLock is process-global: set it to the shared
@@ -1365,13 +1741,11 @@ mod tests {
         let _ = MOSTRO_CONFIG.set(test_settings());
     }
 
-    async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    fn build_ctx(pool: &SqlitePool) -> AppContext {
+    fn build_ctx(pool: &DbPool) -> AppContext {
         TestContextBuilder::new()
             .with_pool(Arc::new(pool.clone()))
             .with_settings(test_settings())
@@ -1425,9 +1799,7 @@ mod tests {
     /// The queue is shared across concurrently running tests, so assertions
     /// must always filter by our own order id.
     async fn queued_actions_for(order_id: uuid::Uuid) -> Vec<Action> {
-        MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(msg, _)| msg.get_inner_message_kind().id == Some(order_id))
@@ -1721,6 +2093,37 @@ mod tests {
         assert!(actions.contains(&Action::Rate));
     }
 
+    #[tokio::test]
+    async fn release_action_settles_the_sellers_held_invoice() {
+        init_global_config();
+        let pool = create_test_pool().await;
+        let ctx = build_ctx(&pool);
+        let seller = Keys::generate().public_key();
+        let buyer = Keys::generate().public_key();
+        let mut escrow = crate::escrow::memory::MemoryEscrow::new();
+        let (_, _, hash) = escrow.create_hold_invoice("trade", 21_021).await.unwrap();
+        let hash = bytes_to_string(&hash);
+        escrow.accept(&hash);
+        let mut order = fiat_sent_sell_order(seller, buyer);
+        order.preimage = escrow.preimage(&hash);
+        order.hash = Some(hash.clone());
+        let order = order.create(&pool).await.unwrap();
+
+        let result = release_action(
+            &ctx,
+            release_message(order.id, None),
+            &create_unwrapped_message_with_pubkey(seller),
+            &Keys::generate(),
+            &mut escrow,
+        )
+        .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(escrow.invoice_state(&hash), Some(InvoiceState::Settled));
+        let db_order = Order::by_id(&pool, order.id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, Status::SettledHoldInvoice.to_string());
+    }
+
     #[tokio::test]
     async fn release_action_closes_open_dispute() {
         // Arrange: disputed order with an open dispute row.
@@ -1785,7 +2188,7 @@ mod tests {
         // Assert: a pending child order carries the remaining range.
         assert!(result.is_ok());
         let child: Order =
-            sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE range_parent_id = ?")
+            sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE range_parent_id = $1")
                 .bind(order.id)
                 .fetch_one(&pool)
                 .await
@@ -1826,7 +2229,7 @@ mod tests {
 
         // Assert: release completes, no child order was persisted.
         assert!(result.is_ok());
-        let children = sqlx::query("SELECT id FROM orders WHERE range_parent_id = ?")
+        let children = sqlx::query("SELECT id FROM orders WHERE range_parent_id = $1")
             .bind(order.id)
             .fetch_all(&pool)
             .await
@@ -1862,7 +2265,7 @@ mod tests {
         assert!(result.is_ok());
         let db_order = Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert_eq!(db_order.status, Status::SettledHoldInvoice.to_string());
-        let children = sqlx::query("SELECT id FROM orders WHERE range_parent_id = ?")
+        let children = sqlx::query("SELECT id FROM orders WHERE range_parent_id = $1")
             .bind(order.id)
             .fetch_all(&pool)
             .await
@@ -2294,8 +2697,8 @@ mod tests {
     #[tokio::test]
     async fn do_payment_fails_fast_when_lnd_is_unreachable() {
         // Arrange: with the global config set to test defaults, the LND cert
-        // path is invalid, so LndConnector::new() returns an error without
-        // any network access.
+        // path is invalid, so connect_lightning() (LND by default) returns an
+        // error without any network access.
         init_global_config();
         let pool = create_test_pool().await;
         let ctx = build_ctx(&pool);
@@ -2596,7 +2999,7 @@ mod tests {
 
     /// Create a `settled-hold-invoice` order and claim a payout marker on it,
     /// returning `(order_id, payout_hash, claim_token)`.
-    async fn settled_order_with_marker(pool: &SqlitePool) -> (uuid::Uuid, String, i64) {
+    async fn settled_order_with_marker(pool: &DbPool) -> (uuid::Uuid, String, i64) {
         let seller = Keys::generate().public_key();
         let buyer = Keys::generate().public_key();
         let mut order = fiat_sent_sell_order(seller, buyer);
@@ -2610,9 +3013,9 @@ mod tests {
         (order.id, hash, token)
     }
 
-    async fn marker_of(pool: &SqlitePool, id: uuid::Uuid) -> Option<String> {
+    async fn marker_of(pool: &DbPool, id: uuid::Uuid) -> Option<String> {
         sqlx::query_scalar::<_, Option<String>>(
-            "SELECT payout_payment_hash FROM orders WHERE id = ?",
+            "SELECT payout_payment_hash FROM orders WHERE id = $1",
         )
         .bind(id)
         .fetch_one(pool)
@@ -2710,7 +3113,7 @@ mod tests {
         let order = order.create(&pool).await.unwrap();
         // Force a corrupt (non-32-byte) marker directly.
         sqlx::query(
-            "UPDATE orders SET payout_payment_hash = ?, payout_claimed_at = ? WHERE id = ?",
+            "UPDATE orders SET payout_payment_hash = $1, payout_claimed_at = $2 WHERE id = $3",
         )
         .bind("abc")
         .bind(1000_i64)
@@ -2735,4 +3138,145 @@ mod tests {
         let db_order = Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert!(db_order.failed_payment, "malformed marker re-arms retry");
     }
+
+    fn released_cashu_order() -> Order {
+        let seller = Keys::generate().public_key();
+        let buyer = Keys::generate().public_key();
+        Order {
+            status: Status::SettledHoldInvoice.to_string(),
+            cashu_escrow_token: Some("cashuBtoken".to_string()),
+            event_id: "old-event".to_string(),
+            ..fiat_sent_sell_order(seller, buyer)
+        }
+    }
+
+    #[tokio::test]
+    async fn a_cashu_release_lost_to_a_concurrent_transition_publishes_nothing() {
+        init_global_config();
+        let _ = crate::NOSTR_CLIENT.set(Client::default());
+        let pool = create_test_pool().await;
+        let ctx = build_ctx(&pool);
+        let order = released_cashu_order().create(&pool).await.unwrap();
+        // A concurrent ruling moved the order on after the watcher read it.
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
+            .bind(Status::Dispute.to_string())
+            .bind(order.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(!finalize_cashu_release(&ctx, &order).await.unwrap());
+        let stored = Order::by_id(&pool, order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, Status::Dispute.to_string());
+        assert_eq!(stored.event_id, "old-event");
+        assert!(queued_actions_for(order.id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_redeemed_cashu_release_completes_once() {
+        init_global_config();
+        let _ = crate::NOSTR_CLIENT.set(Client::default());
+        let pool = create_test_pool().await;
+        let ctx = build_ctx(&pool);
+        let order = released_cashu_order().create(&pool).await.unwrap();
+
+        assert!(finalize_cashu_release(&ctx, &order).await.unwrap());
+        let stored = Order::by_id(&pool, order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, Status::Success.to_string());
+        assert_ne!(stored.event_id, "old-event");
+        assert_eq!(queued_actions_for(order.id).await.len(), 4);
+
+        // A second tick with the stale row changes and sends nothing
+        assert!(!finalize_cashu_release(&ctx, &order).await.unwrap());
+        assert_eq!(queued_actions_for(order.id).await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn an_ambiguous_cashu_release_alerts_solvers_once_and_leaves_the_watcher() {
+        init_global_config();
+        let pool = create_test_pool().await;
+        let admin = Keys::generate().public_key();
+        let solver = Keys::generate().public_key();
+        sqlx::query("INSERT INTO users (pubkey, is_solver, created_at) VALUES ($1, 1, 0)")
+            .bind(solver.to_hex())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut settings = test_settings();
+        settings.mostro.dispute_admin_pubkey = Some(admin.to_hex());
+        let ctx = TestContextBuilder::new()
+            .with_pool(Arc::new(pool.clone()))
+            .with_settings(settings)
+            .build();
+        let order = released_cashu_order().create(&pool).await.unwrap();
+        let alerted = || async {
+            crate::outbox::test_utils::queued("order")
+                .await
+                .into_iter()
+                .filter(|(msg, _)| msg.get_inner_message_kind().id == Some(order.id))
+                .map(|(msg, destination)| {
+                    assert_eq!(msg.get_inner_message_kind().action, Action::Dispute);
+                    destination
+                })
+                .collect::<std::collections::BTreeSet<_>>()
+        };
+
+        hold_ambiguous_cashu_release(&ctx, &order, 1_000)
+            .await
+            .unwrap();
+        assert_eq!(
+            alerted().await,
+            std::collections::BTreeSet::from([admin, solver])
+        );
+        let stored = Order::by_id(&pool, order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, Status::SettledHoldInvoice.to_string());
+        assert!(crate::db::find_released_cashu_orders(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|o| o.id != order.id));
+
+        // A repeated tick sends nothing more
+        hold_ambiguous_cashu_release(&ctx, &order, 1_000)
+            .await
+            .unwrap();
+        assert_eq!(queued_actions_for(order.id).await.len(), 2);
+    }
+
+    #[test]
+    fn cashu_completed_status_maps_both_release_paths() {
+        assert_eq!(
+            cashu_completed_status(Status::SettledHoldInvoice),
+            Some(Status::Success)
+        );
+        assert_eq!(
+            cashu_completed_status(Status::SettledByAdmin),
+            Some(Status::CompletedByAdmin)
+        );
+        assert_eq!(cashu_completed_status(Status::Dispute), None);
+    }
+
+    #[test]
+    fn cashu_release_outcome_only_completes_spends_before_locktime() {
+        let locktime = 1_000_000;
+        assert_eq!(
+            cashu_release_outcome(EscrowSpendState::Spent, locktime, locktime - 1),
+            CashuReleaseOutcome::Redeemed
+        );
+        // At or after the locktime the seller's reclaim path is open too.
+        assert_eq!(
+            cashu_release_outcome(EscrowSpendState::Spent, locktime, locktime),
+            CashuReleaseOutcome::Ambiguous
+        );
+        for state in [EscrowSpendState::Unspent, EscrowSpendState::Mixed] {
+            assert_eq!(
+                cashu_release_outcome(state, locktime, locktime - 1),
+                CashuReleaseOutcome::Waiting
+            );
+            assert_eq!(
+                cashu_release_outcome(state, locktime, locktime + 1),
+                CashuReleaseOutcome::Waiting
+            );
+        }
+    }
 }
@@ -27,11 +27,16 @@ pub async fn restore_session_action(
 
     // Start the background processing
     manager
-        .start_restore_session(pool_clone, master_key.clone())
+        .start_restore_session(
+            pool_clone,
+            master_key.clone(),
+            crate::instance::current_id().to_string(),
+        )
         .await?;
 
-    // Start a background task to handle the results
-    tokio::spawn(async move {
+    // Start a background task to handle the results, replying as the
+    // instance the request was addressed to
+    crate::instance::spawn(async move {
         handle_restore_session_results(manager, trade_key).await;
     });
 
@@ -113,15 +118,13 @@ async fn send_restore_session_timeout(trade_key: &str) -> Result<(), MostroError
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-    use crate::config::MESSAGE_QUEUES;
+    use crate::db::DbPool;
+    use crate::instance::DEFAULT_INSTANCE;
     use nostr_sdk::prelude::Keys;
-    use sqlx::SqlitePool;
     use std::sync::Arc;
 
-    async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn create_event(identity: PublicKey, sender: PublicKey) -> UnwrappedMessage {
@@ -138,9 +141,7 @@ mod tests {
     /// is a global shared across concurrently running tests, so assertions
     /// always filter by destination key.
     async fn queued_restore_msgs_for(dest: &PublicKey) -> Vec<Message> {
-        MESSAGE_QUEUES
-            .queue_restore_session_msg
-            .read()
+        crate::outbox::test_utils::queued("restore")
             .await
             .iter()
             .filter(|(_, key)| key == dest)
@@ -152,6 +153,10 @@ mod tests {
     /// validation guards pass and the background restore session starts.
     /// (The hex-format guards on `master_key`/`trade_key` are dead code for
     /// real `PublicKey` values — see module report.)
+    ///
+    /// The test waits for the reply: the restore runs on a blocking thread
+    /// that the runtime waits for on shutdown, and a query still in flight
+    /// when the runtime's I/O driver stops never completes.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn restore_session_action_starts_background_restore() {
         let pool = create_test_pool().await;
@@ -167,6 +172,13 @@ mod tests {
         let result = restore_session_action(&ctx, &event).await;
 
         assert!(result.is_ok());
+        for _ in 0..500 {
+            if !queued_restore_msgs_for(&trade).await.is_empty() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the background restore never replied");
     }
 
     /// Drives `handle_restore_session_results` through the `Ok(Some(_))`
@@ -181,7 +193,7 @@ mod tests {
 
         let manager = RestoreSessionManager::new();
         manager
-            .start_restore_session(pool.clone(), master_key)
+            .start_restore_session(pool.clone(), master_key, DEFAULT_INSTANCE.to_string())
             .await
             .unwrap();
 
@@ -204,7 +216,7 @@ mod tests {
 
         let manager = RestoreSessionManager::new();
         manager
-            .start_restore_session(pool.clone(), master_key)
+            .start_restore_session(pool.clone(), master_key, DEFAULT_INSTANCE.to_string())
             .await
             .unwrap();
 
@@ -216,6 +216,7 @@ pub async fn take_buy_action(
 
     // Show hold invoice and return success or error
     if let Err(cause) = show_hold_invoice(
+        pool,
         my_keys,
         None,
         &buyer_pubkey,
@@ -237,20 +238,16 @@ pub async fn take_buy_action(
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-    use mostro_core::db::Crud;
-    use sqlx::SqlitePool;
+    use crate::db::Crud;
+    use crate::db::DbPool;
     use std::sync::Arc;
 
-    async fn setup_pool() -> Arc<SqlitePool> {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+    async fn setup_pool() -> Arc<DbPool> {
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         pool
     }
 
-    fn build_ctx(pool: Arc<SqlitePool>) -> AppContext {
+    fn build_ctx(pool: Arc<DbPool>) -> AppContext {
         // With `anti_abuse_bond = None`, `taker_bond_required()` is false,
         // so takes flow through the legacy `show_hold_invoice` path.
         let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
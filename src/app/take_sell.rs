@@ -2,6 +2,7 @@ use crate::app::bond;
 use crate::app::bond::TakerContext;
 use crate::app::context::AppContext;
 use crate::config::settings::Settings;
+use crate::db::DbPool;
 use crate::db::{buyer_has_pending_order, update_user_trade_index};
 use crate::util::{
     enqueue_order_msg, get_dev_fee, get_fiat_amount_requested, get_market_amount_and_fee,
@@ -10,12 +11,11 @@ use crate::util::{
 };
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::{Pool, Sqlite};
 
 async fn update_order_status(
     order: &mut Order,
     my_keys: &Keys,
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     request_id: Option<u64>,
 ) -> Result<(), MostroError> {
     // Get buyer pubkey
@@ -267,6 +267,7 @@ pub async fn take_sell_action(
     // If payment request is present, show hold invoice
     else {
         show_hold_invoice(
+            pool,
             my_keys,
             payment_request,
             &event.sender,
@@ -306,12 +307,12 @@ async fn validate_buyer_invoice(
 mod tests {
     use super::*;
 
+    use crate::db::DbPool;
     use mostro_core::order::{Kind as OrderKind, Status};
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
 
-    async fn create_test_pool() -> SqlitePool {
-        SqlitePool::connect(":memory:").await.unwrap()
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn create_test_keys() -> Keys {
@@ -319,7 +320,7 @@ mod tests {
     }
 
     /// Helper to build a test AppContext with the given pool.
-    fn build_test_context(pool: SqlitePool) -> AppContext {
+    fn build_test_context(pool: DbPool) -> AppContext {
         use crate::app::context::test_utils::{test_settings, TestContextBuilder};
         TestContextBuilder::new()
             .with_pool(std::sync::Arc::new(pool))
@@ -572,20 +573,16 @@ mod tests {
     mod handler_flow_tests {
         use super::*;
         use crate::app::context::test_utils::{test_settings, TestContextBuilder};
-        use mostro_core::db::Crud;
+        use crate::db::Crud;
         use nostr_sdk::prelude::PublicKey;
         use std::sync::Arc;
 
-        async fn setup_pool() -> Arc<SqlitePool> {
-            let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-            sqlx::migrate!("./migrations")
-                .run(pool.as_ref())
-                .await
-                .unwrap();
+        async fn setup_pool() -> Arc<DbPool> {
+            let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
             pool
         }
 
-        fn build_ctx(pool: Arc<SqlitePool>) -> AppContext {
+        fn build_ctx(pool: Arc<DbPool>) -> AppContext {
             let _ = crate::config::MOSTRO_CONFIG.set(test_settings());
             TestContextBuilder::new()
                 .with_pool(pool)
@@ -1,7 +1,7 @@
 use crate::app::context::AppContext;
 use crate::util::{enqueue_order_msg, get_order};
 
-use mostro_core::db::Crud;
+use crate::db::Crud;
 use mostro_core::order::Kind as OrderKind;
 use mostro_core::prelude::*;
 
@@ -87,17 +87,13 @@ pub async fn trade_pubkey_action(
 mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+    use crate::db::DbPool;
     use nostr_sdk::prelude::{Keys, PublicKey, Timestamp};
-    use sqlx::SqlitePool;
     use std::sync::Arc;
     use uuid::Uuid;
 
     async fn setup_ctx() -> AppContext {
-        let pool = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .unwrap();
+        let pool = Arc::new(crate::db::test_utils::migrated_pool().await);
         TestContextBuilder::new()
             .with_pool(pool)
             .with_settings(test_settings())
@@ -157,7 +153,7 @@ mod tests {
         order
     }
 
-    async fn order_by_id(pool: &SqlitePool, id: Uuid) -> Order {
+    async fn order_by_id(pool: &DbPool, id: Uuid) -> Order {
         Order::by_id(pool, id).await.unwrap().unwrap()
     }
 
@@ -197,9 +193,7 @@ mod tests {
         assert_eq!(after.creator_pubkey, new_trade_key.to_string());
         // The confirmation goes to the process-global queue; filter by
         // this test's unique trade key to stay isolated.
-        let confirmations = crate::config::MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        let confirmations = crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(m, pk)| {
@@ -0,0 +1,312 @@
+//! Online backup and restore of the SQLite database.
+//!
+//! Backups use `VACUUM INTO`, which copies the database inside one read
+//! transaction: the snapshot is consistent while mostrod keeps writing, and
+//! unlike a file copy it never catches a half-applied WAL. A restore checks
+//! the backup before it replaces anything — it must pass
+//! `PRAGMA integrity_check` and must not carry migrations this binary does not
+//! know — and keeps the replaced database next to it.
+
+use crate::cli::Command;
+use crate::config::settings::Settings;
+use crate::db::{check_sqlite_url, find_non_terminal_orders};
+use mostro_core::error::MostroError::{self, *};
+use mostro_core::error::ServiceError;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+/// File name prefix of scheduled backups; pruning only touches these.
+const SCHEDULED_PREFIX: &str = "mostro-";
+
+fn db_err(e: impl ToString) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+/// Filesystem path behind a `sqlite://` url, as `db::connect` reads it.
+pub fn sqlite_path(db_url: &str) -> PathBuf {
+    PathBuf::from(db_url.replace("sqlite://", ""))
+}
+
+/// Owner-only permissions, matching the database `db::connect` creates.
+fn restrict_permissions(path: &Path) -> Result<(), MostroError> {
+    #[cfg(unix)]
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(db_err)?;
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Write a consistent snapshot of `pool` to `dest`, which must not exist.
+pub async fn backup_to(pool: &SqlitePool, dest: &Path) -> Result<(), MostroError> {
+    if dest.exists() {
+        return Err(db_err(format!("{} already exists", dest.display())));
+    }
+    let dest_str = dest
+        .to_str()
+        .ok_or_else(|| db_err(format!("{} is not valid UTF-8", dest.display())))?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(dest_str)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    restrict_permissions(dest)
+}
+
+/// Back up into `dir` under a timestamped name, then delete all but the
+/// newest `keep` scheduled backups there.
+pub async fn scheduled_backup(
+    pool: &SqlitePool,
+    dir: &Path,
+    keep: usize,
+) -> Result<PathBuf, MostroError> {
+    std::fs::create_dir_all(dir).map_err(db_err)?;
+    let name = format!(
+        "{SCHEDULED_PREFIX}{}.db",
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    let dest = dir.join(name);
+    backup_to(pool, &dest).await?;
+
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(db_err)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(SCHEDULED_PREFIX) && n.ends_with(".db"))
+        })
+        .collect();
+    // Timestamped names sort chronologically.
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep.max(1));
+    for old in &backups[..excess] {
+        if let Err(e) = std::fs::remove_file(old) {
+            warn!("Could not prune old backup {}: {e}", old.display());
+        }
+    }
+    Ok(dest)
+}
+
+/// Check that `backup` is a sound mostrod database this binary can migrate.
+pub async fn validate_backup(backup: &Path) -> Result<(), MostroError> {
+    if !backup.is_file() {
+        return Err(db_err(format!("{} is not a file", backup.display())));
+    }
+    let options = SqliteConnectOptions::new().filename(backup).read_only(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .map_err(db_err)?;
+
+    let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&pool)
+        .await
+        .map_err(db_err)?;
+    if integrity != "ok" {
+        return Err(db_err(format!("integrity check failed: {integrity}")));
+    }
+
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(&pool)
+            .await
+            .map_err(|e| db_err(format!("not a mostrod database: {e}")))?;
+    pool.close().await;
+
+    let known: HashSet<i64> = sqlx::migrate!().iter().map(|m| m.version).collect();
+    let unknown: Vec<i64> = applied.into_iter().filter(|v| !known.contains(v)).collect();
+    if !unknown.is_empty() {
+        return Err(db_err(format!(
+            "backup has migrations this mostrod does not know ({unknown:?}); restore it with a newer release"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate `backup` and copy it over `target`. An existing `target` (with
+/// its `-wal`/`-shm` files) is renamed to `<target>.pre-restore-<timestamp>`
+/// rather than deleted; that path is returned.
+pub async fn restore_file(backup: &Path, target: &Path) -> Result<Option<PathBuf>, MostroError> {
+    validate_backup(backup).await?;
+
+    let mut kept = None;
+    if target.exists() {
+        let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+        let aside = PathBuf::from(format!("{}.pre-restore-{stamp}", target.display()));
+        std::fs::rename(target, &aside).map_err(db_err)?;
+        for suffix in ["-wal", "-shm"] {
+            let side = PathBuf::from(format!("{}{suffix}", target.display()));
+            if side.exists() {
+                let side_aside = PathBuf::from(format!("{}{suffix}", aside.display()));
+                std::fs::rename(&side, side_aside).map_err(db_err)?;
+            }
+        }
+        kept = Some(aside);
+    }
+
+    std::fs::copy(backup, target).map_err(db_err)?;
+    restrict_permissions(target)?;
+    Ok(kept)
+}
+
+/// Run a maintenance subcommand against `[database].url`.
+pub async fn run(command: Command) -> crate::Result<()> {
+    let db_url = &Settings::get_db().url;
+    check_sqlite_url(db_url)?;
+
+    match command {
+        Command::Backup { path } => {
+            let pool = SqlitePool::connect(db_url).await.map_err(db_err)?;
+            backup_to(&pool, &path).await?;
+            println!("Backup written to {}", path.display());
+        }
+        Command::Restore { path } => {
+            let target = sqlite_path(db_url);
+            if let Some(aside) = restore_file(&path, &target).await? {
+                println!("Previous database kept at {}", aside.display());
+            }
+            // Brings the restored file up to this release's migrations.
+            let pool = crate::db::connect().await?;
+            let orders = find_non_terminal_orders(&pool).await?;
+            println!("Restored {} into {}", path.display(), target.display());
+            if orders.is_empty() {
+                println!("No orders in a non-terminal state.");
+            } else {
+                println!(
+                    "{} order(s) in a non-terminal state; check them against LND or the mint before starting mostrod:",
+                    orders.len()
+                );
+                for (id, status) in orders {
+                    println!("  {id}  {status}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Scheduler body for `[database].backup_dir`: one backup per
+/// `backup_interval_seconds`, starting at boot.
+pub async fn backup_loop(
+    pool: std::sync::Arc<SqlitePool>,
+    dir: PathBuf,
+    interval: u64,
+    keep: usize,
+) {
+    loop {
+        match scheduled_backup(&pool, &dir, keep).await {
+            Ok(path) => info!("Database backup written to {}", path.display()),
+            Err(e) => error!("Scheduled database backup failed: {e}"),
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval.max(60))).await;
+    }
+}
+
+#[cfg(all(test, not(feature = "postgres")))]
+mod tests {
+    use super::*;
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mostro-backup-{tag}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn migrated_file_pool(path: &Path) -> SqlitePool {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn insert_order(pool: &SqlitePool, status: &str) {
+        sqlx::query(
+            "INSERT INTO orders (id, kind, event_id, status, premium, payment_method, \
+             amount, fiat_code, fiat_amount, created_at, expires_at) \
+             VALUES ($1, 'sell', $2, $3, 0, 'ln', 100, 'USD', 10, 0, 0)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(uuid::Uuid::new_v4().simple().to_string())
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn backup_then_restore_round_trips_and_keeps_the_old_db() {
+        let dir = temp_dir("roundtrip");
+        let live = dir.join("mostro.db");
+        let pool = migrated_file_pool(&live).await;
+        insert_order(&pool, "active").await;
+        insert_order(&pool, "success").await;
+
+        let snapshot = dir.join("snapshot.db");
+        backup_to(&pool, &snapshot).await.unwrap();
+        assert!(backup_to(&pool, &snapshot).await.is_err());
+        pool.close().await;
+
+        let kept = restore_file(&snapshot, &live).await.unwrap().unwrap();
+        assert!(kept.exists());
+
+        let restored = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&live))
+            .await
+            .unwrap();
+        let open = find_non_terminal_orders(&restored).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].1, "active");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn validate_backup_refuses_unknown_migrations_and_garbage() {
+        let dir = temp_dir("validate");
+        let db = dir.join("future.db");
+        let pool = migrated_file_pool(&db).await;
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+             VALUES (99990101000000, 'from the future', TRUE, x'00', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+        let err = validate_backup(&db).await.unwrap_err();
+        assert!(err.to_string().contains("99990101000000"));
+
+        let garbage = dir.join("garbage.db");
+        std::fs::write(&garbage, b"not a database").unwrap();
+        assert!(validate_backup(&garbage).await.is_err());
+        assert!(validate_backup(&dir.join("missing.db")).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn scheduled_backup_prunes_to_keep() {
+        let dir = temp_dir("prune");
+        let pool = migrated_file_pool(&dir.join("mostro.db")).await;
+        let backups = dir.join("backups");
+        std::fs::create_dir_all(&backups).unwrap();
+        for stamp in ["20200101T000000Z", "20200102T000000Z"] {
+            std::fs::write(backups.join(format!("mostro-{stamp}.db")), b"old").unwrap();
+        }
+        std::fs::write(backups.join("unrelated.db"), b"keep me").unwrap();
+
+        let newest = scheduled_backup(&pool, &backups, 2).await.unwrap();
+        assert!(newest.exists());
+        assert!(!backups.join("mostro-20200101T000000Z.db").exists());
+        assert!(backups.join("mostro-20200102T000000Z.db").exists());
+        assert!(backups.join("unrelated.db").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -47,11 +47,11 @@ use crate::app::context::test_utils::{test_settings, TestContextBuilder};
 use crate::app::context::AppContext;
 use crate::cashu::{cashu_pubkey_from_xonly_hex, CashuClient};
 use crate::config::types::CashuSettings;
+use crate::db::Crud;
+use crate::db::DbPool;
 use chrono::Utc;
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::SqlitePool;
 
 /// Seconds in a day — the locktime floor is configured in days.
 const SECONDS_PER_DAY: u64 = 86_400;
@@ -248,16 +248,14 @@ async fn mint_escrow_token(
     Token::new(url, proofs, None, CurrencyUnit::Sat).to_string()
 }
 
-async fn temp_pool() -> SqlitePool {
-    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-    sqlx::migrate!().run(&pool).await.unwrap();
-    pool
+async fn temp_pool() -> DbPool {
+    crate::db::test_utils::migrated_pool().await
 }
 
 /// A taken Cashu order parked in `WaitingPayment` — the state TA-2 will
 /// produce, seeded here by hand because `dispatch_cashu` still rejects
 /// `NewOrder`/`TakeSell` until TA-2 lands.
-async fn seed_waiting_payment_order(pool: &SqlitePool, keys: &EscrowKeys, amount: u64) -> Order {
+async fn seed_waiting_payment_order(pool: &DbPool, keys: &EscrowKeys, amount: u64) -> Order {
     Order {
         id: uuid::Uuid::new_v4(),
         status: Status::WaitingPayment.to_string(),
@@ -304,7 +302,7 @@ fn seller_event(keys: &EscrowKeys) -> UnwrappedMessage {
     }
 }
 
-async fn build_ctx(pool: &SqlitePool, mint_url: &str) -> AppContext {
+async fn build_ctx(pool: &DbPool, mint_url: &str) -> AppContext {
     let client = CashuClient::connect(mint_url)
         .await
         .expect("the mint must satisfy NUT-07/11/12 and expose an active sat keyset");
@@ -334,6 +332,7 @@ async fn escrow_lock_end_to_end_against_a_live_mint() {
         enabled: true,
         mint_url: mint_url.clone(),
         escrow_locktime_days: days as u32,
+        ..CashuSettings::default()
     });
     let _ = crate::config::settings::init_mostro_settings(settings);
 
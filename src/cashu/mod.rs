@@ -18,10 +18,13 @@ use cdk::mint_url::MintUrl;
 use cdk::nuts::nut02::ShortKeysetId;
 use cdk::nuts::nut10::{Secret as Nut10Secret, SpendingConditions, TagKind};
 use cdk::nuts::{
-    CheckStateRequest, CheckStateResponse, CurrencyUnit, PublicKey, SigFlag, State, Token,
+    CheckStateRequest, CheckStateResponse, CurrencyUnit, PublicKey, SecretKey, SigFlag, State,
+    Token,
 };
+use cdk::secp256k1::schnorr::Signature;
 use cdk::wallet::MintConnector;
 use cdk::HttpClient;
+use mostro_core::prelude::CashuProofSignature;
 
 /// Per-request bound on every mint HTTP call. `HttpClient` wraps a
 /// `reqwest` client with **no default timeout**, so without this a slow or
@@ -353,12 +356,7 @@ impl CashuClient {
 
         // 5: every proof must be unspent at the mint. Derive the checkstate
         // Y points from the proof secrets (Y = hash_to_curve(secret)).
-        let secrets = token.token_secrets();
-        let ys = secrets
-            .iter()
-            .map(|s| cdk::dhke::hash_to_curve(s.as_bytes()))
-            .collect::<Result<Vec<PublicKey>, _>>()
-            .map_err(|e| Error::Token(format!("proof Y: {e}")))?;
+        let ys = escrow_proof_ys(&token)?;
         let expected_states = ys.len();
         let states = self.check_state(ys).await?;
         // Fail closed if the mint returns fewer states than proofs queried:
@@ -390,6 +388,19 @@ impl CashuClient {
         Ok(response)
     }
 
+    /// Where a locked escrow token stands at the mint (Track B §5C).
+    ///
+    /// Parses the stored token, queries NUT-07 for every proof and folds the
+    /// answers with [`classify_escrow_states`]. Read-only: the release
+    /// watcher uses it to observe the buyer's redeem, never to move funds.
+    pub async fn escrow_spend_state(&self, token_str: &str) -> Result<EscrowSpendState, Error> {
+        let token = Token::from_str(token_str).map_err(|e| Error::Token(e.to_string()))?;
+        let ys = escrow_proof_ys(&token)?;
+        let expected = ys.len();
+        let response = self.check_state(ys).await?;
+        classify_escrow_states(&response.states, expected)
+    }
+
     /// Verify the NUT-12 DLEQ proof of every proof in a token — this
     /// authenticates the ecash as genuinely mint-issued — and that every
     /// proof belongs to a **`sat` keyset** (M-3: a token may mix keysets,
@@ -588,6 +599,219 @@ pub fn cashu_pubkey_from_xonly_hex(xonly_hex: &str) -> Result<PublicKey, Error>
         .map_err(|e| Error::Condition(format!("pubkey convert: {e}")))
 }
 
+/// Aggregate NUT-07 state of a locked escrow token (Track B §5C).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowSpendState {
+    /// Every proof is unspent — nobody has redeemed the escrow yet.
+    Unspent,
+    /// Every proof is spent — the escrow was redeemed.
+    Spent,
+    /// Anything else: some proofs spent and others not, or proofs pending or
+    /// reserved at the mint. Never treated as a completed redeem.
+    Mixed,
+}
+
+/// The checkstate `Y = hash_to_curve(secret)` point of every proof in a
+/// token, in proof order.
+pub fn escrow_proof_ys(token: &Token) -> Result<Vec<PublicKey>, Error> {
+    token
+        .token_secrets()
+        .iter()
+        .map(|s| cdk::dhke::hash_to_curve(s.as_bytes()))
+        .collect::<Result<Vec<PublicKey>, _>>()
+        .map_err(|e| Error::Token(format!("proof Y: {e}")))
+}
+
+/// Fold the per-proof NUT-07 answers for an escrow token into one
+/// [`EscrowSpendState`].
+///
+/// Fails closed, like [`CashuClient::verify_escrow_token`]: a mint that
+/// answers with fewer (or more) states than proofs queried is an error, so a
+/// missing entry can never be read as "spent" and complete a trade.
+pub fn classify_escrow_states(
+    states: &[cdk::nuts::ProofState],
+    expected: usize,
+) -> Result<EscrowSpendState, Error> {
+    if expected == 0 || states.len() != expected {
+        return Err(Error::Token(format!(
+            "checkstate returned {} states for {expected} proofs",
+            states.len()
+        )));
+    }
+    if states.iter().all(|s| s.state == State::Spent) {
+        Ok(EscrowSpendState::Spent)
+    } else if states.iter().all(|s| s.state == State::Unspent) {
+        Ok(EscrowSpendState::Unspent)
+    } else {
+        Ok(EscrowSpendState::Mixed)
+    }
+}
+
+/// The seller-recovery locktime (unix seconds) of a stored escrow token.
+///
+/// A token may carry several proofs, each with its own NUT-11 `locktime`;
+/// the **earliest** one is returned, since that is when the seller can start
+/// reclaiming part of the escrow alone. Every proof must be P2PK with a
+/// locktime — the lock handler only ever stores such tokens, so an error here
+/// means the row is corrupt.
+pub fn escrow_token_locktime(token_str: &str) -> Result<u64, Error> {
+    let token = Token::from_str(token_str).map_err(|e| Error::Token(e.to_string()))?;
+    let mut earliest: Option<u64> = None;
+    for secret in token.token_secrets() {
+        let locktime = match SpendingConditions::try_from(secret) {
+            Ok(SpendingConditions::P2PKConditions {
+                conditions: Some(c),
+                ..
+            }) => c.locktime,
+            Ok(_) => None,
+            Err(e) => return Err(Error::Condition(e.to_string())),
+        }
+        .ok_or_else(|| Error::Condition("escrow proof carries no locktime".into()))?;
+        earliest = Some(earliest.map_or(locktime, |e| e.min(locktime)));
+    }
+    earliest.ok_or_else(|| Error::Token("Token contains no secrets".into()))
+}
+
+/// Check one party's NUT-11 signatures over a stored escrow token
+/// (Track C §5): exactly one entry per proof, matched by `secret`, each a
+/// valid Schnorr signature by `signer` over that proof's secret.
+///
+/// Used before Mostro relays a buyer's cooperative-cancel signatures to the
+/// seller, so a malformed or foreign set is rejected up front instead of
+/// failing later at the seller's swap.
+pub fn verify_escrow_signatures(
+    token_str: &str,
+    signer: PublicKey,
+    signatures: &[(String, String)],
+) -> Result<(), Error> {
+    let token = Token::from_str(token_str).map_err(|e| Error::Token(e.to_string()))?;
+    let secrets: Vec<String> = token
+        .token_secrets()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if secrets.is_empty() {
+        return Err(Error::Token("Token contains no secrets".into()));
+    }
+    if signatures.len() != secrets.len() {
+        return Err(Error::Condition(format!(
+            "expected {} signatures, got {}",
+            secrets.len(),
+            signatures.len()
+        )));
+    }
+
+    let mut seen = HashSet::new();
+    for (secret, signature) in signatures {
+        if !secrets.contains(secret) {
+            return Err(Error::Condition("signature for a foreign proof".into()));
+        }
+        if !seen.insert(secret.as_str()) {
+            return Err(Error::Condition("duplicate signature for one proof".into()));
+        }
+        let sig = Signature::from_str(signature)
+            .map_err(|e| Error::Condition(format!("signature parse: {e}")))?;
+        signer
+            .verify(secret.as_bytes(), &sig)
+            .map_err(|e| Error::Condition(format!("signature verify: {e}")))?;
+    }
+    Ok(())
+}
+
+/// Mostro's NUT-11 P2PK signature over every proof of a stored escrow token
+/// (Track D §4), one `{secret, signature}` per proof.
+///
+/// Only called once a solver has ruled: the winner adds its own signature to
+/// complete the 2-of-3, so this alone never moves the escrow. The input is
+/// the stored token, so re-signing for a re-delivery yields an equally valid
+/// set.
+pub fn sign_with_pm(
+    token_str: &str,
+    p_m_secret: &SecretKey,
+) -> Result<Vec<CashuProofSignature>, Error> {
+    let token = Token::from_str(token_str).map_err(|e| Error::Token(e.to_string()))?;
+    let secrets = token.token_secrets();
+    if secrets.is_empty() {
+        return Err(Error::Token("Token contains no secrets".into()));
+    }
+    secrets
+        .iter()
+        .map(|s| {
+            let secret = s.to_string();
+            let signature = p_m_secret
+                .sign(secret.as_bytes())
+                .map_err(|e| Error::Condition(format!("sign: {e}")))?;
+            Ok(CashuProofSignature::new(secret, signature.to_string()))
+        })
+        .collect()
+}
+
+/// Offline escrow-token fixtures for handler tests outside this module.
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use cdk::nuts::nut00::Proof;
+    use cdk::nuts::nut01::SecretKey as NutSecretKey;
+    use cdk::nuts::nut02::Id;
+    use cdk::nuts::nut10::Conditions;
+    use cdk::secret::Secret;
+    use cdk::Amount;
+
+    /// Serialized single-proof 2-of-3 escrow token over `{p_b, p_s, p_m}`
+    /// with the seller-recovery pathway, shaped like the ones the lock
+    /// handler stores. Never touches a mint.
+    pub fn escrow_token(p_b: PublicKey, p_s: PublicKey, p_m: PublicKey, locktime: u64) -> String {
+        let secret: Secret = SpendingConditions::P2PKConditions {
+            data: p_s,
+            conditions: Some(Conditions {
+                locktime: Some(locktime),
+                pubkeys: Some(vec![p_b, p_m]),
+                refund_keys: Some(vec![p_s]),
+                num_sigs: Some(2),
+                sig_flag: SigFlag::SigInputs,
+                num_sigs_refund: None,
+            }),
+        }
+        .try_into()
+        .expect("valid p2pk secret");
+        let proof = Proof {
+            keyset_id: Id::from_str("009a1f293253e41e").unwrap(),
+            amount: Amount::from(100u64),
+            secret,
+            c: PublicKey::from_str(
+                "02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904",
+            )
+            .unwrap(),
+            witness: None,
+            dleq: None,
+            p2pk_e: None,
+        };
+        Token::new(
+            MintUrl::from_str("https://mint.example.com").unwrap(),
+            vec![proof],
+            None,
+            CurrencyUnit::Sat,
+        )
+        .to_string()
+    }
+
+    /// `(secret, signature)` for every proof of `token`, signed with the
+    /// hex secret key `secret_key_hex` the way a NUT-11 witness is.
+    pub fn sign_escrow_proofs(token: &str, secret_key_hex: &str) -> Vec<(String, String)> {
+        let sk = NutSecretKey::from_hex(secret_key_hex).unwrap();
+        Token::from_str(token)
+            .unwrap()
+            .token_secrets()
+            .iter()
+            .map(|s| {
+                let secret = s.to_string();
+                let sig = sk.sign(secret.as_bytes()).unwrap().to_string();
+                (secret, sig)
+            })
+            .collect()
+    }
+}
+
 /// Mint-backed end-to-end harness for the TA-1 escrow lock — `#[ignore]`d and
 /// env-gated, so it never runs in a plain `cargo test`.
 #[cfg(test)]
@@ -1414,4 +1638,112 @@ mod tests {
             "expected the unit guard to reject, got: {err}"
         );
     }
+
+    #[test]
+    fn escrow_token_locktime_reads_the_proof_locktime() {
+        let (p_b, p_s, p_m) = (keypair(1), keypair(2), keypair(3));
+        let token = valid_escrow_token(p_b, p_s, p_m);
+        assert_eq!(escrow_token_locktime(&token).unwrap(), LOCKTIME);
+    }
+
+    #[test]
+    fn escrow_token_locktime_rejects_missing_locktime_and_garbage() {
+        let (p_b, p_s, p_m) = (keypair(1), keypair(2), keypair(3));
+        let token = token_with_condition(p_s, vec![p_b, p_m], Some(2), None, Some(vec![p_s]), None);
+        assert!(escrow_token_locktime(&token).is_err());
+        assert!(escrow_token_locktime("not a token").is_err());
+    }
+
+    /// Sign every proof secret of `token` with the key behind `keypair(n)`.
+    fn sign_escrow(token: &str, n: u8) -> Vec<(String, String)> {
+        test_utils::sign_escrow_proofs(token, &format!("{:064x}", n as u64 + 1))
+    }
+
+    #[test]
+    fn verify_escrow_signatures_accepts_the_signers_set_only() {
+        let (p_b, p_s, p_m) = (keypair(1), keypair(2), keypair(3));
+        let token = valid_escrow_token(p_b, p_s, p_m);
+
+        let buyer_sigs = sign_escrow(&token, 1);
+        verify_escrow_signatures(&token, p_b, &buyer_sigs).unwrap();
+
+        // Someone else's signatures never pass as the buyer's.
+        let seller_sigs = sign_escrow(&token, 2);
+        assert!(verify_escrow_signatures(&token, p_b, &seller_sigs).is_err());
+    }
+
+    #[test]
+    fn sign_with_pm_produces_a_verifiable_signature_per_proof() {
+        let (p_b, p_s) = (keypair(1), keypair(2));
+        let p_m_secret = NutSecretKey::from_hex(format!("{:064x}", 4)).unwrap();
+        let token = valid_escrow_token(p_b, p_s, p_m_secret.public_key());
+
+        let sigs = sign_with_pm(&token, &p_m_secret).unwrap();
+        let pairs: Vec<(String, String)> = sigs
+            .iter()
+            .map(|s| (s.secret.clone(), s.signature.clone()))
+            .collect();
+        verify_escrow_signatures(&token, p_m_secret.public_key(), &pairs).unwrap();
+        // Someone else's key is not Mostro's signature.
+        assert!(verify_escrow_signatures(&token, p_b, &pairs).is_err());
+        assert!(sign_with_pm("not a token", &p_m_secret).is_err());
+    }
+
+    #[test]
+    fn verify_escrow_signatures_rejects_wrong_shape() {
+        let (p_b, p_s, p_m) = (keypair(1), keypair(2), keypair(3));
+        let token = valid_escrow_token(p_b, p_s, p_m);
+        let sigs = sign_escrow(&token, 1);
+
+        // Missing, duplicated, foreign-secret and unparsable entries.
+        assert!(verify_escrow_signatures(&token, p_b, &[]).is_err());
+        let doubled = [sigs.clone(), sigs.clone()].concat();
+        assert!(verify_escrow_signatures(&token, p_b, &doubled).is_err());
+        let foreign = vec![("not-a-proof".to_string(), sigs[0].1.clone())];
+        assert!(verify_escrow_signatures(&token, p_b, &foreign).is_err());
+        let garbage = vec![(sigs[0].0.clone(), "zz".to_string())];
+        assert!(verify_escrow_signatures(&token, p_b, &garbage).is_err());
+        assert!(verify_escrow_signatures("not a token", p_b, &sigs).is_err());
+    }
+
+    fn proof_states(states: &[State]) -> Vec<cdk::nuts::ProofState> {
+        states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| cdk::nuts::ProofState {
+                y: keypair(i as u8 + 10),
+                state: *state,
+                witness: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classify_escrow_states_folds_per_proof_answers() {
+        assert_eq!(
+            classify_escrow_states(&proof_states(&[State::Spent, State::Spent]), 2).unwrap(),
+            EscrowSpendState::Spent
+        );
+        assert_eq!(
+            classify_escrow_states(&proof_states(&[State::Unspent, State::Unspent]), 2).unwrap(),
+            EscrowSpendState::Unspent
+        );
+        for mixed in [
+            [State::Spent, State::Unspent],
+            [State::Spent, State::Pending],
+            [State::PendingSpent, State::Spent],
+        ] {
+            assert_eq!(
+                classify_escrow_states(&proof_states(&mixed), 2).unwrap(),
+                EscrowSpendState::Mixed
+            );
+        }
+    }
+
+    #[test]
+    fn classify_escrow_states_fails_closed_on_count_mismatch() {
+        // One state for two proofs must never read as "all spent".
+        assert!(classify_escrow_states(&proof_states(&[State::Spent]), 2).is_err());
+        assert!(classify_escrow_states(&[], 0).is_err());
+    }
 }
@@ -3,7 +3,8 @@
 //! CLI
 
 use crate::config::util::init_configuration_file;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
@@ -27,13 +28,32 @@ pub struct Cli {
     /// Set folder for Mostro settings file - default is HOME/.mostro
     #[arg(short, long)]
     dirsettings: Option<String>,
+    /// Run a maintenance command instead of the daemon
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Maintenance commands. Both read `[database].url` from the settings file.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Write a consistent snapshot of the database; safe while mostrod runs
+    Backup {
+        /// Destination file (must not exist)
+        path: PathBuf,
+    },
+    /// Replace the database with a backup; stop mostrod first
+    Restore {
+        /// Backup file written by `mostrod backup`
+        path: PathBuf,
+    },
 }
 
 /// Initialize the settings file and create the global config variable for Mostro settings
 /// Default folder is HOME but user can specify a custom folder with dirsettings (-d ) parameter from CLI
 /// Example: mostro p2p -d /user_folder/mostro
 ///
-pub fn settings_init() -> Result<(), Box<dyn std::error::Error>> {
+/// Returns the maintenance command to run instead of the daemon, if any.
+pub fn settings_init() -> Result<Option<Command>, Box<dyn std::error::Error>> {
     // Parse CLI arguments
     let cli = Cli::parse();
 
@@ -46,7 +66,7 @@ pub fn settings_init() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Mostro settings are initialized
-    Ok(())
+    Ok(cli.command)
 }
 
 #[cfg(test)]
@@ -57,11 +77,15 @@ mod tests {
     #[test]
     fn test_cli_parser_creation() {
         // Test that CLI struct can be created
-        let cli = Cli { dirsettings: None };
+        let cli = Cli {
+            dirsettings: None,
+            command: None,
+        };
         assert!(cli.dirsettings.is_none());
 
         let cli_with_path = Cli {
             dirsettings: Some("/custom/path".to_string()),
+            command: None,
         };
         assert_eq!(cli_with_path.dirsettings.unwrap(), "/custom/path");
     }
@@ -107,6 +131,28 @@ mod tests {
         assert_eq!(cli.dirsettings.unwrap(), "/test/path");
     }
 
+    #[test]
+    fn test_cli_parsing_maintenance_commands() {
+        let cli =
+            Cli::try_parse_from(["mostro", "-d", "/test/path", "backup", "/tmp/b.db"]).unwrap();
+        assert_eq!(cli.dirsettings.as_deref(), Some("/test/path"));
+        assert_eq!(
+            cli.command,
+            Some(Command::Backup {
+                path: PathBuf::from("/tmp/b.db")
+            })
+        );
+
+        let cli = Cli::try_parse_from(["mostro", "restore", "/tmp/b.db"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Restore {
+                path: PathBuf::from("/tmp/b.db")
+            })
+        );
+        assert!(Cli::try_parse_from(["mostro", "backup"]).is_err());
+    }
+
     #[test]
     fn test_cli_parsing_invalid_args() {
         // Test parsing with invalid arguments
@@ -123,7 +169,7 @@ mod tests {
             // In a real implementation, we would need dependency injection for testing
 
             // Test that the function signature is correct
-            let _: fn() -> Result<(), Box<dyn std::error::Error>> = settings_init;
+            let _: fn() -> Result<Option<Command>, Box<dyn std::error::Error>> = settings_init;
 
             // Verify function exists and has correct return type
             // No-op: type check above is sufficient
@@ -135,6 +181,7 @@ mod tests {
             let custom_path = Some("/custom/path".to_string());
             let cli = Cli {
                 dirsettings: custom_path.clone(),
+                command: None,
             };
 
             if let Some(path) = cli.dirsettings.as_deref() {
@@ -147,7 +194,10 @@ mod tests {
         #[test]
         fn test_default_path_handling() {
             // Test the logical flow of default path handling
-            let cli = Cli { dirsettings: None };
+            let cli = Cli {
+                dirsettings: None,
+                command: None,
+            };
 
             if cli.dirsettings.is_none() {
                 // This is the expected path for default settings
@@ -23,6 +23,11 @@ pub const DM_EVENT_KIND: u16 = 14;
 /// This allows the same Mostro instance to publish updated rates that replace previous events
 pub const NOSTR_EXCHANGE_RATES_EVENT_KIND: u16 = 30078;
 
+/// Nostr event kind for the key rotation handoff (NIP-33 addressable event)
+/// Signed by the retired Mostro key, `d` tag = retired pubkey; points to the
+/// new key (see docs/KEY_ROTATION.md)
+pub const NOSTR_KEY_HANDOFF_EVENT_KIND: u16 = 38387;
+
 /// Filename of the environment file auto-loaded from the settings directory at
 /// startup. Shared between the wizard (writes it) and the loader (reads it).
 pub const ENV_FILENAME: &str = ".env";
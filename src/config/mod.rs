@@ -10,6 +10,7 @@ pub mod wizard;
 
 // Mostro configuration module
 // This module provides global configuration settings for the Mostro lightning configuration.
+use crate::db::DbPool;
 use crate::lightning::LnStatus;
 
 // Synchronization primitives for thread safety -used for different global variables
@@ -18,13 +19,12 @@ use tokio::sync::RwLock;
 
 // Re-export for convenience
 pub use constants::{DEV_FEE_LIGHTNING_ADDRESS, MAX_DEV_FEE_PERCENTAGE, MIN_DEV_FEE_PERCENTAGE};
-use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
 pub use secret::{parse_mostro_keys, read_nsec_env_var, take_nsec_for_init};
 pub use settings::{get_db_pool, get_mostro_keys, init_mostro_settings, Settings};
 pub use types::{
-    AntiAbuseBondSettings, BondApplyTo, DatabaseSettings, ExpirationSettings, LightningSettings,
-    MostroSettings, NostrSettings,
+    AntiAbuseBondSettings, BondApplyTo, DatabaseSettings, ExpirationSettings, LightningBackend,
+    LightningSettings, MostroSettings, NostrSettings, SwapSettings,
 };
 
 // Global variables for Mostro configuration, Nostr client, Lightning status, and database pool
@@ -42,7 +42,7 @@ pub static NOSTR_CLIENT: OnceLock<Client> = OnceLock::new();
 /// (hermeme, PR #841).
 pub static PRICE_NOSTR_CLIENT: OnceLock<Client> = OnceLock::new();
 pub static LN_STATUS: OnceLock<LnStatus> = OnceLock::new();
-pub static DB_POOL: OnceLock<Arc<sqlx::SqlitePool>> = OnceLock::new();
+pub static DB_POOL: OnceLock<Arc<DbPool>> = OnceLock::new();
 
 /// Install the canonical test keys into [`NOSTR_KEYS`] so code paths that
 /// call `get_keys()` work in unit tests. Idempotent: whichever test module
@@ -56,19 +56,14 @@ pub fn init_test_nostr_keys() {
 }
 
 /// Global message queues for Mostro
-/// This struct holds three queues:
-/// - `queue_order_msg`: Holds messages related to orders.
-/// - `queue_order_cantdo`: Holds messages that cannot be processed.
 /// - `queue_order_rate`: Holds events related to user rates.
-/// - `queue_restore_session_msg`: Holds messages related to restore session.
 ///
-/// Each queue is wrapped in an `Arc<RwLock<>>` to allow safe concurrent access across tasks.
+/// The queue is wrapped in an `Arc<RwLock<>>` to allow safe concurrent access across tasks.
+/// Protocol messages to users do not queue here: the `enqueue_*` helpers
+/// store them straight in the durable outbox (`crate::outbox`).
 #[derive(Debug, Clone, Default)]
 pub struct MessageQueues {
-    pub queue_order_msg: Arc<RwLock<Vec<(Message, PublicKey)>>>,
-    pub queue_order_cantdo: Arc<RwLock<Vec<(Message, PublicKey)>>>,
     pub queue_order_rate: Arc<RwLock<Vec<Event>>>,
-    pub queue_restore_session_msg: Arc<RwLock<Vec<(Message, PublicKey)>>>,
 }
 
 pub static MESSAGE_QUEUES: LazyLock<MessageQueues> = LazyLock::new(MessageQueues::default);
@@ -68,6 +68,7 @@ mod tests {
                 "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
             ),
             relays: vec![],
+            previous_nsec_privkey: Default::default(),
         };
         let keys = take_nsec_for_init(&mut nostr).expect("valid test nsec");
         assert!(nostr.nsec_privkey.expose_secret().is_empty());
@@ -2,8 +2,10 @@ use super::{DB_POOL, MOSTRO_CONFIG, NOSTR_KEYS};
 use crate::config::secret::take_nsec_for_init;
 use crate::config::types::{
     AntiAbuseBondSettings, CashuSettings, DatabaseSettings, EscrowMode, ExpirationSettings,
-    LightningSettings, MostroSettings, NostrSettings, RpcSettings,
+    InstanceSettings, LightningSettings, MetricsSettings, MostroSettings, NostrSettings,
+    RpcSettings, SwapSettings,
 };
+use crate::db::DbPool;
 use crate::price::PriceSettings;
 use mostro_core::error::MostroError::{self, *};
 use mostro_core::error::ServiceError;
@@ -35,16 +37,30 @@ pub struct Settings {
     /// `anti_abuse_bond`; enforced at startup.
     #[serde(default)]
     pub cashu: Option<CashuSettings>,
+    /// On-chain payouts through reverse submarine swaps
+    /// (`docs/SWAP_PAYOUTS.md`). Absent section ≡ disabled.
+    #[serde(default)]
+    pub swap: Option<SwapSettings>,
     /// Multi-source price configuration (see `docs/PRICE_PROVIDERS.md`).
     /// Absent section ≡ legacy single-source behaviour (synthesised in
     /// Phase 1's migration).
     #[serde(default)]
     pub price: Option<PriceSettings>,
+    /// Prometheus metrics endpoint. Absent section ≡ disabled.
+    #[serde(default)]
+    pub metrics: Option<MetricsSettings>,
+    /// Extra Mostro identities served by this process (multi-tenant mode,
+    /// see `docs/MULTI_TENANT.md`). Empty ≡ a single identity.
+    #[serde(default)]
+    pub instances: Vec<InstanceSettings>,
 }
 
 /// Initialize the global `MOSTRO_CONFIG` and `NOSTR_KEYS` structs.
 pub fn init_mostro_settings(mut s: Settings) -> Result<(), MostroError> {
     let keys = take_nsec_for_init(&mut s.nostr)?;
+    let previous = std::mem::take(&mut s.nostr.previous_nsec_privkey);
+    let instances = crate::instance::build(&mut s, &keys)?;
+    let retired = crate::instance::build_retired(previous, &instances)?;
     NOSTR_KEYS.set(keys).map_err(|_| {
         MostroInternalErr(ServiceError::IOError(
             "Mostro nostr keys already initialized".to_string(),
@@ -55,6 +71,7 @@ pub fn init_mostro_settings(mut s: Settings) -> Result<(), MostroError> {
             "Mostro settings already initialized".to_string(),
         ))
     })?;
+    crate::instance::init(instances, retired)?;
     Ok(())
 }
 
@@ -64,7 +81,7 @@ pub fn get_mostro_keys() -> Option<&'static Keys> {
 }
 
 /// Get database pool for Mostro db operations to share across the thread
-pub fn get_db_pool() -> Arc<sqlx::SqlitePool> {
+pub fn get_db_pool() -> Arc<DbPool> {
     DB_POOL.get().expect("No database pool found").clone()
 }
 
@@ -78,7 +95,12 @@ impl Settings {
     }
 
     /// This function retrieves the Mostro configuration from the global MOSTRO_CONFIG struct.
+    /// Inside a handler for another `[[instances]]` identity it returns that
+    /// instance's configuration instead.
     pub fn get_mostro() -> &'static MostroSettings {
+        if let Some(instance) = crate::instance::current() {
+            return &instance.settings.mostro;
+        }
         &MOSTRO_CONFIG
             .get()
             .expect("No Mostro settings found")
@@ -112,7 +134,8 @@ impl Settings {
             .as_ref()
     }
 
-    /// This function retrieves the anti-abuse bond configuration from the
+    /// This function retrieves the anti-abuse bond configuration of the
+    /// current instance, like [`Settings::get_mostro`], falling back to the
     /// global `MOSTRO_CONFIG`. Returns `None` when the `[anti_abuse_bond]`
     /// block is absent (treated as disabled), and also when the global
     /// settings haven't been initialized yet — unlike the other accessors
@@ -120,9 +143,23 @@ impl Settings {
     /// must never panic in unit tests that don't bring up the full
     /// configuration.
     pub fn get_bond() -> Option<&'static AntiAbuseBondSettings> {
+        if let Some(instance) = crate::instance::current() {
+            return instance.settings.anti_abuse_bond.as_ref();
+        }
         MOSTRO_CONFIG.get()?.anti_abuse_bond.as_ref()
     }
 
+    /// Half-life of a rating's weight in the reputation score. Falls back
+    /// to the default when the global settings haven't been initialized, so
+    /// order event tagging never panics in unit tests, like
+    /// [`Settings::get_bond`].
+    pub fn get_reputation_half_life_days() -> u32 {
+        MOSTRO_CONFIG.get().map_or_else(
+            || MostroSettings::default().reputation_half_life_days,
+            |settings| settings.mostro.reputation_half_life_days,
+        )
+    }
+
     /// Wire transport for protocol messages. Falls back to the daemon
     /// default (`nip44`, protocol v2 — see `default_transport`) when the
     /// global settings haven't been initialized yet — `send_dm()` sits on
@@ -185,6 +222,18 @@ impl Settings {
         MOSTRO_CONFIG.get()?.cashu.as_ref()
     }
 
+    /// The `[swap]` block when present AND enabled. `None` when settings
+    /// haven't been initialized, mirroring [`Settings::get_cashu`].
+    pub fn get_swap() -> Option<&'static SwapSettings> {
+        MOSTRO_CONFIG.get()?.swap.as_ref().filter(|cfg| cfg.enabled)
+    }
+
+    /// The `[metrics]` block, if present. `None` when settings haven't
+    /// been initialized, mirroring [`Settings::get_cashu`].
+    pub fn get_metrics() -> Option<&'static MetricsSettings> {
+        MOSTRO_CONFIG.get()?.metrics.as_ref()
+    }
+
     /// True when the `[cashu]` block is present AND explicitly enabled.
     /// The single gate every Cashu code path must check. Returns `false`
     /// when settings haven't been initialized.
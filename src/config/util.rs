@@ -65,6 +65,14 @@ fn validate_mostro_settings(settings: &Settings) -> Result<(), MostroError> {
         ))));
     }
 
+    if settings.mostro.dispute_admin_pubkey.is_some() && settings.mostro.dispute_admin().is_none() {
+        return Err(MostroInternalErr(ServiceError::IOError(
+            "dispute_admin_pubkey is not a valid npub or hex public key".to_string(),
+        )));
+    }
+
+    validate_lightning_settings(&settings.lightning)?;
+
     validate_cashu_settings(
         settings.cashu.as_ref(),
         settings
@@ -73,6 +81,92 @@ fn validate_mostro_settings(settings: &Settings) -> Result<(), MostroError> {
             .is_some_and(|bond| bond.enabled),
     )?;
 
+    validate_swap_settings(
+        settings.swap.as_ref(),
+        settings.cashu.as_ref().is_some_and(|cashu| cashu.enabled),
+    )?;
+
+    Ok(())
+}
+
+/// A `cln` backend needs the path of its JSON-RPC socket.
+fn validate_lightning_settings(
+    lightning: &crate::config::types::LightningSettings,
+) -> Result<(), MostroError> {
+    if lightning.backend == crate::config::types::LightningBackend::Cln
+        && lightning.cln_rpc_path.trim().is_empty()
+    {
+        return Err(MostroInternalErr(ServiceError::IOError(
+            "lightning.backend = \"cln\" requires lightning.cln_rpc_path".to_string(),
+        )));
+    }
+    Ok(())
+}
+
+/// Validate the `[swap]` block. A swap payout is paid from the Lightning
+/// node, so it cannot run in Cashu mode; when enabled the swap API and the
+/// Esplora API must be `http`/`https` URLs, the lockup must need at least one
+/// confirmation, the network a known chain, the amount range non-empty and
+/// the fee cap a fraction below 1.
+fn validate_swap_settings(
+    swap: Option<&crate::config::types::SwapSettings>,
+    cashu_enabled: bool,
+) -> Result<(), MostroError> {
+    let Some(swap) = swap.filter(|swap| swap.enabled) else {
+        return Ok(());
+    };
+
+    if cashu_enabled {
+        return Err(MostroInternalErr(ServiceError::IOError(
+            "swap.enabled requires a Lightning node and cannot be combined with cashu.enabled"
+                .to_string(),
+        )));
+    }
+
+    for (field, value) in [
+        ("api_url", &swap.api_url),
+        ("esplora_url", &swap.esplora_url),
+    ] {
+        let url = reqwest::Url::parse(value).map_err(|e| {
+            MostroInternalErr(ServiceError::IOError(format!(
+                "swap.{field} ({value:?}) is not a valid URL: {e}"
+            )))
+        })?;
+        if !crate::util::is_http_or_https(&url) {
+            return Err(MostroInternalErr(ServiceError::IOError(format!(
+                "swap.{field} must use http or https, got scheme {:?}",
+                url.scheme()
+            ))));
+        }
+    }
+
+    if swap.lockup_confirmations == 0 {
+        return Err(MostroInternalErr(ServiceError::IOError(
+            "swap.lockup_confirmations must be at least 1".to_string(),
+        )));
+    }
+
+    if swap.bitcoin_network().is_none() {
+        return Err(MostroInternalErr(ServiceError::IOError(format!(
+            "swap.network ({:?}) must be bitcoin, testnet, testnet4, signet or regtest",
+            swap.network
+        ))));
+    }
+
+    if swap.min_amount == 0 || swap.min_amount > swap.max_amount {
+        return Err(MostroInternalErr(ServiceError::IOError(format!(
+            "swap.min_amount ({}) must be > 0 and not above swap.max_amount ({})",
+            swap.min_amount, swap.max_amount
+        ))));
+    }
+
+    if !(0.0..1.0).contains(&swap.max_fee_pct) {
+        return Err(MostroInternalErr(ServiceError::IOError(format!(
+            "swap.max_fee_pct ({}) must be >= 0 and below 1",
+            swap.max_fee_pct
+        ))));
+    }
+
     Ok(())
 }
 
@@ -87,6 +181,9 @@ fn validate_mostro_settings(settings: &Settings) -> Result<(), MostroError> {
 /// - When enabled, `mint_url` must be non-empty and parse as `http`/`https`.
 /// - When enabled, `escrow_locktime_days >= 1` (the seller-recovery
 ///   locktime floor of Track A §4B cannot be zero).
+/// - When enabled, `1 <= escrow_settlement_margin_days < escrow_locktime_days`
+///   (Track B §4): a margin at or above the floor would reject every
+///   `FiatSent` on a minimum-locktime token, silently disabling the market.
 fn validate_cashu_settings(
     cashu: Option<&crate::config::types::CashuSettings>,
     bond_enabled: bool,
@@ -126,6 +223,16 @@ fn validate_cashu_settings(
         ))));
     }
 
+    if cashu.escrow_settlement_margin_days < 1
+        || cashu.escrow_settlement_margin_days >= cashu.escrow_locktime_days
+    {
+        return Err(MostroInternalErr(ServiceError::IOError(format!(
+            "cashu.escrow_settlement_margin_days ({}) must be >= 1 and below \
+             cashu.escrow_locktime_days ({})",
+            cashu.escrow_settlement_margin_days, cashu.escrow_locktime_days
+        ))));
+    }
+
     Ok(())
 }
 
@@ -257,13 +364,17 @@ mod tests {
             nostr: NostrSettings {
                 nsec_privkey: SecretString::from(nsec.to_owned()),
                 relays: vec!["wss://relay.test".to_string()],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: MostroSettings::default(),
             rpc: RpcSettings::default(),
             expiration: None,
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         }
     }
 
@@ -368,21 +479,78 @@ mod tests {
         assert!(nostr.nsec_privkey.expose_secret().is_empty());
         assert_eq!(nostr.relays, vec!["wss://relay.test"]);
     }
+
+    #[test]
+    fn cln_backend_requires_its_socket() {
+        let mut lightning = LightningSettings {
+            backend: crate::config::types::LightningBackend::Cln,
+            ..LightningSettings::default()
+        };
+        assert!(validate_lightning_settings(&lightning).is_err());
+        lightning.cln_rpc_path = "/home/cln/.lightning/bitcoin/lightning-rpc".to_string();
+        assert!(validate_lightning_settings(&lightning).is_ok());
+        assert!(validate_lightning_settings(&LightningSettings::default()).is_ok());
+    }
 }
 
 #[cfg(test)]
 mod cashu_validation_tests {
     use super::*;
-    use crate::config::types::CashuSettings;
+    use crate::config::types::{CashuSettings, SwapSettings};
 
     fn enabled(mint_url: &str, days: u32) -> CashuSettings {
         CashuSettings {
             enabled: true,
             mint_url: mint_url.to_string(),
             escrow_locktime_days: days,
+            ..CashuSettings::default()
+        }
+    }
+
+    fn swap_enabled(api_url: &str) -> SwapSettings {
+        SwapSettings {
+            enabled: true,
+            api_url: api_url.to_string(),
+            esplora_url: "http://127.0.0.1:3002".to_string(),
+            ..SwapSettings::default()
         }
     }
 
+    #[test]
+    fn swap_block_is_only_checked_when_enabled() {
+        assert!(validate_swap_settings(None, true).is_ok());
+        assert!(validate_swap_settings(Some(&SwapSettings::default()), true).is_ok());
+        let swap = swap_enabled("https://api.boltz.exchange");
+        assert!(validate_swap_settings(Some(&swap), false).is_ok());
+        assert!(validate_swap_settings(Some(&swap), true).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_swap_urls_confirmations_network_range_and_fee() {
+        assert!(validate_swap_settings(Some(&swap_enabled("")), false).is_err());
+        assert!(validate_swap_settings(Some(&swap_enabled("wss://swap")), false).is_err());
+
+        let mut swap = swap_enabled("http://127.0.0.1:9001");
+        swap.esplora_url = String::new();
+        assert!(validate_swap_settings(Some(&swap), false).is_err());
+
+        let mut swap = swap_enabled("http://127.0.0.1:9001");
+        swap.lockup_confirmations = 0;
+        assert!(validate_swap_settings(Some(&swap), false).is_err());
+
+        let mut swap = swap_enabled("http://127.0.0.1:9001");
+        swap.network = "liquid".to_string();
+        assert!(validate_swap_settings(Some(&swap), false).is_err());
+
+        let mut swap = swap_enabled("http://127.0.0.1:9001");
+        swap.min_amount = swap.max_amount + 1;
+        assert!(validate_swap_settings(Some(&swap), false).is_err());
+
+        let mut swap = swap_enabled("http://127.0.0.1:9001");
+        swap.max_fee_pct = 1.0;
+        assert!(validate_swap_settings(Some(&swap), false).is_err());
+    }
+
     #[test]
     fn absent_block_is_valid_regardless_of_bonds() {
         assert!(validate_cashu_settings(None, false).is_ok());
@@ -406,7 +574,9 @@ mod cashu_validation_tests {
     fn accepts_valid_enabled_config() {
         let cashu = enabled("https://mint.example.com", 15);
         assert!(validate_cashu_settings(Some(&cashu), false).is_ok());
-        let cashu_http = enabled("http://localhost:3338", 1);
+        // The locktime floor must leave room for the default 3-day
+        // settlement margin (Track B §4).
+        let cashu_http = enabled("http://localhost:3338", 4);
         assert!(validate_cashu_settings(Some(&cashu_http), false).is_ok());
     }
 
@@ -430,6 +600,26 @@ mod cashu_validation_tests {
         let cashu = enabled("https://mint.example.com", 0);
         assert!(validate_cashu_settings(Some(&cashu), false).is_err());
     }
+
+    #[test]
+    fn rejects_settlement_margin_outside_locktime_floor() {
+        // Track B §4: 1 <= margin < escrow_locktime_days.
+        for (days, margin) in [(15, 0), (15, 15), (15, 20), (1, 1)] {
+            let cashu = CashuSettings {
+                escrow_settlement_margin_days: margin,
+                ..enabled("https://mint.example.com", days)
+            };
+            assert!(
+                validate_cashu_settings(Some(&cashu), false).is_err(),
+                "margin {margin} with a {days}-day floor must be rejected"
+            );
+        }
+        let cashu = CashuSettings {
+            escrow_settlement_margin_days: 14,
+            ..enabled("https://mint.example.com", 15)
+        };
+        assert!(validate_cashu_settings(Some(&cashu), false).is_ok());
+    }
 }
 
 #[cfg(test)]
@@ -451,7 +641,10 @@ mod startup_validation_tests {
             expiration: None,
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         }
     }
 
@@ -476,6 +669,16 @@ mod startup_validation_tests {
         assert!(err.to_string().contains("exceeds maximum"));
     }
 
+    #[test]
+    fn dispute_admin_pubkey_must_parse() {
+        let mut settings = base_settings();
+        settings.mostro.dispute_admin_pubkey = Some("not-a-key".to_string());
+        assert!(validate_mostro_settings(&settings).is_err());
+        settings.mostro.dispute_admin_pubkey =
+            Some(nostr_sdk::prelude::Keys::generate().public_key().to_hex());
+        assert!(validate_mostro_settings(&settings).is_ok());
+    }
+
     #[test]
     fn cashu_and_bond_conflict_is_rejected_through_full_validation() {
         let mut settings = base_settings();
@@ -487,6 +690,7 @@ mod startup_validation_tests {
             enabled: true,
             mint_url: "https://mint.example.com".to_string(),
             escrow_locktime_days: 15,
+            ..Default::default()
         });
         assert!(validate_mostro_settings(&settings).is_err());
     }
@@ -11,7 +11,8 @@ use zeroize::Zeroizing;
 use super::constants::{ENV_FILENAME, NSEC_ENV_VAR};
 use super::settings::Settings;
 use super::types::{
-    DatabaseSettings, LightningSettings, MostroSettings, NostrSettings, RpcSettings,
+    DatabaseSettings, LightningBackend, LightningSettings, MostroSettings, NostrSettings,
+    RpcSettings,
 };
 
 const TEMPLATE_BYTES: &[u8] = include_bytes!("../../settings.tpl.toml");
@@ -76,7 +77,10 @@ fn run_setup_wizard(settings_dir: &Path, config_file_path: &Path) -> Result<Sett
         expiration: None,
         anti_abuse_bond: None,
         cashu: None,
+        swap: None,
         price: None,
+        metrics: None,
+        instances: Vec::new(),
     };
 
     let toml_content = toml::to_string_pretty(&settings)
@@ -137,9 +141,11 @@ fn prompt_lightning_settings() -> Result<LightningSettings, MostroError> {
         .map_err(|e| MostroInternalErr(ServiceError::IOError(e.to_string())))?;
 
     Ok(LightningSettings {
+        backend: LightningBackend::Lnd,
         lnd_cert_file,
         lnd_macaroon_file,
         lnd_grpc_host,
+        cln_rpc_path: String::new(),
         invoice_expiration_window: 3600,
         hold_invoice_cltv_delta: 144,
         hold_invoice_expiration_window: 300,
@@ -202,6 +208,7 @@ fn prompt_nostr_settings(settings_dir: &Path) -> Result<NostrSettings, MostroErr
     Ok(NostrSettings {
         nsec_privkey,
         relays,
+        previous_nsec_privkey: Default::default(),
     })
 }
 
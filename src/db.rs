@@ -3,15 +3,39 @@ use mostro_core::order::Kind as OrderKind;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
 use sqlx::pool::Pool;
-use sqlx::sqlite::SqliteRow;
-use sqlx::{AssertSqlSafe, Row, Sqlite, SqlitePool};
+use sqlx::{AssertSqlSafe, QueryBuilder, Row};
 use std::collections::HashSet;
-#[cfg(unix)]
+#[cfg(all(unix, not(feature = "postgres")))]
 use std::fs::{set_permissions, Permissions};
+#[cfg(not(feature = "postgres"))]
 use std::path::Path;
 use std::sync::Arc;
 use uuid::Uuid;
 
+mod crud;
+pub use crud::{create_order_as, Crud};
+
+/// The SQL backend this binary stores its data in: SQLite by default,
+/// PostgreSQL when built with the `postgres` feature. Queries are written in
+/// the dialect both accept (`$N` placeholders, `ON CONFLICT`, `CAST(… AS
+/// BIGINT)` around aggregates), so the same text runs on either.
+#[cfg(not(feature = "postgres"))]
+pub type Db = sqlx::Sqlite;
+#[cfg(feature = "postgres")]
+pub type Db = sqlx::Postgres;
+
+/// Substring position, `0` when absent: `instr` in SQLite, `strpos` in
+/// PostgreSQL (same argument order).
+#[cfg(not(feature = "postgres"))]
+const STRPOS: &str = "instr";
+#[cfg(feature = "postgres")]
+const STRPOS: &str = "strpos";
+
+/// Connection pool of the configured backend.
+pub type DbPool = Pool<Db>;
+/// Result row of the configured backend.
+pub type DbRow = <Db as sqlx::Database>::Row;
+
 // Constants for status filtering used across restore session functions
 const EXCLUDED_ORDER_STATUSES: &str = "'expired','success','canceled','dispute','canceledbyadmin','completedbyadmin','settledbyadmin','cooperativelycanceled'";
 const ACTIVE_DISPUTE_STATUSES: &str = "'initiated','in-progress'";
@@ -24,9 +48,9 @@ const ACTIVE_DISPUTE_STATUSES: &str = "'initiated','in-progress'";
 /// disputed order is still active (buyer, seller and the assigned solver keep
 /// messaging), so its trade keys must stay fast-pathed. See
 /// `find_active_trade_pubkeys` and docs/TRANSPORT_V2_SPEC.md §6 Phase 2.
-const TERMINAL_ORDER_STATUSES: &str = "'expired','success','canceled','canceledbyadmin','completedbyadmin','settledbyadmin','cooperativelycanceled'";
+pub(crate) const TERMINAL_ORDER_STATUSES: &str = "'expired','success','canceled','canceledbyadmin','completedbyadmin','settledbyadmin','cooperativelycanceled'";
 
-#[cfg(unix)]
+#[cfg(all(unix, not(feature = "postgres")))]
 use std::os::unix::fs::PermissionsExt;
 
 /// Collect the "known keys" the Phase 2 anti-spam gate fast-paths: the trade
@@ -37,7 +61,7 @@ use std::os::unix::fs::PermissionsExt;
 /// [`ACTIVE_DISPUTE_STATUSES`]), never user input, so the inline
 /// interpolation carries no injection risk — same pattern the restore-session
 /// queries already use. The result is deduplicated.
-pub async fn find_active_trade_pubkeys(pool: &SqlitePool) -> Result<Vec<String>, MostroError> {
+pub async fn find_active_trade_pubkeys(pool: &DbPool) -> Result<Vec<String>, MostroError> {
     let mut keys: HashSet<String> = HashSet::new();
 
     // Order participants of every still-active order (disputed orders
@@ -79,11 +103,12 @@ pub async fn find_active_trade_pubkeys(pool: &SqlitePool) -> Result<Vec<String>,
     Ok(keys.into_iter().collect())
 }
 
+#[cfg(not(feature = "postgres"))]
 /// Removes deprecated `buyer_token` and `seller_token` columns from the disputes table if present.
 ///
 /// Both drops run inside a single transaction so either both succeed or neither does.
 /// This is a no-op when neither column exists (fresh installs and already-migrated databases).
-async fn migrate_remove_token_columns(pool: &SqlitePool) -> Result<(), MostroError> {
+async fn migrate_remove_token_columns(pool: &DbPool) -> Result<(), MostroError> {
     let buyer_token_exists = table_column_exists(pool, "disputes", "buyer_token").await?;
     let seller_token_exists = table_column_exists(pool, "disputes", "seller_token").await?;
 
@@ -138,16 +163,17 @@ async fn migrate_remove_token_columns(pool: &SqlitePool) -> Result<(), MostroErr
     Ok(())
 }
 
+#[cfg(not(feature = "postgres"))]
 async fn table_column_exists(
-    pool: &SqlitePool,
+    pool: &DbPool,
     table_name: &str,
     column_name: &str,
 ) -> Result<bool, MostroError> {
     Ok(sqlx::query_scalar::<_, i32>(
         r#"
         SELECT COUNT(*)
-        FROM pragma_table_info(?1)
-        WHERE name = ?2
+        FROM pragma_table_info($1)
+        WHERE name = $2
         "#,
     )
     .bind(table_name)
@@ -158,6 +184,7 @@ async fn table_column_exists(
         > 0)
 }
 
+#[cfg(not(feature = "postgres"))]
 fn parse_duplicate_column_name(err: &sqlx::migrate::MigrateError) -> Option<String> {
     let error = err.to_string();
     let marker = "duplicate column name: ";
@@ -165,6 +192,7 @@ fn parse_duplicate_column_name(err: &sqlx::migrate::MigrateError) -> Option<Stri
     Some(column.to_string())
 }
 
+#[cfg(not(feature = "postgres"))]
 fn normalize_sql_identifier(token: &str) -> String {
     token
         .trim()
@@ -176,6 +204,7 @@ fn normalize_sql_identifier(token: &str) -> String {
         .to_string()
 }
 
+#[cfg(not(feature = "postgres"))]
 fn strip_sql_comments(sql: &str) -> String {
     sql.lines()
         .filter(|line| !line.trim_start().starts_with("--"))
@@ -183,6 +212,7 @@ fn strip_sql_comments(sql: &str) -> String {
         .join("\n")
 }
 
+#[cfg(not(feature = "postgres"))]
 fn parse_add_column_statements(sql: &str) -> Option<Vec<(String, String)>> {
     let sql = strip_sql_comments(sql);
     let mut operations = Vec::new();
@@ -220,15 +250,17 @@ fn parse_add_column_statements(sql: &str) -> Option<Vec<(String, String)>> {
     }
 }
 
-async fn applied_migration_versions(pool: &SqlitePool) -> Result<Vec<i64>, MostroError> {
+#[cfg(not(feature = "postgres"))]
+async fn applied_migration_versions(pool: &DbPool) -> Result<Vec<i64>, MostroError> {
     sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations ORDER BY version")
         .fetch_all(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
 }
 
+#[cfg(not(feature = "postgres"))]
 async fn reconcile_existing_add_column_migration(
-    pool: &SqlitePool,
+    pool: &DbPool,
     migrator: &sqlx::migrate::Migrator,
     duplicate_column: &str,
 ) -> Result<bool, MostroError> {
@@ -270,7 +302,7 @@ async fn reconcile_existing_add_column_migration(
                 success,
                 checksum,
                 execution_time
-            ) VALUES (?1, ?2, TRUE, ?3, 0)
+            ) VALUES ($1, $2, TRUE, $3, 0)
             "#,
         )
         .bind(migration.version)
@@ -293,10 +325,83 @@ async fn reconcile_existing_add_column_migration(
     Ok(false)
 }
 
-pub async fn connect() -> Result<Arc<Pool<Sqlite>>, MostroError> {
-    // Get mostro settings
-    let db_settings = Settings::get_db();
-    let db_url = &db_settings.url;
+/// Refuse a `[database].url` whose scheme is not `sqlite`. A url without a
+/// scheme is a SQLite file path.
+pub(crate) fn check_sqlite_url(db_url: &str) -> Result<(), MostroError> {
+    match db_url.split_once(':') {
+        Some((scheme, _)) if scheme.eq_ignore_ascii_case("sqlite") => Ok(()),
+        Some((scheme, _)) if db_url[scheme.len()..].starts_with("://") => {
+            Err(MostroInternalErr(ServiceError::DbAccessError(format!(
+                "unsupported database scheme `{scheme}`: expected a sqlite:// url"
+            ))))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuse a `[database].url` for a backend this binary was not built with.
+///
+/// The backend is picked at build time ([`Db`]). Without this check a
+/// `postgres://` url is taken for a SQLite file path and the daemon fails
+/// with an unrelated I/O error.
+pub(crate) fn check_database_url(db_url: &str) -> Result<(), MostroError> {
+    #[cfg(not(feature = "postgres"))]
+    return check_sqlite_url(db_url).map_err(|_| {
+        MostroInternalErr(ServiceError::DbAccessError(format!(
+            "`{db_url}` is not a sqlite:// url; PostgreSQL needs a mostrod built with --features postgres"
+        )))
+    });
+    #[cfg(feature = "postgres")]
+    match db_url.split_once("://") {
+        Some((scheme, _))
+            if scheme.eq_ignore_ascii_case("postgres")
+                || scheme.eq_ignore_ascii_case("postgresql") =>
+        {
+            Ok(())
+        }
+        _ => Err(MostroInternalErr(ServiceError::DbAccessError(format!(
+            "`{db_url}` is not a postgres:// url; this mostrod was built for PostgreSQL"
+        )))),
+    }
+}
+
+/// `(id, status)` of every order not yet in a terminal status, oldest first.
+/// Disputed orders are included. Used to report what a restore brought back.
+pub async fn find_non_terminal_orders(pool: &DbPool) -> Result<Vec<(String, String)>, MostroError> {
+    let query = format!(
+        "SELECT id, status FROM orders WHERE status NOT IN ({TERMINAL_ORDER_STATUSES}) ORDER BY created_at"
+    );
+    sqlx::query_as::<_, (String, String)>(AssertSqlSafe(query))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+}
+
+/// Open `[database].url` and bring it up to this release's migrations.
+pub async fn connect() -> Result<Arc<DbPool>, MostroError> {
+    let db_url = &Settings::get_db().url;
+    check_database_url(db_url)?;
+    connect_backend(db_url).await.map(Arc::new)
+}
+
+#[cfg(feature = "postgres")]
+async fn connect_backend(db_url: &str) -> Result<DbPool, MostroError> {
+    let pool = DbPool::connect(db_url).await.map_err(|e| {
+        tracing::error!(error = %e, "Failed to connect to PostgreSQL");
+        MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+    })?;
+    if let Err(e) = sqlx::migrate!("./migrations/postgres").run(&pool).await {
+        tracing::error!("Failed to run migrations on PostgreSQL: {}", e);
+        return Err(MostroInternalErr(ServiceError::DbAccessError(
+            e.to_string(),
+        )));
+    }
+    Ok(pool)
+}
+
+/// Creates the database file (owner-only) on first start.
+#[cfg(not(feature = "postgres"))]
+async fn connect_backend(db_url: &str) -> Result<DbPool, MostroError> {
     let tmp = db_url.replace("sqlite://", "");
     let db_path = Path::new(&tmp);
 
@@ -313,7 +418,7 @@ pub async fn connect() -> Result<Arc<Pool<Sqlite>>, MostroError> {
         }
 
         // Create new database connection
-        match SqlitePool::connect(db_url).await {
+        match DbPool::connect(db_url).await {
             Ok(pool) => {
                 match sqlx::migrate!().run(&pool).await {
                     Ok(_) => {
@@ -364,7 +469,7 @@ pub async fn connect() -> Result<Arc<Pool<Sqlite>>, MostroError> {
         }
     } else {
         // Connect to existing database
-        let conn = SqlitePool::connect(db_url)
+        let conn = DbPool::connect(db_url)
             .await
             .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
 
@@ -406,16 +511,16 @@ pub async fn connect() -> Result<Arc<Pool<Sqlite>>, MostroError> {
 
         conn
     };
-    Ok(Arc::new(conn))
+    Ok(conn)
 }
 
 /// Retrieve the stored admin password hash from the users table.
-pub async fn get_admin_password(pool: &SqlitePool) -> Result<Option<String>, MostroError> {
+pub async fn get_admin_password(pool: &DbPool) -> Result<Option<String>, MostroError> {
     if let Some(user) = sqlx::query_as::<_, User>(
         r#"
           SELECT *
           FROM users
-          WHERE is_admin == 1
+          WHERE is_admin = 1
           LIMIT 1
         "#,
     )
@@ -432,12 +537,12 @@ pub async fn get_admin_password(pool: &SqlitePool) -> Result<Option<String>, Mos
     }
 }
 
-pub async fn edit_pubkeys_order(pool: &SqlitePool, order: &Order) -> Result<Order, MostroError> {
+pub async fn edit_pubkeys_order(pool: &DbPool, order: &Order) -> Result<Order, MostroError> {
     let null_key = None::<String>;
     let result = match order.get_order_kind() {
         Ok(OrderKind::Buy) => {
             sqlx::query(
-                "UPDATE orders SET seller_pubkey = ?1, master_seller_pubkey = ?2 WHERE id = ?3",
+                "UPDATE orders SET seller_pubkey = $1, master_seller_pubkey = $2 WHERE id = $3",
             )
             .bind(null_key.clone())
             .bind(null_key)
@@ -447,7 +552,7 @@ pub async fn edit_pubkeys_order(pool: &SqlitePool, order: &Order) -> Result<Orde
         }
         Ok(OrderKind::Sell) => {
             sqlx::query(
-                "UPDATE orders SET buyer_pubkey = ?1, master_buyer_pubkey = ?2 WHERE id = ?3",
+                "UPDATE orders SET buyer_pubkey = $1, master_buyer_pubkey = $2 WHERE id = $3",
             )
             .bind(null_key.clone())
             .bind(null_key)
@@ -474,7 +579,7 @@ pub async fn edit_pubkeys_order(pool: &SqlitePool, order: &Order) -> Result<Orde
         r#"
           SELECT *
           FROM orders
-          WHERE id = ?1
+          WHERE id = $1
         "#,
     )
     .bind(order.id)
@@ -485,12 +590,12 @@ pub async fn edit_pubkeys_order(pool: &SqlitePool, order: &Order) -> Result<Orde
     Ok(order)
 }
 
-pub async fn find_order_by_hash(pool: &SqlitePool, hash: &str) -> Result<Order, MostroError> {
+pub async fn find_order_by_hash(pool: &DbPool, hash: &str) -> Result<Order, MostroError> {
     let order = sqlx::query_as::<_, Order>(
         r#"
           SELECT *
           FROM orders
-          WHERE hash = ?1
+          WHERE hash = $1
         "#,
     )
     .bind(hash)
@@ -501,7 +606,7 @@ pub async fn find_order_by_hash(pool: &SqlitePool, hash: &str) -> Result<Order,
     Ok(order)
 }
 
-pub async fn find_order_by_date(pool: &SqlitePool) -> Result<Vec<Order>, MostroError> {
+pub async fn find_order_by_date(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
     let expire_time = Timestamp::now();
     // Phase 1.5: `waiting-taker-bond` is a daemon-internal pre-trade
     // status (a prospective taker is mid-bond). On the wire it publishes
@@ -521,7 +626,7 @@ pub async fn find_order_by_date(pool: &SqlitePool) -> Result<Vec<Order>, MostroE
         r#"
           SELECT *
           FROM orders
-          WHERE expires_at < ?1
+          WHERE expires_at < $1
             AND status IN ('pending', 'waiting-taker-bond', 'waiting-maker-bond')
         "#,
     )
@@ -533,18 +638,17 @@ pub async fn find_order_by_date(pool: &SqlitePool) -> Result<Vec<Order>, MostroE
     Ok(order)
 }
 
-pub async fn find_order_by_seconds(pool: &SqlitePool) -> Result<Vec<Order>, MostroError> {
+pub async fn find_order_by_seconds(pool: &DbPool, now: i64) -> Result<Vec<Order>, MostroError> {
     let mostro_settings = Settings::get_mostro();
-    let exp_seconds = mostro_settings.expiration_seconds as u64;
-    let expire_time = Timestamp::now() - exp_seconds;
+    let expire_time = now - mostro_settings.expiration_seconds as i64;
     let order = sqlx::query_as::<_, Order>(
         r#"
           SELECT *
           FROM orders
-          WHERE taken_at < ?1 AND ( status == 'waiting-buyer-invoice' OR status == 'waiting-payment' )
+          WHERE taken_at < $1 AND ( status = 'waiting-buyer-invoice' OR status = 'waiting-payment' )
         "#,
     )
-    .bind(expire_time.as_secs() as i64)
+    .bind(expire_time)
     .fetch_all(pool)
     .await
     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
@@ -553,14 +657,14 @@ pub async fn find_order_by_seconds(pool: &SqlitePool) -> Result<Vec<Order>, Most
 }
 
 pub async fn find_dispute_by_order_id(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
 ) -> Result<Dispute, MostroError> {
     let dispute = sqlx::query_as::<_, Dispute>(
         r#"
           SELECT *
           FROM disputes
-          WHERE order_id == ?1
+          WHERE order_id = $1
         "#,
     )
     .bind(order_id)
@@ -572,7 +676,7 @@ pub async fn find_dispute_by_order_id(
 }
 
 pub async fn update_order_to_initial_state(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     amount: i64,
     fee: i64,
@@ -587,16 +691,16 @@ pub async fn update_order_to_initial_state(
         r#"
             UPDATE orders
             SET
-            status = ?1,
-            amount = ?2,
-            fee = ?3,
-            dev_fee = ?4,
-            hash = ?5,
-            preimage = ?6,
-            buyer_invoice = ?7,
-            taken_at = ?8,
-            invoice_held_at = ?9
-            WHERE id = ?10
+            status = $1,
+            amount = $2,
+            fee = $3,
+            dev_fee = $4,
+            hash = $5,
+            preimage = $6,
+            buyer_invoice = $7,
+            taken_at = $8,
+            invoice_held_at = $9
+            WHERE id = $10
         "#,
     )
     .bind(status)
@@ -617,17 +721,14 @@ pub async fn update_order_to_initial_state(
     Ok(rows_affected > 0)
 }
 
-pub async fn reset_order_taken_at_time(
-    pool: &SqlitePool,
-    order_id: Uuid,
-) -> Result<bool, MostroError> {
+pub async fn reset_order_taken_at_time(pool: &DbPool, order_id: Uuid) -> Result<bool, MostroError> {
     let taken_at = 0_i64;
     let result = sqlx::query(
         r#"
             UPDATE orders
             SET
-            taken_at = ?1
-            WHERE id = ?2
+            taken_at = $1
+            WHERE id = $2
         "#,
     )
     .bind(taken_at)
@@ -641,7 +742,7 @@ pub async fn reset_order_taken_at_time(
 }
 
 pub async fn update_order_invoice_held_at_time(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     invoice_held_at: i64,
 ) -> Result<bool, MostroError> {
@@ -649,8 +750,8 @@ pub async fn update_order_invoice_held_at_time(
         r#"
             UPDATE orders
             SET
-            invoice_held_at = ?1
-            WHERE id = ?2
+            invoice_held_at = $1
+            WHERE id = $2
         "#,
     )
     .bind(invoice_held_at)
@@ -676,14 +777,14 @@ const PRETRADE_STATUSES: &str = "'pending','waiting-taker-bond'";
 /// was publishing — letting the caller bail instead of resurrecting or
 /// clobbering the row with a stale full-row write.
 pub async fn cas_pretrade_order_status(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     new_status: Status,
     event_id: &str,
 ) -> Result<bool, MostroError> {
     let query = format!(
-        "UPDATE orders SET status = ?1, event_id = ?2 \
-         WHERE id = ?3 AND status IN ({PRETRADE_STATUSES})"
+        "UPDATE orders SET status = $1, event_id = $2 \
+         WHERE id = $3 AND status IN ({PRETRADE_STATUSES})"
     );
     let result = sqlx::query(AssertSqlSafe(query.as_str()))
         .bind(new_status.to_string())
@@ -693,7 +794,11 @@ pub async fn cas_pretrade_order_status(
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
 
-    Ok(result.rows_affected() > 0)
+    let won = result.rows_affected() > 0;
+    if won {
+        crate::rpc::events::order_status_changed(order_id, &new_status.to_string());
+    }
+    Ok(won)
 }
 
 /// Compare-and-swap the winning taker's bond context onto the order row
@@ -702,22 +807,19 @@ pub async fn cas_pretrade_order_status(
 /// pre-trade, so a maker cancel that already committed is not undone.
 /// `order` must be the merged in-memory row (taker fields applied).
 /// Returns `false` when the CAS missed.
-pub async fn cas_promote_taker_context(
-    pool: &SqlitePool,
-    order: &Order,
-) -> Result<bool, MostroError> {
+pub async fn cas_promote_taker_context(pool: &DbPool, order: &Order) -> Result<bool, MostroError> {
     let kind = order.get_order_kind().map_err(MostroInternalErr)?;
     let taker_columns = match kind {
-        OrderKind::Buy => "seller_pubkey = ?2, master_seller_pubkey = ?3, trade_index_seller = ?4",
+        OrderKind::Buy => "seller_pubkey = $2, master_seller_pubkey = $3, trade_index_seller = $4",
         OrderKind::Sell => {
-            "buyer_pubkey = ?2, master_buyer_pubkey = ?3, trade_index_buyer = ?4, \
-             buyer_invoice = ?5"
+            "buyer_pubkey = $2, master_buyer_pubkey = $3, trade_index_buyer = $4, \
+             buyer_invoice = $5"
         }
     };
     let query = format!(
-        "UPDATE orders SET {taker_columns}, fiat_amount = ?6, amount = ?7, fee = ?8, \
-         dev_fee = ?9, created_at = ?10, taken_at = ?11 \
-         WHERE id = ?1 AND status IN ({PRETRADE_STATUSES})"
+        "UPDATE orders SET {taker_columns}, fiat_amount = $6, amount = $7, fee = $8, \
+         dev_fee = $9, created_at = $10, taken_at = $11 \
+         WHERE id = $1 AND status IN ({PRETRADE_STATUSES})"
     );
     // The unused ?5 slot for buy orders binds a throwaway `None`.
     let buyer_invoice: Option<&str> = match kind {
@@ -775,7 +877,7 @@ pub async fn cas_promote_taker_context(
 /// the caller must then cancel any orphaned invoice instead of
 /// resurrecting the row.
 pub async fn cas_complete_pretrade_take(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order: &Order,
     allow_waiting_buyer_invoice: bool,
 ) -> Result<bool, MostroError> {
@@ -785,13 +887,13 @@ pub async fn cas_complete_pretrade_take(
         ""
     };
     let query = format!(
-        "UPDATE orders SET status = ?2, event_id = ?3, \
-         buyer_pubkey = ?4, seller_pubkey = ?5, \
-         master_buyer_pubkey = ?6, master_seller_pubkey = ?7, \
-         trade_index_buyer = ?8, trade_index_seller = ?9, \
-         fiat_amount = ?10, amount = ?11, fee = ?12, dev_fee = ?13, created_at = ?14, \
-         buyer_invoice = ?15, preimage = ?16, hash = ?17, taken_at = ?18 \
-         WHERE id = ?1 AND status IN ({PRETRADE_STATUSES}{extra_status})"
+        "UPDATE orders SET status = $2, event_id = $3, \
+         buyer_pubkey = $4, seller_pubkey = $5, \
+         master_buyer_pubkey = $6, master_seller_pubkey = $7, \
+         trade_index_buyer = $8, trade_index_seller = $9, \
+         fiat_amount = $10, amount = $11, fee = $12, dev_fee = $13, created_at = $14, \
+         buyer_invoice = $15, preimage = $16, hash = $17, taken_at = $18 \
+         WHERE id = $1 AND status IN ({PRETRADE_STATUSES}{extra_status})"
     );
     let result = sqlx::query(AssertSqlSafe(query.as_str()))
         .bind(order.id)
@@ -816,7 +918,11 @@ pub async fn cas_complete_pretrade_take(
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
 
-    Ok(result.rows_affected() > 0)
+    let won = result.rows_affected() > 0;
+    if won {
+        crate::rpc::events::order_status_changed(order.id, &order.status);
+    }
+    Ok(won)
 }
 
 /// Atomically persist a validated Cashu escrow and advance the order status
@@ -838,7 +944,7 @@ pub async fn cas_complete_pretrade_take(
 /// atomic backstop that closes the check-then-act race between two concurrent
 /// submissions.
 pub async fn update_order_cashu_escrow(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     mint_url: &str,
     token: &str,
@@ -850,14 +956,14 @@ pub async fn update_order_cashu_escrow(
         r#"
             UPDATE orders
             SET
-            cashu_mint_url = ?1,
-            cashu_escrow_token = ?2,
-            cashu_escrow_locked_at = ?3,
-            status = ?4
-            WHERE id = ?5 AND status = ?6 AND cashu_escrow_locked_at IS NULL
+            cashu_mint_url = $1,
+            cashu_escrow_token = $2,
+            cashu_escrow_locked_at = $3,
+            status = $4
+            WHERE id = $5 AND status = $6 AND cashu_escrow_locked_at IS NULL
               AND NOT EXISTS (
                 SELECT 1 FROM orders other
-                WHERE other.cashu_escrow_token = ?2 AND other.id <> ?5
+                WHERE other.cashu_escrow_token = $2 AND other.id <> $5
               )
         "#,
     )
@@ -888,7 +994,7 @@ pub async fn update_order_cashu_escrow(
 /// is already funded must never be dragged back to an earlier status, whatever
 /// its current one.
 pub async fn claim_order_status(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     expected_status: Status,
     new_status: Status,
@@ -896,8 +1002,8 @@ pub async fn claim_order_status(
     let result = sqlx::query(
         r#"
             UPDATE orders
-            SET status = ?1
-            WHERE id = ?2 AND status = ?3 AND cashu_escrow_locked_at IS NULL
+            SET status = $1
+            WHERE id = $2 AND status = $3 AND cashu_escrow_locked_at IS NULL
         "#,
     )
     .bind(new_status.to_string())
@@ -926,17 +1032,17 @@ pub async fn claim_order_status(
 /// re-serialised or re-split around the same proofs — lands with the
 /// `cashu_fee_proofs` table in TA-1f (`docs/cashu/02-track-a-lock.md` §4A).
 pub async fn cashu_escrow_token_in_use(
-    pool: &SqlitePool,
+    pool: &DbPool,
     token: &str,
     exclude_order_id: Uuid,
 ) -> Result<bool, MostroError> {
     // `SELECT 1` rather than a column: existence is the whole question, and
     // `orders.id` is stored as a BLOB uuid that would need decoding for nothing.
-    let found: Option<(i64,)> = sqlx::query_as(
+    let found: Option<(i32,)> = sqlx::query_as(
         r#"
           SELECT 1
           FROM orders
-          WHERE cashu_escrow_token = ?1 AND id <> ?2
+          WHERE cashu_escrow_token = $1 AND id <> $2
           LIMIT 1
         "#,
     )
@@ -955,7 +1061,7 @@ pub async fn cashu_escrow_token_in_use(
 /// the lock, so filtering on a status would skip legitimately locked rows
 /// that have already moved on. Only re-add a status predicate if a separate
 /// invariant guarantees locked rows never leave that status.
-pub async fn find_locked_cashu_orders(pool: &SqlitePool) -> Result<Vec<Order>, MostroError> {
+pub async fn find_locked_cashu_orders(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
     sqlx::query_as::<_, Order>(
         r#"
           SELECT *
@@ -969,6 +1075,68 @@ pub async fn find_locked_cashu_orders(pool: &SqlitePool) -> Result<Vec<Order>, M
     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
 }
 
+/// Released Cashu orders awaiting the buyer's redeem (Track B §5C, Track D
+/// §5D): status `SettledHoldInvoice` (seller released) or `SettledByAdmin`
+/// (solver ruled for the buyer) with a stored escrow token. The release
+/// watcher polls the mint for each and completes the redeemed ones. Orders
+/// already held for a dispute after a spend past the locktime are left out.
+pub async fn find_released_cashu_orders(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
+    sqlx::query_as::<_, Order>(
+        r#"
+          SELECT *
+          FROM orders
+          WHERE status IN ($1, $2) AND cashu_escrow_token IS NOT NULL
+            AND cashu_release_ambiguous_at IS NULL
+          ORDER BY cashu_escrow_locked_at ASC
+        "#,
+    )
+    .bind(Status::SettledHoldInvoice.to_string())
+    .bind(Status::SettledByAdmin.to_string())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+}
+
+/// Persist a solver's ruling on a disputed Cashu order (Track D §5C).
+///
+/// Compare-and-set on `status = dispute`: writes the new status and event id
+/// from `order`, plus who received Mostro's `P_M` signatures
+/// (`pm_signed_to`) and when. A ruling that signed nothing found the escrow
+/// already spent and is recorded as moot (`cashu_ruling_moot_at`). Returns
+/// `Ok(false)` — nothing written — when the dispute was already resolved by a
+/// concurrent ruling or cooperative cancel.
+pub async fn resolve_cashu_dispute(
+    pool: &DbPool,
+    order: &Order,
+    pm_signed_to: Option<&str>,
+    resolved_at: i64,
+) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        r#"
+          UPDATE orders
+          SET status = $1, event_id = $2, cashu_pm_signed_to = $3,
+              cashu_pm_signed_at = $4, cashu_ruling_moot_at = $5
+          WHERE id = $6 AND status = $7
+        "#,
+    )
+    .bind(&order.status)
+    .bind(&order.event_id)
+    .bind(pm_signed_to)
+    .bind(pm_signed_to.map(|_| resolved_at))
+    .bind(pm_signed_to.is_none().then_some(resolved_at))
+    .bind(order.id)
+    .bind(Status::Dispute.to_string())
+    .execute(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+
+    let won = result.rows_affected() > 0;
+    if won {
+        crate::rpc::events::order_status_changed(order.id, &order.status);
+    }
+    Ok(won)
+}
+
 /// Orders whose hold invoice still needs an LND subscription attached.
 ///
 /// Two disjoint cases, and the second is the one that matters after a restart:
@@ -991,7 +1159,7 @@ pub async fn find_locked_cashu_orders(pool: &SqlitePool) -> Result<Vec<Order>, M
 ///
 /// Terminal and post-escrow statuses are deliberately absent: once the hold
 /// is settled or canceled there is nothing left to observe.
-pub async fn find_held_invoices(pool: &SqlitePool) -> Result<Vec<Order>, MostroError> {
+pub async fn find_held_invoices(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
     let order = sqlx::query_as::<_, Order>(
         r#"
           SELECT *
@@ -1020,7 +1188,7 @@ pub async fn find_held_invoices(pool: &SqlitePool) -> Result<Vec<Order>, MostroE
 /// state has no live escrow to protect. `hash IS NOT NULL` because the
 /// guardian needs the payment hash to cancel the invoice proactively.
 pub async fn find_escrow_deadline_orders(
-    pool: &SqlitePool,
+    pool: &DbPool,
     held_before: i64,
 ) -> Result<Vec<Order>, MostroError> {
     let orders = sqlx::query_as::<_, Order>(
@@ -1029,7 +1197,7 @@ pub async fn find_escrow_deadline_orders(
           FROM orders
           WHERE status IN ('active', 'fiat-sent')
             AND invoice_held_at != 0
-            AND invoice_held_at <= ?1
+            AND invoice_held_at <= $1
             AND hash IS NOT NULL
         "#,
     )
@@ -1041,17 +1209,20 @@ pub async fn find_escrow_deadline_orders(
     Ok(orders)
 }
 
-pub async fn find_failed_payment(pool: &SqlitePool) -> Result<Vec<Order>, MostroError> {
+pub async fn find_failed_payment(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
     // `payout_payment_hash IS NULL` excludes orders that already have a payout
     // in flight: retrying them would dispatch a second payment against the same
     // settled escrow. Those are resolved by `find_inflight_payouts` /
-    // reconciliation instead.
+    // reconciliation instead. A live swap is the on-chain equivalent, driven
+    // by the swap watcher.
     let order = sqlx::query_as::<_, Order>(
         r#"
           SELECT *
           FROM orders
-          WHERE failed_payment == true AND status == 'settled-hold-invoice'
+          WHERE failed_payment = true AND status = 'settled-hold-invoice'
             AND payout_payment_hash IS NULL
+            AND NOT EXISTS (SELECT 1 FROM swaps WHERE swaps.order_id = orders.id
+                            AND swaps.status IN ('created', 'claimed'))
         "#,
     )
     .fetch_all(pool)
@@ -1095,14 +1266,14 @@ pub async fn find_failed_payment(pool: &SqlitePool) -> Result<Vec<Order>, Mostro
 /// wall-clock kept only for the grace window — needs a migration and a change
 /// to every claim CAS, and is deliberately left as future work.
 pub async fn claim_order_payout(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     payment_hash: &str,
 ) -> Result<Option<i64>, MostroError> {
     let claimed_at = chrono::Utc::now().timestamp();
     let result = sqlx::query(
-        "UPDATE orders SET payout_payment_hash = ?1, payout_claimed_at = ?2 \
-         WHERE id = ?3 AND payout_payment_hash IS NULL AND status = 'settled-hold-invoice'",
+        "UPDATE orders SET payout_payment_hash = $1, payout_claimed_at = $2 \
+         WHERE id = $3 AND payout_payment_hash IS NULL AND status = 'settled-hold-invoice'",
     )
     .bind(payment_hash)
     .bind(claimed_at)
@@ -1141,16 +1312,16 @@ pub async fn claim_order_payout(
 /// the touch leans on the token harder than the claim does, so keep them in
 /// mind before treating it as a strict identity.
 pub async fn touch_order_payout_claim(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     payment_hash: &str,
     claimed_at: Option<i64>,
 ) -> Result<Option<i64>, MostroError> {
     let refreshed_at = chrono::Utc::now().timestamp();
     let result = sqlx::query(
-        "UPDATE orders SET payout_claimed_at = ?1 \
-         WHERE id = ?2 AND payout_payment_hash = ?3 AND payout_claimed_at IS ?4 \
-           AND status = 'settled-hold-invoice'",
+        "UPDATE orders SET payout_claimed_at = $1 \
+         WHERE id = $2 AND payout_payment_hash = $3 \
+           AND payout_claimed_at IS NOT DISTINCT FROM $4 AND status = 'settled-hold-invoice'",
     )
     .bind(refreshed_at)
     .bind(order_id)
@@ -1177,14 +1348,15 @@ pub async fn touch_order_payout_claim(
 /// Returns `true` when this caller still owned the claim (the row was cleared),
 /// so callers can gate their own terminal side-effects on claim ownership.
 pub async fn clear_order_payout(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     payment_hash: &str,
     claimed_at: Option<i64>,
 ) -> Result<bool, MostroError> {
     let result = sqlx::query(
         "UPDATE orders SET payout_payment_hash = NULL, payout_claimed_at = NULL \
-         WHERE id = ?1 AND payout_payment_hash = ?2 AND payout_claimed_at IS ?3",
+         WHERE id = $1 AND payout_payment_hash = $2 \
+           AND payout_claimed_at IS NOT DISTINCT FROM $3",
     )
     .bind(order_id)
     .bind(payment_hash)
@@ -1204,14 +1376,15 @@ pub async fn clear_order_payout(
 /// in-flight claim. Returns `true` when this caller still owned the claim, so
 /// failure bookkeeping and buyer notifications can be gated on ownership.
 pub async fn fail_order_payout(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     payment_hash: &str,
     claimed_at: Option<i64>,
 ) -> Result<bool, MostroError> {
     let result = sqlx::query(
         "UPDATE orders SET payout_payment_hash = NULL, payout_claimed_at = NULL, failed_payment = true \
-         WHERE id = ?1 AND payout_payment_hash = ?2 AND payout_claimed_at IS ?3",
+         WHERE id = $1 AND payout_payment_hash = $2 \
+           AND payout_claimed_at IS NOT DISTINCT FROM $3",
     )
     .bind(order_id)
     .bind(payment_hash)
@@ -1219,7 +1392,11 @@ pub async fn fail_order_payout(
     .execute(pool)
     .await
     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
-    Ok(result.rows_affected() > 0)
+    let owned = result.rows_affected() > 0;
+    if owned {
+        crate::rpc::events::payout_result(order_id, false);
+    }
+    Ok(owned)
 }
 
 /// Orders with a buyer payout in flight (marker set) that are old enough to
@@ -1237,15 +1414,15 @@ pub async fn fail_order_payout(
 /// also require `settled-hold-invoice`, so nothing ever acts on it — but it can
 /// linger; treat a marker on a non-`settled-hold-invoice` order as stale.
 pub async fn find_inflight_payouts(
-    pool: &SqlitePool,
+    pool: &DbPool,
     claimed_before: i64,
 ) -> Result<Vec<(Uuid, String, Option<i64>)>, MostroError> {
     let rows = sqlx::query(
         r#"
           SELECT id, payout_payment_hash, payout_claimed_at
           FROM orders
-          WHERE payout_payment_hash IS NOT NULL AND status == 'settled-hold-invoice'
-            AND (payout_claimed_at IS NULL OR payout_claimed_at <= ?1)
+          WHERE payout_payment_hash IS NOT NULL AND status = 'settled-hold-invoice'
+            AND (payout_claimed_at IS NULL OR payout_claimed_at <= $1)
         "#,
     )
     .bind(claimed_before)
@@ -1269,14 +1446,14 @@ pub async fn find_inflight_payouts(
     Ok(out)
 }
 
-pub async fn find_unpaid_dev_fees(pool: &SqlitePool) -> Result<Vec<Order>, MostroError> {
+pub async fn find_unpaid_dev_fees(pool: &DbPool) -> Result<Vec<Order>, MostroError> {
     let orders = sqlx::query_as::<_, Order>(
         r#"
           SELECT *
           FROM orders
           WHERE (status = 'settled-hold-invoice' OR status = 'success')
             AND dev_fee > 0
-            AND dev_fee_paid = 0
+            AND dev_fee_paid = FALSE
             AND (dev_fee_payment_hash IS NULL OR dev_fee_payment_hash = '')
         "#,
     )
@@ -1287,15 +1464,12 @@ pub async fn find_unpaid_dev_fees(pool: &SqlitePool) -> Result<Vec<Order>, Mostr
     Ok(orders)
 }
 
-pub async fn find_solver_pubkey(
-    pool: &SqlitePool,
-    solver_npub: String,
-) -> Result<User, MostroError> {
+pub async fn find_solver_pubkey(pool: &DbPool, solver_npub: String) -> Result<User, MostroError> {
     let user = sqlx::query_as::<_, User>(
         r#"
           SELECT *
           FROM users
-          WHERE pubkey == ?1 AND  is_solver == true
+          WHERE pubkey = $1 AND is_solver = 1
           LIMIT 1
         "#,
     )
@@ -1307,12 +1481,12 @@ pub async fn find_solver_pubkey(
     Ok(user)
 }
 
-pub async fn is_user_present(pool: &SqlitePool, public_key: String) -> Result<User, MostroError> {
+pub async fn is_user_present(pool: &DbPool, public_key: String) -> Result<User, MostroError> {
     let user = sqlx::query_as::<_, User>(
         r#"
             SELECT *
             FROM users
-            WHERE pubkey == ?1
+            WHERE pubkey = $1
             LIMIT 1
         "#,
     )
@@ -1324,12 +1498,12 @@ pub async fn is_user_present(pool: &SqlitePool, public_key: String) -> Result<Us
     Ok(user)
 }
 
-pub async fn add_new_user(pool: &SqlitePool, new_user: User) -> Result<String, MostroError> {
+pub async fn add_new_user(pool: &DbPool, new_user: User) -> Result<String, MostroError> {
     let created_at: Timestamp = Timestamp::now();
     let _result = sqlx::query(
         "
             INSERT INTO users (pubkey, is_admin,admin_password, is_solver, is_banned, category, last_trade_index, total_reviews, total_rating, last_rating, max_rating, min_rating, created_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
         ",
     )
     .bind(new_user.pubkey.clone())
@@ -1353,8 +1527,32 @@ pub async fn add_new_user(pool: &SqlitePool, new_user: User) -> Result<String, M
     Ok(new_user.pubkey)
 }
 
+/// Ban or unban an identity key. A key Mostro has never seen gets a fresh
+/// `users` row so the ban is waiting for its first order.
+pub async fn set_user_banned(
+    pool: &DbPool,
+    public_key: &str,
+    banned: bool,
+) -> Result<(), MostroError> {
+    // Validate public key format (32-bytes hex)
+    if !public_key.chars().all(|c| c.is_ascii_hexdigit()) || public_key.len() != 64 {
+        return Err(MostroCantDo(CantDoReason::InvalidPubkey));
+    }
+    sqlx::query(
+        "INSERT INTO users (pubkey, is_banned, created_at) VALUES ($1, $2, $3) \
+         ON CONFLICT(pubkey) DO UPDATE SET is_banned = excluded.is_banned",
+    )
+    .bind(public_key)
+    .bind(i64::from(banned))
+    .bind(Timestamp::now().as_secs() as i64)
+    .execute(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    Ok(())
+}
+
 pub async fn update_user_trade_index(
-    pool: &SqlitePool,
+    pool: &DbPool,
     public_key: String,
     trade_index: i64,
 ) -> Result<bool, MostroError> {
@@ -1369,7 +1567,7 @@ pub async fn update_user_trade_index(
 
     let result = sqlx::query(
         r#"
-            UPDATE users SET last_trade_index = ?1 WHERE pubkey = ?2
+            UPDATE users SET last_trade_index = $1 WHERE pubkey = $2
         "#,
     )
     .bind(trade_index)
@@ -1382,23 +1580,17 @@ pub async fn update_user_trade_index(
     Ok(rows_affected > 0)
 }
 
-pub async fn buyer_has_pending_order(
-    pool: &SqlitePool,
-    pubkey: String,
-) -> Result<bool, MostroError> {
+pub async fn buyer_has_pending_order(pool: &DbPool, pubkey: String) -> Result<bool, MostroError> {
     has_pending_order_with_status(pool, pubkey, "master_buyer_pubkey", "waiting-buyer-invoice")
         .await
 }
 
-pub async fn seller_has_pending_order(
-    pool: &SqlitePool,
-    pubkey: String,
-) -> Result<bool, MostroError> {
+pub async fn seller_has_pending_order(pool: &DbPool, pubkey: String) -> Result<bool, MostroError> {
     has_pending_order_with_status(pool, pubkey, "master_seller_pubkey", "waiting-payment").await
 }
 
 async fn has_pending_order_with_status(
-    pool: &SqlitePool,
+    pool: &DbPool,
     pubkey: String,
     master_key_field: &str,
     status: &str,
@@ -1409,17 +1601,15 @@ async fn has_pending_order_with_status(
     }
 
     let exists = match master_key_field {
-        "master_buyer_pubkey" => {
-            sqlx::query_scalar::<_, bool>(
-                "SELECT EXISTS (SELECT 1 FROM orders WHERE master_buyer_pubkey = ? AND status = ?)",
-            )
-            .bind(pubkey)
-            .bind(status)
-            .fetch_one(pool)
-            .await
-        }
+        "master_buyer_pubkey" => sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM orders WHERE master_buyer_pubkey = $1 AND status = $2)",
+        )
+        .bind(pubkey)
+        .bind(status)
+        .fetch_one(pool)
+        .await,
         "master_seller_pubkey" => sqlx::query_scalar::<_, bool>(
-            "SELECT EXISTS (SELECT 1 FROM orders WHERE master_seller_pubkey = ? AND status = ?)",
+            "SELECT EXISTS (SELECT 1 FROM orders WHERE master_seller_pubkey = $1 AND status = $2)",
         )
         .bind(pubkey)
         .bind(status)
@@ -1444,14 +1634,14 @@ async fn has_pending_order_with_status(
 /// Returns `Ok(true)` when this caller won the claim, or `Ok(false)` when the
 /// flag was already set or the order left the eligible status window.
 pub async fn claim_order_rating_flag(
-    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    tx: &mut sqlx::Transaction<'_, Db>,
     order_id: Uuid,
     buyer_side: bool,
 ) -> Result<bool, MostroError> {
     let result = if buyer_side {
         sqlx::query(
-            "UPDATE orders SET buyer_sent_rate = 1 \
-             WHERE id = ? AND buyer_sent_rate = 0 AND status = ?",
+            "UPDATE orders SET buyer_sent_rate = TRUE \
+             WHERE id = $1 AND buyer_sent_rate = FALSE AND status = $2",
         )
         .bind(order_id)
         .bind(Status::Success.to_string())
@@ -1459,8 +1649,8 @@ pub async fn claim_order_rating_flag(
         .await
     } else {
         sqlx::query(
-            "UPDATE orders SET seller_sent_rate = 1 \
-             WHERE id = ? AND seller_sent_rate = 0 AND status IN (?, ?)",
+            "UPDATE orders SET seller_sent_rate = TRUE \
+             WHERE id = $1 AND seller_sent_rate = FALSE AND status IN ($2, $3)",
         )
         .bind(order_id)
         .bind(Status::Success.to_string())
@@ -1490,7 +1680,7 @@ pub async fn update_user_rating<'e, E>(
     total_rating: f64,
 ) -> Result<bool, MostroError>
 where
-    E: sqlx::Executor<'e, Database = Sqlite>,
+    E: sqlx::Executor<'e, Database = Db>,
 {
     // Validate public key format (32-bytes hex)
     if !public_key.chars().all(|c| c.is_ascii_hexdigit()) || public_key.len() != 64 {
@@ -1517,7 +1707,7 @@ where
     }
     let result = sqlx::query(
         r#"
-            UPDATE users SET last_rating = ?1, min_rating = ?2, max_rating = ?3, total_reviews = ?4, total_rating = ?5 WHERE pubkey = ?6
+            UPDATE users SET last_rating = $1, min_rating = $2, max_rating = $3, total_reviews = $4, total_rating = $5 WHERE pubkey = $6
         "#,
     )
     .bind(last_rating)
@@ -1539,7 +1729,7 @@ where
 /// the matching user row is a solver with read-write permission
 /// (`users.is_solver = true` and `users.category = 2`).
 pub async fn solver_has_write_permission(
-    pool: &SqlitePool,
+    pool: &DbPool,
     solver_pubkey: &str,
     order_id: Uuid,
 ) -> Result<bool, MostroError> {
@@ -1549,9 +1739,9 @@ pub async fn solver_has_write_permission(
             SELECT 1
             FROM disputes d
             INNER JOIN users u ON u.pubkey = d.solver_pubkey
-            WHERE d.solver_pubkey = ?1
-              AND d.order_id = ?2
-              AND u.is_solver = true
+            WHERE d.solver_pubkey = $1
+              AND d.order_id = $2
+              AND u.is_solver = 1
               AND u.category = 2
         )
         "#,
@@ -1572,7 +1762,7 @@ pub async fn solver_has_write_permission(
 /// category checks, matching `admin_take_dispute`. Human solvers must also have
 /// read-write permission on the assigned dispute.
 pub async fn ensure_dispute_finalize_permission(
-    pool: &SqlitePool,
+    pool: &DbPool,
     caller_pubkey: &str,
     admin_pubkey: &str,
     order_id: Uuid,
@@ -1595,7 +1785,7 @@ pub async fn ensure_dispute_finalize_permission(
 /// of any dispute assignment. Use this when the caller is a prospective taker
 /// rather than the currently assigned solver.
 pub async fn user_has_solver_write_permission(
-    pool: &SqlitePool,
+    pool: &DbPool,
     pubkey: &str,
 ) -> Result<bool, MostroError> {
     let result = sqlx::query_scalar::<_, bool>(
@@ -1603,8 +1793,8 @@ pub async fn user_has_solver_write_permission(
         SELECT EXISTS(
             SELECT 1
             FROM users
-            WHERE pubkey = ?1
-              AND is_solver = true
+            WHERE pubkey = $1
+              AND is_solver = 1
               AND category = 2
         )
         "#,
@@ -1618,7 +1808,7 @@ pub async fn user_has_solver_write_permission(
 }
 
 pub async fn is_assigned_solver(
-    pool: &SqlitePool,
+    pool: &DbPool,
     solver_pubkey: &str,
     order_id: Uuid,
 ) -> Result<bool, MostroError> {
@@ -1628,11 +1818,11 @@ pub async fn is_assigned_solver(
         order_id
     );
     let result = sqlx::query(
-        "SELECT EXISTS(SELECT 1 FROM disputes WHERE solver_pubkey = ? AND order_id = ?)",
+        "SELECT EXISTS(SELECT 1 FROM disputes WHERE solver_pubkey = $1 AND order_id = $2)",
     )
     .bind(solver_pubkey)
     .bind(order_id)
-    .map(|row: SqliteRow| row.get(0))
+    .map(|row: DbRow| row.get(0))
     .fetch_one(pool)
     .await
     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
@@ -1643,13 +1833,13 @@ pub async fn is_assigned_solver(
 /// Check if a dispute has been taken over by admin (Mostro daemon)
 /// This helps provide better error messages when solver tries to act on admin-taken disputes
 pub async fn is_dispute_taken_by_admin(
-    pool: &SqlitePool,
+    pool: &DbPool,
     order_id: Uuid,
     admin_pubkey: &str,
 ) -> Result<bool, MostroError> {
     // Get the dispute for this order
     let dispute = sqlx::query(
-        "SELECT solver_pubkey FROM disputes WHERE order_id = ? AND status = 'in-progress'",
+        "SELECT solver_pubkey FROM disputes WHERE order_id = $1 AND status = 'in-progress'",
     )
     .bind(order_id)
     .fetch_optional(pool)
@@ -1669,11 +1859,204 @@ pub async fn is_dispute_taken_by_admin(
     Ok(false)
 }
 
-/// Find all orders for a user by their master key (for restore session).
+/// Filter for [`list_orders`], the admin `ListOrders` RPC. `None` fields
+/// match everything; dates are unix seconds on `created_at`, both inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub status: Option<String>,
+    pub fiat_code: Option<String>,
+    pub created_from: Option<i64>,
+    pub created_to: Option<i64>,
+    /// Matches the creator, either trade key or either master key.
+    pub pubkey: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Filter for [`list_disputes`], the admin `ListDisputes` RPC. Fiat code and
+/// pubkey are matched against the disputed order; the pubkey also matches
+/// the assigned solver.
+#[derive(Debug, Clone, Default)]
+pub struct DisputeFilter {
+    pub status: Option<String>,
+    pub fiat_code: Option<String>,
+    pub created_from: Option<i64>,
+    pub created_to: Option<i64>,
+    pub pubkey: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// One page of orders matching `filter`, newest first.
+pub async fn list_orders(pool: &DbPool, filter: &OrderFilter) -> Result<Vec<Order>, MostroError> {
+    let mut query = QueryBuilder::<Db>::new("SELECT * FROM orders WHERE 1 = 1");
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(fiat_code) = &filter.fiat_code {
+        query.push(" AND fiat_code = ").push_bind(fiat_code);
+    }
+    if let Some(from) = filter.created_from {
+        query.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.created_to {
+        query.push(" AND created_at <= ").push_bind(to);
+    }
+    if let Some(pubkey) = &filter.pubkey {
+        query.push(" AND ").push_bind(pubkey).push(
+            " IN (creator_pubkey, buyer_pubkey, seller_pubkey, master_buyer_pubkey, master_seller_pubkey)",
+        );
+    }
+    query
+        .push(" ORDER BY created_at DESC, id LIMIT ")
+        .push_bind(filter.limit)
+        .push(" OFFSET ")
+        .push_bind(filter.offset);
+
+    query
+        .build_query_as::<Order>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+}
+
+/// Filter for [`find_order_book`], the `orders` action's order book query.
+/// `None` fields match everything; bounds are inclusive and amounts in sats.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookFilter {
+    pub kind: Option<String>,
+    pub fiat_code: Option<String>,
+    /// Case-insensitive substring of the order's payment methods.
+    pub payment_method: Option<String>,
+    /// Sats bounds. Market-priced orders (`amount = 0`) have no sats amount
+    /// until taken and always match.
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    pub min_premium: Option<i64>,
+    pub max_premium: Option<i64>,
+    /// Minimum published reputation score of the maker's identity; unrated
+    /// makers count 0.
+    pub min_rating: Option<f64>,
+    /// Keyset cursor: only orders after this `(created_at, id)` in the
+    /// newest-first order. It comes from the client and is never looked up.
+    pub after: Option<(i64, Uuid)>,
+    pub limit: i64,
+}
+
+/// One page of pending orders of `instance` matching `filter`, newest first.
+pub async fn find_order_book(
+    pool: &DbPool,
+    instance: &str,
+    filter: &OrderBookFilter,
+) -> Result<Vec<Order>, MostroError> {
+    let mut query = QueryBuilder::<Db>::new(
+        "SELECT o.* FROM orders o LEFT JOIN users u ON u.pubkey = \
+         CASE WHEN o.kind = 'sell' THEN o.master_seller_pubkey ELSE o.master_buyer_pubkey END \
+         WHERE o.status = 'pending' AND o.instance = ",
+    );
+    query.push_bind(instance);
+    if let Some(kind) = &filter.kind {
+        query.push(" AND o.kind = ").push_bind(kind);
+    }
+    if let Some(fiat_code) = &filter.fiat_code {
+        query.push(" AND o.fiat_code = ").push_bind(fiat_code);
+    }
+    if let Some(method) = &filter.payment_method {
+        query
+            .push(format!(" AND {STRPOS}(lower(o.payment_method), lower("))
+            .push_bind(method)
+            .push(")) > 0");
+    }
+    if let Some(min) = filter.min_amount {
+        query
+            .push(" AND (o.amount = 0 OR o.amount >= ")
+            .push_bind(min)
+            .push(")");
+    }
+    if let Some(max) = filter.max_amount {
+        query
+            .push(" AND (o.amount = 0 OR o.amount <= ")
+            .push_bind(max)
+            .push(")");
+    }
+    if let Some(min) = filter.min_premium {
+        query.push(" AND o.premium >= ").push_bind(min);
+    }
+    if let Some(max) = filter.max_premium {
+        query.push(" AND o.premium <= ").push_bind(max);
+    }
+    if let Some(rating) = filter.min_rating {
+        query
+            .push(" AND COALESCE(u.reputation_score, 0) >= ")
+            .push_bind(rating);
+    }
+    if let Some((created_at, id)) = filter.after {
+        query
+            .push(" AND (o.created_at < ")
+            .push_bind(created_at)
+            .push(" OR (o.created_at = ")
+            .push_bind(created_at)
+            .push(" AND o.id < ")
+            .push_bind(id)
+            .push("))");
+    }
+    query
+        .push(" ORDER BY o.created_at DESC, o.id DESC LIMIT ")
+        .push_bind(filter.limit);
+
+    query
+        .build_query_as::<Order>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+}
+
+/// One page of disputes matching `filter`, newest first.
+pub async fn list_disputes(
+    pool: &DbPool,
+    filter: &DisputeFilter,
+) -> Result<Vec<Dispute>, MostroError> {
+    let mut query = QueryBuilder::<Db>::new(
+        "SELECT d.* FROM disputes d JOIN orders o ON d.order_id = o.id WHERE 1 = 1",
+    );
+    if let Some(status) = &filter.status {
+        query.push(" AND d.status = ").push_bind(status);
+    }
+    if let Some(fiat_code) = &filter.fiat_code {
+        query.push(" AND o.fiat_code = ").push_bind(fiat_code);
+    }
+    if let Some(from) = filter.created_from {
+        query.push(" AND d.created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.created_to {
+        query.push(" AND d.created_at <= ").push_bind(to);
+    }
+    if let Some(pubkey) = &filter.pubkey {
+        query.push(" AND ").push_bind(pubkey).push(
+            " IN (d.solver_pubkey, o.creator_pubkey, o.buyer_pubkey, o.seller_pubkey, \
+             o.master_buyer_pubkey, o.master_seller_pubkey)",
+        );
+    }
+    query
+        .push(" ORDER BY d.created_at DESC, d.id LIMIT ")
+        .push_bind(filter.limit)
+        .push(" OFFSET ")
+        .push_bind(filter.offset);
+
+    query
+        .build_query_as::<Dispute>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))
+}
+
+/// Find all orders for a user by their master key (for restore session),
+/// limited to the orders of Mostro instance `instance`.
 /// Uses constants for excluded statuses to maintain consistency across queries.
 pub async fn find_user_orders_by_master_key(
-    pool: &SqlitePool,
+    pool: &DbPool,
     master_key: &str,
+    instance: &str,
 ) -> Result<Vec<RestoredOrdersInfo>, MostroError> {
     // Validate public key format (32-bytes hex)
     if !master_key.chars().all(|c| c.is_ascii_hexdigit()) || master_key.len() != 64 {
@@ -1683,18 +2066,20 @@ pub async fn find_user_orders_by_master_key(
     let sql_query = format!(
         r#"
         SELECT id as order_id, trade_index_buyer as trade_index, status FROM orders 
-        WHERE (master_buyer_pubkey = ?)
-        AND status NOT IN ({})
+        WHERE (master_buyer_pubkey = $1)
+        AND status NOT IN ({}) AND instance = $2
         UNION ALL
         SELECT id as order_id, trade_index_seller as trade_index, status FROM orders 
-        WHERE (master_seller_pubkey = ?)
-        AND status NOT IN ({})
+        WHERE (master_seller_pubkey = $3)
+        AND status NOT IN ({}) AND instance = $4
         "#,
         EXCLUDED_ORDER_STATUSES, EXCLUDED_ORDER_STATUSES
     );
     let orders = sqlx::query_as::<_, RestoredOrdersInfo>(AssertSqlSafe(sql_query))
         .bind(master_key)
+        .bind(instance)
         .bind(master_key)
+        .bind(instance)
         .fetch_all(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
@@ -1702,10 +2087,12 @@ pub async fn find_user_orders_by_master_key(
     Ok(orders)
 }
 
-/// Find all disputes for a user by their master key (for restore session)
+/// Find all disputes for a user by their master key (for restore session),
+/// limited to the orders of Mostro instance `instance`.
 pub async fn find_user_disputes_by_master_key(
-    pool: &SqlitePool,
+    pool: &DbPool,
     master_key: &str,
+    instance: &str,
 ) -> Result<Vec<RestoredDisputesInfo>, MostroError> {
     // Validate public key format (32-bytes hex)
     if !master_key.chars().all(|c| c.is_ascii_hexdigit()) || master_key.len() != 64 {
@@ -1719,22 +2106,23 @@ pub async fn find_user_disputes_by_master_key(
             d.order_id AS order_id,
             COALESCE(
                 CASE
-                    WHEN o.master_buyer_pubkey = ? THEN o.trade_index_buyer
-                    WHEN o.master_seller_pubkey = ? THEN o.trade_index_seller
+                    WHEN o.master_buyer_pubkey = $1 THEN o.trade_index_buyer
+                    WHEN o.master_seller_pubkey = $2 THEN o.trade_index_seller
                     ELSE 0
                 END, 0
             ) AS trade_index,
             d.status AS status,
             CASE
-                WHEN o.buyer_dispute = 1 AND o.seller_dispute = 0 THEN 'buyer'
-                WHEN o.seller_dispute = 1 AND o.buyer_dispute = 0 THEN 'seller'
+                WHEN o.buyer_dispute = TRUE AND o.seller_dispute = FALSE THEN 'buyer'
+                WHEN o.seller_dispute = TRUE AND o.buyer_dispute = FALSE THEN 'seller'
                 ELSE NULL
             END AS initiator,
             d.solver_pubkey AS solver_pubkey
         FROM disputes d
         JOIN orders o ON d.order_id = o.id
-        WHERE (o.master_buyer_pubkey = ? OR o.master_seller_pubkey = ?)
+        WHERE (o.master_buyer_pubkey = $3 OR o.master_seller_pubkey = $4)
             AND d.status IN ({})
+            AND o.instance = $5
         "#,
         ACTIVE_DISPUTE_STATUSES
     );
@@ -1745,6 +2133,7 @@ pub async fn find_user_disputes_by_master_key(
         //WHERE
         .bind(master_key)
         .bind(master_key)
+        .bind(instance)
         .fetch_all(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
@@ -1754,13 +2143,14 @@ pub async fn find_user_disputes_by_master_key(
 
 /// The actual work function that runs the restore session query.
 async fn process_restore_session_work(
-    pool: SqlitePool,
+    pool: DbPool,
     master_key: String,
+    instance: String,
 ) -> Result<RestoreSessionInfo, MostroError> {
     // Find all active orders for this user
-    let restore_orders = find_user_orders_by_master_key(&pool, &master_key).await?;
+    let restore_orders = find_user_orders_by_master_key(&pool, &master_key, &instance).await?;
     // Find all active disputes for this user
-    let restore_disputes = find_user_disputes_by_master_key(&pool, &master_key).await?;
+    let restore_disputes = find_user_disputes_by_master_key(&pool, &master_key, &instance).await?;
 
     tracing::info!(
         "Background restore session completed with {} orders, {} disputes",
@@ -1792,18 +2182,20 @@ impl RestoreSessionManager {
         Self { sender, receiver }
     }
 
-    /// Start a restore session background task
+    /// Start a restore session background task over the orders of Mostro
+    /// instance `instance`
     pub async fn start_restore_session(
         &self,
-        pool: SqlitePool,
+        pool: DbPool,
         master_key: String,
+        instance: String,
     ) -> Result<(), MostroError> {
         let sender = self.sender.clone();
 
         // Use spawn_blocking to avoid blocking the async runtime
         let handle = tokio::runtime::Handle::current();
         tokio::task::spawn_blocking(move || {
-            match handle.block_on(process_restore_session_work(pool, master_key)) {
+            match handle.block_on(process_restore_session_work(pool, master_key, instance)) {
                 Ok(restore_data) => {
                     // No need for an async context just to send; this is a blocking thread.
                     if let Err(e) = sender.blocking_send(restore_data) {
@@ -1833,23 +2225,261 @@ impl RestoreSessionManager {
     }
 }
 
+/// Pools for unit tests, on whichever backend the binary is built for.
+#[cfg(test)]
+pub mod test_utils {
+    use super::DbPool;
+    use std::sync::Arc;
+
+    /// A fresh database with every migration applied. Also installs the
+    /// daemon-wide pool ([`global_pool`]) first, so the code under test can
+    /// store outbound messages whatever pool its context carries.
+    pub async fn migrated_pool() -> DbPool {
+        global_pool().await;
+        fresh_pool().await
+    }
+
+    /// The daemon-wide pool ([`crate::config::DB_POOL`]) that code without
+    /// an `AppContext` — chiefly the `util::enqueue_*` helpers — writes to. One
+    /// database shared by every test, so readers filter by their own ids.
+    pub async fn global_pool() -> Arc<DbPool> {
+        static INIT: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+        let _guard = INIT.lock().await;
+        // The `enqueue_*` helpers encrypt what they store with the node key.
+        crate::config::init_test_nostr_keys();
+        if let Some(pool) = crate::config::DB_POOL.get() {
+            return pool.clone();
+        }
+        let _ = crate::config::DB_POOL.set(Arc::new(shared_pool().await));
+        crate::config::DB_POOL.get().expect("just set").clone()
+    }
+
+    /// SQLite: a pooled connection dropped by a finished test's runtime is
+    /// closed, and an in-memory database goes with its last connection. sqlx
+    /// names each pool's database, so a second connection to the same
+    /// options, leaked for the life of the test binary, keeps it open for
+    /// the pool's replacement connection.
+    #[cfg(not(feature = "postgres"))]
+    async fn shared_pool() -> DbPool {
+        use sqlx::Connection;
+
+        let pool = fresh_pool().await;
+        let conn = sqlx::SqliteConnection::connect_with(&pool.connect_options())
+            .await
+            .expect("hold the global test database open");
+        std::mem::forget(conn);
+        pool
+    }
+
+    /// PostgreSQL: a connection stays bound to the runtime that opened it,
+    /// and a test's runtime is gone once the test returns. Every connection
+    /// is closed on release, so each checkout connects on its caller's
+    /// runtime.
+    #[cfg(feature = "postgres")]
+    async fn shared_pool() -> DbPool {
+        postgres_pool(true).await
+    }
+
+    /// SQLite: `sqlite::memory:` gives every *connection* its own private
+    /// database, so a multi-connection pool can hand a second caller a blank
+    /// schema (a `spawn_blocking` worker on another connection would find
+    /// neither the migrations nor the rows a test inserted). Capping the pool
+    /// at one connection keeps every acquisition on the same database.
+    ///
+    /// The connection never idles out or expires: closing it would lose the
+    /// database.
+    #[cfg(not(feature = "postgres"))]
+    async fn fresh_pool() -> DbPool {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .expect("open in-memory sqlite");
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("sqlite migrations");
+        pool
+    }
+
+    /// PostgreSQL: a new schema on `MOSTRO_TEST_DATABASE_URL`, private to
+    /// this pool through its `search_path`, so tests running in parallel
+    /// never see each other's rows. One connection, like the SQLite pool, so
+    /// session settings ([`foreign_keys`]) hold for the whole test.
+    ///
+    /// The acquire timeout is an hour: tests that pause the tokio clock keep
+    /// auto-advancing it while a query waits on the server, which would trip
+    /// the default 30 s timeout long before any real time has passed.
+    #[cfg(feature = "postgres")]
+    async fn fresh_pool() -> DbPool {
+        postgres_pool(false).await
+    }
+
+    #[cfg(feature = "postgres")]
+    async fn postgres_pool(close_on_release: bool) -> DbPool {
+        use sqlx::{AssertSqlSafe, Executor};
+
+        let url = std::env::var("MOSTRO_TEST_DATABASE_URL")
+            .expect("MOSTRO_TEST_DATABASE_URL must point at a PostgreSQL database");
+        let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let admin = DbPool::connect(&url).await.expect("connect to postgres");
+        admin
+            .execute(AssertSqlSafe(format!("CREATE SCHEMA {schema}")))
+            .await
+            .expect("create test schema");
+        admin.close().await;
+
+        let set_path = format!("SET search_path TO {schema}");
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_secs(3600))
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .after_connect(move |conn, _| {
+                let set_path = set_path.clone();
+                Box::pin(async move {
+                    conn.execute(AssertSqlSafe(set_path)).await?;
+                    Ok(())
+                })
+            })
+            .after_release(move |_, _| Box::pin(async move { Ok(!close_on_release) }))
+            .connect(&url)
+            .await
+            .expect("connect to postgres");
+        run_migrations(&pool).await;
+        pool
+    }
+
+    /// Turn foreign key enforcement on or off for the pool's connection, to
+    /// plant the orphan rows a crashed or older daemon could leave behind.
+    pub async fn foreign_keys(pool: &DbPool, on: bool) {
+        #[cfg(not(feature = "postgres"))]
+        let sql = if on {
+            "PRAGMA foreign_keys = ON"
+        } else {
+            "PRAGMA foreign_keys = OFF"
+        };
+        #[cfg(feature = "postgres")]
+        let sql = if on {
+            "SET session_replication_role = origin"
+        } else {
+            "SET session_replication_role = replica"
+        };
+        sqlx::query(sql)
+            .execute(pool)
+            .await
+            .expect("toggle foreign keys");
+    }
+
+    /// Drop `table` even while other tables reference it, to make every
+    /// query on it fail.
+    pub async fn drop_table(pool: &DbPool, table: &str) {
+        #[cfg(not(feature = "postgres"))]
+        let sql = format!("DROP TABLE {table}");
+        #[cfg(feature = "postgres")]
+        let sql = format!("DROP TABLE {table} CASCADE");
+        sqlx::query(sqlx::AssertSqlSafe(sql))
+            .execute(pool)
+            .await
+            .expect("drop table");
+    }
+
+    /// Install a row trigger named `name` that runs `body` (`;`-separated
+    /// statements, which may use `NEW`) on `on`, e.g.
+    /// `AFTER UPDATE OF state ON bonds`, optionally gated by `when`.
+    pub async fn create_trigger(
+        pool: &DbPool,
+        name: &str,
+        on: &str,
+        when: Option<&str>,
+        body: &str,
+    ) {
+        #[cfg(not(feature = "postgres"))]
+        let statements = {
+            let when = when.map(|w| format!("WHEN {w} ")).unwrap_or_default();
+            vec![format!(
+                "CREATE TRIGGER {name} {on} {when}BEGIN {body}; END"
+            )]
+        };
+        #[cfg(feature = "postgres")]
+        let statements = {
+            let when = when.map(|w| format!("WHEN ({w}) ")).unwrap_or_default();
+            vec![
+                format!(
+                    "CREATE FUNCTION {name}_fn() RETURNS trigger LANGUAGE plpgsql AS \
+                     $$ BEGIN {body}; RETURN NEW; END $$"
+                ),
+                format!(
+                    "CREATE TRIGGER {name} {on} FOR EACH ROW {when}EXECUTE FUNCTION {name}_fn()"
+                ),
+            ]
+        };
+        for sql in statements {
+            sqlx::query(sqlx::AssertSqlSafe(sql))
+                .execute(pool)
+                .await
+                .expect("create trigger");
+        }
+    }
+
+    /// Remove a trigger installed by [`create_trigger`] on `table`.
+    pub async fn drop_trigger(pool: &DbPool, name: &str, table: &str) {
+        let sql = if cfg!(feature = "postgres") {
+            format!("DROP TRIGGER {name} ON {table}")
+        } else {
+            format!("DROP TRIGGER {name}")
+        };
+        sqlx::query(sqlx::AssertSqlSafe(sql))
+            .execute(pool)
+            .await
+            .expect("drop trigger");
+    }
+
+    /// Install a trigger that fails every statement matching `on` / `when`
+    /// with `message`, to inject a database error.
+    pub async fn abort_trigger(
+        pool: &DbPool,
+        name: &str,
+        on: &str,
+        when: Option<&str>,
+        message: &str,
+    ) {
+        #[cfg(not(feature = "postgres"))]
+        let body = format!("SELECT RAISE(ABORT, '{message}')");
+        #[cfg(feature = "postgres")]
+        let body = format!("RAISE EXCEPTION '{message}'");
+        create_trigger(pool, name, on, when, &body).await;
+    }
+
+    /// Apply this backend's migrations; a no-op on an up-to-date pool.
+    pub async fn run_migrations(pool: &DbPool) {
+        #[cfg(not(feature = "postgres"))]
+        let migrator = sqlx::migrate!();
+        #[cfg(feature = "postgres")]
+        let migrator = sqlx::migrate!("./migrations/postgres");
+        migrator
+            .run(pool)
+            .await
+            .expect("the test pool must be fully migrated");
+    }
+}
+
 // Add this cfg attribute if the code is *only* for testing
 #[cfg(test)]
 mod tests {
+    use super::test_utils::migrated_pool;
+    use super::DbPool;
     use mostro_core::error::CantDoReason;
     use mostro_core::prelude::MostroError;
-    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
     use sqlx::{AssertSqlSafe, Error};
     use std::collections::HashSet;
 
-    const TEST_DB_URL: &str = "sqlite::memory:";
-
     // Helper function to set up the database and pool
-    async fn setup_db() -> Result<SqlitePool, Error> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1) // Usually fine for simple tests
-            .connect(TEST_DB_URL)
-            .await?;
+    async fn setup_db() -> Result<DbPool, Error> {
+        let pool = migrated_pool().await;
 
         // Create the table
         sqlx::query(
@@ -1867,69 +2497,8 @@ mod tests {
     }
 
     /// Create the orders table matching the production schema (base + dev_fee migration)
-    async fn setup_orders_db() -> Result<SqlitePool, Error> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(TEST_DB_URL)
-            .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS orders (
-                id char(36) primary key not null,
-                kind varchar(4) not null,
-                event_id char(64) not null,
-                hash char(64),
-                preimage char(64),
-                creator_pubkey char(64),
-                cancel_initiator_pubkey char(64),
-                dispute_initiator_pubkey char(64),
-                buyer_pubkey char(64),
-                master_buyer_pubkey char(64),
-                seller_pubkey char(64),
-                master_seller_pubkey char(64),
-                status varchar(50) not null,
-                price_from_api integer not null default 0,
-                premium integer not null,
-                payment_method varchar(500) not null,
-                amount integer not null,
-                min_amount integer default 0,
-                max_amount integer default 0,
-                buyer_dispute integer not null default 0,
-                seller_dispute integer not null default 0,
-                buyer_cooperativecancel integer not null default 0,
-                seller_cooperativecancel integer not null default 0,
-                fee integer not null default 0,
-                routing_fee integer not null default 0,
-                fiat_code varchar(5) not null,
-                fiat_amount integer not null,
-                buyer_invoice text,
-                range_parent_id char(36),
-                invoice_held_at integer default 0,
-                taken_at integer default 0,
-                created_at integer not null,
-                buyer_sent_rate integer default 0,
-                seller_sent_rate integer default 0,
-                payment_attempts integer default 0,
-                failed_payment integer default 0,
-                expires_at integer not null,
-                trade_index_seller integer default 0,
-                trade_index_buyer integer default 0,
-                next_trade_pubkey char(64),
-                next_trade_index integer default 0,
-                dev_fee integer default 0,
-                dev_fee_paid integer not null default 0,
-                dev_fee_payment_hash char(64),
-                cashu_mint_url text,
-                cashu_escrow_token text,
-                cashu_escrow_locked_at integer,
-                payout_payment_hash char(64),
-                payout_claimed_at integer
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+    async fn setup_orders_db() -> Result<DbPool, Error> {
+        let pool = migrated_pool().await;
 
         Ok(pool)
     }
@@ -1937,7 +2506,7 @@ mod tests {
     /// Insert a minimal test order with the fields relevant to order status and dev fee queries.
     /// Binds `id` as `Uuid` so storage format matches production queries.
     async fn insert_test_order(
-        pool: &SqlitePool,
+        pool: &DbPool,
         id: uuid::Uuid,
         status: &str,
         dev_fee: i64,
@@ -1950,9 +2519,9 @@ mod tests {
                                 amount, fiat_code, fiat_amount, created_at, expires_at,
                                 failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                                 dev_fee_payment_hash)
-            VALUES (?1, 'buy', 'event123', ?2, 0, 'lightning',
+            VALUES ($1, 'buy', 'event123', $2, 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, ?3, ?4, ?5)
+                    FALSE, 0, $3, $4, $5)
             "#,
         )
         .bind(id)
@@ -1968,7 +2537,7 @@ mod tests {
     /// Insert an order carrying explicit trade pubkeys, for the Phase 2
     /// active-trade-pubkey cache query.
     async fn insert_order_with_pubkeys(
-        pool: &SqlitePool,
+        pool: &DbPool,
         id: uuid::Uuid,
         status: &str,
         creator: Option<&str>,
@@ -1980,8 +2549,8 @@ mod tests {
             INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                                 amount, fiat_code, fiat_amount, created_at, expires_at,
                                 creator_pubkey, buyer_pubkey, seller_pubkey)
-            VALUES (?1, 'buy', 'event123', ?2, 0, 'lightning',
-                    100000, 'USD', 100, 1700000000, 1700086400, ?3, ?4, ?5)
+            VALUES ($1, 'buy', 'event123', $2, 0, 'lightning',
+                    100000, 'USD', 100, 1700000000, 1700086400, $3, $4, $5)
             "#,
         )
         .bind(id)
@@ -1994,25 +2563,6 @@ mod tests {
         .unwrap();
     }
 
-    async fn setup_disputes_table(pool: &SqlitePool) {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS disputes (
-                id char(36) primary key not null,
-                order_id char(36) unique not null,
-                status varchar(10) not null,
-                order_previous_status varchar(10) not null,
-                solver_pubkey char(64),
-                created_at integer not null,
-                taken_at integer default 0
-            )
-            "#,
-        )
-        .execute(pool)
-        .await
-        .unwrap();
-    }
-
     /// Phase 2 (docs/TRANSPORT_V2_SPEC.md §6): the active-trade-pubkey cache
     /// must include participants of every non-terminal order — **including
     /// disputed ones** — and the solvers of active disputes, while excluding
@@ -2023,7 +2573,6 @@ mod tests {
         // inserted as a zero-length "active key" — exercises the
         // `if !pk.is_empty()` guard for both the orders and disputes loops.
         let pool = setup_orders_db().await.unwrap();
-        setup_disputes_table(&pool).await;
 
         insert_order_with_pubkeys(
             &pool,
@@ -2037,7 +2586,7 @@ mod tests {
         // Active dispute whose solver pubkey is the empty string → skipped.
         sqlx::query(
             "INSERT INTO disputes (id, order_id, status, order_previous_status, solver_pubkey, created_at) \
-             VALUES (?1, ?2, 'in-progress', 'fiat-sent', '', 1700000000)",
+             VALUES ($1, $2, 'in-progress', 'fiat-sent', '', 1700000000)",
         )
         .bind(uuid::Uuid::new_v4())
         .bind(uuid::Uuid::new_v4())
@@ -2062,7 +2611,6 @@ mod tests {
     #[tokio::test]
     async fn find_active_trade_pubkeys_covers_active_and_disputed_excludes_terminal() {
         let pool = setup_orders_db().await.unwrap();
-        setup_disputes_table(&pool).await;
 
         // Active order — all three keys are "known".
         insert_order_with_pubkeys(
@@ -2108,7 +2656,7 @@ mod tests {
         // Active dispute with an assigned solver → solver key included.
         sqlx::query(
             "INSERT INTO disputes (id, order_id, status, order_previous_status, solver_pubkey, created_at) \
-             VALUES (?1, ?2, 'in-progress', 'fiat-sent', 'solver_active', 1700000000)",
+             VALUES ($1, $2, 'in-progress', 'fiat-sent', 'solver_active', 1700000000)",
         )
         .bind(uuid::Uuid::new_v4())
         .bind(uuid::Uuid::new_v4())
@@ -2118,7 +2666,7 @@ mod tests {
         // Resolved dispute → its solver is NOT included.
         sqlx::query(
             "INSERT INTO disputes (id, order_id, status, order_previous_status, solver_pubkey, created_at) \
-             VALUES (?1, ?2, 'settled', 'fiat-sent', 'solver_settled', 1700000000)",
+             VALUES ($1, $2, 'settled', 'fiat-sent', 'solver_settled', 1700000000)",
         )
         .bind(uuid::Uuid::new_v4())
         .bind(uuid::Uuid::new_v4())
@@ -2170,7 +2718,7 @@ mod tests {
             if i > 0 {
                 query_builder.push_str(", ");
             }
-            query_builder.push_str(&format!("({}, ?)", i));
+            query_builder.push_str(&format!("({}, ${})", i, i + 1));
             params.push(value_string);
         }
 
@@ -2324,9 +2872,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     invoice_held_at)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, 1700001000)"#,
+                    FALSE, 0, 0, FALSE, 1700001000)"#,
         )
         .bind(id)
         .execute(&pool)
@@ -2355,9 +2903,9 @@ mod tests {
                         amount, fiat_code, fiat_amount, created_at, expires_at,
                         failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                         invoice_held_at, hash)
-                VALUES (?1, 'buy', 'ev1', ?2, 0, 'lightning',
+                VALUES ($1, 'buy', 'ev1', $2, 0, 'lightning',
                         100000, 'USD', 100, 1700000000, 1700086400,
-                        0, 0, 0, 0, 1700001000, ?3)"#,
+                        FALSE, 0, 0, FALSE, 1700001000, $3)"#,
             )
             .bind(uuid::Uuid::new_v4())
             .bind(status)
@@ -2394,9 +2942,9 @@ mod tests {
                         amount, fiat_code, fiat_amount, created_at, expires_at,
                         failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                         invoice_held_at, hash)
-                VALUES (?1, 'buy', 'ev1', ?2, 0, 'lightning',
+                VALUES ($1, 'buy', 'ev1', $2, 0, 'lightning',
                         100000, 'USD', 100, 1700000000, 1700086400,
-                        0, 0, 0, 0, 1700001000, ?3)"#,
+                        FALSE, 0, 0, FALSE, 1700001000, $3)"#,
             )
             .bind(uuid::Uuid::new_v4())
             .bind(status)
@@ -2427,9 +2975,9 @@ mod tests {
                         amount, fiat_code, fiat_amount, created_at, expires_at,
                         failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                         invoice_held_at, hash)
-                VALUES (?1, 'buy', 'ev1', ?2, 0, 'lightning',
+                VALUES ($1, 'buy', 'ev1', $2, 0, 'lightning',
                         100000, 'USD', 100, 1700000000, 1700086400,
-                        0, 0, 0, 0, 0, ?3)"#,
+                        FALSE, 0, 0, FALSE, 0, $3)"#,
             )
             .bind(uuid::Uuid::new_v4())
             .bind(status)
@@ -2460,9 +3008,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     invoice_held_at, hash)
-            VALUES (?1, 'buy', 'ev1', 'waiting-buyer-invoice', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'waiting-buyer-invoice', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, 1700001000, ?2)"#,
+                    FALSE, 0, 0, FALSE, 1700001000, $2)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .bind("bb".repeat(32))
@@ -2485,9 +3033,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     invoice_held_at)
-            VALUES (?1, 'buy', 'ev1', 'waiting-payment', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'waiting-payment', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, 0)"#,
+                    FALSE, 0, 0, FALSE, 0)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .execute(&pool)
@@ -2500,16 +3048,10 @@ mod tests {
 
     // ── pre-trade compare-and-swap writes ────────────────────────────────
 
-    async fn migrated_pool() -> SqlitePool {
-        let pool = SqlitePool::connect(":memory:").await.unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        pool
-    }
-
     /// A `pending` sell order carrying escrow material, so a targeted write
     /// that touches columns it shouldn't is visible in the assertions.
-    async fn insert_pretrade_order(pool: &SqlitePool, status: &str) -> super::Order {
-        use mostro_core::db::Crud;
+    async fn insert_pretrade_order(pool: &DbPool, status: &str) -> super::Order {
+        use super::Crud;
         let order = super::Order {
             id: uuid::Uuid::new_v4(),
             kind: "sell".to_string(),
@@ -2538,7 +3080,7 @@ mod tests {
                 .unwrap();
         assert!(won, "pending orders accept the transition");
 
-        use mostro_core::db::Crud;
+        use super::Crud;
         let after = super::Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert_eq!(after.status, "canceled");
         assert_eq!(after.event_id, "ev-new");
@@ -2558,12 +3100,43 @@ mod tests {
                 .unwrap();
         assert!(!won, "waiting-payment is past the pre-trade window");
 
-        use mostro_core::db::Crud;
+        use super::Crud;
         let after = super::Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert_eq!(after.status, "waiting-payment");
         assert_ne!(after.event_id, "ev-new");
     }
 
+    #[tokio::test]
+    async fn cas_pretrade_order_status_announces_only_a_committed_transition() {
+        use crate::rpc::admin::admin_event::Event;
+        let pool = migrated_pool().await;
+        let lost = insert_pretrade_order(&pool, "waiting-payment").await;
+        let won = insert_pretrade_order(&pool, "pending").await;
+        let mut events = crate::rpc::events::subscribe();
+
+        for order_id in [lost.id, won.id] {
+            super::cas_pretrade_order_status(&pool, order_id, super::Status::Canceled, "ev-new")
+                .await
+                .unwrap();
+        }
+
+        // Publishing is synchronous, so everything is already buffered. The
+        // channel is process-wide: only our two orders count.
+        let mut announced = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let Some(Event::OrderStatusChanged(e)) = event.event {
+                if e.order_id == lost.id.to_string() || e.order_id == won.id.to_string() {
+                    announced.push((e.order_id, e.status));
+                }
+            }
+        }
+        assert_eq!(
+            announced,
+            vec![(won.id.to_string(), "canceled".to_string())],
+            "a lost compare-and-set must not announce its transition"
+        );
+    }
+
     #[tokio::test]
     async fn cas_complete_pretrade_take_covers_every_legitimate_caller_state() {
         let pool = migrated_pool().await;
@@ -2599,7 +3172,7 @@ mod tests {
                 .unwrap();
             assert!(won, "{from} must accept the take transition");
 
-            use mostro_core::db::Crud;
+            use super::Crud;
             let after = super::Order::by_id(&pool, order.id).await.unwrap().unwrap();
             assert_eq!(after.status, "waiting-payment");
             assert_eq!(after.event_id, "ev-escrow");
@@ -2635,7 +3208,7 @@ mod tests {
             .unwrap();
         assert!(won, "pending orders accept the promotion");
 
-        use mostro_core::db::Crud;
+        use super::Crud;
         let after = super::Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert_eq!(after.buyer_pubkey, Some("bb".repeat(32)));
         assert_eq!(after.master_buyer_pubkey, Some("99".repeat(32)));
@@ -2664,7 +3237,7 @@ mod tests {
             .unwrap();
         assert!(!won);
 
-        use mostro_core::db::Crud;
+        use super::Crud;
         let after = super::Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert_eq!(after.status, "waiting-buyer-invoice");
         assert_ne!(after.buyer_pubkey, Some("bb".repeat(32)));
@@ -2682,7 +3255,7 @@ mod tests {
             .unwrap();
         assert!(!won, "a committed cancel must win over the escrow storage");
 
-        use mostro_core::db::Crud;
+        use super::Crud;
         let after = super::Order::by_id(&pool, order.id).await.unwrap().unwrap();
         assert_eq!(after.status, "canceled");
         assert_ne!(after.event_id, "ev-escrow");
@@ -2690,15 +3263,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_find_escrow_deadline_orders_selects_only_due_escrows() {
-        async fn insert_order(pool: &SqlitePool, status: &str, held_at: i64, with_hash: bool) {
+        async fn insert_order(pool: &DbPool, status: &str, held_at: i64, with_hash: bool) {
             sqlx::query(
                 r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                         amount, fiat_code, fiat_amount, created_at, expires_at,
                         failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                         invoice_held_at, hash)
-                VALUES (?1, 'sell', 'ev1', ?2, 0, 'lightning',
+                VALUES ($1, 'sell', 'ev1', $2, 0, 'lightning',
                         100000, 'USD', 100, 1700000000, 1700086400,
-                        0, 0, 0, 0, ?3, ?4)"#,
+                        FALSE, 0, 0, FALSE, $3, $4)"#,
             )
             .bind(uuid::Uuid::new_v4())
             .bind(status)
@@ -2754,9 +3327,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     invoice_held_at)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, 0)"#,
+                    FALSE, 0, 0, FALSE, 0)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .execute(&pool)
@@ -2778,9 +3351,9 @@ mod tests {
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid)
-            VALUES (?1, 'buy', 'ev1', 'settled-hold-invoice', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'settled-hold-invoice', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    1, 3, 0, 0)"#,
+                    TRUE, 3, 0, FALSE)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .execute(&pool)
@@ -2819,9 +3392,9 @@ mod tests {
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    1, 3, 0, 0)"#,
+                    TRUE, 3, 0, FALSE)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .execute(&pool)
@@ -2846,9 +3419,9 @@ mod tests {
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid, payout_payment_hash)
-            VALUES (?1, 'buy', 'ev1', 'settled-hold-invoice', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'settled-hold-invoice', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    1, 0, 0, 0, ?2)"#,
+                    TRUE, 0, 0, FALSE, $2)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .bind("a".repeat(64))
@@ -2863,16 +3436,51 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_find_failed_payment_ignores_live_swap() {
+        let pool = setup_orders_db().await.unwrap();
+        let id = uuid::Uuid::new_v4();
+        sqlx::query(
+            r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
+                    amount, fiat_code, fiat_amount, created_at, expires_at,
+                    failed_payment, payment_attempts, dev_fee, dev_fee_paid)
+            VALUES ($1, 'buy', 'ev1', 'settled-hold-invoice', 0, 'lightning',
+                    100000, 'USD', 100, 1700000000, 1700086400,
+                    TRUE, 1, 0, FALSE)"#,
+        )
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO swaps (id, order_id, address, invoice, invoice_amount, \
+             onchain_amount, lockup_address, timeout_block_height, preimage, preimage_hash, \
+             claim_key, status, created_at, updated_at) \
+             VALUES ('swap1', $1, 'bc1q', 'lnbc', 1, 1, 'bc1q', 1, 'p', 'h', 'k', 'claimed', 1, 1)",
+        )
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(super::find_failed_payment(&pool).await.unwrap().is_empty());
+
+        sqlx::query("UPDATE swaps SET status = 'failed'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(super::find_failed_payment(&pool).await.unwrap().len(), 1);
+    }
+
     // -- Tests for the in-flight payout marker --
 
-    async fn insert_settled_order(pool: &SqlitePool, id: uuid::Uuid, status: &str) {
+    async fn insert_settled_order(pool: &DbPool, id: uuid::Uuid, status: &str) {
         sqlx::query(
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid)
-            VALUES (?1, 'buy', 'ev1', ?2, 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', $2, 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0)"#,
+                    FALSE, 0, 0, FALSE)"#,
         )
         .bind(id)
         .bind(status)
@@ -2881,9 +3489,9 @@ mod tests {
         .unwrap();
     }
 
-    async fn payout_hash_of(pool: &SqlitePool, id: uuid::Uuid) -> Option<String> {
+    async fn payout_hash_of(pool: &DbPool, id: uuid::Uuid) -> Option<String> {
         sqlx::query_scalar::<_, Option<String>>(
-            "SELECT payout_payment_hash FROM orders WHERE id = ?",
+            "SELECT payout_payment_hash FROM orders WHERE id = $1",
         )
         .bind(id)
         .fetch_one(pool)
@@ -2968,12 +3576,12 @@ mod tests {
         assert!(owned, "failing an owned claim must report ownership");
 
         assert!(payout_hash_of(&pool, id).await.is_none());
-        let failed: i64 = sqlx::query_scalar("SELECT failed_payment FROM orders WHERE id = ?")
+        let failed: bool = sqlx::query_scalar("SELECT failed_payment FROM orders WHERE id = $1")
             .bind(id)
             .fetch_one(&pool)
             .await
             .unwrap();
-        assert_eq!(failed, 1, "failed_payment must be re-armed for retry");
+        assert!(failed, "failed_payment must be re-armed for retry");
         // Now that the marker is clear, the order is retry-eligible again.
         let matching = super::find_failed_payment(&pool).await.unwrap();
         assert_eq!(matching.len(), 1);
@@ -3025,12 +3633,12 @@ mod tests {
             Some(current.as_str()),
             "fail with a stale hash must not erase the current marker"
         );
-        let failed: i64 = sqlx::query_scalar("SELECT failed_payment FROM orders WHERE id = ?")
+        let failed: bool = sqlx::query_scalar("SELECT failed_payment FROM orders WHERE id = $1")
             .bind(id_b)
             .fetch_one(&pool)
             .await
             .unwrap();
-        assert_eq!(failed, 0, "a stale fail must not re-arm retry");
+        assert!(!failed, "a stale fail must not re-arm retry");
 
         // Correct hash but a STALE timestamp token (the same-invoice retry
         // case): a watcher from a previous attempt must still lose the release
@@ -3056,8 +3664,8 @@ mod tests {
         );
     }
 
-    async fn claimed_at_of(pool: &SqlitePool, id: uuid::Uuid) -> Option<i64> {
-        sqlx::query_scalar::<_, Option<i64>>("SELECT payout_claimed_at FROM orders WHERE id = ?")
+    async fn claimed_at_of(pool: &DbPool, id: uuid::Uuid) -> Option<i64> {
+        sqlx::query_scalar::<_, Option<i64>>("SELECT payout_claimed_at FROM orders WHERE id = $1")
             .bind(id)
             .fetch_one(pool)
             .await
@@ -3148,7 +3756,7 @@ mod tests {
         let id = uuid::Uuid::new_v4();
         let hash = "e".repeat(64);
         insert_inflight_order(&pool, id, &hash, Some(1000)).await;
-        sqlx::query("UPDATE orders SET status = 'success' WHERE id = ?")
+        sqlx::query("UPDATE orders SET status = 'success' WHERE id = $1")
             .bind(id)
             .execute(&pool)
             .await
@@ -3191,14 +3799,14 @@ mod tests {
     }
 
     async fn insert_inflight_order(
-        pool: &SqlitePool,
+        pool: &DbPool,
         id: uuid::Uuid,
         hash: &str,
         claimed_at: Option<i64>,
     ) {
         insert_settled_order(pool, id, "settled-hold-invoice").await;
         sqlx::query(
-            "UPDATE orders SET payout_payment_hash = ?1, payout_claimed_at = ?2 WHERE id = ?3",
+            "UPDATE orders SET payout_payment_hash = $1, payout_claimed_at = $2 WHERE id = $3",
         )
         .bind(hash)
         .bind(claimed_at)
@@ -3265,9 +3873,9 @@ mod tests {
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid, hash)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, ?2)"#,
+                    FALSE, 0, 0, FALSE, $2)"#,
         )
         .bind(id)
         .bind(hash)
@@ -3302,14 +3910,14 @@ mod tests {
         let future = now + 3600;
 
         // Helper to insert an order with a specific status + expires_at.
-        async fn insert(pool: &SqlitePool, id: uuid::Uuid, status: &str, expires_at: i64) {
+        async fn insert(pool: &DbPool, id: uuid::Uuid, status: &str, expires_at: i64) {
             sqlx::query(
                 r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid)
-            VALUES (?1, 'buy', 'ev', ?2, 0, 'lightning',
-                    1000, 'USD', 10, ?3, ?4,
-                    0, 0, 0, 0)"#,
+            VALUES ($1, 'buy', 'ev', $2, 0, 'lightning',
+                    1000, 'USD', 10, $3, $4,
+                    FALSE, 0, 0, FALSE)"#,
             )
             .bind(id)
             .bind(status)
@@ -3406,9 +4014,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     hash, preimage, buyer_invoice, taken_at, invoice_held_at)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 500, 0,
+                    FALSE, 0, 500, FALSE,
                     'somehash', 'somepreimage', 'someinvoice', 1700001000, 1700002000)"#,
         )
         .bind(id)
@@ -3421,7 +4029,7 @@ mod tests {
         assert!(result.unwrap(), "Should return true for existing order");
 
         // Verify status was reset
-        let status: (String,) = sqlx::query_as("SELECT status FROM orders WHERE id = ?1")
+        let status: (String,) = sqlx::query_as("SELECT status FROM orders WHERE id = $1")
             .bind(id)
             .fetch_one(&pool)
             .await
@@ -3430,7 +4038,7 @@ mod tests {
 
         // Verify amounts were updated
         let amounts: (i64, i64, i64) =
-            sqlx::query_as("SELECT amount, fee, dev_fee FROM orders WHERE id = ?1")
+            sqlx::query_as("SELECT amount, fee, dev_fee FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -3441,7 +4049,7 @@ mod tests {
 
         // Verify fields were cleared
         let cleared: (Option<String>, Option<String>, Option<String>) =
-            sqlx::query_as("SELECT hash, preimage, buyer_invoice FROM orders WHERE id = ?1")
+            sqlx::query_as("SELECT hash, preimage, buyer_invoice FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -3452,7 +4060,7 @@ mod tests {
 
         // Verify timestamps were reset
         let times: (i64, i64) =
-            sqlx::query_as("SELECT taken_at, invoice_held_at FROM orders WHERE id = ?1")
+            sqlx::query_as("SELECT taken_at, invoice_held_at FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -3476,67 +4084,31 @@ mod tests {
 
     // -- Tests for update_user_trade_index --
 
-    async fn setup_users_db() -> Result<SqlitePool, Error> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(TEST_DB_URL)
-            .await?;
-
-        sqlx::query(
-            r#"CREATE TABLE IF NOT EXISTS users (
-                pubkey char(64) primary key not null,
-                is_admin integer not null default 0,
-                admin_password char(64),
-                is_solver integer not null default 0,
-                is_banned integer not null default 0,
-                category integer not null default 0,
-                last_trade_index integer not null default 0,
-                total_reviews integer not null default 0,
-                total_rating real not null default 0.0,
-                last_rating integer not null default 0,
-                max_rating integer not null default 0,
-                min_rating integer not null default 0,
-                created_at integer not null
-            )"#,
-        )
-        .execute(&pool)
-        .await?;
+    async fn setup_users_db() -> Result<DbPool, Error> {
+        let pool = migrated_pool().await;
 
         Ok(pool)
     }
 
-    async fn insert_test_user(pool: &SqlitePool, pubkey: &str) {
-        sqlx::query("INSERT INTO users (pubkey, created_at) VALUES (?1, 1700000000)")
+    async fn insert_test_user(pool: &DbPool, pubkey: &str) {
+        sqlx::query("INSERT INTO users (pubkey, created_at) VALUES ($1, 1700000000)")
             .bind(pubkey)
             .execute(pool)
             .await
             .unwrap();
     }
 
-    async fn setup_finalize_permission_db() -> Result<SqlitePool, Error> {
+    async fn setup_finalize_permission_db() -> Result<DbPool, Error> {
         let pool = setup_users_db().await?;
-        sqlx::query(
-            r#"CREATE TABLE IF NOT EXISTS disputes (
-                id char(36) primary key not null,
-                order_id char(36) unique not null,
-                status varchar(10) not null,
-                order_previous_status varchar(10) not null,
-                solver_pubkey char(64),
-                created_at integer not null,
-                taken_at integer default 0
-            )"#,
-        )
-        .execute(&pool)
-        .await?;
         Ok(pool)
     }
 
-    async fn insert_assigned_dispute(pool: &SqlitePool, order_id: uuid::Uuid, solver_pubkey: &str) {
+    async fn insert_assigned_dispute(pool: &DbPool, order_id: uuid::Uuid, solver_pubkey: &str) {
         sqlx::query(
             "INSERT INTO disputes (id, order_id, status, order_previous_status, solver_pubkey, created_at)
-             VALUES (?1, ?2, 'in-progress', 'dispute', ?3, 1700000000)",
+             VALUES ($1, $2, 'in-progress', 'dispute', $3, 1700000000)",
         )
-        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(uuid::Uuid::new_v4())
         .bind(order_id)
         .bind(solver_pubkey)
         .execute(pool)
@@ -3544,9 +4116,9 @@ mod tests {
         .unwrap();
     }
 
-    async fn insert_read_only_solver(pool: &SqlitePool, pubkey: &str) {
+    async fn insert_read_only_solver(pool: &DbPool, pubkey: &str) {
         sqlx::query(
-            "INSERT INTO users (pubkey, is_solver, category, created_at) VALUES (?1, 1, 1, 1700000000)",
+            "INSERT INTO users (pubkey, is_solver, category, created_at) VALUES ($1, 1, 1, 1700000000)",
         )
         .bind(pubkey)
         .execute(pool)
@@ -3610,7 +4182,7 @@ mod tests {
         assert!(result.unwrap(), "Should return true for existing user");
 
         // Verify
-        let idx: (i64,) = sqlx::query_as("SELECT last_trade_index FROM users WHERE pubkey = ?1")
+        let idx: (i64,) = sqlx::query_as("SELECT last_trade_index FROM users WHERE pubkey = $1")
             .bind(VALID_PUBKEY)
             .fetch_one(&pool)
             .await
@@ -3664,9 +4236,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     master_buyer_pubkey)
-            VALUES (?1, 'buy', 'ev1', 'waiting-buyer-invoice', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'waiting-buyer-invoice', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, ?2)"#,
+                    FALSE, 0, 0, FALSE, $2)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .bind(VALID_PUBKEY)
@@ -3689,9 +4261,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     master_buyer_pubkey)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, ?2)"#,
+                    FALSE, 0, 0, FALSE, $2)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .bind(VALID_PUBKEY)
@@ -3716,9 +4288,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     master_seller_pubkey)
-            VALUES (?1, 'sell', 'ev1', 'waiting-payment', 0, 'lightning',
+            VALUES ($1, 'sell', 'ev1', 'waiting-payment', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, ?2)"#,
+                    FALSE, 0, 0, FALSE, $2)"#,
         )
         .bind(uuid::Uuid::new_v4())
         .bind(VALID_PUBKEY)
@@ -3753,7 +4325,7 @@ mod tests {
 
         // Verify
         let row: (i64, i64, i64, i64, f64) = sqlx::query_as(
-            "SELECT last_rating, min_rating, max_rating, total_reviews, total_rating FROM users WHERE pubkey = ?1"
+            "SELECT last_rating, min_rating, max_rating, total_reviews, total_rating FROM users WHERE pubkey = $1"
         )
         .bind(VALID_PUBKEY)
         .fetch_one(&pool)
@@ -3845,9 +4417,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     taken_at)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, 1700005000)"#,
+                    FALSE, 0, 0, FALSE, 1700005000)"#,
         )
         .bind(id)
         .execute(&pool)
@@ -3857,7 +4429,7 @@ mod tests {
         let result = super::reset_order_taken_at_time(&pool, id).await;
         assert!(result.is_ok());
 
-        let row: (i64,) = sqlx::query_as("SELECT taken_at FROM orders WHERE id = ?1")
+        let row: (i64,) = sqlx::query_as("SELECT taken_at FROM orders WHERE id = $1")
             .bind(id)
             .fetch_one(&pool)
             .await
@@ -3877,9 +4449,9 @@ mod tests {
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     invoice_held_at)
-            VALUES (?1, 'buy', 'ev1', 'active', 0, 'lightning',
+            VALUES ($1, 'buy', 'ev1', 'active', 0, 'lightning',
                     100000, 'USD', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0, 0)"#,
+                    FALSE, 0, 0, FALSE, 0)"#,
         )
         .bind(id)
         .execute(&pool)
@@ -3889,7 +4461,7 @@ mod tests {
         let result = super::update_order_invoice_held_at_time(&pool, id, 1700005000).await;
         assert!(result.is_ok());
 
-        let row: (i64,) = sqlx::query_as("SELECT invoice_held_at FROM orders WHERE id = ?1")
+        let row: (i64,) = sqlx::query_as("SELECT invoice_held_at FROM orders WHERE id = $1")
             .bind(id)
             .fetch_one(&pool)
             .await
@@ -3911,17 +4483,17 @@ mod tests {
 
     /// Insert an order whose non-target columns carry `tag`-derived
     /// sentinels, so any accidental overwrite is detectable.
-    async fn insert_sentinel_order(pool: &SqlitePool, id: uuid::Uuid, tag: &str) {
+    async fn insert_sentinel_order(pool: &DbPool, id: uuid::Uuid, tag: &str) {
         sqlx::query(
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                     hash, preimage, buyer_invoice, taken_at, invoice_held_at,
                     fee, routing_fee)
-            VALUES (?1, 'sell', ?2, 'active', 7, 'face to face',
+            VALUES ($1, 'sell', $2, 'active', 7, 'face to face',
                     111111, 'EUR', 42, 1700000001, 1700086401,
-                    0, 0, 33, 0,
-                    ?3, ?4, ?5, 1700001111, 1700002222,
+                    FALSE, 0, 33, FALSE,
+                    $3, $4, $5, 1700001111, 1700002222,
                     21, 9)"#,
         )
         .bind(id)
@@ -3935,12 +4507,12 @@ mod tests {
     }
 
     /// Insert a user whose non-target columns carry non-default sentinels.
-    async fn insert_sentinel_user(pool: &SqlitePool, pubkey: &str) {
+    async fn insert_sentinel_user(pool: &DbPool, pubkey: &str) {
         sqlx::query(
             r#"INSERT INTO users (pubkey, is_admin, is_solver, is_banned, category,
                     last_trade_index, total_reviews, total_rating, last_rating,
                     max_rating, min_rating, created_at)
-            VALUES (?1, 1, 1, 0, 2, 77, 8, 32.0, 4, 5, 2, 1700000123)"#,
+            VALUES ($1, 1, 1, 0, 2, 77, 8, 32.0, 4, 5, 2, 1700000123)"#,
         )
         .bind(pubkey)
         .execute(pool)
@@ -3966,7 +4538,7 @@ mod tests {
         let untouched: (String, String, i64, String, String, i64, i64, i64, i64) = sqlx::query_as(
             "SELECT kind, event_id, premium, payment_method, fiat_code,
                     fiat_amount, created_at, expires_at, routing_fee
-             FROM orders WHERE id = ?1",
+             FROM orders WHERE id = $1",
         )
         .bind(target)
         .fetch_one(&pool)
@@ -3988,7 +4560,7 @@ mod tests {
         // Other row: the columns the helper writes stay untouched (WHERE
         // must pin the update to the target id).
         let other_row: (String, i64, Option<String>, i64) =
-            sqlx::query_as("SELECT status, amount, hash, taken_at FROM orders WHERE id = ?1")
+            sqlx::query_as("SELECT status, amount, hash, taken_at FROM orders WHERE id = $1")
                 .bind(other)
                 .fetch_one(&pool)
                 .await
@@ -4019,7 +4591,7 @@ mod tests {
             .unwrap());
 
         let row: (i64, String, i64, i64, Option<String>) = sqlx::query_as(
-            "SELECT taken_at, status, amount, invoice_held_at, hash FROM orders WHERE id = ?1",
+            "SELECT taken_at, status, amount, invoice_held_at, hash FROM orders WHERE id = $1",
         )
         .bind(target)
         .fetch_one(&pool)
@@ -4031,7 +4603,7 @@ mod tests {
         assert_eq!(row.3, 1700002222, "invoice_held_at must not change");
         assert_eq!(row.4.as_deref(), Some("hash-a"), "hash must not change");
 
-        let other_taken_at: (i64,) = sqlx::query_as("SELECT taken_at FROM orders WHERE id = ?1")
+        let other_taken_at: (i64,) = sqlx::query_as("SELECT taken_at FROM orders WHERE id = $1")
             .bind(other)
             .fetch_one(&pool)
             .await
@@ -4057,7 +4629,7 @@ mod tests {
         );
 
         let row: (i64, String, i64, i64) = sqlx::query_as(
-            "SELECT invoice_held_at, status, amount, taken_at FROM orders WHERE id = ?1",
+            "SELECT invoice_held_at, status, amount, taken_at FROM orders WHERE id = $1",
         )
         .bind(target)
         .fetch_one(&pool)
@@ -4069,7 +4641,7 @@ mod tests {
         assert_eq!(row.3, 1700001111, "taken_at must not change");
 
         let other_held_at: (i64,) =
-            sqlx::query_as("SELECT invoice_held_at FROM orders WHERE id = ?1")
+            sqlx::query_as("SELECT invoice_held_at FROM orders WHERE id = $1")
                 .bind(other)
                 .fetch_one(&pool)
                 .await
@@ -4095,7 +4667,7 @@ mod tests {
         let row: (i64, i64, i64, i64, i64, f64, i64, i64, i64, i64) = sqlx::query_as(
             "SELECT last_trade_index, is_admin, is_solver, category, total_reviews,
                     total_rating, last_rating, max_rating, min_rating, created_at
-             FROM users WHERE pubkey = ?1",
+             FROM users WHERE pubkey = $1",
         )
         .bind(VALID_PUBKEY)
         .fetch_one(&pool)
@@ -4116,7 +4688,7 @@ mod tests {
         assert_eq!(row.9, 1700000123, "created_at must not change");
 
         let other_idx: (i64,) =
-            sqlx::query_as("SELECT last_trade_index FROM users WHERE pubkey = ?1")
+            sqlx::query_as("SELECT last_trade_index FROM users WHERE pubkey = $1")
                 .bind(DAEMON_PUBKEY)
                 .fetch_one(&pool)
                 .await
@@ -4136,10 +4708,10 @@ mod tests {
                 amount, fiat_code, fiat_amount, created_at, expires_at,
                 failed_payment, payment_attempts, dev_fee, dev_fee_paid,
                 buyer_sent_rate, seller_sent_rate)
-            VALUES (?1, 'sell', 'ev', 'success', 0, 'lightning',
+            VALUES ($1, 'sell', 'ev', 'success', 0, 'lightning',
                     1000, 'USD', 10, 1, 2,
-                    0, 0, 0, 0,
-                    0, 1)"#,
+                    FALSE, 0, 0, FALSE,
+                    FALSE, TRUE)"#,
         )
         .bind(id)
         .execute(&pool)
@@ -4159,7 +4731,7 @@ mod tests {
         tx.commit().await.unwrap();
 
         let row: (bool, bool) =
-            sqlx::query_as("SELECT buyer_sent_rate, seller_sent_rate FROM orders WHERE id = ?1")
+            sqlx::query_as("SELECT buyer_sent_rate, seller_sent_rate FROM orders WHERE id = $1")
                 .bind(id)
                 .fetch_one(&pool)
                 .await
@@ -4182,7 +4754,7 @@ mod tests {
 
         let row: (i64, i64, i64, i64, i64) = sqlx::query_as(
             "SELECT is_admin, is_solver, category, last_trade_index, created_at
-             FROM users WHERE pubkey = ?1",
+             FROM users WHERE pubkey = $1",
         )
         .bind(VALID_PUBKEY)
         .fetch_one(&pool)
@@ -4196,7 +4768,7 @@ mod tests {
 
         let other: (i64, i64, i64, i64, f64) = sqlx::query_as(
             "SELECT last_rating, min_rating, max_rating, total_reviews, total_rating
-             FROM users WHERE pubkey = ?1",
+             FROM users WHERE pubkey = $1",
         )
         .bind(DAEMON_PUBKEY)
         .fetch_one(&pool)
@@ -4216,14 +4788,14 @@ mod tests {
 
     use mostro_core::order::Status;
 
-    async fn insert_cashu_test_order(pool: &SqlitePool, id: uuid::Uuid, status: &str) {
+    async fn insert_cashu_test_order(pool: &DbPool, id: uuid::Uuid, status: &str) {
         sqlx::query(
             r#"INSERT INTO orders (id, kind, event_id, status, premium, payment_method,
                     amount, fiat_code, fiat_amount, created_at, expires_at,
                     failed_payment, payment_attempts, dev_fee, dev_fee_paid)
-            VALUES (?1, 'sell', ?2, ?3, 0, 'face to face',
+            VALUES ($1, 'sell', $2, $3, 0, 'face to face',
                     100000, 'EUR', 100, 1700000000, 1700086400,
-                    0, 0, 0, 0)"#,
+                    FALSE, 0, 0, FALSE)"#,
         )
         .bind(id)
         .bind(format!("ev-{id}"))
@@ -4234,12 +4806,12 @@ mod tests {
     }
 
     async fn cashu_columns(
-        pool: &SqlitePool,
+        pool: &DbPool,
         id: uuid::Uuid,
     ) -> (Option<String>, Option<String>, Option<i64>, String) {
         sqlx::query_as(
             "SELECT cashu_mint_url, cashu_escrow_token, cashu_escrow_locked_at, status
-             FROM orders WHERE id = ?1",
+             FROM orders WHERE id = $1",
         )
         .bind(id)
         .fetch_one(pool)
@@ -4343,7 +4915,7 @@ mod tests {
 
         // Move the locked order past Active: the finder must still return
         // it (CF-4: no status predicate — the escrow is what matters).
-        sqlx::query("UPDATE orders SET status = ?1 WHERE id = ?2")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::FiatSent.to_string())
             .bind(locked_id)
             .execute(&pool)
@@ -4515,7 +5087,7 @@ mod tests {
         .await
         .unwrap());
 
-        sqlx::query("UPDATE orders SET status = ?1 WHERE id = ?2")
+        sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
             .bind(Status::Canceled.to_string())
             .bind(id)
             .execute(&pool)
@@ -4538,43 +5110,25 @@ mod tests {
 // are exercised on the production schema.
 #[cfg(test)]
 mod migration_and_query_tests {
+    use super::test_utils::migrated_pool;
+    use super::Crud;
     use super::*;
     use crate::app::context::test_utils::test_settings;
     use crate::config::MOSTRO_CONFIG;
+    use crate::instance::DEFAULT_INSTANCE;
     use mostro_core::prelude::CantDoReason;
-    use sqlx::sqlite::SqlitePoolOptions;
 
     fn init_test_settings() {
         crate::config::init_test_nostr_keys();
         let _ = MOSTRO_CONFIG.set(test_settings());
     }
 
-    /// Migrated in-memory pool pinned to **one** connection.
-    ///
-    /// `sqlite::memory:` gives every *connection* its own private database,
-    /// so a multi-connection pool can hand a second caller a blank schema.
-    /// `restore_session_manager_delivers_background_results` clones this pool
-    /// into a `spawn_blocking` worker: on a different connection that worker
-    /// would find neither the migrations nor the inserted order, log an
-    /// error, deliver nothing, and leave the untimed `wait_for_result()`
-    /// blocked forever. Capping at one connection keeps every acquisition on
-    /// the same database.
-    async fn migrated_pool() -> SqlitePool {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect("sqlite::memory:")
-            .await
-            .unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
-    }
-
     const HEX_KEY_A: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
     const HEX_KEY_B: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
 
     #[allow(clippy::too_many_arguments)]
     async fn insert_order(
-        pool: &SqlitePool,
+        pool: &DbPool,
         id: Uuid,
         kind: &str,
         status: &str,
@@ -4589,8 +5143,8 @@ mod migration_and_query_tests {
                 seller_pubkey, master_seller_pubkey, status, payment_method, amount,
                 fiat_code, fiat_amount, premium, taken_at, created_at, expires_at,
                 trade_index_buyer, trade_index_seller
-            ) VALUES (?1, ?2, 'ev', ?3, ?4, ?5, ?6, ?7, ?8, 'bank', 1000, 'USD', 10, 0,
-                      ?9, 0, 0, 7, 9)"#,
+            ) VALUES ($1, $2, 'ev', $3, $4, $5, $6, $7, $8, 'bank', 1000, 'USD', 10, 0,
+                      $9, 0, 0, 7, 9)"#,
         )
         .bind(id)
         .bind(kind)
@@ -4606,10 +5160,10 @@ mod migration_and_query_tests {
         .unwrap();
     }
 
-    async fn insert_dispute(pool: &SqlitePool, order_id: Uuid, status: &str, solver: Option<&str>) {
+    async fn insert_dispute(pool: &DbPool, order_id: Uuid, status: &str, solver: Option<&str>) {
         sqlx::query(
             r#"INSERT INTO disputes (id, order_id, status, order_previous_status, solver_pubkey, created_at, taken_at)
-               VALUES (?1, ?2, ?3, 'active', ?4, 0, 0)"#,
+               VALUES ($1, $2, $3, 'active', $4, 0, 0)"#,
         )
         .bind(Uuid::new_v4())
         .bind(order_id)
@@ -4621,7 +5175,7 @@ mod migration_and_query_tests {
     }
 
     async fn insert_user(
-        pool: &SqlitePool,
+        pool: &DbPool,
         pubkey: &str,
         is_admin: bool,
         admin_password: Option<&str>,
@@ -4632,12 +5186,12 @@ mod migration_and_query_tests {
             r#"INSERT INTO users (pubkey, is_admin, admin_password, is_solver, is_banned,
                category, last_trade_index, total_reviews, total_rating, last_rating,
                max_rating, min_rating, created_at)
-               VALUES (?1, ?2, ?3, ?4, 0, ?5, 0, 0, 0, 0, 0, 0, 0)"#,
+               VALUES ($1, $2, $3, $4, 0, $5, 0, 0, 0, 0, 0, 0, 0)"#,
         )
         .bind(pubkey)
-        .bind(is_admin)
+        .bind(is_admin as i64)
         .bind(admin_password)
-        .bind(is_solver)
+        .bind(is_solver as i64)
         .bind(category)
         .execute(pool)
         .await
@@ -4646,6 +5200,7 @@ mod migration_and_query_tests {
 
     // ── schema helpers ───────────────────────────────────────────────────
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn table_column_exists_detects_present_and_absent_columns() {
         let pool = migrated_pool().await;
@@ -4660,6 +5215,7 @@ mod migration_and_query_tests {
             .unwrap());
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[test]
     fn strip_sql_comments_removes_only_comment_lines() {
         let sql = "-- header comment\nALTER TABLE t ADD COLUMN c INTEGER;\n  -- indented comment\nSELECT 1;";
@@ -4669,6 +5225,7 @@ mod migration_and_query_tests {
         assert!(stripped.contains("SELECT 1;"));
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[test]
     fn normalize_sql_identifier_strips_quotes_and_commas() {
         assert_eq!(normalize_sql_identifier(" \"orders\","), "orders");
@@ -4677,6 +5234,7 @@ mod migration_and_query_tests {
         assert_eq!(normalize_sql_identifier("plain"), "plain");
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[test]
     fn parse_add_column_statements_accepts_only_pure_add_column_migrations() {
         let ops = parse_add_column_statements(
@@ -4702,6 +5260,7 @@ mod migration_and_query_tests {
         assert!(parse_add_column_statements("-- only comments\n").is_none());
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn applied_migration_versions_lists_all_applied_migrations() {
         let pool = migrated_pool().await;
@@ -4715,6 +5274,7 @@ mod migration_and_query_tests {
     /// Replaying an add-column migration on a database that already has the
     /// columns produces the exact "duplicate column name" error `connect`
     /// reconciles; pin the parser on the real error object.
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn parse_duplicate_column_name_extracts_column_from_real_migrate_error() {
         let pool = migrated_pool().await;
@@ -4731,6 +5291,7 @@ mod migration_and_query_tests {
         assert_eq!(column, "dev_fee");
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn reconcile_add_column_migration_records_and_unblocks_migrator() {
         let pool = migrated_pool().await;
@@ -4758,6 +5319,7 @@ mod migration_and_query_tests {
 
     // ── legacy disputes-table token-column migration ─────────────────────
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn migrate_remove_token_columns_is_noop_without_token_columns() {
         let pool = migrated_pool().await;
@@ -4767,6 +5329,7 @@ mod migration_and_query_tests {
             .unwrap());
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn migrate_remove_token_columns_drops_legacy_columns_and_keeps_rows() {
         init_test_settings();
@@ -4795,6 +5358,7 @@ mod migration_and_query_tests {
         assert_eq!(dispute.order_id, order_id);
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn migrate_remove_token_columns_handles_single_legacy_column() {
         let pool = migrated_pool().await;
@@ -4826,6 +5390,254 @@ mod migration_and_query_tests {
         );
     }
 
+    #[test]
+    fn check_sqlite_url_refuses_other_schemes() {
+        assert!(check_sqlite_url("sqlite://mostro.db").is_ok());
+        assert!(check_sqlite_url("sqlite::memory:").is_ok());
+        assert!(check_sqlite_url("mostro.db").is_ok());
+        assert!(check_sqlite_url("postgres://mostro@localhost/mostro").is_err());
+        assert!(check_sqlite_url("mysql://localhost/mostro").is_err());
+    }
+
+    #[tokio::test]
+    async fn list_orders_and_disputes_apply_filters_and_pages() {
+        let pool = migrated_pool().await;
+        for (i, (status, fiat)) in [
+            ("pending", "USD"),
+            ("dispute", "EUR"),
+            ("dispute", "USD"),
+            ("success", "USD"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let order = Order {
+                id: Uuid::new_v4(),
+                kind: "sell".to_string(),
+                status: status.to_string(),
+                fiat_code: fiat.to_string(),
+                creator_pubkey: HEX_KEY_A.to_string(),
+                buyer_pubkey: (i == 2).then(|| HEX_KEY_B.to_string()),
+                created_at: 100 * (i as i64 + 1),
+                ..Default::default()
+            }
+            .create(&pool)
+            .await
+            .unwrap();
+            if status == "dispute" {
+                let mut dispute = Dispute::new(order.id, "active".to_string());
+                dispute.created_at = order.created_at;
+                dispute.create(&pool).await.unwrap();
+            }
+        }
+
+        let page = |filter: OrderFilter| OrderFilter {
+            limit: 10,
+            ..filter
+        };
+        let all = list_orders(&pool, &page(OrderFilter::default()))
+            .await
+            .unwrap();
+        assert_eq!(
+            all.iter().map(|o| o.created_at).collect::<Vec<_>>(),
+            [400, 300, 200, 100],
+            "newest first"
+        );
+        let usd_disputes = list_orders(
+            &pool,
+            &page(OrderFilter {
+                status: Some("dispute".to_string()),
+                fiat_code: Some("USD".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(usd_disputes.len(), 1);
+        assert_eq!(usd_disputes[0].created_at, 300);
+        let ranged = list_orders(
+            &pool,
+            &page(OrderFilter {
+                created_from: Some(200),
+                created_to: Some(300),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ranged.len(), 2);
+        let by_buyer = list_orders(
+            &pool,
+            &page(OrderFilter {
+                pubkey: Some(HEX_KEY_B.to_string()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_buyer.len(), 1);
+        let second_page = list_orders(
+            &pool,
+            &OrderFilter {
+                limit: 2,
+                offset: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            second_page.iter().map(|o| o.created_at).collect::<Vec<_>>(),
+            [200, 100]
+        );
+
+        let disputes = list_disputes(
+            &pool,
+            &DisputeFilter {
+                limit: 10,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(disputes.len(), 2);
+        let eur = list_disputes(
+            &pool,
+            &DisputeFilter {
+                fiat_code: Some("EUR".to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(eur.len(), 1);
+        assert_eq!(eur[0].created_at, 200);
+        let by_buyer = list_disputes(
+            &pool,
+            &DisputeFilter {
+                pubkey: Some(HEX_KEY_B.to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_buyer.len(), 1);
+        assert_eq!(by_buyer[0].created_at, 300);
+    }
+
+    #[tokio::test]
+    async fn set_user_banned_creates_and_toggles_the_flag() {
+        let pool = migrated_pool().await;
+        // Unknown key: the ban creates the row.
+        set_user_banned(&pool, HEX_KEY_A, true).await.unwrap();
+        assert_eq!(
+            is_user_present(&pool, HEX_KEY_A.to_string())
+                .await
+                .unwrap()
+                .is_banned,
+            1
+        );
+        // Known key: only the flag changes.
+        sqlx::query("UPDATE users SET last_trade_index = 5 WHERE pubkey = $1")
+            .bind(HEX_KEY_A)
+            .execute(&pool)
+            .await
+            .unwrap();
+        set_user_banned(&pool, HEX_KEY_A, false).await.unwrap();
+        let user = is_user_present(&pool, HEX_KEY_A.to_string()).await.unwrap();
+        assert_eq!(user.is_banned, 0);
+        assert_eq!(user.last_trade_index, 5);
+
+        assert!(matches!(
+            set_user_banned(&pool, "npub1nope", true).await,
+            Err(MostroCantDo(CantDoReason::InvalidPubkey))
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolve_cashu_dispute_records_the_signature_once() {
+        let pool = migrated_pool().await;
+        let id = Uuid::new_v4();
+        insert_order(
+            &pool,
+            id,
+            "sell",
+            "dispute",
+            Some(HEX_KEY_A),
+            Some(HEX_KEY_B),
+            HEX_KEY_B,
+            0,
+        )
+        .await;
+        let mut order = Order::by_id(&pool, id).await.unwrap().unwrap();
+        order.status = Status::SettledByAdmin.to_string();
+
+        assert!(
+            resolve_cashu_dispute(&pool, &order, Some(HEX_KEY_A), 1_700_000_000)
+                .await
+                .unwrap()
+        );
+        // A second ruling finds the dispute already resolved.
+        order.status = Status::CanceledByAdmin.to_string();
+        assert!(
+            !resolve_cashu_dispute(&pool, &order, Some(HEX_KEY_B), 1_700_000_100)
+                .await
+                .unwrap()
+        );
+
+        let (status, signed_to, signed_at, moot_at): (
+            String,
+            Option<String>,
+            Option<i64>,
+            Option<i64>,
+        ) = sqlx::query_as(
+            "SELECT status, cashu_pm_signed_to, cashu_pm_signed_at, cashu_ruling_moot_at \
+             FROM orders WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(status, Status::SettledByAdmin.to_string());
+        assert_eq!(signed_to.as_deref(), Some(HEX_KEY_A));
+        assert_eq!(signed_at, Some(1_700_000_000));
+        assert_eq!(moot_at, None);
+    }
+
+    #[tokio::test]
+    async fn resolve_cashu_dispute_without_a_signature_is_moot() {
+        let pool = migrated_pool().await;
+        let id = Uuid::new_v4();
+        insert_order(
+            &pool,
+            id,
+            "sell",
+            "dispute",
+            Some(HEX_KEY_A),
+            Some(HEX_KEY_B),
+            HEX_KEY_B,
+            0,
+        )
+        .await;
+        let mut order = Order::by_id(&pool, id).await.unwrap().unwrap();
+        order.status = Status::CompletedByAdmin.to_string();
+
+        assert!(resolve_cashu_dispute(&pool, &order, None, 1_700_000_000)
+            .await
+            .unwrap());
+        let (signed_at, moot_at): (Option<i64>, Option<i64>) = sqlx::query_as(
+            "SELECT cashu_pm_signed_at, cashu_ruling_moot_at FROM orders WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(signed_at, None);
+        assert_eq!(moot_at, Some(1_700_000_000));
+    }
+
     #[tokio::test]
     async fn ensure_finalize_permission_rejects_unassigned_caller() {
         let pool = migrated_pool().await;
@@ -4930,7 +5742,7 @@ mod migration_and_query_tests {
             0,
         )
         .await;
-        let buy_order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?1")
+        let buy_order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
             .bind(buy_id)
             .fetch_one(&pool)
             .await
@@ -4953,7 +5765,7 @@ mod migration_and_query_tests {
             0,
         )
         .await;
-        let sell_order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?1")
+        let sell_order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
             .bind(sell_id)
             .fetch_one(&pool)
             .await
@@ -5028,7 +5840,9 @@ mod migration_and_query_tests {
         )
         .await;
 
-        let stale = find_order_by_seconds(&pool).await.unwrap();
+        let stale = find_order_by_seconds(&pool, Timestamp::now().as_secs() as i64)
+            .await
+            .unwrap();
         assert_eq!(stale.len(), 1);
         assert_eq!(stale[0].id, stale_id);
     }
@@ -5059,7 +5873,7 @@ mod migration_and_query_tests {
         // The take transition exactly as the handlers drive it: mutate the
         // in-memory row (including `set_timestamp_now()`) and persist via
         // the wide CAS.
-        use mostro_core::db::Crud;
+        use super::Crud;
         let mut order = Order::by_id(&pool, id).await.unwrap().unwrap();
         order.status = Status::WaitingPayment.to_string();
         order.event_id = "ev-take".to_string();
@@ -5072,7 +5886,9 @@ mod migration_and_query_tests {
 
         let after = Order::by_id(&pool, id).await.unwrap().unwrap();
         assert!(after.taken_at > 0, "take must persist taken_at");
-        let stale = find_order_by_seconds(&pool).await.unwrap();
+        let stale = find_order_by_seconds(&pool, Timestamp::now().as_secs() as i64)
+            .await
+            .unwrap();
         assert!(
             stale.is_empty(),
             "a just-taken order must not be timeout-cancel eligible"
@@ -5135,9 +5951,11 @@ mod migration_and_query_tests {
     #[tokio::test]
     async fn find_user_orders_by_master_key_validates_and_finds_both_sides() {
         let pool = migrated_pool().await;
-        assert!(find_user_orders_by_master_key(&pool, "not-hex")
-            .await
-            .is_err());
+        assert!(
+            find_user_orders_by_master_key(&pool, "not-hex", DEFAULT_INSTANCE)
+                .await
+                .is_err()
+        );
 
         // One active order as buyer, one as seller, one terminal (excluded).
         insert_order(
@@ -5174,7 +5992,7 @@ mod migration_and_query_tests {
         )
         .await;
 
-        let orders = find_user_orders_by_master_key(&pool, HEX_KEY_A)
+        let orders = find_user_orders_by_master_key(&pool, HEX_KEY_A, DEFAULT_INSTANCE)
             .await
             .unwrap();
         assert_eq!(orders.len(), 2, "terminal orders are excluded");
@@ -5183,9 +6001,11 @@ mod migration_and_query_tests {
     #[tokio::test]
     async fn find_user_disputes_by_master_key_validates_and_joins_orders() {
         let pool = migrated_pool().await;
-        assert!(find_user_disputes_by_master_key(&pool, "xyz")
-            .await
-            .is_err());
+        assert!(
+            find_user_disputes_by_master_key(&pool, "xyz", DEFAULT_INSTANCE)
+                .await
+                .is_err()
+        );
 
         let order_id = Uuid::new_v4();
         insert_order(
@@ -5201,7 +6021,7 @@ mod migration_and_query_tests {
         .await;
         insert_dispute(&pool, order_id, "initiated", None).await;
 
-        let disputes = find_user_disputes_by_master_key(&pool, HEX_KEY_A)
+        let disputes = find_user_disputes_by_master_key(&pool, HEX_KEY_A, DEFAULT_INSTANCE)
             .await
             .unwrap();
         assert_eq!(disputes.len(), 1);
@@ -5229,7 +6049,11 @@ mod migration_and_query_tests {
         assert!(manager.check_results().await.is_none());
 
         manager
-            .start_restore_session(pool.clone(), HEX_KEY_A.to_string())
+            .start_restore_session(
+                pool.clone(),
+                HEX_KEY_A.to_string(),
+                DEFAULT_INSTANCE.to_string(),
+            )
             .await
             .unwrap();
         let info = manager
@@ -5241,7 +6065,11 @@ mod migration_and_query_tests {
 
         // Invalid master key: worker logs the error, nothing is delivered.
         manager
-            .start_restore_session(pool.clone(), "not-hex".to_string())
+            .start_restore_session(
+                pool.clone(),
+                "not-hex".to_string(),
+                DEFAULT_INSTANCE.to_string(),
+            )
             .await
             .unwrap();
         // Give the blocking task a moment, then confirm no result arrived.
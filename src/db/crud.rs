@@ -0,0 +1,356 @@
+//! Backend-neutral [`Crud`] for the mostro-core rows this daemon persists.
+//!
+//! mostro-core ships the same three operations, but bound to `Pool<Sqlite>`.
+//! These run on [`DbPool`] whichever backend it is, so a module that persists
+//! an [`Order`] or a [`Dispute`] imports this trait — the explicit import
+//! shadows the one `mostro_core::prelude::*` brings in.
+
+use std::future::Future;
+
+use mostro_core::dispute::Dispute;
+use mostro_core::order::Order;
+use sqlx::{query_builder::Separated, QueryBuilder};
+use uuid::Uuid;
+
+use super::{Db, DbPool};
+
+/// Create, read-by-id, and update operations for a single table row. IDs are
+/// assigned in application code before insert.
+pub trait Crud: Sized + for<'r> sqlx::FromRow<'r, super::DbRow> {
+    /// Insert `self` into the backing table and return the persisted row.
+    fn create(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send;
+
+    /// Update the row identified by `self`'s primary key and return the new
+    /// row as stored in the database.
+    fn update(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send;
+
+    /// Load a row by primary key. Returns `None` when no matching row exists.
+    fn by_id(
+        pool: &DbPool,
+        id: Uuid,
+    ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send;
+}
+
+/// Persisted `orders` INSERT column names, in bind order. Keep in sync with
+/// [`push_order_insert_binds`], the migrations, and [`Order`]'s
+/// `FromRow` mapping. Drift is caught by the roundtrip tests below.
+const ORDER_INSERT_COLUMNS: &[&str] = &[
+    "id",
+    "kind",
+    "event_id",
+    "hash",
+    "preimage",
+    "creator_pubkey",
+    "cancel_initiator_pubkey",
+    "buyer_pubkey",
+    "master_buyer_pubkey",
+    "seller_pubkey",
+    "master_seller_pubkey",
+    "status",
+    "price_from_api",
+    "premium",
+    "payment_method",
+    "amount",
+    "min_amount",
+    "max_amount",
+    "buyer_dispute",
+    "seller_dispute",
+    "buyer_cooperativecancel",
+    "seller_cooperativecancel",
+    "fee",
+    "routing_fee",
+    "dev_fee",
+    "dev_fee_paid",
+    "dev_fee_payment_hash",
+    "fiat_code",
+    "fiat_amount",
+    "buyer_invoice",
+    "range_parent_id",
+    "invoice_held_at",
+    "taken_at",
+    "created_at",
+    "buyer_sent_rate",
+    "seller_sent_rate",
+    "failed_payment",
+    "payment_attempts",
+    "expires_at",
+    "trade_index_seller",
+    "trade_index_buyer",
+    "next_trade_pubkey",
+    "next_trade_index",
+    "cashu_mint_url",
+    "cashu_escrow_token",
+    "cashu_escrow_locked_at",
+];
+
+fn push_order_insert_binds(b: &mut Separated<'_, Db, &'static str>, order: &Order) {
+    b.push_bind(order.id)
+        .push_bind(&order.kind)
+        .push_bind(&order.event_id)
+        .push_bind(&order.hash)
+        .push_bind(&order.preimage)
+        .push_bind(&order.creator_pubkey)
+        .push_bind(&order.cancel_initiator_pubkey)
+        .push_bind(&order.buyer_pubkey)
+        .push_bind(&order.master_buyer_pubkey)
+        .push_bind(&order.seller_pubkey)
+        .push_bind(&order.master_seller_pubkey)
+        .push_bind(&order.status)
+        .push_bind(order.price_from_api)
+        .push_bind(order.premium)
+        .push_bind(&order.payment_method)
+        .push_bind(order.amount)
+        .push_bind(order.min_amount)
+        .push_bind(order.max_amount)
+        .push_bind(order.buyer_dispute)
+        .push_bind(order.seller_dispute)
+        .push_bind(order.buyer_cooperativecancel)
+        .push_bind(order.seller_cooperativecancel)
+        .push_bind(order.fee)
+        .push_bind(order.routing_fee)
+        .push_bind(order.dev_fee)
+        .push_bind(order.dev_fee_paid)
+        .push_bind(&order.dev_fee_payment_hash)
+        .push_bind(&order.fiat_code)
+        .push_bind(order.fiat_amount)
+        .push_bind(&order.buyer_invoice)
+        .push_bind(order.range_parent_id)
+        .push_bind(order.invoice_held_at)
+        .push_bind(order.taken_at)
+        .push_bind(order.created_at)
+        .push_bind(order.buyer_sent_rate)
+        .push_bind(order.seller_sent_rate)
+        .push_bind(order.failed_payment)
+        .push_bind(order.payment_attempts)
+        .push_bind(order.expires_at)
+        .push_bind(order.trade_index_seller)
+        .push_bind(order.trade_index_buyer)
+        .push_bind(&order.next_trade_pubkey)
+        .push_bind(order.next_trade_index)
+        .push_bind(&order.cashu_mint_url)
+        .push_bind(&order.cashu_escrow_token)
+        .push_bind(order.cashu_escrow_locked_at);
+}
+
+fn push_order_update_set(set: &mut Separated<'_, Db, &'static str>, order: &Order) {
+    set.push("kind = ").push_bind_unseparated(&order.kind);
+    set.push("event_id = ")
+        .push_bind_unseparated(&order.event_id);
+    set.push("hash = ").push_bind_unseparated(&order.hash);
+    set.push("preimage = ")
+        .push_bind_unseparated(&order.preimage);
+    set.push("creator_pubkey = ")
+        .push_bind_unseparated(&order.creator_pubkey);
+    set.push("cancel_initiator_pubkey = ")
+        .push_bind_unseparated(&order.cancel_initiator_pubkey);
+    set.push("buyer_pubkey = ")
+        .push_bind_unseparated(&order.buyer_pubkey);
+    set.push("master_buyer_pubkey = ")
+        .push_bind_unseparated(&order.master_buyer_pubkey);
+    set.push("seller_pubkey = ")
+        .push_bind_unseparated(&order.seller_pubkey);
+    set.push("master_seller_pubkey = ")
+        .push_bind_unseparated(&order.master_seller_pubkey);
+    set.push("status = ").push_bind_unseparated(&order.status);
+    set.push("price_from_api = ")
+        .push_bind_unseparated(order.price_from_api);
+    set.push("premium = ").push_bind_unseparated(order.premium);
+    set.push("payment_method = ")
+        .push_bind_unseparated(&order.payment_method);
+    set.push("amount = ").push_bind_unseparated(order.amount);
+    set.push("min_amount = ")
+        .push_bind_unseparated(order.min_amount);
+    set.push("max_amount = ")
+        .push_bind_unseparated(order.max_amount);
+    set.push("buyer_dispute = ")
+        .push_bind_unseparated(order.buyer_dispute);
+    set.push("seller_dispute = ")
+        .push_bind_unseparated(order.seller_dispute);
+    set.push("buyer_cooperativecancel = ")
+        .push_bind_unseparated(order.buyer_cooperativecancel);
+    set.push("seller_cooperativecancel = ")
+        .push_bind_unseparated(order.seller_cooperativecancel);
+    set.push("fee = ").push_bind_unseparated(order.fee);
+    set.push("routing_fee = ")
+        .push_bind_unseparated(order.routing_fee);
+    set.push("dev_fee = ").push_bind_unseparated(order.dev_fee);
+    set.push("dev_fee_paid = ")
+        .push_bind_unseparated(order.dev_fee_paid);
+    set.push("dev_fee_payment_hash = ")
+        .push_bind_unseparated(&order.dev_fee_payment_hash);
+    set.push("fiat_code = ")
+        .push_bind_unseparated(&order.fiat_code);
+    set.push("fiat_amount = ")
+        .push_bind_unseparated(order.fiat_amount);
+    set.push("buyer_invoice = ")
+        .push_bind_unseparated(&order.buyer_invoice);
+    set.push("range_parent_id = ")
+        .push_bind_unseparated(order.range_parent_id);
+    set.push("invoice_held_at = ")
+        .push_bind_unseparated(order.invoice_held_at);
+    set.push("taken_at = ")
+        .push_bind_unseparated(order.taken_at);
+    set.push("created_at = ")
+        .push_bind_unseparated(order.created_at);
+    set.push("buyer_sent_rate = ")
+        .push_bind_unseparated(order.buyer_sent_rate);
+    set.push("seller_sent_rate = ")
+        .push_bind_unseparated(order.seller_sent_rate);
+    set.push("failed_payment = ")
+        .push_bind_unseparated(order.failed_payment);
+    set.push("payment_attempts = ")
+        .push_bind_unseparated(order.payment_attempts);
+    set.push("expires_at = ")
+        .push_bind_unseparated(order.expires_at);
+    set.push("trade_index_seller = ")
+        .push_bind_unseparated(order.trade_index_seller);
+    set.push("trade_index_buyer = ")
+        .push_bind_unseparated(order.trade_index_buyer);
+    set.push("next_trade_pubkey = ")
+        .push_bind_unseparated(&order.next_trade_pubkey);
+    set.push("next_trade_index = ")
+        .push_bind_unseparated(order.next_trade_index);
+    set.push("cashu_mint_url = ")
+        .push_bind_unseparated(&order.cashu_mint_url);
+    set.push("cashu_escrow_token = ")
+        .push_bind_unseparated(&order.cashu_escrow_token);
+    set.push("cashu_escrow_locked_at = ")
+        .push_bind_unseparated(order.cashu_escrow_locked_at);
+}
+
+/// Insert `order` owned by `instance` (`orders.instance`, see
+/// [`crate::instance`]). The owner is written by the INSERT itself, so the
+/// row is never visible under another instance.
+pub async fn create_order_as(
+    pool: &DbPool,
+    order: Order,
+    instance: &str,
+) -> Result<Order, sqlx::Error> {
+    let mut qb = QueryBuilder::new("INSERT INTO orders (");
+    {
+        let mut cols = qb.separated(", ");
+        for &column in ORDER_INSERT_COLUMNS {
+            cols.push(column);
+        }
+        cols.push("instance");
+    }
+    qb.push(") ");
+    qb.push_values(std::iter::once(&order), |mut binds, order| {
+        push_order_insert_binds(&mut binds, order);
+        binds.push_bind(instance);
+    });
+    qb.push(" RETURNING *");
+    qb.build_query_as::<Order>().fetch_one(pool).await
+}
+
+impl Crud for Order {
+    fn create(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
+        let pool = pool.clone();
+        async move { create_order_as(&pool, self, crate::instance::DEFAULT_INSTANCE).await }
+    }
+
+    fn update(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
+        let pool = pool.clone();
+        async move {
+            let mut qb = QueryBuilder::new("UPDATE orders SET ");
+            {
+                let mut set = qb.separated(", ");
+                push_order_update_set(&mut set, &self);
+            }
+            qb.push(" WHERE id = ");
+            qb.push_bind(self.id);
+            qb.push(" RETURNING *");
+            qb.build_query_as::<Order>().fetch_one(&pool).await
+        }
+    }
+
+    fn by_id(
+        pool: &DbPool,
+        id: Uuid,
+    ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 LIMIT 1")
+                .bind(id)
+                .fetch_optional(&pool)
+                .await
+        }
+    }
+}
+
+fn push_dispute_insert_binds(b: &mut Separated<'_, Db, &'static str>, dispute: &Dispute) {
+    b.push_bind(dispute.id)
+        .push_bind(dispute.order_id)
+        .push_bind(&dispute.status)
+        .push_bind(&dispute.order_previous_status)
+        .push_bind(&dispute.solver_pubkey)
+        .push_bind(dispute.created_at)
+        .push_bind(dispute.taken_at);
+}
+
+fn push_dispute_update_set(set: &mut Separated<'_, Db, &'static str>, dispute: &Dispute) {
+    set.push("order_id = ")
+        .push_bind_unseparated(dispute.order_id);
+    set.push("status = ").push_bind_unseparated(&dispute.status);
+    set.push("order_previous_status = ")
+        .push_bind_unseparated(&dispute.order_previous_status);
+    set.push("solver_pubkey = ")
+        .push_bind_unseparated(&dispute.solver_pubkey);
+    set.push("created_at = ")
+        .push_bind_unseparated(dispute.created_at);
+    set.push("taken_at = ")
+        .push_bind_unseparated(dispute.taken_at);
+}
+
+impl Crud for Dispute {
+    fn create(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
+        let pool = pool.clone();
+        async move {
+            let mut qb = QueryBuilder::new("INSERT INTO disputes (");
+            {
+                let mut cols = qb.separated(", ");
+                cols.push("id");
+                cols.push("order_id");
+                cols.push("status");
+                cols.push("order_previous_status");
+                cols.push("solver_pubkey");
+                cols.push("created_at");
+                cols.push("taken_at");
+            }
+            qb.push(") ");
+            qb.push_values(std::iter::once(&self), |mut binds, dispute| {
+                push_dispute_insert_binds(&mut binds, dispute);
+            });
+            qb.push(" RETURNING *");
+            qb.build_query_as::<Dispute>().fetch_one(&pool).await
+        }
+    }
+
+    fn update(self, pool: &DbPool) -> impl Future<Output = Result<Self, sqlx::Error>> + Send {
+        let pool = pool.clone();
+        async move {
+            let mut qb = QueryBuilder::new("UPDATE disputes SET ");
+            {
+                let mut set = qb.separated(", ");
+                push_dispute_update_set(&mut set, &self);
+            }
+            qb.push(" WHERE id = ");
+            qb.push_bind(self.id);
+            qb.push(" RETURNING *");
+            qb.build_query_as::<Dispute>().fetch_one(&pool).await
+        }
+    }
+
+    fn by_id(
+        pool: &DbPool,
+        id: Uuid,
+    ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_as::<_, Dispute>("SELECT * FROM disputes WHERE id = $1 LIMIT 1")
+                .bind(id)
+                .fetch_optional(&pool)
+                .await
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Dispute service levels.
+//!
+//! Two optional timers watch open disputes (docs/ADMIN_RPC_AND_DISPUTES.md):
+//!
+//! * `dispute_take_alert_minutes`: a dispute still `initiated` after this
+//!   long is announced to every solver and to the admin, once.
+//! * `dispute_resolve_escalate_hours`: a dispute still `in-progress` this
+//!   long after it was taken is escalated to its solver and the admin, once.
+//!
+//! The admin is `dispute_admin_pubkey`; without it alerts only reach
+//! solvers. Alerts go through the outbox as the instance owning the order,
+//! with a `dispute` action and a JSON `text-message` payload. The `dispute_sla`
+//! table remembers what was sent and when each dispute was resolved, which
+//! gives the per-solver response and resolution times of
+//! [`solver_stats`].
+
+use crate::db::DbPool;
+use crate::util::enqueue_dispute_msg;
+use mostro_core::prelude::*;
+use nostr_sdk::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use tracing::info;
+use uuid::Uuid;
+
+/// Why an SLA message was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlaBreach {
+    /// Nobody took the dispute in time.
+    Untaken,
+    /// The solver did not resolve the dispute in time.
+    Unresolved,
+}
+
+/// JSON payload of an SLA message.
+#[derive(Debug, Serialize)]
+struct SlaAlert {
+    sla: SlaBreach,
+    dispute_id: Uuid,
+    order_id: Uuid,
+    /// Seconds since the dispute was opened (untaken) or taken (unresolved).
+    waiting_secs: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solver_pubkey: Option<String>,
+}
+
+/// Response and resolution record of one solver, over the disputes it
+/// currently holds. A dispute handed over to another solver counts for the
+/// new one only, timed from the handover.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SolverStats {
+    pub solver_pubkey: String,
+    pub disputes_taken: i64,
+    pub disputes_resolved: i64,
+    /// Mean seconds from opening to taking.
+    pub avg_response_secs: Option<f64>,
+    /// Mean seconds from taking to resolution, over resolved disputes.
+    pub avg_resolution_secs: Option<f64>,
+    pub disputes_escalated: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct OverdueDispute {
+    id: Uuid,
+    order_id: Uuid,
+    since: i64,
+    solver_pubkey: Option<String>,
+}
+
+fn db_err(e: sqlx::Error) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+/// Records that `dispute_id` reached a final status at `now`.
+pub async fn record_resolution(
+    pool: &DbPool,
+    dispute_id: Uuid,
+    now: i64,
+) -> Result<(), MostroError> {
+    sqlx::query(
+        "INSERT INTO dispute_sla (dispute_id, resolved_at) VALUES ($1, $2) \
+         ON CONFLICT(dispute_id) DO UPDATE SET resolved_at = excluded.resolved_at",
+    )
+    .bind(dispute_id)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
+
+/// Disputes `initiated` since before `cutoff` and not alerted yet.
+async fn untaken_since(pool: &DbPool, cutoff: i64) -> Result<Vec<OverdueDispute>, MostroError> {
+    sqlx::query_as(
+        "SELECT d.id, d.order_id, d.created_at AS since, NULL AS solver_pubkey \
+         FROM disputes d LEFT JOIN dispute_sla s ON s.dispute_id = d.id \
+         WHERE d.status = 'initiated' AND d.created_at <= $1 AND s.take_alerted_at IS NULL",
+    )
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Disputes taken before `cutoff`, still `in-progress` and not escalated yet.
+async fn unresolved_since(pool: &DbPool, cutoff: i64) -> Result<Vec<OverdueDispute>, MostroError> {
+    sqlx::query_as(
+        "SELECT d.id, d.order_id, d.taken_at AS since, d.solver_pubkey \
+         FROM disputes d LEFT JOIN dispute_sla s ON s.dispute_id = d.id \
+         WHERE d.status = 'in-progress' AND d.taken_at > 0 AND d.taken_at <= $1 \
+         AND s.escalated_at IS NULL",
+    )
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+async fn mark(
+    pool: &DbPool,
+    dispute_id: Uuid,
+    breach: SlaBreach,
+    now: i64,
+) -> Result<(), MostroError> {
+    let query = match breach {
+        SlaBreach::Untaken => {
+            "INSERT INTO dispute_sla (dispute_id, take_alerted_at) VALUES ($1, $2) \
+             ON CONFLICT(dispute_id) DO UPDATE SET take_alerted_at = excluded.take_alerted_at"
+        }
+        SlaBreach::Unresolved => {
+            "INSERT INTO dispute_sla (dispute_id, escalated_at) VALUES ($1, $2) \
+             ON CONFLICT(dispute_id) DO UPDATE SET escalated_at = excluded.escalated_at"
+        }
+    };
+    sqlx::query(query)
+        .bind(dispute_id)
+        .bind(now)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    Ok(())
+}
+
+/// Solvers that are not banned.
+pub(crate) async fn solver_pubkeys(pool: &DbPool) -> Result<Vec<PublicKey>, MostroError> {
+    let pubkeys: Vec<String> =
+        sqlx::query_scalar("SELECT pubkey FROM users WHERE is_solver = 1 AND is_banned = 0")
+            .fetch_all(pool)
+            .await
+            .map_err(db_err)?;
+    Ok(pubkeys
+        .iter()
+        .filter_map(|pk| PublicKey::from_hex(pk).ok())
+        .collect())
+}
+
+/// Queues `breach` about `dispute` for `recipients`, sent as the instance
+/// owning the order.
+async fn alert(
+    pool: &DbPool,
+    dispute: OverdueDispute,
+    breach: SlaBreach,
+    recipients: &BTreeSet<PublicKey>,
+    now: i64,
+) -> Result<(), MostroError> {
+    let owner = crate::instance::of_order(pool, dispute.order_id).await;
+    let payload = serde_json::to_string(&SlaAlert {
+        sla: breach,
+        dispute_id: dispute.id,
+        order_id: dispute.order_id,
+        waiting_secs: now - dispute.since,
+        solver_pubkey: dispute.solver_pubkey,
+    })
+    .map_err(|_| MostroInternalErr(ServiceError::MessageSerializationError))?;
+
+    mark(pool, dispute.id, breach, now).await?;
+    info!(dispute_id = %dispute.id, "dispute SLA breached: {breach:?}");
+    crate::instance::within(owner, async {
+        for recipient in recipients {
+            enqueue_dispute_msg(
+                Some(dispute.id),
+                Action::Dispute,
+                Some(Payload::TextMessage(payload.clone())),
+                *recipient,
+            )
+            .await;
+        }
+    })
+    .await;
+    Ok(())
+}
+
+/// One SLA pass at `now`: alerts on disputes untaken for
+/// `take_alert_minutes` and escalates those unresolved for
+/// `escalate_hours`; `0` disables either. `admin`, when set, receives both.
+/// Returns how many disputes were alerted and escalated.
+pub async fn check_once(
+    pool: &DbPool,
+    admin: Option<PublicKey>,
+    take_alert_minutes: u32,
+    escalate_hours: u32,
+    now: i64,
+) -> Result<(usize, usize), MostroError> {
+    let mut alerted = 0;
+    if take_alert_minutes > 0 {
+        let untaken = untaken_since(pool, now - i64::from(take_alert_minutes) * 60).await?;
+        if !untaken.is_empty() {
+            let mut recipients: BTreeSet<PublicKey> =
+                solver_pubkeys(pool).await?.into_iter().collect();
+            recipients.extend(admin);
+            for dispute in untaken {
+                alert(pool, dispute, SlaBreach::Untaken, &recipients, now).await?;
+                alerted += 1;
+            }
+        }
+    }
+    let mut escalated = 0;
+    if escalate_hours > 0 {
+        for dispute in unresolved_since(pool, now - i64::from(escalate_hours) * 3600).await? {
+            let mut recipients: BTreeSet<PublicKey> = dispute
+                .solver_pubkey
+                .as_deref()
+                .and_then(|pk| PublicKey::from_hex(pk).ok())
+                .into_iter()
+                .collect();
+            recipients.extend(admin);
+            alert(pool, dispute, SlaBreach::Unresolved, &recipients, now).await?;
+            escalated += 1;
+        }
+    }
+    Ok((alerted, escalated))
+}
+
+/// Per-solver response and resolution times, busiest solver first.
+pub async fn solver_stats(pool: &DbPool) -> Result<Vec<SolverStats>, MostroError> {
+    sqlx::query_as(
+        "SELECT d.solver_pubkey, COUNT(*) AS disputes_taken, \
+         COUNT(s.resolved_at) AS disputes_resolved, \
+         CAST(AVG(d.taken_at - d.created_at) AS DOUBLE PRECISION) AS avg_response_secs, \
+         CAST(AVG(s.resolved_at - d.taken_at) AS DOUBLE PRECISION) AS avg_resolution_secs, \
+         COUNT(s.escalated_at) AS disputes_escalated \
+         FROM disputes d LEFT JOIN dispute_sla s ON s.dispute_id = d.id \
+         WHERE d.solver_pubkey IS NOT NULL AND d.taken_at > 0 \
+         GROUP BY d.solver_pubkey ORDER BY disputes_taken DESC, d.solver_pubkey",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Crud;
+
+    async fn setup_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
+    }
+
+    async fn dispute(pool: &DbPool, created_at: i64, taken: Option<(&str, i64)>) -> Dispute {
+        let mut dispute = Dispute::new(Uuid::new_v4(), "active".to_string());
+        dispute.created_at = created_at;
+        if let Some((solver, taken_at)) = taken {
+            dispute.status = DisputeStatus::InProgress.to_string();
+            dispute.solver_pubkey = Some(solver.to_string());
+            dispute.taken_at = taken_at;
+        }
+        dispute.create(pool).await.unwrap()
+    }
+
+    async fn sla_row(pool: &DbPool, id: Uuid) -> (Option<i64>, Option<i64>) {
+        sqlx::query_as(
+            "SELECT take_alerted_at, escalated_at FROM dispute_sla WHERE dispute_id = $1",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+        .unwrap_or((None, None))
+    }
+
+    /// SLA messages queued for `dispute_id`, by destination.
+    async fn alerts_for(dispute_id: Uuid) -> Vec<PublicKey> {
+        crate::outbox::test_utils::queued("dispute")
+            .await
+            .into_iter()
+            .filter(|(message, _)| message.get_inner_message_kind().id == Some(dispute_id))
+            .map(|(_, destination)| destination)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn overdue_disputes_are_alerted_once() {
+        let pool = setup_pool().await;
+        let admin = Keys::generate().public_key();
+        let solver = Keys::generate().public_key();
+        sqlx::query("INSERT INTO users (pubkey, is_solver, created_at) VALUES ($1, 1, 0)")
+            .bind(solver.to_hex())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let now = 100_000;
+        let untaken = dispute(&pool, now - 31 * 60, None).await;
+        let fresh = dispute(&pool, now - 60, None).await;
+        let stale = dispute(
+            &pool,
+            now - 90_000,
+            Some((&solver.to_hex(), now - 25 * 3600)),
+        )
+        .await;
+        let recent = dispute(&pool, now - 90_000, Some((&solver.to_hex(), now - 3600))).await;
+
+        // Disabled timers alert nothing
+        assert_eq!(
+            check_once(&pool, Some(admin), 0, 0, now).await.unwrap(),
+            (0, 0)
+        );
+
+        assert_eq!(
+            check_once(&pool, Some(admin), 30, 24, now).await.unwrap(),
+            (1, 1)
+        );
+        assert_eq!(sla_row(&pool, untaken.id).await, (Some(now), None));
+        assert_eq!(sla_row(&pool, stale.id).await, (None, Some(now)));
+        assert_eq!(sla_row(&pool, fresh.id).await, (None, None));
+        assert_eq!(sla_row(&pool, recent.id).await, (None, None));
+
+        // The solvers and the admin hear of the untaken dispute, the
+        // assigned solver and the admin of the stale one
+        let expected = BTreeSet::from([solver, admin]);
+        for id in [untaken.id, stale.id] {
+            let sent = alerts_for(id).await;
+            assert_eq!(sent.len(), 2);
+            assert_eq!(sent.into_iter().collect::<BTreeSet<_>>(), expected);
+        }
+
+        // Already alerted
+        assert_eq!(
+            check_once(&pool, Some(admin), 30, 24, now + 60)
+                .await
+                .unwrap(),
+            (0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn solver_stats_time_response_and_resolution() {
+        let pool = setup_pool().await;
+        let fast = Keys::generate().public_key().to_hex();
+        let slow = Keys::generate().public_key().to_hex();
+        let first = dispute(&pool, 1_000, Some((&fast, 1_100))).await;
+        dispute(&pool, 2_000, Some((&fast, 2_300))).await;
+        let third = dispute(&pool, 1_000, Some((&slow, 8_200))).await;
+        dispute(&pool, 5_000, None).await;
+        record_resolution(&pool, first.id, 1_700).await.unwrap();
+        record_resolution(&pool, third.id, 9_000).await.unwrap();
+        mark(&pool, third.id, SlaBreach::Unresolved, 8_900)
+            .await
+            .unwrap();
+
+        let stats = solver_stats(&pool).await.unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].solver_pubkey, fast);
+        assert_eq!(stats[0].disputes_taken, 2);
+        assert_eq!(stats[0].disputes_resolved, 1);
+        assert_eq!(stats[0].avg_response_secs, Some(200.0));
+        assert_eq!(stats[0].avg_resolution_secs, Some(600.0));
+        assert_eq!(stats[0].disputes_escalated, 0);
+        assert_eq!(stats[1].solver_pubkey, slow);
+        assert_eq!(stats[1].avg_resolution_secs, Some(800.0));
+        assert_eq!(stats[1].disputes_escalated, 1);
+    }
+}
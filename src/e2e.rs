@@ -0,0 +1,1022 @@
+//! End-to-end trade scenarios.
+//!
+//! Each scenario runs the daemon's event loop ([`crate::app::run`]) against
+//! an embedded relay and the simulated Lightning node in
+//! [`crate::escrow::memory`]. Traders talk to the daemon with real protocol
+//! messages over the relay, from the maker's new order and the taker's take
+//! on; the daemon subscribes to the hold invoices it issues on the node;
+//! scheduler jobs are driven one pass at a time. On-chain payouts go
+//! through the simulated swap service in [`crate::swap::memory`], which
+//! holds the node's payments of its invoices and plays the chain Mostro
+//! checks lockups on and broadcasts its claims to.
+//!
+//! Time is simulated too: [`Harness::elapse`] moves the node clock, and the
+//! scheduler passes are run at [`Harness::now`] instead of the wall clock.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::Crud;
+use crate::db::DbPool;
+use fedimint_tonic_lnd::lnrpc::invoice::InvoiceState;
+use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
+use mostro_core::dispute::Status as DisputeStatus;
+use mostro_core::nip59::WrapOptions;
+use mostro_core::order::Kind;
+use mostro_core::prelude::*;
+use mostro_core::transport::wrap_message_nip44;
+use nostr_sdk::local_relay::MockRelay;
+use nostr_sdk::prelude::Kind as NostrKind;
+use nostr_sdk::prelude::*;
+use rand::RngCore;
+use tokio::task::{JoinHandle, LocalSet};
+use uuid::Uuid;
+
+use crate::app::bond::db::{find_bond_by_hash, find_bond_by_id};
+use crate::app::bond::{run_bond_payout_cycle, BondRole, BondState};
+use crate::app::context::test_utils::{test_settings, TestContextBuilder};
+use crate::app::context::AppContext;
+use crate::config::types::{AntiAbuseBondSettings, BondApplyTo, SwapSettings};
+use crate::config::{init_test_nostr_keys, MOSTRO_CONFIG};
+use crate::escrow::memory::{bolt11, with_node, MemoryEscrow, HOLD_EXPIRY_DELTA, HTLC_CLTV_DELTA};
+use crate::instance::{self, Instance, DEFAULT_INSTANCE};
+use crate::lightning::invoice::decode_invoice;
+use crate::scheduler::{
+    cancel_timed_out_orders_pass, enforce_escrow_deadline_pass, retry_failed_payments_pass,
+};
+use crate::swap::memory::{test_address, with_service, MemorySwapService};
+use crate::swap::payout::swap_payout_pass;
+use crate::util::bytes_to_string;
+
+/// Nominal block interval the daemon's wall-clock deadlines assume.
+const SECS_PER_BLOCK: i64 = 600;
+const AMOUNT: i64 = 100_000;
+
+/// A trade opened by [`Harness::open_trade`].
+struct Trade {
+    id: Uuid,
+    hash: String,
+    /// Mostro fee charged to each side.
+    fee: i64,
+    seller: Keys,
+    buyer: Keys,
+    /// The buyer's payout invoice, or the address or offer it was replaced
+    /// with.
+    payout: String,
+}
+
+/// Daemon, relay and Lightning node for one scenario.
+struct Harness {
+    ctx: AppContext,
+    node: MemoryEscrow,
+    /// Swap service for on-chain payouts, holding payments on `node`.
+    swap: MemorySwapService,
+    /// Client the traders publish with and read the relay through.
+    client: Client,
+    /// The `default` instance, carrying the scenario's settings, that the
+    /// daemon and the scheduler passes run as.
+    instance: &'static Instance,
+    /// Daemon event loop; aborted on drop.
+    tasks: Vec<JoinHandle<()>>,
+    _relay: MockRelay,
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn relay_client(url: &RelayUrl) -> Client {
+    let client = Client::default();
+    client.add_relay(url.clone()).await.expect("add_relay");
+    client.connect().await;
+    client
+}
+
+/// Random bolt11 for `amount` sats, as a wallet would issue it.
+fn wallet_invoice(amount: i64) -> String {
+    let mut hash = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut hash);
+    bolt11(&hash, amount, "wallet")
+}
+
+/// Run a scenario. The daemon's event loop is not `Send`, so the harness
+/// spawns it on a local task set.
+async fn scenario(f: impl Future<Output = ()>) {
+    LocalSet::new().run_until(f).await
+}
+
+/// Poll `check` until it holds; panics after 10 seconds with `what`.
+async fn eventually<F, Fut>(what: &str, mut check: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while !check().await {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "timed out waiting for {what}"
+        );
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+/// Actions queued for `pubkey` on the process-global order queue.
+async fn queued_actions(pubkey: PublicKey) -> Vec<Action> {
+    crate::outbox::test_utils::queued("order")
+        .await
+        .iter()
+        .filter(|(_, to)| *to == pubkey)
+        .map(|(msg, _)| msg.get_inner_message_kind().action.clone())
+        .collect()
+}
+
+/// The bond invoice queued for `pubkey` to pay, if any.
+async fn queued_bond_invoice(pubkey: PublicKey) -> Option<String> {
+    crate::outbox::test_utils::queued("order")
+        .await
+        .into_iter()
+        .filter(|(_, to)| *to == pubkey)
+        .find_map(|(msg, _)| match msg.get_inner_message_kind() {
+            MessageKind {
+                action: Action::PayBondInvoice,
+                payload: Some(Payload::PaymentRequest(_, bolt11, _)),
+                ..
+            } => Some(bolt11.clone()),
+            _ => None,
+        })
+}
+
+impl Harness {
+    async fn start() -> Self {
+        Self::start_with(None).await
+    }
+
+    /// Like [`Self::start`], with `bond` as the daemon's
+    /// `[anti_abuse_bond]` block.
+    async fn with_bond(bond: AntiAbuseBondSettings) -> Self {
+        Self::start_with(Some(bond)).await
+    }
+
+    async fn start_with(bond: Option<AntiAbuseBondSettings>) -> Self {
+        let _ = MOSTRO_CONFIG.set(test_settings());
+        // The new-order path publishes through the process-wide client;
+        // installing it is idempotent across harnesses.
+        let _ = crate::NOSTR_CLIENT.set(Client::default());
+        init_test_nostr_keys();
+
+        let pool = crate::db::test_utils::migrated_pool().await;
+        let relay = MockRelay::run().await.expect("mock relay");
+        let url = relay.url().await;
+
+        let mut settings = test_settings();
+        settings.lightning.hold_invoice_cltv_delta = HTLC_CLTV_DELTA;
+        settings.lightning.payment_attempts = 3;
+        settings.lightning.payment_retries_interval = 60;
+        settings.swap = Some(SwapSettings {
+            enabled: true,
+            api_url: "http://127.0.0.1:1".to_string(),
+            esplora_url: "http://127.0.0.1:1".to_string(),
+            network: "regtest".to_string(),
+            ..SwapSettings::default()
+        });
+        settings.anti_abuse_bond = bond;
+        #[allow(deprecated)]
+        let inbox_kind = settings.mostro.transport.event_kind();
+
+        let daemon_client = relay_client(&url).await;
+        let ctx = TestContextBuilder::new()
+            .with_pool(Arc::new(pool))
+            .with_settings(settings)
+            .with_nostr_client(daemon_client.clone())
+            .build();
+        let inbox = Filter::new()
+            .pubkey(ctx.keys().public_key())
+            .kind(inbox_kind)
+            .limit(0);
+        daemon_client.subscribe(inbox).await.expect("subscribe");
+        // Settings the handlers read through `Settings::get_*` rather than
+        // the context come from the current instance.
+        let instance: &'static Instance = Box::leak(Box::new(Instance {
+            id: DEFAULT_INSTANCE.to_string(),
+            keys: ctx.keys().clone(),
+            settings: Arc::new(ctx.settings().clone()),
+            retired: false,
+        }));
+
+        let node = MemoryEscrow::new();
+        let swap = MemorySwapService::new().on_node(node.clone());
+        let daemon = {
+            let ctx = ctx.clone();
+            let mut ln = node.clone();
+            // Boxed: the loop's future is too large to move through the
+            // wrappers below on a test thread's stack in debug builds.
+            let run = with_node(node.clone(), async move {
+                let _ = Box::pin(crate::app::run(ctx, &mut ln)).await;
+            });
+            let run = instance::within(Some(instance), with_service(swap.clone(), run));
+            tokio::task::spawn_local(run)
+        };
+        let client = relay_client(&url).await;
+        // Let both clients finish the handshake and the loop take its
+        // notification stream before anyone publishes.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        Self {
+            ctx,
+            node,
+            swap,
+            client,
+            instance,
+            tasks: vec![daemon],
+            _relay: relay,
+        }
+    }
+
+    fn pool(&self) -> &DbPool {
+        self.ctx.pool()
+    }
+
+    /// Run `f` the way the daemon runs its jobs: on the simulated node, as
+    /// the harness's instance.
+    async fn as_daemon<F: Future>(&self, f: F) -> F::Output {
+        let f = instance::within(Some(self.instance), Box::pin(f));
+        with_node(self.node.clone(), f).await
+    }
+
+    /// The maker publishes a `kind` order for [`AMOUNT`] sats; returns its
+    /// id once stored.
+    async fn publish_order(&self, maker: &Keys, kind: Kind, buyer_invoice: Option<String>) -> Uuid {
+        let order = SmallOrder::new(
+            None,
+            Some(kind),
+            Some(Status::Pending),
+            AMOUNT,
+            "USD".to_string(),
+            None,
+            None,
+            100,
+            "SEPA".to_string(),
+            0,
+            None,
+            None,
+            buyer_invoice,
+            None,
+            None,
+        );
+        self.send(
+            maker,
+            Message::new_order(
+                None,
+                Some(1),
+                None,
+                Action::NewOrder,
+                Some(Payload::Order(order)),
+            ),
+        )
+        .await;
+        eventually("the new order to be published", || async {
+            self.order_by(maker).await.is_some()
+        })
+        .await;
+        self.order_by(maker).await.unwrap()
+    }
+
+    /// Open a `kind` trade the way clients do: the maker publishes a new
+    /// order and the taker takes it, the buyer handing in a payout invoice.
+    /// The trade ends up waiting for the seller's hold invoice payment, with
+    /// the daemon subscribed to that invoice.
+    async fn open_trade(&self, kind: Kind) -> Trade {
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+        let fee = crate::util::get_fee(AMOUNT);
+        let payout = wallet_invoice(AMOUNT - fee);
+        let (maker, taker, take) = match kind {
+            Kind::Sell => (&seller, &buyer, Action::TakeSell),
+            Kind::Buy => (&buyer, &seller, Action::TakeBuy),
+        };
+        let buyer_invoice = (kind == Kind::Buy).then(|| payout.clone());
+        let id = self.publish_order(maker, kind, buyer_invoice).await;
+
+        let payload =
+            (kind == Kind::Sell).then(|| Payload::PaymentRequest(None, payout.clone(), None));
+        self.send(
+            taker,
+            Message::new_order(Some(id), Some(1), None, take, payload),
+        )
+        .await;
+        self.wait_for_status(id, Status::WaitingPayment).await;
+        let order = self.order(id).await;
+
+        Trade {
+            id,
+            hash: order.hash.expect("hold invoice hash"),
+            fee: order.fee,
+            seller,
+            buyer,
+            payout,
+        }
+    }
+
+    /// Have the buyer paid on-chain to a fresh regtest address instead.
+    async fn pay_on_chain(&self, trade: &mut Trade) {
+        trade.payout = test_address(bitcoin::Network::Regtest);
+        sqlx::query("UPDATE orders SET buyer_invoice = $1 WHERE id = $2")
+            .bind(&trade.payout)
+            .bind(trade.id)
+            .execute(self.pool())
+            .await
+            .unwrap();
+    }
+
+    /// Have the buyer paid through a BOLT12 offer the node answers for.
+    async fn pay_to_offer(&self, trade: &mut Trade) {
+        trade.payout = self.node.create_offer(None);
+        sqlx::query("UPDATE orders SET buyer_invoice = $1 WHERE id = $2")
+            .bind(&trade.payout)
+            .bind(trade.id)
+            .execute(self.pool())
+            .await
+            .unwrap();
+    }
+
+    /// One pass of the swap payout watcher.
+    async fn swap_pass(&self) {
+        let mut ln = self.node.clone();
+        let pass = swap_payout_pass(&self.ctx, &self.swap, &self.swap, &mut ln);
+        with_node(self.node.clone(), pass).await.unwrap();
+    }
+
+    /// Publish `message` from `trader` to the daemon.
+    async fn send(&self, trader: &Keys, message: Message) {
+        let event = wrap_message_nip44(
+            &message,
+            trader,
+            trader,
+            self.ctx.keys().public_key(),
+            WrapOptions::default(),
+        )
+        .expect("wrap message");
+        self.client.send_event(&event).await.expect("publish");
+    }
+
+    async fn send_order_action(&self, trader: &Keys, id: Uuid, action: Action) {
+        self.send(
+            trader,
+            Message::new_order(Some(id), Some(1), None, action, None),
+        )
+        .await;
+    }
+
+    /// The order `maker` published, once stored.
+    async fn order_by(&self, maker: &Keys) -> Option<Uuid> {
+        sqlx::query_scalar("SELECT id FROM orders WHERE creator_pubkey = $1")
+            .bind(maker.public_key().to_string())
+            .fetch_optional(self.pool())
+            .await
+            .unwrap()
+    }
+
+    async fn order(&self, id: Uuid) -> Order {
+        Order::by_id(self.pool(), id).await.unwrap().unwrap()
+    }
+
+    /// Payment hash of the payout in flight for `id`, if any.
+    async fn payout_claim(&self, id: Uuid) -> Option<String> {
+        sqlx::query_scalar("SELECT payout_payment_hash FROM orders WHERE id = $1")
+            .bind(id)
+            .fetch_one(self.pool())
+            .await
+            .unwrap()
+    }
+
+    async fn wait_for_status(&self, id: Uuid, status: Status) {
+        eventually(&format!("order {id} to reach {status}"), || async {
+            self.order(id).await.status == status.to_string()
+        })
+        .await;
+    }
+
+    /// Seller pays the escrow; the trade goes active.
+    async fn fund(&self, trade: &Trade) {
+        self.node.accept(&trade.hash);
+        self.wait_for_status(trade.id, Status::Active).await;
+    }
+
+    /// Buyer reports the fiat as sent.
+    async fn fiat_sent(&self, trade: &Trade) {
+        self.send_order_action(&trade.buyer, trade.id, Action::FiatSent)
+            .await;
+        self.wait_for_status(trade.id, Status::FiatSent).await;
+    }
+
+    /// Let `secs` pass on the node clock. Scheduler passes are handed
+    /// [`Self::now`] rather than reading the wall clock.
+    fn elapse(&self, secs: i64) {
+        self.node.advance_time(secs);
+    }
+
+    /// The simulated clock, in unix seconds.
+    fn now(&self) -> i64 {
+        self.node.now()
+    }
+
+    /// One pass of the waiting-timeout job.
+    async fn timeout_pass(&self) {
+        let mut ln = self.node.clone();
+        self.as_daemon(cancel_timed_out_orders_pass(&self.ctx, &mut ln, self.now()))
+            .await;
+    }
+
+    /// One tick of the bond payout job.
+    async fn bond_payout_pass(&self) {
+        let mut ln = self.node.clone();
+        self.as_daemon(run_bond_payout_cycle(self.pool(), &mut ln))
+            .await;
+    }
+
+    /// The buyer's payout as the node saw it.
+    fn payouts_to(&self, trade: &Trade) -> Vec<(i64, PaymentStatus)> {
+        self.node
+            .sent_payments()
+            .into_iter()
+            .filter(|payment| payment.payment_request == trade.payout)
+            .map(|payment| (payment.amount, payment.status))
+            .collect()
+    }
+}
+
+async fn settles_the_escrow_and_pays_the_buyer(kind: Kind) {
+    let h = Harness::start().await;
+    let trade = h.open_trade(kind).await;
+
+    h.fund(&trade).await;
+    assert!(queued_actions(trade.buyer.public_key())
+        .await
+        .contains(&Action::HoldInvoicePaymentAccepted));
+    h.fiat_sent(&trade).await;
+    h.send_order_action(&trade.seller, trade.id, Action::Release)
+        .await;
+    h.wait_for_status(trade.id, Status::Success).await;
+    // The payout claim is released after the order reaches `Success`.
+    eventually("the payout claim to clear", || async {
+        h.payout_claim(trade.id).await.is_none()
+    })
+    .await;
+
+    assert_eq!(
+        h.node.invoice_state(&trade.hash),
+        Some(InvoiceState::Settled)
+    );
+    assert_eq!(
+        h.payouts_to(&trade),
+        [(AMOUNT - trade.fee, PaymentStatus::Succeeded)]
+    );
+    assert!(queued_actions(trade.buyer.public_key())
+        .await
+        .contains(&Action::PurchaseCompleted));
+}
+
+#[tokio::test]
+async fn sell_trade_settles_the_escrow_and_pays_the_buyer() {
+    scenario(async {
+        settles_the_escrow_and_pays_the_buyer(Kind::Sell).await;
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn buy_trade_settles_the_escrow_and_pays_the_buyer() {
+    scenario(async {
+        settles_the_escrow_and_pays_the_buyer(Kind::Buy).await;
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn failed_payout_is_retried_until_the_buyer_is_paid() {
+    scenario(async {
+        let h = Harness::start().await;
+        let trade = h.open_trade(Kind::Sell).await;
+        h.node.script_payments([PaymentStatus::Failed]);
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        eventually("the failed payout to be re-armed", || async {
+            h.order(trade.id).await.failed_payment && h.payout_claim(trade.id).await.is_none()
+        })
+        .await;
+        assert_eq!(
+            h.order(trade.id).await.status,
+            Status::SettledHoldInvoice.to_string()
+        );
+        assert!(queued_actions(trade.buyer.public_key())
+            .await
+            .contains(&Action::PaymentFailed));
+
+        let attempts = h.ctx.settings().lightning.payment_attempts as i64;
+        with_node(h.node.clone(), retry_failed_payments_pass(&h.ctx, attempts)).await;
+        h.wait_for_status(trade.id, Status::Success).await;
+
+        assert_eq!(
+            h.payouts_to(&trade),
+            [
+                (AMOUNT - trade.fee, PaymentStatus::Failed),
+                (AMOUNT - trade.fee, PaymentStatus::Succeeded)
+            ]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn on_chain_payout_settles_the_escrow_once_the_swap_completes() {
+    const SWAP_FEE: u64 = 400;
+
+    scenario(async {
+        let h = Harness::start().await;
+        let mut trade = h.open_trade(Kind::Sell).await;
+        h.pay_on_chain(&mut trade).await;
+        h.swap.set_fee(SWAP_FEE);
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        h.wait_for_status(trade.id, Status::SettledHoldInvoice)
+            .await;
+        eventually("the swap invoice to be paid", || async {
+            !h.node.sent_payments().is_empty()
+        })
+        .await;
+        let [id] = h.swap.swap_ids().try_into().expect("one swap");
+
+        // Nothing is claimed, nor the seller's sats taken, before the lockup
+        // confirms.
+        h.swap.lock_up(&id);
+        h.swap_pass().await;
+        assert_eq!(h.swap.paid_to(&trade.payout), 0);
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Accepted)
+        );
+
+        // Mostro claims the confirmed lockup itself; the seller's sats stay
+        // held until that claim confirms.
+        h.swap.confirm(&id);
+        h.swap_pass().await;
+        let claim_fee = h.swap.claim_fee(&id).expect("the lockup is claimed");
+        assert_eq!(h.swap.paid_to(&trade.payout), 0);
+        h.swap_pass().await;
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Accepted)
+        );
+
+        h.swap.mine();
+        assert_eq!(
+            h.swap.paid_to(&trade.payout),
+            (AMOUNT - trade.fee) as u64 - SWAP_FEE - claim_fee
+        );
+        h.swap_pass().await;
+        h.wait_for_status(trade.id, Status::Success).await;
+
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Settled)
+        );
+        assert_eq!(
+            h.node
+                .sent_payments()
+                .into_iter()
+                .map(|payment| (payment.amount, payment.status))
+                .collect::<Vec<_>>(),
+            [(AMOUNT - trade.fee, PaymentStatus::Succeeded)]
+        );
+        assert!(queued_actions(trade.buyer.public_key())
+            .await
+            .contains(&Action::PurchaseCompleted));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn expired_swap_settles_the_escrow_and_counts_a_failed_payout() {
+    scenario(async {
+        let h = Harness::start().await;
+        let mut trade = h.open_trade(Kind::Sell).await;
+        h.pay_on_chain(&mut trade).await;
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        eventually("the swap invoice to be paid", || async {
+            !h.node.sent_payments().is_empty()
+        })
+        .await;
+        let [id] = h.swap.swap_ids().try_into().expect("one swap");
+
+        h.swap.expire(&id);
+        h.swap_pass().await;
+
+        // The fiat moved, so the seller's sats are released regardless and
+        // the buyer's payout goes to the failed-payment retries.
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Settled)
+        );
+        let order = h.order(trade.id).await;
+        assert_eq!(order.status, Status::SettledHoldInvoice.to_string());
+        assert!(order.failed_payment);
+        assert_eq!(h.swap.paid_to(&trade.payout), 0);
+        assert!(queued_actions(trade.buyer.public_key())
+            .await
+            .contains(&Action::PaymentFailed));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn refused_swap_releases_as_usual_and_is_retried() {
+    scenario(async {
+        let h = Harness::start().await;
+        let mut trade = h.open_trade(Kind::Sell).await;
+        h.pay_on_chain(&mut trade).await;
+        h.swap.refuse_swaps(true);
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        eventually("the refused swap to count as a failed payout", || async {
+            h.order(trade.id).await.failed_payment
+        })
+        .await;
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Settled)
+        );
+        assert!(h.swap.swap_ids().is_empty());
+
+        h.swap.refuse_swaps(false);
+        let attempts = h.ctx.settings().lightning.payment_attempts as i64;
+        let retry = with_node(h.node.clone(), retry_failed_payments_pass(&h.ctx, attempts));
+        with_service(h.swap.clone(), retry).await;
+        let [id] = h.swap.swap_ids().try_into().expect("one swap");
+        h.swap.confirm(&id);
+        h.swap_pass().await;
+        h.swap.mine();
+        h.swap_pass().await;
+        h.wait_for_status(trade.id, Status::Success).await;
+        let claim_fee = h.swap.claim_fee(&id).expect("the lockup is claimed");
+        assert_eq!(
+            h.swap.paid_to(&trade.payout),
+            (AMOUNT - trade.fee) as u64 - claim_fee
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn offer_payout_is_retried_with_a_fresh_invoice() {
+    scenario(async {
+        let h = Harness::start().await;
+        let mut trade = h.open_trade(Kind::Sell).await;
+        h.pay_to_offer(&mut trade).await;
+        h.node.script_payments([PaymentStatus::Failed]);
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        eventually("the failed payout to be re-armed", || async {
+            h.order(trade.id).await.failed_payment && h.payout_claim(trade.id).await.is_none()
+        })
+        .await;
+
+        let attempts = h.ctx.settings().lightning.payment_attempts as i64;
+        with_node(h.node.clone(), retry_failed_payments_pass(&h.ctx, attempts)).await;
+        h.wait_for_status(trade.id, Status::Success).await;
+
+        let payments = h.node.sent_payments();
+        assert_eq!(
+            payments
+                .iter()
+                .map(|payment| (payment.amount, payment.status))
+                .collect::<Vec<_>>(),
+            [
+                (AMOUNT - trade.fee, PaymentStatus::Failed),
+                (AMOUNT - trade.fee, PaymentStatus::Succeeded)
+            ]
+        );
+        assert!(payments[0].payment_request.starts_with("lni1"));
+        assert_ne!(payments[0].payment_request, payments[1].payment_request);
+        assert_eq!(h.order(trade.id).await.buyer_invoice, Some(trade.payout));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn escrow_deadline_guardian_cancels_an_idle_active_trade() {
+    scenario(async {
+        let h = Harness::start().await;
+        let trade = h.open_trade(Kind::Sell).await;
+        h.fund(&trade).await;
+        let mut ln = h.node.clone();
+
+        // Fresh escrow: nothing to do.
+        enforce_escrow_deadline_pass(&h.ctx, &mut ln, h.now())
+            .await
+            .unwrap();
+        assert_eq!(h.order(trade.id).await.status, Status::Active.to_string());
+
+        // Nobody moves until the HTLC is within the safety margin of its
+        // CLTV expiry.
+        let margin = h.ctx.settings().lightning.escrow_deadline_margin_blocks;
+        let blocks = HTLC_CLTV_DELTA - margin;
+        h.node.mine(blocks);
+        h.elapse(i64::from(blocks) * SECS_PER_BLOCK);
+        enforce_escrow_deadline_pass(&h.ctx, &mut ln, h.now())
+            .await
+            .unwrap();
+
+        assert_eq!(h.order(trade.id).await.status, Status::Canceled.to_string());
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Canceled)
+        );
+        assert!(h.payouts_to(&trade).is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn htlc_auto_cancel_during_fiat_sent_alarms_both_parties() {
+    scenario(async {
+        let h = Harness::start().await;
+        let trade = h.open_trade(Kind::Sell).await;
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+
+        // The guardian never ran (daemon down): the node refunds the seller on
+        // its own at the hold-expiry horizon.
+        let blocks = HTLC_CLTV_DELTA - HOLD_EXPIRY_DELTA;
+        h.node.mine(blocks);
+        h.elapse(i64::from(blocks) * SECS_PER_BLOCK);
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Canceled)
+        );
+
+        for party in [trade.buyer.public_key(), trade.seller.public_key()] {
+            eventually("the escrow-loss alarm", || async {
+                queued_actions(party)
+                    .await
+                    .contains(&Action::HoldInvoicePaymentCanceled)
+            })
+            .await;
+        }
+        // The fiat leg may have moved: a human resolves it.
+        assert_eq!(h.order(trade.id).await.status, Status::FiatSent.to_string());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn dispute_is_published_and_admin_settle_pays_the_buyer() {
+    scenario(async {
+        let h = Harness::start().await;
+        let trade = h.open_trade(Kind::Sell).await;
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+
+        h.send_order_action(&trade.buyer, trade.id, Action::Dispute)
+            .await;
+        h.wait_for_status(trade.id, Status::Dispute).await;
+        let dispute = crate::db::find_dispute_by_order_id(h.pool(), trade.id)
+            .await
+            .unwrap();
+        // The event is published after the status change is stored.
+        let filter = Filter::new()
+            .kind(NostrKind::Custom(38386))
+            .author(h.ctx.keys().public_key())
+            .identifier(dispute.id.to_string());
+        eventually("the dispute event to reach the relay", || async {
+            h.client
+                .fetch_events(filter.clone())
+                .timeout(Duration::from_secs(1))
+                .await
+                .is_ok_and(|published| !published.is_empty())
+        })
+        .await;
+
+        let admin = h.ctx.keys().clone();
+        h.send(
+            &admin,
+            Message::new_dispute(
+                Some(dispute.id),
+                Some(1),
+                None,
+                Action::AdminTakeDispute,
+                None,
+            ),
+        )
+        .await;
+        eventually("the admin to take the dispute", || async {
+            crate::db::find_dispute_by_order_id(h.pool(), trade.id)
+                .await
+                .is_ok_and(|d| d.status == DisputeStatus::InProgress.to_string())
+        })
+        .await;
+        h.send_order_action(&admin, trade.id, Action::AdminSettle)
+            .await;
+        h.wait_for_status(trade.id, Status::Success).await;
+
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Settled)
+        );
+        assert_eq!(
+            h.payouts_to(&trade),
+            [(AMOUNT - trade.fee, PaymentStatus::Succeeded)]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn timed_out_taker_bond_is_slashed_and_paid_to_the_maker() {
+    scenario(async {
+        let h = Harness::with_bond(AntiAbuseBondSettings {
+            enabled: true,
+            slash_on_waiting_timeout: true,
+            apply_to: BondApplyTo::Take,
+            ..AntiAbuseBondSettings::default()
+        })
+        .await;
+        let (seller, buyer) = (Keys::generate(), Keys::generate());
+        let id = h.publish_order(&seller, Kind::Sell, None).await;
+
+        // The buyer takes without an invoice and is asked for a bond first.
+        h.send_order_action(&buyer, id, Action::TakeSell).await;
+        eventually("the bond invoice", || async {
+            queued_bond_invoice(buyer.public_key()).await.is_some()
+        })
+        .await;
+        let bond_hash = bytes_to_string(
+            decode_invoice(&queued_bond_invoice(buyer.public_key()).await.unwrap())
+                .unwrap()
+                .payment_hash()
+                .as_ref(),
+        );
+
+        // Paying it resumes the take; then the buyer never sends an invoice.
+        h.node.accept(&bond_hash);
+        h.wait_for_status(id, Status::WaitingBuyerInvoice).await;
+        h.timeout_pass().await;
+        assert_eq!(
+            h.order(id).await.status,
+            Status::WaitingBuyerInvoice.to_string()
+        );
+
+        h.elapse(i64::from(h.ctx.settings().mostro.expiration_seconds) + 60);
+        h.timeout_pass().await;
+        assert_eq!(h.order(id).await.status, Status::Pending.to_string());
+        assert_eq!(
+            h.node.invoice_state(&bond_hash),
+            Some(InvoiceState::Settled)
+        );
+        let bond = find_bond_by_hash(h.pool(), &bond_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bond.role, BondRole::Taker.to_string());
+
+        // First payout tick asks the seller for an invoice; the seller answers
+        // over the relay.
+        h.bond_payout_pass().await;
+        eventually("the bond invoice request", || async {
+            queued_actions(seller.public_key())
+                .await
+                .contains(&Action::AddBondInvoice)
+        })
+        .await;
+        let share = {
+            let bond = find_bond_by_id(h.pool(), bond.id).await.unwrap().unwrap();
+            bond.amount_sats - bond.node_share_sats.unwrap()
+        };
+        let invoice = wallet_invoice(share);
+        h.send(
+            &seller,
+            Message::new_order(
+                Some(id),
+                Some(1),
+                None,
+                Action::AddBondInvoice,
+                Some(Payload::PaymentRequest(None, invoice.clone(), None)),
+            ),
+        )
+        .await;
+        eventually("the bond invoice to be stored", || async {
+            find_bond_by_id(h.pool(), bond.id)
+                .await
+                .unwrap()
+                .is_some_and(|b| b.payout_invoice.is_some())
+        })
+        .await;
+
+        h.bond_payout_pass().await;
+        let bond = find_bond_by_id(h.pool(), bond.id).await.unwrap().unwrap();
+        assert_eq!(bond.state, BondState::Slashed.to_string());
+        let paid: Vec<_> = h
+            .node
+            .sent_payments()
+            .into_iter()
+            .filter(|payment| payment.payment_request == invoice)
+            .map(|payment| (payment.amount, payment.status))
+            .collect();
+        assert_eq!(paid, [(share, PaymentStatus::Succeeded)]);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn short_lockup_is_never_claimed_and_the_swap_runs_out() {
+    scenario(async {
+        let h = Harness::start().await;
+        let mut trade = h.open_trade(Kind::Sell).await;
+        h.pay_on_chain(&mut trade).await;
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        eventually("the swap invoice to be paid", || async {
+            !h.node.sent_payments().is_empty()
+        })
+        .await;
+        let [id] = h.swap.swap_ids().try_into().expect("one swap");
+
+        // The service confirms a lockup worth less than it quoted: the
+        // preimage is never revealed, so it cannot take Mostro's payment.
+        h.swap.lock_up_short(&id, 1_000);
+        h.swap.confirm(&id);
+        h.swap_pass().await;
+        assert_eq!(h.swap.claim_fee(&id), None);
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Accepted)
+        );
+
+        h.swap.expire(&id);
+        h.swap_pass().await;
+        assert_eq!(
+            h.node.invoice_state(&trade.hash),
+            Some(InvoiceState::Settled)
+        );
+        assert!(h.order(trade.id).await.failed_payment);
+        assert_eq!(
+            h.node
+                .sent_payments()
+                .into_iter()
+                .map(|payment| payment.status)
+                .collect::<Vec<_>>(),
+            [PaymentStatus::Failed]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn forged_lockup_address_is_refused_like_a_refused_swap() {
+    scenario(async {
+        let h = Harness::start().await;
+        let mut trade = h.open_trade(Kind::Sell).await;
+        h.pay_on_chain(&mut trade).await;
+        h.swap.forge_lockup_addresses(true);
+
+        h.fund(&trade).await;
+        h.fiat_sent(&trade).await;
+        h.send_order_action(&trade.seller, trade.id, Action::Release)
+            .await;
+        eventually("the forged swap to count as a failed payout", || async {
+            h.order(trade.id).await.failed_payment
+        })
+        .await;
+
+        // The quote was refused before its invoice was paid.
+        assert!(h.node.sent_payments().is_empty());
+        assert!(crate::swap::db::find_live_swaps(h.pool())
+            .await
+            .unwrap()
+            .is_empty());
+    })
+    .await;
+}
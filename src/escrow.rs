@@ -9,13 +9,25 @@
 //! a high-level `lock`/`release`/`dispute` abstraction.
 
 use async_trait::async_trait;
+use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
 use mostro_core::prelude::*;
+use tokio::sync::mpsc::Sender;
 
-use crate::lightning::LndConnector;
+use crate::config::settings::Settings;
+use crate::config::LightningBackend;
+use crate::lightning::cln::ClnConnector;
+use crate::lightning::{InvoiceMessage, LnStatus, LndConnector, PaymentMessage};
+
+#[cfg(test)]
+pub(crate) mod memory;
 
 /// The escrow seam. Implemented as a behaviour-preserving pass-through by
-/// [`LndConnector`] and as an inert stub by [`CashuBackend`] until the
-/// feature tracks land.
+/// [`LndConnector`], by [`ClnConnector`] for Core Lightning nodes, and as an
+/// inert stub by [`CashuBackend`] until the feature tracks land.
+///
+/// Every handler that touches the node (hold invoices, payouts, payment
+/// reconciliation, invoice subscriptions) takes `&mut dyn EscrowBackend`;
+/// tests drive them with the in-memory backend in `escrow::memory`.
 #[async_trait]
 pub trait EscrowBackend: Send {
     /// Create a hold invoice for `amount` sats.
@@ -53,6 +65,74 @@ pub trait EscrowBackend: Send {
             "HTLC expiry height not available for this escrow backend".to_string(),
         )))
     }
+
+    /// Stream the state changes of the invoice `r_hash` (raw bytes) into
+    /// `listener` until the node closes the subscription.
+    async fn subscribe_invoice(
+        &mut self,
+        _r_hash: Vec<u8>,
+        _listener: Sender<InvoiceMessage>,
+    ) -> Result<(), MostroError> {
+        Err(MostroInternalErr(ServiceError::LnNodeError(
+            "invoice subscription not available for this escrow backend".to_string(),
+        )))
+    }
+
+    /// Pay `payment_request` for `amount` sats, streaming every payment
+    /// update into `listener`. Refuses a hash the node already reports as
+    /// in flight or settled, and an invoice whose amount is not `amount`.
+    async fn send_payment(
+        &mut self,
+        _payment_request: &str,
+        _amount: i64,
+        _listener: Sender<PaymentMessage>,
+    ) -> Result<(), MostroError> {
+        Err(MostroInternalErr(ServiceError::LnPaymentError(
+            "payments not available for this escrow backend".to_string(),
+        )))
+    }
+
+    /// Ask the issuer of the BOLT12 `offer` for an invoice of `amount` sats
+    /// and return it (`lni…`). Only nodes that speak BOLT12 can.
+    async fn fetch_offer_invoice(
+        &mut self,
+        _offer: &str,
+        _amount: u64,
+    ) -> Result<String, MostroError> {
+        Err(MostroInternalErr(ServiceError::LnPaymentError(
+            "BOLT12 offers not available for this escrow backend".to_string(),
+        )))
+    }
+
+    /// Status of the outgoing payment `payment_hash` (raw bytes).
+    /// `Ok(None)` means the node has no record of the hash.
+    async fn lookup_payment_status(
+        &mut self,
+        _payment_hash: &[u8],
+    ) -> Result<Option<PaymentStatus>, MostroError> {
+        Err(MostroInternalErr(ServiceError::LnPaymentError(
+            "payment lookup not available for this escrow backend".to_string(),
+        )))
+    }
+
+    /// Identity and sync state of the node, published as `LN_STATUS`.
+    async fn node_status(&mut self) -> Result<LnStatus, MostroError> {
+        Err(MostroInternalErr(ServiceError::LnNodeError(
+            "node status not available for this escrow backend".to_string(),
+        )))
+    }
+}
+
+/// Escrow on the node selected by `[lightning] backend`.
+pub async fn connect_lightning() -> Result<Box<dyn EscrowBackend>, MostroError> {
+    #[cfg(test)]
+    if let Some(node) = memory::scoped_node() {
+        return Ok(Box::new(node));
+    }
+    Ok(match Settings::get_ln().backend {
+        LightningBackend::Lnd => Box::new(LndConnector::new().await?),
+        LightningBackend::Cln => Box::new(ClnConnector::new().await?),
+    })
 }
 
 #[async_trait]
@@ -90,6 +170,99 @@ impl EscrowBackend for LndConnector {
     async fn hold_invoice_expiry_height(&mut self, hash: &str) -> Result<Option<u32>, MostroError> {
         LndConnector::get_hold_invoice_expiry_height(self, hash).await
     }
+
+    async fn subscribe_invoice(
+        &mut self,
+        r_hash: Vec<u8>,
+        listener: Sender<InvoiceMessage>,
+    ) -> Result<(), MostroError> {
+        LndConnector::subscribe_invoice(self, r_hash, listener).await
+    }
+
+    async fn send_payment(
+        &mut self,
+        payment_request: &str,
+        amount: i64,
+        listener: Sender<PaymentMessage>,
+    ) -> Result<(), MostroError> {
+        LndConnector::send_payment(self, payment_request, amount, listener).await
+    }
+
+    async fn lookup_payment_status(
+        &mut self,
+        payment_hash: &[u8],
+    ) -> Result<Option<PaymentStatus>, MostroError> {
+        LndConnector::lookup_payment_status(self, payment_hash).await
+    }
+
+    async fn node_status(&mut self) -> Result<LnStatus, MostroError> {
+        Ok(LnStatus::from_get_info_response(
+            LndConnector::get_node_info(self).await?,
+        ))
+    }
+}
+
+#[async_trait]
+impl EscrowBackend for ClnConnector {
+    async fn create_hold_invoice(
+        &mut self,
+        description: &str,
+        amount: i64,
+    ) -> Result<(String, Vec<u8>, Vec<u8>), MostroError> {
+        ClnConnector::create_hold_invoice(self, description, amount).await
+    }
+
+    async fn settle_hold_invoice(&mut self, preimage: &str) -> Result<(), MostroError> {
+        ClnConnector::settle_hold_invoice(self, preimage).await
+    }
+
+    async fn cancel_hold_invoice(&mut self, hash: &str) -> Result<(), MostroError> {
+        ClnConnector::cancel_hold_invoice(self, hash).await
+    }
+
+    async fn chain_height(&mut self) -> Result<u32, MostroError> {
+        ClnConnector::get_chain_height(self).await
+    }
+
+    async fn hold_invoice_expiry_height(&mut self, hash: &str) -> Result<Option<u32>, MostroError> {
+        ClnConnector::get_hold_invoice_expiry_height(self, hash).await
+    }
+
+    async fn subscribe_invoice(
+        &mut self,
+        r_hash: Vec<u8>,
+        listener: Sender<InvoiceMessage>,
+    ) -> Result<(), MostroError> {
+        ClnConnector::subscribe_invoice(self, r_hash, listener).await
+    }
+
+    async fn send_payment(
+        &mut self,
+        payment_request: &str,
+        amount: i64,
+        listener: Sender<PaymentMessage>,
+    ) -> Result<(), MostroError> {
+        ClnConnector::send_payment(self, payment_request, amount, listener).await
+    }
+
+    async fn lookup_payment_status(
+        &mut self,
+        payment_hash: &[u8],
+    ) -> Result<Option<PaymentStatus>, MostroError> {
+        ClnConnector::lookup_payment_status(self, payment_hash).await
+    }
+
+    async fn fetch_offer_invoice(
+        &mut self,
+        offer: &str,
+        amount: u64,
+    ) -> Result<String, MostroError> {
+        ClnConnector::fetch_offer_invoice(self, offer, amount).await
+    }
+
+    async fn node_status(&mut self) -> Result<LnStatus, MostroError> {
+        ClnConnector::get_node_status(self).await
+    }
 }
 
 /// Cashu escrow backend — CF-0 stub.
@@ -0,0 +1,791 @@
+//! Simulated Lightning node implementing [`EscrowBackend`], for tests.
+//!
+//! Hold invoices, outgoing payments, the chain tip and the node's clock live
+//! in memory behind a shared handle: every clone of a [`MemoryEscrow`] is the
+//! same node, so a test keeps one to drive and inspect it while the daemon
+//! code holds others. Nothing happens on its own; the test scripts each
+//! transition:
+//!
+//! - [`MemoryEscrow::accept`] — the payer locked an HTLC on a hold invoice;
+//! - [`MemoryEscrow::script_payments`] / [`MemoryEscrow::set_payment_outcome`]
+//!   — how the next outgoing payments end (failed, stuck in flight, paid);
+//! - [`MemoryEscrow::hold_outgoing`] / [`MemoryEscrow::resolve_outgoing`]
+//!   — a payee that holds the HTLC (a swap service) and later settles or
+//!   fails it;
+//! - [`MemoryEscrow::create_offer`] — a BOLT12 offer whose invoices the node
+//!   fetches from itself, a fresh one per `fetch_offer_invoice`;
+//! - [`MemoryEscrow::mine`] — move the tip; accepted HTLCs within
+//!   [`HOLD_EXPIRY_DELTA`] blocks of their CLTV expiry are auto-canceled,
+//!   as LND does;
+//! - [`MemoryEscrow::advance_time`] — move the clock; open invoices past
+//!   their expiry are canceled.
+//!
+//! Invoice subscriptions are live: every state change is pushed to the
+//! listeners registered through `subscribe_invoice`. Error strings follow
+//! LND's (`code=NotFound`, `already settled`), so the callers' error
+//! classification behaves as it does against a real node.
+//!
+//! Code that opens its own connection (`escrow::connect_lightning`, e.g.
+//! `do_payment` or the bond flows) reaches the simulated node when it runs
+//! inside [`with_node`].
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{Keypair, PublicKey, Secp256k1, SecretKey};
+use fedimint_tonic_lnd::lnrpc::invoice::InvoiceState;
+use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
+use fedimint_tonic_lnd::lnrpc::{Payment, PaymentFailureReason};
+use lightning::blinded_path::payment::{BlindedPayInfo, BlindedPaymentPath};
+use lightning::blinded_path::BlindedHop;
+use lightning::ln::channelmanager::PaymentId;
+use lightning::ln::inbound_payment::ExpandedKey;
+use lightning::offers::invoice::UnsignedBolt12Invoice;
+use lightning::offers::nonce::Nonce;
+use lightning::offers::offer::OfferBuilder;
+use lightning::types::features::BlindedHopFeatures;
+use lightning::types::payment::PaymentHash;
+use lightning::util::ser::Writeable;
+use lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+use mostro_core::prelude::*;
+use rand::RngCore;
+use tokio::sync::mpsc::Sender;
+
+use super::EscrowBackend;
+use crate::lightning::bolt12::{decode_offer, payment_hash_and_amount};
+use crate::lightning::{decode_hash32, InvoiceMessage, LnStatus, PaymentMessage};
+use crate::util::bytes_to_string;
+
+/// CLTV delta of the HTLC backing an accepted hold invoice, in blocks.
+pub(crate) const HTLC_CLTV_DELTA: u32 = 144;
+
+/// Blocks before an accepted HTLC's CLTV expiry at which the node cancels
+/// it back to the payer (LND's `invoices.holdexpirydelta` default).
+pub(crate) const HOLD_EXPIRY_DELTA: u32 = 12;
+
+/// Expiry of a hold invoice that was never paid, in seconds (LND's default).
+pub(crate) const INVOICE_EXPIRY_SECS: i64 = 86_400;
+
+/// Chain tip of a fresh node.
+const START_HEIGHT: u32 = 800_000;
+
+tokio::task_local! {
+    static NODE: MemoryEscrow;
+}
+
+/// Run `f` with `node` standing in for the configured Lightning node:
+/// `escrow::connect_lightning` hands out clones of it instead of dialing
+/// LND or Core Lightning.
+pub(crate) async fn with_node<F: Future>(node: MemoryEscrow, f: F) -> F::Output {
+    NODE.scope(node, f).await
+}
+
+/// The node installed by [`with_node`] for the current task, if any.
+pub(crate) fn scoped_node() -> Option<MemoryEscrow> {
+    NODE.try_with(Clone::clone).ok()
+}
+
+/// A hold invoice issued by [`MemoryEscrow`].
+#[derive(Debug, Clone)]
+struct HeldInvoice {
+    preimage: Vec<u8>,
+    state: InvoiceState,
+    /// Node clock past which an unpaid invoice expires.
+    expires_at: i64,
+    /// CLTV expiry of the accepted HTLC; `None` unless accepted.
+    expiry_height: Option<u32>,
+}
+
+/// An outgoing payment dispatched through [`MemoryEscrow`].
+#[derive(Debug, Clone)]
+pub(crate) struct SentPayment {
+    pub payment_request: String,
+    pub amount: i64,
+    pub status: PaymentStatus,
+}
+
+#[derive(Debug)]
+struct Node {
+    /// Hold invoices by payment hash (hex).
+    invoices: HashMap<String, HeldInvoice>,
+    /// Outgoing payments by payment hash (hex), latest attempt.
+    payments: HashMap<String, SentPayment>,
+    /// Outgoing payments in dispatch order, every attempt.
+    attempts: Vec<SentPayment>,
+    /// Outcomes for the next payments, consumed one per `send_payment`.
+    script: VecDeque<PaymentStatus>,
+    /// Outcome once the script is exhausted.
+    payment_outcome: PaymentStatus,
+    /// Payment hashes whose payee holds the HTLC: payments to them stay in
+    /// flight until resolved.
+    held_outgoing: HashSet<String>,
+    /// Signing keys of the offers from [`MemoryEscrow::create_offer`].
+    offers: HashMap<String, SecretKey>,
+    chain_height: u32,
+    /// Node clock, unix seconds.
+    now: i64,
+    /// Live invoice subscriptions by payment hash (hex).
+    listeners: HashMap<String, Vec<Sender<InvoiceMessage>>>,
+}
+
+impl Node {
+    /// Move `hash` to `state` and tell its subscribers.
+    fn transition(&mut self, hash: &str, state: InvoiceState) {
+        if let Some(invoice) = self.invoices.get_mut(hash) {
+            invoice.state = state;
+            if state != InvoiceState::Accepted {
+                invoice.expiry_height = None;
+            }
+        }
+        let Some(listeners) = self.listeners.get_mut(hash) else {
+            return;
+        };
+        let Some(r_hash) = decode_hash32("hash", hash).ok() else {
+            return;
+        };
+        listeners.retain(|listener| {
+            listener
+                .try_send(InvoiceMessage {
+                    hash: r_hash.clone(),
+                    state,
+                })
+                .is_ok()
+        });
+    }
+
+    /// Hashes of the invoices in `state` matching `due`.
+    fn due(&self, state: InvoiceState, due: impl Fn(&HeldInvoice) -> bool) -> Vec<String> {
+        self.invoices
+            .iter()
+            .filter(|(_, invoice)| invoice.state == state && due(invoice))
+            .map(|(hash, _)| hash.clone())
+            .collect()
+    }
+}
+
+/// Handle on a simulated node; clones share it.
+#[derive(Debug, Clone)]
+pub(crate) struct MemoryEscrow {
+    node: Arc<Mutex<Node>>,
+}
+
+impl Default for MemoryEscrow {
+    fn default() -> Self {
+        Self {
+            node: Arc::new(Mutex::new(Node {
+                invoices: HashMap::new(),
+                payments: HashMap::new(),
+                attempts: Vec::new(),
+                script: VecDeque::new(),
+                payment_outcome: PaymentStatus::Succeeded,
+                held_outgoing: HashSet::new(),
+                offers: HashMap::new(),
+                chain_height: START_HEIGHT,
+                now: chrono::Utc::now().timestamp(),
+                listeners: HashMap::new(),
+            })),
+        }
+    }
+}
+
+fn not_found(what: &str) -> MostroError {
+    MostroInternalErr(ServiceError::LnNodeError(format!(
+        "code=NotFound message=unable to locate {what}"
+    )))
+}
+
+/// Signed regtest-style bolt11 for `hash`; the payee key is a throwaway.
+pub(crate) fn bolt11(hash: &[u8], amount: i64, description: &str) -> String {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[42u8; 32]).expect("valid key");
+    InvoiceBuilder::new(Currency::Bitcoin)
+        .description(description.to_string())
+        .payment_hash(sha256::Hash::from_slice(hash).expect("32-byte hash"))
+        .payment_secret(PaymentSecret([7u8; 32]))
+        .amount_milli_satoshis(amount as u64 * 1_000)
+        .current_timestamp()
+        .min_final_cltv_expiry_delta(18)
+        .build_signed(|digest| secp.sign_ecdsa_recoverable(digest, &sk))
+        .expect("valid invoice")
+        .to_string()
+}
+
+fn random_key() -> SecretKey {
+    SecretKey::from_slice(&rand::random::<[u8; 32]>()).expect("valid key")
+}
+
+/// A BOLT12 offer for `amount_sats` (open amount when `None`), with the
+/// issuer's signing key.
+pub(crate) fn offer(amount_sats: Option<u64>) -> (String, SecretKey) {
+    let key = random_key();
+    let builder = OfferBuilder::new(PublicKey::from_secret_key(&Secp256k1::new(), &key));
+    let offer = match amount_sats {
+        Some(sats) => builder.amount_msats(sats * 1_000).build(),
+        None => builder.build(),
+    };
+    (offer.expect("valid offer").to_string(), key)
+}
+
+/// Invoice (`lni…`) for `amount_sats` to `hash`, answering `offer` as its
+/// issuer (`key`) would to an invoice request.
+pub(crate) fn offer_invoice(
+    offer: &str,
+    key: &SecretKey,
+    amount_sats: u64,
+    hash: &[u8; 32],
+) -> String {
+    let secp = Secp256k1::new();
+    let offer = decode_offer(offer).expect("valid offer");
+    let mut request = offer
+        .request_invoice(
+            &ExpandedKey::new(rand::random()),
+            Nonce::try_from(&rand::random::<[u8; 16]>()[..]).expect("valid nonce"),
+            &secp,
+            PaymentId(rand::random()),
+        )
+        .expect("invoice request");
+    if offer.amount().is_none() {
+        request = request.amount_msats(amount_sats * 1_000).expect("amount");
+    }
+    let path = BlindedPaymentPath::from_blinded_path_and_payinfo(
+        PublicKey::from_secret_key(&secp, &random_key()),
+        PublicKey::from_secret_key(&secp, &random_key()),
+        vec![BlindedHop {
+            blinded_node_id: PublicKey::from_secret_key(&secp, &random_key()),
+            encrypted_payload: vec![0; 32],
+        }],
+        BlindedPayInfo {
+            fee_base_msat: 0,
+            fee_proportional_millionths: 0,
+            cltv_expiry_delta: 42,
+            htlc_minimum_msat: 1,
+            htlc_maximum_msat: u64::MAX,
+            features: BlindedHopFeatures::empty(),
+        },
+    );
+    let keypair = Keypair::from_secret_key(&secp, key);
+    let invoice = request
+        .build_and_sign()
+        .expect("signed invoice request")
+        .respond_with(vec![path], PaymentHash(*hash))
+        .expect("invoice builder")
+        .build()
+        .expect("invoice")
+        .sign(|invoice: &UnsignedBolt12Invoice| {
+            Ok(secp.sign_schnorr_no_aux_rand(invoice.as_ref().as_digest(), &keypair))
+        })
+        .expect("signed invoice");
+    let hrp = bech32::Hrp::parse("lni").expect("valid hrp");
+    bech32::encode::<bech32::NoChecksum>(hrp, &invoice.encode()).expect("bech32 invoice")
+}
+
+impl MemoryEscrow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn node(&self) -> MutexGuard<'_, Node> {
+        self.node.lock().expect("simulated node poisoned")
+    }
+
+    /// The buyer or seller paid the hold invoice `hash` (hex): its HTLC is
+    /// now held until settle or cancel.
+    pub fn accept(&self, hash: &str) {
+        let mut node = self.node();
+        let height = node.chain_height;
+        let invoice = node
+            .invoices
+            .get_mut(hash)
+            .expect("accept: unknown hold invoice");
+        assert_eq!(
+            invoice.state,
+            InvoiceState::Open,
+            "accept: hold invoice is not open"
+        );
+        invoice.expiry_height = Some(height + HTLC_CLTV_DELTA);
+        node.transition(hash, InvoiceState::Accepted);
+    }
+
+    pub fn invoice_state(&self, hash: &str) -> Option<InvoiceState> {
+        self.node().invoices.get(hash).map(|invoice| invoice.state)
+    }
+
+    /// Preimage (hex) of the hold invoice `hash`.
+    pub fn preimage(&self, hash: &str) -> Option<String> {
+        self.node()
+            .invoices
+            .get(hash)
+            .map(|invoice| bytes_to_string(&invoice.preimage))
+    }
+
+    /// A BOLT12 offer (open amount when `amount_sats` is `None`) the node
+    /// answers invoice requests for.
+    pub fn create_offer(&self, amount_sats: Option<u64>) -> String {
+        let (offer, key) = offer(amount_sats);
+        self.node().offers.insert(offer.clone(), key);
+        offer
+    }
+
+    /// Every payment attempt so far, in dispatch order.
+    pub fn sent_payments(&self) -> Vec<SentPayment> {
+        self.node().attempts.clone()
+    }
+
+    /// Final status of every payment once the script is exhausted.
+    pub fn set_payment_outcome(&self, status: PaymentStatus) {
+        self.node().payment_outcome = status;
+    }
+
+    /// The next payments end with `outcomes`, in order. `InFlight` leaves a
+    /// payment stuck: the update stream ends without a terminal state.
+    pub fn script_payments(&self, outcomes: impl IntoIterator<Item = PaymentStatus>) {
+        self.node().script.extend(outcomes);
+    }
+
+    /// Payments to `hash` (hex) stay in flight until
+    /// [`MemoryEscrow::resolve_outgoing`], whatever the script says.
+    pub fn hold_outgoing(&self, hash: &str) {
+        self.node().held_outgoing.insert(hash.to_string());
+    }
+
+    /// The payee of a held payment to `hash` settled (`Succeeded`) or
+    /// failed it.
+    pub fn resolve_outgoing(&self, hash: &str, status: PaymentStatus) {
+        let mut node = self.node();
+        node.held_outgoing.remove(hash);
+        let Some(payment) = node.payments.get_mut(hash) else {
+            return;
+        };
+        payment.status = status;
+        let request = payment.payment_request.clone();
+        if let Some(attempt) = node
+            .attempts
+            .iter_mut()
+            .rev()
+            .find(|attempt| attempt.payment_request == request)
+        {
+            attempt.status = status;
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        self.node().chain_height
+    }
+
+    /// Mine `blocks` blocks. Accepted HTLCs now within
+    /// [`HOLD_EXPIRY_DELTA`] of their expiry are canceled back to the payer.
+    pub fn mine(&self, blocks: u32) {
+        let mut node = self.node();
+        node.chain_height += blocks;
+        let height = node.chain_height;
+        let expiring = node.due(InvoiceState::Accepted, |invoice| {
+            invoice
+                .expiry_height
+                .is_some_and(|expiry| height + HOLD_EXPIRY_DELTA >= expiry)
+        });
+        for hash in expiring {
+            node.transition(&hash, InvoiceState::Canceled);
+        }
+    }
+
+    /// The node clock, in unix seconds.
+    pub fn now(&self) -> i64 {
+        self.node().now
+    }
+
+    /// Move the node clock `secs` forward. Open invoices past their expiry
+    /// are canceled.
+    pub fn advance_time(&self, secs: i64) {
+        let mut node = self.node();
+        node.now += secs;
+        let now = node.now;
+        for hash in node.due(InvoiceState::Open, |invoice| now >= invoice.expires_at) {
+            node.transition(&hash, InvoiceState::Canceled);
+        }
+    }
+}
+
+#[async_trait]
+impl EscrowBackend for MemoryEscrow {
+    async fn create_hold_invoice(
+        &mut self,
+        description: &str,
+        amount: i64,
+    ) -> Result<(String, Vec<u8>, Vec<u8>), MostroError> {
+        let mut preimage = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut preimage);
+        let hash = sha256::Hash::hash(&preimage).to_byte_array().to_vec();
+        let payment_request = bolt11(&hash, amount, description);
+        let mut node = self.node();
+        let expires_at = node.now + INVOICE_EXPIRY_SECS;
+        node.invoices.insert(
+            bytes_to_string(&hash),
+            HeldInvoice {
+                preimage: preimage.to_vec(),
+                state: InvoiceState::Open,
+                expires_at,
+                expiry_height: None,
+            },
+        );
+        Ok((payment_request, preimage.to_vec(), hash))
+    }
+
+    async fn settle_hold_invoice(&mut self, preimage: &str) -> Result<(), MostroError> {
+        let preimage = decode_hash32("preimage", preimage)?;
+        let hash = bytes_to_string(sha256::Hash::hash(&preimage).as_ref());
+        let mut node = self.node();
+        let state = node
+            .invoices
+            .get(&hash)
+            .map(|invoice| invoice.state)
+            .ok_or_else(|| not_found("invoice"))?;
+        match state {
+            InvoiceState::Accepted => {
+                node.transition(&hash, InvoiceState::Settled);
+                Ok(())
+            }
+            InvoiceState::Settled => Err(MostroInternalErr(ServiceError::LnNodeError(
+                "code=Unknown message=invoice already settled".to_string(),
+            ))),
+            state => Err(MostroInternalErr(ServiceError::LnNodeError(format!(
+                "code=Unknown message=invoice is {state:?}, not accepted"
+            )))),
+        }
+    }
+
+    async fn cancel_hold_invoice(&mut self, hash: &str) -> Result<(), MostroError> {
+        let mut node = self.node();
+        let state = node
+            .invoices
+            .get(hash)
+            .map(|invoice| invoice.state)
+            .ok_or_else(|| not_found("invoice"))?;
+        match state {
+            InvoiceState::Settled => Err(MostroInternalErr(ServiceError::LnNodeError(
+                "code=Unknown message=invoice already settled".to_string(),
+            ))),
+            InvoiceState::Canceled => Ok(()),
+            _ => {
+                node.transition(hash, InvoiceState::Canceled);
+                Ok(())
+            }
+        }
+    }
+
+    async fn chain_height(&mut self) -> Result<u32, MostroError> {
+        Ok(self.height())
+    }
+
+    async fn hold_invoice_expiry_height(&mut self, hash: &str) -> Result<Option<u32>, MostroError> {
+        Ok(self
+            .node()
+            .invoices
+            .get(hash)
+            .and_then(|invoice| invoice.expiry_height))
+    }
+
+    /// Reports the current state, then every later transition until the
+    /// listener is dropped.
+    async fn subscribe_invoice(
+        &mut self,
+        r_hash: Vec<u8>,
+        listener: Sender<InvoiceMessage>,
+    ) -> Result<(), MostroError> {
+        let hash = bytes_to_string(&r_hash);
+        let mut node = self.node();
+        let state = node
+            .invoices
+            .get(&hash)
+            .map(|invoice| invoice.state)
+            .ok_or_else(|| not_found("invoice"))?;
+        listener
+            .try_send(InvoiceMessage {
+                hash: r_hash,
+                state,
+            })
+            .map_err(|e| MostroInternalErr(ServiceError::LnNodeError(e.to_string())))?;
+        node.listeners.entry(hash).or_default().push(listener);
+        Ok(())
+    }
+
+    async fn send_payment(
+        &mut self,
+        payment_request: &str,
+        amount: i64,
+        listener: Sender<PaymentMessage>,
+    ) -> Result<(), MostroError> {
+        let (hash, invoice_msat) = payment_hash_and_amount(payment_request)?;
+        let hash = bytes_to_string(&hash);
+        let status = {
+            let mut node = self.node();
+            if let Some(PaymentStatus::InFlight | PaymentStatus::Succeeded) =
+                node.payments.get(&hash).map(|payment| payment.status)
+            {
+                return Err(MostroInternalErr(ServiceError::LnPaymentError(
+                    "payment already dispatched for this hash".to_string(),
+                )));
+            }
+            if let Some(msat) = invoice_msat {
+                if msat != amount as u64 * 1000 {
+                    return Err(MostroInternalErr(ServiceError::LnPaymentError(
+                        "Wrong amount".to_string(),
+                    )));
+                }
+            }
+
+            let status = if node.held_outgoing.contains(&hash) {
+                PaymentStatus::InFlight
+            } else {
+                node.script.pop_front().unwrap_or(node.payment_outcome)
+            };
+            let payment = SentPayment {
+                payment_request: payment_request.to_string(),
+                amount,
+                status,
+            };
+            node.attempts.push(payment.clone());
+            node.payments.insert(hash.clone(), payment);
+            status
+        };
+        let failure_reason = match status {
+            PaymentStatus::Failed => PaymentFailureReason::FailureReasonNoRoute,
+            _ => PaymentFailureReason::FailureReasonNone,
+        };
+        listener
+            .send(PaymentMessage {
+                payment: Payment {
+                    payment_hash: hash,
+                    value_sat: amount,
+                    status: status as i32,
+                    failure_reason: failure_reason as i32,
+                    ..Default::default()
+                },
+            })
+            .await
+            .map_err(|e| MostroInternalErr(ServiceError::LnNodeError(e.to_string())))
+    }
+
+    async fn lookup_payment_status(
+        &mut self,
+        payment_hash: &[u8],
+    ) -> Result<Option<PaymentStatus>, MostroError> {
+        Ok(self
+            .node()
+            .payments
+            .get(&bytes_to_string(payment_hash))
+            .map(|payment| payment.status))
+    }
+
+    async fn fetch_offer_invoice(
+        &mut self,
+        offer: &str,
+        amount: u64,
+    ) -> Result<String, MostroError> {
+        let key = self.node().offers.get(offer).copied().ok_or_else(|| {
+            MostroInternalErr(ServiceError::LnPaymentError(
+                "Timeout waiting for response".to_string(),
+            ))
+        })?;
+        let amount = match decode_offer(offer)?.amount() {
+            Some(lightning::offers::offer::Amount::Bitcoin { amount_msats }) => {
+                amount_msats / 1_000
+            }
+            _ => amount,
+        };
+        Ok(offer_invoice(offer, &key, amount, &rand::random()))
+    }
+
+    async fn node_status(&mut self) -> Result<LnStatus, MostroError> {
+        Ok(LnStatus {
+            version: "memory".to_string(),
+            node_pubkey: String::new(),
+            commit_hash: String::new(),
+            node_alias: "memory-escrow".to_string(),
+            chains: vec!["bitcoin".to_string()],
+            networks: vec!["regtest".to_string()],
+            uris: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lightning::invoice::decode_invoice;
+
+    #[tokio::test]
+    async fn hold_invoice_lifecycle_matches_lnd_semantics() {
+        let mut escrow = MemoryEscrow::new();
+        let (bolt11, preimage, hash) = escrow.create_hold_invoice("trade", 5_000).await.unwrap();
+        let hash = bytes_to_string(&hash);
+        assert_eq!(
+            decode_invoice(&bolt11).unwrap().amount_milli_satoshis(),
+            Some(5_000_000)
+        );
+        assert_eq!(escrow.invoice_state(&hash), Some(InvoiceState::Open));
+        assert_eq!(
+            escrow.hold_invoice_expiry_height(&hash).await.unwrap(),
+            None
+        );
+
+        escrow.accept(&hash);
+        assert_eq!(
+            escrow.hold_invoice_expiry_height(&hash).await.unwrap(),
+            Some(START_HEIGHT + HTLC_CLTV_DELTA)
+        );
+
+        let preimage = bytes_to_string(&preimage);
+        escrow.settle_hold_invoice(&preimage).await.unwrap();
+        assert_eq!(escrow.invoice_state(&hash), Some(InvoiceState::Settled));
+        let again = escrow.settle_hold_invoice(&preimage).await.unwrap_err();
+        assert!(again.to_string().contains("already settled"), "{again}");
+        assert!(escrow.cancel_hold_invoice(&hash).await.is_err());
+
+        let unknown = escrow.cancel_hold_invoice(&"ab".repeat(32)).await;
+        assert!(format!("{:?}", unknown.unwrap_err()).contains("code=NotFound"));
+    }
+
+    #[tokio::test]
+    async fn payments_follow_the_scripted_outcome_and_refuse_duplicates() {
+        let mut escrow = MemoryEscrow::new();
+        let (invoice, _, hash) = escrow.create_hold_invoice("payout", 1_000).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+
+        let wrong = escrow.send_payment(&invoice, 999, tx.clone()).await;
+        assert!(wrong.is_err());
+        assert_eq!(escrow.lookup_payment_status(&hash).await.unwrap(), None);
+
+        escrow.set_payment_outcome(PaymentStatus::Failed);
+        escrow
+            .send_payment(&invoice, 1_000, tx.clone())
+            .await
+            .unwrap();
+        let msg = rx.recv().await.unwrap();
+        assert_eq!(msg.payment.status, PaymentStatus::Failed as i32);
+
+        // A failed hash may be retried; a succeeded one may not.
+        escrow.set_payment_outcome(PaymentStatus::Succeeded);
+        escrow
+            .send_payment(&invoice, 1_000, tx.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            escrow.lookup_payment_status(&hash).await.unwrap(),
+            Some(PaymentStatus::Succeeded)
+        );
+        assert!(escrow.send_payment(&invoice, 1_000, tx).await.is_err());
+        assert_eq!(escrow.sent_payments().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn scripted_failures_come_before_the_default_outcome() {
+        let mut escrow = MemoryEscrow::new();
+        escrow.script_payments([PaymentStatus::Failed, PaymentStatus::InFlight]);
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let mut outcomes = Vec::new();
+        for _ in 0..3 {
+            let (invoice, _, hash) = escrow.create_hold_invoice("payout", 10).await.unwrap();
+            escrow.send_payment(&invoice, 10, tx.clone()).await.unwrap();
+            outcomes.push(escrow.lookup_payment_status(&hash).await.unwrap());
+        }
+        assert_eq!(
+            outcomes,
+            [
+                Some(PaymentStatus::Failed),
+                Some(PaymentStatus::InFlight),
+                Some(PaymentStatus::Succeeded)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn held_payments_stay_in_flight_until_the_payee_resolves_them() {
+        let mut escrow = MemoryEscrow::new();
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let (invoice, _, hash) = escrow.create_hold_invoice("swap", 10).await.unwrap();
+        let hash_hex = bytes_to_string(&hash);
+        escrow.hold_outgoing(&hash_hex);
+        escrow.send_payment(&invoice, 10, tx).await.unwrap();
+        assert_eq!(
+            escrow.lookup_payment_status(&hash).await.unwrap(),
+            Some(PaymentStatus::InFlight)
+        );
+        escrow.resolve_outgoing(&hash_hex, PaymentStatus::Succeeded);
+        assert_eq!(
+            escrow.lookup_payment_status(&hash).await.unwrap(),
+            Some(PaymentStatus::Succeeded)
+        );
+    }
+
+    #[tokio::test]
+    async fn subscribers_see_every_transition_including_auto_cancel() {
+        let mut escrow = MemoryEscrow::new();
+        let (_, _, r_hash) = escrow.create_hold_invoice("trade", 1_000).await.unwrap();
+        let hash = bytes_to_string(&r_hash);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        escrow.subscribe_invoice(r_hash, tx).await.unwrap();
+
+        escrow.accept(&hash);
+        // One block short of the hold-expiry window: still held.
+        escrow.mine(HTLC_CLTV_DELTA - HOLD_EXPIRY_DELTA - 1);
+        assert_eq!(escrow.invoice_state(&hash), Some(InvoiceState::Accepted));
+        escrow.mine(1);
+
+        let mut seen = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            seen.push(msg.state);
+        }
+        assert_eq!(
+            seen,
+            [
+                InvoiceState::Open,
+                InvoiceState::Accepted,
+                InvoiceState::Canceled
+            ]
+        );
+        assert_eq!(
+            escrow.hold_invoice_expiry_height(&hash).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn unpaid_invoices_expire_with_the_node_clock() {
+        let mut escrow = MemoryEscrow::new();
+        let (_, _, open) = escrow.create_hold_invoice("open", 1_000).await.unwrap();
+        let (_, _, held) = escrow.create_hold_invoice("held", 1_000).await.unwrap();
+        let (open, held) = (bytes_to_string(&open), bytes_to_string(&held));
+        escrow.accept(&held);
+
+        escrow.advance_time(INVOICE_EXPIRY_SECS - 1);
+        assert_eq!(escrow.invoice_state(&open), Some(InvoiceState::Open));
+        escrow.advance_time(1);
+        assert_eq!(escrow.invoice_state(&open), Some(InvoiceState::Canceled));
+        assert_eq!(escrow.invoice_state(&held), Some(InvoiceState::Accepted));
+    }
+
+    #[tokio::test]
+    async fn connect_lightning_hands_out_the_scoped_node() {
+        let node = MemoryEscrow::new();
+        let (_, _, hash) = node
+            .clone()
+            .create_hold_invoice("trade", 1_000)
+            .await
+            .unwrap();
+        let hash = bytes_to_string(&hash);
+
+        let state = with_node(node.clone(), async {
+            let mut ln = crate::escrow::connect_lightning().await.unwrap();
+            ln.cancel_hold_invoice(&hash).await.unwrap();
+            scoped_node().is_some()
+        })
+        .await;
+
+        assert!(state);
+        assert_eq!(node.invoice_state(&hash), Some(InvoiceState::Canceled));
+        assert!(scoped_node().is_none());
+    }
+}
@@ -1,9 +1,9 @@
+use crate::db::Crud;
+use crate::db::DbPool;
 use crate::util::{enqueue_order_msg, notify_taker_reputation};
 use crate::Result;
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::SqlitePool;
 use tracing::info;
 
 /// Advance an order whose seller has just paid the hold invoice.
@@ -24,7 +24,7 @@ use tracing::info;
 pub async fn hold_invoice_paid(
     hash: &str,
     request_id: Option<u64>,
-    pool: &SqlitePool,
+    pool: &DbPool,
     my_keys: &Keys,
 ) -> Result<(), MostroError> {
     let mut order = crate::db::find_order_by_hash(pool, hash)
@@ -174,7 +174,9 @@ pub async fn hold_invoice_paid(
     // and update on local database the status and new event id
     if let Ok(updated_order) = crate::util::update_order_event(my_keys, status, &order).await {
         // Update order on db
-        let _ = updated_order.update(pool).await;
+        if let Ok(updated_order) = updated_order.update(pool).await {
+            crate::rpc::events::order_status_changed(updated_order.id, &updated_order.status);
+        }
     }
 
     // Update the invoice_held_at field
@@ -185,7 +187,7 @@ pub async fn hold_invoice_paid(
     Ok(())
 }
 
-pub async fn hold_invoice_settlement(hash: &str, pool: &SqlitePool) -> Result<()> {
+pub async fn hold_invoice_settlement(hash: &str, pool: &DbPool) -> Result<()> {
     let order = crate::db::find_order_by_hash(pool, hash).await?;
     info!(
         "Order Id: {} - Invoice with hash: {} was settled!",
@@ -227,7 +229,7 @@ pub async fn hold_invoice_settlement(hash: &str, pool: &SqlitePool) -> Result<()
 ///   and the `error!` are the alarm.
 pub async fn hold_invoice_canceled(
     hash: &str,
-    pool: &SqlitePool,
+    pool: &DbPool,
     my_keys: &Keys,
 ) -> Result<(), MostroError> {
     let order = crate::db::find_order_by_hash(pool, hash)
@@ -288,6 +290,7 @@ pub async fn hold_invoice_canceled(
                     .update(pool)
                     .await
                     .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+                crate::rpc::events::order_status_changed(updated.id, &updated.status);
                 crate::scheduler::notify_users_canceled_order(
                     &updated,
                     &order,
@@ -312,22 +315,20 @@ pub async fn hold_invoice_canceled(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::DbPool;
     use mostro_core::order::{Kind as OrderKind, Status};
     use nostr_sdk::prelude::{Keys, Timestamp};
-    use sqlx::SqlitePool;
 
-    async fn create_test_pool() -> SqlitePool {
-        SqlitePool::connect(":memory:").await.unwrap()
+    async fn create_test_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn create_test_keys() -> Keys {
         Keys::generate()
     }
 
-    async fn create_migrated_pool() -> SqlitePool {
-        let pool = SqlitePool::connect(":memory:").await.unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        pool
+    async fn create_migrated_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     fn init_global_settings() {
@@ -336,7 +337,7 @@ mod tests {
 
     /// Insert a sell order carrying `hash` so `find_order_by_hash` resolves it.
     async fn insert_order_with_hash(
-        pool: &SqlitePool,
+        pool: &DbPool,
         hash: &str,
         status: Status,
         buyer_invoice: Option<String>,
@@ -446,7 +447,7 @@ mod tests {
         // scheduler would immediately deem the buyer's brand-new duty
         // expired and slash their bond.
         let stale_taken_at = Timestamp::now().as_secs() as i64 - 10_000;
-        sqlx::query("UPDATE orders SET taken_at = ?1 WHERE id = ?2")
+        sqlx::query("UPDATE orders SET taken_at = $1 WHERE id = $2")
             .bind(stale_taken_at)
             .bind(order.id)
             .execute(&pool)
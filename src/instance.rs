@@ -0,0 +1,531 @@
+//! Multi-tenant mode: several Mostro identities served by one process.
+//!
+//! Each `[[instances]]` entry adds a Mostro pubkey with its own fee, limits,
+//! accepted currencies, kind-0 metadata and info event. The `[nostr]` key
+//! remains the `default` instance. All of them share the relays, the database
+//! and the Lightning node; each order records the instance it was created on
+//! (`orders.instance`), and an instance never sees another one's orders.
+//!
+//! An inbound event is handled inside [`within`] the instance it was
+//! addressed to, so [`crate::util::get_keys`] and
+//! [`crate::config::settings::Settings::get_mostro`] answer for that instance
+//! without every handler having to thread it through. Work that runs outside
+//! a handler (scheduler jobs) resolves the instance from the order instead.
+//!
+//! After a key rotation the previous `[nostr]` key is kept as a *retired*
+//! `default` instance: it only serves the trades that were in flight when the
+//! key was rotated (see [`crate::rotation`]).
+
+use crate::config::settings::Settings;
+use crate::config::types::InstanceSettings;
+use crate::db::DbPool;
+use mostro_core::error::MostroError::{self, *};
+use mostro_core::error::ServiceError;
+use nostr_sdk::prelude::*;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+/// Instance id of the `[nostr]` identity, and the `orders.instance` default.
+pub const DEFAULT_INSTANCE: &str = "default";
+/// `outbox.sender` of messages signed with the retired `[nostr]` key.
+pub const RETIRED_SENDER: &str = "retired";
+
+/// One Mostro identity.
+#[derive(Debug)]
+pub struct Instance {
+    pub id: String,
+    pub keys: Keys,
+    /// Full settings, with this instance's `[mostro]` overrides applied.
+    pub settings: Arc<Settings>,
+    /// The pre-rotation key of the `default` instance.
+    pub retired: bool,
+}
+
+impl Instance {
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_INSTANCE
+    }
+
+    /// `outbox.sender` of the messages this instance signs.
+    pub fn sender_id(&self) -> &str {
+        if self.retired {
+            RETIRED_SENDER
+        } else {
+            &self.id
+        }
+    }
+}
+
+static INSTANCES: OnceLock<Vec<Instance>> = OnceLock::new();
+static RETIRED: OnceLock<Instance> = OnceLock::new();
+
+tokio::task_local! {
+    static CURRENT: &'static Instance;
+}
+
+fn config_err(message: String) -> MostroError {
+    MostroInternalErr(ServiceError::IOError(message))
+}
+
+/// Build the instance list from `settings`: the `default` instance first,
+/// then one per `[[instances]]` entry. Each entry's nsec is parsed and
+/// cleared from `settings`, like `[nostr].nsec_privkey`.
+pub fn build(settings: &mut Settings, primary: &Keys) -> Result<Vec<Instance>, MostroError> {
+    let entries: Vec<(InstanceSettings, Keys)> = std::mem::take(&mut settings.instances)
+        .into_iter()
+        .map(|mut entry| {
+            let keys = crate::config::secret::parse_mostro_keys(&entry.nsec_privkey)
+                .map_err(|e| config_err(format!("instance {:?}: {e}", entry.id)))?;
+            entry.nsec_privkey = Default::default();
+            Ok((entry, keys))
+        })
+        .collect::<Result<_, MostroError>>()?;
+
+    let mut ids = HashSet::from([DEFAULT_INSTANCE.to_string()]);
+    let mut pubkeys = HashSet::from([primary.public_key()]);
+    for (entry, keys) in &entries {
+        if entry.id.trim().is_empty() || !ids.insert(entry.id.clone()) {
+            return Err(config_err(format!(
+                "[[instances]] id {:?} is empty, reserved or used twice",
+                entry.id
+            )));
+        }
+        if !pubkeys.insert(keys.public_key()) {
+            return Err(config_err(format!(
+                "instance {:?} reuses the key of another instance",
+                entry.id
+            )));
+        }
+    }
+
+    // Instance settings keep the entry list itself (without secrets) so
+    // they describe the whole deployment, as the default's do.
+    settings.instances = entries.iter().map(|(entry, _)| entry.clone()).collect();
+    let mut instances = vec![Instance {
+        id: DEFAULT_INSTANCE.to_string(),
+        keys: primary.clone(),
+        settings: Arc::new(settings.clone()),
+        retired: false,
+    }];
+    for (entry, keys) in entries {
+        let mut instance_settings = settings.clone();
+        instance_settings.mostro = entry.apply(&settings.mostro);
+        instances.push(Instance {
+            id: entry.id,
+            keys,
+            settings: Arc::new(instance_settings),
+            retired: false,
+        });
+    }
+    Ok(instances)
+}
+
+/// The retired `default` instance for the `[nostr].previous_nsec_privkey`
+/// taken out of the settings before [`build`], if one was set.
+pub fn build_retired(
+    secret: SecretString,
+    instances: &[Instance],
+) -> Result<Option<Instance>, MostroError> {
+    if secret.expose_secret().is_empty() {
+        return Ok(None);
+    }
+    let keys = crate::config::secret::parse_mostro_keys(&secret)
+        .map_err(|e| config_err(format!("[nostr] previous_nsec_privkey: {e}")))?;
+    if instances
+        .iter()
+        .any(|instance| instance.keys.public_key() == keys.public_key())
+    {
+        return Err(config_err(
+            "[nostr] previous_nsec_privkey is a key still in use".to_string(),
+        ));
+    }
+    let default = instances
+        .iter()
+        .find(|instance| instance.is_default())
+        .ok_or_else(|| config_err("no default instance".to_string()))?;
+    Ok(Some(Instance {
+        id: DEFAULT_INSTANCE.to_string(),
+        keys,
+        settings: default.settings.clone(),
+        retired: true,
+    }))
+}
+
+/// Install the process-wide instance list and retired key.
+pub fn init(instances: Vec<Instance>, retired: Option<Instance>) -> Result<(), MostroError> {
+    INSTANCES
+        .set(instances)
+        .map_err(|_| config_err("Mostro instances already initialized".to_string()))?;
+    if let Some(retired) = retired {
+        RETIRED
+            .set(retired)
+            .map_err(|_| config_err("Retired Mostro key already initialized".to_string()))?;
+    }
+    Ok(())
+}
+
+/// The retired `default` key, while `[nostr].previous_nsec_privkey` is set.
+pub fn retired() -> Option<&'static Instance> {
+    RETIRED.get()
+}
+
+/// Every instance, `default` first. Empty before [`init`].
+pub fn all() -> &'static [Instance] {
+    INSTANCES.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// `true` when `[[instances]]` adds at least one identity.
+pub fn is_multi_tenant() -> bool {
+    all().len() > 1
+}
+
+/// `true` when more than one key serves this node: several instances, or a
+/// retired key next to the current one. Events and orders then need routing.
+pub fn has_several_keys() -> bool {
+    is_multi_tenant() || retired().is_some()
+}
+
+pub fn by_id(id: &str) -> Option<&'static Instance> {
+    all().iter().find(|instance| instance.id == id)
+}
+
+/// The instance signing outbox rows with `sender`.
+pub fn by_sender(sender: &str) -> Option<&'static Instance> {
+    if sender == RETIRED_SENDER {
+        retired()
+    } else {
+        by_id(sender)
+    }
+}
+
+/// The instance whose pubkey `event` is addressed to (its `p` tags),
+/// including the retired key.
+pub fn for_event(event: &Event) -> Option<&'static Instance> {
+    find_recipient(all(), event)
+        .or_else(|| retired().and_then(|r| find_recipient(std::slice::from_ref(r), event)))
+}
+
+fn find_recipient<'a>(instances: &'a [Instance], event: &Event) -> Option<&'a Instance> {
+    event.tags.public_keys().find_map(|pubkey| {
+        instances
+            .iter()
+            .find(|instance| instance.keys.public_key() == pubkey)
+    })
+}
+
+/// The instance the running handler serves, if any.
+pub fn current() -> Option<&'static Instance> {
+    CURRENT.try_with(|instance| *instance).ok()
+}
+
+/// Id of the running handler's instance; `default` outside a handler.
+pub fn current_id() -> &'static str {
+    current().map_or(DEFAULT_INSTANCE, |instance| instance.id.as_str())
+}
+
+/// Run `f` as `instance`, or unchanged when there is none.
+pub async fn within<F: Future>(instance: Option<&'static Instance>, f: F) -> F::Output {
+    match instance {
+        Some(instance) => CURRENT.scope(instance, f).await,
+        None => f.await,
+    }
+}
+
+/// `f` carrying the current instance along, for `tokio::spawn`.
+pub fn inherit<F: Future>(f: F) -> impl Future<Output = F::Output> {
+    within(current(), f)
+}
+
+/// `tokio::spawn` as the current instance. Every task a handler starts goes
+/// through here: a bare `tokio::spawn` loses the task-local instance and
+/// runs as `default`, signing and sending under the wrong key.
+pub fn spawn<F>(f: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(inherit(f))
+}
+
+fn db_err(e: sqlx::Error) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+/// Instance id stored on an order, `None` when the order does not exist.
+pub async fn order_instance_id(
+    pool: &DbPool,
+    order_id: Uuid,
+) -> Result<Option<String>, MostroError> {
+    sqlx::query_scalar("SELECT instance FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)
+}
+
+/// Order a dispute is about, `None` when the dispute does not exist.
+pub async fn dispute_order_id(
+    pool: &DbPool,
+    dispute_id: Uuid,
+) -> Result<Option<Uuid>, MostroError> {
+    sqlx::query_scalar("SELECT order_id FROM disputes WHERE id = $1")
+        .bind(dispute_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)
+}
+
+/// The instance owning an order; `default` for unknown orders or ids no
+/// longer configured, the retired key for trades still served under it.
+pub async fn of_order(pool: &DbPool, order_id: Uuid) -> Option<&'static Instance> {
+    if let Some(retired) = retired() {
+        if crate::rotation::serves(pool, order_id).await {
+            return Some(retired);
+        }
+    }
+    if !is_multi_tenant() {
+        return by_id(DEFAULT_INSTANCE);
+    }
+    let id = order_instance_id(pool, order_id).await.ok().flatten();
+    id.as_deref()
+        .and_then(by_id)
+        .or_else(|| by_id(DEFAULT_INSTANCE))
+}
+
+/// The instance owning the order with hold invoice `hash`, for invoice
+/// subscriptions that outlive the handler which started them.
+pub async fn of_hash(pool: &DbPool, hash: &str) -> Option<&'static Instance> {
+    if !has_several_keys() {
+        return None;
+    }
+    let order_id: Option<Uuid> = sqlx::query_scalar("SELECT id FROM orders WHERE hash = $1")
+        .bind(hash)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    of_order(pool, order_id?).await
+}
+
+/// The instance owning the order whose anti-abuse bond has hold invoice
+/// `hash`, for bond subscriptions that outlive the handler which started
+/// them.
+pub async fn of_bond_hash(pool: &DbPool, hash: &str) -> Option<&'static Instance> {
+    if !has_several_keys() {
+        return None;
+    }
+    let order_id: Option<Uuid> = sqlx::query_scalar("SELECT order_id FROM bonds WHERE hash = $1")
+        .bind(hash)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    of_order(pool, order_id?).await
+}
+
+/// Whether `instance_id` may act on `order_id`. An unknown order passes, so
+/// the handler reports it the way it always has.
+pub async fn owns_order(
+    pool: &DbPool,
+    instance_id: &str,
+    order_id: Uuid,
+) -> Result<bool, MostroError> {
+    Ok(order_instance_id(pool, order_id)
+        .await?
+        .is_none_or(|owner| owner == instance_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::InstanceSettings;
+
+    const NSEC_A: &str = "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd";
+
+    fn test_settings() -> Settings {
+        crate::app::context::test_utils::test_settings()
+    }
+
+    fn entry(id: &str, keys: &Keys) -> InstanceSettings {
+        InstanceSettings {
+            id: id.to_string(),
+            nsec_privkey: SecretString::from(keys.secret_key().to_bech32().unwrap()),
+            fee: Some(0.01),
+            fiat_currencies_accepted: Some(vec!["CUP".into()]),
+            pow: Some(12),
+            name: Some("Cuba".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_applies_overrides_and_clears_secrets() {
+        let primary = Keys::generate();
+        let cuba = Keys::generate();
+        let mut settings = test_settings();
+        settings.mostro.name = Some("Main".into());
+        settings.mostro.max_order_amount = 1_000;
+        settings.instances = vec![entry("cuba", &cuba)];
+
+        let instances = build(&mut settings, &primary).unwrap();
+        assert_eq!(instances.len(), 2);
+        assert!(instances[0].is_default());
+        assert_eq!(instances[0].keys.public_key(), primary.public_key());
+        assert_eq!(instances[0].settings.mostro.name.as_deref(), Some("Main"));
+
+        let tenant = &instances[1];
+        assert_eq!(tenant.id, "cuba");
+        assert_eq!(tenant.keys.public_key(), cuba.public_key());
+        assert_eq!(tenant.settings.mostro.fee, 0.01);
+        assert_eq!(tenant.settings.mostro.fiat_currencies_accepted, ["CUP"]);
+        assert_eq!(tenant.settings.mostro.pow, 12);
+        assert_eq!(tenant.settings.mostro.name.as_deref(), Some("Cuba"));
+        // Not overridden: inherited from `[mostro]`.
+        assert_eq!(tenant.settings.mostro.max_order_amount, 1_000);
+        assert!(settings.instances[0]
+            .nsec_privkey
+            .expose_secret()
+            .is_empty());
+    }
+
+    #[test]
+    fn build_rejects_duplicate_ids_and_keys() {
+        let primary = Keys::generate();
+        let other = Keys::generate();
+
+        let mut settings = test_settings();
+        settings.instances = vec![entry("a", &other), entry("a", &Keys::generate())];
+        assert!(build(&mut settings, &primary).is_err());
+
+        let mut settings = test_settings();
+        settings.instances = vec![entry(DEFAULT_INSTANCE, &other)];
+        assert!(build(&mut settings, &primary).is_err());
+
+        let mut settings = test_settings();
+        settings.instances = vec![entry("a", &primary)];
+        assert!(build(&mut settings, &primary).is_err());
+
+        let mut settings = test_settings();
+        let mut bad = entry("a", &other);
+        bad.nsec_privkey = SecretString::from("not-an-nsec");
+        settings.instances = vec![bad];
+        assert!(build(&mut settings, &primary).is_err());
+
+        let mut settings = test_settings();
+        settings.instances = vec![InstanceSettings {
+            id: "b".into(),
+            nsec_privkey: SecretString::from(NSEC_A),
+            ..Default::default()
+        }];
+        let built = build(&mut settings, &primary).unwrap();
+        assert_eq!(built[1].settings.mostro.fee, settings.mostro.fee);
+        assert!(settings.instances[0]
+            .nsec_privkey
+            .expose_secret()
+            .is_empty());
+    }
+
+    #[test]
+    fn retired_key_signs_as_its_own_sender() {
+        let primary = Keys::generate();
+        let old = Keys::generate();
+        let mut settings = test_settings();
+        let instances = build(&mut settings, &primary).unwrap();
+
+        assert!(build_retired(SecretString::default(), &instances)
+            .unwrap()
+            .is_none());
+        let in_use = SecretString::from(primary.secret_key().to_bech32().unwrap());
+        assert!(build_retired(in_use, &instances).is_err());
+
+        let secret = SecretString::from(old.secret_key().to_bech32().unwrap());
+        let retired = build_retired(secret, &instances).unwrap().unwrap();
+        assert!(retired.is_default() && retired.retired);
+        assert_eq!(retired.keys.public_key(), old.public_key());
+        assert_eq!(retired.sender_id(), RETIRED_SENDER);
+        assert_eq!(instances[0].sender_id(), DEFAULT_INSTANCE);
+    }
+
+    #[tokio::test]
+    async fn events_are_routed_by_recipient_and_handlers_see_their_instance() {
+        let primary = Keys::generate();
+        let cuba = Keys::generate();
+        let mut settings = test_settings();
+        settings.instances = vec![entry("cuba", &cuba)];
+        let instances: &'static [Instance] =
+            Box::leak(build(&mut settings, &primary).unwrap().into_boxed_slice());
+
+        let to_cuba = EventBuilder::new(Kind::PrivateDirectMessage, "")
+            .tag(Tag::public_key(cuba.public_key()))
+            .finalize(&Keys::generate())
+            .unwrap();
+        let found = find_recipient(instances, &to_cuba).unwrap();
+        assert_eq!(found.id, "cuba");
+        let stranger = EventBuilder::new(Kind::PrivateDirectMessage, "")
+            .tag(Tag::public_key(Keys::generate().public_key()))
+            .finalize(&Keys::generate())
+            .unwrap();
+        assert!(find_recipient(instances, &stranger).is_none());
+
+        assert_eq!(current_id(), DEFAULT_INSTANCE);
+        let (inside, spawned) = within(Some(found), async {
+            let spawned = spawn(async { current_id() }).await.unwrap();
+            (current_id(), spawned)
+        })
+        .await;
+        assert_eq!((inside, spawned), ("cuba", "cuba"));
+        assert_eq!(
+            within(Some(found), async { Settings::get_mostro().fee }).await,
+            0.01
+        );
+    }
+
+    #[tokio::test]
+    async fn orders_belong_to_the_instance_that_created_them() {
+        use crate::db::{create_order_as, Crud};
+        use mostro_core::order::Order;
+
+        let pool = crate::db::test_utils::migrated_pool().await;
+        let new_order = || Order {
+            id: Uuid::new_v4(),
+            kind: "sell".into(),
+            event_id: "e".into(),
+            status: "pending".into(),
+            payment_method: "ln".into(),
+            fiat_code: "USD".into(),
+            ..Default::default()
+        };
+
+        // A plain insert stays with `default`.
+        let plain = new_order().create(&pool).await.unwrap();
+        assert_eq!(
+            order_instance_id(&pool, plain.id).await.unwrap().as_deref(),
+            Some(DEFAULT_INSTANCE)
+        );
+
+        let order_id = create_order_as(&pool, new_order(), "cuba")
+            .await
+            .unwrap()
+            .id;
+
+        assert!(owns_order(&pool, "cuba", order_id).await.unwrap());
+        assert!(!owns_order(&pool, DEFAULT_INSTANCE, order_id).await.unwrap());
+        assert!(owns_order(&pool, DEFAULT_INSTANCE, Uuid::new_v4())
+            .await
+            .unwrap());
+
+        // A dispute id resolves to the order it is about.
+        let dispute = mostro_core::dispute::Dispute::new(order_id, "active".into())
+            .create(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            dispute_order_id(&pool, dispute.id).await.unwrap(),
+            Some(order_id)
+        );
+        assert_eq!(dispute_order_id(&pool, Uuid::new_v4()).await.unwrap(), None);
+    }
+}
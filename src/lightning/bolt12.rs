@@ -0,0 +1,204 @@
+//! BOLT12 offers as buyer payout destinations.
+//!
+//! An offer is reusable, so a buyer can leave one where an invoice goes and
+//! it never goes stale, however long the trade or its dispute takes. Each
+//! payout attempt asks the buyer's node for a fresh invoice for the exact
+//! payout amount ([`fetch_payout_invoice`], `fetchinvoice` on Core
+//! Lightning; LND speaks no BOLT12) and pays it only once it answers the
+//! offer and passes the rules of [`validate_payout_invoice`].
+//!
+//! [`validate_payout_invoice`]: super::invoice::validate_payout_invoice
+
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::NoChecksum;
+use bitcoin::constants::ChainHash;
+use bitcoin::Network;
+use lightning::offers::invoice::Bolt12Invoice;
+use lightning::offers::offer::{Amount, Offer};
+use mostro_core::prelude::*;
+use std::str::FromStr;
+
+use super::invoice::decode_invoice;
+use crate::config::settings::Settings;
+use crate::LN_STATUS;
+
+/// Human readable part of a bech32-encoded BOLT12 invoice.
+const INVOICE_HRP: &str = "lni";
+
+fn invalid() -> MostroError {
+    MostroInternalErr(ServiceError::InvoiceInvalidError)
+}
+
+/// The chain the node runs on, from the startup `GetInfo` probe. `None`
+/// (tests, unmapped networks) skips the chain checks, as for BOLT11.
+fn node_chain() -> Option<ChainHash> {
+    let network = match LN_STATUS.get()?.networks.first()?.as_str() {
+        "mainnet" => Network::Bitcoin,
+        "testnet" => Network::Testnet,
+        "signet" => Network::Signet,
+        "regtest" => Network::Regtest,
+        _ => return None,
+    };
+    Some(ChainHash::using_genesis_block(network))
+}
+
+pub fn decode_offer(offer: &str) -> Result<Offer, MostroError> {
+    Offer::from_str(offer.trim()).map_err(|_| invalid())
+}
+
+/// True when `destination` is a BOLT12 offer (`lno…`).
+pub fn is_offer(destination: &str) -> bool {
+    decode_offer(destination).is_ok()
+}
+
+/// Decodes a bech32 BOLT12 invoice (`lni…`), checking its signature.
+pub fn decode_bolt12_invoice(payment_request: &str) -> Result<Bolt12Invoice, MostroError> {
+    let parsed =
+        CheckedHrpstring::new::<NoChecksum>(payment_request.trim()).map_err(|_| invalid())?;
+    if !parsed.hrp().lowercase_char_iter().eq(INVOICE_HRP.chars()) {
+        return Err(invalid());
+    }
+    Bolt12Invoice::try_from(parsed.byte_iter().collect::<Vec<u8>>()).map_err(|_| invalid())
+}
+
+/// Payment hash and amount (msat, when set) of an invoice about to be paid,
+/// BOLT11 or BOLT12.
+pub fn payment_hash_and_amount(
+    payment_request: &str,
+) -> Result<([u8; 32], Option<u64>), MostroError> {
+    if let Ok(invoice) = decode_invoice(payment_request) {
+        let hash: &[u8; 32] = invoice.payment_hash().as_ref();
+        return Ok((*hash, invoice.amount_milli_satoshis()));
+    }
+    let invoice = decode_bolt12_invoice(payment_request)?;
+    Ok((invoice.payment_hash().0, Some(invoice.amount_msats())))
+}
+
+/// Checks an offer a buyer gives as payout destination: payable on the
+/// node's chain, not expired, and either open-amount or priced in sats at
+/// exactly `amount - fee` (when `amount` is known). Offers priced in a fiat
+/// currency or asking for a quantity are refused: the node could not fetch
+/// an invoice for an exact sats amount from them.
+pub fn validate_offer(
+    offer: &str,
+    amount: Option<u64>,
+    fee: Option<u64>,
+) -> Result<(), MostroError> {
+    let offer = decode_offer(offer)?;
+    if offer.is_expired() || offer.expects_quantity() {
+        return Err(invalid());
+    }
+    if node_chain().is_some_and(|chain| !offer.supports_chain(chain)) {
+        return Err(invalid());
+    }
+    match offer.amount() {
+        None => {}
+        Some(Amount::Bitcoin { amount_msats }) => {
+            let amount_sat = amount_msats / 1000;
+            if amount_sat < Settings::get_mostro().min_payment_amount as u64 {
+                return Err(invalid());
+            }
+            if let Some(amount) = amount.filter(|amount| *amount > 0) {
+                let expected = amount.checked_sub(fee.unwrap_or(0)).ok_or_else(invalid)?;
+                if amount_msats != expected * 1000 {
+                    return Err(invalid());
+                }
+            }
+        }
+        Some(Amount::Currency { .. }) => return Err(invalid()),
+    }
+    Ok(())
+}
+
+/// Checks an invoice the buyer's node returned for `offer` before it is
+/// paid: it must answer that offer for exactly `amount_sats`, and pass the
+/// payout rules — the node's chain, blinded paths within
+/// `max_final_cltv_expiry_delta`, not expired.
+pub fn validate_offer_invoice(
+    invoice: &Bolt12Invoice,
+    offer: &Offer,
+    amount_sats: u64,
+) -> Result<(), MostroError> {
+    if invoice.offer_id() != Some(offer.id()) || invoice.amount_msats() != amount_sats * 1000 {
+        return Err(invalid());
+    }
+    if node_chain().is_some_and(|chain| invoice.chain() != chain) {
+        return Err(invalid());
+    }
+    // The payinfo delta covers the whole blinded path, final hop included.
+    let max_cltv = Settings::get_ln().max_final_cltv_expiry_delta as u64;
+    if invoice
+        .payment_paths()
+        .iter()
+        .any(|path| path.payinfo.cltv_expiry_delta as u64 > max_cltv)
+    {
+        return Err(invalid());
+    }
+    if invoice.is_expired() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Fetches a fresh invoice of `amount_sats` from the issuer of `offer` and
+/// validates it, returning it ready to pay.
+pub async fn fetch_payout_invoice(offer: &str, amount_sats: u64) -> Result<String, MostroError> {
+    let decoded = decode_offer(offer)?;
+    let mut node = crate::escrow::connect_lightning().await?;
+    let payment_request = node.fetch_offer_invoice(offer.trim(), amount_sats).await?;
+    let invoice = decode_bolt12_invoice(&payment_request)?;
+    validate_offer_invoice(&invoice, &decoded, amount_sats)?;
+    Ok(payment_request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escrow::memory::{bolt11, offer, offer_invoice};
+    use crate::util::bytes_to_string;
+
+    fn init_settings() {
+        let _ = crate::config::MOSTRO_CONFIG.set(crate::app::context::test_utils::test_settings());
+    }
+
+    #[test]
+    fn offers_are_told_apart_from_other_destinations() {
+        init_settings();
+        let (lno, _) = offer(None);
+        assert!(is_offer(&lno));
+        assert!(!is_offer(&bolt11(&[1u8; 32], 1_000, "wallet")));
+        assert!(!is_offer("alice@example.com"));
+    }
+
+    #[test]
+    fn offer_amount_must_match_the_payout() {
+        init_settings();
+        let (open, _) = offer(None);
+        assert!(validate_offer(&open, Some(100_000), Some(1_000)).is_ok());
+
+        let (priced, _) = offer(Some(99_000));
+        assert!(validate_offer(&priced, Some(100_000), Some(1_000)).is_ok());
+        assert!(validate_offer(&priced, Some(0), None).is_ok());
+        assert!(validate_offer(&priced, Some(100_000), None).is_err());
+    }
+
+    #[test]
+    fn fetched_invoice_must_answer_the_offer_for_the_exact_amount() {
+        init_settings();
+        let (lno, key) = offer(None);
+        let decoded = decode_offer(&lno).unwrap();
+        let lni = offer_invoice(&lno, &key, 50_000, &[5u8; 32]);
+        let invoice = decode_bolt12_invoice(&lni).unwrap();
+        assert!(validate_offer_invoice(&invoice, &decoded, 50_000).is_ok());
+        assert!(validate_offer_invoice(&invoice, &decoded, 49_000).is_err());
+
+        let (other, _) = offer(None);
+        let other = decode_offer(&other).unwrap();
+        assert!(validate_offer_invoice(&invoice, &other, 50_000).is_err());
+
+        let (hash, amount) = payment_hash_and_amount(&lni).unwrap();
+        assert_eq!(bytes_to_string(&hash), bytes_to_string(&[5u8; 32]));
+        assert_eq!(amount, Some(50_000_000));
+        assert!(decode_bolt12_invoice(&lno).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod bolt12;
+pub mod cln;
 pub mod invoice;
 
 use crate::config::settings::Settings;
@@ -467,47 +469,6 @@ impl LndConnector {
         }
     }
 
-    /// Query the current status of a payment by its hash.
-    ///
-    /// Returns the LND `PaymentStatus` if the payment is found, or an error
-    /// if the payment cannot be tracked (e.g., unknown hash).
-    pub async fn check_payment_status(
-        &mut self,
-        payment_hash: &[u8],
-    ) -> Result<fedimint_tonic_lnd::lnrpc::payment::PaymentStatus, MostroError> {
-        let track_req = TrackPaymentRequest {
-            payment_hash: payment_hash.to_vec(),
-            no_inflight_updates: false,
-        };
-
-        let mut stream = self
-            .client
-            .router()
-            .track_payment_v2(track_req)
-            .await
-            .map_err(|e| MostroInternalErr(ServiceError::LnPaymentError(e.to_string())))?
-            .into_inner();
-
-        // Get the first (current) status update
-        match stream.message().await {
-            Ok(Some(payment)) => fedimint_tonic_lnd::lnrpc::payment::PaymentStatus::try_from(
-                payment.status,
-            )
-            .map_err(|_| {
-                MostroInternalErr(ServiceError::LnPaymentError(
-                    "Unknown payment status".to_string(),
-                ))
-            }),
-            Ok(None) => Err(MostroInternalErr(ServiceError::LnPaymentError(
-                "No payment status received (stream ended)".to_string(),
-            ))),
-            Err(e) => Err(MostroInternalErr(ServiceError::LnPaymentError(format!(
-                "Failed to get payment status: {}",
-                e
-            )))),
-        }
-    }
-
     pub async fn get_node_info(&mut self) -> Result<GetInfoResponse, MostroError> {
         let info = self.client.lightning().get_info(GetInfoRequest {}).await;
 
@@ -560,6 +521,7 @@ mod tests {
                     "nsec13as48eum93hkg7plv526r9gjpa0uc52zysqm93pmnkca9e69x6tsdjmdxd",
                 ),
                 relays: vec![],
+                previous_nsec_privkey: Default::default(),
             },
             mostro: Default::default(),
             lightning: Default::default(),
@@ -567,7 +529,10 @@ mod tests {
             expiration: Some(Default::default()),
             anti_abuse_bond: None,
             cashu: None,
+            swap: None,
             price: None,
+            metrics: None,
+            instances: Vec::new(),
         });
     }
 
@@ -796,13 +761,6 @@ mod offline_connector_tests {
         assert!(err.to_string().contains("code="));
     }
 
-    #[tokio::test]
-    async fn check_payment_status_surfaces_transport_error() {
-        init_test_settings();
-        let mut ln = offline_connector().await;
-        assert!(ln.check_payment_status(&[0u8; 32]).await.is_err());
-    }
-
     #[tokio::test]
     async fn send_payment_rejects_wrong_amount_before_paying() {
         init_test_settings();
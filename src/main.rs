@@ -1,19 +1,30 @@
 pub mod app;
+pub mod backup;
 mod bitcoin_price;
 pub mod cashu;
 pub mod cli;
 pub mod config;
 pub mod db;
+pub mod dispute_sla;
+#[cfg(test)]
+mod e2e;
 pub mod escrow;
 pub mod flow;
+pub mod instance;
 pub mod lightning;
 pub mod lnurl;
 pub mod messages;
+pub mod metrics;
 pub mod nip33;
+pub mod outbox;
 pub mod price;
+pub mod relay_health;
+pub mod reputation;
+pub mod rotation;
 pub mod rpc;
 pub mod scheduler;
 pub mod spam_gate;
+pub mod swap;
 pub mod util;
 
 /// Convenience alias mirroring the one `nostr` (pre-0.45) used to re-export
@@ -24,12 +35,9 @@ pub type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
 use crate::app::context::AppContext;
 use crate::app::{run, run_cashu};
 use crate::cli::settings_init;
-use crate::config::{
-    get_db_pool, Settings, DB_POOL, LN_STATUS, MESSAGE_QUEUES, MOSTRO_CONFIG, NOSTR_CLIENT,
-};
+use crate::config::{get_db_pool, Settings, DB_POOL, LN_STATUS, MOSTRO_CONFIG, NOSTR_CLIENT};
 use crate::db::find_held_invoices;
-use crate::lightning::LnStatus;
-use crate::lightning::LndConnector;
+use crate::escrow::connect_lightning;
 use crate::rpc::RpcServer;
 use nostr_sdk::prelude::*;
 use scheduler::start_scheduler;
@@ -59,7 +67,10 @@ async fn main() -> Result<()> {
         .init();
 
     // Init MOSTRO_SETTINGS oncelock with all settings variables from TOML file
-    settings_init()?;
+    // `mostrod backup` / `mostrod restore` run instead of the daemon.
+    if let Some(command) = settings_init()? {
+        return backup::run(command).await;
+    }
 
     // Build and install the multi-source price manager (spec §9 Phase 1).
     // Done immediately after settings load so every later subsystem
@@ -104,7 +115,15 @@ async fn main() -> Result<()> {
              support protocol v2. See https://github.com/MostroP2P/mostro/issues/786"
         );
     }
+    // Every `[[instances]]` identity, and a retired key still serving the
+    // trades in flight at its rotation, shares this subscription.
     let subscription = Filter::new()
+        .pubkeys(
+            instance::all()
+                .iter()
+                .chain(instance::retired())
+                .map(|i| i.keys.public_key()),
+        )
         .pubkey(mostro_keys.public_key())
         .kind(transport.event_kind())
         .limit(0);
@@ -121,45 +140,18 @@ async fn main() -> Result<()> {
     // Client subscription
     client.subscribe(subscription).await?;
 
-    // Publish NIP-01 kind 0 metadata event
-    let mostro_settings = Settings::get_mostro();
-    let mut has_metadata = false;
-    let mut metadata = Metadata::new();
-
-    if let Some(ref name) = mostro_settings.name {
-        metadata = metadata.name(name);
-        has_metadata = true;
-    }
-    if let Some(ref about) = mostro_settings.about {
-        metadata = metadata.about(about);
-        has_metadata = true;
-    }
-    if let Some(ref picture) = mostro_settings.picture {
-        if let Ok(url) = Url::parse(picture) {
-            metadata = metadata.picture(url);
-            has_metadata = true;
-        } else {
-            tracing::warn!("Invalid picture URL in settings: {}", picture);
-        }
-    }
-    if let Some(ref website) = mostro_settings.website {
-        if let Ok(url) = Url::parse(website) {
-            metadata = metadata.website(url);
-            has_metadata = true;
-        } else {
-            tracing::warn!("Invalid website URL in settings: {}", website);
-        }
+    // Publish NIP-01 kind 0 metadata event, one per Mostro instance
+    for instance in instance::all() {
+        publish_metadata(client, &instance.keys, &instance.settings.mostro).await;
     }
 
-    if has_metadata {
-        if let Ok(metadata_ev) = metadata.finalize(mostro_keys) {
-            let _ = client.send_event(&metadata_ev).await;
-            tracing::info!("Published NIP-01 kind 0 metadata event");
-        }
+    // Key rotation: hand off from the retired key and move the order book.
+    if let Err(e) = rotation::start(&get_db_pool(), client).await {
+        tracing::error!("Key rotation handoff failed: {e}");
     }
 
     // Cashu escrow mode (docs/cashu/, CF-5): run the daemon with NO Lightning
-    // node. Skip `LndConnector::new()` and the LN status probe entirely,
+    // node. Skip `connect_lightning()` and the LN status probe entirely,
     // connect the configured mint instead (fail fast if unreachable, mirroring
     // the LND-refusal behaviour), attach the client to the context, and hand
     // off to the Cashu event loop. Every trade action is still rejected with
@@ -184,19 +176,27 @@ async fn main() -> Result<()> {
             }
         };
 
-        // The admin gRPC server takes a Lightning client that Cashu mode never
-        // initialises, so it is not started here. Warn (rather than silently
-        // skip) when an operator has RPC enabled, so the missing API is not a
-        // surprise. Starting the LN-independent RPC subset in Cashu mode is a
-        // follow-up (see PR #828 review).
+        // Start the admin gRPC server without a Lightning client: dispute
+        // rulings (`CancelOrder` / `SettleOrder`) go through the mint.
         if RpcServer::is_enabled() {
-            tracing::warn!(
-                "[rpc].enabled = true but the admin gRPC server is NOT started in Cashu mode: \
-                 it requires a Lightning client Cashu mode does not initialise. Disable [rpc], \
-                 or run in Lightning mode, if you need the RPC API."
-            );
+            let rpc_server = RpcServer::new();
+            let rpc_keys = mostro_keys.clone();
+            let rpc_pool = get_db_pool();
+            let rpc_cashu_client = cashu_client.clone();
+
+            tokio::spawn(async move {
+                match rpc_server
+                    .start_cashu(rpc_keys, rpc_pool, rpc_cashu_client)
+                    .await
+                {
+                    Ok(_) => tracing::info!("RPC server started successfully"),
+                    Err(e) => tracing::error!("RPC server failed to start: {}", e),
+                }
+            });
         }
 
+        metrics::start(get_db_pool());
+
         // Warm the anti-spam gate exactly as the Lightning path does.
         install_spam_gate().await;
 
@@ -206,14 +206,8 @@ async fn main() -> Result<()> {
                 .expect("MOSTRO_CONFIG not initialized")
                 .clone(),
         );
-        let ctx = AppContext::new(
-            get_db_pool(),
-            client.clone(),
-            settings,
-            MESSAGE_QUEUES.queue_order_msg.clone(),
-            mostro_keys.clone(),
-        )
-        .with_cashu_client(cashu_client);
+        let ctx = AppContext::new(get_db_pool(), client.clone(), settings, mostro_keys.clone())
+            .with_cashu_client(cashu_client);
 
         start_scheduler(ctx.clone()).await;
 
@@ -221,9 +215,8 @@ async fn main() -> Result<()> {
         return run_cashu(ctx).await;
     }
 
-    let mut ln_client = LndConnector::new().await?;
-    let ln_status = ln_client.get_node_info().await?;
-    let ln_status = LnStatus::from_get_info_response(ln_status);
+    let mut ln_client = connect_lightning().await?;
+    let ln_status = ln_client.node_status().await?;
     if LN_STATUS.set(ln_status).is_err() {
         panic!("No connection to LND node - shutting down Mostro!");
     };
@@ -231,7 +224,8 @@ async fn main() -> Result<()> {
     // A failure here means no in-flight hold invoice is resubscribed for the
     // whole run, which is indistinguishable from "there were none" unless it is
     // said out loud — the same silent-failure shape this path already had.
-    match find_held_invoices(get_db_pool().as_ref()).await {
+    let pool = get_db_pool();
+    match find_held_invoices(&pool).await {
         Err(e) => tracing::error!(
             "Could not load held invoices to resubscribe; in-flight trades will \
              not be observed until the next restart: {e}"
@@ -252,7 +246,7 @@ async fn main() -> Result<()> {
                         }
                     };
                     tracing::info!("Resubscribing order id - {}", invoice.id);
-                    if let Err(e) = invoice_subscribe(r_hash, None).await {
+                    if let Err(e) = invoice_subscribe(&pool, r_hash, None).await {
                         tracing::error!("Ln node error {e}")
                     }
                 }
@@ -273,7 +267,9 @@ async fn main() -> Result<()> {
         let rpc_server = RpcServer::new();
         let rpc_keys = mostro_keys.clone();
         let rpc_pool = get_db_pool();
-        let rpc_ln_client = Arc::new(tokio::sync::Mutex::new(ln_client.clone()));
+        // The event loop owns `ln_client`; the admin server gets its own
+        // connection to the same node.
+        let rpc_ln_client = Arc::new(tokio::sync::Mutex::new(connect_lightning().await?));
 
         tokio::spawn(async move {
             match rpc_server.start(rpc_keys, rpc_pool, rpc_ln_client).await {
@@ -283,6 +279,8 @@ async fn main() -> Result<()> {
         });
     }
 
+    metrics::start(get_db_pool());
+
     // Install the protocol-v2 anti-spam gate and warm its active-trade-pubkey
     // cache before the event loop starts (mode-agnostic — both `run` and
     // `run_cashu` consult it for kind-14 events).
@@ -295,19 +293,56 @@ async fn main() -> Result<()> {
             .expect("MOSTRO_CONFIG not initialized")
             .clone(),
     );
-    let ctx = AppContext::new(
-        get_db_pool(),
-        client.clone(),
-        settings,
-        MESSAGE_QUEUES.queue_order_msg.clone(),
-        mostro_keys.clone(),
-    );
+    let ctx = AppContext::new(get_db_pool(), client.clone(), settings, mostro_keys.clone());
 
     // Start scheduler for tasks
     start_scheduler(ctx.clone()).await;
 
     // Run the Mostro and be happy!!
-    run(ctx, &mut ln_client).await
+    run(ctx, ln_client.as_mut()).await
+}
+
+/// Publish the NIP-01 kind 0 metadata event of one Mostro identity, if its
+/// settings define any metadata.
+async fn publish_metadata(
+    client: &Client,
+    keys: &Keys,
+    mostro_settings: &crate::config::types::MostroSettings,
+) {
+    let mut has_metadata = false;
+    let mut metadata = Metadata::new();
+
+    if let Some(ref name) = mostro_settings.name {
+        metadata = metadata.name(name);
+        has_metadata = true;
+    }
+    if let Some(ref about) = mostro_settings.about {
+        metadata = metadata.about(about);
+        has_metadata = true;
+    }
+    if let Some(ref picture) = mostro_settings.picture {
+        if let Ok(url) = Url::parse(picture) {
+            metadata = metadata.picture(url);
+            has_metadata = true;
+        } else {
+            tracing::warn!("Invalid picture URL in settings: {}", picture);
+        }
+    }
+    if let Some(ref website) = mostro_settings.website {
+        if let Ok(url) = Url::parse(website) {
+            metadata = metadata.website(url);
+            has_metadata = true;
+        } else {
+            tracing::warn!("Invalid website URL in settings: {}", website);
+        }
+    }
+
+    if has_metadata {
+        if let Ok(metadata_ev) = metadata.finalize(keys) {
+            let _ = client.send_event(&metadata_ev).await;
+            tracing::info!("Published NIP-01 kind 0 metadata event");
+        }
+    }
 }
 
 /// Install the protocol-v2 anti-spam gate and warm its active-trade-pubkey
@@ -0,0 +1,562 @@
+//! Prometheus metrics endpoint (`[metrics]` settings block).
+//!
+//! Counters are process-wide atomics bumped on the hot paths (`accept_event`,
+//! the payment retry job, the RPC password rate limiter). Gauges are read at
+//! scrape time — orders and bonds by state straight from the database, the
+//! outbound message queue depths, the price providers' circuit breakers and
+//! the relays' health scores —
+//! so nothing has to be kept in sync with the state it describes.
+
+use crate::config::settings::Settings;
+use crate::config::types::MetricsSettings;
+use crate::config::MESSAGE_QUEUES;
+use crate::db::DbPool;
+use crate::price::PriceManager;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use tracing::{error, info};
+
+/// Why `accept_event` skipped a relay event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    Pow,
+    Kind,
+    InvalidSignature,
+    Replay,
+    SpamGate,
+    Decrypt,
+    Unwrap,
+    Stale,
+    MissingInnerSignature,
+    TradeIndex,
+    InnerVerify,
+    NoAction,
+}
+
+impl DropReason {
+    const ALL: [DropReason; 12] = [
+        DropReason::Pow,
+        DropReason::Kind,
+        DropReason::InvalidSignature,
+        DropReason::Replay,
+        DropReason::SpamGate,
+        DropReason::Decrypt,
+        DropReason::Unwrap,
+        DropReason::Stale,
+        DropReason::MissingInnerSignature,
+        DropReason::TradeIndex,
+        DropReason::InnerVerify,
+        DropReason::NoAction,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            DropReason::Pow => "pow",
+            DropReason::Kind => "kind",
+            DropReason::InvalidSignature => "invalid_signature",
+            DropReason::Replay => "replay",
+            DropReason::SpamGate => "spam_gate",
+            DropReason::Decrypt => "decrypt",
+            DropReason::Unwrap => "unwrap",
+            DropReason::Stale => "stale",
+            DropReason::MissingInnerSignature => "missing_inner_signature",
+            DropReason::TradeIndex => "trade_index",
+            DropReason::InnerVerify => "inner_verify",
+            DropReason::NoAction => "no_action",
+        }
+    }
+}
+
+static EVENTS_ACCEPTED: AtomicU64 = AtomicU64::new(0);
+static EVENTS_DROPPED: [AtomicU64; DropReason::ALL.len()] =
+    [const { AtomicU64::new(0) }; DropReason::ALL.len()];
+static PAYMENT_RETRIES: AtomicU64 = AtomicU64::new(0);
+static RPC_RATE_LIMITED: AtomicU64 = AtomicU64::new(0);
+
+/// An event passed every `accept_event` check and is being dispatched.
+pub fn event_accepted() {
+    EVENTS_ACCEPTED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// `accept_event` skipped an event for `reason`.
+pub fn event_dropped(reason: DropReason) {
+    EVENTS_DROPPED[reason as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// `job_retry_failed_payments` re-attempted a failed buyer payout.
+pub fn payment_retried() {
+    PAYMENT_RETRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The RPC password rate limiter refused a request.
+pub fn rpc_rate_limited() {
+    RPC_RATE_LIMITED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Upper bounds (seconds) of the latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 120.0];
+
+/// Fixed-bucket histogram on atomics; `counts[i]` holds observations in
+/// bucket `i` alone and is accumulated at render time.
+struct Histogram {
+    counts: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            counts: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len() + 1],
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, secs: f64) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add((secs.max(0.0) * 1e6) as u64, Ordering::Relaxed);
+    }
+
+    /// `labels` is the rendered label list without braces, or empty.
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let (bucket_prefix, series) = if labels.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (format!("{labels},"), format!("{{{labels}}}"))
+        };
+        let mut cumulative = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            cumulative += count.load(Ordering::Relaxed);
+            let le = LATENCY_BUCKETS
+                .get(i)
+                .map_or_else(|| "+Inf".to_string(), |b| b.to_string());
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{bucket_prefix}le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{name}_sum{series} {sum}");
+        let _ = writeln!(out, "{name}_count{series} {cumulative}");
+    }
+}
+
+static OUTBOX_DELIVERY_SECONDS: Histogram = Histogram::new();
+/// Send latency of each relay, by url; an entry appears on its first answer.
+static RELAY_SEND_SECONDS: LazyLock<Mutex<BTreeMap<String, Histogram>>> =
+    LazyLock::new(Mutex::default);
+
+/// The outbox delivered a message `queued_secs` after it was enqueued.
+pub fn outbox_delivered(queued_secs: f64) {
+    OUTBOX_DELIVERY_SECONDS.observe(queued_secs);
+}
+
+/// `relay` answered one publish (accepted, refused or timed out) after `secs`.
+pub fn relay_answered(relay: &str, secs: f64) {
+    RELAY_SEND_SECONDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(relay.to_string())
+        .or_insert_with(Histogram::new)
+        .observe(secs);
+}
+
+/// Gauges read at scrape time.
+#[derive(Debug, Default)]
+struct Snapshot {
+    orders_by_status: Vec<(String, i64)>,
+    bonds_by_state: Vec<(String, i64)>,
+    queue_depths: Vec<(&'static str, usize)>,
+    outbox_by_status: Vec<(String, i64)>,
+    /// `(provider, available, consecutive_failures)`
+    providers: Vec<(String, bool, u32)>,
+    /// `(relay, score, demoted)`
+    relays: Vec<(String, f64, bool)>,
+}
+
+const ORDERS_BY_STATUS: &str =
+    "SELECT status, COUNT(*) FROM orders GROUP BY status ORDER BY status";
+const BONDS_BY_STATE: &str = "SELECT state, COUNT(*) FROM bonds GROUP BY state ORDER BY state";
+
+async fn count_by(pool: &DbPool, sql: &'static str) -> Vec<(String, i64)> {
+    match sqlx::query_as::<_, (String, i64)>(sql)
+        .fetch_all(pool)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("metrics: `{sql}` failed: {e}");
+            Vec::new()
+        }
+    }
+}
+
+async fn snapshot(pool: &DbPool) -> Snapshot {
+    let queues = &*MESSAGE_QUEUES;
+    let queue_depths = vec![("order_rate", queues.queue_order_rate.read().await.len())];
+    let now = chrono::Utc::now().timestamp();
+    let providers = PriceManager::global()
+        .map(|m| {
+            m.provider_health(now)
+                .into_iter()
+                .map(|(id, available, failures)| (id.to_string(), available, failures))
+                .collect()
+        })
+        .unwrap_or_default();
+    let configured = match crate::util::get_nostr_client() {
+        Ok(client) => crate::relay_health::relay_urls(client).await,
+        Err(_) => Vec::new(),
+    };
+    let relays = crate::relay_health::global()
+        .snapshot(&configured)
+        .into_iter()
+        .map(|(url, stats)| (url.to_string(), stats.score, stats.is_demoted(now)))
+        .collect();
+
+    Snapshot {
+        orders_by_status: count_by(pool, ORDERS_BY_STATUS).await,
+        bonds_by_state: count_by(pool, BONDS_BY_STATE).await,
+        queue_depths,
+        outbox_by_status: crate::outbox::count_by_status(pool)
+            .await
+            .unwrap_or_else(|e| {
+                error!("metrics: counting outbox rows failed: {e}");
+                Vec::new()
+            }),
+        providers,
+        relays,
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Prometheus text exposition format (version 0.0.4).
+fn render(snapshot: &Snapshot) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "mostro_events_accepted_total",
+        "counter",
+        "Relay events that passed validation and were dispatched.",
+    );
+    let _ = writeln!(
+        out,
+        "mostro_events_accepted_total {}",
+        EVENTS_ACCEPTED.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "mostro_events_dropped_total",
+        "counter",
+        "Relay events skipped before dispatch, by reason.",
+    );
+    for reason in DropReason::ALL {
+        let _ = writeln!(
+            out,
+            "mostro_events_dropped_total{{reason=\"{}\"}} {}",
+            reason.label(),
+            EVENTS_DROPPED[reason as usize].load(Ordering::Relaxed)
+        );
+    }
+
+    header(
+        &mut out,
+        "mostro_payment_retries_total",
+        "counter",
+        "Failed buyer payouts re-attempted by the retry job.",
+    );
+    let _ = writeln!(
+        out,
+        "mostro_payment_retries_total {}",
+        PAYMENT_RETRIES.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "mostro_rpc_rate_limited_total",
+        "counter",
+        "Admin RPC requests refused by the rate limiter.",
+    );
+    let _ = writeln!(
+        out,
+        "mostro_rpc_rate_limited_total {}",
+        RPC_RATE_LIMITED.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "mostro_outbox_delivery_seconds",
+        "histogram",
+        "Time from enqueue to delivery of outbox messages.",
+    );
+    OUTBOX_DELIVERY_SECONDS.render(&mut out, "mostro_outbox_delivery_seconds", "");
+
+    header(
+        &mut out,
+        "mostro_orders",
+        "gauge",
+        "Orders in the database, by status.",
+    );
+    for (status, count) in &snapshot.orders_by_status {
+        let _ = writeln!(out, "mostro_orders{{status=\"{status}\"}} {count}");
+    }
+
+    header(
+        &mut out,
+        "mostro_bonds",
+        "gauge",
+        "Anti-abuse bonds in the database, by state.",
+    );
+    for (state, count) in &snapshot.bonds_by_state {
+        let _ = writeln!(out, "mostro_bonds{{state=\"{state}\"}} {count}");
+    }
+
+    header(
+        &mut out,
+        "mostro_message_queue_depth",
+        "gauge",
+        "Outbound messages waiting in each queue.",
+    );
+    for (queue, depth) in &snapshot.queue_depths {
+        let _ = writeln!(
+            out,
+            "mostro_message_queue_depth{{queue=\"{queue}\"}} {depth}"
+        );
+    }
+
+    header(
+        &mut out,
+        "mostro_outbox_messages",
+        "gauge",
+        "Outbound messages in the durable outbox, by status.",
+    );
+    for (status, count) in &snapshot.outbox_by_status {
+        let _ = writeln!(out, "mostro_outbox_messages{{status=\"{status}\"}} {count}");
+    }
+
+    header(
+        &mut out,
+        "mostro_price_provider_up",
+        "gauge",
+        "1 when the price provider's circuit breaker is closed.",
+    );
+    for (provider, available, _) in &snapshot.providers {
+        let _ = writeln!(
+            out,
+            "mostro_price_provider_up{{provider=\"{provider}\"}} {}",
+            u8::from(*available)
+        );
+    }
+    header(
+        &mut out,
+        "mostro_price_provider_consecutive_failures",
+        "gauge",
+        "Failed polls in a row for each price provider.",
+    );
+    for (provider, _, failures) in &snapshot.providers {
+        let _ = writeln!(
+            out,
+            "mostro_price_provider_consecutive_failures{{provider=\"{provider}\"}} {failures}"
+        );
+    }
+
+    header(
+        &mut out,
+        "mostro_relay_score",
+        "gauge",
+        "Moving average of recent publish outcomes per relay (1 = all accepted).",
+    );
+    for (relay, score, _) in &snapshot.relays {
+        let _ = writeln!(out, "mostro_relay_score{{relay=\"{relay}\"}} {score:.3}");
+    }
+    header(
+        &mut out,
+        "mostro_relay_send_seconds",
+        "histogram",
+        "Time a relay took to answer one publish, by relay.",
+    );
+    for (relay, histogram) in RELAY_SEND_SECONDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+    {
+        histogram.render(
+            &mut out,
+            "mostro_relay_send_seconds",
+            &format!("relay=\"{relay}\""),
+        );
+    }
+    header(
+        &mut out,
+        "mostro_relay_demoted",
+        "gauge",
+        "1 while a relay is skipped after consecutive failures.",
+    );
+    for (relay, _, demoted) in &snapshot.relays {
+        let _ = writeln!(
+            out,
+            "mostro_relay_demoted{{relay=\"{relay}\"}} {}",
+            u8::from(*demoted)
+        );
+    }
+
+    out
+}
+
+async fn metrics_handler(State(pool): State<Arc<DbPool>>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render(&snapshot(&pool).await),
+    )
+}
+
+/// Serve `GET /metrics` until the process exits.
+pub async fn serve(
+    settings: MetricsSettings,
+    pool: Arc<DbPool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = format!("{}:{}", settings.listen_address, settings.port);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    info!("Starting metrics endpoint on {}", addr);
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(pool);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Spawn the metrics endpoint when `[metrics]` is present and enabled.
+pub fn start(pool: Arc<DbPool>) {
+    let Some(settings) = Settings::get_metrics().filter(|m| m.enabled).cloned() else {
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = serve(settings, pool).await {
+            error!("Metrics endpoint failed: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_emits_counters_and_gauges() {
+        event_dropped(DropReason::Replay);
+        let snapshot = Snapshot {
+            orders_by_status: vec![("pending".into(), 3), ("success".into(), 1)],
+            bonds_by_state: vec![("locked".into(), 2)],
+            queue_depths: vec![("order_rate", 4)],
+            outbox_by_status: vec![("dead".into(), 1)],
+            providers: vec![("yadio".into(), false, 5)],
+            relays: vec![("wss://relay.example/".into(), 0.25, true)],
+        };
+        let text = render(&snapshot);
+
+        assert!(text.contains("# TYPE mostro_events_dropped_total counter"));
+        // Counters are process-wide and other tests bump them too.
+        let replay = text
+            .lines()
+            .find_map(|l| l.strip_prefix("mostro_events_dropped_total{reason=\"replay\"} "))
+            .unwrap();
+        assert!(replay.parse::<u64>().unwrap() >= 1);
+        assert!(text.contains("mostro_orders{status=\"pending\"} 3\n"));
+        assert!(text.contains("mostro_bonds{state=\"locked\"} 2\n"));
+        assert!(text.contains("mostro_message_queue_depth{queue=\"order_rate\"} 4\n"));
+        assert!(text.contains("mostro_outbox_messages{status=\"dead\"} 1\n"));
+        assert!(text.contains("mostro_price_provider_up{provider=\"yadio\"} 0\n"));
+        assert!(text.contains("mostro_price_provider_consecutive_failures{provider=\"yadio\"} 5\n"));
+        assert!(text.contains("mostro_relay_score{relay=\"wss://relay.example/\"} 0.250\n"));
+        assert!(text.contains("mostro_relay_demoted{relay=\"wss://relay.example/\"} 1\n"));
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let h = Histogram::new();
+        h.observe(0.2);
+        h.observe(3.0);
+        h.observe(500.0);
+        let mut out = String::new();
+        h.render(&mut out, "x", "p=\"a\"");
+        assert!(out.contains("x_bucket{p=\"a\",le=\"0.1\"} 0\n"));
+        assert!(out.contains("x_bucket{p=\"a\",le=\"0.25\"} 1\n"));
+        assert!(out.contains("x_bucket{p=\"a\",le=\"5\"} 2\n"));
+        assert!(out.contains("x_bucket{p=\"a\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("x_sum{p=\"a\"} 503.2\n"));
+        assert!(out.contains("x_count{p=\"a\"} 3\n"));
+
+        let mut out = String::new();
+        h.render(&mut out, "x", "");
+        assert!(out.contains("x_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("x_count 3\n"));
+    }
+
+    #[test]
+    fn relay_latency_is_rendered_per_relay() {
+        relay_answered("wss://slow.metrics.example/", 3.0);
+        relay_answered("wss://fast.metrics.example/", 0.07);
+        let text = render(&Snapshot::default());
+        assert!(text.contains(
+            "mostro_relay_send_seconds_bucket{relay=\"wss://slow.metrics.example/\",le=\"2.5\"} 0\n"
+        ));
+        assert!(text.contains(
+            "mostro_relay_send_seconds_count{relay=\"wss://slow.metrics.example/\"} 1\n"
+        ));
+        assert!(text.contains(
+            "mostro_relay_send_seconds_bucket{relay=\"wss://fast.metrics.example/\",le=\"0.1\"} 1\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn snapshot_counts_orders_by_status() {
+        let pool = crate::db::test_utils::migrated_pool().await;
+        for status in ["pending", "pending", "active"] {
+            sqlx::query(
+                "INSERT INTO orders (id, kind, event_id, status, premium, payment_method, \
+                 amount, fiat_code, fiat_amount, created_at, expires_at) \
+                 VALUES ($1, 'sell', $2, $3, 0, 'ln', 100, 'USD', 10, 0, 0)",
+            )
+            .bind(uuid::Uuid::new_v4())
+            .bind(uuid::Uuid::new_v4().simple().to_string())
+            .bind(status)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let snapshot = snapshot(&pool).await;
+        assert_eq!(
+            snapshot.orders_by_status,
+            vec![("active".to_string(), 1), ("pending".to_string(), 2)]
+        );
+        assert!(snapshot.bonds_by_state.is_empty());
+        assert_eq!(snapshot.queue_depths.len(), 1);
+    }
+
+    #[test]
+    fn settings_default_to_disabled_on_localhost() {
+        let settings: MetricsSettings = toml::from_str("enabled = true").unwrap();
+        assert!(settings.enabled);
+        assert_eq!(settings.listen_address, "127.0.0.1");
+        assert_eq!(settings.port, 9184);
+        assert!(!MetricsSettings::default().enabled);
+    }
+}
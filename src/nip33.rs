@@ -1,7 +1,8 @@
-use crate::config::constants::NOSTR_EXCHANGE_RATES_EVENT_KIND;
+use crate::config::constants::{NOSTR_EXCHANGE_RATES_EVENT_KIND, NOSTR_KEY_HANDOFF_EVENT_KIND};
 use crate::config::settings::Settings;
 use crate::config::types::{BondApplyTo, MostroSettings};
 use crate::lightning::LnStatus;
+use crate::reputation::Reputation;
 use crate::util::{get_expiration_timestamp_for_kind, get_keys, monotonic_dispute_event_timestamp};
 use crate::LN_STATUS;
 use mostro_core::prelude::*;
@@ -254,6 +255,27 @@ pub fn new_exchange_rates_event(
     )
 }
 
+/// Creates a key rotation handoff event (kind 38387)
+///
+/// # Arguments
+///
+/// * `keys` - The retired Mostro keys signing the handoff
+/// * `content` - The content of the event
+/// * `extra_tags` - Tags naming the new key and its proof
+///
+/// # Returns
+/// Returns a new handoff event, replaceable per retired key (`d` tag)
+pub fn new_key_handoff_event(keys: &Keys, content: &str, extra_tags: Tags) -> Result<Event, Error> {
+    create_event(
+        keys,
+        content,
+        keys.public_key().to_hex(),
+        extra_tags,
+        NOSTR_KEY_HANDOFF_EVENT_KIND,
+        None,
+    )
+}
+
 /// Create a rating tag
 ///
 /// # Arguments
@@ -261,21 +283,21 @@ pub fn new_exchange_rates_event(
 /// * `reputation_data` - The reputation data of the user
 ///
 /// # Returns a json string
-fn create_rating_tag(reputation_data: Option<(f64, i64, i64)>) -> String {
+fn create_rating_tag(reputation_data: Option<&Reputation>) -> String {
     if let Some(data) = reputation_data {
-        const SECONDS_IN_DAY: u64 = 86400;
-        // If operating day is 0, it means the user is new and we don't have a valid reputation data
-        let days = if data.2 != 0 {
-            let now = Timestamp::now();
-            (now.as_secs() - data.2 as u64) / SECONDS_IN_DAY
-        } else {
-            0
-        };
-
-        // Create the json string
+        // Create the json string; the weighted profile (`score`, `trades`,
+        // `volume`, `disputes_lost`) follows the fields clients already read
         let json_data = json!([
         "rating",
-            {"total_reviews": data.1, "total_rating": data.0, "days": days}
+            {
+                "total_reviews": data.total_reviews,
+                "total_rating": data.total_rating,
+                "days": data.operating_days(),
+                "score": (data.score * 100.0).round() / 100.0,
+                "trades": data.trades,
+                "volume": data.volume_band,
+                "disputes_lost": data.disputes_lost,
+            }
         ]);
         json_data.to_string()
     } else {
@@ -409,10 +431,7 @@ fn create_source_tag(
 /// # Arguments
 ///
 /// * `order` - The order to transform into tags
-/// * `reputation_data` - Optional reputation data tuple containing:
-///   - `f64`: Total rating score
-///   - `i64`: Total number of reviews
-///   - `i64`: Unix timestamp of first operation (used to calculate operating days)
+/// * `reputation_data` - Optional reputation of the maker, see [`Reputation`]
 ///
 /// # Returns
 ///
@@ -464,7 +483,7 @@ fn nip40_expiration_for_order(order: &Order, published_status: Status) -> i64 {
 
 pub fn order_to_tags(
     order: &Order,
-    reputation_data: Option<(f64, i64, i64)>,
+    reputation_data: Option<Reputation>,
     mostro_pubkey: Option<&str>,
 ) -> Result<Option<Tags>, MostroError> {
     // Position of the tags in the list
@@ -519,7 +538,7 @@ pub fn order_to_tags(
         if reputation_data.is_some() {
             tags.insert(
                 RATING_TAG_INDEX,
-                Tag::custom("rating", vec![create_rating_tag(reputation_data)]),
+                Tag::custom("rating", vec![create_rating_tag(reputation_data.as_ref())]),
             );
         }
         // Add source tag if available
@@ -722,6 +741,7 @@ mod tests {
     use crate::app::context::test_utils::test_settings;
     use crate::config::MOSTRO_CONFIG;
     use crate::lightning::LnStatus;
+    use crate::reputation::Reputation;
     use mostro_core::prelude::*;
     use nostr_sdk::prelude::*;
 
@@ -1510,13 +1530,27 @@ mod tests {
     fn create_rating_tag_serializes_reputation_or_empty_object() {
         // Established user: days computed from created_at.
         let created_at = Timestamp::now().as_secs() as i64 - 2 * 86_400;
-        let json = super::create_rating_tag(Some((4.5, 12, created_at)));
+        let reputation = Reputation {
+            total_rating: 4.5,
+            total_reviews: 12,
+            created_at,
+            score: 4.256,
+            trades: 15,
+            volume_band: "1m-10m",
+            feedback: vec![("fast-payment", 3)],
+            disputes_lost: 1,
+        };
+        let json = super::create_rating_tag(Some(&reputation));
         assert!(json.contains("\"total_reviews\":12"));
         assert!(json.contains("\"total_rating\":4.5"));
         assert!(json.contains("\"days\":2"));
+        assert!(json.contains("\"score\":4.26"));
+        assert!(json.contains("\"trades\":15"));
+        assert!(json.contains("\"volume\":\"1m-10m\""));
+        assert!(json.contains("\"disputes_lost\":1"));
 
         // Brand-new user: created_at == 0 → days must be 0.
-        let json_new = super::create_rating_tag(Some((0.0, 0, 0)));
+        let json_new = super::create_rating_tag(Some(&Reputation::unrated()));
         assert!(json_new.contains("\"days\":0"));
 
         // No reputation data at all → placeholder object.
@@ -1631,7 +1665,12 @@ mod tests {
         let _ = crate::LN_STATUS.set(make_ln_status());
         let order = make_pending_order();
 
-        let tags = order_to_tags(&order, Some((4.2, 7, 0)), Some(TEST_MOSTRO_PUBKEY))
+        let reputation = Reputation {
+            total_rating: 4.2,
+            total_reviews: 7,
+            ..Reputation::unrated()
+        };
+        let tags = order_to_tags(&order, Some(reputation), Some(TEST_MOSTRO_PUBKEY))
             .expect("order_to_tags must not error")
             .expect("pending order must produce tags");
 
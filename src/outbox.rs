@@ -0,0 +1,887 @@
+//! Durable outbox for outbound protocol messages (`outbox` table).
+//!
+//! The `enqueue_*` helpers in [`crate::util`] insert a pending row for every
+//! message before the handler moves on; the dispatcher only reads due rows and
+//! sends them. Delivery is at-least-once: a row is deleted only after
+//! `send_dm` succeeded, so a crash in between resends it on restart.
+//!
+//! Sends run in parallel on a bounded worker pool ([`Dispatcher`]), most
+//! urgent [`Priority`] first. A failed attempt reschedules the row with
+//! exponential backoff. Messages to one destination go out in enqueue order —
+//! a row is not sent while an older row for the same pubkey is due too — so
+//! two messages ready together never race each other. A row waiting out its
+//! backoff holds nothing back: the messages behind it, urgent ones included,
+//! go out and the retry follows when it is due. After [`MAX_ATTEMPTS`] the
+//! row becomes a dead letter, listed and requeued through the admin RPC.
+//!
+//! Each row records the instance it is sent as (`sender`, see
+//! [`crate::instance`]). The message is stored NIP-44 encrypted to that
+//! instance's own key (`key_pubkey`) and only decrypted to be sent, so the
+//! table never holds payout invoices or Cashu signatures in the clear.
+
+use crate::db::DbPool;
+use crate::instance::DEFAULT_INSTANCE;
+use crate::metrics;
+use crate::util::send_dm;
+use futures::future::BoxFuture;
+use mostro_core::error::MostroError::{self, *};
+use mostro_core::error::ServiceError;
+use mostro_core::message::{Action, Message};
+use nostr_sdk::prelude::{nip44, Keys, PublicKey};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, Semaphore};
+use tracing::{debug, error, warn};
+
+/// Failed attempts after which a message becomes a dead letter. With the
+/// backoff below this spans roughly 13 minutes of relay trouble.
+pub const MAX_ATTEMPTS: i64 = 10;
+/// Delay before the first retry, doubled on every further failure.
+const BASE_BACKOFF_SECS: i64 = 2;
+/// Ceiling of the retry delay.
+const MAX_BACKOFF_SECS: i64 = 300;
+
+const STATUS_PENDING: &str = "pending";
+const STATUS_DEAD: &str = "dead";
+
+fn db_err(e: sqlx::Error) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+/// Delivery priority, stored in the `priority` column (lower goes first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Hold invoices, payout requests and Cashu signatures: a user is
+    /// waiting to move funds and the trade has a clock running.
+    Urgent = 0,
+    Normal = 1,
+    /// Restore-session replies and order listings.
+    Low = 2,
+}
+
+impl Priority {
+    /// Priority of a message from `queue` carrying `action`.
+    pub fn of(queue: &str, action: Option<Action>) -> Self {
+        match action {
+            Some(
+                Action::PayInvoice
+                | Action::PayBondInvoice
+                | Action::AddInvoice
+                | Action::AddBondInvoice
+                | Action::CashuPmSignature,
+            ) => Priority::Urgent,
+            Some(Action::Orders | Action::LastTradeIndex | Action::RestoreSession) => Priority::Low,
+            _ if queue == "restore" => Priority::Low,
+            _ => Priority::Normal,
+        }
+    }
+
+    fn from_column(value: i64) -> Self {
+        match value {
+            0 => Priority::Urgent,
+            2 => Priority::Low,
+            _ => Priority::Normal,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Urgent => "urgent",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+}
+
+/// Wakes the dispatcher as soon as a message is enqueued.
+static WAKE: Notify = Notify::const_new();
+
+/// Tell the dispatcher there is work; called by the `enqueue_*` helpers.
+pub fn wake() {
+    WAKE.notify_one();
+}
+
+/// One outbox row.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub queue: String,
+    pub destination: String,
+    pub action: Option<String>,
+    pub message: String,
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub priority: i64,
+    pub sender: String,
+    /// Key that encrypted `message`; empty for rows stored in plaintext
+    /// before messages were encrypted.
+    pub key_pubkey: String,
+}
+
+/// Seconds to wait after the `attempts`-th failure.
+fn backoff_secs(attempts: i64) -> i64 {
+    let exp = attempts.saturating_sub(1).clamp(0, 30) as u32;
+    BASE_BACKOFF_SECS
+        .saturating_mul(1i64 << exp)
+        .min(MAX_BACKOFF_SECS)
+}
+
+/// Store one message as pending, due immediately, sent as instance `sender`
+/// whose key is `keys`. The message is encrypted to `keys` itself.
+pub async fn persist_from(
+    pool: &DbPool,
+    sender: &str,
+    keys: &Keys,
+    queue: &str,
+    message: &Message,
+    destination: &PublicKey,
+    now: i64,
+) -> Result<i64, MostroError> {
+    let json = message
+        .as_json()
+        .map_err(|_| MostroInternalErr(ServiceError::MessageSerializationError))?;
+    let sealed = nip44::encrypt(
+        keys.secret_key(),
+        &keys.public_key(),
+        json,
+        nip44::Version::V2,
+    )
+    .map_err(|e| MostroInternalErr(ServiceError::EncryptionError(e.to_string())))?;
+    let action = message.inner_action();
+    let priority = Priority::of(queue, action.clone());
+    sqlx::query_scalar(
+        "INSERT INTO outbox (queue, destination, action, message, status, next_attempt_at, created_at, priority, sender, key_pubkey) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id",
+    )
+    .bind(queue)
+    .bind(destination.to_hex())
+    .bind(action.map(|a| a.to_string()))
+    .bind(sealed)
+    .bind(STATUS_PENDING)
+    .bind(now)
+    .bind(now)
+    .bind(priority as i64)
+    .bind(sender)
+    .bind(keys.public_key().to_hex())
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// The node key with hex pubkey `pubkey`: `known`, an instance's, the
+/// retired one or the `[nostr]` key.
+fn key_for<'a>(pubkey: &str, known: Option<&'a Keys>) -> Option<&'a Keys> {
+    known
+        .into_iter()
+        .chain(crate::instance::all().iter().map(|instance| &instance.keys))
+        .chain(crate::instance::retired().map(|instance| &instance.keys))
+        .chain(crate::config::get_mostro_keys())
+        .find(|keys| keys.public_key().to_hex() == pubkey)
+}
+
+/// The message JSON of `entry`, decrypted with the key that stored it.
+pub fn open(entry: &OutboxEntry, known: Option<&Keys>) -> Result<String, MostroError> {
+    if entry.key_pubkey.is_empty() {
+        return Ok(entry.message.clone());
+    }
+    let keys = key_for(&entry.key_pubkey, known).ok_or_else(|| {
+        MostroInternalErr(ServiceError::DecryptionError(format!(
+            "no key {} for outbox message {}",
+            entry.key_pubkey, entry.id
+        )))
+    })?;
+    nip44::decrypt(keys.secret_key(), &keys.public_key(), &entry.message)
+        .map_err(|e| MostroInternalErr(ServiceError::DecryptionError(e.to_string())))
+}
+
+/// Pending rows due at `now`, most urgent first and oldest first within a
+/// priority, skipping any row behind an older row for the same destination
+/// that is due as well. Rows in backoff do not block the ones after them.
+pub async fn due_messages(
+    pool: &DbPool,
+    now: i64,
+    limit: i64,
+) -> Result<Vec<OutboxEntry>, MostroError> {
+    sqlx::query_as::<_, OutboxEntry>(
+        "SELECT * FROM outbox o \
+         WHERE o.status = 'pending' AND o.next_attempt_at <= $1 \
+         AND NOT EXISTS (SELECT 1 FROM outbox p WHERE p.status = 'pending' \
+                         AND p.next_attempt_at <= $1 \
+                         AND p.destination = o.destination AND p.id < o.id) \
+         ORDER BY o.priority, o.id LIMIT $2",
+    )
+    .bind(now)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Delete a delivered row.
+pub async fn mark_delivered(pool: &DbPool, id: i64) -> Result<(), MostroError> {
+    sqlx::query("DELETE FROM outbox WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    Ok(())
+}
+
+/// Record a failed attempt: reschedule with backoff, or turn the row into a
+/// dead letter once it reaches [`MAX_ATTEMPTS`]. Returns true when it died.
+pub async fn mark_failed(
+    pool: &DbPool,
+    entry: &OutboxEntry,
+    error: &str,
+    now: i64,
+) -> Result<bool, MostroError> {
+    let attempts = entry.attempts + 1;
+    let dead = attempts >= MAX_ATTEMPTS;
+    sqlx::query(
+        "UPDATE outbox SET attempts = $1, status = $2, next_attempt_at = $3, last_error = $4 \
+         WHERE id = $5",
+    )
+    .bind(attempts)
+    .bind(if dead { STATUS_DEAD } else { STATUS_PENDING })
+    .bind(now + backoff_secs(attempts))
+    .bind(error)
+    .bind(entry.id)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(dead)
+}
+
+/// Dead letters, oldest first.
+pub async fn list_dead_letters(
+    pool: &DbPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<OutboxEntry>, MostroError> {
+    sqlx::query_as::<_, OutboxEntry>(
+        "SELECT * FROM outbox WHERE status = 'dead' ORDER BY id LIMIT $1 OFFSET $2",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Put a dead letter back in the queue with a fresh attempt budget. Returns
+/// false when `id` is not a dead letter.
+pub async fn requeue_dead_letter(pool: &DbPool, id: i64, now: i64) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        "UPDATE outbox SET status = 'pending', attempts = 0, next_attempt_at = $1 \
+         WHERE id = $2 AND status = 'dead'",
+    )
+    .bind(now)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// `(status, rows)` for the metrics endpoint.
+pub async fn count_by_status(pool: &DbPool) -> Result<Vec<(String, i64)>, MostroError> {
+    sqlx::query_as::<_, (String, i64)>(
+        "SELECT status, COUNT(*) FROM outbox GROUP BY status ORDER BY status",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Sends a message JSON to a destination as the given sender instance;
+/// `send_dm` in production.
+pub type SendFn =
+    Arc<dyn Fn(String, PublicKey, String) -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Concurrent outbox dispatcher. Each [`Dispatcher::tick`] hands due rows,
+/// most urgent first, to a bounded pool of send tasks; a row stays claimed
+/// while its send is in flight. Because [`due_messages`] only yields the
+/// oldest due row of each destination, one slow recipient or relay never
+/// holds up anyone else.
+#[derive(Clone)]
+pub struct Dispatcher {
+    pool: Arc<DbPool>,
+    send: SendFn,
+    /// Key tried first when decrypting a row, besides the node's own.
+    keys: Option<Keys>,
+    workers: Arc<Semaphore>,
+    in_flight: Arc<Mutex<HashSet<i64>>>,
+}
+
+impl Dispatcher {
+    /// Dispatcher sending with `send_dm` as `sender_keys`, or as the row's
+    /// `[[instances]]` identity. A row whose instance is no longer configured
+    /// fails, rather than going out under another identity.
+    pub fn new(pool: Arc<DbPool>, sender_keys: Keys, workers: usize) -> Self {
+        let keys = sender_keys.clone();
+        let send: SendFn = Arc::new(move |sender, destination, message| {
+            let keys = if sender == DEFAULT_INSTANCE {
+                Some(sender_keys.clone())
+            } else {
+                crate::instance::by_sender(&sender).map(|instance| instance.keys.clone())
+            };
+            Box::pin(async move {
+                let keys = keys.ok_or_else(|| format!("unknown sender instance {sender:?}"))?;
+                send_dm(destination, &keys, &message, None)
+                    .await
+                    .map_err(|e| e.to_string())
+            })
+        });
+        Self {
+            keys: Some(keys),
+            ..Self::with_sender(pool, send, workers)
+        }
+    }
+
+    /// Dispatcher with a custom send function.
+    pub fn with_sender(pool: Arc<DbPool>, send: SendFn, workers: usize) -> Self {
+        Self {
+            pool,
+            send,
+            keys: None,
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    fn in_flight(&self) -> std::sync::MutexGuard<'_, HashSet<i64>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start a send task for every due row a worker is free for. Returns the
+    /// number of sends started.
+    pub async fn tick(&self) -> usize {
+        let now = chrono::Utc::now().timestamp();
+
+        let free = self.workers.available_permits();
+        if free == 0 {
+            return 0;
+        }
+        // Rows already in flight are still pending, so ask for enough to
+        // fill every free worker after skipping them.
+        let limit = (free + self.in_flight().len()) as i64;
+        let due = match due_messages(&self.pool, now, limit).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("Could not read the outbox: {e}");
+                return 0;
+            }
+        };
+
+        let mut started = 0;
+        for entry in due {
+            if self.in_flight().contains(&entry.id) {
+                continue;
+            }
+            let Ok(permit) = self.workers.clone().try_acquire_owned() else {
+                break;
+            };
+            self.in_flight().insert(entry.id);
+            let this = self.clone();
+            tokio::spawn(async move {
+                let id = entry.id;
+                this.deliver(entry).await;
+                this.in_flight().remove(&id);
+                drop(permit);
+                // A finished send frees a worker and may unblock the next
+                // message for the same destination.
+                wake();
+            });
+            started += 1;
+        }
+        started
+    }
+
+    async fn deliver(&self, entry: OutboxEntry) {
+        let started = Instant::now();
+        let sent = match (
+            PublicKey::from_hex(&entry.destination),
+            open(&entry, self.keys.as_ref()),
+        ) {
+            (Ok(destination), Ok(message)) => {
+                (self.send)(entry.sender.clone(), destination, message).await
+            }
+            (Err(e), _) => Err(format!("invalid destination: {e}")),
+            (_, Err(e)) => Err(e.to_string()),
+        };
+        let send_secs = started.elapsed().as_secs_f64();
+        let now = chrono::Utc::now().timestamp();
+        let result = match sent {
+            Ok(()) => {
+                let queued_secs = (now - entry.created_at).max(0);
+                metrics::outbox_delivered(queued_secs as f64);
+                debug!(
+                    "Delivered outbox message {} ({}) to {} in {:.3}s, {}s after enqueue",
+                    entry.id,
+                    Priority::from_column(entry.priority).label(),
+                    entry.destination,
+                    send_secs,
+                    queued_secs
+                );
+                mark_delivered(&self.pool, entry.id).await
+            }
+            Err(e) => {
+                error!("Failed to send outbox message {}: {}", entry.id, e);
+                match mark_failed(&self.pool, &entry, &e, now).await {
+                    Ok(true) => {
+                        warn!(
+                            "Outbox message {} to {} is a dead letter after {} attempts",
+                            entry.id, entry.destination, MAX_ATTEMPTS
+                        );
+                        Ok(())
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        if let Err(e) = result {
+            error!("Could not update outbox message {}: {e}", entry.id);
+        }
+    }
+
+    /// Run forever: tick whenever a message is enqueued or a send finishes,
+    /// and at least every `idle` so backed-off rows are picked up.
+    pub async fn run(self, idle: Duration) {
+        loop {
+            self.tick().await;
+            tokio::select! {
+                _ = WAKE.notified() => {}
+                _ = tokio::time::sleep(idle) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+
+    /// Store one message as pending, sent as the `default` instance with the
+    /// canonical test key.
+    pub async fn persist(
+        pool: &DbPool,
+        queue: &str,
+        message: &Message,
+        destination: &PublicKey,
+        now: i64,
+    ) -> Result<i64, MostroError> {
+        crate::config::init_test_nostr_keys();
+        let keys = crate::config::get_mostro_keys().expect("test keys");
+        persist_from(
+            pool,
+            DEFAULT_INSTANCE,
+            keys,
+            queue,
+            message,
+            destination,
+            now,
+        )
+        .await
+    }
+
+    /// Messages stored so far for `queue` on the daemon-wide test pool
+    /// (`crate::db::test_utils::global_pool`), oldest first. That pool is
+    /// shared by every test, so callers filter by their own order id or keys.
+    pub async fn queued(queue: &str) -> Vec<(Message, PublicKey)> {
+        let pool = crate::db::test_utils::global_pool().await;
+        let rows: Vec<OutboxEntry> =
+            sqlx::query_as("SELECT * FROM outbox WHERE queue = $1 ORDER BY id")
+                .bind(queue)
+                .fetch_all(pool.as_ref())
+                .await
+                .expect("read the outbox");
+        rows.into_iter()
+            .map(|entry| {
+                (
+                    Message::from_json(&open(&entry, None).expect("decrypt the stored message"))
+                        .expect("stored message"),
+                    PublicKey::from_hex(&entry.destination).expect("stored destination"),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::persist;
+    use super::*;
+    use mostro_core::message::Action;
+    use uuid::Uuid;
+
+    async fn migrated_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
+    }
+
+    fn order_msg(action: Action) -> Message {
+        Message::new_order(Some(Uuid::new_v4()), None, None, action, None)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff_secs(1), 2);
+        assert_eq!(backoff_secs(2), 4);
+        assert_eq!(backoff_secs(5), 32);
+        assert_eq!(backoff_secs(9), MAX_BACKOFF_SECS);
+        assert_eq!(backoff_secs(i64::MAX), MAX_BACKOFF_SECS);
+    }
+
+    #[tokio::test]
+    async fn due_messages_keep_per_destination_order_and_honour_backoff() {
+        let pool = migrated_pool().await;
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+        let first = persist(&pool, "order", &order_msg(Action::PayInvoice), &alice, 100)
+            .await
+            .unwrap();
+        let second = persist(&pool, "order", &order_msg(Action::Released), &alice, 100)
+            .await
+            .unwrap();
+        let other = persist(&pool, "cantdo", &order_msg(Action::CantDo), &bob, 100)
+            .await
+            .unwrap();
+
+        let due = due_messages(&pool, 100, 10).await.unwrap();
+        let ids: Vec<i64> = due.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![first, other]);
+        assert_eq!(due[0].action.as_deref(), Some("PayInvoice"));
+
+        // A head in backoff does not hold back the rest of its destination.
+        assert!(!mark_failed(&pool, &due[0], "relay down", 100)
+            .await
+            .unwrap());
+        let ids: Vec<i64> = due_messages(&pool, 101, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![second, other]);
+
+        // Once the retry is due again it goes first.
+        let ids: Vec<i64> = due_messages(&pool, 102, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![first, other]);
+
+        mark_delivered(&pool, first).await.unwrap();
+        let ids: Vec<i64> = due_messages(&pool, 102, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![second, other]);
+    }
+
+    #[tokio::test]
+    async fn an_urgent_message_is_not_stuck_behind_a_backed_off_one() {
+        let pool = migrated_pool().await;
+        let dest = Keys::generate().public_key();
+        let stale = persist(&pool, "order", &order_msg(Action::Released), &dest, 100)
+            .await
+            .unwrap();
+        let entry = due_messages(&pool, 100, 10).await.unwrap().remove(0);
+        mark_failed(&pool, &entry, "relay down", 100).await.unwrap();
+        let urgent = persist(&pool, "order", &order_msg(Action::PayInvoice), &dest, 101)
+            .await
+            .unwrap();
+
+        let ids: Vec<i64> = due_messages(&pool, 101, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![urgent]);
+
+        let ids: Vec<i64> = due_messages(&pool, 102, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![stale]);
+    }
+
+    #[tokio::test]
+    async fn exhausted_messages_become_dead_letters_and_can_be_requeued() {
+        let pool = migrated_pool().await;
+        let dest = Keys::generate().public_key();
+        let id = persist(&pool, "order", &order_msg(Action::AddInvoice), &dest, 0)
+            .await
+            .unwrap();
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let entry = due_messages(&pool, i64::MAX, 1).await.unwrap().remove(0);
+            let dead = mark_failed(&pool, &entry, "no relay", 0).await.unwrap();
+            assert_eq!(dead, attempt == MAX_ATTEMPTS);
+        }
+        assert!(due_messages(&pool, i64::MAX, 10).await.unwrap().is_empty());
+
+        let dead = list_dead_letters(&pool, 10, 0).await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, MAX_ATTEMPTS);
+        assert_eq!(dead[0].last_error.as_deref(), Some("no relay"));
+        assert_eq!(
+            count_by_status(&pool).await.unwrap(),
+            vec![("dead".to_string(), 1)]
+        );
+
+        assert!(requeue_dead_letter(&pool, id, 50).await.unwrap());
+        assert!(!requeue_dead_letter(&pool, id, 50).await.unwrap());
+        let due = due_messages(&pool, 50, 10).await.unwrap();
+        assert_eq!(due[0].id, id);
+        assert_eq!(due[0].attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn enqueue_stores_the_row_before_returning() {
+        let _ = crate::db::test_utils::global_pool().await;
+        let dest = Keys::generate().public_key();
+        let order_id = Uuid::new_v4();
+
+        crate::util::enqueue_cant_do_msg(
+            Some(1),
+            Some(order_id),
+            mostro_core::error::CantDoReason::InvalidPeer,
+            dest,
+        )
+        .await;
+
+        // No dispatcher runs on the shared test pool: the row is the
+        // enqueue's own doing.
+        let stored: Vec<_> = test_utils::queued("cantdo")
+            .await
+            .into_iter()
+            .filter(|(_, to)| *to == dest)
+            .collect();
+        assert_eq!(stored.len(), 1);
+        let kind = stored[0].0.get_inner_message_kind();
+        assert_eq!(kind.id, Some(order_id));
+        assert_eq!(kind.action, Action::CantDo);
+    }
+
+    #[tokio::test]
+    async fn rows_remember_their_sender_instance() {
+        let pool = migrated_pool().await;
+        let dest = Keys::generate().public_key();
+        let other = Keys::generate().public_key();
+        persist(&pool, "order", &order_msg(Action::Canceled), &dest, 1)
+            .await
+            .unwrap();
+        persist_from(
+            &pool,
+            "community-b",
+            &Keys::generate(),
+            "order",
+            &order_msg(Action::Canceled),
+            &other,
+            1,
+        )
+        .await
+        .unwrap();
+
+        let mut senders: Vec<(String, String)> = due_messages(&pool, 1, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.destination, entry.sender))
+            .collect();
+        senders.sort();
+        let mut expected = vec![
+            (dest.to_hex(), DEFAULT_INSTANCE.to_string()),
+            (other.to_hex(), "community-b".to_string()),
+        ];
+        expected.sort();
+        assert_eq!(senders, expected);
+    }
+
+    #[tokio::test]
+    async fn messages_are_stored_encrypted_and_sent_in_the_clear() {
+        let pool = Arc::new(migrated_pool().await);
+        let dest = Keys::generate().public_key();
+        let message = order_msg(Action::CashuPmSignature);
+        persist(&pool, "order", &message, &dest, 0).await.unwrap();
+        // A row stored before messages were encrypted still goes out.
+        sqlx::query(
+            "INSERT INTO outbox (queue, destination, action, message, status, next_attempt_at, created_at) \
+             VALUES ('order', $1, 'Canceled', $2, 'pending', 0, 0)",
+        )
+        .bind(Keys::generate().public_key().to_hex())
+        .bind(order_msg(Action::Canceled).as_json().unwrap())
+        .execute(pool.as_ref())
+        .await
+        .unwrap();
+
+        let stored = due_messages(&pool, 0, 10).await.unwrap();
+        let json = message.as_json().unwrap();
+        assert_ne!(stored[0].message, json);
+        assert!(!stored[0].message.contains("CashuPmSignature"));
+        assert_eq!(
+            stored[0].key_pubkey,
+            crate::config::get_mostro_keys()
+                .unwrap()
+                .public_key()
+                .to_hex()
+        );
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let log = sent.clone();
+        let send: SendFn = Arc::new(move |_sender, _destination, message| {
+            log.lock().unwrap().push(message);
+            Box::pin(async { Ok(()) })
+        });
+        let dispatcher = Dispatcher::with_sender(pool.clone(), send, 1);
+        for _ in 0..2000 {
+            dispatcher.tick().await;
+            if sent.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        wait_until_empty(&pool).await;
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent[0], json);
+        assert_eq!(
+            Message::from_json(&sent[1]).unwrap().inner_action(),
+            Some(Action::Canceled)
+        );
+    }
+
+    /// Sender that records destinations and holds each send until released.
+    fn gated_sender(log: Arc<Mutex<Vec<PublicKey>>>, gate: Arc<Semaphore>) -> SendFn {
+        Arc::new(move |_sender, destination, _message| {
+            let log = log.clone();
+            let gate = gate.clone();
+            Box::pin(async move {
+                log.lock().unwrap().push(destination);
+                gate.acquire().await.unwrap().forget();
+                Ok(())
+            })
+        })
+    }
+
+    async fn wait_until_empty(pool: &DbPool) {
+        for _ in 0..2000 {
+            let (n,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM outbox")
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            if n == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("outbox never drained");
+    }
+
+    #[test]
+    fn priority_follows_action_and_queue() {
+        assert_eq!(
+            Priority::of("order", Some(Action::PayInvoice)),
+            Priority::Urgent
+        );
+        assert_eq!(
+            Priority::of("order", Some(Action::AddBondInvoice)),
+            Priority::Urgent
+        );
+        assert_eq!(
+            Priority::of("order", Some(Action::Released)),
+            Priority::Normal
+        );
+        assert_eq!(
+            Priority::of("cantdo", Some(Action::CantDo)),
+            Priority::Normal
+        );
+        assert_eq!(Priority::of("order", Some(Action::Orders)), Priority::Low);
+        assert_eq!(Priority::of("restore", None), Priority::Low);
+    }
+
+    #[tokio::test]
+    async fn dispatcher_sends_urgent_messages_first() {
+        let pool = Arc::new(migrated_pool().await);
+        let (a, b, c) = (
+            Keys::generate().public_key(),
+            Keys::generate().public_key(),
+            Keys::generate().public_key(),
+        );
+        persist(&pool, "restore", &Message::new_restore(None), &c, 0)
+            .await
+            .unwrap();
+        persist(&pool, "order", &order_msg(Action::Released), &a, 0)
+            .await
+            .unwrap();
+        persist(&pool, "order", &order_msg(Action::PayInvoice), &b, 0)
+            .await
+            .unwrap();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let gate = Arc::new(Semaphore::new(Semaphore::MAX_PERMITS));
+        let dispatcher = Dispatcher::with_sender(pool.clone(), gated_sender(log.clone(), gate), 1);
+        for _ in 0..2000 {
+            dispatcher.tick().await;
+            if log.lock().unwrap().len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        wait_until_empty(&pool).await;
+        assert_eq!(*log.lock().unwrap(), vec![b, a, c]);
+    }
+
+    #[tokio::test]
+    async fn dispatcher_sends_in_parallel_but_in_order_per_destination() {
+        let pool = Arc::new(migrated_pool().await);
+        let slow = Keys::generate().public_key();
+        let others: Vec<PublicKey> = (0..3).map(|_| Keys::generate().public_key()).collect();
+        persist(&pool, "order", &order_msg(Action::PayInvoice), &slow, 0)
+            .await
+            .unwrap();
+        persist(&pool, "order", &order_msg(Action::Released), &slow, 0)
+            .await
+            .unwrap();
+        for dest in &others {
+            persist(&pool, "order", &order_msg(Action::Canceled), dest, 0)
+                .await
+                .unwrap();
+        }
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let gate = Arc::new(Semaphore::new(0));
+        let dispatcher =
+            Dispatcher::with_sender(pool.clone(), gated_sender(log.clone(), gate.clone()), 3);
+
+        // Three workers, four destinations: three sends start at once; the
+        // slow destination's second message is not among them.
+        assert_eq!(dispatcher.tick().await, 3);
+        assert_eq!(dispatcher.tick().await, 0, "every worker is busy");
+
+        gate.add_permits(100);
+        for _ in 0..2000 {
+            dispatcher.tick().await;
+            if log.lock().unwrap().len() == 5 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        wait_until_empty(&pool).await;
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.iter().filter(|d| **d == slow).count(), 2);
+        assert_eq!(log[0], slow, "the urgent message leads");
+    }
+}
@@ -163,6 +163,19 @@ impl PriceManager {
         PRICE_MANAGER.get()
     }
 
+    /// Circuit-breaker snapshot of every enabled provider as
+    /// `(id, available, consecutive_failures)`, for the metrics endpoint.
+    /// A poisoned health lock reads as available, as in `update_all`.
+    pub fn provider_health(&self, now: i64) -> Vec<(ProviderId, bool, u32)> {
+        self.providers
+            .iter()
+            .map(|p| match p.health.lock() {
+                Ok(h) => (p.id, h.is_available(now), h.consecutive_failures()),
+                Err(_) => (p.id, true, 0),
+            })
+            .collect()
+    }
+
     /// Read-only view of the active settings (used by the scheduler to size
     /// its sleep and by tests).
     pub fn settings(&self) -> &PriceSettings {
@@ -139,6 +139,11 @@ impl ProviderHealth {
         }
     }
 
+    /// Failures in a row since the last successful poll.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
     /// Record a successful poll: reset the breaker.
     pub fn record_success(&mut self) {
         self.consecutive_failures = 0;
@@ -0,0 +1,629 @@
+//! Per-relay delivery bookkeeping and health scoring.
+//!
+//! `Client::send_event` reports, per relay, whether the event was accepted;
+//! a publish "succeeds" as soon as one relay takes it. [`publish`] keeps that
+//! per-relay result, and sends to each relay separately so it can time each
+//! answer (`mostro_relay_send_seconds`). Every accept, rejection and timeout
+//! feeds a
+//! [`RelayStats`] entry whose score is an exponential moving average of
+//! recent outcomes. A relay that fails [`DEMOTE_AFTER_FAILURES`] times in a
+//! row is demoted — skipped by [`publish`] for [`DEMOTE_SECS`], then tried
+//! again — so a dead relay stops costing every send the full OK timeout.
+//!
+//! Order events and direct messages that some relays missed (rejected, timed
+//! out, or skipped while demoted) are queued for catch-up: [`catch_up_once`]
+//! re-sends the *same* signed event to just those relays once they are tried
+//! again. Republishing a fresh revision cannot fix such a gap — it only
+//! rewrites the healthy relays' copies — and a user reading only from a
+//! demoted relay would otherwise never see a reply.
+
+use crate::metrics;
+use futures::future::join_all;
+use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Weight of the newest outcome in the score's moving average.
+const SCORE_WEIGHT: f64 = 0.2;
+
+/// Consecutive failures after which a relay is demoted.
+pub const DEMOTE_AFTER_FAILURES: u32 = 5;
+
+/// How long a demoted relay is skipped before it is tried again.
+pub const DEMOTE_SECS: i64 = 300;
+
+/// Catch-up sends per event before the remaining relays are given up on;
+/// the divergence left behind is bounded by the event's NIP-40 expiration.
+pub const MAX_CATCH_UP_ATTEMPTS: u8 = 5;
+
+/// Result of sending one event to one relay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    Accepted,
+    Rejected(String),
+    TimedOut(String),
+}
+
+impl SendOutcome {
+    /// Classify a per-relay error from `SendEventOutput::failed`.
+    pub fn from_error(error: &str) -> Self {
+        let lower = error.to_lowercase();
+        if lower.contains("timeout") || lower.contains("timed out") {
+            SendOutcome::TimedOut(error.to_string())
+        } else {
+            SendOutcome::Rejected(error.to_string())
+        }
+    }
+}
+
+/// Delivery record of one relay since startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayStats {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub timed_out: u64,
+    pub consecutive_failures: u32,
+    /// Moving average of outcomes, 1.0 (all accepted) to 0.0.
+    pub score: f64,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+    /// Skipped by [`publish`] until this time.
+    pub demoted_until: Option<i64>,
+}
+
+impl Default for RelayStats {
+    fn default() -> Self {
+        Self {
+            accepted: 0,
+            rejected: 0,
+            timed_out: 0,
+            consecutive_failures: 0,
+            score: 1.0,
+            last_error: None,
+            last_success_at: None,
+            last_failure_at: None,
+            demoted_until: None,
+        }
+    }
+}
+
+impl RelayStats {
+    pub fn is_demoted(&self, now: i64) -> bool {
+        self.demoted_until.is_some_and(|until| now < until)
+    }
+
+    fn record(&mut self, outcome: &SendOutcome, now: i64) {
+        let value = match outcome {
+            SendOutcome::Accepted => {
+                self.accepted += 1;
+                self.consecutive_failures = 0;
+                self.last_success_at = Some(now);
+                self.demoted_until = None;
+                1.0
+            }
+            SendOutcome::Rejected(error) | SendOutcome::TimedOut(error) => {
+                if matches!(outcome, SendOutcome::TimedOut(_)) {
+                    self.timed_out += 1;
+                } else {
+                    self.rejected += 1;
+                }
+                self.consecutive_failures += 1;
+                self.last_failure_at = Some(now);
+                self.last_error = Some(error.clone());
+                // Each further failure on probation extends the demotion.
+                if self.consecutive_failures >= DEMOTE_AFTER_FAILURES {
+                    self.demoted_until = Some(now + DEMOTE_SECS);
+                }
+                0.0
+            }
+        };
+        self.score = self.score * (1.0 - SCORE_WEIGHT) + value * SCORE_WEIGHT;
+    }
+}
+
+/// What a catch-up entry re-sends: the latest revision of an order event, or
+/// one direct message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CatchUpKey {
+    Order(Uuid),
+    Message(EventId),
+}
+
+impl std::fmt::Display for CatchUpKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatchUpKey::Order(id) => write!(f, "order {id}"),
+            CatchUpKey::Message(id) => write!(f, "message {id}"),
+        }
+    }
+}
+
+/// An event still missing from some relays.
+#[derive(Debug, Clone)]
+struct CatchUp {
+    event: Event,
+    missing: HashSet<RelayUrl>,
+    attempts: u8,
+}
+
+/// Health of every relay mostrod has sent to, plus the catch-up queue.
+#[derive(Default)]
+pub struct RelayHealth {
+    relays: Mutex<HashMap<RelayUrl, RelayStats>>,
+    catch_up: Mutex<HashMap<CatchUpKey, CatchUp>>,
+}
+
+static RELAY_HEALTH: LazyLock<RelayHealth> = LazyLock::new(RelayHealth::default);
+
+/// The process-wide tracker.
+pub fn global() -> &'static RelayHealth {
+    &RELAY_HEALTH
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl RelayHealth {
+    pub fn record(&self, url: &RelayUrl, outcome: &SendOutcome, now: i64) {
+        let mut relays = lock(&self.relays);
+        let stats = relays.entry(url.clone()).or_default();
+        let was_demoted = stats.is_demoted(now);
+        stats.record(outcome, now);
+        if !was_demoted && stats.is_demoted(now) {
+            warn!(
+                "relay {url} demoted for {DEMOTE_SECS}s after {} consecutive failures (last: {})",
+                stats.consecutive_failures,
+                stats.last_error.as_deref().unwrap_or("unknown")
+            );
+        } else if was_demoted && *outcome == SendOutcome::Accepted {
+            info!("relay {url} accepted an event again; no longer demoted");
+        }
+    }
+
+    /// Record every per-relay result of one send.
+    pub fn record_output(&self, output: &SendEventOutput, now: i64) {
+        for url in output.success.keys() {
+            self.record(url, &SendOutcome::Accepted, now);
+        }
+        for (url, error) in &output.failed {
+            self.record(url, &SendOutcome::from_error(error), now);
+        }
+    }
+
+    pub fn is_demoted(&self, url: &RelayUrl, now: i64) -> bool {
+        lock(&self.relays)
+            .get(url)
+            .is_some_and(|stats| stats.is_demoted(now))
+    }
+
+    /// The relays of `all` a send should go to: every relay that is not
+    /// demoted — or all of them when every relay is, so a send is never
+    /// dropped outright.
+    pub fn targets(&self, all: &[RelayUrl], now: i64) -> Vec<RelayUrl> {
+        let healthy: Vec<RelayUrl> = all
+            .iter()
+            .filter(|url| !self.is_demoted(url, now))
+            .cloned()
+            .collect();
+        if healthy.is_empty() {
+            all.to_vec()
+        } else {
+            healthy
+        }
+    }
+
+    /// Stats of every relay in `relays` (fresh defaults for relays never
+    /// sent to) followed by any other relay that has a record, by url.
+    pub fn snapshot(&self, relays: &[RelayUrl]) -> Vec<(RelayUrl, RelayStats)> {
+        let recorded = lock(&self.relays);
+        let mut urls: Vec<&RelayUrl> = relays.iter().chain(recorded.keys()).collect();
+        urls.sort_by_key(|url| url.as_str().to_string());
+        urls.dedup();
+        urls.into_iter()
+            .map(|url| (url.clone(), recorded.get(url).cloned().unwrap_or_default()))
+            .collect()
+    }
+
+    /// Queue `event` (the latest revision of `order_id`) for re-sending to
+    /// `missing`. An entry for an older revision is replaced; a newer one
+    /// is kept.
+    pub fn queue_catch_up(&self, order_id: Uuid, event: &Event, missing: HashSet<RelayUrl>) {
+        self.queue(CatchUpKey::Order(order_id), event, missing);
+    }
+
+    /// Queue the direct message `event` for re-sending to `missing`.
+    pub fn queue_message_catch_up(&self, event: &Event, missing: HashSet<RelayUrl>) {
+        self.queue(CatchUpKey::Message(event.id), event, missing);
+    }
+
+    fn queue(&self, key: CatchUpKey, event: &Event, missing: HashSet<RelayUrl>) {
+        if missing.is_empty() {
+            return;
+        }
+        let mut queue = lock(&self.catch_up);
+        if queue
+            .get(&key)
+            .is_some_and(|queued| queued.event.created_at > event.created_at)
+        {
+            return;
+        }
+        queue.insert(
+            key,
+            CatchUp {
+                event: event.clone(),
+                missing,
+                attempts: 0,
+            },
+        );
+    }
+
+    /// Drop the catch-up entry of `order_id` once a revision at least as
+    /// new as `event` reached every relay.
+    pub fn clear_catch_up(&self, order_id: Uuid, event: &Event) {
+        let key = CatchUpKey::Order(order_id);
+        let mut queue = lock(&self.catch_up);
+        if queue
+            .get(&key)
+            .is_some_and(|queued| queued.event.created_at <= event.created_at)
+        {
+            queue.remove(&key);
+        }
+    }
+
+    /// Order events and direct messages waiting for catch-up on `url`.
+    pub fn pending_catch_up(&self, url: &RelayUrl) -> usize {
+        lock(&self.catch_up)
+            .values()
+            .filter(|entry| entry.missing.contains(url))
+            .count()
+    }
+
+    #[cfg(test)]
+    fn catch_up_missing(&self, order_id: Uuid) -> Option<HashSet<RelayUrl>> {
+        lock(&self.catch_up)
+            .get(&CatchUpKey::Order(order_id))
+            .map(|entry| entry.missing.clone())
+    }
+
+    #[cfg(test)]
+    fn message_catch_up_missing(&self, event_id: EventId) -> Option<HashSet<RelayUrl>> {
+        lock(&self.catch_up)
+            .get(&CatchUpKey::Message(event_id))
+            .map(|entry| entry.missing.clone())
+    }
+}
+
+/// Relays a `Client` publishes to.
+pub async fn relay_urls(client: &Client) -> Vec<RelayUrl> {
+    client.relays().await.into_keys().collect()
+}
+
+/// Send `event` to every relay that is not demoted and record how each one
+/// answered and how long it took. Returns the send output together with the
+/// demoted relays that were skipped, which never saw the event.
+///
+/// The relays are sent to concurrently, one send each: a slow relay delays
+/// only its own answer, and its latency is not hidden behind the others'.
+/// A relay whose send errors counts as failed; the publish errors only when
+/// every relay's did.
+pub async fn publish(
+    client: &Client,
+    event: &Event,
+) -> Result<(SendEventOutput, Vec<RelayUrl>), nostr_sdk::error::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let all = relay_urls(client).await;
+    let targets = global().targets(&all, now);
+    if targets.is_empty() {
+        return Ok((client.send_event(event).await?, Vec::new()));
+    }
+    let sends = targets.iter().enumerate().map(|(i, url)| async move {
+        let started = Instant::now();
+        let sent = client
+            .send_event(event)
+            .to([url.clone()])
+            .save_into_database(i == 0)
+            .await;
+        (url, started.elapsed().as_secs_f64(), sent)
+    });
+    let mut output = SendEventOutput {
+        value: event.id,
+        success: HashMap::new(),
+        failed: HashMap::new(),
+    };
+    let mut errors = Vec::new();
+    for (url, secs, sent) in join_all(sends).await {
+        match sent {
+            Ok(sent) => {
+                metrics::relay_answered(url.as_str(), secs);
+                output.success.extend(sent.success);
+                output.failed.extend(sent.failed);
+            }
+            Err(e) => {
+                output.failed.insert(url.clone(), e.to_string());
+                errors.push(e);
+            }
+        }
+    }
+    // Recorded even when every relay errored: a total outage is what
+    // demotion has to see.
+    global().record_output(&output, now);
+    if errors.len() == targets.len() {
+        return Err(errors.remove(0));
+    }
+    let skipped = all
+        .into_iter()
+        .filter(|url| !targets.contains(url))
+        .collect();
+    Ok((output, skipped))
+}
+
+/// Relays that did not get the event from a [`publish`] call.
+pub fn missed_relays(output: &SendEventOutput, skipped: &[RelayUrl]) -> HashSet<RelayUrl> {
+    output
+        .failed
+        .keys()
+        .chain(skipped.iter())
+        .cloned()
+        .collect()
+}
+
+/// One catch-up pass: re-send every queued event to the relays that missed
+/// it. Demoted relays wait without using an attempt, so a message skipped
+/// during a demotion goes out once the relay is tried again; relays no
+/// longer configured are dropped.
+pub async fn catch_up_once(client: &Client) {
+    let configured: HashSet<RelayUrl> = relay_urls(client).await.into_iter().collect();
+    let health = global();
+    let entries: Vec<(CatchUpKey, CatchUp)> = lock(&health.catch_up).drain().collect();
+    for (key, mut entry) in entries {
+        let now = chrono::Utc::now().timestamp();
+        entry.missing.retain(|url| configured.contains(url));
+        let due: Vec<RelayUrl> = entry
+            .missing
+            .iter()
+            .filter(|url| !health.is_demoted(url, now))
+            .cloned()
+            .collect();
+        if !due.is_empty() {
+            entry.attempts += 1;
+            match client.send_event(&entry.event).to(due).await {
+                Ok(output) => {
+                    health.record_output(&output, now);
+                    for url in output.success.keys() {
+                        entry.missing.remove(url);
+                    }
+                }
+                Err(e) => warn!("relay catch-up for {key} failed: {e}"),
+            }
+        }
+        if entry.missing.is_empty() {
+            continue;
+        }
+        if entry.attempts >= MAX_CATCH_UP_ATTEMPTS {
+            warn!(
+                "relay catch-up: giving up on {key} after {} attempts; still missing on {:?}",
+                entry.attempts, entry.missing
+            );
+            continue;
+        }
+        // A newer revision queued meanwhile supersedes this one.
+        let mut queue = lock(&health.catch_up);
+        if queue
+            .get(&key)
+            .is_none_or(|queued| queued.event.created_at < entry.event.created_at)
+        {
+            queue.insert(key, entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> RelayUrl {
+        RelayUrl::parse(s).unwrap()
+    }
+
+    fn event_at(secs: u64) -> Event {
+        EventBuilder::new(Kind::TextNote, "order")
+            .custom_created_at(Timestamp::from(secs))
+            .finalize(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn outcomes_are_classified_by_error_text() {
+        assert!(matches!(
+            SendOutcome::from_error("timeout"),
+            SendOutcome::TimedOut(_)
+        ));
+        assert!(matches!(
+            SendOutcome::from_error("blocked: kind not allowed"),
+            SendOutcome::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn consistent_failures_demote_a_relay_until_it_accepts_again() {
+        let health = RelayHealth::default();
+        let bad = url("wss://bad.example");
+        let good = url("wss://good.example");
+        let rejected = SendOutcome::Rejected("rate-limited".into());
+
+        for _ in 0..DEMOTE_AFTER_FAILURES - 1 {
+            health.record(&bad, &rejected, 100);
+        }
+        assert!(!health.is_demoted(&bad, 100));
+        health.record(&bad, &SendOutcome::TimedOut("timeout".into()), 100);
+        assert!(health.is_demoted(&bad, 100));
+        health.record(&good, &SendOutcome::Accepted, 100);
+
+        let all = vec![bad.clone(), good.clone()];
+        assert_eq!(health.targets(&all, 100), vec![good.clone()]);
+        // Probation: tried again once the demotion runs out.
+        assert_eq!(health.targets(&all, 100 + DEMOTE_SECS), all);
+        // Nothing is dropped when every relay is demoted.
+        assert_eq!(
+            health.targets(std::slice::from_ref(&bad), 100),
+            vec![bad.clone()]
+        );
+
+        let stats = &health.snapshot(&all)[0].1;
+        assert_eq!(stats.rejected, 4);
+        assert_eq!(stats.timed_out, 1);
+        assert!(stats.score < 0.4);
+        assert_eq!(stats.last_error.as_deref(), Some("timeout"));
+
+        health.record(&bad, &SendOutcome::Accepted, 500);
+        assert!(!health.is_demoted(&bad, 500));
+        assert_eq!(health.snapshot(&all)[0].1.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn snapshot_lists_configured_relays_never_sent_to() {
+        let health = RelayHealth::default();
+        let fresh = url("wss://fresh.example");
+        let snapshot = health.snapshot(std::slice::from_ref(&fresh));
+        assert_eq!(snapshot, vec![(fresh, RelayStats::default())]);
+    }
+
+    #[tokio::test]
+    async fn publish_records_each_relay_and_catch_up_retries_the_misses() {
+        use nostr_sdk::local_relay::MockRelay;
+
+        let mock = MockRelay::run().await.expect("mock relay");
+        let good = mock.url().await;
+        let dead = url("ws://127.0.0.1:9");
+        let client = Client::default();
+        client.add_relay(good.clone()).await.unwrap();
+        client.add_relay(dead.clone()).await.unwrap();
+        client
+            .connect()
+            .and_wait(std::time::Duration::from_secs(2))
+            .await;
+
+        let event = event_at(1_000);
+        let (output, skipped) = publish(&client, &event).await.unwrap();
+        assert!(output.success.contains_key(&good));
+        assert!(output.failed.contains_key(&dead));
+        assert!(skipped.is_empty());
+        let snapshot = global().snapshot(&[]);
+        let stats = |u: &RelayUrl| snapshot.iter().find(|(r, _)| r == u).unwrap().1.clone();
+        assert!(stats(&good).accepted >= 1);
+        assert_eq!(stats(&dead).consecutive_failures, 1);
+
+        let order_id = Uuid::new_v4();
+        let missed = missed_relays(&output, &skipped);
+        assert_eq!(missed, HashSet::from([dead.clone()]));
+        global().queue_catch_up(order_id, &event, missed);
+        catch_up_once(&client).await;
+        // Still unreachable: the entry stays queued with one attempt used.
+        assert_eq!(
+            global().catch_up_missing(order_id),
+            Some(HashSet::from([dead.clone()]))
+        );
+        assert_eq!(
+            global()
+                .snapshot(&[])
+                .into_iter()
+                .find(|(r, _)| *r == dead)
+                .unwrap()
+                .1
+                .consecutive_failures,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn a_publish_no_relay_takes_is_still_recorded() {
+        let dead = [url("ws://127.0.0.1:19"), url("ws://127.0.0.1:21")];
+        let client = Client::default();
+        for relay in &dead {
+            client.add_relay(relay.clone()).await.unwrap();
+        }
+        client
+            .connect()
+            .and_wait(std::time::Duration::from_secs(1))
+            .await;
+
+        // Unreachable relays come back as failed sends; a relay the pool
+        // loses mid-publish errors instead. Both are recorded.
+        let _ = publish(&client, &event_at(2_000)).await;
+        let snapshot = global().snapshot(&[]);
+        for relay in &dead {
+            let stats = &snapshot.iter().find(|(r, _)| r == relay).unwrap().1;
+            assert_eq!(stats.consecutive_failures, 1, "{relay}");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_message_skipped_while_demoted_goes_out_once_the_relay_is_tried_again() {
+        use nostr_sdk::local_relay::MockRelay;
+
+        let healthy_relay = MockRelay::run().await.expect("mock relay");
+        let demoted_relay = MockRelay::run().await.expect("mock relay");
+        let healthy = healthy_relay.url().await;
+        let demoted = demoted_relay.url().await;
+        let client = Client::default();
+        client.add_relay(healthy.clone()).await.unwrap();
+        client.add_relay(demoted.clone()).await.unwrap();
+        client
+            .connect()
+            .and_wait(std::time::Duration::from_secs(2))
+            .await;
+        let now = chrono::Utc::now().timestamp();
+        for _ in 0..DEMOTE_AFTER_FAILURES {
+            global().record(&demoted, &SendOutcome::Rejected("down".into()), now);
+        }
+
+        let event = event_at(now as u64);
+        let (output, skipped) = publish(&client, &event).await.unwrap();
+        assert!(output.success.contains_key(&healthy));
+        assert_eq!(skipped, vec![demoted.clone()]);
+        global().queue_message_catch_up(&event, missed_relays(&output, &skipped));
+
+        // Still demoted: the message waits.
+        catch_up_once(&client).await;
+        assert_eq!(
+            global().message_catch_up_missing(event.id),
+            Some(HashSet::from([demoted.clone()]))
+        );
+
+        // The demotion runs out: the relay gets the same event.
+        lock(&global().relays)
+            .get_mut(&demoted)
+            .unwrap()
+            .demoted_until = Some(now - 1);
+        catch_up_once(&client).await;
+        assert_eq!(global().message_catch_up_missing(event.id), None);
+        assert!(!global().is_demoted(&demoted, now));
+    }
+
+    #[test]
+    fn catch_up_keeps_only_the_newest_revision() {
+        let health = RelayHealth::default();
+        let order_id = Uuid::new_v4();
+        let relay = url("wss://slow.example");
+        let (old, new) = (event_at(10), event_at(20));
+
+        health.queue_catch_up(order_id, &new, HashSet::from([relay.clone()]));
+        health.queue_catch_up(order_id, &old, HashSet::new());
+        health.queue_catch_up(order_id, &old, HashSet::from([url("wss://x.example")]));
+        assert_eq!(
+            health.catch_up_missing(order_id),
+            Some(HashSet::from([relay.clone()]))
+        );
+        assert_eq!(health.pending_catch_up(&relay), 1);
+
+        health.clear_catch_up(order_id, &old);
+        assert!(health.catch_up_missing(order_id).is_some());
+        health.clear_catch_up(order_id, &new);
+        assert!(health.catch_up_missing(order_id).is_none());
+    }
+}
@@ -0,0 +1,592 @@
+//! Volume- and recency-weighted reputation (`docs/REPUTATION.md`).
+//!
+//! The `users` table keeps the running average mostro-core computes
+//! (`total_rating`, `total_reviews`, ...), where every rating counts the
+//! same forever. On top of it, every rating is also stored in
+//! `user_ratings` with the trade's volume and time, and [`profile`] derives:
+//!
+//! * `score`: the average rating weighted by trade volume (logarithmically,
+//!   so large trades count more without one whale trade dominating) and by
+//!   age (halved every `reputation_half_life_days`).
+//! * `trades`: completed trades of the identity, including those a solver
+//!   completed for the buyer.
+//! * `volume_band`: band of the identity's total completed volume; the exact
+//!   figure is not published.
+//! * `feedback`: how often each structured feedback label (`fast-payment`,
+//!   `unresponsive`, ...) came with a rating.
+//! * `disputes_lost`: disputes resolved against the identity. Each is kept in
+//!   `dispute_marks` and counts as a lowest rating in `score`, whether or not
+//!   the counterparty rates.
+//!
+//! The profile is published in the order events' `rating` tag and in the
+//! rating event (kind 38384).
+
+use crate::config::settings::Settings;
+use crate::db::{Db, DbPool};
+use mostro_core::error::MostroError::{self, *};
+use mostro_core::error::{CantDoReason, ServiceError};
+use mostro_core::prelude::{BondResolution, Order, MIN_RATING};
+use mostro_core::user::User;
+use nostr_sdk::prelude::{Tag, Timestamp};
+use uuid::Uuid;
+
+/// Trade volume at which a rating weighs twice as much as a dust trade's.
+const VOLUME_REFERENCE_SATS: f64 = 100_000.0;
+
+const SECONDS_IN_DAY: i64 = 86_400;
+
+/// Lower bounds, in sats, and labels of the published volume bands.
+const VOLUME_BANDS: [(i64, &str); 5] = [
+    (100_000_000, "100m+"),
+    (10_000_000, "10m-100m"),
+    (1_000_000, "1m-10m"),
+    (100_000, "100k-1m"),
+    (0, "0-100k"),
+];
+
+/// Structured feedback labels a rating may carry; positive ones first.
+pub const FEEDBACK_LABELS: [&str; 6] = [
+    "fast-payment",
+    "good-communication",
+    "smooth-trade",
+    "slow-payment",
+    "unresponsive",
+    "rude",
+];
+
+/// Published reputation of an identity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reputation {
+    /// Running average from the `users` table.
+    pub total_rating: f64,
+    pub total_reviews: i64,
+    /// When the identity was first seen; `0` for a new identity.
+    pub created_at: i64,
+    /// Volume- and recency-weighted average rating, 0 to 5.
+    pub score: f64,
+    /// Completed trades.
+    pub trades: i64,
+    /// Band of the total volume of the completed trades.
+    pub volume_band: &'static str,
+    /// Times each feedback label was received, in [`FEEDBACK_LABELS`] order;
+    /// labels never received are left out.
+    pub feedback: Vec<(&'static str, i64)>,
+    /// Disputes resolved against the identity.
+    pub disputes_lost: i64,
+}
+
+impl Reputation {
+    /// Reputation of an identity without any history.
+    pub fn unrated() -> Self {
+        Self {
+            total_rating: 0.0,
+            total_reviews: 0,
+            created_at: 0,
+            score: 0.0,
+            trades: 0,
+            volume_band: volume_band(0),
+            feedback: Vec::new(),
+            disputes_lost: 0,
+        }
+    }
+
+    /// Days since the identity was first seen; `0` for a new identity.
+    pub fn operating_days(&self) -> u64 {
+        u64::try_from(self.created_at)
+            .ok()
+            .filter(|ts| *ts > 0)
+            .map(|ts| Timestamp::now().as_secs().saturating_sub(ts) / SECONDS_IN_DAY as u64)
+            .unwrap_or(0)
+    }
+
+    /// `score`, `trades`, `volume`, `disputes_lost` and, once any was
+    /// received, `feedback` tags of the rating event.
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::custom("score", vec![format!("{:.2}", self.score)]),
+            Tag::custom("trades", vec![self.trades.to_string()]),
+            Tag::custom("volume", vec![self.volume_band.to_string()]),
+            Tag::custom("disputes_lost", vec![self.disputes_lost.to_string()]),
+        ];
+        if !self.feedback.is_empty() {
+            let counts = self
+                .feedback
+                .iter()
+                .map(|(label, count)| format!("{label}:{count}"))
+                .collect::<Vec<_>>();
+            tags.push(Tag::custom("feedback", counts));
+        }
+        tags
+    }
+}
+
+/// Band label of a total volume of `sats`.
+pub fn volume_band(sats: i64) -> &'static str {
+    VOLUME_BANDS
+        .iter()
+        .find(|(floor, _)| sats >= *floor)
+        .map_or(VOLUME_BANDS[VOLUME_BANDS.len() - 1].1, |(_, label)| label)
+}
+
+/// Weight of a rating given on a trade of `amount_sats`, `age_secs` ago.
+pub fn rating_weight(amount_sats: i64, age_secs: i64, half_life_days: u32) -> f64 {
+    let volume = 1.0 + (1.0 + amount_sats.max(0) as f64 / VOLUME_REFERENCE_SATS).log2();
+    let recency = if half_life_days == 0 {
+        1.0
+    } else {
+        let half_life_secs = (i64::from(half_life_days) * SECONDS_IN_DAY) as f64;
+        0.5f64.powf(age_secs.max(0) as f64 / half_life_secs)
+    };
+    volume * recency
+}
+
+/// Weighted average of `(rating, amount_sats, created_at)` rows on top of
+/// `legacy_reviews` ratings of `legacy_rating` at base weight, or `None` when
+/// there are none.
+fn weighted_score(
+    ratings: &[(i64, i64, i64)],
+    legacy_rating: f64,
+    legacy_reviews: i64,
+    now: i64,
+    half_life_days: u32,
+) -> Option<f64> {
+    let legacy_weight = legacy_reviews.max(0) as f64 * rating_weight(0, 0, half_life_days);
+    let (sum, weights) = ratings.iter().fold(
+        (legacy_rating * legacy_weight, legacy_weight),
+        |(sum, weights), (rating, amount, created_at)| {
+            let weight = rating_weight(*amount, now - created_at, half_life_days);
+            (sum + *rating as f64 * weight, weights + weight)
+        },
+    );
+    (weights > 0.0).then(|| sum / weights)
+}
+
+/// Checks `labels` against [`FEEDBACK_LABELS`] and joins them in that order
+/// for storage. Unknown or repeated labels are `InvalidParameters`.
+pub fn feedback_column(labels: &[String]) -> Result<String, MostroError> {
+    let mut known = Vec::with_capacity(labels.len());
+    for label in labels {
+        let Some(index) = FEEDBACK_LABELS.iter().position(|known| known == label) else {
+            return Err(MostroCantDo(CantDoReason::InvalidParameters));
+        };
+        if known.contains(&index) {
+            return Err(MostroCantDo(CantDoReason::InvalidParameters));
+        }
+        known.push(index);
+    }
+    known.sort_unstable();
+    Ok(known
+        .into_iter()
+        .map(|index| FEEDBACK_LABELS[index])
+        .collect::<Vec<_>>()
+        .join(","))
+}
+
+/// Stores `rating` received by `pubkey` for the trade `order_id`, with the
+/// feedback labels already checked by [`feedback_column`].
+///
+/// `executor` should be the transaction that claims the order's rating flag,
+/// so the rating is stored exactly when the aggregate is updated.
+pub async fn record_rating<'e, E>(
+    executor: E,
+    pubkey: &str,
+    order_id: Uuid,
+    rating: u8,
+    feedback: &str,
+    amount_sats: i64,
+    now: i64,
+) -> Result<(), MostroError>
+where
+    E: sqlx::Executor<'e, Database = Db>,
+{
+    sqlx::query(
+        "INSERT INTO user_ratings \
+         (pubkey, order_id, rating, feedback, amount_sats, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+    )
+    .bind(pubkey)
+    .bind(order_id)
+    .bind(i64::from(rating))
+    .bind(feedback)
+    .bind(amount_sats)
+    .bind(now)
+    .execute(executor)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    Ok(())
+}
+
+/// Side of a trade that lost its dispute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeLoser {
+    Buyer,
+    Seller,
+}
+
+impl DisputeLoser {
+    /// Losers of a dispute closed by `admin-cancel`. The ruling refunds the
+    /// seller, so it goes against the buyer; only a bond resolution that
+    /// slashes the seller alone says the seller was at fault instead, and one
+    /// slashing both sides marks both. Bonds being disabled changes nothing.
+    pub fn of_cancel(resolution: &BondResolution) -> Vec<Self> {
+        match (resolution.slash_buyer, resolution.slash_seller) {
+            (false, true) => vec![DisputeLoser::Seller],
+            (true, true) => vec![DisputeLoser::Buyer, DisputeLoser::Seller],
+            _ => vec![DisputeLoser::Buyer],
+        }
+    }
+}
+
+/// Marks the losing side of the dispute of `order`; a repeated resolution of
+/// the same order is ignored. Returns `false` when the loser traded in full
+/// privacy mode and has no reputation to mark.
+///
+/// Solvers call it when they resolve a dispute: `admin-settle` pays the
+/// buyer, so the seller lost; for `admin-cancel` see
+/// [`DisputeLoser::of_cancel`]. The mark does not depend on the counterparty
+/// rating, and a failure to write it must not undo the resolution, so
+/// callers only log the error (`docs/REPUTATION.md`).
+pub async fn record_lost_dispute(
+    pool: &DbPool,
+    order: &Order,
+    loser: DisputeLoser,
+    now: i64,
+) -> Result<bool, MostroError> {
+    let (buyer_identity, seller_identity) = order
+        .is_full_privacy_order()
+        .map_err(|_| MostroInternalErr(ServiceError::InvalidPubkey))?;
+    let identity = match loser {
+        DisputeLoser::Buyer => buyer_identity,
+        DisputeLoser::Seller => seller_identity,
+    };
+    let Some(identity) = identity else {
+        return Ok(false);
+    };
+    sqlx::query(
+        "INSERT INTO dispute_marks (pubkey, order_id, amount_sats, created_at) \
+         VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+    )
+    .bind(identity)
+    .bind(order.id)
+    .bind(order.amount)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    Ok(true)
+}
+
+/// Reputation of `user`. The score is also kept in `users.reputation_score`,
+/// so the order book filters makers on the score last published for them.
+///
+/// Reviews counted in `total_reviews` before per-trade ratings were stored
+/// have no rows in `user_ratings`; each of them enters the score at base
+/// weight with the running average `total_rating`, so that history is not
+/// dropped once the identity is rated again.
+pub async fn profile(pool: &DbPool, user: &User) -> Result<Reputation, MostroError> {
+    let db_err = |e: sqlx::Error| MostroInternalErr(ServiceError::DbAccessError(e.to_string()));
+
+    let ratings: Vec<(i64, i64, i64)> = sqlx::query_as(
+        "SELECT rating, amount_sats, created_at FROM user_ratings WHERE pubkey = $1 \
+         UNION ALL SELECT $2, amount_sats, created_at FROM dispute_marks WHERE pubkey = $1",
+    )
+    .bind(&user.pubkey)
+    .bind(i64::from(MIN_RATING))
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    let disputes_lost: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM dispute_marks WHERE pubkey = $1")
+            .bind(&user.pubkey)
+            .fetch_one(pool)
+            .await
+            .map_err(db_err)?;
+
+    let feedback_rows: Vec<String> = sqlx::query_scalar(
+        "SELECT feedback FROM user_ratings WHERE pubkey = $1 AND feedback != ''",
+    )
+    .bind(&user.pubkey)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let feedback = FEEDBACK_LABELS
+        .iter()
+        .map(|label| {
+            let count = feedback_rows
+                .iter()
+                .filter(|row| row.split(',').any(|given| given == *label))
+                .count() as i64;
+            (*label, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect();
+
+    let (trades, volume): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), CAST(COALESCE(SUM(amount), 0) AS BIGINT) FROM orders \
+         WHERE status IN ('success', 'completed-by-admin') \
+         AND (master_buyer_pubkey = $1 OR master_seller_pubkey = $1)",
+    )
+    .bind(&user.pubkey)
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)?;
+
+    let stored_reviews: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM user_ratings WHERE pubkey = $1")
+            .bind(&user.pubkey)
+            .fetch_one(pool)
+            .await
+            .map_err(db_err)?;
+
+    let now = Timestamp::now().as_secs() as i64;
+    let score = weighted_score(
+        &ratings,
+        user.total_rating,
+        user.total_reviews - stored_reviews,
+        now,
+        Settings::get_reputation_half_life_days(),
+    )
+    .unwrap_or_default();
+    sqlx::query("UPDATE users SET reputation_score = $1 WHERE pubkey = $2")
+        .bind(score)
+        .bind(&user.pubkey)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+
+    Ok(Reputation {
+        total_rating: user.total_rating,
+        total_reviews: user.total_reviews,
+        created_at: user.created_at,
+        score,
+        trades,
+        volume_band: volume_band(volume),
+        feedback,
+        disputes_lost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_grows_with_volume_and_halves_per_half_life() {
+        let dust = rating_weight(0, 0, 180);
+        let reference = rating_weight(100_000, 0, 180);
+        assert!((dust - 1.0).abs() < f64::EPSILON);
+        assert!((reference - 2.0).abs() < f64::EPSILON);
+        // A hundredfold trade does not weigh a hundredfold
+        assert!(rating_weight(10_000_000, 0, 180) < 10.0);
+
+        let aged = rating_weight(100_000, 180 * SECONDS_IN_DAY, 180);
+        assert!((aged - 1.0).abs() < 1e-9);
+        assert!((rating_weight(100_000, 180 * SECONDS_IN_DAY, 0) - 2.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn recent_large_trades_dominate_the_score() {
+        let now = 1_000 * SECONDS_IN_DAY;
+        let two_years = 730 * SECONDS_IN_DAY;
+        let ratings = [
+            (5, 1_000, now - two_years),
+            (5, 1_000, now - two_years),
+            (2, 2_000_000, now),
+        ];
+        let score = weighted_score(&ratings, 0.0, 0, now, 180).unwrap();
+        assert!(score < 2.5, "old dust ratings barely count: {score}");
+        assert_eq!(weighted_score(&[], 4.0, 0, now, 180), None);
+        assert_eq!(weighted_score(&[], 4.0, 3, now, 180), Some(4.0));
+    }
+
+    #[test]
+    fn a_cancel_marks_the_buyer_unless_only_the_seller_is_slashed() {
+        let ruling = |slash_seller, slash_buyer| BondResolution {
+            slash_seller,
+            slash_buyer,
+        };
+        assert_eq!(
+            DisputeLoser::of_cancel(&ruling(false, false)),
+            vec![DisputeLoser::Buyer]
+        );
+        assert_eq!(
+            DisputeLoser::of_cancel(&ruling(false, true)),
+            vec![DisputeLoser::Buyer]
+        );
+        assert_eq!(
+            DisputeLoser::of_cancel(&ruling(true, false)),
+            vec![DisputeLoser::Seller]
+        );
+        assert_eq!(
+            DisputeLoser::of_cancel(&ruling(true, true)),
+            vec![DisputeLoser::Buyer, DisputeLoser::Seller]
+        );
+    }
+
+    #[test]
+    fn volume_bands_cover_every_amount() {
+        assert_eq!(volume_band(0), "0-100k");
+        assert_eq!(volume_band(99_999), "0-100k");
+        assert_eq!(volume_band(100_000), "100k-1m");
+        assert_eq!(volume_band(5_000_000), "1m-10m");
+        assert_eq!(volume_band(250_000_000), "100m+");
+    }
+
+    #[tokio::test]
+    async fn profile_counts_completed_trades_and_recorded_ratings() {
+        let pool = crate::db::test_utils::migrated_pool().await;
+        let pubkey = "a".repeat(64);
+        for (status, amount) in [
+            ("success", 600_000),
+            ("completed-by-admin", 500_000),
+            ("canceled-by-admin", 7),
+            ("active", 9),
+        ] {
+            sqlx::query(
+                "INSERT INTO orders (id, kind, event_id, status, premium, payment_method, \
+                 amount, fiat_code, fiat_amount, created_at, expires_at, master_seller_pubkey) \
+                 VALUES ($1, 'sell', 'e', $2, 0, 'x', $3, 'USD', 10, 1, 1, $4)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(status)
+            .bind(amount)
+            .bind(&pubkey)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let user = User {
+            pubkey: pubkey.clone(),
+            total_rating: 4.0,
+            total_reviews: 3,
+            ..Default::default()
+        };
+
+        let legacy = profile(&pool, &user).await.unwrap();
+        assert_eq!(legacy.trades, 2);
+        assert_eq!(legacy.volume_band, "1m-10m");
+        assert!((legacy.score - 4.0).abs() < f64::EPSILON);
+
+        let now = Timestamp::now().as_secs() as i64;
+        let order_id = Uuid::new_v4();
+        record_rating(&pool, &pubkey, order_id, 3, "", 500_000, now)
+            .await
+            .unwrap();
+        // A second rating of the same trade is ignored
+        record_rating(&pool, &pubkey, order_id, 5, "", 500_000, now)
+            .await
+            .unwrap();
+        // The three earlier reviews still count, at base weight
+        let user = User {
+            total_rating: 3.75,
+            total_reviews: 4,
+            ..user
+        };
+        sqlx::query("INSERT INTO users (pubkey, total_rating, created_at) VALUES ($1, 3.75, 1)")
+            .bind(&pubkey)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let rated = profile(&pool, &user).await.unwrap();
+        let weight = rating_weight(500_000, 0, Settings::get_reputation_half_life_days());
+        let expected = (3.0 * 3.75 + 3.0 * weight) / (3.0 + weight);
+        assert!((rated.score - expected).abs() < 1e-6, "{}", rated.score);
+        // The order book filters on the score just published
+        let stored: f64 =
+            sqlx::query_scalar("SELECT reputation_score FROM users WHERE pubkey = $1")
+                .bind(&pubkey)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!((stored - expected).abs() < 1e-4, "{stored}");
+    }
+
+    #[test]
+    fn feedback_labels_are_checked_and_stored_in_canonical_order() {
+        let labels = |list: &[&str]| list.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            feedback_column(&labels(&["unresponsive", "fast-payment"])).unwrap(),
+            "fast-payment,unresponsive"
+        );
+        assert_eq!(feedback_column(&[]).unwrap(), "");
+        for invalid in [&["friendly"][..], &["rude", "rude"][..]] {
+            assert!(matches!(
+                feedback_column(&labels(invalid)),
+                Err(MostroCantDo(CantDoReason::InvalidParameters))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn lost_disputes_and_feedback_show_in_the_profile() {
+        let pool = crate::db::test_utils::migrated_pool().await;
+        let key = || nostr_sdk::prelude::Keys::generate().public_key().to_hex();
+        let seller = key();
+        let buyer = key();
+        let order = Order {
+            id: Uuid::new_v4(),
+            amount: 100_000,
+            seller_pubkey: Some(key()),
+            buyer_pubkey: Some(key()),
+            master_seller_pubkey: Some(seller.clone()),
+            master_buyer_pubkey: Some(buyer.clone()),
+            ..Default::default()
+        };
+        let now = Timestamp::now().as_secs() as i64;
+        record_rating(
+            &pool,
+            &seller,
+            Uuid::new_v4(),
+            5,
+            "fast-payment",
+            100_000,
+            now,
+        )
+        .await
+        .unwrap();
+        record_rating(
+            &pool,
+            &seller,
+            Uuid::new_v4(),
+            5,
+            "fast-payment,smooth-trade",
+            100_000,
+            now,
+        )
+        .await
+        .unwrap();
+        assert!(
+            record_lost_dispute(&pool, &order, DisputeLoser::Seller, now)
+                .await
+                .unwrap()
+        );
+        // Resolving the same dispute again does not mark twice
+        record_lost_dispute(&pool, &order, DisputeLoser::Seller, now)
+            .await
+            .unwrap();
+
+        let user = User {
+            pubkey: seller,
+            ..Default::default()
+        };
+        let reputation = profile(&pool, &user).await.unwrap();
+        assert_eq!(reputation.disputes_lost, 1);
+        assert!((reputation.score - 11.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            reputation.feedback,
+            vec![("fast-payment", 2), ("smooth-trade", 1)]
+        );
+
+        // A full-privacy loser has no identity to mark
+        let private = Order {
+            master_buyer_pubkey: order.buyer_pubkey.clone(),
+            ..order
+        };
+        assert!(
+            !record_lost_dispute(&pool, &private, DisputeLoser::Buyer, now)
+                .await
+                .unwrap()
+        );
+    }
+}
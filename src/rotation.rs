@@ -0,0 +1,351 @@
+//! Node key rotation with a signed handoff (`docs/KEY_ROTATION.md`).
+//!
+//! To rotate, the operator moves the old nsec to
+//! `[nostr].previous_nsec_privkey` and sets the new one as
+//! `[nostr].nsec_privkey`. On the first start with that pair, [`start`]
+//! records the rotation, pins every trade then in flight to the old key
+//! (`key_rotation_orders`), republishes the order book under the new key and
+//! asks relays to delete the old key's copies. On every start it publishes
+//! the handoff event: signed by the old key, naming the new key, and carrying
+//! the new key's signature over both pubkeys, so the new key's holder agreed
+//! to it. The handoff is only as trustworthy as the old key: whoever holds it
+//! can mint a new keypair and sign a handoff that verifies.
+//!
+//! The old key stays subscribed as the retired instance
+//! ([`crate::instance::retired`]). It only answers messages about pinned
+//! trades, and those trades' messages and order events stay signed with it,
+//! so counterparties who never saw the handoff can finish. Once none is left
+//! in flight, [`check_completion`] marks the rotation complete and the old
+//! key stops answering.
+
+use crate::db::Crud;
+use crate::db::DbPool;
+use crate::db::TERMINAL_ORDER_STATUSES;
+use crate::instance::{self, DEFAULT_INSTANCE};
+use bitcoin::hashes::{sha256, Hash};
+use mostro_core::error::MostroError::{self, *};
+use mostro_core::error::ServiceError;
+use mostro_core::prelude::*;
+use nostr_sdk::prelude::Kind as NostrKind;
+use nostr_sdk::prelude::*;
+use sqlx::AssertSqlSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Domain separator of the new key's proof signature.
+const HANDOFF_DOMAIN: &str = "mostro-key-handoff";
+
+/// Set once every pinned trade has finished; the retired key then serves
+/// nothing.
+static COMPLETE: AtomicBool = AtomicBool::new(false);
+
+fn db_err(e: sqlx::Error) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+fn nostr_err(e: impl std::fmt::Display) -> MostroError {
+    MostroInternalErr(ServiceError::NostrError(e.to_string()))
+}
+
+/// What the new key signs: `sha256("mostro-key-handoff:<old hex>:<new hex>")`.
+fn handoff_digest(old: &PublicKey, new: &PublicKey) -> [u8; 32] {
+    let preimage = format!("{HANDOFF_DOMAIN}:{}:{}", old.to_hex(), new.to_hex());
+    sha256::Hash::hash(preimage.as_bytes()).to_byte_array()
+}
+
+/// The handoff event: kind 38387 signed by `old`, `d` = old pubkey, a `p` tag
+/// with the new pubkey and a `proof` tag with the new key's signature.
+pub fn handoff_event(old: &Keys, new: &Keys, rotated_at: i64) -> Result<Event, MostroError> {
+    let proof = new.sign_schnorr(handoff_digest(&old.public_key(), &new.public_key()));
+    let tags = Tags::from_list(vec![
+        Tag::public_key(new.public_key()),
+        Tag::custom("proof", vec![proof.to_hex()]),
+        Tag::custom("rotated_at", vec![rotated_at.to_string()]),
+    ]);
+    let content = format!(
+        "This Mostro node now uses {}",
+        new.public_key().to_bech32().map_err(nostr_err)?
+    );
+    crate::nip33::new_key_handoff_event(old, &content, tags).map_err(nostr_err)
+}
+
+/// The new pubkey a handoff event points to, if both its signature and the
+/// new key's proof check out.
+pub fn verify_handoff(event: &Event) -> Option<PublicKey> {
+    if event.kind != NostrKind::Custom(crate::config::constants::NOSTR_KEY_HANDOFF_EVENT_KIND)
+        || event.verify().is_err()
+        || event.tags.identifier() != Some(event.pubkey.to_hex())
+    {
+        return None;
+    }
+    let new = event.tags.public_keys().next()?;
+    let proof = event
+        .tags
+        .iter()
+        .find(|tag| tag.kind() == "proof")
+        .and_then(|tag| tag.content())?;
+    let proof =
+        secp256k1::schnorr::Signature::from_slice(Signature::from_hex(proof).ok()?.as_bytes())
+            .ok()?;
+    secp256k1::Secp256k1::verification_only()
+        .verify_schnorr(
+            &proof,
+            &handoff_digest(&event.pubkey, &new),
+            &new.xonly().ok()?,
+        )
+        .ok()?;
+    Some(new)
+}
+
+/// Record the rotation from `old` to `new` and pin the `default` instance's
+/// trades in flight. Returns `false` when the rotation was already recorded.
+/// Pending orders (and those still waiting for the maker bond) are not in
+/// flight: they move to the new key.
+pub async fn record_rotation(
+    pool: &DbPool,
+    old: &PublicKey,
+    new: &PublicKey,
+    now: i64,
+) -> Result<bool, MostroError> {
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    let inserted = sqlx::query(
+        "INSERT INTO key_rotations (old_pubkey, new_pubkey, rotated_at) VALUES ($1, $2, $3) \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(old.to_hex())
+    .bind(new.to_hex())
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?
+    .rows_affected()
+        == 1;
+    if inserted {
+        let sql = format!(
+            "INSERT INTO key_rotation_orders (order_id, old_pubkey) \
+             SELECT id, $1 FROM orders WHERE instance = $2 \
+             AND status NOT IN ({TERMINAL_ORDER_STATUSES}, 'pending', 'waiting-maker-bond') \
+             ON CONFLICT DO NOTHING"
+        );
+        sqlx::query(AssertSqlSafe(sql))
+            .bind(old.to_hex())
+            .bind(DEFAULT_INSTANCE)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+    }
+    tx.commit().await.map_err(db_err)?;
+    Ok(inserted)
+}
+
+/// Whether the retired key serves `order_id`: the order was pinned at
+/// rotation time and has not since gone back to the order book.
+pub async fn serves(pool: &DbPool, order_id: Uuid) -> bool {
+    if COMPLETE.load(Ordering::Relaxed) {
+        return false;
+    }
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM key_rotation_orders k JOIN orders o ON o.id = k.order_id \
+         WHERE k.order_id = $1 AND o.status != 'pending'",
+    )
+    .bind(order_id)
+    .fetch_one(pool)
+    .await
+    .map(|n| n > 0)
+    .unwrap_or_else(|e| {
+        error!("Could not read pinned orders: {e}");
+        false
+    })
+}
+
+/// Pinned trades of `old` that have not finished yet.
+pub async fn in_flight(pool: &DbPool, old: &PublicKey) -> Result<i64, MostroError> {
+    let sql = format!(
+        "SELECT COUNT(*) FROM key_rotation_orders k JOIN orders o ON o.id = k.order_id \
+         WHERE k.old_pubkey = $1 AND o.status NOT IN ({TERMINAL_ORDER_STATUSES}, 'pending')"
+    );
+    sqlx::query_scalar(AssertSqlSafe(sql))
+        .bind(old.to_hex())
+        .fetch_one(pool)
+        .await
+        .map_err(db_err)
+}
+
+/// Mark the rotation complete once no pinned trade is in flight. Returns
+/// whether it is complete.
+pub async fn check_completion(pool: &DbPool) -> Result<bool, MostroError> {
+    let Some(retired) = instance::retired() else {
+        return Ok(true);
+    };
+    if COMPLETE.load(Ordering::Relaxed) {
+        return Ok(true);
+    }
+    let old = retired.keys.public_key();
+    let left = in_flight(pool, &old).await?;
+    if left > 0 {
+        info!("Key rotation: {left} trade(s) still in flight under the retired key");
+        return Ok(false);
+    }
+    sqlx::query(
+        "UPDATE key_rotations SET completed_at = $1 WHERE old_pubkey = $2 AND completed_at IS NULL",
+    )
+    .bind(chrono::Utc::now().timestamp())
+    .bind(old.to_hex())
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    COMPLETE.store(true, Ordering::Relaxed);
+    info!(
+        "Key rotation complete: no trade is left under {}; previous_nsec_privkey can be removed",
+        old.to_hex()
+    );
+    Ok(true)
+}
+
+/// Republish every pending `default` order under `new` and ask relays to
+/// delete the retired key's copies (NIP-09).
+async fn move_order_book(
+    pool: &DbPool,
+    client: &Client,
+    old: &Keys,
+    new: &Keys,
+) -> Result<usize, MostroError> {
+    let orders: Vec<Order> =
+        sqlx::query_as("SELECT * FROM orders WHERE status = 'pending' AND instance = $1")
+            .bind(DEFAULT_INSTANCE)
+            .fetch_all(pool)
+            .await
+            .map_err(db_err)?;
+    let mut coordinates = Vec::with_capacity(orders.len());
+    for order in &orders {
+        let updated = crate::util::update_order_event(new, Status::Pending, order).await?;
+        updated.update(pool).await.map_err(db_err)?;
+        coordinates.push(
+            Coordinate::new(NostrKind::Custom(NOSTR_ORDER_EVENT_KIND), old.public_key())
+                .identifier(order.id.to_string()),
+        );
+    }
+    if !coordinates.is_empty() {
+        let deletion = EventDeletionRequest::new()
+            .coordinates(coordinates)
+            .reason("Mostro key rotated")
+            .finalize(old)
+            .map_err(nostr_err)?;
+        if let Err(e) = client.send_event(&deletion).await {
+            warn!("Could not publish the deletion of the retired key's orders: {e}");
+        }
+    }
+    Ok(orders.len())
+}
+
+/// Record a pending key rotation, move the order book on its first start and
+/// publish the handoff event. Does nothing without a retired key.
+pub async fn start(pool: &DbPool, client: &Client) -> Result<(), MostroError> {
+    let Some(retired) = instance::retired() else {
+        return Ok(());
+    };
+    let new =
+        crate::config::get_mostro_keys().ok_or_else(|| nostr_err("Nostr keys not initialized"))?;
+    let old = &retired.keys;
+    let now = chrono::Utc::now().timestamp();
+
+    if record_rotation(pool, &old.public_key(), &new.public_key(), now).await? {
+        let moved = move_order_book(pool, client, old, new).await?;
+        info!("Key rotation recorded; {moved} pending order(s) republished under the new key");
+    }
+    let rotated_at: i64 =
+        sqlx::query_scalar("SELECT rotated_at FROM key_rotations WHERE old_pubkey = $1")
+            .bind(old.public_key().to_hex())
+            .fetch_one(pool)
+            .await
+            .map_err(db_err)?;
+
+    let handoff = handoff_event(old, new, rotated_at)?;
+    if let Err(e) = client.send_event(&handoff).await {
+        warn!("Could not publish the key handoff event: {e}");
+    }
+    check_completion(pool).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_order(pool: &DbPool, status: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO orders (id, kind, event_id, status, premium, payment_method, \
+             amount, fiat_code, fiat_amount, created_at, expires_at) \
+             VALUES ($1, 'sell', 'e', $2, 0, 'ln', 100, 'USD', 10, 0, 0)",
+        )
+        .bind(id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    #[test]
+    fn handoff_is_verified_with_both_keys() {
+        let _ = crate::config::MOSTRO_CONFIG.set(crate::app::context::test_utils::test_settings());
+        let old = Keys::generate();
+        let new = Keys::generate();
+        let event = handoff_event(&old, &new, 1_700_000_000).unwrap();
+        assert_eq!(verify_handoff(&event), Some(new.public_key()));
+        assert_eq!(event.tags.identifier(), Some(old.public_key().to_hex()));
+
+        // A handoff to a key whose owner did not sign the proof is rejected.
+        let stranger = Keys::generate();
+        let forged = crate::nip33::new_key_handoff_event(
+            &old,
+            "",
+            Tags::from_list(
+                event
+                    .tags
+                    .iter()
+                    .map(|tag| {
+                        if tag.kind() == "p" {
+                            Tag::public_key(stranger.public_key())
+                        } else {
+                            tag.clone()
+                        }
+                    })
+                    .collect(),
+            ),
+        )
+        .unwrap();
+        assert_eq!(verify_handoff(&forged), None);
+    }
+
+    #[tokio::test]
+    async fn rotation_pins_trades_in_flight_once() {
+        let pool = crate::db::test_utils::migrated_pool().await;
+        let (old, new) = (Keys::generate().public_key(), Keys::generate().public_key());
+        let active = insert_order(&pool, "active").await;
+        let pending = insert_order(&pool, "pending").await;
+        let done = insert_order(&pool, "success").await;
+
+        assert!(record_rotation(&pool, &old, &new, 10).await.unwrap());
+        assert!(serves(&pool, active).await);
+        assert!(!serves(&pool, pending).await);
+        assert!(!serves(&pool, done).await);
+        assert_eq!(in_flight(&pool, &old).await.unwrap(), 1);
+
+        // A restart does not pin orders created under the new key.
+        let later = insert_order(&pool, "active").await;
+        assert!(!record_rotation(&pool, &old, &new, 20).await.unwrap());
+        assert!(!serves(&pool, later).await);
+
+        // A pinned trade that goes back to the book moves to the new key.
+        sqlx::query("UPDATE orders SET status = 'pending' WHERE id = $1")
+            .bind(active)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(!serves(&pool, active).await);
+        assert_eq!(in_flight(&pool, &old).await.unwrap(), 0);
+    }
+}
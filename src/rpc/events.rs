@@ -0,0 +1,160 @@
+//! Admin event feed behind the `WatchEvents` RPC.
+//!
+//! Handlers publish into one process-wide broadcast channel and every
+//! `WatchEvents` call subscribes to it. Publishing never blocks: with no
+//! subscriber the event is dropped, and a subscriber that falls more than
+//! [`EVENT_BUFFER`] events behind skips the backlog (logged) rather than
+//! holding the daemon back.
+
+use crate::app::bond::model::Bond;
+use crate::rpc::admin::{
+    admin_event::Event, AdminEvent, BondStateChanged, DisputeChanged, OrderStatusChanged,
+    PayoutResult,
+};
+use futures::Stream;
+use nostr_sdk::prelude::Timestamp;
+use std::sync::LazyLock;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Events a subscriber may lag behind before it starts losing them.
+const EVENT_BUFFER: usize = 1024;
+
+static ADMIN_EVENTS: LazyLock<broadcast::Sender<AdminEvent>> =
+    LazyLock::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+/// Subscribe to every event published from now on.
+pub fn subscribe() -> broadcast::Receiver<AdminEvent> {
+    ADMIN_EVENTS.subscribe()
+}
+
+/// Turn a subscription into the `WatchEvents` response stream. The stream
+/// ends only if the channel closes, which never happens while the daemon runs.
+pub fn into_stream(
+    rx: broadcast::Receiver<AdminEvent>,
+) -> impl Stream<Item = Result<AdminEvent, tonic::Status>> + Send + 'static {
+    futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((Ok(event), rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("WatchEvents subscriber lagged, {} events skipped", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+fn publish(event: Event) {
+    // `send` only fails when nobody is subscribed.
+    let _ = ADMIN_EVENTS.send(AdminEvent {
+        timestamp: Timestamp::now().as_secs() as i64,
+        event: Some(event),
+    });
+}
+
+/// An order status revision was published.
+pub fn order_status_changed(order_id: Uuid, status: &str) {
+    publish(Event::OrderStatusChanged(OrderStatusChanged {
+        order_id: order_id.to_string(),
+        status: status.to_string(),
+    }));
+}
+
+/// A dispute was opened, taken or resolved.
+pub fn dispute_changed(dispute_id: Uuid, order_id: Uuid, status: &str) {
+    publish(Event::DisputeChanged(DisputeChanged {
+        dispute_id: dispute_id.to_string(),
+        order_id: order_id.to_string(),
+        status: status.to_string(),
+    }));
+}
+
+/// A bond row was persisted with a (possibly) new state.
+pub fn bond_state_changed(bond: &Bond) {
+    bond_transitioned(bond.id, bond.order_id, &bond.state);
+}
+
+/// A targeted `UPDATE bonds SET state = …` won its compare-and-set.
+pub fn bond_transitioned(bond_id: Uuid, order_id: Uuid, state: &str) {
+    publish(Event::BondStateChanged(BondStateChanged {
+        bond_id: bond_id.to_string(),
+        order_id: order_id.to_string(),
+        state: state.to_string(),
+    }));
+}
+
+/// A buyer payout finished, successfully or not.
+pub fn payout_result(order_id: Uuid, success: bool) {
+    publish(Event::PayoutResult(PayoutResult {
+        order_id: order_id.to_string(),
+        success,
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    /// The channel is process-wide and tests run concurrently, so only
+    /// events about our own order count.
+    async fn next_for(
+        stream: &mut (impl Stream<Item = Result<AdminEvent, tonic::Status>> + Unpin),
+        order_id: Uuid,
+    ) -> Event {
+        loop {
+            let event = stream.next().await.unwrap().unwrap().event.unwrap();
+            let id = match &event {
+                Event::OrderStatusChanged(e) => &e.order_id,
+                Event::DisputeChanged(e) => &e.order_id,
+                Event::BondStateChanged(e) => &e.order_id,
+                Event::PayoutResult(e) => &e.order_id,
+            };
+            if *id == order_id.to_string() {
+                return event;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_events_in_publish_order() {
+        let mut stream = Box::pin(into_stream(subscribe()));
+        let order_id = Uuid::new_v4();
+        let dispute_id = Uuid::new_v4();
+
+        order_status_changed(order_id, "dispute");
+        dispute_changed(dispute_id, order_id, "initiated");
+        payout_result(order_id, false);
+
+        assert_eq!(
+            next_for(&mut stream, order_id).await,
+            Event::OrderStatusChanged(OrderStatusChanged {
+                order_id: order_id.to_string(),
+                status: "dispute".to_string(),
+            })
+        );
+        assert_eq!(
+            next_for(&mut stream, order_id).await,
+            Event::DisputeChanged(DisputeChanged {
+                dispute_id: dispute_id.to_string(),
+                order_id: order_id.to_string(),
+                status: "initiated".to_string(),
+            })
+        );
+        assert_eq!(
+            next_for(&mut stream, order_id).await,
+            Event::PayoutResult(PayoutResult {
+                order_id: order_id.to_string(),
+                success: false,
+            })
+        );
+    }
+
+    #[test]
+    fn publishing_without_subscribers_is_a_no_op() {
+        payout_result(Uuid::new_v4(), true);
+    }
+}
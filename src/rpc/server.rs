@@ -1,10 +1,11 @@
 //! RPC server implementation for admin operations
 
+use crate::cashu::CashuClient;
 use crate::config::settings::Settings;
-use crate::lightning::LndConnector;
+use crate::db::DbPool;
+use crate::escrow::EscrowBackend;
 use crate::rpc::service::AdminServiceImpl;
 use nostr_sdk::prelude::Keys;
-use sqlx::{Pool, Sqlite};
 use std::sync::Arc;
 use tonic::transport::Server;
 use tracing::{error, info};
@@ -31,15 +32,33 @@ impl RpcServer {
     pub async fn start(
         &self,
         my_keys: Keys,
-        pool: Arc<Pool<Sqlite>>,
-        ln_client: Arc<tokio::sync::Mutex<LndConnector>>,
+        pool: Arc<DbPool>,
+        ln_client: Arc<tokio::sync::Mutex<Box<dyn EscrowBackend>>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.serve(AdminServiceImpl::new(my_keys, pool, Some(ln_client)))
+            .await
+    }
+
+    /// Start the RPC server in Cashu mode. There is no Lightning client:
+    /// `CancelOrder` and `SettleOrder` resolve disputes through the mint.
+    pub async fn start_cashu(
+        &self,
+        my_keys: Keys,
+        pool: Arc<DbPool>,
+        cashu_client: Arc<CashuClient>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.serve(AdminServiceImpl::new(my_keys, pool, None).with_cashu_client(cashu_client))
+            .await
+    }
+
+    async fn serve(
+        &self,
+        admin_service: AdminServiceImpl,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let addr = format!("{}:{}", self.listen_address, self.port)
             .parse()
             .map_err(|e| format!("Invalid address: {}", e))?;
 
-        let admin_service = AdminServiceImpl::new(my_keys, pool, ln_client);
-
         info!("Starting RPC server on {}", addr);
 
         let server = Server::builder()
@@ -70,6 +89,7 @@ impl Default for RpcServer {
 mod tests {
     use super::*;
     use crate::config::types::RpcSettings;
+    use crate::lightning::LndConnector;
 
     #[test]
     fn test_rpc_settings_default() {
@@ -112,7 +132,7 @@ mod tests {
     }
 
     /// Offline `LndConnector` (lazy connect, no network until first RPC).
-    async fn offline_ln_client() -> Arc<tokio::sync::Mutex<LndConnector>> {
+    async fn offline_ln_client() -> Arc<tokio::sync::Mutex<Box<dyn EscrowBackend>>> {
         let dir = std::env::temp_dir().join(format!("mostro-rpcsrv-{}", std::process::id()));
         std::fs::create_dir_all(&dir).expect("create temp dir");
         let cert = dir.join("tls.cert");
@@ -122,7 +142,7 @@ mod tests {
         let client = fedimint_tonic_lnd::connect("https://127.0.0.1:1".to_string(), cert, macaroon)
             .await
             .expect("lazy connect must not touch the network");
-        Arc::new(tokio::sync::Mutex::new(LndConnector { client }))
+        Arc::new(tokio::sync::Mutex::new(Box::new(LndConnector { client })))
     }
 
     #[test]
@@ -152,7 +172,7 @@ mod tests {
             listen_address: "not an address".to_string(),
             port: 50051,
         };
-        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
         let result = server
             .start(Keys::generate(), Arc::new(pool), offline_ln_client().await)
             .await;
@@ -168,7 +188,7 @@ mod tests {
             listen_address: "8.8.8.8".to_string(),
             port: 1,
         };
-        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
         let result = server
             .start(Keys::generate(), Arc::new(pool), offline_ln_client().await)
             .await;
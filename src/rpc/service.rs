@@ -1,45 +1,301 @@
 //! RPC service implementation for admin operations
 
+use crate::app::context::AppContext;
+use crate::app::evidence::{list_evidence, Evidence};
+use crate::cashu::CashuClient;
 use crate::config::settings::Settings;
-
-use crate::lightning::LndConnector;
+use crate::config::MOSTRO_CONFIG;
+use crate::db::Crud;
+use crate::db::DbPool;
+use crate::db::{list_disputes, list_orders, set_user_banned, DisputeFilter, OrderFilter};
+use crate::dispute_sla::{solver_stats, SolverStats};
+use crate::escrow::EscrowBackend;
+use crate::outbox::{list_dead_letters, requeue_dead_letter, OutboxEntry};
+use crate::relay_health;
 use crate::rpc::admin::{
-    admin_service_server::AdminService, AddSolverRequest, AddSolverResponse, CancelOrderRequest,
-    CancelOrderResponse, SettleOrderRequest, SettleOrderResponse, TakeDisputeRequest,
-    TakeDisputeResponse, ValidateDbPasswordRequest, ValidateDbPasswordResponse,
+    admin_service_server::AdminService, AddSolverRequest, AddSolverResponse, AdminEvent,
+    BanUserRequest, BanUserResponse, CancelOrderRequest, CancelOrderResponse, DeadLetterInfo,
+    DisputeEvidenceInfo, DisputeInfo, GetDisputeRequest, GetDisputeResponse, GetOrderRequest,
+    GetOrderResponse, ListDeadLettersRequest, ListDeadLettersResponse, ListDisputeEvidenceRequest,
+    ListDisputeEvidenceResponse, ListDisputesRequest, ListDisputesResponse, ListOrdersRequest,
+    ListOrdersResponse, ListRelaysRequest, ListRelaysResponse, ListSolverStatsRequest,
+    ListSolverStatsResponse, OrderInfo, RelayStatusInfo, RetryDeadLetterRequest,
+    RetryDeadLetterResponse, SettleOrderRequest, SettleOrderResponse, SolverStatsInfo,
+    TakeDisputeRequest, TakeDisputeResponse, UnbanUserRequest, UnbanUserResponse,
+    ValidateDbPasswordRequest, ValidateDbPasswordResponse, WatchEventsRequest,
 };
+use crate::rpc::events;
 use crate::rpc::rate_limiter::RateLimiter;
+use crate::util::get_nostr_client;
+use futures::Stream;
+use mostro_core::dispute::{Dispute, Status as DisputeStatus};
 use mostro_core::nip59::UnwrappedMessage;
-use nostr_sdk::prelude::Keys;
-use sqlx::{Pool, Sqlite};
+use mostro_core::order::{Order, Status as OrderStatus};
+use nostr_sdk::prelude::{Keys, PublicKey};
+use std::pin::Pin;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tonic::{Request, Response, Status};
 use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Page size of the list RPCs when the request leaves `limit` at 0.
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Largest page the list RPCs return, whatever the request asks for.
+const MAX_PAGE_SIZE: u32 = 500;
+
+impl From<Order> for OrderInfo {
+    fn from(order: Order) -> Self {
+        Self {
+            id: order.id.to_string(),
+            kind: order.kind,
+            status: order.status,
+            amount: order.amount,
+            fiat_code: order.fiat_code,
+            fiat_amount: order.fiat_amount,
+            min_amount: order.min_amount,
+            max_amount: order.max_amount,
+            payment_method: order.payment_method,
+            premium: order.premium,
+            fee: order.fee,
+            dev_fee: order.dev_fee,
+            routing_fee: order.routing_fee,
+            creator_pubkey: order.creator_pubkey,
+            buyer_pubkey: order.buyer_pubkey,
+            seller_pubkey: order.seller_pubkey,
+            master_buyer_pubkey: order.master_buyer_pubkey,
+            master_seller_pubkey: order.master_seller_pubkey,
+            buyer_dispute: order.buyer_dispute,
+            seller_dispute: order.seller_dispute,
+            created_at: order.created_at,
+            taken_at: order.taken_at,
+            expires_at: order.expires_at,
+            invoice_held_at: order.invoice_held_at,
+            hash: order.hash,
+            range_parent_id: order.range_parent_id.map(|id| id.to_string()),
+            event_id: order.event_id,
+            failed_payment: order.failed_payment,
+            payment_attempts: order.payment_attempts,
+            cashu_mint_url: order.cashu_mint_url,
+            cashu_escrow_locked_at: order.cashu_escrow_locked_at,
+        }
+    }
+}
+
+impl From<Dispute> for DisputeInfo {
+    fn from(dispute: Dispute) -> Self {
+        Self {
+            id: dispute.id.to_string(),
+            order_id: dispute.order_id.to_string(),
+            status: dispute.status,
+            order_previous_status: dispute.order_previous_status,
+            solver_pubkey: dispute.solver_pubkey,
+            created_at: dispute.created_at,
+            taken_at: dispute.taken_at,
+        }
+    }
+}
+
+impl From<Evidence> for DisputeEvidenceInfo {
+    fn from(item: Evidence) -> Self {
+        Self {
+            id: item.id,
+            dispute_id: item.dispute_id.to_string(),
+            submitted_by: item.submitted_by,
+            kind: item.kind,
+            content: item.content,
+            sha256: item.sha256,
+            occurred_at: item.occurred_at,
+            created_at: item.created_at,
+        }
+    }
+}
+
+impl From<SolverStats> for SolverStatsInfo {
+    fn from(stats: SolverStats) -> Self {
+        Self {
+            solver_pubkey: stats.solver_pubkey,
+            disputes_taken: stats.disputes_taken,
+            disputes_resolved: stats.disputes_resolved,
+            avg_response_secs: stats.avg_response_secs,
+            avg_resolution_secs: stats.avg_resolution_secs,
+            disputes_escalated: stats.disputes_escalated,
+        }
+    }
+}
+
+impl From<OutboxEntry> for DeadLetterInfo {
+    fn from(entry: OutboxEntry) -> Self {
+        Self {
+            id: entry.id,
+            queue: entry.queue,
+            destination: entry.destination,
+            action: entry.action,
+            message: entry.message,
+            attempts: entry.attempts,
+            last_error: entry.last_error,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+/// RPC view of one relay's [`relay_health::RelayStats`].
+fn relay_status_info(
+    url: &nostr_sdk::prelude::RelayUrl,
+    stats: relay_health::RelayStats,
+    now: i64,
+) -> RelayStatusInfo {
+    RelayStatusInfo {
+        url: url.to_string(),
+        score: stats.score,
+        demoted: stats.is_demoted(now),
+        accepted: stats.accepted,
+        rejected: stats.rejected,
+        timed_out: stats.timed_out,
+        consecutive_failures: stats.consecutive_failures,
+        last_error: stats.last_error,
+        last_success_at: stats.last_success_at,
+        last_failure_at: stats.last_failure_at,
+        demoted_until: stats.demoted_until.filter(|until| now < *until),
+        pending_catch_up: relay_health::global().pending_catch_up(url) as u32,
+    }
+}
+
+/// `(limit, offset)` for a list request, `limit` defaulted and capped.
+fn page_bounds(limit: u32, offset: u32) -> (i64, i64) {
+    let limit = match limit {
+        0 => DEFAULT_PAGE_SIZE,
+        n => n.min(MAX_PAGE_SIZE),
+    };
+    (i64::from(limit), i64::from(offset))
+}
+
+/// Offset of the next page, when the current one came back full.
+fn next_offset(offset: i64, limit: i64, returned: usize) -> Option<u32> {
+    (returned as i64 == limit).then(|| u32::try_from(offset + limit).unwrap_or(u32::MAX))
+}
+
+/// Reject a pubkey filter that is not 32-byte hex, the only form stored.
+fn validate_pubkey_filter(pubkey: &Option<String>) -> Result<(), Status> {
+    match pubkey {
+        Some(pk) if pk.len() != 64 || !pk.chars().all(|c| c.is_ascii_hexdigit()) => Err(
+            Status::invalid_argument("pubkey must be a 32-byte hex public key"),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn parse_uuid(id: &str, what: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(id).map_err(|_| Status::invalid_argument(format!("invalid {what}: {id}")))
+}
+
+fn db_status(e: impl std::fmt::Display) -> Status {
+    error!("Admin read RPC failed: {}", e);
+    Status::internal("database error")
+}
+
+impl TryFrom<&ListOrdersRequest> for OrderFilter {
+    type Error = Status;
+
+    fn try_from(req: &ListOrdersRequest) -> Result<Self, Self::Error> {
+        if let Some(status) = &req.status {
+            OrderStatus::from_str(status)
+                .map_err(|_| Status::invalid_argument(format!("unknown order status: {status}")))?;
+        }
+        validate_pubkey_filter(&req.pubkey)?;
+        let (limit, offset) = page_bounds(req.limit, req.offset);
+        Ok(Self {
+            status: req.status.clone(),
+            fiat_code: req.fiat_code.as_ref().map(|c| c.to_uppercase()),
+            created_from: req.created_from,
+            created_to: req.created_to,
+            pubkey: req.pubkey.as_ref().map(|pk| pk.to_lowercase()),
+            limit,
+            offset,
+        })
+    }
+}
+
+impl TryFrom<&ListDisputesRequest> for DisputeFilter {
+    type Error = Status;
+
+    fn try_from(req: &ListDisputesRequest) -> Result<Self, Self::Error> {
+        if let Some(status) = &req.status {
+            DisputeStatus::from_str(status).map_err(|_| {
+                Status::invalid_argument(format!("unknown dispute status: {status}"))
+            })?;
+        }
+        validate_pubkey_filter(&req.pubkey)?;
+        let (limit, offset) = page_bounds(req.limit, req.offset);
+        Ok(Self {
+            status: req.status.clone(),
+            fiat_code: req.fiat_code.as_ref().map(|c| c.to_uppercase()),
+            created_from: req.created_from,
+            created_to: req.created_to,
+            pubkey: req.pubkey.as_ref().map(|pk| pk.to_lowercase()),
+            limit,
+            offset,
+        })
+    }
+}
 
 /// Implementation of the AdminService gRPC service
 pub struct AdminServiceImpl {
     keys: Keys,
-    pool: Arc<Pool<Sqlite>>,
-    ln_client: Arc<tokio::sync::Mutex<LndConnector>>,
+    pool: Arc<DbPool>,
+    /// Lightning escrow. `None` in Cashu mode, where no node client exists:
+    /// `CancelOrder` / `SettleOrder` then go through the Cashu dispute
+    /// handlers instead of settling or cancelling a hold invoice.
+    ln_client: Option<Arc<tokio::sync::Mutex<Box<dyn EscrowBackend>>>>,
+    /// Mint client the Cashu dispute handlers read the escrow state from.
+    /// Attached with [`AdminServiceImpl::with_cashu_client`].
+    cashu_client: Option<Arc<CashuClient>>,
     password_rate_limiter: Arc<RateLimiter>,
 }
 
 impl AdminServiceImpl {
     pub fn new(
         keys: Keys,
-        pool: Arc<Pool<Sqlite>>,
-        ln_client: Arc<tokio::sync::Mutex<LndConnector>>,
+        pool: Arc<DbPool>,
+        ln_client: Option<Arc<tokio::sync::Mutex<Box<dyn EscrowBackend>>>>,
     ) -> Self {
         let retention_secs = Settings::get_rpc().rate_limiter_stale_duration;
         Self {
             keys,
             pool,
             ln_client,
+            cashu_client: None,
             password_rate_limiter: Arc::new(RateLimiter::new(Duration::from_secs(retention_secs))),
         }
     }
 
+    /// Attach the mint client for Cashu mode, mirroring
+    /// [`AppContext::with_cashu_client`].
+    pub fn with_cashu_client(mut self, cashu_client: Arc<CashuClient>) -> Self {
+        self.cashu_client = Some(cashu_client);
+        self
+    }
+
+    /// Build the handler context for one admin call. The mint client is
+    /// attached when present so the Cashu dispute handlers can reach it.
+    fn app_context(&self) -> Result<AppContext, Box<dyn std::error::Error + Send + Sync>> {
+        let nostr_client = get_nostr_client()
+            .map_err(|e| format!("Failed to get Nostr client: {}", e))?
+            .clone();
+        let settings = std::sync::Arc::new(
+            MOSTRO_CONFIG
+                .get()
+                .ok_or_else(|| "MOSTRO_CONFIG not initialized".to_string())?
+                .clone(),
+        );
+        let ctx = AppContext::new(self.pool.clone(), nostr_client, settings, self.keys.clone());
+        Ok(match &self.cashu_client {
+            Some(cashu_client) => ctx.with_cashu_client(cashu_client.clone()),
+            None => ctx,
+        })
+    }
+
     /// Convert admin actions to use existing handlers
     /// This creates the necessary structures to call existing admin handlers
     async fn call_admin_cancel(
@@ -47,7 +303,7 @@ impl AdminServiceImpl {
         order_id: String,
         request_id: Option<String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        use crate::app::admin_cancel::admin_cancel_action;
+        use crate::app::admin_cancel::{admin_cancel_action, admin_cancel_cashu_action};
         use mostro_core::message::{Action, Message};
         use nostr_sdk::prelude::Timestamp;
         use uuid::Uuid;
@@ -75,31 +331,15 @@ impl AdminServiceImpl {
             created_at: Timestamp::now(),
         };
 
-        use crate::app::context::AppContext;
-        use crate::config::MESSAGE_QUEUES;
-        use crate::config::MOSTRO_CONFIG;
-        use crate::util::get_nostr_client;
-
-        let nostr_client = get_nostr_client()
-            .map_err(|e| format!("Failed to get Nostr client: {}", e))?
-            .clone();
-        let settings = std::sync::Arc::new(
-            MOSTRO_CONFIG
-                .get()
-                .ok_or_else(|| "MOSTRO_CONFIG not initialized".to_string())?
-                .clone(),
-        );
-        let ctx = AppContext::new(
-            self.pool.clone(),
-            nostr_client,
-            settings,
-            MESSAGE_QUEUES.queue_order_msg.clone(),
-            self.keys.clone(),
-        );
-        let mut ln_client = self.ln_client.lock().await;
-        admin_cancel_action(&ctx, msg, &event, &self.keys, &mut ln_client)
-            .await
-            .map_err(|e| format!("Admin cancel failed: {}", e))?;
+        let ctx = self.app_context()?;
+        match &self.ln_client {
+            Some(ln_client) => {
+                let mut ln_client = ln_client.lock().await;
+                admin_cancel_action(&ctx, msg, &event, &self.keys, ln_client.as_mut()).await
+            }
+            None => admin_cancel_cashu_action(&ctx, msg, &event, &self.keys).await,
+        }
+        .map_err(|e| format!("Admin cancel failed: {}", e))?;
 
         Ok(())
     }
@@ -109,7 +349,7 @@ impl AdminServiceImpl {
         order_id: String,
         request_id: Option<String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        use crate::app::admin_settle::admin_settle_action;
+        use crate::app::admin_settle::{admin_settle_action, admin_settle_cashu_action};
         use mostro_core::message::{Action, Message};
         use nostr_sdk::prelude::Timestamp;
         use uuid::Uuid;
@@ -130,31 +370,15 @@ impl AdminServiceImpl {
             created_at: Timestamp::now(),
         };
 
-        use crate::app::context::AppContext;
-        use crate::config::MESSAGE_QUEUES;
-        use crate::config::MOSTRO_CONFIG;
-        use crate::util::get_nostr_client;
-
-        let nostr_client = get_nostr_client()
-            .map_err(|e| format!("Failed to get Nostr client: {}", e))?
-            .clone();
-        let settings = std::sync::Arc::new(
-            MOSTRO_CONFIG
-                .get()
-                .ok_or_else(|| "MOSTRO_CONFIG not initialized".to_string())?
-                .clone(),
-        );
-        let ctx = AppContext::new(
-            self.pool.clone(),
-            nostr_client,
-            settings,
-            MESSAGE_QUEUES.queue_order_msg.clone(),
-            self.keys.clone(),
-        );
-        let mut ln_client = self.ln_client.lock().await;
-        admin_settle_action(&ctx, msg, &event, &self.keys, &mut ln_client)
-            .await
-            .map_err(|e| format!("Admin settle failed: {}", e))?;
+        let ctx = self.app_context()?;
+        match &self.ln_client {
+            Some(ln_client) => {
+                let mut ln_client = ln_client.lock().await;
+                admin_settle_action(&ctx, msg, &event, &self.keys, ln_client.as_mut()).await
+            }
+            None => admin_settle_cashu_action(&ctx, msg, &event, &self.keys).await,
+        }
+        .map_err(|e| format!("Admin settle failed: {}", e))?;
 
         Ok(())
     }
@@ -184,27 +408,7 @@ impl AdminServiceImpl {
             created_at: Timestamp::now(),
         };
 
-        use crate::app::context::AppContext;
-        use crate::config::MESSAGE_QUEUES;
-        use crate::config::MOSTRO_CONFIG;
-        use crate::util::get_nostr_client;
-
-        let nostr_client = get_nostr_client()
-            .map_err(|e| format!("Failed to get Nostr client: {}", e))?
-            .clone();
-        let settings = std::sync::Arc::new(
-            MOSTRO_CONFIG
-                .get()
-                .ok_or_else(|| "MOSTRO_CONFIG not initialized".to_string())?
-                .clone(),
-        );
-        let ctx = AppContext::new(
-            self.pool.clone(),
-            nostr_client,
-            settings,
-            MESSAGE_QUEUES.queue_order_msg.clone(),
-            self.keys.clone(),
-        );
+        let ctx = self.app_context()?;
         admin_add_solver_action(&ctx, msg, &event, &self.keys)
             .await
             .map_err(|e| format!("Admin add solver failed: {}", e))?;
@@ -212,6 +416,24 @@ impl AdminServiceImpl {
         Ok(())
     }
 
+    async fn call_set_user_banned(
+        &self,
+        pubkey: &str,
+        banned: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let public_key =
+            PublicKey::parse(pubkey.trim()).map_err(|e| format!("Invalid pubkey: {}", e))?;
+        set_user_banned(&self.pool, &public_key.to_hex(), banned)
+            .await
+            .map_err(|e| format!("Updating ban failed: {}", e))?;
+        info!(
+            "User {} {} via RPC",
+            public_key,
+            if banned { "banned" } else { "unbanned" }
+        );
+        Ok(())
+    }
+
     async fn call_admin_take_dispute(
         &self,
         dispute_id: String,
@@ -238,27 +460,7 @@ impl AdminServiceImpl {
             created_at: Timestamp::now(),
         };
 
-        use crate::app::context::AppContext;
-        use crate::config::MESSAGE_QUEUES;
-        use crate::config::MOSTRO_CONFIG;
-        use crate::util::get_nostr_client;
-
-        let nostr_client = get_nostr_client()
-            .map_err(|e| format!("Failed to get Nostr client: {}", e))?
-            .clone();
-        let settings = std::sync::Arc::new(
-            MOSTRO_CONFIG
-                .get()
-                .ok_or_else(|| "MOSTRO_CONFIG not initialized".to_string())?
-                .clone(),
-        );
-        let ctx = AppContext::new(
-            self.pool.clone(),
-            nostr_client,
-            settings,
-            MESSAGE_QUEUES.queue_order_msg.clone(),
-            self.keys.clone(),
-        );
+        let ctx = self.app_context()?;
         admin_take_dispute_action(&ctx, msg, &event, &self.keys)
             .await
             .map_err(|e| format!("Admin take dispute failed: {}", e))?;
@@ -269,6 +471,9 @@ impl AdminServiceImpl {
 
 #[tonic::async_trait]
 impl AdminService for AdminServiceImpl {
+    type WatchEventsStream =
+        Pin<Box<dyn Stream<Item = Result<AdminEvent, Status>> + Send + 'static>>;
+
     async fn cancel_order(
         &self,
         request: Request<CancelOrderRequest>,
@@ -369,6 +574,50 @@ impl AdminService for AdminServiceImpl {
         }
     }
 
+    async fn ban_user(
+        &self,
+        request: Request<BanUserRequest>,
+    ) -> Result<Response<BanUserResponse>, Status> {
+        let req = request.into_inner();
+        info!("Received ban user request for pubkey: {}", req.pubkey);
+
+        match self.call_set_user_banned(&req.pubkey, true).await {
+            Ok(()) => Ok(Response::new(BanUserResponse {
+                success: true,
+                error_message: None,
+            })),
+            Err(e) => {
+                error!("Ban user failed: {}", e);
+                Ok(Response::new(BanUserResponse {
+                    success: false,
+                    error_message: Some(e.to_string()),
+                }))
+            }
+        }
+    }
+
+    async fn unban_user(
+        &self,
+        request: Request<UnbanUserRequest>,
+    ) -> Result<Response<UnbanUserResponse>, Status> {
+        let req = request.into_inner();
+        info!("Received unban user request for pubkey: {}", req.pubkey);
+
+        match self.call_set_user_banned(&req.pubkey, false).await {
+            Ok(()) => Ok(Response::new(UnbanUserResponse {
+                success: true,
+                error_message: None,
+            })),
+            Err(e) => {
+                error!("Unban user failed: {}", e);
+                Ok(Response::new(UnbanUserResponse {
+                    success: false,
+                    error_message: Some(e.to_string()),
+                }))
+            }
+        }
+    }
+
     async fn get_version(
         &self,
         _request: Request<crate::rpc::admin::GetVersionRequest>,
@@ -379,6 +628,158 @@ impl AdminService for AdminServiceImpl {
         }))
     }
 
+    async fn list_orders(
+        &self,
+        request: Request<ListOrdersRequest>,
+    ) -> Result<Response<ListOrdersResponse>, Status> {
+        let filter = OrderFilter::try_from(request.get_ref())?;
+        let orders = list_orders(&self.pool, &filter).await.map_err(db_status)?;
+        let next_offset = next_offset(filter.offset, filter.limit, orders.len());
+        Ok(Response::new(ListOrdersResponse {
+            orders: orders.into_iter().map(OrderInfo::from).collect(),
+            next_offset,
+        }))
+    }
+
+    async fn get_order(
+        &self,
+        request: Request<GetOrderRequest>,
+    ) -> Result<Response<GetOrderResponse>, Status> {
+        let order_id = parse_uuid(&request.get_ref().order_id, "order id")?;
+        let order = Order::by_id(&self.pool, order_id)
+            .await
+            .map_err(db_status)?
+            .ok_or_else(|| Status::not_found(format!("order {order_id} not found")))?;
+        Ok(Response::new(GetOrderResponse {
+            order: Some(order.into()),
+        }))
+    }
+
+    async fn list_disputes(
+        &self,
+        request: Request<ListDisputesRequest>,
+    ) -> Result<Response<ListDisputesResponse>, Status> {
+        let filter = DisputeFilter::try_from(request.get_ref())?;
+        let disputes = list_disputes(&self.pool, &filter)
+            .await
+            .map_err(db_status)?;
+        let next_offset = next_offset(filter.offset, filter.limit, disputes.len());
+        Ok(Response::new(ListDisputesResponse {
+            disputes: disputes.into_iter().map(DisputeInfo::from).collect(),
+            next_offset,
+        }))
+    }
+
+    async fn get_dispute(
+        &self,
+        request: Request<GetDisputeRequest>,
+    ) -> Result<Response<GetDisputeResponse>, Status> {
+        let dispute_id = parse_uuid(&request.get_ref().dispute_id, "dispute id")?;
+        let dispute = Dispute::by_id(&self.pool, dispute_id)
+            .await
+            .map_err(db_status)?
+            .ok_or_else(|| Status::not_found(format!("dispute {dispute_id} not found")))?;
+        Ok(Response::new(GetDisputeResponse {
+            dispute: Some(dispute.into()),
+        }))
+    }
+
+    async fn list_dispute_evidence(
+        &self,
+        request: Request<ListDisputeEvidenceRequest>,
+    ) -> Result<Response<ListDisputeEvidenceResponse>, Status> {
+        let dispute_id = parse_uuid(&request.get_ref().dispute_id, "dispute id")?;
+        let evidence = list_evidence(&self.pool, dispute_id, &self.keys)
+            .await
+            .map_err(db_status)?;
+        Ok(Response::new(ListDisputeEvidenceResponse {
+            evidence: evidence
+                .into_iter()
+                .map(DisputeEvidenceInfo::from)
+                .collect(),
+        }))
+    }
+
+    async fn watch_events(
+        &self,
+        _request: Request<WatchEventsRequest>,
+    ) -> Result<Response<Self::WatchEventsStream>, Status> {
+        info!("New WatchEvents subscriber");
+        Ok(Response::new(Box::pin(events::into_stream(
+            events::subscribe(),
+        ))))
+    }
+
+    async fn list_dead_letters(
+        &self,
+        request: Request<ListDeadLettersRequest>,
+    ) -> Result<Response<ListDeadLettersResponse>, Status> {
+        let req = request.get_ref();
+        let (limit, offset) = page_bounds(req.limit, req.offset);
+        let entries = list_dead_letters(&self.pool, limit, offset)
+            .await
+            .map_err(db_status)?;
+        let next_offset = next_offset(offset, limit, entries.len());
+        Ok(Response::new(ListDeadLettersResponse {
+            dead_letters: entries.into_iter().map(DeadLetterInfo::from).collect(),
+            next_offset,
+        }))
+    }
+
+    async fn retry_dead_letter(
+        &self,
+        request: Request<RetryDeadLetterRequest>,
+    ) -> Result<Response<RetryDeadLetterResponse>, Status> {
+        let id = request.into_inner().id;
+        info!("Received retry request for dead letter {}", id);
+        let now = chrono::Utc::now().timestamp();
+        let response = match requeue_dead_letter(&self.pool, id, now).await {
+            Ok(true) => RetryDeadLetterResponse {
+                success: true,
+                error_message: None,
+            },
+            Ok(false) => RetryDeadLetterResponse {
+                success: false,
+                error_message: Some(format!("outbox message {id} is not a dead letter")),
+            },
+            Err(e) => {
+                error!("Retry dead letter failed: {}", e);
+                RetryDeadLetterResponse {
+                    success: false,
+                    error_message: Some(e.to_string()),
+                }
+            }
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn list_relays(
+        &self,
+        _request: Request<ListRelaysRequest>,
+    ) -> Result<Response<ListRelaysResponse>, Status> {
+        let configured = match get_nostr_client() {
+            Ok(client) => relay_health::relay_urls(client).await,
+            Err(_) => Vec::new(),
+        };
+        let now = chrono::Utc::now().timestamp();
+        let relays = relay_health::global()
+            .snapshot(&configured)
+            .into_iter()
+            .map(|(url, stats)| relay_status_info(&url, stats, now))
+            .collect();
+        Ok(Response::new(ListRelaysResponse { relays }))
+    }
+
+    async fn list_solver_stats(
+        &self,
+        _request: Request<ListSolverStatsRequest>,
+    ) -> Result<Response<ListSolverStatsResponse>, Status> {
+        let solvers = solver_stats(&self.pool).await.map_err(db_status)?;
+        Ok(Response::new(ListSolverStatsResponse {
+            solvers: solvers.into_iter().map(SolverStatsInfo::from).collect(),
+        }))
+    }
+
     async fn validate_db_password(
         &self,
         request: Request<ValidateDbPasswordRequest>,
@@ -394,6 +795,7 @@ impl AdminService for AdminServiceImpl {
             .check_rate_limit(&remote_addr)
             .await
         {
+            crate::metrics::rpc_rate_limited();
             warn!(
                 "ValidateDbPassword rate-limited for client {}: {}",
                 remote_addr.ip(),
@@ -426,6 +828,7 @@ impl AdminService for AdminServiceImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lightning::LndConnector;
 
     // Note: We skip the admin service creation test that requires LND
     // since it would require a real Lightning node connection.
@@ -528,12 +931,19 @@ mod tests {
         let client = fedimint_tonic_lnd::connect("https://127.0.0.1:1".to_string(), cert, macaroon)
             .await
             .expect("lazy connect must not touch the network");
-        let ln_client = Arc::new(tokio::sync::Mutex::new(LndConnector { client }));
+        let ln_client: Box<dyn EscrowBackend> = Box::new(LndConnector { client });
+        let ln_client = Arc::new(tokio::sync::Mutex::new(ln_client));
 
-        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
+
+        AdminServiceImpl::new(Keys::generate(), Arc::new(pool), Some(ln_client))
+    }
 
-        AdminServiceImpl::new(Keys::generate(), Arc::new(pool), ln_client)
+    /// The Cashu-mode service: no Lightning client at all.
+    async fn cashu_service() -> AdminServiceImpl {
+        init_test_settings();
+        let pool = crate::db::test_utils::migrated_pool().await;
+        AdminServiceImpl::new(Keys::generate(), Arc::new(pool), None)
     }
 
     fn request_with_addr<T>(inner: T, last_octet: u8) -> Request<T> {
@@ -706,6 +1116,365 @@ mod tests {
         assert_eq!(status.code(), tonic::Code::ResourceExhausted);
     }
 
+    #[tokio::test]
+    async fn cashu_service_answers_without_a_lightning_client() {
+        let service = cashu_service().await;
+        let version = service
+            .get_version(Request::new(GetVersionRequest {}))
+            .await
+            .expect("get_version never fails");
+        assert_eq!(version.into_inner().version, env!("CARGO_PKG_VERSION"));
+
+        // Settle and cancel take the Cashu handlers; an unknown order still
+        // surfaces as an unsuccessful response rather than a transport error.
+        let settle = service
+            .settle_order(Request::new(SettleOrderRequest {
+                order_id: uuid::Uuid::new_v4().to_string(),
+                request_id: None,
+            }))
+            .await
+            .expect("RPC surface always answers with a response");
+        assert!(!settle.into_inner().success);
+        let cancel = service
+            .cancel_order(Request::new(CancelOrderRequest {
+                order_id: uuid::Uuid::new_v4().to_string(),
+                request_id: None,
+            }))
+            .await
+            .expect("RPC surface always answers with a response");
+        assert!(!cancel.into_inner().success);
+    }
+
+    #[tokio::test]
+    async fn ban_and_unban_user_toggle_the_flag() {
+        use nostr_sdk::prelude::ToBech32;
+
+        let service = offline_service().await;
+        let user = Keys::generate().public_key();
+
+        let banned = service
+            .ban_user(Request::new(BanUserRequest {
+                pubkey: user.to_bech32().unwrap(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(banned.success);
+        let row = crate::db::is_user_present(&service.pool, user.to_hex())
+            .await
+            .unwrap();
+        assert_eq!(row.is_banned, 1);
+
+        let unbanned = service
+            .unban_user(Request::new(UnbanUserRequest {
+                pubkey: user.to_hex(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(unbanned.success);
+        let row = crate::db::is_user_present(&service.pool, user.to_hex())
+            .await
+            .unwrap();
+        assert_eq!(row.is_banned, 0);
+
+        let garbage = service
+            .ban_user(Request::new(BanUserRequest {
+                pubkey: "nope".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!garbage.success);
+    }
+
+    #[tokio::test]
+    async fn dead_letters_are_listed_and_retried() {
+        let service = offline_service().await;
+        let dest = Keys::generate().public_key();
+        let message = mostro_core::message::Message::new_order(
+            Some(Uuid::new_v4()),
+            None,
+            None,
+            mostro_core::message::Action::PayInvoice,
+            None,
+        );
+        let id = crate::outbox::test_utils::persist(&service.pool, "order", &message, &dest, 0)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE outbox SET status = 'dead', attempts = 10 WHERE id = $1")
+            .bind(id)
+            .execute(service.pool.as_ref())
+            .await
+            .unwrap();
+
+        let listed = service
+            .list_dead_letters(Request::new(ListDeadLettersRequest {
+                limit: 0,
+                offset: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listed.dead_letters.len(), 1);
+        assert_eq!(listed.dead_letters[0].id, id);
+        assert_eq!(listed.dead_letters[0].action.as_deref(), Some("PayInvoice"));
+        assert_eq!(listed.next_offset, None);
+
+        let retried = service
+            .retry_dead_letter(Request::new(RetryDeadLetterRequest { id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(retried.success);
+        let again = service
+            .retry_dead_letter(Request::new(RetryDeadLetterRequest { id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!again.success);
+    }
+
+    #[tokio::test]
+    async fn list_relays_reports_recorded_outcomes() {
+        let service = offline_service().await;
+        let url = nostr_sdk::prelude::RelayUrl::parse("wss://list-relays.test").unwrap();
+        let now = chrono::Utc::now().timestamp();
+        for _ in 0..relay_health::DEMOTE_AFTER_FAILURES {
+            relay_health::global().record(
+                &url,
+                &relay_health::SendOutcome::Rejected("blocked".into()),
+                now,
+            );
+        }
+
+        let relays = service
+            .list_relays(Request::new(ListRelaysRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .relays;
+        let relay = relays
+            .iter()
+            .find(|r| r.url == url.to_string())
+            .expect("recorded relay is listed");
+        assert!(relay.demoted);
+        assert_eq!(relay.rejected, relay_health::DEMOTE_AFTER_FAILURES as u64);
+        assert_eq!(relay.last_error.as_deref(), Some("blocked"));
+        assert!(relay.score < 1.0);
+    }
+
+    #[tokio::test]
+    async fn watch_events_streams_published_changes() {
+        use crate::rpc::admin::admin_event::Event;
+        use futures::StreamExt;
+
+        let service = offline_service().await;
+        let mut stream = service
+            .watch_events(Request::new(WatchEventsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        let order_id = uuid::Uuid::new_v4();
+        events::order_status_changed(order_id, "active");
+
+        // Other tests publish into the same feed; wait for ours.
+        loop {
+            let event = stream.next().await.unwrap().unwrap();
+            if let Some(Event::OrderStatusChanged(changed)) = event.event {
+                if changed.order_id == order_id.to_string() {
+                    assert_eq!(changed.status, "active");
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn page_bounds_default_and_cap_the_limit() {
+        assert_eq!(page_bounds(0, 0), (DEFAULT_PAGE_SIZE as i64, 0));
+        assert_eq!(page_bounds(10, 20), (10, 20));
+        assert_eq!(page_bounds(100_000, 0), (MAX_PAGE_SIZE as i64, 0));
+        assert_eq!(next_offset(20, 10, 10), Some(30));
+        assert_eq!(next_offset(20, 10, 3), None);
+    }
+
+    #[test]
+    fn list_filters_reject_unknown_status_and_bad_pubkey() {
+        let bad_status = ListOrdersRequest {
+            status: Some("bogus".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            OrderFilter::try_from(&bad_status).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+        let bad_pubkey = ListDisputesRequest {
+            pubkey: Some("npub1xyz".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            DisputeFilter::try_from(&bad_pubkey).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+        let ok = OrderFilter::try_from(&ListOrdersRequest {
+            status: Some("dispute".to_string()),
+            fiat_code: Some("usd".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(ok.fiat_code.as_deref(), Some("USD"));
+        assert_eq!(ok.limit, DEFAULT_PAGE_SIZE as i64);
+    }
+
+    #[tokio::test]
+    async fn read_rpcs_return_structured_orders_and_disputes() {
+        let service = offline_service().await;
+        let order = Order {
+            id: uuid::Uuid::new_v4(),
+            kind: "sell".to_string(),
+            status: "dispute".to_string(),
+            fiat_code: "EUR".to_string(),
+            creator_pubkey: Keys::generate().public_key().to_hex(),
+            preimage: Some("secret".to_string()),
+            created_at: 42,
+            ..Default::default()
+        }
+        .create(service.pool.as_ref())
+        .await
+        .unwrap();
+        let dispute = Dispute::new(order.id, "dispute".to_string())
+            .create(service.pool.as_ref())
+            .await
+            .unwrap();
+
+        let listed = service
+            .list_orders(Request::new(ListOrdersRequest {
+                fiat_code: Some("EUR".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listed.orders.len(), 1);
+        assert_eq!(listed.orders[0].id, order.id.to_string());
+        assert_eq!(listed.next_offset, None);
+
+        let fetched = service
+            .get_order(Request::new(GetOrderRequest {
+                order_id: order.id.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .order
+            .unwrap();
+        assert_eq!(fetched.status, "dispute");
+        assert_eq!(fetched.created_at, 42);
+
+        let disputes = service
+            .list_disputes(Request::new(ListDisputesRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(disputes.disputes.len(), 1);
+        let fetched = service
+            .get_dispute(Request::new(GetDisputeRequest {
+                dispute_id: dispute.id.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .dispute
+            .unwrap();
+        assert_eq!(fetched.order_id, order.id.to_string());
+
+        let missing = service
+            .get_order(Request::new(GetOrderRequest {
+                order_id: uuid::Uuid::new_v4().to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+        let garbage = service
+            .get_dispute(Request::new(GetDisputeRequest {
+                dispute_id: "nope".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(garbage.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn list_dispute_evidence_decrypts_stored_items() {
+        use nostr_sdk::prelude::nip44;
+
+        let service = offline_service().await;
+        let dispute_id = uuid::Uuid::new_v4();
+        let keys = &service.keys;
+        let ciphertext = nip44::encrypt(
+            keys.secret_key(),
+            &keys.public_key(),
+            "paid via SEPA",
+            nip44::Version::V2,
+        )
+        .unwrap();
+        for (by, content) in [("buyer", ciphertext.as_str()), ("seller", "")] {
+            sqlx::query(
+                "INSERT INTO dispute_evidence (dispute_id, order_id, submitted_by, kind, \
+                 content, sha256, key_pubkey, created_at) \
+                 VALUES ($1, $2, $3, 'receipt', $4, $5, $6, 7)",
+            )
+            .bind(dispute_id)
+            .bind(uuid::Uuid::new_v4())
+            .bind(by)
+            .bind(content)
+            .bind("ab".repeat(32))
+            .bind(keys.public_key().to_hex())
+            .execute(service.pool.as_ref())
+            .await
+            .unwrap();
+        }
+
+        let evidence = service
+            .list_dispute_evidence(Request::new(ListDisputeEvidenceRequest {
+                dispute_id: dispute_id.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .evidence;
+        assert_eq!(evidence.len(), 2);
+        assert_eq!(evidence[0].submitted_by, "buyer");
+        assert_eq!(evidence[0].content, "paid via SEPA");
+        assert_eq!(evidence[1].content, "");
+        assert_eq!(evidence[1].sha256, "ab".repeat(32));
+    }
+
+    #[tokio::test]
+    async fn list_solver_stats_reports_taken_disputes() {
+        let service = offline_service().await;
+        let solver = Keys::generate().public_key().to_hex();
+        let mut dispute = Dispute::new(uuid::Uuid::new_v4(), "active".to_string());
+        dispute.created_at = 1_000;
+        dispute.taken_at = 1_060;
+        dispute.solver_pubkey = Some(solver.clone());
+        dispute.create(service.pool.as_ref()).await.unwrap();
+
+        let solvers = service
+            .list_solver_stats(Request::new(ListSolverStatsRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .solvers;
+        assert_eq!(solvers.len(), 1);
+        assert_eq!(solvers[0].solver_pubkey, solver);
+        assert_eq!(solvers[0].disputes_taken, 1);
+        assert_eq!(solvers[0].disputes_resolved, 0);
+        assert_eq!(solvers[0].avg_response_secs, Some(60.0));
+        assert_eq!(solvers[0].avg_resolution_secs, None);
+    }
+
     #[test]
     fn test_optional_fields() {
         // Test that optional fields work correctly
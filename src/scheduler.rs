@@ -1,27 +1,24 @@
 use crate::app::bond;
 use crate::app::context::AppContext;
 use crate::app::dev_fee::run_dev_fee_cycle;
-use crate::app::release::{do_payment, reconcile_inflight_payout};
+use crate::app::release::{confirm_cashu_release, do_payment, reconcile_inflight_payout};
 use crate::config;
 use crate::db::*;
-use crate::escrow::EscrowBackend;
-use crate::lightning::LndConnector;
+use crate::escrow::{connect_lightning, EscrowBackend};
 use crate::price::PriceManager;
 use crate::util;
+use crate::Keys;
 use crate::LN_STATUS;
-use crate::{Keys, PublicKey};
 
+use crate::db::Crud;
 use chrono::{TimeDelta, Utc};
 use config::*;
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::EventBuilder;
 use nostr_sdk::prelude::{FinalizeEvent, Kind as NostrKind, Nip65Tag, Tag};
 use std::collections::HashSet;
-use std::sync::Arc;
-use tokio::sync::RwLock;
 use tracing::{error, info, warn};
-use util::{enqueue_order_msg, get_nostr_relays, send_dm, update_order_event};
+use util::{enqueue_order_msg, get_nostr_relays, update_order_event};
 
 pub async fn start_scheduler(ctx: AppContext) {
     info!("Creating scheduler");
@@ -34,7 +31,7 @@ pub async fn start_scheduler(ctx: AppContext) {
     // payments, pay the dev fee over LN, and service anti-abuse bonds — all of
     // which require an LND node that Cashu mode never initialises (CF-5).
     // Bonds are additionally mutually exclusive with Cashu mode (CF-1). Gating
-    // the spawns here keeps them from calling `LndConnector::new()` on a node
+    // the spawns here keeps them from calling `connect_lightning()` on a node
     // that has no LND. Lightning mode is unaffected (`is_cashu_enabled()` is
     // `false`, so every job below still starts exactly as before).
     if !Settings::is_cashu_enabled() {
@@ -45,19 +42,49 @@ pub async fn start_scheduler(ctx: AppContext) {
         job_process_dev_fee_payment(ctx.clone()).await;
         job_process_bond_payouts(ctx.clone()).await;
         job_reconcile_stranded_maker_bonds(ctx.clone()).await;
+        job_swap_payout_watcher(ctx.clone()).await;
+    } else {
+        // Cashu-only jobs: they watch escrow tokens at the mint instead.
+        job_cashu_release_watcher(ctx.clone()).await;
     }
 
     // Mode-agnostic jobs (the info event self-skips when LN status is absent).
     job_orderbook_reconciler(ctx.clone()).await;
+    job_relay_catch_up(ctx.clone()).await;
+    job_key_rotation(ctx.clone()).await;
+    job_dispute_sla(ctx.clone()).await;
     job_info_event_send(ctx.clone()).await;
     job_relay_list(ctx.clone()).await;
     job_update_bitcoin_prices().await;
     job_flush_messages_queue(ctx.clone()).await;
     job_refresh_active_pubkeys(ctx.clone()).await;
+    job_backup_database(ctx.clone()).await;
 
     info!("Scheduler Started");
 }
 
+/// Write an online backup into `[database].backup_dir` every
+/// `backup_interval_seconds`. Inert when `backup_dir` is unset.
+async fn job_backup_database(ctx: AppContext) {
+    let db = &ctx.settings().database;
+    let Some(dir) = db.backup_dir.clone() else {
+        return;
+    };
+    // Backups copy a SQLite file; PostgreSQL is backed up with its own tools.
+    #[cfg(feature = "postgres")]
+    {
+        let _ = dir;
+        warn!("[database].backup_dir is ignored with PostgreSQL storage; use pg_dump");
+    }
+    #[cfg(not(feature = "postgres"))]
+    tokio::spawn(crate::backup::backup_loop(
+        ctx.pool_arc(),
+        dir.into(),
+        db.backup_interval_seconds,
+        db.backup_keep,
+    ));
+}
+
 /// Periodically rebuild the protocol-v2 anti-spam gate's active-trade-pubkey
 /// cache from the DB (spec §6 Phase 2). Status mutations are scattered across
 /// many handlers with no single choke-point, so a periodic full reload is the
@@ -87,71 +114,27 @@ async fn job_refresh_active_pubkeys(ctx: AppContext) {
     });
 }
 
+/// Deliver outbound protocol messages through the durable outbox: the
+/// dispatcher moves the in-memory queues into the `outbox` table and sends
+/// due rows on `outbox_workers` parallel workers (see `crate::outbox`). It
+/// wakes on every enqueue; rows left over from a previous run are replayed.
 async fn job_flush_messages_queue(ctx: AppContext) {
-    // Clone for closure owning with Arc
-    let order_msg_list = MESSAGE_QUEUES.queue_order_msg.clone();
-    // Clone for closure owning with Arc
-    let cantdo_msg_list = MESSAGE_QUEUES.queue_order_cantdo.clone();
-    // Clone for closure owning with Arc
-    let restore_session_msg_list = MESSAGE_QUEUES.queue_restore_session_msg.clone();
-    let sender_keys = ctx.keys().clone();
-
-    // Helper function to send messages
-    async fn send_messages(
-        msg_list: Arc<RwLock<Vec<(Message, PublicKey)>>>,
-        sender_keys: Keys,
-        retries: &mut usize,
-    ) {
-        if !msg_list.read().await.is_empty() {
-            let (message, destination_key) = msg_list.read().await[0].clone();
-            match message.as_json() {
-                Ok(msg) => {
-                    if let Err(e) = send_dm(destination_key, &sender_keys, &msg, None).await {
-                        error!("Failed to send message: {}", e);
-                        *retries += 1;
-                    } else {
-                        *retries = 0;
-                        msg_list.write().await.remove(0);
-                    }
-                }
-                Err(e) => error!("Failed to parse message: {}", e),
-            }
-            if *retries > 3 {
-                *retries = 0; // Reset retries after removing message
-                msg_list.write().await.remove(0);
-            }
-        }
-    }
-
-    // Spawn a new task to flush the messages queue
-    tokio::spawn(async move {
-        let mut retries_messages = 0;
-        let mut retries_cantdo_messages = 0;
-        let mut retries_restore_session_messages = 0;
+    let dispatcher = crate::outbox::Dispatcher::new(
+        ctx.pool_arc(),
+        ctx.keys().clone(),
+        ctx.settings().mostro.outbox_workers,
+    );
 
-        loop {
-            send_messages(
-                order_msg_list.clone(),
-                sender_keys.clone(),
-                &mut retries_messages,
-            )
-            .await;
-            send_messages(
-                cantdo_msg_list.clone(),
-                sender_keys.clone(),
-                &mut retries_cantdo_messages,
-            )
-            .await;
-            send_messages(
-                restore_session_msg_list.clone(),
-                sender_keys.clone(),
-                &mut retries_restore_session_messages,
-            )
-            .await;
+    tokio::spawn(dispatcher.run(std::time::Duration::from_millis(250)));
+}
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
-        }
-    });
+/// Keys of every Mostro instance, or just `primary` before the instance list
+/// is installed.
+fn instance_keys(primary: &Keys) -> Vec<Keys> {
+    match crate::instance::all() {
+        [] => vec![primary.clone()],
+        instances => instances.iter().map(|i| i.keys.clone()).collect(),
+    }
 }
 
 async fn job_relay_list(ctx: AppContext) {
@@ -165,8 +148,12 @@ async fn job_relay_list(ctx: AppContext) {
             if let Some(relays) = get_nostr_relays().await {
                 let mut relay_tags: Vec<Tag> = vec![];
 
+                let now = chrono::Utc::now().timestamp();
                 for (_, r) in relays.iter() {
-                    if r.status().is_connected() {
+                    // Demoted relays are not advertised until they recover.
+                    if r.status().is_connected()
+                        && !crate::relay_health::global().is_demoted(r.url(), now)
+                    {
                         relay_tags.push(
                             Nip65Tag::RelayMetadata {
                                 relay_url: r.url().clone(),
@@ -176,11 +163,14 @@ async fn job_relay_list(ctx: AppContext) {
                         )
                     }
                 }
-                if let Ok(relay_ev) = EventBuilder::new(NostrKind::RelayList, "")
-                    .tags(relay_tags)
-                    .finalize(&mostro_keys)
-                {
-                    let _ = client.send_event(&relay_ev).await;
+                // Every Mostro instance advertises the same shared relays.
+                for keys in instance_keys(&mostro_keys) {
+                    if let Ok(relay_ev) = EventBuilder::new(NostrKind::RelayList, "")
+                        .tags(relay_tags.clone())
+                        .finalize(&keys)
+                    {
+                        let _ = client.send_event(&relay_ev).await;
+                    }
                 }
             }
             tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
@@ -203,15 +193,24 @@ async fn job_info_event_send(ctx: AppContext) {
         loop {
             info!("Sending info about mostro");
 
-            let tags = crate::nip33::info_to_tags(ln_status);
-            let id = mostro_keys.public_key().to_string();
-
-            let info_ev = match crate::nip33::new_info_event(&mostro_keys, "", id, tags) {
-                Ok(info) => info,
-                Err(e) => return error!("{e}"),
-            };
+            // One info event per Mostro instance, built from its own
+            // settings (fee, limits, currencies).
+            for keys in instance_keys(&mostro_keys) {
+                let owner = crate::instance::all()
+                    .iter()
+                    .find(|instance| instance.keys.public_key() == keys.public_key());
+                let tags =
+                    crate::instance::within(owner, async { crate::nip33::info_to_tags(ln_status) })
+                        .await;
+                let id = keys.public_key().to_string();
+
+                let info_ev = match crate::nip33::new_info_event(&keys, "", id, tags) {
+                    Ok(info) => info,
+                    Err(e) => return error!("{e}"),
+                };
 
-            let _ = client.send_event(&info_ev).await;
+                let _ = client.send_event(&info_ev).await;
+            }
 
             tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
         }
@@ -230,20 +229,27 @@ async fn job_retry_failed_payments(ctx: AppContext) {
                 interval
             );
 
-            if let Ok(payment_failed_list) = crate::db::find_failed_payment(ctx.pool()).await {
-                for payment_failed in payment_failed_list.into_iter() {
-                    if payment_failed.payment_attempts < retries_number {
-                        if let Err(e) = do_payment(&ctx, payment_failed.clone(), None).await {
-                            error!("{e}");
-                        }
-                    }
-                }
-            }
+            retry_failed_payments_pass(&ctx, retries_number).await;
             tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
         }
     });
 }
 
+/// One retry tick, split out for testing: re-dispatch every failed buyer
+/// payout that still has attempts left.
+pub(crate) async fn retry_failed_payments_pass(ctx: &AppContext, retries_number: i64) {
+    if let Ok(payment_failed_list) = crate::db::find_failed_payment(ctx.pool()).await {
+        for payment_failed in payment_failed_list.into_iter() {
+            if payment_failed.payment_attempts < retries_number {
+                crate::metrics::payment_retried();
+                if let Err(e) = do_payment(ctx, payment_failed.clone(), None).await {
+                    error!("{e}");
+                }
+            }
+        }
+    }
+}
+
 /// Floor of the payout-reconcile grace window, in seconds.
 ///
 /// `LightningSettings::default()` has `payment_retries_interval = 0`, and a
@@ -277,14 +283,14 @@ async fn job_reconcile_inflight_payouts(ctx: AppContext) {
         .max(MIN_GRACE_SECS) as i64;
 
     tokio::spawn(async move {
-        // Same capped-backoff LndConnector bootstrap as the bond payout job: a
+        // Same capped-backoff connect_lightning bootstrap as the bond payout job: a
         // transient LND outage at boot must not permanently halt reconciliation.
         let mut backoff_secs: u64 = 2;
         let mut ln_client = loop {
-            match LndConnector::new().await {
+            match connect_lightning().await {
                 Ok(client) => break client,
                 Err(e) => {
-                    error!("payout reconcile: LndConnector::new failed: {e} — retrying in {backoff_secs}s");
+                    error!("payout reconcile: connect_lightning failed: {e} — retrying in {backoff_secs}s");
                     tokio::time::sleep(tokio::time::Duration::from_secs(backoff_secs)).await;
                     backoff_secs = (backoff_secs * 2).min(60);
                 }
@@ -299,7 +305,7 @@ async fn job_reconcile_inflight_payouts(ctx: AppContext) {
                     for (order_id, payout_hash, payout_claimed_at) in inflight.into_iter() {
                         if let Err(e) = reconcile_inflight_payout(
                             &ctx,
-                            &mut ln_client,
+                            ln_client.as_mut(),
                             order_id,
                             &payout_hash,
                             payout_claimed_at,
@@ -317,6 +323,83 @@ async fn job_reconcile_inflight_payouts(ctx: AppContext) {
     });
 }
 
+/// Swap payout watcher (`docs/SWAP_PAYOUTS.md`): drives every live reverse
+/// swap of an on-chain buyer payout. A lockup the chain source confirms,
+/// paying the swap script the agreed amount, is claimed to the buyer by
+/// Mostro itself; a confirmed claim settles the seller's hold invoice (when
+/// release left it open) and finishes the trade; a failed swap is counted as
+/// a failed payout attempt. Only runs when `[swap]` is enabled.
+async fn job_swap_payout_watcher(ctx: AppContext) {
+    // Fixed poll cadence: it bounds how quickly a confirmed lockup is claimed.
+    const SWAP_WATCH_INTERVAL_SECS: u64 = 30;
+
+    let Some(cfg) = ctx.settings().swap.clone().filter(|swap| swap.enabled) else {
+        return;
+    };
+    let provider = match crate::swap::connect_swap(&cfg) {
+        Ok(provider) => provider,
+        Err(e) => return error!("swap payout watcher: {e}"),
+    };
+    let chain = match crate::swap::connect_chain(&cfg) {
+        Ok(chain) => chain,
+        Err(e) => return error!("swap payout watcher: {e}"),
+    };
+    let mut escrow = match connect_lightning().await {
+        Ok(escrow) => escrow,
+        Err(e) => return error!("swap payout watcher: connect_lightning failed: {e}"),
+    };
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = crate::swap::payout::swap_payout_pass(
+                &ctx,
+                provider.as_ref(),
+                chain.as_ref(),
+                escrow.as_mut(),
+            )
+            .await
+            {
+                error!("swap payout watcher: {e}");
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(SWAP_WATCH_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// Cashu release watcher (Track B §5C, Track D §5D): the only path to
+/// `Success` and `CompletedByAdmin` in Cashu mode. Every tick, each released
+/// order (`SettledHoldInvoice` or `SettledByAdmin` with a stored escrow token)
+/// is checked at the mint; an escrow observed spent before its locktime means
+/// the buyer redeemed, so the order completes and becomes rateable. An
+/// unreachable mint just skips the tick.
+async fn job_cashu_release_watcher(ctx: AppContext) {
+    // Fixed poll cadence: it only bounds how quickly a redeem is noticed.
+    const CASHU_RELEASE_WATCH_INTERVAL_SECS: u64 = 60;
+
+    let Some(cashu_client) = ctx.cashu_client().cloned() else {
+        return error!("cashu release watcher: no Cashu client attached to the context");
+    };
+
+    tokio::spawn(async move {
+        loop {
+            match find_released_cashu_orders(ctx.pool()).await {
+                Ok(orders) => {
+                    for order in orders {
+                        if let Err(e) = confirm_cashu_release(&ctx, &cashu_client, &order).await {
+                            warn!("cashu release watcher: order {}: {e}", order.id);
+                        }
+                    }
+                }
+                Err(e) => error!("cashu release watcher: {e}"),
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                CASHU_RELEASE_WATCH_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    });
+}
+
 async fn job_update_rate_events(ctx: AppContext) {
     // Clone for closure owning with Arc
     let queue_order_rate = MESSAGE_QUEUES.queue_order_rate.clone();
@@ -447,9 +530,10 @@ pub(crate) async fn notify_users_canceled_order(
 /// and the caller must skip; skipping never loses a genuine timeout
 /// because the next tick re-selects it.
 async fn reconfirm_timeout_eligibility(
-    pool: &sqlx::Pool<sqlx::Sqlite>,
+    pool: &DbPool,
     order_id: uuid::Uuid,
     exp_seconds: u32,
+    now: i64,
 ) -> Option<Order> {
     let fresh = match Order::by_id(pool, order_id).await {
         Ok(Some(fresh)) => fresh,
@@ -464,16 +548,14 @@ async fn reconfirm_timeout_eligibility(
     };
     let still_waiting = fresh.status == Status::WaitingBuyerInvoice.to_string()
         || fresh.status == Status::WaitingPayment.to_string();
-    let still_expired = fresh.taken_at < Utc::now().timestamp() - exp_seconds as i64;
+    let still_expired = fresh.taken_at < now - exp_seconds as i64;
     (still_waiting && still_expired).then_some(fresh)
 }
 
 async fn job_cancel_orders(ctx: AppContext) {
     info!("Create a pool to connect to db");
 
-    let keys = ctx.keys().clone();
-
-    let mut ln_client = if let Ok(client) = LndConnector::new().await {
+    let mut ln_client = if let Ok(client) = connect_lightning().await {
         client
     } else {
         return error!("Failed to create LND client");
@@ -482,276 +564,291 @@ async fn job_cancel_orders(ctx: AppContext) {
     let exp_seconds = mostro_settings.expiration_seconds;
 
     tokio::spawn(async move {
-        let pool = ctx.pool();
         loop {
             info!("Check for order to republish for late actions of users");
+            cancel_timed_out_orders_pass(&ctx, ln_client.as_mut(), Utc::now().timestamp()).await;
+            let now = Utc::now();
+            if let Some(next_tick) = now.checked_add_signed(
+                TimeDelta::try_seconds(exp_seconds as i64).expect("Wrong seconds value"),
+            ) {
+                info!(
+                    "Next tick for late action users check is {}",
+                    next_tick.format("%a %b %e %T %Y")
+                );
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    });
+}
 
-            if let Ok(older_orders_list) = crate::db::find_order_by_seconds(pool).await {
-                for order in older_orders_list.into_iter() {
-                    // The tick-start snapshot may be stale by the time this
-                    // iteration is reached — re-read and re-confirm before
-                    // acting, so the hold-invoice cancel, the bond blame,
-                    // and the cancel/republish below all run against the
-                    // order's *current* duty rather than the snapshot's.
-                    let Some(order) =
-                        reconfirm_timeout_eligibility(pool, order.id, exp_seconds).await
-                    else {
+/// One timeout tick, split out for testing: cancel or republish every order
+/// whose taker or maker let the waiting window lapse by `now`, slashing or
+/// releasing its bonds first. See [`job_cancel_orders`].
+pub(crate) async fn cancel_timed_out_orders_pass(
+    ctx: &AppContext,
+    ln_client: &mut dyn EscrowBackend,
+    now: i64,
+) {
+    let pool = ctx.pool();
+    let keys = ctx.keys().clone();
+    let exp_seconds = ctx.settings().mostro.expiration_seconds;
+
+    if let Ok(older_orders_list) = crate::db::find_order_by_seconds(pool, now).await {
+        for order in older_orders_list.into_iter() {
+            // The tick-start snapshot may be stale by the time this
+            // iteration is reached — re-read and re-confirm before
+            // acting, so the hold-invoice cancel, the bond blame,
+            // and the cancel/republish below all run against the
+            // order's *current* duty rather than the snapshot's.
+            let Some(order) = reconfirm_timeout_eligibility(pool, order.id, exp_seconds, now).await
+            else {
+                continue;
+            };
+            // Check if order is a sell order and Buyer is not sending the invoice for too much time.
+            // Same if seller is not paying hold invoice
+            if order.status == Status::WaitingBuyerInvoice.to_string()
+                || order.status == Status::WaitingPayment.to_string()
+            {
+                // If hold invoice is paid return funds to seller
+                // We return funds to seller
+                if let Some(hash) = order.hash.as_ref() {
+                    // The cancel must succeed before we clear the
+                    // order. Falling through on error would take the
+                    // order out of `find_order_by_seconds`'s
+                    // waiting-state eligibility window — and log
+                    // "funds returned" — while the hold invoice is
+                    // still encumbered, with no later tick to fix it.
+                    // Same reasoning as the bond slash/release below:
+                    // stay eligible and retry rather than persist a
+                    // state that doesn't match the HTLC.
+                    if let Err(e) = ln_client.cancel_hold_invoice(hash).await {
+                        error!(
+                            "scheduler_timeout: cancel_hold_invoice failed for order {} ({e}); skipping cancel/republish so next tick retries",
+                            order.id
+                        );
                         continue;
-                    };
-                    // Check if order is a sell order and Buyer is not sending the invoice for too much time.
-                    // Same if seller is not paying hold invoice
-                    if order.status == Status::WaitingBuyerInvoice.to_string()
-                        || order.status == Status::WaitingPayment.to_string()
-                    {
-                        // If hold invoice is paid return funds to seller
-                        // We return funds to seller
-                        if let Some(hash) = order.hash.as_ref() {
-                            // The cancel must succeed before we clear the
-                            // order. Falling through on error would take the
-                            // order out of `find_order_by_seconds`'s
-                            // waiting-state eligibility window — and log
-                            // "funds returned" — while the hold invoice is
-                            // still encumbered, with no later tick to fix it.
-                            // Same reasoning as the bond slash/release below:
-                            // stay eligible and retry rather than persist a
-                            // state that doesn't match the HTLC.
-                            if let Err(e) = ln_client.cancel_hold_invoice(hash).await {
-                                error!(
-                                    "scheduler_timeout: cancel_hold_invoice failed for order {} ({e}); skipping cancel/republish so next tick retries",
-                                    order.id
-                                );
-                                continue;
-                            }
-                            // A hash exists in both waiting states, but they
-                            // mean different things: in `waiting-payment` the
-                            // hold invoice was never paid (canceling voids
-                            // it), while in `waiting-buyer-invoice` the
-                            // seller already paid it and gets their funds
-                            // back.
-                            if order.status == Status::WaitingPayment.to_string() {
-                                info!("Order Id {}: Hold invoice canceled - seller did not pay it in time", &order.id);
-                            } else {
-                                info!("Order Id {}: Funds returned to seller - buyer did not send their invoice in time", &order.id);
-                            }
-                        };
-                        let mut order = order.clone();
-                        // dev_fee should be reset unconditionally
-                        order.dev_fee = 0;
-                        // We re-publish the event with Pending status
-                        // and update on local database
-                        if order.price_from_api {
-                            order.amount = 0;
-                            order.fee = 0;
+                    }
+                    // A hash exists in both waiting states, but they
+                    // mean different things: in `waiting-payment` the
+                    // hold invoice was never paid (canceling voids
+                    // it), while in `waiting-buyer-invoice` the
+                    // seller already paid it and gets their funds
+                    // back.
+                    if order.status == Status::WaitingPayment.to_string() {
+                        info!(
+                            "Order Id {}: Hold invoice canceled - seller did not pay it in time",
+                            &order.id
+                        );
+                    } else {
+                        info!("Order Id {}: Funds returned to seller - buyer did not send their invoice in time", &order.id);
+                    }
+                };
+                let mut order = order.clone();
+                // dev_fee should be reset unconditionally
+                order.dev_fee = 0;
+                // We re-publish the event with Pending status
+                // and update on local database
+                if order.price_from_api {
+                    order.amount = 0;
+                    order.fee = 0;
+                }
+
+                // Get order status and kind
+                let (order_status, order_kind) =
+                    match (order.get_order_status(), order.get_order_kind()) {
+                        (Ok(status), Ok(kind)) => (status, kind),
+                        _ => {
+                            tracing::warn!(
+                                "Error getting order status or kind in order {} cancel",
+                                order.id
+                            );
+                            continue;
                         }
+                    };
 
-                        // Get order status and kind
-                        let (order_status, order_kind) =
-                            match (order.get_order_status(), order.get_order_kind()) {
-                                (Ok(status), Ok(kind)) => (status, kind),
-                                _ => {
-                                    tracing::warn!(
-                                        "Error getting order status or kind in order {} cancel",
-                                        order.id
-                                    );
-                                    continue;
-                                }
-                            };
-
-                        // Phase 4: run the bond slash/release **before** any
-                        // DB mutation that takes the order out of
-                        // `find_order_by_seconds`'s
-                        // `status ∈ {WaitingBuyerInvoice, WaitingPayment}`
-                        // eligibility window — both `update_order_to_initial_state`
-                        // (republish path) and `order_updated.update`
-                        // (cancel path) below are such mutations. A
-                        // transient `settle_hold_invoice` failure inside
-                        // `slash_one` leaves the bond `Locked`; with the
-                        // slash gated on persist success (the original
-                        // Phase 4 layout) that means the slash is dropped
-                        // entirely, because the order has already moved
-                        // out of the eligible set and the next tick never
-                        // re-picks it up. Running it here means a
-                        // transient LND hiccup just defers the cancel to
-                        // the next tick, at which point the slash is
-                        // idempotent (HTLC's "already settled" path
-                        // proceeds to a CAS no-op and returns `Ok(None)`,
-                        // so neither the bond nor the user are touched
-                        // twice). The notification fires immediately on
-                        // first success so a later persist failure
-                        // doesn't lose it — by next tick `slash_or_release_on_timeout`
-                        // sees no `Locked` bond and returns `Ok(None)`,
-                        // so the notice never duplicates either.
-                        // `order` is the pre-mutation snapshot — its
-                        // waiting status and trade pubkeys are intact,
-                        // which the §3.1 buyer/seller → bond mapping needs.
-                        match bond::slash_or_release_on_timeout(
+                // Phase 4: run the bond slash/release **before** any
+                // DB mutation that takes the order out of
+                // `find_order_by_seconds`'s
+                // `status ∈ {WaitingBuyerInvoice, WaitingPayment}`
+                // eligibility window — both `update_order_to_initial_state`
+                // (republish path) and `order_updated.update`
+                // (cancel path) below are such mutations. A
+                // transient `settle_hold_invoice` failure inside
+                // `slash_one` leaves the bond `Locked`; with the
+                // slash gated on persist success (the original
+                // Phase 4 layout) that means the slash is dropped
+                // entirely, because the order has already moved
+                // out of the eligible set and the next tick never
+                // re-picks it up. Running it here means a
+                // transient LND hiccup just defers the cancel to
+                // the next tick, at which point the slash is
+                // idempotent (HTLC's "already settled" path
+                // proceeds to a CAS no-op and returns `Ok(None)`,
+                // so neither the bond nor the user are touched
+                // twice). The notification fires immediately on
+                // first success so a later persist failure
+                // doesn't lose it — by next tick `slash_or_release_on_timeout`
+                // sees no `Locked` bond and returns `Ok(None)`,
+                // so the notice never duplicates either.
+                // `order` is the pre-mutation snapshot — its
+                // waiting status and trade pubkeys are intact,
+                // which the §3.1 buyer/seller → bond mapping needs.
+                match bond::slash_or_release_on_timeout(
+                    pool,
+                    ln_client,
+                    &order,
+                    Settings::get_bond(),
+                )
+                .await
+                {
+                    Ok(Some(slashed)) => {
+                        bond::notify_bond_slashed(&order, &slashed).await;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        // `Err` from `slash_or_release_on_timeout` is a DB-read
+                        // failure (e.g. `find_active_bonds_for_order` /
+                        // `timeout_slash_confirmed` couldn't read the bond
+                        // rows), so we don't yet know whether the slash
+                        // applies. Falling through to cancel/republish
+                        // would persist the order out of
+                        // `find_order_by_seconds`'s waiting-state
+                        // eligibility window, and the next tick would
+                        // never re-evaluate it — losing the slash whose
+                        // applicability we couldn't even determine.
+                        // `continue` keeps the order eligible so the
+                        // next tick re-runs the full path (the slash
+                        // primitive is idempotent on a settled HTLC and
+                        // a `PendingPayout` bond, so a retry that
+                        // finds the work already done is a no-op).
+                        tracing::warn!(
+                            "scheduler_timeout: bond slash/release errored for {} ({}); skipping cancel/republish so next tick retries",
+                            order.id, e
+                        );
+                        continue;
+                    }
+                }
+
+                let (maker_action, new_status, edited_order) = match (order_status, order_kind) {
+                    (Status::WaitingBuyerInvoice, Kind::Sell)
+                    | (Status::WaitingPayment, Kind::Buy) => {
+                        // Update order status
+                        let _ = update_order_to_initial_state(
                             pool,
-                            &mut ln_client,
-                            &order,
-                            Settings::get_bond(),
+                            order.id,
+                            order.amount,
+                            order.fee,
+                            order.dev_fee,
                         )
-                        .await
-                        {
-                            Ok(Some(slashed)) => {
-                                bond::notify_bond_slashed(&order, &slashed).await;
+                        .await;
+                        info!(
+                            "Republishing order Id {}, {}",
+                            order.id,
+                            if order_status == Status::WaitingPayment {
+                                "taker (seller) did not pay the hold invoice in time"
+                            } else {
+                                "taker (buyer) did not send their invoice in time"
                             }
-                            Ok(None) => {}
-                            Err(e) => {
-                                // `Err` from `slash_or_release_on_timeout` is a DB-read
-                                // failure (e.g. `find_active_bonds_for_order` /
-                                // `timeout_slash_confirmed` couldn't read the bond
-                                // rows), so we don't yet know whether the slash
-                                // applies. Falling through to cancel/republish
-                                // would persist the order out of
-                                // `find_order_by_seconds`'s waiting-state
-                                // eligibility window, and the next tick would
-                                // never re-evaluate it — losing the slash whose
-                                // applicability we couldn't even determine.
-                                // `continue` keeps the order eligible so the
-                                // next tick re-runs the full path (the slash
-                                // primitive is idempotent on a settled HTLC and
-                                // a `PendingPayout` bond, so a retry that
-                                // finds the work already done is a no-op).
-                                tracing::warn!(
-                                    "scheduler_timeout: bond slash/release errored for {} ({}); skipping cancel/republish so next tick retries",
-                                    order.id, e
-                                );
-                                continue;
+                        );
+                        (
+                            Some(Action::NewOrder),
+                            Status::Pending,
+                            edit_pubkeys_order(pool, &order).await,
+                        )
+                    }
+                    (Status::WaitingBuyerInvoice, Kind::Buy)
+                    | (Status::WaitingPayment, Kind::Sell) => {
+                        // Update order status
+                        info!(
+                            "Canceled order Id {}, {}",
+                            order.id,
+                            if order_status == Status::WaitingPayment {
+                                "maker (seller) did not pay the hold invoice in time"
+                            } else {
+                                "maker (buyer) did not send their invoice in time"
                             }
-                        }
+                        );
+                        (
+                            Some(Action::Canceled),
+                            Status::Canceled,
+                            edit_pubkeys_order(pool, &order).await,
+                        )
+                    }
+                    _ => {
+                        tracing::info!("Order Id {} not available for cancel", &order.id);
+                        continue;
+                    }
+                };
 
-                        let (maker_action, new_status, edited_order) =
-                            match (order_status, order_kind) {
-                                (Status::WaitingBuyerInvoice, Kind::Sell)
-                                | (Status::WaitingPayment, Kind::Buy) => {
-                                    // Update order status
-                                    let _ = update_order_to_initial_state(
-                                        pool,
-                                        order.id,
-                                        order.amount,
-                                        order.fee,
-                                        order.dev_fee,
-                                    )
-                                    .await;
-                                    info!(
-                                        "Republishing order Id {}, {}",
-                                        order.id,
-                                        if order_status == Status::WaitingPayment {
-                                            "taker (seller) did not pay the hold invoice in time"
-                                        } else {
-                                            "taker (buyer) did not send their invoice in time"
-                                        }
-                                    );
-                                    (
-                                        Some(Action::NewOrder),
-                                        Status::Pending,
-                                        edit_pubkeys_order(pool, &order).await,
-                                    )
-                                }
-                                (Status::WaitingBuyerInvoice, Kind::Buy)
-                                | (Status::WaitingPayment, Kind::Sell) => {
-                                    // Update order status
-                                    info!(
-                                        "Canceled order Id {}, {}",
-                                        order.id,
-                                        if order_status == Status::WaitingPayment {
-                                            "maker (seller) did not pay the hold invoice in time"
-                                        } else {
-                                            "maker (buyer) did not send their invoice in time"
-                                        }
-                                    );
-                                    (
-                                        Some(Action::Canceled),
-                                        Status::Canceled,
-                                        edit_pubkeys_order(pool, &order).await,
-                                    )
-                                }
-                                _ => {
-                                    tracing::info!(
-                                        "Order Id {} not available for cancel",
-                                        &order.id
-                                    );
-                                    continue;
-                                }
-                            };
-
-                        // Get edited order to use for update_order_event
-                        let edited_order = if let Ok(edited_order) = edited_order {
-                            println!("Edited order: {:?}", edited_order);
-                            edited_order
-                        } else {
-                            tracing::warn!("Error editing pubkeys in order {} cancel", order.id);
-                            continue;
-                        };
+                // Get edited order to use for update_order_event
+                let edited_order = if let Ok(edited_order) = edited_order {
+                    println!("Edited order: {:?}", edited_order);
+                    edited_order
+                } else {
+                    tracing::warn!("Error editing pubkeys in order {} cancel", order.id);
+                    continue;
+                };
 
-                        // Update order status
-                        if let Ok(order_updated) =
-                            update_order_event(&keys, new_status, &edited_order).await
-                        {
-                            // Notify users about order status changes - here order is updated
-                            notify_users_canceled_order(&order_updated, &order, maker_action).await;
-                            // trace new status
-                            tracing::info!(
-                                "Order Id {}: Reset to status {:?}",
-                                &order_updated.id,
-                                new_status
+                // Update order status
+                if let Ok(order_updated) =
+                    update_order_event(&keys, new_status, &edited_order).await
+                {
+                    // Notify users about order status changes - here order is updated
+                    notify_users_canceled_order(&order_updated, &order, maker_action).await;
+                    // trace new status
+                    tracing::info!(
+                        "Order Id {}: Reset to status {:?}",
+                        &order_updated.id,
+                        new_status
+                    );
+                    let order_id = order_updated.id;
+                    // Persist the new status. The bond slash/release
+                    // has already run above (before any DB mutation
+                    // that strips eligibility) — on persist failure
+                    // the next tick retries this branch only; the
+                    // slash is durable in `bonds.slashed_reason` and
+                    // a re-entry sees no `Locked` bond (or an
+                    // already-recorded slice child), so it is a
+                    // no-op (no duplicate notify).
+                    match order_updated.update(pool).await {
+                        Ok(persisted) => {
+                            crate::rpc::events::order_status_changed(
+                                persisted.id,
+                                &persisted.status,
                             );
-                            let order_id = order_updated.id;
-                            // Persist the new status. The bond slash/release
-                            // has already run above (before any DB mutation
-                            // that strips eligibility) — on persist failure
-                            // the next tick retries this branch only; the
-                            // slash is durable in `bonds.slashed_reason` and
-                            // a re-entry sees no `Locked` bond (or an
-                            // already-recorded slice child), so it is a
-                            // no-op (no duplicate notify).
-                            match order_updated.update(pool).await {
-                                Ok(_) => {
-                                    // Phase 7: a maker-responsible timeout
-                                    // cancels the order outright, terminating
-                                    // its range chain — resolve the range
-                                    // maker bond at close (settle + per-slice
-                                    // payouts + maker refund when a slice was
-                                    // slashed; plain release otherwise). The
-                                    // close helper is idempotent and a cheap
-                                    // no-op for non-range / already-resolved
-                                    // bonds; on transient failure the
-                                    // reconciliation sweep retries. The
-                                    // republish branch must NOT close: the
-                                    // order returns to the book with the
-                                    // maker still committed.
-                                    if matches!(new_status, Status::Canceled) {
-                                        bond::resolve_range_maker_bond_at_close_or_warn(
-                                            pool,
-                                            &order,
-                                            "scheduler_timeout",
-                                        )
-                                        .await;
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!(
-                                        "scheduler_timeout: persist failed for order {} ({}); will retry next tick",
-                                        order_id, e
-                                    );
-                                }
+                            // Phase 7: a maker-responsible timeout
+                            // cancels the order outright, terminating
+                            // its range chain — resolve the range
+                            // maker bond at close (settle + per-slice
+                            // payouts + maker refund when a slice was
+                            // slashed; plain release otherwise). The
+                            // close helper is idempotent and a cheap
+                            // no-op for non-range / already-resolved
+                            // bonds; on transient failure the
+                            // reconciliation sweep retries. The
+                            // republish branch must NOT close: the
+                            // order returns to the book with the
+                            // maker still committed.
+                            if matches!(new_status, Status::Canceled) {
+                                bond::resolve_range_maker_bond_at_close_or_warn(
+                                    pool,
+                                    &order,
+                                    "scheduler_timeout",
+                                )
+                                .await;
                             }
                         }
+                        Err(e) => {
+                            tracing::warn!(
+                                "scheduler_timeout: persist failed for order {} ({}); will retry next tick",
+                                order_id, e
+                            );
+                        }
                     }
                 }
             }
-            let now = Utc::now();
-            if let Some(next_tick) = now.checked_add_signed(
-                TimeDelta::try_seconds(exp_seconds as i64).expect("Wrong seconds value"),
-            ) {
-                info!(
-                    "Next tick for late action users check is {}",
-                    next_tick.format("%a %b %e %T %Y")
-                );
-            }
-            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
         }
-    });
+    }
 }
 
 /// Guardian for the trade escrow's CLTV lifetime.
@@ -779,15 +876,16 @@ async fn job_cancel_orders(ctx: AppContext) {
 ///   solver can settle or cancel *while the escrow is still settleable*.
 ///   The hold invoice is deliberately NOT canceled here.
 async fn job_enforce_escrow_deadline(ctx: AppContext) {
-    let mut ln_client = if let Ok(client) = LndConnector::new().await {
-        client
-    } else {
-        return error!("Failed to create LND client");
+    let mut escrow = match crate::escrow::connect_lightning().await {
+        Ok(escrow) => escrow,
+        Err(e) => return error!("Failed to create Lightning client: {e}"),
     };
 
     tokio::spawn(async move {
         loop {
-            if let Err(e) = enforce_escrow_deadline_pass(&ctx, &mut ln_client).await {
+            if let Err(e) =
+                enforce_escrow_deadline_pass(&ctx, escrow.as_mut(), Utc::now().timestamp()).await
+            {
                 error!("escrow deadline pass failed: {e}");
             }
             tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
@@ -797,13 +895,13 @@ async fn job_enforce_escrow_deadline(ctx: AppContext) {
 
 /// One guardian tick, split out for testing. See
 /// [`job_enforce_escrow_deadline`] for the semantics.
-async fn enforce_escrow_deadline_pass(
+pub(crate) async fn enforce_escrow_deadline_pass(
     ctx: &AppContext,
     escrow: &mut dyn EscrowBackend,
+    now: i64,
 ) -> Result<(), MostroError> {
     let pool = ctx.pool();
     let keys = ctx.keys().clone();
-    let now = Utc::now().timestamp();
     let ln_settings = &ctx.settings().lightning;
     let cltv = ln_settings.hold_invoice_cltv_delta;
     let margin = ln_settings.escrow_deadline_margin_blocks;
@@ -929,6 +1027,7 @@ async fn enforce_escrow_deadline_pass(
                         continue;
                     }
                 };
+                crate::rpc::events::order_status_changed(updated.id, &updated.status);
                 info!(
                     "escrow_deadline: order {} canceled — trade escrow reached its CLTV lifetime",
                     order.id
@@ -1098,7 +1197,7 @@ const ORDERBOOK_QUIESCENT_SECS: u64 = 120;
 /// The returned order (fresh `event_id`) is deliberately not persisted,
 /// matching the CAS-miss repair path: the DB row's status is the source
 /// of truth being re-advertised, not mutated.
-async fn reconcile_orderbook_once(pool: &sqlx::SqlitePool, keys: &Keys) {
+async fn reconcile_orderbook_once(pool: &DbPool, keys: &Keys) {
     for (order_id, generation, attempts) in util::take_failed_orderbook_publishes() {
         if attempts >= util::MAX_ORDERBOOK_REPUBLISH_ATTEMPTS {
             warn!(
@@ -1187,6 +1286,86 @@ async fn job_orderbook_reconciler(ctx: AppContext) {
     });
 }
 
+/// Seconds between relay catch-up passes.
+const RELAY_CATCH_UP_INTERVAL_SECS: u64 = 30;
+
+/// Re-sends order events to the relays that missed them (see
+/// [`crate::relay_health`]). Like the reconciler queue, the catch-up queue
+/// is process-local and starts empty.
+async fn job_relay_catch_up(ctx: AppContext) {
+    let client = ctx.nostr_client().clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                RELAY_CATCH_UP_INTERVAL_SECS,
+            ))
+            .await;
+            crate::relay_health::catch_up_once(&client).await;
+        }
+    });
+}
+
+/// Seconds between checks whether a key rotation has completed.
+const KEY_ROTATION_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Watches the trades pinned to a retired key (see [`crate::rotation`]) and
+/// marks the rotation complete once all have finished. Inert without
+/// `[nostr].previous_nsec_privkey`.
+async fn job_key_rotation(ctx: AppContext) {
+    if crate::instance::retired().is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                KEY_ROTATION_CHECK_INTERVAL_SECS,
+            ))
+            .await;
+            match crate::rotation::check_completion(ctx.pool()).await {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => error!("Key rotation check failed: {e}"),
+            }
+        }
+    });
+}
+
+/// Seconds between dispute SLA passes.
+const DISPUTE_SLA_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Alerts solvers and the admin about disputes left untaken and escalates
+/// disputes left unresolved (see [`crate::dispute_sla`]). Inert when both
+/// `dispute_take_alert_minutes` and `dispute_resolve_escalate_hours` are 0.
+async fn job_dispute_sla(ctx: AppContext) {
+    let mostro_settings = &ctx.settings().mostro;
+    let take_alert_minutes = mostro_settings.dispute_take_alert_minutes;
+    let escalate_hours = mostro_settings.dispute_resolve_escalate_hours;
+    if take_alert_minutes == 0 && escalate_hours == 0 {
+        return;
+    }
+    let admin = mostro_settings.dispute_admin();
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now().timestamp();
+            if let Err(e) = crate::dispute_sla::check_once(
+                ctx.pool(),
+                admin,
+                take_alert_minutes,
+                escalate_hours,
+                now,
+            )
+            .await
+            {
+                error!("Dispute SLA check failed: {e}");
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                DISPUTE_SLA_CHECK_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    });
+}
+
 async fn job_expire_pending_older_orders(ctx: AppContext) {
     let keys = ctx.keys().clone();
 
@@ -1218,7 +1397,7 @@ async fn job_expire_pending_older_orders(ctx: AppContext) {
                             Ok(_) => {
                                 // Bonds are Lightning-only and mutually exclusive
                                 // with Cashu mode (CF-1), which has no LND — the
-                                // release helpers open `LndConnector::new()`, so
+                                // release helpers open `connect_lightning()`, so
                                 // skip them here. A cashu node should carry no
                                 // bond rows; any left over (e.g. a reused DB) are
                                 // a misconfiguration, not this job's concern.
@@ -1255,10 +1434,14 @@ async fn job_expire_pending_older_orders(ctx: AppContext) {
                         // reprocesses the still-Pending order; CLTV
                         // expiry is the eventual safety net.
                         match order_updated.update(pool).await {
-                            Ok(_) => {
+                            Ok(persisted) => {
+                                crate::rpc::events::order_status_changed(
+                                    persisted.id,
+                                    &persisted.status,
+                                );
                                 // Bonds are Lightning-only and mutually exclusive
                                 // with Cashu mode (CF-1); the release helpers open
-                                // `LndConnector::new()`, which a cashu node has
+                                // `connect_lightning()`, which a cashu node has
                                 // not initialised. Skip them — a cashu node
                                 // carries no bond rows by construction.
                                 if !Settings::is_cashu_enabled() {
@@ -1411,7 +1594,7 @@ async fn job_update_bitcoin_prices() {
 async fn job_process_dev_fee_payment(ctx: AppContext) {
     let interval = 60u64;
 
-    let mut ln_client = if let Ok(client) = LndConnector::new().await {
+    let mut ln_client = if let Ok(client) = connect_lightning().await {
         client
     } else {
         return error!("Failed to create LND client for dev fee payment job");
@@ -1424,7 +1607,7 @@ async fn job_process_dev_fee_payment(ctx: AppContext) {
         let pool = ctx.pool();
         let keys = ctx.keys();
         loop {
-            run_dev_fee_cycle(pool, &mut ln_client, &mut confirmed, keys).await;
+            run_dev_fee_cycle(pool, ln_client.as_mut(), &mut confirmed, keys).await;
             tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
         }
     });
@@ -1446,7 +1629,7 @@ async fn job_process_bond_payouts(ctx: AppContext) {
     let interval = 60u64;
 
     tokio::spawn(async move {
-        // Retry LndConnector::new() with capped exponential backoff so a
+        // Retry connect_lightning() with capped exponential backoff so a
         // transient LND startup failure (e.g. LND not yet listening when
         // mostrod boots, or a brief restart) does not permanently halt
         // PendingPayout draining. Without this, every bond stuck in
@@ -1455,11 +1638,11 @@ async fn job_process_bond_payouts(ctx: AppContext) {
         // duration. Backoff caps at 60s to keep retry pressure modest.
         let mut backoff_secs: u64 = 2;
         let mut ln_client = loop {
-            match LndConnector::new().await {
+            match connect_lightning().await {
                 Ok(client) => break client,
                 Err(e) => {
                     error!(
-                        "bond payout: LndConnector::new failed: {e} — retrying in {backoff_secs}s"
+                        "bond payout: connect_lightning failed: {e} — retrying in {backoff_secs}s"
                     );
                     tokio::time::sleep(tokio::time::Duration::from_secs(backoff_secs)).await;
                     backoff_secs = (backoff_secs * 2).min(60);
@@ -1469,7 +1652,7 @@ async fn job_process_bond_payouts(ctx: AppContext) {
 
         let pool = ctx.pool();
         loop {
-            bond::run_bond_payout_cycle(pool, &mut ln_client).await;
+            bond::run_bond_payout_cycle(pool, ln_client.as_mut()).await;
             tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
         }
     });
@@ -1480,6 +1663,7 @@ mod tests {
     use super::*;
     use crate::app::context::test_utils::{test_settings, TestContextBuilder};
     use crate::config::MOSTRO_CONFIG;
+    use std::sync::Arc;
     use uuid::Uuid;
 
     fn init_test_settings() {
@@ -1489,8 +1673,7 @@ mod tests {
 
     async fn migrated_ctx() -> AppContext {
         init_test_settings();
-        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
         TestContextBuilder::new()
             .with_pool(Arc::new(pool))
             .with_settings(test_settings())
@@ -1521,9 +1704,7 @@ mod tests {
     }
 
     async fn queued_actions_for(order_id: Uuid) -> Vec<Action> {
-        MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(msg, _)| msg.get_inner_message_kind().id == Some(order_id))
@@ -1543,7 +1724,8 @@ mod tests {
         order.taken_at = Utc::now().timestamp() - 10_000;
         let stored = order.create(pool).await.unwrap();
 
-        let fresh = reconfirm_timeout_eligibility(pool, stored.id, 900).await;
+        let fresh =
+            reconfirm_timeout_eligibility(pool, stored.id, 900, Utc::now().timestamp()).await;
         assert!(
             fresh.is_some(),
             "a genuinely expired waiting order must stay eligible"
@@ -1563,7 +1745,7 @@ mod tests {
         order.taken_at = Utc::now().timestamp() - 10_000;
         let stored = order.create(pool).await.unwrap();
 
-        sqlx::query("UPDATE orders SET status = ?1, taken_at = ?2 WHERE id = ?3")
+        sqlx::query("UPDATE orders SET status = $1, taken_at = $2 WHERE id = $3")
             .bind(Status::WaitingPayment.to_string())
             .bind(Utc::now().timestamp())
             .bind(stored.id)
@@ -1572,7 +1754,7 @@ mod tests {
             .unwrap();
 
         assert!(
-            reconfirm_timeout_eligibility(pool, stored.id, 900)
+            reconfirm_timeout_eligibility(pool, stored.id, 900, Utc::now().timestamp())
                 .await
                 .is_none(),
             "a just-started duty must not inherit the snapshot's expiry"
@@ -1589,7 +1771,7 @@ mod tests {
         order.taken_at = Utc::now().timestamp() - 10_000;
         let stored = order.create(pool).await.unwrap();
 
-        sqlx::query("UPDATE orders SET taken_at = ?1 WHERE id = ?2")
+        sqlx::query("UPDATE orders SET taken_at = $1 WHERE id = $2")
             .bind(Utc::now().timestamp())
             .bind(stored.id)
             .execute(pool)
@@ -1597,7 +1779,7 @@ mod tests {
             .unwrap();
 
         assert!(
-            reconfirm_timeout_eligibility(pool, stored.id, 900)
+            reconfirm_timeout_eligibility(pool, stored.id, 900, Utc::now().timestamp())
                 .await
                 .is_none(),
             "a re-anchored clock is no longer expired"
@@ -1616,13 +1798,13 @@ mod tests {
         let stored = order.create(pool).await.unwrap();
 
         assert!(
-            reconfirm_timeout_eligibility(pool, stored.id, 900)
+            reconfirm_timeout_eligibility(pool, stored.id, 900, Utc::now().timestamp())
                 .await
                 .is_none(),
             "a terminal order is not timeout-eligible however stale its clock"
         );
         assert!(
-            reconfirm_timeout_eligibility(pool, Uuid::new_v4(), 900)
+            reconfirm_timeout_eligibility(pool, Uuid::new_v4(), 900, Utc::now().timestamp())
                 .await
                 .is_none(),
             "a missing row is skipped"
@@ -1976,8 +2158,7 @@ mod tests {
 
     async fn escrow_guardian_ctx(cltv: u32, margin: u32) -> AppContext {
         init_test_settings();
-        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
         let mut settings = test_settings();
         settings.lightning.hold_invoice_cltv_delta = cltv;
         settings.lightning.escrow_deadline_margin_blocks = margin;
@@ -2016,7 +2197,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::ok();
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2045,7 +2226,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::failing("transport: connection refused");
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2064,7 +2245,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::failing("invoice already canceled");
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2086,7 +2267,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::ok();
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2122,7 +2303,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::ok();
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2157,7 +2338,7 @@ mod tests {
         let prior = prior.create(ctx.pool()).await.unwrap();
         let mut escrow = StubEscrow::ok();
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2189,7 +2370,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::ok().with_heights(900_000, Some(900_010));
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2211,7 +2392,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::ok().with_heights(900_000, Some(900_100));
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2235,7 +2416,7 @@ mod tests {
         let mut escrow = StubEscrow::failing("transport: connection refused")
             .with_heights(900_000, Some(900_010));
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2258,7 +2439,7 @@ mod tests {
         let mut escrow =
             StubEscrow::failing("transport: connection refused").with_heights(900_000, None);
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2280,7 +2461,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::failing("transport: connection refused");
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2299,7 +2480,7 @@ mod tests {
             .unwrap();
         let mut escrow = StubEscrow::ok();
 
-        enforce_escrow_deadline_pass(&ctx, &mut escrow)
+        enforce_escrow_deadline_pass(&ctx, &mut escrow, Utc::now().timestamp())
             .await
             .unwrap();
 
@@ -2315,7 +2496,7 @@ mod tests {
     // iterations against the (empty) migrated database. Tasks die with the
     // test runtime. LND-backed jobs exercise their startup-failure paths:
     // the default lightning settings point at unreadable cert/macaroon
-    // paths, so `LndConnector::new()` fails fast without any network.
+    // paths, so `connect_lightning()` fails fast without any network.
 
     #[tokio::test]
     async fn start_scheduler_spawns_all_jobs_without_panicking() {
@@ -2342,16 +2523,20 @@ mod tests {
         .expect("empty provider set builds")
         .install_global();
 
-        // Push one message into the restore-session queue so the flush
-        // job's send-failure/retry path runs (no Nostr relays reachable).
-        MESSAGE_QUEUES
-            .queue_restore_session_msg
-            .write()
-            .await
-            .push((
-                Message::new_order(Some(Uuid::new_v4()), None, None, Action::Canceled, None),
-                ctx.keys().public_key(),
-            ));
+        // Store one restore-session message so the flush job's
+        // send-failure/retry path runs (no Nostr relays reachable).
+        let id = crate::outbox::persist_from(
+            ctx.pool(),
+            crate::instance::DEFAULT_INSTANCE,
+            ctx.keys(),
+            "restore",
+            &Message::new_order(Some(Uuid::new_v4()), None, None, Action::Canceled, None),
+            &ctx.keys().public_key(),
+            chrono::Utc::now().timestamp(),
+        )
+        .await
+        .unwrap();
+        let pool = ctx.pool_arc();
 
         // Pause only after the pool and globals exist: pool setup under a
         // paused clock trips sqlx's acquire timeout via auto-advance.
@@ -2359,19 +2544,28 @@ mod tests {
         start_scheduler(ctx).await;
 
         // Let every loop take a few virtual-time laps (60s cadence jobs run
-        // ~6 times; the 250ms flush loop drains its retry budget).
+        // ~6 times; the 250ms flush loop tries the stored message).
         tokio::time::sleep(tokio::time::Duration::from_secs(400)).await;
 
-        // The flush job must have dropped the undeliverable message after
-        // exhausting its retries.
-        assert!(
-            MESSAGE_QUEUES
-                .queue_restore_session_msg
-                .read()
-                .await
-                .is_empty(),
-            "undeliverable restore-session message must be dropped after retries"
-        );
+        // The flush job must have attempted the message: it is either gone
+        // or carries a failed attempt. A PostgreSQL pool waits on the
+        // server, which the paused clock does not, so give it real time.
+        tokio::time::resume();
+        let mut attempted = false;
+        for _ in 0..50 {
+            let attempts: Option<i64> =
+                sqlx::query_scalar("SELECT attempts FROM outbox WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(pool.as_ref())
+                    .await
+                    .unwrap();
+            if attempts.is_none_or(|n| n > 0) {
+                attempted = true;
+                break;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        assert!(attempted, "the flush job must try the stored message");
     }
 
     #[tokio::test]
@@ -2408,7 +2602,7 @@ mod tests {
     #[tokio::test]
     async fn ln_backed_jobs_fail_fast_without_lnd() {
         let ctx = migrated_ctx().await;
-        // Both return early (error log) because LndConnector::new() cannot
+        // Both return early (error log) because connect_lightning() cannot
         // read the default cert/macaroon paths.
         job_cancel_orders(ctx.clone()).await;
         job_process_dev_fee_payment(ctx).await;
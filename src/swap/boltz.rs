@@ -0,0 +1,279 @@
+//! [`SwapProvider`] over the Boltz v2 HTTP API.
+//!
+//! - `POST /v2/swap/reverse` creates the swap (`from`/`to` = `BTC`),
+//! - `GET /v2/swap/{id}` reads its status,
+//! - `GET /v2/swap/reverse/{id}/transaction` fetches the lockup transaction,
+//!   which Mostro checks and claims itself ([`super::script`]).
+//!
+//! The endpoint is the operator's own configuration, not a buyer-supplied
+//! host, so none of the LNURL host policy of [`crate::lnurl`] applies here.
+
+use async_trait::async_trait;
+use mostro_core::prelude::*;
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::{swap_error, ReverseSwap, ReverseSwapRequest, SwapProvider};
+
+/// Cap on one round-trip to the swap service.
+const SWAP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct BoltzClient {
+    http: Client,
+    base: Url,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateReverseBody<'a> {
+    from: &'a str,
+    to: &'a str,
+    invoice_amount: u64,
+    preimage_hash: &'a str,
+    claim_public_key: &'a str,
+    address: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateReverseResponse {
+    id: String,
+    invoice: String,
+    lockup_address: String,
+    refund_public_key: String,
+    onchain_amount: u64,
+    timeout_block_height: u32,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct TransactionResponse {
+    hex: String,
+}
+
+/// Boltz answers errors as `{"error": "..."}`.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl BoltzClient {
+    pub fn new(api_url: &str) -> Result<Self, MostroError> {
+        let mut base = Url::parse(api_url).map_err(swap_error)?;
+        // `Url::join` drops the last path segment without a trailing slash.
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let http = Client::builder()
+            .timeout(SWAP_REQUEST_TIMEOUT)
+            .user_agent(concat!("mostro/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(swap_error)?;
+        Ok(Self { http, base })
+    }
+
+    fn url(&self, path: &str) -> Result<Url, MostroError> {
+        self.base.join(path).map_err(swap_error)
+    }
+
+    async fn read<T: for<'de> Deserialize<'de>>(
+        response: reqwest::Response,
+    ) -> Result<T, MostroError> {
+        let status = response.status();
+        if status.is_success() {
+            return response.json().await.map_err(swap_error);
+        }
+        Err(swap_error(error_message(status, response).await))
+    }
+}
+
+async fn error_message(status: StatusCode, response: reqwest::Response) -> String {
+    match response.json::<ErrorResponse>().await {
+        Ok(body) => format!("{status}: {}", body.error),
+        Err(_) => status.to_string(),
+    }
+}
+
+#[async_trait]
+impl SwapProvider for BoltzClient {
+    async fn create_reverse_swap(
+        &self,
+        request: &ReverseSwapRequest,
+    ) -> Result<ReverseSwap, MostroError> {
+        let body = CreateReverseBody {
+            from: "BTC",
+            to: "BTC",
+            invoice_amount: request.invoice_amount,
+            preimage_hash: &request.preimage_hash,
+            claim_public_key: &request.claim_public_key,
+            address: &request.address,
+        };
+        let response = self
+            .http
+            .post(self.url("v2/swap/reverse")?)
+            .json(&body)
+            .send()
+            .await
+            .map_err(swap_error)?;
+        let created: CreateReverseResponse = Self::read(response).await?;
+        Ok(ReverseSwap {
+            id: created.id,
+            invoice: created.invoice,
+            lockup_address: created.lockup_address,
+            refund_public_key: created.refund_public_key,
+            onchain_amount: created.onchain_amount,
+            timeout_block_height: created.timeout_block_height,
+        })
+    }
+
+    async fn swap_status(&self, id: &str) -> Result<String, MostroError> {
+        let response = self
+            .http
+            .get(self.url(&format!("v2/swap/{id}"))?)
+            .send()
+            .await
+            .map_err(swap_error)?;
+        let status: StatusResponse = Self::read(response).await?;
+        Ok(status.status)
+    }
+
+    async fn lockup_transaction(&self, id: &str) -> Result<String, MostroError> {
+        let response = self
+            .http
+            .get(self.url(&format!("v2/swap/reverse/{id}/transaction"))?)
+            .send()
+            .await
+            .map_err(swap_error)?;
+        let transaction: TransactionResponse = Self::read(response).await?;
+        Ok(transaction.hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    /// Serve `app` on a loopback port and return its base URL, with a path
+    /// prefix to check that joins keep it.
+    async fn serve(app: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().nest("/api", app))
+                .await
+                .unwrap();
+        });
+        format!("http://127.0.0.1:{port}/api")
+    }
+
+    #[tokio::test]
+    async fn speaks_the_boltz_reverse_swap_api() {
+        let seen = Arc::new(Mutex::new(Vec::<Value>::new()));
+        let seen_create = seen.clone();
+        let app = Router::new()
+            .route(
+                "/v2/swap/reverse",
+                post(move |Json(body): Json<Value>| {
+                    let seen = seen_create.clone();
+                    async move {
+                        seen.lock().unwrap().push(body);
+                        Json(json!({
+                            "id": "swap1",
+                            "invoice": "lnbc1invoice",
+                            "swapTree": {},
+                            "lockupAddress": "bc1qlockup",
+                            "refundPublicKey": "02aa",
+                            "timeoutBlockHeight": 800_144,
+                            "onchainAmount": 98_500
+                        }))
+                    }
+                }),
+            )
+            .route(
+                "/v2/swap/{id}",
+                get(|Path(id): Path<String>| async move {
+                    Json(json!({ "status": format!("{id}:transaction.confirmed") }))
+                }),
+            )
+            .route(
+                "/v2/swap/reverse/{id}/transaction",
+                get(|Path(id): Path<String>| async move {
+                    Json(json!({
+                        "id": format!("{id}-lockup"),
+                        "hex": "0200",
+                        "timeoutBlockHeight": 800_144
+                    }))
+                }),
+            );
+        let client = BoltzClient::new(&serve(app).await).unwrap();
+
+        let swap = client
+            .create_reverse_swap(&ReverseSwapRequest {
+                invoice_amount: 100_000,
+                preimage_hash: "ab".repeat(32),
+                claim_public_key: "02cd".to_string(),
+                address: "bc1qbuyer".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(swap.id, "swap1");
+        assert_eq!(swap.lockup_address, "bc1qlockup");
+        assert_eq!(swap.refund_public_key, "02aa");
+        assert_eq!(swap.onchain_amount, 98_500);
+        assert_eq!(swap.timeout_block_height, 800_144);
+
+        assert_eq!(
+            client.swap_status("swap1").await.unwrap(),
+            "swap1:transaction.confirmed"
+        );
+        assert_eq!(client.lockup_transaction("swap1").await.unwrap(), "0200");
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(
+            seen[0],
+            json!({
+                "from": "BTC",
+                "to": "BTC",
+                "invoiceAmount": 100_000,
+                "preimageHash": "ab".repeat(32),
+                "claimPublicKey": "02cd",
+                "address": "bc1qbuyer"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn service_errors_carry_the_reason() {
+        let app = Router::new().route(
+            "/v2/swap/reverse",
+            post(|| async {
+                (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": "10 is less than minimal of 25000" })),
+                )
+            }),
+        );
+        let client = BoltzClient::new(&serve(app).await).unwrap();
+        let err = client
+            .create_reverse_swap(&ReverseSwapRequest {
+                invoice_amount: 10,
+                preimage_hash: "ab".repeat(32),
+                claim_public_key: "02cd".to_string(),
+                address: "bc1qbuyer".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("less than minimal"), "{err}");
+    }
+}
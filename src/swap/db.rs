@@ -0,0 +1,155 @@
+//! Persistence of the per-order swap state (`swaps` table).
+
+use crate::db::DbPool;
+use chrono::Utc;
+use mostro_core::prelude::*;
+use uuid::Uuid;
+
+/// Where a swap stands on Mostro's side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum SwapState {
+    /// Created at the service; Mostro's payment of its invoice dispatched.
+    Created,
+    /// The lockup checked out and Mostro broadcast its claim to the buyer's
+    /// address.
+    Claimed,
+    /// The claim confirmed: the buyer is paid.
+    Completed,
+    /// The swap will not complete; the payout falls back to the retry path.
+    Failed,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Swap {
+    pub id: String,
+    pub order_id: Uuid,
+    pub address: String,
+    pub invoice: String,
+    pub invoice_amount: i64,
+    pub onchain_amount: i64,
+    pub lockup_address: String,
+    pub refund_public_key: String,
+    pub timeout_block_height: i64,
+    pub preimage: String,
+    pub preimage_hash: String,
+    pub claim_key: String,
+    /// The signed claim transaction, hex encoded, once the lockup is claimed.
+    pub claim_tx: Option<String>,
+    /// The seller's hold invoice is still held and is settled when this
+    /// swap completes (or fails).
+    pub settle_hold: bool,
+    pub status: SwapState,
+    pub provider_status: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+fn db_err(e: sqlx::Error) -> MostroError {
+    MostroInternalErr(ServiceError::DbAccessError(e.to_string()))
+}
+
+/// Store a new swap. Returns `false`, storing nothing, when the order
+/// already has a live swap.
+pub async fn create_swap(pool: &DbPool, swap: &Swap) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        "INSERT INTO swaps (id, order_id, address, invoice, invoice_amount, \
+         onchain_amount, lockup_address, refund_public_key, timeout_block_height, preimage, \
+         preimage_hash, claim_key, claim_tx, settle_hold, status, provider_status, \
+         created_at, updated_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, \
+         $18) \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(&swap.id)
+    .bind(swap.order_id)
+    .bind(&swap.address)
+    .bind(&swap.invoice)
+    .bind(swap.invoice_amount)
+    .bind(swap.onchain_amount)
+    .bind(&swap.lockup_address)
+    .bind(&swap.refund_public_key)
+    .bind(swap.timeout_block_height)
+    .bind(&swap.preimage)
+    .bind(&swap.preimage_hash)
+    .bind(&swap.claim_key)
+    .bind(&swap.claim_tx)
+    .bind(swap.settle_hold)
+    .bind(swap.status)
+    .bind(&swap.provider_status)
+    .bind(swap.created_at)
+    .bind(swap.updated_at)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Swaps still in progress, oldest first.
+pub async fn find_live_swaps(pool: &DbPool) -> Result<Vec<Swap>, MostroError> {
+    sqlx::query_as::<_, Swap>(
+        "SELECT * FROM swaps WHERE status IN ('created', 'claimed') ORDER BY created_at",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Move swap `id` from `from` to `to`, recording the service's status.
+/// Returns `false` when another task moved it first.
+pub async fn transition_swap(
+    pool: &DbPool,
+    id: &str,
+    from: SwapState,
+    to: SwapState,
+    provider_status: &str,
+) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        "UPDATE swaps SET status = $1, provider_status = $2, updated_at = $3 \
+         WHERE id = $4 AND status = $5",
+    )
+    .bind(to)
+    .bind(provider_status)
+    .bind(Utc::now().timestamp())
+    .bind(id)
+    .bind(from)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Move swap `id` from `Created` to `Claimed`, storing the claim
+/// transaction. Returns `false` when another task moved it first.
+pub async fn record_claim(
+    pool: &DbPool,
+    id: &str,
+    claim_tx: &str,
+    provider_status: &str,
+) -> Result<bool, MostroError> {
+    let result = sqlx::query(
+        "UPDATE swaps SET status = $1, claim_tx = $2, provider_status = $3, updated_at = $4 \
+         WHERE id = $5 AND status = $6",
+    )
+    .bind(SwapState::Claimed)
+    .bind(claim_tx)
+    .bind(provider_status)
+    .bind(Utc::now().timestamp())
+    .bind(id)
+    .bind(SwapState::Created)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Record that the seller's hold invoice was settled ahead of the swap.
+pub async fn clear_settle_hold(pool: &DbPool, id: &str) -> Result<(), MostroError> {
+    sqlx::query("UPDATE swaps SET settle_hold = FALSE, updated_at = $1 WHERE id = $2")
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    Ok(())
+}
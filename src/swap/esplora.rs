@@ -0,0 +1,244 @@
+//! [`ChainSource`] over the Esplora HTTP API:
+//!
+//! - `GET /blocks/tip/height` is the chain tip,
+//! - `GET /tx/{txid}/status` tells whether and where a transaction confirmed,
+//! - `GET /fee-estimates` maps confirmation targets to sat/vB,
+//! - `POST /tx` with the raw hex broadcasts a transaction.
+//!
+//! Like the swap API, the endpoint is the operator's own configuration. It
+//! should be a node the operator trusts: it is what tells Mostro a lockup is
+//! really on-chain before the preimage is revealed.
+
+use async_trait::async_trait;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::{Transaction, Txid};
+use mostro_core::prelude::*;
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::ChainSource;
+
+/// Cap on one round-trip to the chain source.
+const CHAIN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Confirmation target, in blocks, the claim's fee rate is taken for.
+const CLAIM_TARGET_BLOCKS: &str = "6";
+
+/// Lowest fee rate a transaction relays with, in sat/vB.
+const MIN_RELAY_FEE_RATE: f64 = 1.0;
+
+pub struct EsploraClient {
+    http: Client,
+    base: Url,
+}
+
+#[derive(Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+}
+
+impl EsploraClient {
+    pub fn new(esplora_url: &str) -> Result<Self, MostroError> {
+        let mut base = Url::parse(esplora_url).map_err(chain_error)?;
+        // `Url::join` drops the last path segment without a trailing slash.
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let http = Client::builder()
+            .timeout(CHAIN_REQUEST_TIMEOUT)
+            .user_agent(concat!("mostro/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(chain_error)?;
+        Ok(Self { http, base })
+    }
+
+    fn url(&self, path: &str) -> Result<Url, MostroError> {
+        self.base.join(path).map_err(chain_error)
+    }
+
+    /// The body of a successful answer; Esplora answers errors as plain text.
+    async fn read(response: reqwest::Response) -> Result<String, MostroError> {
+        let status = response.status();
+        let body = response.text().await.map_err(chain_error)?;
+        if status.is_success() {
+            return Ok(body);
+        }
+        Err(chain_error(format!("{status}: {}", body.trim())))
+    }
+}
+
+fn chain_error(e: impl std::fmt::Display) -> MostroError {
+    MostroInternalErr(ServiceError::LnPaymentError(format!("chain source: {e}")))
+}
+
+#[async_trait]
+impl ChainSource for EsploraClient {
+    async fn tip_height(&self) -> Result<u32, MostroError> {
+        let response = self
+            .http
+            .get(self.url("blocks/tip/height")?)
+            .send()
+            .await
+            .map_err(chain_error)?;
+        Self::read(response)
+            .await?
+            .trim()
+            .parse()
+            .map_err(chain_error)
+    }
+
+    async fn confirmations(&self, txid: &Txid) -> Result<Option<u32>, MostroError> {
+        let response = self
+            .http
+            .get(self.url(&format!("tx/{txid}/status"))?)
+            .send()
+            .await
+            .map_err(chain_error)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let status: TxStatus =
+            serde_json::from_str(&Self::read(response).await?).map_err(chain_error)?;
+        match status.block_height.filter(|_| status.confirmed) {
+            Some(height) => {
+                let tip = self.tip_height().await?;
+                Ok(Some(tip.saturating_sub(height) + 1))
+            }
+            None => Ok(Some(0)),
+        }
+    }
+
+    async fn fee_rate(&self) -> Result<f64, MostroError> {
+        let response = self
+            .http
+            .get(self.url("fee-estimates")?)
+            .send()
+            .await
+            .map_err(chain_error)?;
+        let estimates: HashMap<String, f64> =
+            serde_json::from_str(&Self::read(response).await?).map_err(chain_error)?;
+        Ok(estimates
+            .get(CLAIM_TARGET_BLOCKS)
+            .copied()
+            .unwrap_or(MIN_RELAY_FEE_RATE)
+            .max(MIN_RELAY_FEE_RATE))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), MostroError> {
+        let response = self
+            .http
+            .post(self.url("tx")?)
+            .body(serialize_hex(tx))
+            .send()
+            .await
+            .map_err(chain_error)?;
+        Self::read(response).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use bitcoin::hashes::Hash;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    async fn serve(app: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().nest("/api", app))
+                .await
+                .unwrap();
+        });
+        format!("http://127.0.0.1:{port}/api")
+    }
+
+    #[tokio::test]
+    async fn speaks_the_esplora_api() {
+        let confirmed = Txid::from_byte_array([1u8; 32]);
+        let pending = Txid::from_byte_array([2u8; 32]);
+        let broadcast = Arc::new(Mutex::new(Vec::<String>::new()));
+        let seen = broadcast.clone();
+        let app = Router::new()
+            .route("/blocks/tip/height", get(|| async { "800105" }))
+            .route(
+                "/tx/{txid}/status",
+                get(move |Path(txid): Path<String>| async move {
+                    if txid == confirmed.to_string() {
+                        Json(json!({ "confirmed": true, "block_height": 800_100 })).into_response()
+                    } else if txid == pending.to_string() {
+                        Json(json!({ "confirmed": false })).into_response()
+                    } else {
+                        (axum::http::StatusCode::NOT_FOUND, "Transaction not found").into_response()
+                    }
+                }),
+            )
+            .route(
+                "/fee-estimates",
+                get(|| async { Json(json!({ "1": 20.5, "6": 4.2, "144": 1.01 })) }),
+            )
+            .route(
+                "/tx",
+                post(move |body: String| {
+                    let seen = seen.clone();
+                    async move {
+                        seen.lock().unwrap().push(body);
+                        "txid"
+                    }
+                }),
+            );
+        let client = EsploraClient::new(&serve(app).await).unwrap();
+
+        assert_eq!(client.tip_height().await.unwrap(), 800_105);
+        assert_eq!(client.confirmations(&confirmed).await.unwrap(), Some(6));
+        assert_eq!(client.confirmations(&pending).await.unwrap(), Some(0));
+        assert_eq!(
+            client
+                .confirmations(&Txid::from_byte_array([3u8; 32]))
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(client.fee_rate().await.unwrap(), 4.2);
+
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        client.broadcast(&tx).await.unwrap();
+        assert_eq!(*broadcast.lock().unwrap(), [serialize_hex(&tx)]);
+    }
+
+    #[tokio::test]
+    async fn rejected_broadcasts_carry_the_reason() {
+        let app = Router::new().route(
+            "/tx",
+            post(|| async {
+                (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    "sendrawtransaction RPC error: bad-txns-inputs-missingorspent",
+                )
+            }),
+        );
+        let client = EsploraClient::new(&serve(app).await).unwrap();
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let err = client.broadcast(&tx).await.unwrap_err();
+        assert!(err.to_string().contains("missingorspent"), "{err}");
+    }
+}
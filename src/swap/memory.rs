@@ -0,0 +1,584 @@
+//! Simulated Boltz-compatible swap service implementing [`SwapProvider`],
+//! and the chain it locks up on, implementing [`ChainSource`], for tests.
+//!
+//! Like the simulated node, every clone of a [`MemorySwapService`] is the
+//! same service and nothing happens on its own: the test moves each swap
+//! through the lockup with [`MemorySwapService::lock_up`] and
+//! [`MemorySwapService::confirm`], mines Mostro's claim with
+//! [`MemorySwapService::mine`], or lets the swap lapse with
+//! [`MemorySwapService::expire`]. The lockup pays the real swap script and a
+//! claim is only accepted if it spends it with the right preimage and a
+//! valid claim key signature. Linked to a [`MemoryEscrow`] with
+//! [`MemorySwapService::on_node`], the service holds the HTLC of the node's
+//! payment of its invoice as Boltz does, settling it once a claim reveals
+//! the preimage and failing it on expiry.
+//!
+//! Code that builds its own clients (`swap::connect_swap`,
+//! `swap::connect_chain`) reaches the simulated service when it runs inside
+//! [`with_service`].
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use bitcoin::absolute::LockTime;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{schnorr, Message, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{ControlBlock, LeafVersion, TapLeafHash};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, CompressedPublicKey, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid,
+};
+use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
+use mostro_core::prelude::*;
+
+use super::script::SwapScript;
+use super::{swap_error, ChainSource, ReverseSwap, ReverseSwapRequest, SwapProvider};
+use crate::escrow::memory::{bolt11, MemoryEscrow};
+use crate::lightning::decode_hash32;
+
+/// Blocks until the service may refund a lockup to itself.
+const SWAP_TIMEOUT_BLOCKS: u32 = 144;
+
+/// Height of the simulated chain's tip when a service is created.
+const START_HEIGHT: u32 = 1_000;
+
+tokio::task_local! {
+    static SERVICE: MemorySwapService;
+}
+
+/// Run `f` with `service` standing in for the `[swap]` service and chain:
+/// `swap::connect_swap` and `swap::connect_chain` hand out clones of it
+/// instead of dialing the APIs.
+pub(crate) async fn with_service<F: Future>(service: MemorySwapService, f: F) -> F::Output {
+    SERVICE.scope(service, f).await
+}
+
+/// The service installed by [`with_service`] for the current task, if any.
+pub(crate) fn scoped_service() -> Option<MemorySwapService> {
+    SERVICE.try_with(Clone::clone).ok()
+}
+
+/// A fresh P2WPKH address on `network`.
+pub(crate) fn test_address(network: Network) -> String {
+    let key = SecretKey::from_slice(&rand::random::<[u8; 32]>()).expect("valid key");
+    let public = CompressedPublicKey(PublicKey::from_secret_key(&Secp256k1::new(), &key));
+    Address::p2wpkh(&public, network).to_string()
+}
+
+#[derive(Debug, Clone)]
+struct SimSwap {
+    request: ReverseSwapRequest,
+    onchain_amount: u64,
+    status: String,
+    lockup_script: ScriptBuf,
+    lockup: Option<Transaction>,
+}
+
+#[derive(Debug)]
+struct Service {
+    swaps: HashMap<String, SimSwap>,
+    node: Option<MemoryEscrow>,
+    /// Sats kept by the service on every swap.
+    fee: u64,
+    refuse: bool,
+    /// Quote lockup addresses only the service can spend.
+    forge_addresses: bool,
+    created: u64,
+    /// The chain: every transaction with the height it confirmed at.
+    txs: HashMap<Txid, (Transaction, Option<u32>)>,
+    height: u32,
+    fee_rate: f64,
+}
+
+impl Default for Service {
+    fn default() -> Self {
+        Self {
+            swaps: HashMap::new(),
+            node: None,
+            fee: 0,
+            refuse: false,
+            forge_addresses: false,
+            created: 0,
+            txs: HashMap::new(),
+            height: START_HEIGHT,
+            fee_rate: 2.0,
+        }
+    }
+}
+
+impl Service {
+    fn swap(&mut self, id: &str) -> &mut SimSwap {
+        self.swaps.get_mut(id).expect("unknown swap")
+    }
+
+    /// The swap whose lockup `outpoint` is.
+    fn swap_locked_at(&self, outpoint: &OutPoint) -> Option<&SimSwap> {
+        self.swaps.values().find(|swap| {
+            swap.lockup
+                .as_ref()
+                .is_some_and(|lockup| lockup.compute_txid() == outpoint.txid)
+        })
+    }
+
+    fn spender_of(&self, outpoint: &OutPoint) -> Option<Txid> {
+        self.txs.iter().find_map(|(txid, (tx, _))| {
+            tx.input
+                .iter()
+                .any(|input| input.previous_output == *outpoint)
+                .then_some(*txid)
+        })
+    }
+}
+
+/// Handle on a simulated swap service; clones share it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MemorySwapService {
+    service: Arc<Mutex<Service>>,
+}
+
+impl MemorySwapService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn service(&self) -> MutexGuard<'_, Service> {
+        self.service
+            .lock()
+            .expect("simulated swap service poisoned")
+    }
+
+    /// Hold the node's payments of this service's invoices.
+    pub fn on_node(self, node: MemoryEscrow) -> Self {
+        self.service().node = Some(node);
+        self
+    }
+
+    /// Keep `sats` of every new swap as the service fee.
+    pub fn set_fee(&self, sats: u64) {
+        self.service().fee = sats;
+    }
+
+    /// Refuse every new swap, as a service that is down or out of funds.
+    pub fn refuse_swaps(&self, refuse: bool) {
+        self.service().refuse = refuse;
+    }
+
+    /// Quote every new swap with a lockup address the service alone can
+    /// spend, as a dishonest service would.
+    pub fn forge_lockup_addresses(&self, forge: bool) {
+        self.service().forge_addresses = forge;
+    }
+
+    /// Ids of every swap created so far, oldest first.
+    pub fn swap_ids(&self) -> Vec<String> {
+        let mut ids: Vec<_> = self.service().swaps.keys().cloned().collect();
+        ids.sort_by_key(|id| id.trim_start_matches("swap").parse::<u64>().unwrap_or(0));
+        ids
+    }
+
+    pub fn status(&self, id: &str) -> Option<String> {
+        self.service().swaps.get(id).map(|swap| swap.status.clone())
+    }
+
+    /// Sats paid to `address` by confirmed transactions.
+    pub fn paid_to(&self, address: &str) -> u64 {
+        let script = Address::from_str(address)
+            .expect("valid address")
+            .assume_checked()
+            .script_pubkey();
+        self.service()
+            .txs
+            .values()
+            .filter(|(_, height)| height.is_some())
+            .flat_map(|(tx, _)| &tx.output)
+            .filter(|output| output.script_pubkey == script)
+            .map(|output| output.value.to_sat())
+            .sum()
+    }
+
+    /// Miner fee of the transaction spending swap `id`'s lockup.
+    pub fn claim_fee(&self, id: &str) -> Option<u64> {
+        let service = self.service();
+        let lockup = service.swaps.get(id)?.lockup.as_ref()?;
+        let outpoint = OutPoint::new(lockup.compute_txid(), 0);
+        let (claim, _) = service.txs.get(&service.spender_of(&outpoint)?)?;
+        let paid: u64 = claim
+            .output
+            .iter()
+            .map(|output| output.value.to_sat())
+            .sum();
+        Some(lockup.output[0].value.to_sat() - paid)
+    }
+
+    /// The lockup transaction of swap `id`, paying its script the agreed
+    /// amount, reached the mempool.
+    pub fn lock_up(&self, id: &str) {
+        let amount = self.service().swap(id).onchain_amount;
+        self.lock_up_amount(id, amount);
+    }
+
+    /// The service locked up `sats` short of the agreed amount.
+    pub fn lock_up_short(&self, id: &str, sats: u64) {
+        let amount = self.service().swap(id).onchain_amount;
+        self.lock_up_amount(id, amount - sats);
+    }
+
+    fn lock_up_amount(&self, id: &str, sats: u64) {
+        let mut service = self.service();
+        let funding = OutPoint::new(Txid::from_byte_array(rand::random()), 0);
+        let swap = service.swap(id);
+        let lockup = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: funding,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey: swap.lockup_script.clone(),
+            }],
+        };
+        swap.lockup = Some(lockup.clone());
+        swap.status = "transaction.mempool".to_string();
+        service.txs.insert(lockup.compute_txid(), (lockup, None));
+    }
+
+    /// The lockup transaction of swap `id` confirmed (locking up first if
+    /// the test did not).
+    pub fn confirm(&self, id: &str) {
+        if self.service().swap(id).lockup.is_none() {
+            self.lock_up(id);
+        }
+        self.mine();
+        self.service().swap(id).status = "transaction.confirmed".to_string();
+    }
+
+    /// Mine a block confirming every transaction in the mempool.
+    pub fn mine(&self) {
+        let mut service = self.service();
+        service.height += 1;
+        let height = service.height;
+        for (_, confirmed) in service.txs.values_mut() {
+            confirmed.get_or_insert(height);
+        }
+    }
+
+    /// Swap `id` expired: the service fails the node's held payment.
+    pub fn expire(&self, id: &str) {
+        self.service().swap(id).status = "swap.expired".to_string();
+        let service = self.service();
+        if let (Some(node), Some(swap)) = (&service.node, service.swaps.get(id)) {
+            node.resolve_outgoing(&swap.request.preimage_hash, PaymentStatus::Failed);
+        }
+    }
+}
+
+/// Check that input 0 of `tx` spends `prevout` through the claim leaf,
+/// signed by `claim_key`, and return the preimage it reveals.
+fn verify_claim(
+    tx: &Transaction,
+    prevout: &TxOut,
+    claim_key: &XOnlyPublicKey,
+) -> Result<Vec<u8>, MostroError> {
+    let secp = Secp256k1::verification_only();
+    let invalid = || swap_error("mandatory-script-verify-flag-failed");
+    let witness: Vec<&[u8]> = tx.input[0].witness.iter().collect();
+    let [signature, preimage, leaf, control_block] = witness[..] else {
+        return Err(invalid());
+    };
+    let leaf = ScriptBuf::from_bytes(leaf.to_vec());
+    let output_key = XOnlyPublicKey::from_slice(&prevout.script_pubkey.as_bytes()[2..])
+        .map_err(|_| invalid())?;
+    let control_block = ControlBlock::decode(control_block).map_err(|_| invalid())?;
+    if !control_block.verify_taproot_commitment(&secp, output_key, &leaf) {
+        return Err(invalid());
+    }
+    let sighash = SighashCache::new(tx)
+        .taproot_script_spend_signature_hash(
+            0,
+            &Prevouts::All(&[prevout]),
+            TapLeafHash::from_script(&leaf, LeafVersion::TapScript),
+            TapSighashType::Default,
+        )
+        .map_err(|_| invalid())?;
+    let signature = schnorr::Signature::from_slice(signature).map_err(|_| invalid())?;
+    secp.verify_schnorr(
+        &signature,
+        &Message::from_digest(sighash.to_byte_array()),
+        claim_key,
+    )
+    .map_err(|_| invalid())?;
+    Ok(preimage.to_vec())
+}
+
+#[async_trait]
+impl SwapProvider for MemorySwapService {
+    async fn create_reverse_swap(
+        &self,
+        request: &ReverseSwapRequest,
+    ) -> Result<ReverseSwap, MostroError> {
+        let mut service = self.service();
+        if service.refuse {
+            return Err(swap_error("400 Bad Request: swaps are paused"));
+        }
+        service.created += 1;
+        let id = format!("swap{}", service.created);
+        let hash = decode_hash32("preimageHash", &request.preimage_hash)?;
+        let claim_key = PublicKey::from_str(&request.claim_public_key).map_err(swap_error)?;
+        let refund_secret =
+            SecretKey::from_slice(&rand::random::<[u8; 32]>()).map_err(swap_error)?;
+        let refund_key = PublicKey::from_secret_key(&Secp256k1::new(), &refund_secret);
+        let timeout = service.height + SWAP_TIMEOUT_BLOCKS;
+        let lockup_claim_key = if service.forge_addresses {
+            refund_key
+        } else {
+            claim_key
+        };
+        let script = SwapScript::new(&lockup_claim_key, &refund_key, &hash, timeout)?;
+
+        let invoice = bolt11(&hash, request.invoice_amount as i64, "Send to BTC address");
+        if let Some(node) = &service.node {
+            node.hold_outgoing(&request.preimage_hash);
+        }
+        let onchain_amount = request.invoice_amount.saturating_sub(service.fee);
+        service.swaps.insert(
+            id.clone(),
+            SimSwap {
+                request: request.clone(),
+                onchain_amount,
+                status: "swap.created".to_string(),
+                lockup_script: script.script_pubkey(),
+                lockup: None,
+            },
+        );
+        Ok(ReverseSwap {
+            id,
+            invoice,
+            lockup_address: script.address(Network::Regtest).to_string(),
+            refund_public_key: refund_key.to_string(),
+            onchain_amount,
+            timeout_block_height: timeout,
+        })
+    }
+
+    async fn swap_status(&self, id: &str) -> Result<String, MostroError> {
+        self.status(id)
+            .ok_or_else(|| swap_error(format!("404 Not Found: could not find swap {id}")))
+    }
+
+    async fn lockup_transaction(&self, id: &str) -> Result<String, MostroError> {
+        self.service()
+            .swaps
+            .get(id)
+            .and_then(|swap| swap.lockup.as_ref())
+            .map(serialize_hex)
+            .ok_or_else(|| swap_error(format!("404 Not Found: no lockup for swap {id}")))
+    }
+}
+
+#[async_trait]
+impl ChainSource for MemorySwapService {
+    async fn tip_height(&self) -> Result<u32, MostroError> {
+        Ok(self.service().height)
+    }
+
+    async fn confirmations(&self, txid: &Txid) -> Result<Option<u32>, MostroError> {
+        let service = self.service();
+        Ok(service
+            .txs
+            .get(txid)
+            .map(|(_, confirmed)| confirmed.map_or(0, |height| service.height - height + 1)))
+    }
+
+    async fn fee_rate(&self) -> Result<f64, MostroError> {
+        Ok(self.service().fee_rate)
+    }
+
+    /// Accept a claim of a swap lockup. The service watches the chain: the
+    /// preimage the claim reveals settles the node's held payment.
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), MostroError> {
+        let mut service = self.service();
+        let txid = tx.compute_txid();
+        if service.txs.contains_key(&txid) {
+            return Ok(());
+        }
+        let outpoint = tx.input[0].previous_output;
+        let swap = service
+            .swap_locked_at(&outpoint)
+            .ok_or_else(|| swap_error("bad-txns-inputs-missingorspent"))?
+            .clone();
+        if service.spender_of(&outpoint).is_some() {
+            return Err(swap_error("bad-txns-inputs-missingorspent"));
+        }
+        let prevout = &swap.lockup.as_ref().expect("locked up").output[0];
+        let claim_key = PublicKey::from_str(&swap.request.claim_public_key)
+            .map_err(swap_error)?
+            .x_only_public_key()
+            .0;
+        let preimage = verify_claim(tx, prevout, &claim_key)?;
+        if sha256::Hash::hash(&preimage).to_string() != swap.request.preimage_hash {
+            return Err(swap_error("mandatory-script-verify-flag-failed"));
+        }
+        service.txs.insert(txid, (tx.clone(), None));
+        let hash = swap.request.preimage_hash.clone();
+        if let Some(sim) = service
+            .swaps
+            .values_mut()
+            .find(|sim| sim.request.preimage_hash == hash)
+        {
+            sim.status = "invoice.settled".to_string();
+        }
+        if let Some(node) = &service.node {
+            node.resolve_outgoing(&hash, PaymentStatus::Succeeded);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::consensus::encode::deserialize_hex;
+
+    struct Claimer {
+        secret: SecretKey,
+        preimage: [u8; 32],
+        request: ReverseSwapRequest,
+    }
+
+    fn claimer(preimage: [u8; 32]) -> Claimer {
+        let secret = SecretKey::from_slice(&[11u8; 32]).unwrap();
+        let request = ReverseSwapRequest {
+            invoice_amount: 100_000,
+            preimage_hash: sha256::Hash::hash(&preimage).to_string(),
+            claim_public_key: PublicKey::from_secret_key(&Secp256k1::new(), &secret).to_string(),
+            address: test_address(Network::Regtest),
+        };
+        Claimer {
+            secret,
+            preimage,
+            request,
+        }
+    }
+
+    /// Mostro's claim of `swap`, revealing `preimage`.
+    async fn claim(
+        service: &MemorySwapService,
+        claimer: &Claimer,
+        swap: &ReverseSwap,
+        preimage: &[u8; 32],
+    ) -> Transaction {
+        let lockup: Transaction =
+            deserialize_hex(&service.lockup_transaction(&swap.id).await.unwrap()).unwrap();
+        let script = SwapScript::new(
+            &PublicKey::from_secret_key(&Secp256k1::new(), &claimer.secret),
+            &PublicKey::from_str(&swap.refund_public_key).unwrap(),
+            sha256::Hash::hash(&claimer.preimage).as_byte_array(),
+            swap.timeout_block_height,
+        )
+        .unwrap();
+        script
+            .claim_tx(
+                OutPoint::new(lockup.compute_txid(), 0),
+                &lockup.output[0],
+                preimage,
+                &claimer.secret,
+                Address::from_str(&claimer.request.address)
+                    .unwrap()
+                    .assume_checked()
+                    .script_pubkey(),
+                2.0,
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn accepts_only_a_valid_claim_of_the_lockup() {
+        let service = MemorySwapService::new();
+        service.set_fee(500);
+        let claimer = claimer([9u8; 32]);
+        let swap = service.create_reverse_swap(&claimer.request).await.unwrap();
+        assert_eq!(swap.onchain_amount, 99_500);
+        assert!(service.lockup_transaction(&swap.id).await.is_err());
+
+        service.confirm(&swap.id);
+        let lockup: Transaction =
+            deserialize_hex(&service.lockup_transaction(&swap.id).await.unwrap()).unwrap();
+        assert_eq!(
+            service.confirmations(&lockup.compute_txid()).await.unwrap(),
+            Some(1)
+        );
+
+        let wrong = claim(&service, &claimer, &swap, &[1u8; 32]).await;
+        assert!(service.broadcast(&wrong).await.is_err());
+        let right = claim(&service, &claimer, &swap, &claimer.preimage).await;
+        service.broadcast(&right).await.unwrap();
+        assert_eq!(
+            service.swap_status(&swap.id).await.unwrap(),
+            "invoice.settled"
+        );
+        assert_eq!(service.paid_to(&claimer.request.address), 0);
+
+        service.mine();
+        let fee = service.claim_fee(&swap.id).unwrap();
+        assert!(fee > 0);
+        assert_eq!(service.paid_to(&claimer.request.address), 99_500 - fee);
+    }
+
+    #[tokio::test]
+    async fn holds_the_node_payment_until_claim_or_expiry() {
+        use crate::escrow::EscrowBackend;
+
+        let mut node = MemoryEscrow::new();
+        let service = MemorySwapService::new().on_node(node.clone());
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+
+        let claimer_a = claimer([3u8; 32]);
+        let hash = sha256::Hash::hash(&claimer_a.preimage);
+        let claimed = service
+            .create_reverse_swap(&claimer_a.request)
+            .await
+            .unwrap();
+        node.send_payment(&claimed.invoice, 100_000, tx.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            node.lookup_payment_status(hash.as_byte_array())
+                .await
+                .unwrap(),
+            Some(PaymentStatus::InFlight)
+        );
+        service.confirm(&claimed.id);
+        let claim_tx = claim(&service, &claimer_a, &claimed, &claimer_a.preimage).await;
+        service.broadcast(&claim_tx).await.unwrap();
+        assert_eq!(
+            node.lookup_payment_status(hash.as_byte_array())
+                .await
+                .unwrap(),
+            Some(PaymentStatus::Succeeded)
+        );
+
+        let claimer_b = claimer([4u8; 32]);
+        let hash = sha256::Hash::hash(&claimer_b.preimage);
+        let expired = service
+            .create_reverse_swap(&claimer_b.request)
+            .await
+            .unwrap();
+        node.send_payment(&expired.invoice, 100_000, tx)
+            .await
+            .unwrap();
+        service.expire(&expired.id);
+        assert_eq!(
+            node.lookup_payment_status(hash.as_byte_array())
+                .await
+                .unwrap(),
+            Some(PaymentStatus::Failed)
+        );
+    }
+}
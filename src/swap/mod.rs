@@ -0,0 +1,240 @@
+//! On-chain buyer payouts through reverse submarine swaps
+//! (`docs/SWAP_PAYOUTS.md`).
+//!
+//! A buyer whose payout would struggle to route over Lightning can give a
+//! bitcoin address instead of an invoice. At release Mostro asks a
+//! Boltz-compatible swap service for a reverse swap: the service locks the
+//! payout on-chain against a hash only Mostro knows the preimage of, and
+//! Mostro pays the service's invoice from its node. Once the lockup is
+//! confirmed on a chain source Mostro trusts and matches the swap's script
+//! and amount, Mostro claims it to the buyer's address itself — and only
+//! when that claim confirms is the seller's hold invoice settled.
+//!
+//! The service sits behind [`SwapProvider`] and the chain behind
+//! [`ChainSource`]: [`boltz::BoltzClient`] and [`esplora::EsploraClient`]
+//! speak the HTTP APIs, `memory` is the stand-in the tests drive.
+use async_trait::async_trait;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Transaction, Txid};
+use mostro_core::prelude::*;
+use std::str::FromStr;
+
+use crate::config::types::SwapSettings;
+
+pub mod boltz;
+pub mod db;
+pub mod esplora;
+#[cfg(test)]
+pub(crate) mod memory;
+pub mod payout;
+pub mod script;
+
+/// What Mostro asks the swap service for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReverseSwapRequest {
+    /// Amount of the invoice Mostro pays, in sats.
+    pub invoice_amount: u64,
+    /// Hex SHA-256 of the preimage Mostro holds.
+    pub preimage_hash: String,
+    /// Hex compressed public key the lockup can be claimed with.
+    pub claim_public_key: String,
+    /// The buyer's address the claim pays to.
+    pub address: String,
+}
+
+/// The service's side of a freshly created reverse swap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReverseSwap {
+    pub id: String,
+    /// BOLT11 invoice Mostro pays; it must pay to the requested hash.
+    pub invoice: String,
+    pub lockup_address: String,
+    /// Hex compressed public key the service can refund the lockup with
+    /// after the timeout.
+    pub refund_public_key: String,
+    /// Sats the service locks on-chain, after its fees.
+    pub onchain_amount: u64,
+    /// Block from which the service may refund the lockup to itself.
+    pub timeout_block_height: u32,
+}
+
+/// A swap's progress as reported by the service, folded to what the payout
+/// watcher acts on. Only a hint: the lockup and the claim are checked on the
+/// chain source before Mostro acts on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapStatus {
+    /// Waiting for Mostro's payment or for the lockup transaction.
+    Pending,
+    /// The service broadcast the lockup transaction.
+    LockedUp,
+    /// The service saw the lockup claimed and settled Mostro's payment.
+    Settled,
+    /// The swap will not complete; Mostro's payment is or will be refunded.
+    Failed,
+}
+
+impl SwapStatus {
+    /// Map a Boltz swap status string.
+    pub fn from_provider(status: &str) -> Self {
+        match status {
+            "transaction.mempool" | "transaction.confirmed" => SwapStatus::LockedUp,
+            "invoice.settled" | "transaction.claimed" => SwapStatus::Settled,
+            "swap.expired"
+            | "invoice.expired"
+            | "transaction.failed"
+            | "transaction.refunded"
+            | "transaction.lockupFailed" => SwapStatus::Failed,
+            _ => SwapStatus::Pending,
+        }
+    }
+}
+
+/// A Boltz-compatible reverse swap service.
+#[async_trait]
+pub trait SwapProvider: Send + Sync {
+    /// Create a reverse swap paying `request.address`.
+    async fn create_reverse_swap(
+        &self,
+        request: &ReverseSwapRequest,
+    ) -> Result<ReverseSwap, MostroError>;
+
+    /// The raw status string of swap `id`.
+    async fn swap_status(&self, id: &str) -> Result<String, MostroError>;
+
+    /// The raw lockup transaction of swap `id`, hex encoded.
+    async fn lockup_transaction(&self, id: &str) -> Result<String, MostroError>;
+}
+
+/// The bitcoin chain, as seen by a node Mostro trusts rather than the swap
+/// service.
+#[async_trait]
+pub trait ChainSource: Send + Sync {
+    async fn tip_height(&self) -> Result<u32, MostroError>;
+
+    /// Confirmations of `txid`: `None` when the chain does not know it,
+    /// `Some(0)` while it is in the mempool.
+    async fn confirmations(&self, txid: &Txid) -> Result<Option<u32>, MostroError>;
+
+    /// Fee rate, in sat/vB, for a transaction to confirm within a few blocks.
+    async fn fee_rate(&self) -> Result<f64, MostroError>;
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), MostroError>;
+}
+
+/// The swap service configured in `[swap]`.
+pub fn connect_swap(cfg: &SwapSettings) -> Result<Box<dyn SwapProvider>, MostroError> {
+    #[cfg(test)]
+    if let Some(service) = memory::scoped_service() {
+        return Ok(Box::new(service));
+    }
+    Ok(Box::new(boltz::BoltzClient::new(&cfg.api_url)?))
+}
+
+/// The chain source configured in `[swap]`.
+pub fn connect_chain(cfg: &SwapSettings) -> Result<Box<dyn ChainSource>, MostroError> {
+    #[cfg(test)]
+    if let Some(service) = memory::scoped_service() {
+        return Ok(Box::new(service));
+    }
+    Ok(Box::new(esplora::EsploraClient::new(&cfg.esplora_url)?))
+}
+
+pub(crate) fn swap_error(e: impl std::fmt::Display) -> MostroError {
+    MostroInternalErr(ServiceError::LnPaymentError(format!("swap service: {e}")))
+}
+
+/// The destination parsed as a bitcoin address, whatever its network.
+/// `None` means it is a Lightning destination.
+pub fn payout_address(destination: &str) -> Option<Address<NetworkUnchecked>> {
+    Address::from_str(destination.trim()).ok()
+}
+
+/// True when the order's buyer is paid on-chain.
+pub fn is_swap_payout(order: &Order) -> bool {
+    order
+        .buyer_invoice
+        .as_deref()
+        .is_some_and(|destination| payout_address(destination).is_some())
+}
+
+/// Check a buyer's on-chain payout destination: an address on the configured
+/// chain, for a net payout (`amount` minus the Mostro fee) inside the swap
+/// range. A zero net amount (market-priced order not taken yet) is checked
+/// again when the swap is created.
+pub fn validate_payout_address(
+    destination: &str,
+    net_amount: u64,
+    cfg: &SwapSettings,
+) -> Result<(), MostroError> {
+    let network = cfg
+        .bitcoin_network()
+        .ok_or(MostroCantDo(CantDoReason::InvalidInvoice))?;
+    payout_address(destination)
+        .and_then(|address| address.require_network(network).ok())
+        .ok_or(MostroCantDo(CantDoReason::InvalidInvoice))?;
+    if net_amount != 0 && !(cfg.min_amount..=cfg.max_amount).contains(&net_amount) {
+        return Err(MostroCantDo(CantDoReason::OutOfRangeSatsAmount));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Network;
+
+    const MAINNET: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+    fn cfg() -> SwapSettings {
+        SwapSettings {
+            enabled: true,
+            api_url: "http://127.0.0.1:9001".to_string(),
+            ..SwapSettings::default()
+        }
+    }
+
+    #[test]
+    fn addresses_and_invoices_are_told_apart() {
+        assert!(payout_address(MAINNET).is_some());
+        assert!(payout_address(&memory::test_address(Network::Regtest)).is_some());
+        assert!(payout_address("lnbc1pvjluezpp5qqqsyqcyq5rqwzqf").is_none());
+        assert!(payout_address("alice@example.com").is_none());
+    }
+
+    #[test]
+    fn payout_address_must_match_network_and_range() {
+        assert!(validate_payout_address(MAINNET, 100_000, &cfg()).is_ok());
+        assert!(validate_payout_address(MAINNET, 0, &cfg()).is_ok());
+        assert_eq!(
+            validate_payout_address(&memory::test_address(Network::Regtest), 100_000, &cfg())
+                .unwrap_err(),
+            MostroCantDo(CantDoReason::InvalidInvoice)
+        );
+        assert_eq!(
+            validate_payout_address(MAINNET, 1_000, &cfg()).unwrap_err(),
+            MostroCantDo(CantDoReason::OutOfRangeSatsAmount)
+        );
+        assert_eq!(
+            validate_payout_address(MAINNET, 30_000_000, &cfg()).unwrap_err(),
+            MostroCantDo(CantDoReason::OutOfRangeSatsAmount)
+        );
+    }
+
+    #[test]
+    fn provider_statuses_fold_to_what_the_watcher_needs() {
+        assert_eq!(
+            SwapStatus::from_provider("swap.created"),
+            SwapStatus::Pending
+        );
+        for locked in ["transaction.mempool", "transaction.confirmed"] {
+            assert_eq!(SwapStatus::from_provider(locked), SwapStatus::LockedUp);
+        }
+        assert_eq!(
+            SwapStatus::from_provider("invoice.settled"),
+            SwapStatus::Settled
+        );
+        for failed in ["swap.expired", "invoice.expired", "transaction.refunded"] {
+            assert_eq!(SwapStatus::from_provider(failed), SwapStatus::Failed);
+        }
+    }
+}
@@ -0,0 +1,553 @@
+//! Paying a buyer through a reverse swap and driving the swap to completion.
+//!
+//! [`prepare_swap_payout`] creates the swap at the service, checks its quote
+//! and lockup address, and stores it; [`dispatch_swap_payment`] pays the
+//! service's invoice. From there the payout watcher ([`swap_payout_pass`])
+//! follows the swap. Once the chain source shows the lockup paying the swap
+//! script the agreed amount with enough confirmations, Mostro signs and
+//! broadcasts its own claim to the buyer's address. When that claim confirms
+//! it settles the seller's hold invoice (if the swap was started by a
+//! release, which leaves it held) and moves the order to `Success`.
+//!
+//! A failed swap settles a still-held hold invoice — the seller released,
+//! the sats are the buyer's — and hands the order to the failed-payment
+//! retries, which start a fresh swap (or pay the new invoice the buyer
+//! sends once the retries run out).
+
+use crate::db::Crud;
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{PublicKey as SecpPublicKey, Secp256k1, SecretKey};
+use bitcoin::{Address, OutPoint, Transaction};
+use chrono::Utc;
+use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus;
+use mostro_core::prelude::*;
+use rand::RngCore;
+use std::str::FromStr;
+use tokio::sync::mpsc::channel;
+use tracing::{error, info, warn};
+
+use super::db::{self, Swap, SwapState};
+use super::script::SwapScript;
+use super::{
+    connect_swap, swap_error, validate_payout_address, ChainSource, ReverseSwapRequest,
+    SwapProvider, SwapStatus,
+};
+use crate::app::context::AppContext;
+use crate::app::release::{check_failure_retries_or_log, payment_success};
+use crate::config::types::SwapSettings;
+use crate::escrow::EscrowBackend;
+use crate::lightning::decode_hash32;
+use crate::lightning::invoice::decode_invoice;
+use crate::util::bytes_to_string;
+
+/// Blocks that must remain before the service may refund a lockup for
+/// Mostro to still claim it: revealing the preimage any later risks the
+/// service settling Mostro's payment and refunding the lockup too.
+const MIN_CLAIM_WINDOW_BLOCKS: u32 = 12;
+
+fn swap_settings(ctx: &AppContext) -> Result<&SwapSettings, MostroError> {
+    ctx.settings()
+        .swap
+        .as_ref()
+        .filter(|cfg| cfg.enabled)
+        .ok_or_else(|| swap_error("on-chain payouts are disabled"))
+}
+
+fn swap_network(cfg: &SwapSettings) -> Result<bitcoin::Network, MostroError> {
+    cfg.bitcoin_network()
+        .ok_or_else(|| swap_error(format!("unknown network {:?}", cfg.network)))
+}
+
+/// The lockup script of a stored swap.
+fn swap_script(swap: &Swap) -> Result<SwapScript, MostroError> {
+    let claim_key =
+        SecretKey::from_slice(&decode_hash32("claim_key", &swap.claim_key)?).map_err(swap_error)?;
+    let refund_key = SecpPublicKey::from_str(&swap.refund_public_key).map_err(swap_error)?;
+    SwapScript::new(
+        &SecpPublicKey::from_secret_key(&Secp256k1::new(), &claim_key),
+        &refund_key,
+        &decode_hash32("preimage_hash", &swap.preimage_hash)?,
+        swap.timeout_block_height as u32,
+    )
+}
+
+/// Create a reverse swap paying the order's on-chain destination and store
+/// it. `settle_hold` records that the seller's hold invoice is still held
+/// and must be settled when the swap completes.
+///
+/// The service's quote is checked before anything is stored: its invoice
+/// must pay to Mostro's hash for exactly the net payout, the amount it locks
+/// on-chain may not fall short of it by more than `max_fee_pct`, and its
+/// lockup address must be the one Mostro derives from both keys, the hash
+/// and the timeout — so the claim leaf really pays Mostro's claim key.
+///
+/// Returns `Ok(None)` when the order already has a live swap.
+pub async fn prepare_swap_payout(
+    ctx: &AppContext,
+    order: &Order,
+    settle_hold: bool,
+) -> Result<Option<Swap>, MostroError> {
+    let cfg = swap_settings(ctx)?;
+    let address = order
+        .buyer_invoice
+        .as_deref()
+        .ok_or(MostroInternalErr(ServiceError::InvoiceInvalidError))?
+        .trim()
+        .to_string();
+    let amount = (order.amount as u64).saturating_sub(order.fee as u64);
+    if amount == 0 {
+        return Err(MostroInternalErr(ServiceError::InvoiceInvalidError));
+    }
+    validate_payout_address(&address, amount, cfg)?;
+
+    let mut preimage = [0u8; 32];
+    let mut claim_secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut preimage);
+    rand::thread_rng().fill_bytes(&mut claim_secret);
+    let preimage_hash = sha256::Hash::hash(&preimage);
+    let claim_key = SecretKey::from_slice(&claim_secret).map_err(swap_error)?;
+    let claim_public_key = SecpPublicKey::from_secret_key(&Secp256k1::new(), &claim_key);
+
+    let provider = connect_swap(cfg)?;
+    let created = provider
+        .create_reverse_swap(&ReverseSwapRequest {
+            invoice_amount: amount,
+            preimage_hash: preimage_hash.to_string(),
+            claim_public_key: claim_public_key.to_string(),
+            address: address.clone(),
+        })
+        .await?;
+
+    let invoice = decode_invoice(&created.invoice)?;
+    if bytes_to_string(invoice.payment_hash().as_ref()) != preimage_hash.to_string()
+        || invoice.amount_milli_satoshis() != Some(amount * 1000)
+    {
+        return Err(swap_error(format!(
+            "swap {} invoice does not pay {amount} sats to the requested hash",
+            created.id
+        )));
+    }
+    let min_onchain = (amount as f64 * (1.0 - cfg.max_fee_pct)).ceil() as u64;
+    if created.onchain_amount > amount || created.onchain_amount < min_onchain {
+        return Err(swap_error(format!(
+            "swap {} locks {} sats for a {amount} sats payout, fee cap is {}",
+            created.id, created.onchain_amount, cfg.max_fee_pct
+        )));
+    }
+    let refund_key = SecpPublicKey::from_str(&created.refund_public_key).map_err(swap_error)?;
+    let lockup_address = SwapScript::new(
+        &claim_public_key,
+        &refund_key,
+        preimage_hash.as_byte_array(),
+        created.timeout_block_height,
+    )?
+    .address(swap_network(cfg)?);
+    if created.lockup_address != lockup_address.to_string() {
+        return Err(swap_error(format!(
+            "swap {} locks up to {}, not to its script at {lockup_address}",
+            created.id, created.lockup_address
+        )));
+    }
+
+    let now = Utc::now().timestamp();
+    let swap = Swap {
+        id: created.id,
+        order_id: order.id,
+        address,
+        invoice: created.invoice,
+        invoice_amount: amount as i64,
+        onchain_amount: created.onchain_amount as i64,
+        lockup_address: created.lockup_address,
+        refund_public_key: created.refund_public_key,
+        timeout_block_height: created.timeout_block_height as i64,
+        preimage: bytes_to_string(&preimage),
+        preimage_hash: preimage_hash.to_string(),
+        claim_key: bytes_to_string(&claim_secret),
+        claim_tx: None,
+        settle_hold,
+        status: SwapState::Created,
+        provider_status: None,
+        created_at: now,
+        updated_at: now,
+    };
+    if !db::create_swap(ctx.pool(), &swap).await? {
+        warn!(
+            "Order {}: a swap payout is already in progress, not creating another",
+            order.id
+        );
+        return Ok(None);
+    }
+    info!(
+        "Order {}: swap {} pays {} sats on-chain to {}",
+        order.id, swap.id, swap.onchain_amount, swap.address
+    );
+    Ok(Some(swap))
+}
+
+/// Pay the swap's invoice from the node, off the caller's task: the service
+/// holds the HTLC until Mostro reveals the preimage, so the payment stays in
+/// flight for as long as the lockup takes to confirm. Its outcome is read
+/// back by the watcher, never from this task.
+pub async fn dispatch_swap_payment(swap: &Swap) -> Result<(), MostroError> {
+    let mut ln_client = crate::escrow::connect_lightning().await?;
+    let invoice = swap.invoice.clone();
+    let amount = swap.invoice_amount;
+    let swap_id = swap.id.clone();
+    crate::instance::spawn(async move {
+        let (tx, mut rx) = channel(8);
+        let send = ln_client.send_payment(&invoice, amount, tx);
+        let drain = async {
+            while let Some(msg) = rx.recv().await {
+                info!(
+                    "Swap {swap_id}: invoice payment status {:?}",
+                    msg.payment.status()
+                );
+            }
+        };
+        let (sent, ()) = tokio::join!(send, drain);
+        if let Err(e) = sent {
+            warn!("Swap {swap_id}: invoice payment failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Pay an order whose hold invoice is already settled (a retry, or a
+/// solver's ruling for the buyer) through a fresh swap. A swap that cannot
+/// be created counts as a failed payment attempt.
+pub async fn swap_payout(
+    ctx: &AppContext,
+    order: &Order,
+    request_id: Option<u64>,
+) -> Result<(), MostroError> {
+    let swap = match prepare_swap_payout(ctx, order, false).await {
+        Ok(Some(swap)) => swap,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            warn!("Order id {}: could not start swap payout: {e}", order.id);
+            check_failure_retries_or_log(ctx, order, request_id).await;
+            return Err(e);
+        }
+    };
+    // An unpaid swap lapses at the service; the watcher then counts the
+    // failed attempt.
+    if let Err(e) = dispatch_swap_payment(&swap).await {
+        warn!("Order id {}: swap {} not paid: {e}", order.id, swap.id);
+    }
+    Ok(())
+}
+
+/// Give up on a stored swap whose payment was never dispatched, because the
+/// release it was created for did not go through.
+pub async fn abandon_swap(ctx: &AppContext, swap: &Swap) {
+    match db::transition_swap(
+        ctx.pool(),
+        &swap.id,
+        SwapState::Created,
+        SwapState::Failed,
+        "abandoned",
+    )
+    .await
+    {
+        Ok(_) => info!("Swap {} abandoned before payment", swap.id),
+        Err(e) => warn!("Swap {}: could not abandon: {e}", swap.id),
+    }
+}
+
+/// One watcher tick: move every live swap forward as far as the service's
+/// state and the chain allow. Errors on one swap are logged and retried next
+/// tick.
+pub async fn swap_payout_pass(
+    ctx: &AppContext,
+    provider: &dyn SwapProvider,
+    chain: &dyn ChainSource,
+    escrow: &mut dyn EscrowBackend,
+) -> Result<(), MostroError> {
+    for swap in db::find_live_swaps(ctx.pool()).await? {
+        if let Err(e) = advance_swap(ctx, provider, chain, escrow, &swap).await {
+            warn!("Swap {} of order {}: {e}", swap.id, swap.order_id);
+        }
+    }
+    Ok(())
+}
+
+async fn advance_swap(
+    ctx: &AppContext,
+    provider: &dyn SwapProvider,
+    chain: &dyn ChainSource,
+    escrow: &mut dyn EscrowBackend,
+    swap: &Swap,
+) -> Result<(), MostroError> {
+    let mut order = Order::by_id(ctx.pool(), swap.order_id)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?
+        .ok_or(MostroInternalErr(ServiceError::InvalidOrderId))?;
+
+    // Once claimed, only the chain tells whether the buyer is paid.
+    if swap.status == SwapState::Claimed {
+        return follow_claim(ctx, chain, escrow, swap, &mut order).await;
+    }
+
+    let raw_status = provider.swap_status(&swap.id).await?;
+    match SwapStatus::from_provider(&raw_status) {
+        SwapStatus::LockedUp => {
+            // A lockup that does not check out is never claimed, but the
+            // seller's HTLC is still guarded while the swap runs out.
+            let claimed = claim_lockup(ctx, provider, chain, swap, &raw_status).await;
+            guard_hold_deadline(ctx, escrow, swap, &order).await?;
+            claimed
+        }
+        SwapStatus::Failed => fail_swap(ctx, escrow, swap, &order, &raw_status).await,
+        _ => guard_hold_deadline(ctx, escrow, swap, &order).await,
+    }
+}
+
+/// Claim the lockup to the buyer's address once the chain source shows it
+/// paying the swap script at least the agreed amount, with
+/// `lockup_confirmations` and enough blocks left before the service could
+/// refund it. The claim is stored before it is broadcast, as broadcasting
+/// it reveals the preimage.
+async fn claim_lockup(
+    ctx: &AppContext,
+    provider: &dyn SwapProvider,
+    chain: &dyn ChainSource,
+    swap: &Swap,
+    raw_status: &str,
+) -> Result<(), MostroError> {
+    let cfg = swap_settings(ctx)?;
+    let script = swap_script(swap)?;
+    let lockup: Transaction =
+        deserialize_hex(&provider.lockup_transaction(&swap.id).await?).map_err(swap_error)?;
+    let txid = lockup.compute_txid();
+    let lockup_script = script.script_pubkey();
+    let (vout, prevout) = lockup
+        .output
+        .iter()
+        .enumerate()
+        .find(|(_, output)| output.script_pubkey == lockup_script)
+        .ok_or_else(|| swap_error(format!("lockup {txid} does not pay the swap script")))?;
+    if prevout.value.to_sat() < swap.onchain_amount as u64 {
+        return Err(swap_error(format!(
+            "lockup {txid} locks {} sats, {} were agreed",
+            prevout.value.to_sat(),
+            swap.onchain_amount
+        )));
+    }
+
+    let confirmations = chain.confirmations(&txid).await?.unwrap_or(0);
+    if confirmations < cfg.lockup_confirmations {
+        info!(
+            "Swap {}: lockup {txid} has {confirmations} of {} confirmations",
+            swap.id, cfg.lockup_confirmations
+        );
+        return Ok(());
+    }
+    let tip = chain.tip_height().await?;
+    if tip.saturating_add(MIN_CLAIM_WINDOW_BLOCKS) >= swap.timeout_block_height as u32 {
+        return Err(swap_error(format!(
+            "lockup {txid} times out at block {}, too close to tip {tip} to claim",
+            swap.timeout_block_height
+        )));
+    }
+
+    let destination = Address::from_str(&swap.address)
+        .map_err(swap_error)?
+        .require_network(swap_network(cfg)?)
+        .map_err(swap_error)?
+        .script_pubkey();
+    let claim_key =
+        SecretKey::from_slice(&decode_hash32("claim_key", &swap.claim_key)?).map_err(swap_error)?;
+    let claim = script.claim_tx(
+        OutPoint::new(txid, vout as u32),
+        prevout,
+        &decode_hash32("preimage", &swap.preimage)?,
+        &claim_key,
+        destination,
+        chain.fee_rate().await?,
+    )?;
+    if !db::record_claim(ctx.pool(), &swap.id, &serialize_hex(&claim), raw_status).await? {
+        return Ok(());
+    }
+    info!(
+        "Swap {}: lockup {txid} confirmed, claiming {} sats to {} in {}",
+        swap.id,
+        claim.output[0].value.to_sat(),
+        swap.address,
+        claim.compute_txid()
+    );
+    // A failed broadcast is retried by the next tick's `follow_claim`.
+    chain.broadcast(&claim).await
+}
+
+/// Wait for Mostro's claim to confirm, sending it again if the chain source
+/// lost it.
+async fn follow_claim(
+    ctx: &AppContext,
+    chain: &dyn ChainSource,
+    escrow: &mut dyn EscrowBackend,
+    swap: &Swap,
+    order: &mut Order,
+) -> Result<(), MostroError> {
+    let claim: Transaction = deserialize_hex(
+        swap.claim_tx
+            .as_deref()
+            .ok_or_else(|| swap_error("claimed without a claim transaction"))?,
+    )
+    .map_err(swap_error)?;
+    let txid = claim.compute_txid();
+    match chain.confirmations(&txid).await? {
+        Some(confirmations) if confirmations > 0 => complete_swap(ctx, escrow, swap, order).await,
+        Some(_) => guard_hold_deadline(ctx, escrow, swap, order).await,
+        None => {
+            warn!(
+                "Swap {}: claim {txid} is unknown to the chain source, broadcasting it again",
+                swap.id
+            );
+            let sent = chain.broadcast(&claim).await;
+            guard_hold_deadline(ctx, escrow, swap, order).await?;
+            sent
+        }
+    }
+}
+
+/// The buyer's claim confirmed: settle the hold invoice if the swap still
+/// owes it, then finish the trade as a Lightning payout would.
+async fn complete_swap(
+    ctx: &AppContext,
+    escrow: &mut dyn EscrowBackend,
+    swap: &Swap,
+    order: &mut Order,
+) -> Result<(), MostroError> {
+    if swap.settle_hold {
+        settle_held_escrow(escrow, order).await?;
+    }
+    if !db::transition_swap(
+        ctx.pool(),
+        &swap.id,
+        SwapState::Claimed,
+        SwapState::Completed,
+        swap.provider_status.as_deref().unwrap_or_default(),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    info!(
+        "Swap {}: order {} paid {} sats on-chain",
+        swap.id, order.id, swap.onchain_amount
+    );
+    let buyer_pubkey = order.get_buyer_pubkey().map_err(MostroInternalErr)?;
+    let keys = ctx.keys().clone();
+    payment_success(ctx, order, buyer_pubkey, &keys, None)
+        .await
+        .map_err(|e| MostroInternalErr(ServiceError::UnexpectedError(e.to_string())))?;
+    Ok(())
+}
+
+/// The service gave up on the swap. Mostro's payment of its invoice must be
+/// back before the payout is retried, or the buyer could be paid twice.
+async fn fail_swap(
+    ctx: &AppContext,
+    escrow: &mut dyn EscrowBackend,
+    swap: &Swap,
+    order: &Order,
+    raw_status: &str,
+) -> Result<(), MostroError> {
+    let hash = decode_invoice(&swap.invoice)?
+        .payment_hash()
+        .to_byte_array();
+    match escrow.lookup_payment_status(&hash).await? {
+        Some(PaymentStatus::InFlight) => return Ok(()),
+        Some(PaymentStatus::Succeeded) => {
+            error!(
+                "Swap {} of order {} reported {raw_status} but its invoice was paid — manual review required",
+                swap.id, order.id
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let released = order.get_order_status().ok() == Some(Status::SettledHoldInvoice);
+    if released && swap.settle_hold {
+        settle_held_escrow(escrow, order).await?;
+    }
+    if !db::transition_swap(
+        ctx.pool(),
+        &swap.id,
+        swap.status,
+        SwapState::Failed,
+        raw_status,
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    warn!(
+        "Swap {} of order {} failed: {raw_status}",
+        swap.id, order.id
+    );
+    if released {
+        check_failure_retries_or_log(ctx, order, None).await;
+    }
+    Ok(())
+}
+
+/// Settle the order's hold invoice unless no accepted HTLC backs it anymore
+/// (settled by an earlier tick that did not get to record it).
+async fn settle_held_escrow(
+    escrow: &mut dyn EscrowBackend,
+    order: &Order,
+) -> Result<(), MostroError> {
+    let hash = order
+        .hash
+        .as_deref()
+        .ok_or(MostroCantDo(CantDoReason::InvalidInvoice))?;
+    if let Ok(None) = escrow.hold_invoice_expiry_height(hash).await {
+        warn!(
+            "Order {}: no held HTLC left behind the escrow, not settling it again",
+            order.id
+        );
+        return Ok(());
+    }
+    let preimage = order
+        .preimage
+        .as_deref()
+        .ok_or(MostroCantDo(CantDoReason::InvalidInvoice))?;
+    escrow.settle_hold_invoice(preimage).await?;
+    info!(
+        "Order {}: hold invoice settled for its swap payout",
+        order.id
+    );
+    Ok(())
+}
+
+/// A slow lockup must not outlive the seller's HTLC: once the hold invoice
+/// is within `escrow_deadline_margin_blocks` of its expiry, settle it now —
+/// the seller released already — instead of waiting for the swap.
+async fn guard_hold_deadline(
+    ctx: &AppContext,
+    escrow: &mut dyn EscrowBackend,
+    swap: &Swap,
+    order: &Order,
+) -> Result<(), MostroError> {
+    if !swap.settle_hold {
+        return Ok(());
+    }
+    let Some(hash) = order.hash.as_deref() else {
+        return Ok(());
+    };
+    let chain = escrow.chain_height().await?;
+    let margin = ctx.settings().lightning.escrow_deadline_margin_blocks;
+    match escrow.hold_invoice_expiry_height(hash).await? {
+        Some(expiry) if chain.saturating_add(margin) < expiry => return Ok(()),
+        _ => {}
+    }
+    warn!(
+        "Swap {}: hold invoice of order {} is about to expire, settling it before the swap completes",
+        swap.id, order.id
+    );
+    settle_held_escrow(escrow, order).await?;
+    db::clear_settle_hold(ctx.pool(), &swap.id).await
+}
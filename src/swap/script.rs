@@ -0,0 +1,330 @@
+//! The on-chain side of a Boltz v2 reverse swap, rebuilt by Mostro so it
+//! never has to take the service's word for it.
+//!
+//! The lockup is a taproot output whose internal key is the MuSig2 (BIP-327)
+//! aggregate of the service's refund key and Mostro's claim key, in that
+//! order, with two leaves:
+//!
+//! - claim: `OP_SIZE 32 OP_EQUALVERIFY OP_HASH160 <hash160(preimage)>
+//!   OP_EQUALVERIFY <claim key> OP_CHECKSIG`,
+//! - refund: `<refund key> OP_CHECKSIGVERIFY <timeout> OP_CHECKLOCKTIMEVERIFY`.
+//!
+//! Mostro claims through the claim leaf alone, with the claim key and the
+//! preimage, so no signing round with the service is needed.
+
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::{ripemd160, sha256, Hash};
+use bitcoin::opcodes::all::{
+    OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CLTV, OP_EQUALVERIFY, OP_HASH160, OP_SIZE,
+};
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{
+    Keypair, Message, PublicKey, Scalar, Secp256k1, SecretKey, XOnlyPublicKey,
+};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{LeafVersion, TapLeafHash, TaprootBuilder, TaprootSpendInfo};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+};
+use mostro_core::prelude::*;
+
+use super::swap_error;
+
+/// Smallest claim output Mostro broadcasts; below it the claim would not
+/// relay.
+const DUST_LIMIT_SATS: u64 = 546;
+
+/// The lockup script of one reverse swap.
+pub struct SwapScript {
+    claim_leaf: ScriptBuf,
+    spend_info: TaprootSpendInfo,
+}
+
+impl SwapScript {
+    pub fn new(
+        claim_key: &PublicKey,
+        refund_key: &PublicKey,
+        preimage_hash: &[u8],
+        timeout_block_height: u32,
+    ) -> Result<Self, MostroError> {
+        let claim_leaf = Builder::new()
+            .push_opcode(OP_SIZE)
+            .push_int(32)
+            .push_opcode(OP_EQUALVERIFY)
+            .push_opcode(OP_HASH160)
+            .push_slice(ripemd160::Hash::hash(preimage_hash).to_byte_array())
+            .push_opcode(OP_EQUALVERIFY)
+            .push_x_only_key(&claim_key.x_only_public_key().0)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let refund_leaf = Builder::new()
+            .push_x_only_key(&refund_key.x_only_public_key().0)
+            .push_opcode(OP_CHECKSIGVERIFY)
+            .push_int(timeout_block_height as i64)
+            .push_opcode(OP_CLTV)
+            .into_script();
+        let internal_key = key_agg(&[*refund_key, *claim_key])?;
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, claim_leaf.clone())
+            .and_then(|builder| builder.add_leaf(1, refund_leaf))
+            .map_err(swap_error)?
+            .finalize(&Secp256k1::verification_only(), internal_key)
+            .map_err(|_| swap_error("incomplete swap script tree"))?;
+        Ok(Self {
+            claim_leaf,
+            spend_info,
+        })
+    }
+
+    /// The output script the lockup must pay to.
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr_tweaked(self.spend_info.output_key())
+    }
+
+    pub fn address(&self, network: Network) -> Address {
+        Address::p2tr_tweaked(self.spend_info.output_key(), network)
+    }
+
+    /// A transaction spending `lockup` (worth `prevout`) through the claim
+    /// leaf to `destination`, paying `fee_rate` sat/vB out of the lockup.
+    pub fn claim_tx(
+        &self,
+        lockup: OutPoint,
+        prevout: &TxOut,
+        preimage: &[u8],
+        claim_key: &SecretKey,
+        destination: ScriptBuf,
+        fee_rate: f64,
+    ) -> Result<Transaction, MostroError> {
+        // Sign once to learn the claim's size, then again with its fee.
+        let unfunded = self.signed_claim(lockup, prevout, preimage, claim_key, &destination, 0)?;
+        let fee = (fee_rate * unfunded.vsize() as f64).ceil() as u64;
+        if prevout.value.to_sat() < fee + DUST_LIMIT_SATS {
+            return Err(swap_error(format!(
+                "a {} sats lockup cannot pay a {fee} sats claim fee",
+                prevout.value.to_sat()
+            )));
+        }
+        self.signed_claim(lockup, prevout, preimage, claim_key, &destination, fee)
+    }
+
+    fn signed_claim(
+        &self,
+        lockup: OutPoint,
+        prevout: &TxOut,
+        preimage: &[u8],
+        claim_key: &SecretKey,
+        destination: &ScriptBuf,
+        fee: u64,
+    ) -> Result<Transaction, MostroError> {
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: lockup,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(prevout.value.to_sat().saturating_sub(fee)),
+                script_pubkey: destination.clone(),
+            }],
+        };
+        let leaf_hash = TapLeafHash::from_script(&self.claim_leaf, LeafVersion::TapScript);
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prevout]),
+                leaf_hash,
+                TapSighashType::Default,
+            )
+            .map_err(swap_error)?;
+        let secp = Secp256k1::new();
+        let signature = secp.sign_schnorr_no_aux_rand(
+            &Message::from_digest(sighash.to_byte_array()),
+            &Keypair::from_secret_key(&secp, claim_key),
+        );
+        let control_block = self
+            .spend_info
+            .control_block(&(self.claim_leaf.clone(), LeafVersion::TapScript))
+            .ok_or_else(|| swap_error("claim leaf missing from the swap script tree"))?;
+
+        let mut witness = Witness::new();
+        witness.push(signature.as_ref());
+        witness.push(preimage);
+        witness.push(self.claim_leaf.as_bytes());
+        witness.push(control_block.serialize());
+        tx.input[0].witness = witness;
+        Ok(tx)
+    }
+}
+
+/// BIP-327 `KeyAgg`: the x-only aggregate of `keys`, in the order given.
+fn key_agg(keys: &[PublicKey]) -> Result<XOnlyPublicKey, MostroError> {
+    let secp = Secp256k1::verification_only();
+    let serialized: Vec<[u8; 33]> = keys.iter().map(PublicKey::serialize).collect();
+    let list_hash = tagged_hash("KeyAgg list", &serialized.concat());
+    let second = serialized.iter().find(|key| **key != serialized[0]);
+
+    let mut terms = Vec::with_capacity(keys.len());
+    for (key, bytes) in keys.iter().zip(&serialized) {
+        if Some(bytes) == second {
+            terms.push(*key);
+            continue;
+        }
+        let coefficient = tagged_hash(
+            "KeyAgg coefficient",
+            &[list_hash.as_slice(), bytes].concat(),
+        );
+        let coefficient = Scalar::from_be_bytes(coefficient).map_err(swap_error)?;
+        terms.push(key.mul_tweak(&secp, &coefficient).map_err(swap_error)?);
+    }
+    let terms: Vec<&PublicKey> = terms.iter().collect();
+    let aggregate = PublicKey::combine_keys(&terms).map_err(swap_error)?;
+    Ok(aggregate.x_only_public_key().0)
+}
+
+/// BIP-340 tagged hash.
+fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    bitcoin::hashes::HashEngine::input(&mut engine, tag.as_ref());
+    bitcoin::hashes::HashEngine::input(&mut engine, tag.as_ref());
+    bitcoin::hashes::HashEngine::input(&mut engine, data);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::Txid;
+    use std::str::FromStr;
+
+    fn key(hex: &str) -> PublicKey {
+        PublicKey::from_str(hex).unwrap()
+    }
+
+    #[test]
+    fn aggregates_keys_as_bip327() {
+        // BIP-327 key aggregation test vectors.
+        let x = [
+            key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            key("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ];
+        let cases: [(&[usize], &str); 4] = [
+            (
+                &[0, 1, 2],
+                "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C",
+            ),
+            (
+                &[2, 1, 0],
+                "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B",
+            ),
+            (
+                &[0, 0, 0],
+                "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935",
+            ),
+            (
+                &[0, 0, 1, 1],
+                "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E",
+            ),
+        ];
+        for (indices, expected) in cases {
+            let keys: Vec<_> = indices.iter().map(|i| x[*i]).collect();
+            assert_eq!(
+                key_agg(&keys).unwrap(),
+                XOnlyPublicKey::from_str(expected).unwrap(),
+                "{indices:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn claim_spends_the_lockup_through_the_claim_leaf() {
+        let secp = Secp256k1::new();
+        let claim_secret = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let refund_secret = SecretKey::from_slice(&[8u8; 32]).unwrap();
+        let claim_key = PublicKey::from_secret_key(&secp, &claim_secret);
+        let refund_key = PublicKey::from_secret_key(&secp, &refund_secret);
+        let preimage = [5u8; 32];
+        let hash = sha256::Hash::hash(&preimage).to_byte_array();
+        let script = SwapScript::new(&claim_key, &refund_key, &hash, 800_144).unwrap();
+
+        let prevout = TxOut {
+            value: Amount::from_sat(99_000),
+            script_pubkey: script.script_pubkey(),
+        };
+        let lockup = OutPoint::new(
+            Txid::from_byte_array(<[u8; 32]>::from_hex(&"ab".repeat(32)).unwrap()),
+            1,
+        );
+        let destination = ScriptBuf::new_p2tr(&secp, refund_key.x_only_public_key().0, None);
+        let tx = script
+            .claim_tx(
+                lockup,
+                &prevout,
+                &preimage,
+                &claim_secret,
+                destination.clone(),
+                2.0,
+            )
+            .unwrap();
+
+        assert_eq!(tx.input[0].previous_output, lockup);
+        assert_eq!(tx.output[0].script_pubkey, destination);
+        let fee = 99_000 - tx.output[0].value.to_sat();
+        assert_eq!(fee, (2.0 * tx.vsize() as f64).ceil() as u64);
+
+        let witness: Vec<&[u8]> = tx.input[0].witness.iter().collect();
+        assert_eq!(witness[1], preimage);
+        let leaf = ScriptBuf::from_bytes(witness[2].to_vec());
+        let control_block = bitcoin::taproot::ControlBlock::decode(witness[3]).unwrap();
+        assert!(control_block.verify_taproot_commitment(
+            &secp,
+            script.spend_info.output_key().to_x_only_public_key(),
+            &leaf
+        ));
+
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[&prevout]),
+                TapLeafHash::from_script(&leaf, LeafVersion::TapScript),
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let signature = bitcoin::secp256k1::schnorr::Signature::from_slice(witness[0]).unwrap();
+        secp.verify_schnorr(
+            &signature,
+            &Message::from_digest(sighash.to_byte_array()),
+            &claim_key.x_only_public_key().0,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn refuses_a_claim_the_lockup_cannot_fund() {
+        let secp = Secp256k1::new();
+        let claim_secret = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let claim_key = PublicKey::from_secret_key(&secp, &claim_secret);
+        let script = SwapScript::new(&claim_key, &claim_key, &[1u8; 32], 100).unwrap();
+        let prevout = TxOut {
+            value: Amount::from_sat(600),
+            script_pubkey: script.script_pubkey(),
+        };
+        assert!(script
+            .claim_tx(
+                OutPoint::null(),
+                &prevout,
+                &[0u8; 32],
+                &claim_secret,
+                script.script_pubkey(),
+                5.0
+            )
+            .is_err());
+    }
+}
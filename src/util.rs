@@ -4,7 +4,7 @@ use crate::config::constants::{
 use crate::config::settings::{get_db_pool, Settings};
 use crate::config::*;
 use crate::db;
-use crate::db::{claim_order_status, is_user_present};
+use crate::db::{claim_order_status, create_order_as, is_user_present};
 use crate::escrow::EscrowBackend;
 use crate::flow;
 use crate::lightning;
@@ -13,17 +13,17 @@ use crate::messages;
 use crate::nip33::{
     create_platform_tag_values, new_order_event_with_created_at, new_rating_event, order_to_tags,
 };
+use crate::relay_health;
+use crate::reputation::{self, Reputation};
 use crate::Result;
 
+use crate::db::Crud;
+use crate::db::DbPool;
 use chrono::Duration;
 use fedimint_tonic_lnd::lnrpc::invoice::InvoiceState;
-use mostro_core::db::Crud;
 use mostro_core::prelude::*;
 use nostr_sdk::prelude::*;
-use sqlx::Pool;
 use sqlx::QueryBuilder;
-use sqlx::Sqlite;
-use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::fmt::Write;
 use std::str::FromStr;
@@ -218,7 +218,7 @@ pub fn get_expiration_timestamp_for_kind(kind: u16) -> Option<i64> {
 /// # async fn example() -> Result<(), MostroError> {
 /// // Assume proper initialization of the order, pool, and public keys.
 /// let order = Order { /* initialize order fields */ };
-/// let pool = SqlitePool::connect("sqlite://:memory:").await.unwrap();
+/// let pool = DbPool::connect("sqlite://:memory:").await.unwrap();
 /// let identity_pubkey = PublicKey::from_str("02abcdef...").unwrap();
 /// let trade_pubkey = identity_pubkey.clone();
 ///
@@ -228,7 +228,7 @@ pub fn get_expiration_timestamp_for_kind(kind: u16) -> Option<i64> {
 /// # }
 pub async fn get_tags_for_new_order(
     new_order_db: &Order,
-    pool: &SqlitePool,
+    pool: &DbPool,
     identity_pubkey: &PublicKey,
     trade_pubkey: &PublicKey,
     mostro_keys: &Keys,
@@ -239,14 +239,18 @@ pub async fn get_tags_for_new_order(
             // We transform the order fields to tags to use in the event
             order_to_tags(
                 new_order_db,
-                Some((user.total_rating, user.total_reviews, user.created_at)),
+                Some(reputation::profile(pool, &user).await?),
                 Some(&mostro_pubkey),
             )
         }
         Err(_) => {
             // We transform the order fields to tags to use in the event
             if identity_pubkey == trade_pubkey {
-                order_to_tags(new_order_db, Some((0.0, 0, 0)), Some(&mostro_pubkey))
+                order_to_tags(
+                    new_order_db,
+                    Some(Reputation::unrated()),
+                    Some(&mostro_pubkey),
+                )
             } else {
                 Err(MostroInternalErr(ServiceError::InvalidPubkey))
             }
@@ -269,11 +273,11 @@ pub async fn get_tags_for_new_order(
 ///
 /// ```rust
 /// # async fn example() -> Result<(), MostroError> {
-/// # use sqlx::sqlite::SqlitePool;
+/// # use DbPool;
 /// # use nostr::Keys;
 /// # use my_crate::{SmallOrder, publish_order};
 /// // Initialize the database pool and keys.
-/// let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+/// let pool = DbPool::connect("sqlite::memory:").await.unwrap();
 /// let keys = Keys::generate();
 ///
 /// // Prepare a new order along with associated public keys.
@@ -289,7 +293,7 @@ pub async fn get_tags_for_new_order(
 /// # }
 /// ```
 pub async fn publish_order(
-    pool: &SqlitePool,
+    pool: &DbPool,
     keys: &Keys,
     new_order: &SmallOrder,
     initiator_pubkey: PublicKey,
@@ -326,8 +330,7 @@ pub async fn publish_order(
     if maker_bond_required {
         let notional = maker_bond_notional_sats(&new_order_db)?;
         new_order_db.status = Status::WaitingMakerBond.to_string();
-        let order = new_order_db
-            .create(pool)
+        let order = create_order_as(pool, new_order_db, crate::instance::current_id())
             .await
             .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
         info!("New order saved (awaiting maker bond) Id: {}", order.id);
@@ -353,7 +356,7 @@ pub async fn publish_order(
                 "publish_order: request_maker_bond failed ({}); deleting stranded WaitingMakerBond order",
                 e
             );
-            if let Err(del) = sqlx::query("DELETE FROM orders WHERE id = ? AND status = ?")
+            if let Err(del) = sqlx::query("DELETE FROM orders WHERE id = $1 AND status = $2")
                 .bind(order.id)
                 .bind(Status::WaitingMakerBond.to_string())
                 .execute(pool)
@@ -370,8 +373,7 @@ pub async fn publish_order(
     }
 
     // CRUD order creation
-    let order = new_order_db
-        .create(pool)
+    let order = create_order_as(pool, new_order_db, crate::instance::current_id())
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
     info!("New order saved Id: {}", order.id);
@@ -441,7 +443,7 @@ fn maker_bond_notional_sats(order: &Order) -> Result<i64, MostroError> {
 /// The order row must already exist in the DB; on success it is in
 /// `Status::Pending` with its `event_id` set.
 async fn finalize_order_publication(
-    pool: &SqlitePool,
+    pool: &DbPool,
     keys: &Keys,
     mut order: Order,
     identity_pubkey: PublicKey,
@@ -493,25 +495,33 @@ async fn finalize_order_publication(
     )
     .await;
 
-    NOSTR_CLIENT
-        .get()
-        .unwrap()
-        .send_event(&event)
+    relay_health::publish(NOSTR_CLIENT.get().unwrap(), &event)
         .await
-        .map(|output| {
+        .map(|(output, skipped)| {
             // A per-relay rejection resolves to `Ok` with the refusing
             // relays in `output.failed`. The publish still stands — the row
             // is `Pending` and at least one side of the wire may have it —
-            // but the divergent relays must be converged, so queue the
-            // order for the reconciler.
-            if !output.failed.is_empty() {
+            // but the divergent relays must be converged: the same event is
+            // re-sent to them, or, if no relay took it, the order is queued
+            // for the reconciler.
+            if output.success.is_empty() {
                 tracing::warn!(
-                    "initial orderbook publish rejected by {} relay(s) for order {}: {:?}; queued for republish",
-                    output.failed.len(),
+                    "initial orderbook publish rejected by every relay for order {}: {:?}; queued for republish",
                     order_id,
                     output.failed
                 );
                 mark_orderbook_publish_failed(order_id);
+            } else {
+                let missed = relay_health::missed_relays(&output, &skipped);
+                if !missed.is_empty() {
+                    tracing::warn!(
+                        "initial orderbook publish missed {} relay(s) for order {}: {:?}; queued for catch-up",
+                        missed.len(),
+                        order_id,
+                        output.failed
+                    );
+                    relay_health::global().queue_catch_up(order_id, &event, missed);
+                }
             }
         })
         .map_err(|err| {
@@ -532,7 +542,7 @@ async fn finalize_order_publication(
 /// across redeliveries is enforced by the caller, which only invokes this
 /// while the order is still in `WaitingMakerBond`.
 pub async fn resume_publish_after_maker_bond(
-    pool: &SqlitePool,
+    pool: &DbPool,
     keys: &Keys,
     order: Order,
     request_id: Option<u64>,
@@ -546,7 +556,7 @@ pub async fn resume_publish_after_maker_bond(
     // `finalize_order_publication` would blindly resurrect the dead order
     // back to `Pending` and emit a NIP-33 event for it. If the CAS affects
     // 0 rows another path already owns the status, so we skip cleanly.
-    let cas = sqlx::query("UPDATE orders SET status = ? WHERE id = ? AND status = ?")
+    let cas = sqlx::query("UPDATE orders SET status = $1 WHERE id = $2 AND status = $3")
         .bind(Status::Pending.to_string())
         .bind(order.id)
         .bind(Status::WaitingMakerBond.to_string())
@@ -740,10 +750,20 @@ pub async fn send_dm(
     );
 
     if let Ok(client) = get_nostr_client() {
-        client
-            .send_event(&event)
+        let (output, skipped) = relay_health::publish(client, &event)
             .await
             .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
+        // Nobody has the message: fail so the outbox retries it.
+        if output.success.is_empty() {
+            return Err(MostroInternalErr(ServiceError::NostrError(format!(
+                "no relay accepted the message: {:?}",
+                output.failed
+            ))));
+        }
+        // The recipient may read only from a relay that refused, timed out
+        // or was skipped while demoted; it gets the same event later.
+        relay_health::global()
+            .queue_message_catch_up(&event, relay_health::missed_relays(&output, &skipped));
     }
 
     Ok(())
@@ -834,8 +854,12 @@ pub async fn publish_dev_fee_audit_event(
 /// Returns a borrowed `Keys` on success. Returns
 /// [`MostroInternalErr`](mostro_core::error::MostroError::MostroInternalErr) with
 /// [`ServiceError::NostrError`] when `NOSTR_KEYS` has not been set — for example
-/// in unit tests that skip the full configuration bootstrap.
+/// in unit tests that skip the full configuration bootstrap. Inside a handler
+/// for an `[[instances]]` identity it returns that instance's keys.
 pub fn get_keys() -> Result<&'static Keys, MostroError> {
+    if let Some(instance) = crate::instance::current() {
+        return Ok(&instance.keys);
+    }
     crate::config::get_mostro_keys().ok_or_else(|| {
         MostroInternalErr(ServiceError::NostrError(
             "Nostr keys not initialized".to_string(),
@@ -864,7 +888,7 @@ pub async fn update_user_rating_event(
 async fn get_ratings_for_pending_order(
     order_updated: &Order,
     status: Status,
-) -> Result<Option<(f64, i64, i64)>, MostroError> {
+) -> Result<Option<Reputation>, MostroError> {
     // Phase 1.5: `WaitingTakerBond` publishes on the wire as `pending`
     // (see `nip33::create_status_tags`), so the maker rating must travel
     // with both buckets — otherwise clients browsing the orderbook would
@@ -888,15 +912,12 @@ async fn get_ratings_for_pending_order(
                 .map_err(MostroInternalErr)?,
         };
 
-        match is_user_present(&get_db_pool(), identity_pubkey.to_string()).await {
-            Ok(user) => Ok(Some((
-                user.total_rating,
-                user.total_reviews,
-                user.created_at,
-            ))),
+        let pool = get_db_pool();
+        match is_user_present(&pool, identity_pubkey.to_string()).await {
+            Ok(user) => Ok(Some(reputation::profile(&pool, &user).await?)),
             Err(_) => {
                 if identity_pubkey == trade_pubkey {
-                    Ok(Some((0.0, 0, 0)))
+                    Ok(Some(Reputation::unrated()))
                 } else {
                     Err(MostroInternalErr(ServiceError::InvalidPubkey))
                 }
@@ -914,11 +935,7 @@ async fn get_ratings_for_pending_order(
 /// an order that is actually `canceled`). Republish the state that
 /// actually won so the orderbook converges. Never fails the caller: the
 /// database is already correct — this is only the advertised view.
-pub async fn republish_winning_state_after_cas_miss(
-    pool: &Pool<Sqlite>,
-    my_keys: &Keys,
-    order_id: Uuid,
-) {
+pub async fn republish_winning_state_after_cas_miss(pool: &DbPool, my_keys: &Keys, order_id: Uuid) {
     let current = match Order::by_id(pool, order_id).await {
         Ok(Some(order)) => order,
         Ok(None) => return,
@@ -1138,7 +1155,7 @@ pub(crate) fn monotonic_dispute_event_timestamp(d_tag: &str, candidate: Timestam
     ))
 }
 
-/// Orders whose latest kind-38383 publish failed (a relay rejected the
+/// Orders whose latest kind-38383 publish failed (every relay rejected the
 /// event, the send errored, or no Nostr client existed), so the DB state
 /// and the advertised orderbook diverged. The scheduler's orderbook
 /// reconciler drains this map and republishes the current DB state until
@@ -1329,6 +1346,17 @@ async fn update_order_event_stamped(
     order: &Order,
     policy: StampPolicy,
 ) -> Result<Option<Order>, MostroError> {
+    // Jobs running outside any instance scope pass the primary keys; the
+    // order book event must still be signed by the instance owning the order.
+    let keys = match crate::config::DB_POOL.get() {
+        Some(pool) if crate::instance::has_several_keys() => {
+            match crate::instance::of_order(pool, order.id).await {
+                Some(owner) => &owner.keys,
+                None => keys,
+            }
+        }
+        _ => keys,
+    };
     let mut order_updated = order.clone();
     // update order.status with new status
     order_updated.status = status.to_string();
@@ -1371,20 +1399,36 @@ async fn update_order_event_stamped(
         // state. Queue the order so the scheduler's orderbook reconciler
         // republishes the current DB state until the wire converges.
         match get_nostr_client() {
-            Ok(client) => match client.send_event(&event).await {
+            Ok(client) => match relay_health::publish(client, &event).await {
                 // Only failures recorded by publications stamped no later
                 // than this one may be cleared: a newer concurrent
                 // publication's failure must survive this older success.
-                Ok(output) if output.failed.is_empty() => {
-                    clear_orderbook_publish_failure_up_to(order.id, stamp.generation)
+                Ok((output, skipped)) if output.failed.is_empty() && skipped.is_empty() => {
+                    clear_orderbook_publish_failure_up_to(order.id, stamp.generation);
+                    relay_health::global().clear_catch_up(order.id, &event);
                 }
                 // A per-relay rejection or timeout resolves to `Ok` with the
                 // refusing relays in `output.failed` — `send_event` returns
-                // `Err` only when there was no relay to send to at all. Any
-                // rejected relay means the book is divergent there, so the
+                // `Err` only when there was no relay to send to at all. When
+                // some relay took the event, republishing a new revision
+                // cannot reach the ones that refused it; the same event is
+                // re-sent to just those relays instead.
+                Ok((output, skipped)) if !output.success.is_empty() => {
+                    let missed = relay_health::missed_relays(&output, &skipped);
+                    tracing::warn!(
+                        "orderbook publish missed {} relay(s) for order {} (status {}): {:?}; queued for catch-up",
+                        missed.len(),
+                        order_updated.id,
+                        status,
+                        output.failed
+                    );
+                    clear_orderbook_publish_failure_up_to(order.id, stamp.generation);
+                    relay_health::global().queue_catch_up(order.id, &event, missed);
+                }
+                // No relay took it: the book is divergent everywhere, so the
                 // publish counts as failed and stays queued until a
                 // republish converges it.
-                Ok(output) => {
+                Ok((output, _)) => {
                     tracing::warn!(
                         "orderbook publish rejected by {} relay(s) for order {} (status {}): {:?}; queued for republish",
                         output.failed.len(),
@@ -1537,7 +1581,9 @@ impl HoldInvoiceOrigin {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn show_hold_invoice(
+    pool: &DbPool,
     my_keys: &Keys,
     payment_request: Option<String>,
     buyer_pubkey: &PublicKey,
@@ -1546,13 +1592,13 @@ pub async fn show_hold_invoice(
     request_id: Option<u64>,
     origin: HoldInvoiceOrigin,
 ) -> Result<(), MostroError> {
-    let mut ln_client = lightning::LndConnector::new().await?;
+    let mut ln_client = crate::escrow::connect_lightning().await?;
     // Seller pays only the order amount and their Mostro fee
     // Dev fee is NOT charged to seller - it's paid by mostrod from its earnings
     let new_amount = order.amount + order.fee;
 
     // Now we generate the hold invoice that seller should pay
-    let (invoice_response, preimage, hash) = ln_client
+    let (hold_invoice, preimage, hash) = ln_client
         .create_hold_invoice(
             &messages::hold_invoice_description(
                 &order.id.to_string(),
@@ -1585,9 +1631,6 @@ pub async fn show_hold_invoice(
     order.seller_pubkey = Some(seller_pubkey.to_string());
 
     // We need to publish a new event with the new status
-    let pool = db::connect()
-        .await
-        .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
     let order_updated = update_order_event(my_keys, Status::WaitingPayment, &order)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
@@ -1598,12 +1641,9 @@ pub async fn show_hold_invoice(
     // bail instead. Only the `add_invoice` origin may also swap out
     // `waiting-buyer-invoice`; a take arriving from there raced in behind
     // a committed take and must lose rather than clobber it.
-    let won = db::cas_complete_pretrade_take(
-        &pool,
-        &order_updated,
-        origin.allows_waiting_buyer_invoice(),
-    )
-    .await?;
+    let won =
+        db::cas_complete_pretrade_take(pool, &order_updated, origin.allows_waiting_buyer_invoice())
+            .await?;
     if !won {
         if let Err(e) = ln_client.cancel_hold_invoice(&bytes_to_string(&hash)).await {
             tracing::warn!(
@@ -1611,7 +1651,7 @@ pub async fn show_hold_invoice(
                 order.id
             );
         }
-        republish_winning_state_after_cas_miss(&pool, my_keys, order.id).await;
+        republish_winning_state_after_cas_miss(pool, my_keys, order.id).await;
         return Err(MostroCantDo(CantDoReason::NotAllowedByStatus));
     }
 
@@ -1626,11 +1666,7 @@ pub async fn show_hold_invoice(
         request_id,
         Some(order.id),
         Action::PayInvoice,
-        Some(Payload::PaymentRequest(
-            Some(new_order),
-            invoice_response.payment_request,
-            None,
-        )),
+        Some(Payload::PaymentRequest(Some(new_order), hold_invoice, None)),
         *seller_pubkey,
         order.trade_index_seller,
     )
@@ -1647,7 +1683,7 @@ pub async fn show_hold_invoice(
     )
     .await;
 
-    let _ = invoice_subscribe(hash, request_id).await;
+    let _ = invoice_subscribe(pool, hash, request_id).await;
 
     Ok(())
 }
@@ -1677,7 +1713,7 @@ pub async fn show_hold_invoice(
 /// on a Cashu node the seller's client reads that as "lock the escrow" (the
 /// Lightning path instead sends `Action::PayInvoice` with a bolt11).
 pub async fn show_cashu_escrow_request(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     my_keys: &Keys,
     buyer_pubkey: &PublicKey,
     seller_pubkey: &PublicKey,
@@ -1728,10 +1764,11 @@ pub async fn show_cashu_escrow_request(
     let order_updated = update_order_event(my_keys, Status::WaitingPayment, &order)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::NostrError(e.to_string())))?;
-    order_updated
+    let order_updated = order_updated
         .update(pool)
         .await
         .map_err(|e| MostroInternalErr(ServiceError::DbAccessError(e.to_string())))?;
+    crate::rpc::events::order_status_changed(order_updated.id, &order_updated.status);
 
     // Build the escrow request for the seller.
     let mut new_order = order.as_new_order();
@@ -1768,8 +1805,12 @@ pub async fn show_cashu_escrow_request(
 }
 
 // Create function to reuse in case of resubscription
-pub async fn invoice_subscribe(hash: Vec<u8>, request_id: Option<u64>) -> Result<(), MostroError> {
-    let mut ln_client_invoices = lightning::LndConnector::new().await?;
+pub async fn invoice_subscribe(
+    pool: &DbPool,
+    hash: Vec<u8>,
+    request_id: Option<u64>,
+) -> Result<(), MostroError> {
+    let mut ln_client_invoices = crate::escrow::connect_lightning().await?;
     let (tx, mut rx) = channel(100);
 
     let invoice_task = {
@@ -1780,57 +1821,68 @@ pub async fn invoice_subscribe(hash: Vec<u8>, request_id: Option<u64>) -> Result
                 .map_err(|e| e.to_string());
         }
     };
-    tokio::spawn(invoice_task);
+    crate::instance::spawn(invoice_task);
 
-    // Arc clone db pool to safe use across threads
-    let pool = get_db_pool();
+    let pool = pool.clone();
 
     let subs = {
         async move {
             // Receiving msgs from the invoice subscription.
             while let Some(msg) = rx.recv().await {
                 let hash = bytes_to_string(msg.hash.as_ref());
-                // If this invoice was paid by the seller
-                if msg.state == InvoiceState::Accepted {
-                    let keys = match get_keys() {
-                        Ok(k) => k,
-                        Err(e) => {
-                            info!("Failed to get keys: {e}");
-                            continue;
-                        }
-                    };
-                    if let Err(e) = flow::hold_invoice_paid(&hash, request_id, &pool, keys).await {
-                        info!("Invoice flow error {e}");
-                    } else {
-                        info!("Invoice with hash {hash} accepted!");
-                    }
-                } else if msg.state == InvoiceState::Settled {
-                    // If the payment was settled
-                    if let Err(e) = flow::hold_invoice_settlement(&hash, &pool).await {
-                        info!("Invoice flow error {e}");
-                    }
-                } else if msg.state == InvoiceState::Canceled {
-                    // If the payment was canceled
-                    let keys = match get_keys() {
-                        Ok(k) => k,
-                        Err(e) => {
-                            info!("Failed to get keys: {e}");
-                            continue;
-                        }
-                    };
-                    if let Err(e) = flow::hold_invoice_canceled(&hash, &pool, keys).await {
-                        info!("Invoice flow error {e}");
-                    }
-                } else {
-                    info!("Invoice with hash: {hash} subscribed!");
-                }
+                let owner = crate::instance::of_hash(&pool, &hash).await;
+                crate::instance::within(owner, handle_invoice_update(msg, hash, request_id, &pool))
+                    .await;
             }
         }
     };
-    tokio::spawn(subs);
+    crate::instance::spawn(subs);
     Ok(())
 }
 
+/// React to one hold invoice state change, as the instance owning the order.
+pub(crate) async fn handle_invoice_update(
+    msg: lightning::InvoiceMessage,
+    hash: String,
+    request_id: Option<u64>,
+    pool: &DbPool,
+) {
+    // If this invoice was paid by the seller
+    if msg.state == InvoiceState::Accepted {
+        let keys = match get_keys() {
+            Ok(k) => k,
+            Err(e) => {
+                info!("Failed to get keys: {e}");
+                return;
+            }
+        };
+        if let Err(e) = flow::hold_invoice_paid(&hash, request_id, pool, keys).await {
+            info!("Invoice flow error {e}");
+        } else {
+            info!("Invoice with hash {hash} accepted!");
+        }
+    } else if msg.state == InvoiceState::Settled {
+        // If the payment was settled
+        if let Err(e) = flow::hold_invoice_settlement(&hash, pool).await {
+            info!("Invoice flow error {e}");
+        }
+    } else if msg.state == InvoiceState::Canceled {
+        // If the payment was canceled
+        let keys = match get_keys() {
+            Ok(k) => k,
+            Err(e) => {
+                info!("Failed to get keys: {e}");
+                return;
+            }
+        };
+        if let Err(e) = flow::hold_invoice_canceled(&hash, pool, keys).await {
+            info!("Invoice flow error {e}");
+        }
+    } else {
+        info!("Invoice with hash: {hash} subscribed!");
+    }
+}
+
 /// Price a market order and compute its Mostro fee in one step.
 ///
 /// Converts `fiat_amount` (denominated in `fiat_code`) to sats through the
@@ -2021,6 +2073,46 @@ pub fn bytes_to_string(bytes: &[u8]) -> String {
     })
 }
 
+/// Store an outbound message in the outbox, tagged with the instance it is
+/// sent as (`default` when `sender` is `None`), and wake the dispatcher. The
+/// row is durable before the handler moves on; the dispatcher only sends it.
+async fn enqueue_outbox(
+    sender: Option<&'static crate::instance::Instance>,
+    queue: &str,
+    message: Message,
+    destination_key: PublicKey,
+) {
+    let sender_id = sender.map_or(crate::instance::DEFAULT_INSTANCE, |instance| {
+        instance.sender_id()
+    });
+    let Some(pool) = crate::config::DB_POOL.get() else {
+        tracing::error!("No database pool to store {queue} message from {sender_id}");
+        return;
+    };
+    let Some(keys) = sender
+        .map(|instance| &instance.keys)
+        .or_else(crate::config::get_mostro_keys)
+    else {
+        tracing::error!("No key to store {queue} message from {sender_id}");
+        return;
+    };
+    let now = chrono::Utc::now().timestamp();
+    if let Err(e) = crate::outbox::persist_from(
+        pool,
+        sender_id,
+        keys,
+        queue,
+        &message,
+        &destination_key,
+        now,
+    )
+    .await
+    {
+        tracing::error!("Could not store {queue} message from {sender_id}: {e}");
+    }
+    crate::outbox::wake();
+}
+
 pub async fn enqueue_cant_do_msg(
     request_id: Option<u64>,
     order_id: Option<Uuid>,
@@ -2029,21 +2121,43 @@ pub async fn enqueue_cant_do_msg(
 ) {
     // Send message to event creator
     let message = Message::cant_do(order_id, request_id, Some(Payload::CantDo(Some(reason))));
-    MESSAGE_QUEUES
-        .queue_order_cantdo
-        .write()
-        .await
-        .push((message, destination_key));
+    enqueue_outbox(
+        crate::instance::current(),
+        "cantdo",
+        message,
+        destination_key,
+    )
+    .await;
 }
 
 pub async fn enqueue_restore_session_msg(payload: Option<Payload>, destination_key: PublicKey) {
     // Send message to event creator
     let message = Message::new_restore(payload);
-    MESSAGE_QUEUES
-        .queue_restore_session_msg
-        .write()
-        .await
-        .push((message, destination_key));
+    enqueue_outbox(
+        crate::instance::current(),
+        "restore",
+        message,
+        destination_key,
+    )
+    .await;
+}
+
+/// Queue a dispute message (`Message::new_dispute`) sent as the current
+/// instance.
+pub async fn enqueue_dispute_msg(
+    dispute_id: Option<Uuid>,
+    action: Action,
+    payload: Option<Payload>,
+    destination_key: PublicKey,
+) {
+    let message = Message::new_dispute(dispute_id, None, None, action, payload);
+    enqueue_outbox(
+        crate::instance::current(),
+        "dispute",
+        message,
+        destination_key,
+    )
+    .await;
 }
 
 pub async fn enqueue_order_msg(
@@ -2056,11 +2170,19 @@ pub async fn enqueue_order_msg(
 ) {
     // Send message to event creator
     let message = Message::new_order(order_id, request_id, trade_index, action, payload);
-    MESSAGE_QUEUES
-        .queue_order_msg
-        .write()
-        .await
-        .push((message, destination_key));
+    // Scheduler jobs notify outside any instance scope: send as the order's.
+    let sender = match (
+        crate::instance::current(),
+        order_id,
+        crate::config::DB_POOL.get(),
+    ) {
+        (Some(current), _, _) => Some(current),
+        (None, Some(order_id), Some(pool)) if crate::instance::has_several_keys() => {
+            crate::instance::of_order(pool, order_id).await
+        }
+        _ => None,
+    };
+    enqueue_outbox(sender, "order", message, destination_key).await;
 }
 
 pub fn get_fiat_amount_requested(order: &Order, msg: &Message) -> Option<i64> {
@@ -2122,7 +2244,7 @@ pub async fn get_nostr_relays() -> Option<HashMap<RelayUrl, Relay>> {
     }
 }
 
-pub async fn get_dispute(msg: &Message, pool: &Pool<Sqlite>) -> Result<Dispute, MostroError> {
+pub async fn get_dispute(msg: &Message, pool: &DbPool) -> Result<Dispute, MostroError> {
     let dispute_msg = msg.get_inner_message_kind();
     let dispute_id = dispute_msg
         .id
@@ -2137,7 +2259,7 @@ pub async fn get_dispute(msg: &Message, pool: &Pool<Sqlite>) -> Result<Dispute,
     }
 }
 
-pub async fn get_order(msg: &Message, pool: &Pool<Sqlite>) -> Result<Order, MostroError> {
+pub async fn get_order(msg: &Message, pool: &DbPool) -> Result<Order, MostroError> {
     let order_msg = msg.get_inner_message_kind();
     let order_id = order_msg.id.ok_or(MostroCantDo(CantDoReason::NotFound))?;
     let order = Order::by_id(pool, order_id)
@@ -2166,7 +2288,7 @@ pub async fn get_order(msg: &Message, pool: &Pool<Sqlite>) -> Result<Order, Most
 /// - Uses a single SQL query with IN clause and user validation for efficiency
 /// - Validates that the user has access to the requested orders
 pub async fn get_user_orders_by_id(
-    pool: &Pool<Sqlite>,
+    pool: &DbPool,
     orders: &[Uuid],
     user_pubkey: &str,
 ) -> Result<Vec<Order>, MostroError> {
@@ -2219,6 +2341,30 @@ pub async fn validate_invoice(msg: &Message, order: &Order) -> Result<Option<Str
         // Dev fee is NOT charged to buyer - it's paid by mostrod from its earnings
         let total_buyer_fees = order.fee;
 
+        // A bitcoin address is paid on-chain through a reverse swap, when
+        // the node offers it.
+        if crate::swap::payout_address(&pr).is_some() {
+            let cfg = Settings::get_swap().ok_or(MostroCantDo(CantDoReason::InvalidInvoice))?;
+            let net_amount = (order.amount as u64).saturating_sub(total_buyer_fees as u64);
+            crate::swap::validate_payout_address(&pr, net_amount, cfg)?;
+            return Ok(Some(pr.trim().to_string()));
+        }
+
+        // A BOLT12 offer is paid with invoices fetched at payout time, which
+        // only a Core Lightning node can do.
+        if crate::lightning::bolt12::is_offer(&pr) {
+            if Settings::get_ln().backend != crate::config::LightningBackend::Cln {
+                return Err(MostroCantDo(CantDoReason::InvalidInvoice));
+            }
+            crate::lightning::bolt12::validate_offer(
+                &pr,
+                Some(order.amount as u64),
+                Some(total_buyer_fees as u64),
+            )
+            .map_err(|_| MostroCantDo(CantDoReason::InvalidInvoice))?;
+            return Ok(Some(pr.trim().to_string()));
+        }
+
         // if invoice is valid
         if is_valid_invoice(
             pr.clone(),
@@ -2238,10 +2384,7 @@ pub async fn validate_invoice(msg: &Message, order: &Order) -> Result<Option<Str
     Ok(payment_request)
 }
 
-pub async fn notify_taker_reputation(
-    pool: &Pool<Sqlite>,
-    order: &Order,
-) -> Result<(), MostroError> {
+pub async fn notify_taker_reputation(pool: &DbPool, order: &Order) -> Result<(), MostroError> {
     // Check if is buy or sell order we need this info to understand the user needed and the receiver of notification
     let is_buy_order = order.is_buy_order().is_ok();
     // Get user needed
@@ -2322,10 +2465,9 @@ pub async fn notify_taker_reputation(
 mod tests {
     use super::*;
     use crate::bitcoin_price::BitcoinPriceManager;
+    use crate::db::DbPool;
     use mostro_core::message::{Message, MessageKind};
     use mostro_core::order::Order;
-    use sqlx::sqlite::SqlitePoolOptions;
-    use sqlx::SqlitePool;
     use std::sync::Once;
     use uuid::{uuid, Uuid};
     // Setup function to initialize common settings or data before tests
@@ -2632,33 +2774,12 @@ mod tests {
         }
     }
 
-    async fn setup_orders_pool() -> SqlitePool {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .unwrap();
-
-        sqlx::query(include_str!("../migrations/20221222153301_orders.sql"))
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query(include_str!("../migrations/20251126120000_dev_fee.sql"))
-            .execute(&pool)
-            .await
-            .unwrap();
-        sqlx::query(include_str!(
-            "../migrations/20260530120000_cashu_escrow_fields.sql"
-        ))
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        pool
+    async fn setup_orders_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
     async fn insert_order(
-        pool: &SqlitePool,
+        pool: &DbPool,
         id: Uuid,
         identity_buyer_pubkey: Option<&str>,
         identity_seller_pubkey: Option<&str>,
@@ -2681,7 +2802,7 @@ mod tests {
                 expires_at,
                 master_buyer_pubkey,
                 master_seller_pubkey
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
         "#,
         )
         .bind(id)
@@ -3006,20 +3127,14 @@ mod tests {
     }
 
     /// In-memory pool with the full production schema.
-    async fn migrated_pool() -> SqlitePool {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect(":memory:")
-            .await
-            .unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        pool
+    async fn migrated_pool() -> DbPool {
+        crate::db::test_utils::migrated_pool().await
     }
 
-    async fn insert_user_row(pool: &SqlitePool, pubkey: &str, last_trade_index: i64) {
+    async fn insert_user_row(pool: &DbPool, pubkey: &str, last_trade_index: i64) {
         sqlx::query(
             "INSERT INTO users (pubkey, last_trade_index, created_at, total_rating, total_reviews) \
-             VALUES (?, ?, ?, ?, ?)",
+             VALUES ($1, $2, $3, $4, $5)",
         )
         .bind(pubkey)
         .bind(last_trade_index)
@@ -3047,40 +3162,6 @@ mod tests {
         }
     }
 
-    /// Ensure the process-wide DB pool exists (migrated, in-memory).
-    ///
-    /// `DB_POOL` is a set-once global shared by every test in the binary, so
-    /// whichever test runs first wins the race and installs its pool. That
-    /// winner may have set up a different (or partial) schema, so we run our
-    /// migrations against the live pool unconditionally — they are idempotent
-    /// (tracked in `_sqlx_migrations`) and guarantee the tables these tests
-    /// need exist regardless of who won.
-    /// Serializes global-pool initialization: two tests racing through this
-    /// helper could interleave `set` + migration runs on the winning pool,
-    /// leaving one of them querying tables that are not applied yet.
-    static GLOBAL_POOL_INIT: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
-
-    async fn ensure_global_db_pool() -> std::sync::Arc<SqlitePool> {
-        let _guard = GLOBAL_POOL_INIT.lock().await;
-        if DB_POOL.get().is_none() {
-            let pool = migrated_pool().await;
-            let _ = DB_POOL.set(std::sync::Arc::new(pool));
-        }
-        let pool = get_db_pool();
-        // Surface a migration failure instead of discarding it. Swallowing it
-        // would hand back a pool with a partial schema, and the tests built on
-        // it would fail much later with a confusing "no such table" instead of
-        // the real cause. The migrator is idempotent (tracked in
-        // `_sqlx_migrations`) and every `CREATE TABLE` in `migrations/` is
-        // `IF NOT EXISTS`, so re-running it against an already-migrated pool
-        // is a no-op — a failure here means a genuinely broken global pool.
-        sqlx::migrate!("./migrations")
-            .run(pool.as_ref())
-            .await
-            .expect("the process-global test pool must be fully migrated");
-        pool
-    }
-
     /// Freshly-signed BOLT11 invoice built locally (no network, no LND).
     fn build_test_invoice(amount_msat: u64, expiry_secs: u64) -> String {
         use bitcoin::hashes::{sha256, Hash};
@@ -3427,7 +3508,7 @@ mod tests {
     #[tokio::test]
     async fn update_order_event_covers_reputation_paths() {
         init_globals();
-        let gpool = ensure_global_db_pool().await;
+        let gpool = crate::db::test_utils::global_pool().await;
         let keys = Keys::generate();
         let trade = Keys::generate().public_key();
 
@@ -3510,9 +3591,7 @@ mod tests {
         assert_eq!(db.seller_pubkey, Some(seller.to_string()));
 
         // Collect our order's queued messages (the queue is shared across tests).
-        let msgs: Vec<(Message, PublicKey)> = MESSAGE_QUEUES
-            .queue_order_msg
-            .read()
+        let msgs: Vec<(Message, PublicKey)> = crate::outbox::test_utils::queued("order")
             .await
             .iter()
             .filter(|(m, _)| m.get_inner_message_kind().id == Some(order.id))
@@ -3602,7 +3681,7 @@ mod tests {
             .await
             .unwrap();
         sqlx::query(
-            "UPDATE orders SET cashu_escrow_token = ?1, cashu_escrow_locked_at = ?2 WHERE id = ?3",
+            "UPDATE orders SET cashu_escrow_token = $1, cashu_escrow_locked_at = $2 WHERE id = $3",
         )
         .bind("cashuAtoken")
         .bind(1700000100_i64)
@@ -3845,10 +3924,9 @@ mod tests {
     /// just asserting an enum against itself.
     #[tokio::test]
     async fn take_origin_cas_scope_cannot_clobber_a_committed_take() {
-        use mostro_core::db::Crud;
+        use crate::db::Crud;
 
-        let pool = SqlitePoolOptions::new().connect(":memory:").await.unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let pool = crate::db::test_utils::migrated_pool().await;
 
         // First taker already committed: row waits for their invoice.
         let mut committed = base_order(OrderKind::Sell, Status::WaitingBuyerInvoice);
@@ -3889,8 +3967,10 @@ mod tests {
         let buyer = Keys::generate().public_key();
         let seller = Keys::generate().public_key();
         let order = base_order(OrderKind::Sell, Status::WaitingPayment);
+        let pool = crate::db::test_utils::migrated_pool().await;
 
         let res = show_hold_invoice(
+            &pool,
             &keys,
             None,
             &buyer,
@@ -3906,7 +3986,8 @@ mod tests {
     #[tokio::test]
     async fn invoice_subscribe_fails_fast_without_lnd() {
         init_globals();
-        assert!(invoice_subscribe(vec![0u8; 32], None).await.is_err());
+        let pool = crate::db::test_utils::migrated_pool().await;
+        assert!(invoice_subscribe(&pool, vec![0u8; 32], None).await.is_err());
     }
 
     // ───────────────────────── messaging helpers ─────────────────────────
@@ -3935,15 +4016,26 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn enqueue_helpers_push_into_global_queues() {
-        // The queues are process-wide and concurrently drained by the
-        // scheduler flush-job tests, so asserting on queue contents here
-        // would race. The contract under test is "enqueue completes and
-        // never panics"; delivery is covered by the scheduler tests.
+    async fn enqueue_helpers_store_outbox_rows() {
+        crate::db::test_utils::global_pool().await;
         let key = Keys::generate().public_key();
         enqueue_cant_do_msg(Some(1), None, CantDoReason::NotFound, key).await;
         enqueue_restore_session_msg(None, key).await;
         enqueue_order_msg(Some(1), None, Action::Rate, None, key, None).await;
+
+        for (queue, action) in [
+            ("cantdo", Action::CantDo),
+            ("restore", Action::RestoreSession),
+            ("order", Action::Rate),
+        ] {
+            let actions: Vec<Action> = crate::outbox::test_utils::queued(queue)
+                .await
+                .iter()
+                .filter(|(_, to)| *to == key)
+                .map(|(m, _)| m.get_inner_message_kind().action.clone())
+                .collect();
+            assert_eq!(actions, vec![action], "{queue}");
+        }
     }
 
     // ───────────────────────── escrow settlement ─────────────────────────
@@ -4076,7 +4168,7 @@ mod tests {
         let order_id = Uuid::new_v4();
         sqlx::query(
             "INSERT INTO disputes (id, order_id, status, order_previous_status, created_at) \
-             VALUES (?, ?, 'initiated', 'active', ?)",
+             VALUES ($1, $2, 'initiated', 'active', $3)",
         )
         .bind(dispute_id)
         .bind(order_id)
@@ -4131,6 +4223,33 @@ mod tests {
             Some(Payload::PaymentRequest(None, pr.clone(), None)),
         );
         assert_eq!(validate_invoice(&msg, &order).await.unwrap(), Some(pr));
+
+        // A bitcoin address needs on-chain payouts, off in the test config.
+        let msg = Message::new_order(
+            None,
+            None,
+            None,
+            Action::AddInvoice,
+            Some(Payload::PaymentRequest(
+                None,
+                "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string(),
+                None,
+            )),
+        );
+        let err = validate_invoice(&msg, &order).await.unwrap_err();
+        assert!(matches!(err, MostroCantDo(CantDoReason::InvalidInvoice)));
+
+        // A BOLT12 offer needs a Core Lightning node; the test config runs LND.
+        let (offer, _) = crate::escrow::memory::offer(None);
+        let msg = Message::new_order(
+            None,
+            None,
+            None,
+            Action::AddInvoice,
+            Some(Payload::PaymentRequest(None, offer, None)),
+        );
+        let err = validate_invoice(&msg, &order).await.unwrap_err();
+        assert!(matches!(err, MostroCantDo(CantDoReason::InvalidInvoice)));
     }
 
     // ───────────────────────── taker reputation notification ─────────────────────────